@@ -0,0 +1,215 @@
+//! Baselines for the per-timestep kernels, a full `Model::process` pass, the simulation
+//! engines compared on the same model and the fused synapse forward pass against separate ones.
+//!
+//! `cargo bench --bench kernels` runs every group on the CPU, and on CUDA device 0 when one is
+//! available. Filter with e.g. `cargo bench --bench kernels -- lif_step/cuda`.
+
+use candle_core::{DType, Device, Tensor};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use custom_framework::layer::Layer;
+use custom_framework::layer::lif::LIFLayer;
+use custom_framework::layer::mod_signal::standard::StandardModSignal;
+use custom_framework::models::Model;
+use custom_framework::models::engine::{
+    ClockDriven, EventDriven, ExponentialEuler, SimulationEngine,
+};
+use custom_framework::models::parallel::{self, SynapseGroups};
+use custom_framework::synapse::SynapseOps;
+use custom_framework::synapse::csdp::CSDP;
+use std::hint::black_box;
+
+const SIZES: [usize; 3] = [64, 256, 1024];
+const BATCH: usize = 16;
+const DT: f32 = 0.1;
+const TIMESTEPS: usize = 40;
+
+fn devices() -> Vec<(&'static str, Device)> {
+    let mut devices = vec![("cpu", Device::Cpu)];
+    if let Ok(cuda) = Device::new_cuda(0) {
+        devices.push(("cuda", cuda));
+    }
+    devices
+}
+
+/// Same LIF parameters `Model::new` uses for its hidden layers
+fn lif_layer(size: usize, device: &Device) -> Box<dyn Layer> {
+    let mod_signal =
+        Box::new(StandardModSignal::new(size, 5.0, 1.0, size as f32 / 2.0, device).unwrap());
+    Box::new(LIFLayer::new(size, 13.0, 0.5, 0.01, mod_signal, device).unwrap())
+}
+
+fn spikes(size: usize, device: &Device) -> Tensor {
+    Tensor::rand(0.0f32, 1.0, (size, BATCH), device)
+        .unwrap()
+        .ge(0.8)
+        .unwrap()
+        .to_dtype(DType::F32)
+        .unwrap()
+}
+
+fn bench_lif_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lif_step");
+    for (name, device) in devices() {
+        for size in SIZES {
+            let mut layer = lif_layer(size, &device);
+            layer.reset(BATCH).unwrap();
+            let input = Tensor::rand(0.0f32, 2.0, (size, BATCH), &device).unwrap();
+
+            group.throughput(Throughput::Elements((size * BATCH) as u64));
+            group.bench_function(BenchmarkId::new(name, size), |b| {
+                b.iter(|| {
+                    layer.reset_input().unwrap();
+                    layer.add_input(&input).unwrap();
+                    layer.step(DT).unwrap();
+                    device.synchronize().unwrap();
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_csdp_forward(c: &mut Criterion) {
+    let mut group = c.benchmark_group("csdp_forward");
+    for (name, device) in devices() {
+        for size in SIZES {
+            let synapse = CSDP::new(size, size, &device).unwrap();
+            let pre = spikes(size, &device);
+
+            group.throughput(Throughput::Elements((size * size) as u64));
+            group.bench_function(BenchmarkId::new(name, size), |b| {
+                b.iter(|| {
+                    black_box(synapse.forward(&pre).unwrap());
+                    device.synchronize().unwrap();
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_csdp_update_weights(c: &mut Criterion) {
+    let mut group = c.benchmark_group("csdp_update_weights");
+    for (name, device) in devices() {
+        for size in SIZES {
+            let mut synapse = CSDP::new(size, size, &device).unwrap();
+            let mut post = lif_layer(size, &device);
+            post.reset(BATCH).unwrap();
+            let pre = spikes(size, &device);
+
+            // Drive the post layer once so the modulatory signal is non-trivial
+            post.add_input(&synapse.forward(&pre).unwrap()).unwrap();
+            post.step(DT).unwrap();
+
+            group.throughput(Throughput::Elements((size * size) as u64));
+            group.bench_function(BenchmarkId::new(name, size), |b| {
+                b.iter(|| {
+                    synapse.update_weights(&pre, post.as_ref(), DT).unwrap();
+                    device.synchronize().unwrap();
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_model_process(c: &mut Criterion) {
+    let mut group = c.benchmark_group("model_process");
+    group.sample_size(10);
+    for (name, device) in devices() {
+        for size in SIZES {
+            let input = Tensor::rand(0.0f32, 1.0, (size, BATCH), &device).unwrap();
+            for learning in [false, true] {
+                let mut model = Model::new(size, 10, vec![size, size], &device, DT, None).unwrap();
+                if learning {
+                    model.enable_learning();
+                } else {
+                    model.disable_learning();
+                }
+
+                let id = format!("{}/{}", name, if learning { "learn" } else { "infer" });
+                group.bench_function(BenchmarkId::new(id, size), |b| {
+                    b.iter(|| {
+                        black_box(model.process(&input, TIMESTEPS, false, &device).unwrap());
+                        device.synchronize().unwrap();
+                    })
+                });
+            }
+        }
+    }
+    group.finish();
+}
+
+fn bench_engines(c: &mut Criterion) {
+    let mut group = c.benchmark_group("engines");
+    group.sample_size(10);
+    for (name, device) in devices() {
+        let size = 256;
+        let input = Tensor::rand(0.0f32, 1.0, (size, BATCH), &device).unwrap();
+        let engines: [Box<dyn SimulationEngine>; 3] = [
+            Box::new(ClockDriven),
+            Box::new(EventDriven),
+            Box::new(ExponentialEuler),
+        ];
+        for engine in engines {
+            let id = format!("{}/{}", name, engine.name());
+            let mut model = Model::new(size, 10, vec![size, size], &device, DT, None).unwrap();
+            model.set_engine(engine).unwrap();
+            group.bench_function(BenchmarkId::new(id, size), |b| {
+                b.iter(|| {
+                    black_box(model.process(&input, TIMESTEPS, false, &device).unwrap());
+                    device.synchronize().unwrap();
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+/// One synapse forward pass of the default topology, where every hidden layer and the output
+/// feed two layers each
+fn bench_forward_synapses(c: &mut Criterion) {
+    let mut group = c.benchmark_group("forward_synapses");
+    for (name, device) in devices() {
+        for size in SIZES {
+            let input = Tensor::rand(0.0f32, 1.0, (size, BATCH), &device).unwrap();
+            let mut model = Model::new(size, 10, vec![size, size], &device, DT, None).unwrap();
+            model.reset(BATCH).unwrap();
+            for _ in 0..TIMESTEPS {
+                model.step(&input, None).unwrap();
+            }
+            let variants = [
+                ("fused", SynapseGroups::new(&model.synapses)),
+                ("separate", SynapseGroups::unfused(&model.synapses)),
+            ];
+            for (variant, mut groups) in variants {
+                let id = format!("{}/{}", name, variant);
+                group.bench_function(BenchmarkId::new(id, size), |b| {
+                    b.iter(|| {
+                        parallel::forward_synapses(
+                            &mut model.layers,
+                            &model.synapses,
+                            &mut groups,
+                            false,
+                            false,
+                        )
+                        .unwrap();
+                        device.synchronize().unwrap();
+                    })
+                });
+            }
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_lif_step,
+    bench_csdp_forward,
+    bench_csdp_update_weights,
+    bench_model_process,
+    bench_engines,
+    bench_forward_synapses
+);
+criterion_main!(benches);
@@ -1,7 +1,10 @@
 use super::Algorithm;
+use super::checkpoint::{self, CheckpointPolicy, Checkpointer};
+use super::validation::Validator;
 use crate::environment::Environment;
 use crate::models::rl_model1::RLModel1;
-use crate::visualization::{RuntimeStats, VisualizationState};
+use crate::visualization::publisher::VisPublisher;
+use crate::visualization::{ModelStructure, VisualizationState, keep_running};
 use candle_core::{Device, Tensor};
 use std::error::Error;
 use std::sync::{Arc, Mutex};
@@ -14,6 +17,8 @@ pub struct Algorithm1 {
     pub epochs_per_episode: usize,
     pub n_timesteps: usize,
     pub device: Device,
+    pub checkpoints: Checkpointer,
+    validator: Option<Validator>,
 }
 
 impl Algorithm1 {
@@ -32,8 +37,7 @@ impl Algorithm1 {
             &device,
             dt,
             input_bounds,
-        )
-        .expect("Failed to create RLModel1");
+        )?;
 
         Ok(Self {
             model,
@@ -42,9 +46,19 @@ impl Algorithm1 {
             epochs_per_episode: 5,
             n_timesteps: 40,
             device,
+            checkpoints: Checkpointer::new("checkpoints/csdp1", CheckpointPolicy::every(10)),
+            validator: None,
         })
     }
 
+    /// Save the model into the rotation under `checkpoints`; `metric` competes for `best/`
+    fn save_rotating(&mut self, episode: usize, metric: Option<f32>) -> Result<(), Box<dyn Error>> {
+        let dir = self.checkpoints.begin(episode)?;
+        self.model.save(dir.join("model.safetensors"))?;
+        self.checkpoints.commit(metric)?;
+        Ok(())
+    }
+
     fn get_action_tensor(&self, action_idx: usize) -> Result<Tensor, Box<dyn Error>> {
         Ok(Tensor::from_vec(
             vec![action_idx as f32],
@@ -55,6 +69,88 @@ impl Algorithm1 {
 }
 
 impl Algorithm for Algorithm1 {
+    fn checkpoints_mut(&mut self) -> Option<&mut Checkpointer> {
+        Some(&mut self.checkpoints)
+    }
+
+    fn set_validator(&mut self, validator: Validator) -> Result<(), Box<dyn Error>> {
+        self.validator = Some(validator);
+        Ok(())
+    }
+
+    /// The model's named weights, as exported by `export`
+    fn named_tensors(&self) -> Result<Vec<(String, Tensor)>, Box<dyn Error>> {
+        Ok(self.model.named_tensors()?)
+    }
+
+    fn visualization_snapshot(&self) -> Result<ModelStructure, Box<dyn Error>> {
+        Ok(self.model.get_visualization_snapshot()?)
+    }
+
+    /// Accepts a model file, a checkpoint directory or a rotation root (newest checkpoint wins)
+    fn restore(&mut self, path: &std::path::Path) -> Result<Vec<(usize, f32)>, Box<dyn Error>> {
+        if path.is_dir() {
+            self.model
+                .load(checkpoint::resolve(path).join("model.safetensors"))?;
+        } else {
+            self.model.load(path)?;
+        }
+        Ok(Vec::new())
+    }
+
+    fn evaluate(
+        &mut self,
+        env: &mut dyn Environment,
+        n_episodes: usize,
+    ) -> Result<Vec<f32>, Box<dyn Error>> {
+        let action_size = env.action_size();
+        let state_size = env.state_size();
+        let mut episode_rewards = Vec::with_capacity(n_episodes);
+
+        self.model.disable_learning();
+
+        for episode in 1..=n_episodes {
+            env.reset()?;
+            std::thread::sleep(Duration::from_millis(500)); // wait for environment to settle
+
+            let mut total_reward = 0.0;
+            for _step in 0..self.n_steps_per_episode {
+                let current_state = env.get_state()?;
+                let state_f32: Vec<f32> = current_state.iter().map(|&x| x as f32).collect();
+                let state_tensor = Tensor::from_vec(state_f32, (state_size, 1), &self.device)?;
+
+                let mut best_activity = -1.0;
+                let mut best_action = 0;
+                for a in 0..action_size {
+                    let action_tensor = self.get_action_tensor(a)?;
+                    let activity = self
+                        .model
+                        .process(&state_tensor, &action_tensor, self.n_timesteps)?
+                        .to_vec2::<f32>()?[0][0];
+                    if activity > best_activity {
+                        best_activity = activity;
+                        best_action = a;
+                    }
+                }
+
+                total_reward += env.evaluate_action(&current_state, best_action);
+                env.apply_action(best_action)?;
+                std::thread::sleep(Duration::from_millis(100)); // give some time for action to take effect
+            }
+
+            log::info!("[Eval {}] reward: {:.3}", episode, total_reward);
+            episode_rewards.push(total_reward as f32);
+        }
+
+        Ok(episode_rewards)
+    }
+
+    fn save_to(&mut self, dir: &std::path::Path) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(dir)?;
+        self.model.save(dir.join("model.safetensors"))?;
+        Ok(())
+    }
+
     fn run(
         &mut self,
         env: &mut dyn Environment,
@@ -62,7 +158,13 @@ impl Algorithm for Algorithm1 {
         vis_state: Option<Arc<Mutex<VisualizationState>>>,
     ) -> Result<(), Box<dyn Error>> {
         let mut total_iteration = 0;
-        let start_time = Instant::now();
+        // Weights to go back to on a reset command from the visualizer
+        let initial_model = vis_state.as_ref().map(|_| self.model.fork());
+        let mut publisher = vis_state.clone().map(|vs| {
+            let mut p = VisPublisher::new(vs);
+            p.snapshot_interval = 20 * self.n_timesteps;
+            p
+        });
         let mut total_inference_time = Duration::new(0, 0);
         let mut total_inference_actions: usize = 0;
         let mut total_training_time = Duration::new(0, 0);
@@ -132,6 +234,7 @@ impl Algorithm for Algorithm1 {
                                 .push((env_state[0] + env_state[2], env_state[1] + env_state[3]));
                         }
                         state.environment_state = Some(env_state);
+                        state.robot_status = env.robot_status();
                     }
 
                 // Sort actions by reward (ascending)
@@ -145,35 +248,19 @@ impl Algorithm for Algorithm1 {
                     episode_data.push((state_tensor.clone(), action_tensor, ytype));
                 }
 
-                // Visualization check during inference
-                if let Some(ref vis_state_arc) = vis_state {
-                    let should_break = false;
-                    loop {
-                        let (is_paused, should_close, delay) = vis_state_arc
-                            .try_lock()
-                            .map(|state| (state.is_paused, state.should_close, state.delay_ms))
-                            .unwrap_or((false, false, 0));
-                        if should_close {
-                            return Ok(());
-                        }
-                        if !is_paused {
-                            if delay > 0 {
-                                std::thread::sleep(std::time::Duration::from_millis(delay));
-                            }
-                            break;
-                        }
-                        std::thread::sleep(std::time::Duration::from_millis(50));
-                    }
-                    if should_break {
-                        break;
-                    }
+                // Visualization check during inference; the training samples below are the
+                // pause points, so a pause or step takes effect there
+                if let Some(ref vis_state_arc) = vis_state
+                    && !keep_running(vis_state_arc)
+                {
+                    return Ok(());
                 }
             } // end of inference steps
 
             let inference_elapsed = inference_start.elapsed();
             total_inference_time += inference_elapsed;
 
-            // Check if save or load was requested
+            // Check if save, load or reset was requested
             if let Some(state_arc) = &vis_state
                 && let Ok(mut lock) = state_arc.lock() {
                     if lock.save_requested {
@@ -230,6 +317,14 @@ impl Algorithm for Algorithm1 {
                         }
                         lock.load_requested = false;
                     }
+
+                    if lock.reset_requested
+                        && let Some(initial) = &initial_model
+                    {
+                        log::info!("Manual reset requested, restoring the initial weights...");
+                        self.model = initial.fork();
+                        lock.reset_requested = false;
+                    }
                 }
 
             // Update epoch rewards
@@ -252,45 +347,30 @@ impl Algorithm for Algorithm1 {
                         layer.set_positive_sample(&ytype_tensor);
                     }
 
-                    // For spike plot history
-                    let mut spike_history = Vec::new();
-                    let record_layer = vis_state
-                        .as_ref()
-                        .and_then(|vs| vs.try_lock().ok().and_then(|s| s.selected_layer_id));
+                    if let Some(p) = publisher.as_mut()
+                        && !p.begin_sample()
+                    {
+                        return Ok(());
+                    }
 
                     self.model.reset(1)?;
                     for _ in 0..self.n_timesteps {
+                        if let Some(p) = publisher.as_ref()
+                            && !p.before_timestep()
+                        {
+                            return Ok(());
+                        }
+
                         self.model.step(state_t, action_t)?;
-                        if let Some(layer_id) = record_layer
-                            && let Ok(activity) = self.model.get_layer_activity(layer_id) {
-                                spike_history.push(activity);
-                            }
-                    }
 
-                    // Update visualization snapshot every 20 iterations during training
-                    if let Some(ref vis_state_arc) = vis_state
-                        && let Ok(mut state) = vis_state_arc.try_lock() {
-                            if !spike_history.is_empty() {
-                                state.epoch_spike_history = Some((episode, spike_history));
-                            }
-                            if total_iteration % 20 == 0 {
-                                if let Ok(snapshot) = self.model.get_visualization_snapshot() {
-                                    state.update_from_snapshot(snapshot);
-                                }
-                                let elapsed = start_time.elapsed().as_secs_f32();
-                                let speed = if elapsed > 0.0 {
-                                    total_iteration as f32 / elapsed
-                                } else {
-                                    0.0
-                                };
-                                state.runtime_stats = RuntimeStats {
-                                    epoch: episode,
-                                    iteration: total_iteration,
-                                    timestep: total_iteration * self.n_timesteps,
-                                    iterations_per_second: speed,
-                                }
-                            }
+                        if let Some(p) = publisher.as_mut() {
+                            p.after_timestep(&self.model, episode, total_iteration);
                         }
+                    }
+
+                    if let Some(p) = publisher.as_mut() {
+                        p.end_sample(episode);
+                    }
                 }
             }
 
@@ -314,6 +394,26 @@ impl Algorithm for Algorithm1 {
                 inf_aps,
                 ep_s
             );
+
+            // With a validator, only the validation reward competes for the best checkpoint
+            let mut metric = Some(total_reward as f32);
+            if let Some(mut validator) = self.validator.take() {
+                metric = None;
+                if validator.is_due(episode) {
+                    match validator.run(episode, |env, n| self.evaluate(env, n)) {
+                        Ok(reward) => metric = Some(reward),
+                        Err(e) => log::error!("Validation failed at episode {}: {}", episode, e),
+                    }
+                    self.model.enable_learning();
+                }
+                self.validator = Some(validator);
+            }
+
+            let due = self.checkpoints.policy.is_due(episode)
+                || (self.validator.is_some() && metric.is_some());
+            if due && let Err(e) = self.save_rotating(episode, metric) {
+                log::error!("Auto-save failed at episode {}: {}", episode, e);
+            }
         } // end of episodes
 
         // Final auto-save
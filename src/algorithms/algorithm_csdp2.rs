@@ -1,8 +1,10 @@
 #![allow(clippy::needless_range_loop)]
 use super::Algorithm;
+use super::checkpoint::{self, CheckpointPolicy, Checkpointer};
 use crate::environment::Environment;
 use crate::models::rl_model2::RLModel2;
-use crate::visualization::{RuntimeStats, VisualizationState};
+use crate::visualization::publisher::VisPublisher;
+use crate::visualization::{ModelStructure, VisualizationState, keep_running};
 use candle_core::{Device, Tensor};
 use std::error::Error;
 use std::sync::{Arc, Mutex};
@@ -15,6 +17,7 @@ pub struct Algorithm2 {
     pub epochs_per_episode: usize,
     pub n_timesteps: usize,
     pub device: Device,
+    pub checkpoints: Checkpointer,
 }
 
 impl Algorithm2 {
@@ -45,8 +48,7 @@ impl Algorithm2 {
             &device,
             dt,
             input_bounds,
-        )
-        .expect("Failed to create RLModel2");
+        )?;
 
         Ok(Self {
             model,
@@ -55,11 +57,50 @@ impl Algorithm2 {
             epochs_per_episode: 5,
             n_timesteps: 40,
             device,
+            checkpoints: Checkpointer::new("checkpoints/csdp2", CheckpointPolicy::every(10)),
         })
     }
+
+    /// Save the model into the rotation under `checkpoints`; `metric` competes for `best/`
+    fn save_rotating(&mut self, episode: usize, metric: Option<f32>) -> Result<(), Box<dyn Error>> {
+        let dir = self.checkpoints.begin(episode)?;
+        self.model.save(dir.join("model.safetensors"))?;
+        self.checkpoints.commit(metric)?;
+        Ok(())
+    }
 }
 
 impl Algorithm for Algorithm2 {
+    fn checkpoints_mut(&mut self) -> Option<&mut Checkpointer> {
+        Some(&mut self.checkpoints)
+    }
+
+    /// The model's named weights, as exported by `export`
+    fn named_tensors(&self) -> Result<Vec<(String, Tensor)>, Box<dyn Error>> {
+        Ok(self.model.named_tensors()?)
+    }
+
+    fn visualization_snapshot(&self) -> Result<ModelStructure, Box<dyn Error>> {
+        Ok(self.model.get_visualization_snapshot()?)
+    }
+
+    /// Accepts a model file, a checkpoint directory or a rotation root (newest checkpoint wins)
+    fn restore(&mut self, path: &std::path::Path) -> Result<Vec<(usize, f32)>, Box<dyn Error>> {
+        if path.is_dir() {
+            self.model
+                .load(checkpoint::resolve(path).join("model.safetensors"))?;
+        } else {
+            self.model.load(path)?;
+        }
+        Ok(Vec::new())
+    }
+
+    fn save_to(&mut self, dir: &std::path::Path) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(dir)?;
+        self.model.save(dir.join("model.safetensors"))?;
+        Ok(())
+    }
+
     fn run(
         &mut self,
         env: &mut dyn Environment,
@@ -67,7 +108,13 @@ impl Algorithm for Algorithm2 {
         vis_state: Option<Arc<Mutex<VisualizationState>>>,
     ) -> Result<(), Box<dyn Error>> {
         let mut total_iteration = 0;
-        let start_time = Instant::now();
+        // Weights to go back to on a reset command from the visualizer
+        let initial_model = vis_state.as_ref().map(|_| self.model.fork());
+        let mut publisher = vis_state.clone().map(|vs| {
+            let mut p = VisPublisher::new(vs);
+            p.snapshot_interval = 20 * self.n_timesteps;
+            p
+        });
         let mut total_inference_time = Duration::new(0, 0);
         let mut total_inference_actions: usize = 0;
         let mut total_training_time = Duration::new(0, 0);
@@ -144,37 +191,22 @@ impl Algorithm for Algorithm2 {
                                 .push((env_state[0] + env_state[2], env_state[1] + env_state[3]));
                         }
                         state.environment_state = Some(env_state);
+                        state.robot_status = env.robot_status();
                     }
 
-                // Visualization check during inference
-                if let Some(ref vis_state_arc) = vis_state {
-                    let should_break = false;
-                    loop {
-                        let (is_paused, should_close, delay) = vis_state_arc
-                            .try_lock()
-                            .map(|state| (state.is_paused, state.should_close, state.delay_ms))
-                            .unwrap_or((false, false, 0));
-                        if should_close {
-                            return Ok(());
-                        }
-                        if !is_paused {
-                            if delay > 0 {
-                                std::thread::sleep(std::time::Duration::from_millis(delay));
-                            }
-                            break;
-                        }
-                        std::thread::sleep(std::time::Duration::from_millis(50));
-                    }
-                    if should_break {
-                        break;
-                    }
+                // Visualization check during inference; the training samples below are the
+                // pause points, so a pause or step takes effect there
+                if let Some(ref vis_state_arc) = vis_state
+                    && !keep_running(vis_state_arc)
+                {
+                    return Ok(());
                 }
             } // end of inference steps
 
             let inference_elapsed = inference_start.elapsed();
             total_inference_time += inference_elapsed;
 
-            // Check if save or load was requested
+            // Check if save, load or reset was requested
             if let Some(state_arc) = &vis_state
                 && let Ok(mut lock) = state_arc.lock() {
                     if lock.save_requested {
@@ -231,6 +263,14 @@ impl Algorithm for Algorithm2 {
                         }
                         lock.load_requested = false;
                     }
+
+                    if lock.reset_requested
+                        && let Some(initial) = &initial_model
+                    {
+                        log::info!("Manual reset requested, restoring the initial weights...");
+                        self.model = initial.fork();
+                        lock.reset_requested = false;
+                    }
                 }
 
             // Update epoch rewards
@@ -241,7 +281,7 @@ impl Algorithm for Algorithm2 {
 
             // 2. Data pairing and augmentation
             let mut train_data = Vec::new(); // (input_tensor_vec, label, reward_normalized)
-            let mut rng = rand::thread_rng();
+            let mut rng = crate::seed::rng();
             let n_states = episode_states.len();
 
             use rand::Rng;
@@ -319,46 +359,30 @@ impl Algorithm for Algorithm2 {
                     }
                     self.model.set_reward(&reward_tensor);
 
-                    // For spike plot history
-                    let mut spike_history = Vec::new();
-                    let record_layer = vis_state
-                        .as_ref()
-                        .and_then(|vs| vs.try_lock().ok().and_then(|s| s.selected_layer_id));
+                    if let Some(p) = publisher.as_mut()
+                        && !p.begin_sample()
+                    {
+                        return Ok(());
+                    }
 
                     self.model.reset(1)?;
                     for _ in 0..self.n_timesteps {
+                        if let Some(p) = publisher.as_ref()
+                            && !p.before_timestep()
+                        {
+                            return Ok(());
+                        }
+
                         self.model.step(&input_tensor, Some(&context_tensor))?;
 
-                        if let Some(layer_id) = record_layer
-                            && let Ok(activity) = self.model.get_layer_activity(layer_id) {
-                                spike_history.push(activity);
-                            }
+                        if let Some(p) = publisher.as_mut() {
+                            p.after_timestep(&self.model, episode, total_iteration);
+                        }
                     }
 
-                    // Update visualization snapshot every 20 iterations during training
-                    if let Some(ref vis_state_arc) = vis_state
-                        && let Ok(mut state) = vis_state_arc.try_lock() {
-                            if !spike_history.is_empty() {
-                                state.epoch_spike_history = Some((episode, spike_history));
-                            }
-                            if total_iteration % 20 == 0 {
-                                if let Ok(snapshot) = self.model.get_visualization_snapshot() {
-                                    state.update_from_snapshot(snapshot);
-                                }
-                                let elapsed = start_time.elapsed().as_secs_f32();
-                                let speed = if elapsed > 0.0 {
-                                    total_iteration as f32 / elapsed
-                                } else {
-                                    0.0
-                                };
-                                state.runtime_stats = RuntimeStats {
-                                    epoch: episode,
-                                    iteration: total_iteration,
-                                    timestep: total_iteration * self.n_timesteps,
-                                    iterations_per_second: speed,
-                                }
-                            }
-                        }
+                    if let Some(p) = publisher.as_mut() {
+                        p.end_sample(episode);
+                    }
                 }
             }
 
@@ -382,6 +406,12 @@ impl Algorithm for Algorithm2 {
                 inf_aps,
                 ep_s
             );
+
+            if self.checkpoints.policy.is_due(episode)
+                && let Err(e) = self.save_rotating(episode, Some(total_reward as f32))
+            {
+                log::error!("Auto-save failed at episode {}: {}", episode, e);
+            }
         } // end of episodes
 
         // Final auto-save
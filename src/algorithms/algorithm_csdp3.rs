@@ -1,7 +1,7 @@
 use super::Algorithm;
 use crate::environment::Environment;
 use crate::models::rl_model3::RLModel3;
-use crate::visualization::{RuntimeStats, VisualizationState};
+use crate::visualization::{RuntimeStats, StepGranularity, VisualizationState, wait_for_advance};
 use candle_core::{Device, Tensor};
 use std::error::Error;
 use std::sync::{Arc, Mutex};
@@ -36,8 +36,7 @@ impl Algorithm3 {
             &device,
             dt,
             state_bounds,
-        )
-        .ok_or("Failed to create RLModel3")?;
+        )?;
 
         Ok(Self {
             model,
@@ -115,7 +114,7 @@ impl Algorithm3 {
                     let pre_act = self.model.actor.layers[pre_id].output()?.clone();
                     syn_conn.synapse.update_weights(
                         &pre_act,
-                        &mut self.model.actor.layers[post_id],
+                        self.model.actor.layers[post_id].as_ref(),
                         self.model.dt,
                     )?;
                 }
@@ -190,6 +189,10 @@ impl Algorithm for Algorithm3 {
         let action_size = env.action_size();
         let state_size = env.state_size();
 
+        // The critic runs through Model::step, so its phase timings feed the perf overlay.
+        // Profiling slows every step down, so it only runs while the overlay is shown.
+        let mut profiling = false;
+
         for episode in 1..=self.n_episodes {
             if let Some(ref vis_state_arc) = vis_state
                 && vis_state_arc
@@ -206,21 +209,8 @@ impl Algorithm for Algorithm3 {
 
             for step in 0..self.n_steps_per_episode {
                 if let Some(ref vis_state_arc) = vis_state {
-                    loop {
-                        let (is_paused, should_close, delay) = vis_state_arc
-                            .try_lock()
-                            .map(|state| (state.is_paused, state.should_close, state.delay_ms))
-                            .unwrap_or((false, false, 0));
-                        if should_close {
-                            return Ok(());
-                        }
-                        if !is_paused {
-                            if delay > 0 {
-                                std::thread::sleep(std::time::Duration::from_millis(delay));
-                            }
-                            break;
-                        }
-                        std::thread::sleep(std::time::Duration::from_millis(50));
+                    if !wait_for_advance(vis_state_arc, StepGranularity::Sample) {
+                        return Ok(());
                     }
                 }
 
@@ -382,6 +372,7 @@ impl Algorithm for Algorithm3 {
                                 .push((env_state[0] + env_state[2], env_state[1] + env_state[3]));
                         }
                         state.environment_state = Some(env_state);
+                        state.robot_status = env.robot_status();
 
                         if !spike_history.is_empty() {
                             state.epoch_spike_history = Some((episode, spike_history.clone()));
@@ -406,6 +397,20 @@ impl Algorithm for Algorithm3 {
                                 iteration: total_iteration,
                                 timestep: total_iteration * self.n_timesteps,
                                 iterations_per_second: speed,
+                            };
+                            if state.profiling_requested != profiling {
+                                profiling = state.profiling_requested;
+                                if profiling {
+                                    self.model.critic.enable_profiling();
+                                } else {
+                                    self.model.critic.disable_profiling();
+                                }
+                            }
+                            if let Some(mut perf) = self.model.critic.take_perf_stats() {
+                                // GPU memory is polled by the visualizer thread
+                                perf.gpu_memory_mb =
+                                    state.perf_stats.as_ref().and_then(|p| p.gpu_memory_mb);
+                                state.perf_stats = Some(perf);
                             }
                         }
                     }
@@ -2,7 +2,8 @@
 use super::Algorithm;
 use crate::environment::Environment;
 use crate::models::rl_model2::RLModel2;
-use crate::visualization::VisualizationState;
+use crate::shaping::{RewardNormalizer, discounted_returns};
+use crate::visualization::{ModelStructure, StepGranularity, VisualizationState, wait_for_advance};
 use candle_core::{Device, Tensor};
 use rand::Rng;
 use std::error::Error;
@@ -17,6 +18,9 @@ pub struct Algorithm4 {
     pub n_timesteps: usize,
     pub device: Device,
     pub buffer: Vec<(Vec<f32>, usize, f32)>, // state, action, reward
+    /// Standardize each episode's returns by their running statistics before they enter the
+    /// buffer, so the sigmoid of the positive rewards doesn't saturate on large returns
+    pub return_normalizer: Option<RewardNormalizer>,
 }
 
 impl Algorithm4 {
@@ -46,8 +50,7 @@ impl Algorithm4 {
             &device,
             dt,
             input_bounds,
-        )
-        .ok_or("Failed to create RLModel2")?;
+        )?;
 
         Ok(Self {
             model,
@@ -57,11 +60,25 @@ impl Algorithm4 {
             n_timesteps: 40,
             device,
             buffer: Vec::new(),
+            return_normalizer: None,
         })
     }
 }
 
 impl Algorithm for Algorithm4 {
+    fn named_tensors(&self) -> Result<Vec<(String, Tensor)>, Box<dyn Error>> {
+        Ok(self.model.named_tensors()?)
+    }
+
+    fn visualization_snapshot(&self) -> Result<ModelStructure, Box<dyn Error>> {
+        Ok(self.model.get_visualization_snapshot()?)
+    }
+
+    fn restore(&mut self, path: &std::path::Path) -> Result<Vec<(usize, f32)>, Box<dyn Error>> {
+        self.model.load(path)?;
+        Ok(Vec::new())
+    }
+
     fn run(
         &mut self,
         env: &mut dyn Environment,
@@ -94,7 +111,7 @@ impl Algorithm for Algorithm4 {
             let mut raw_rewards = vec![0.0; n_envs];
 
             let inference_start = Instant::now();
-            let mut rng = rand::thread_rng();
+            let mut rng = crate::seed::rng();
 
             let tau = 0.07;
 
@@ -199,21 +216,8 @@ impl Algorithm for Algorithm4 {
 
                 if let Some(ref vis_state_arc) = vis_state {
                     let should_break = false;
-                    loop {
-                        let (is_paused, should_close, delay) = vis_state_arc
-                            .try_lock()
-                            .map(|state| (state.is_paused, state.should_close, state.delay_ms))
-                            .unwrap_or((false, false, 0));
-                        if should_close {
-                            return Ok(());
-                        }
-                        if !is_paused {
-                            if delay > 0 {
-                                std::thread::sleep(std::time::Duration::from_millis(delay));
-                            }
-                            break;
-                        }
-                        std::thread::sleep(std::time::Duration::from_millis(50));
+                    if !wait_for_advance(vis_state_arc, StepGranularity::Sample) {
+                        return Ok(());
                     }
                     if should_break {
                         break;
@@ -239,10 +243,13 @@ impl Algorithm for Algorithm4 {
 
             for env_idx in 0..n_envs {
                 let seq = &mut episode_data[env_idx];
-                let mut current_r = 0.0;
-                for t in (0..seq.len()).rev() {
-                    current_r = seq[t].2 + gamma * current_r;
-                    seq[t] = (seq[t].0.clone(), seq[t].1, current_r);
+                let rewards: Vec<f32> = seq.iter().map(|item| item.2).collect();
+                let mut returns = discounted_returns(&rewards, gamma);
+                if let Some(normalizer) = self.return_normalizer.as_mut() {
+                    normalizer.normalize_batch(&mut returns);
+                }
+                for (item, ret) in seq.iter_mut().zip(returns) {
+                    item.2 = ret;
                 }
                 for item in seq.iter() {
                     self.buffer.push(item.clone());
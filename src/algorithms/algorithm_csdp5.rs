@@ -1,8 +1,9 @@
 #![allow(clippy::needless_range_loop)]
 use super::Algorithm;
+use super::checkpoint::{self, CheckpointPolicy, Checkpointer};
 use crate::environment::Environment;
 use crate::models::csdp_multi_model::CSDPMultiModel;
-use crate::visualization::VisualizationState;
+use crate::visualization::{ModelStructure, StepGranularity, VisualizationState, wait_for_advance};
 use candle_core::{Device, Tensor};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
@@ -12,7 +13,6 @@ use std::time::{Duration, Instant};
 
 const NUM_RETURN_CLASSES: usize = 50;
 const ACTION_SCALE: f32 = 3.0; // Same action amplification map as FFMulti2
-const AUTO_SAVE_INTERVAL: usize = 50;
 const CHECKPOINT_DIR: &str = "checkpoints/csdp5";
 
 #[derive(Serialize, Deserialize)]
@@ -23,6 +23,9 @@ struct TrainingState {
     bounds_initialized: bool,
     completed_episode: usize,
     epoch_rewards: Vec<(usize, f32)>,
+    /// Host RNG seed the run continued from after this checkpoint
+    #[serde(default)]
+    rng_seed: Option<u64>,
 }
 
 pub struct AlgorithmCSDP5 {
@@ -37,6 +40,7 @@ pub struct AlgorithmCSDP5 {
     pub bounds_initialized: bool,
     pub start_episode: usize,
     pub timesteps: usize, // SNN evaluation timesteps
+    pub checkpoints: Checkpointer,
 }
 
 impl AlgorithmCSDP5 {
@@ -74,6 +78,7 @@ impl AlgorithmCSDP5 {
             bounds_initialized: false,
             start_episode: 0,
             timesteps,
+            checkpoints: Checkpointer::new(CHECKPOINT_DIR, CheckpointPolicy::every(50)),
         })
     }
 
@@ -96,16 +101,32 @@ impl AlgorithmCSDP5 {
             bounds_initialized: self.bounds_initialized,
             completed_episode,
             epoch_rewards: epoch_rewards.to_vec(),
+            rng_seed: Some(crate::seed::fork(&self.device)?),
         };
         let json = serde_json::to_string(&state)?;
         std::fs::write(dir.join("training_state.json"), json)?;
         Ok(())
     }
 
+    /// Save into the rotation under `checkpoints`; `metric` competes for `best/`
+    fn save_rotating(
+        &mut self,
+        episode: usize,
+        metric: Option<f32>,
+        epoch_rewards: &[(usize, f32)],
+    ) -> Result<(), Box<dyn Error>> {
+        let dir = self.checkpoints.begin(episode)?;
+        self.save_checkpoint(&dir, episode, epoch_rewards)?;
+        self.checkpoints.commit(metric)?;
+        Ok(())
+    }
+
+    /// Load from a checkpoint directory, or the newest one in a rotation root
     pub fn load_checkpoint(
         &mut self,
         dir: &std::path::Path,
     ) -> Result<Vec<(usize, f32)>, Box<dyn Error>> {
+        let dir = checkpoint::resolve(dir);
         let model_file = dir.join("model.safetensors");
         self.model.load(&model_file)?;
 
@@ -117,6 +138,9 @@ impl AlgorithmCSDP5 {
         self.max_return = state.max_return;
         self.bounds_initialized = state.bounds_initialized;
         self.start_episode = state.completed_episode;
+        if let Some(seed) = state.rng_seed {
+            crate::seed::reseed(seed, &self.device)?;
+        }
 
         Ok(state.epoch_rewards)
     }
@@ -140,6 +164,22 @@ impl AlgorithmCSDP5 {
 }
 
 impl Algorithm for AlgorithmCSDP5 {
+    fn checkpoints_mut(&mut self) -> Option<&mut Checkpointer> {
+        Some(&mut self.checkpoints)
+    }
+
+    fn named_tensors(&self) -> Result<Vec<(String, Tensor)>, Box<dyn Error>> {
+        Ok(self.model.named_tensors()?)
+    }
+
+    fn visualization_snapshot(&self) -> Result<ModelStructure, Box<dyn Error>> {
+        Ok(self.model.get_visualization_snapshot()?)
+    }
+
+    fn restore(&mut self, path: &std::path::Path) -> Result<Vec<(usize, f32)>, Box<dyn Error>> {
+        self.load_checkpoint(path)
+    }
+
     fn run(
         &mut self,
         env: &mut dyn Environment,
@@ -163,7 +203,7 @@ impl Algorithm for AlgorithmCSDP5 {
         let gamma = 0.9f32;
         let tau = 0.5f32;
 
-        let checkpoint_dir = std::path::Path::new(CHECKPOINT_DIR);
+        let checkpoint_dir = self.checkpoints.root().to_path_buf();
 
         // Episode range
         let mut episode = self.start_episode + 1;
@@ -189,7 +229,7 @@ impl Algorithm for AlgorithmCSDP5 {
             }
 
             let inference_start = Instant::now();
-            let mut rng = rand::thread_rng();
+            let mut rng = crate::seed::rng();
             let epsilon = (0.2f32 * (-(episode as f32) / 50.0).exp()).max(0.01);
 
             self.model.disable_learning();
@@ -352,21 +392,8 @@ impl Algorithm for AlgorithmCSDP5 {
                     }
 
                     let should_break = false;
-                    loop {
-                        let (is_paused, should_close, delay) = vs_arc
-                            .try_lock()
-                            .map(|state| (state.is_paused, state.should_close, state.delay_ms))
-                            .unwrap_or((false, false, 0));
-                        if should_close {
-                            return Ok(());
-                        }
-                        if !is_paused {
-                            if delay > 0 {
-                                std::thread::sleep(std::time::Duration::from_millis(delay));
-                            }
-                            break;
-                        }
-                        std::thread::sleep(std::time::Duration::from_millis(50));
+                    if !wait_for_advance(vs_arc, StepGranularity::Sample) {
+                        return Ok(());
                     }
                     if should_break {
                         break;
@@ -458,10 +485,11 @@ impl Algorithm for AlgorithmCSDP5 {
 
             total_training_time += training_start.elapsed();
 
+            let avg_reward = raw_rewards.iter().sum::<f64>() as f32 / n_envs as f32;
+
             // Update Visualization
             if let Some(ref vs_arc) = vis_state
                 && let Ok(mut state) = vs_arc.try_lock() {
-                    let avg_reward = raw_rewards.iter().sum::<f64>() as f32 / n_envs as f32;
                     state.epoch_rewards.push((episode, avg_reward));
                     state.runtime_stats.epoch = episode;
                     state.total_epochs = self.n_episodes;
@@ -484,16 +512,14 @@ impl Algorithm for AlgorithmCSDP5 {
                         let epoch_rewards = state.epoch_rewards.clone();
                         state.save_requested = false;
                         drop(state);
-                        if let Err(e) =
-                            self.save_checkpoint(checkpoint_dir, episode, &epoch_rewards)
-                        {
+                        if let Err(e) = self.save_rotating(episode, None, &epoch_rewards) {
                             log::error!("Manual save failed: {}", e);
                         }
                     } else if state.load_requested {
                         log::info!("Manual load requested...");
                         state.load_requested = false;
                         drop(state);
-                        match self.load_checkpoint(checkpoint_dir) {
+                        match self.load_checkpoint(&checkpoint_dir) {
                             Ok(epoch_rewards) => {
                                 if let Some(ref vs2) = vis_state
                                     && let Ok(mut s) = vs2.try_lock() {
@@ -510,12 +536,12 @@ impl Algorithm for AlgorithmCSDP5 {
                     }
                 }
 
-            if episode.is_multiple_of(AUTO_SAVE_INTERVAL) {
+            if self.checkpoints.policy.is_due(episode) {
                 let epoch_rewards = vis_state
                     .as_ref()
                     .and_then(|vs| vs.try_lock().ok().map(|s| s.epoch_rewards.clone()))
                     .unwrap_or_default();
-                if let Err(e) = self.save_checkpoint(checkpoint_dir, episode, &epoch_rewards) {
+                if let Err(e) = self.save_rotating(episode, Some(avg_reward), &epoch_rewards) {
                     log::error!("Auto-save failed: {}", e);
                 }
             }
@@ -547,6 +573,17 @@ impl Algorithm for AlgorithmCSDP5 {
             episode += 1;
         }
 
+        // Final checkpoint, unless the last episode was just saved
+        if episode_end > self.start_episode && !self.checkpoints.policy.is_due(episode_end) {
+            let epoch_rewards = vis_state
+                .as_ref()
+                .and_then(|vs| vs.try_lock().ok().map(|s| s.epoch_rewards.clone()))
+                .unwrap_or_default();
+            if let Err(e) = self.save_rotating(episode_end, None, &epoch_rewards) {
+                log::error!("Final save failed: {}", e);
+            }
+        }
+
         Ok(())
     }
 }
@@ -1,8 +1,9 @@
 #![allow(clippy::needless_range_loop)]
-use super::Algorithm;
+use super::{Algorithm, prefixed};
+use super::checkpoint::{self, CheckpointPolicy, Checkpointer};
 use crate::environment::Environment;
 use crate::models::csdp_multi_model::CSDPMultiModel;
-use crate::visualization::VisualizationState;
+use crate::visualization::{ModelStructure, StepGranularity, VisualizationState, wait_for_advance};
 use candle_core::{Device, Tensor};
 use rand::Rng;
 use rand::seq::SliceRandom;
@@ -21,7 +22,6 @@ const PPO_EPOCHS: usize = 2;
 const TRAIN_BATCH_SIZE: usize = 256;
 const GAMMA: f32 = 0.99;
 const GAE_LAMBDA: f32 = 0.95;
-const AUTO_SAVE_INTERVAL: usize = 25;
 const CHECKPOINT_DIR: &str = "checkpoints/csdp_ppo";
 const BOUNDS_EMA_ALPHA: f32 = 0.1;
 const MIN_RETURN_RANGE: f32 = 2.0;
@@ -41,6 +41,9 @@ struct TrainingState {
     bounds_initialized: bool,
     completed_episode: usize,
     epoch_rewards: Vec<(usize, f32)>,
+    /// Host RNG seed the run continued from after this checkpoint
+    #[serde(default)]
+    rng_seed: Option<u64>,
 }
 
 fn class_to_value(class_id: usize, min_ret: f32, max_ret: f32, n_classes: usize) -> f32 {
@@ -158,6 +161,7 @@ pub struct AlgorithmCSDPPPO {
     pub max_return: f32,
     pub bounds_initialized: bool,
     pub start_episode: usize,
+    pub checkpoints: Checkpointer,
 }
 
 impl AlgorithmCSDPPPO {
@@ -213,6 +217,7 @@ impl AlgorithmCSDPPPO {
             max_return: 0.0,
             bounds_initialized: false,
             start_episode: 0,
+            checkpoints: Checkpointer::new(CHECKPOINT_DIR, CheckpointPolicy::every(25)),
         })
     }
 
@@ -231,6 +236,7 @@ impl AlgorithmCSDPPPO {
             bounds_initialized: self.bounds_initialized,
             completed_episode,
             epoch_rewards: epoch_rewards.to_vec(),
+            rng_seed: Some(crate::seed::fork(&self.device)?),
         };
         std::fs::write(
             dir.join("training_state.json"),
@@ -246,10 +252,25 @@ impl AlgorithmCSDPPPO {
         Ok(())
     }
 
+    /// Save into the rotation under `checkpoints`; `metric` competes for `best/`
+    fn save_rotating(
+        &mut self,
+        episode: usize,
+        metric: Option<f32>,
+        epoch_rewards: &[(usize, f32)],
+    ) -> Result<(), Box<dyn Error>> {
+        let dir = self.checkpoints.begin(episode)?;
+        self.save_checkpoint(&dir, episode, epoch_rewards)?;
+        self.checkpoints.commit(metric)?;
+        Ok(())
+    }
+
+    /// Load from a checkpoint directory, or the newest one in a rotation root
     pub fn load_checkpoint(
         &mut self,
         dir: &std::path::Path,
     ) -> Result<Vec<(usize, f32)>, Box<dyn Error>> {
+        let dir = checkpoint::resolve(dir);
         self.policy_model.load(dir.join("policy_model.safetensors"))?;
         self.value_model.load(dir.join("value_model.safetensors"))?;
         let json = std::fs::read_to_string(dir.join("training_state.json"))?;
@@ -258,6 +279,9 @@ impl AlgorithmCSDPPPO {
         self.max_return = state.max_return;
         self.bounds_initialized = state.bounds_initialized;
         self.start_episode = state.completed_episode;
+        if let Some(seed) = state.rng_seed {
+            crate::seed::reseed(seed, &self.device)?;
+        }
         log::info!(
             "Checkpoint loaded from {:?} (resuming after episode {}, return range [{:.4}, {:.4}])",
             dir,
@@ -270,6 +294,24 @@ impl AlgorithmCSDPPPO {
 }
 
 impl Algorithm for AlgorithmCSDPPPO {
+    fn checkpoints_mut(&mut self) -> Option<&mut Checkpointer> {
+        Some(&mut self.checkpoints)
+    }
+
+    fn named_tensors(&self) -> Result<Vec<(String, Tensor)>, Box<dyn Error>> {
+        let mut tensors = prefixed("policy", self.policy_model.named_tensors()?);
+        tensors.extend(prefixed("value", self.value_model.named_tensors()?));
+        Ok(tensors)
+    }
+
+    fn visualization_snapshot(&self) -> Result<ModelStructure, Box<dyn Error>> {
+        Ok(self.policy_model.get_visualization_snapshot()?)
+    }
+
+    fn restore(&mut self, path: &std::path::Path) -> Result<Vec<(usize, f32)>, Box<dyn Error>> {
+        self.load_checkpoint(path)
+    }
+
     fn run(
         &mut self,
         env: &mut dyn Environment,
@@ -284,8 +326,8 @@ impl Algorithm for AlgorithmCSDPPPO {
             envs.push(env.clone_box());
         }
 
-        let checkpoint_dir = std::path::Path::new(CHECKPOINT_DIR);
-        let mut rng = rand::thread_rng();
+        let checkpoint_dir = self.checkpoints.root().to_path_buf();
+        let mut rng = crate::seed::rng();
 
         let mut episode = self.start_episode + 1;
         let mut episode_end = self.start_episode.saturating_add(self.n_episodes);
@@ -421,21 +463,8 @@ impl Algorithm for AlgorithmCSDPPPO {
                         ]);
                     }
 
-                    loop {
-                        let (is_paused, should_close, delay) = vs
-                            .try_lock()
-                            .map(|s| (s.is_paused, s.should_close, s.delay_ms))
-                            .unwrap_or((false, false, 0));
-                        if should_close {
-                            return Ok(());
-                        }
-                        if !is_paused {
-                            if delay > 0 {
-                                std::thread::sleep(Duration::from_millis(delay));
-                            }
-                            break;
-                        }
-                        std::thread::sleep(Duration::from_millis(50));
+                    if !wait_for_advance(vs, StepGranularity::Sample) {
+                        return Ok(());
                     }
                 }
             }
@@ -666,13 +695,13 @@ impl Algorithm for AlgorithmCSDPPPO {
                     let epoch_rewards = state.epoch_rewards.clone();
                     state.save_requested = false;
                     drop(state);
-                    if let Err(e) = self.save_checkpoint(checkpoint_dir, episode, &epoch_rewards) {
+                    if let Err(e) = self.save_rotating(episode, None, &epoch_rewards) {
                         log::error!("Manual save failed: {}", e);
                     }
                 } else if state.load_requested {
                     state.load_requested = false;
                     drop(state);
-                    match self.load_checkpoint(checkpoint_dir) {
+                    match self.load_checkpoint(&checkpoint_dir) {
                         Ok(epoch_rewards) => {
                             if let Some(ref vs2) = vis_state
                                 && let Ok(mut s) = vs2.try_lock()
@@ -689,12 +718,12 @@ impl Algorithm for AlgorithmCSDPPPO {
             }
 
             // ── Periodic auto-save ──
-            if episode.is_multiple_of(AUTO_SAVE_INTERVAL) {
+            if self.checkpoints.policy.is_due(episode) {
                 let epoch_rewards = vis_state
                     .as_ref()
                     .and_then(|vs| vs.try_lock().ok().map(|s| s.epoch_rewards.clone()))
                     .unwrap_or_default();
-                if let Err(e) = self.save_checkpoint(checkpoint_dir, episode, &epoch_rewards) {
+                if let Err(e) = self.save_rotating(episode, Some(avg_reward), &epoch_rewards) {
                     log::error!("Auto-save failed at episode {}: {}", episode, e);
                 }
             }
@@ -729,7 +758,7 @@ impl Algorithm for AlgorithmCSDPPPO {
             .as_ref()
             .and_then(|vs| vs.try_lock().ok().map(|s| s.epoch_rewards.clone()))
             .unwrap_or_default();
-        if let Err(e) = self.save_checkpoint(checkpoint_dir, episode_end, &epoch_rewards) {
+        if let Err(e) = self.save_rotating(episode_end, None, &epoch_rewards) {
             log::error!("Final save failed: {}", e);
         }
         if let Some(ref vs) = vis_state
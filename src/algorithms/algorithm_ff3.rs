@@ -1,7 +1,7 @@
 use super::Algorithm;
 use crate::environment::Environment;
 use crate::models::ff_model::FFModel;
-use crate::visualization::VisualizationState;
+use crate::visualization::{StepGranularity, VisualizationState, wait_for_advance};
 use candle_core::{Device, Tensor};
 use rand::Rng;
 use std::error::Error;
@@ -128,21 +128,8 @@ impl Algorithm for AlgorithmFF3 {
 
                 if let Some(ref vis_state_arc) = vis_state {
                     let should_break = false;
-                    loop {
-                        let (is_paused, should_close, delay) = vis_state_arc
-                            .try_lock()
-                            .map(|state| (state.is_paused, state.should_close, state.delay_ms))
-                            .unwrap_or((false, false, 0));
-                        if should_close {
-                            return Ok(());
-                        }
-                        if !is_paused {
-                            if delay > 0 {
-                                std::thread::sleep(std::time::Duration::from_millis(delay));
-                            }
-                            break;
-                        }
-                        std::thread::sleep(std::time::Duration::from_millis(50));
+                    if !wait_for_advance(vis_state_arc, StepGranularity::Sample) {
+                        return Ok(());
                     }
                     if should_break {
                         break;
@@ -172,7 +159,7 @@ impl Algorithm for AlgorithmFF3 {
                 total_rewards.iter().copied().enumerate().collect();
             env_ranks.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
 
-            let mut rng = rand::thread_rng();
+            let mut rng = crate::seed::rng();
             let k = n_envs as f32;
 
             for (rank, &(env_idx, _r)) in env_ranks.iter().enumerate() {
@@ -3,7 +3,7 @@
 use super::Algorithm;
 use crate::environment::Environment;
 use crate::models::ff_model::FFModel;
-use crate::visualization::VisualizationState;
+use crate::visualization::{StepGranularity, VisualizationState, wait_for_advance};
 use candle_core::{Device, Tensor};
 use rand::Rng;
 use std::error::Error;
@@ -76,7 +76,7 @@ impl Algorithm for AlgorithmFF4 {
             let mut raw_rewards = vec![0.0; n_envs];
 
             let inference_start = Instant::now();
-            let mut rng = rand::thread_rng();
+            let mut rng = crate::seed::rng();
 
             // let tau = f32::max(0.05, 0.25 - (episode as f32 / self.n_episodes as f32) * 0.2);
             let tau = 0.07;
@@ -167,21 +167,8 @@ impl Algorithm for AlgorithmFF4 {
 
                 if let Some(ref vis_state_arc) = vis_state {
                     let should_break = false;
-                    loop {
-                        let (is_paused, should_close, delay) = vis_state_arc
-                            .try_lock()
-                            .map(|state| (state.is_paused, state.should_close, state.delay_ms))
-                            .unwrap_or((false, false, 0));
-                        if should_close {
-                            return Ok(());
-                        }
-                        if !is_paused {
-                            if delay > 0 {
-                                std::thread::sleep(std::time::Duration::from_millis(delay));
-                            }
-                            break;
-                        }
-                        std::thread::sleep(std::time::Duration::from_millis(50));
+                    if !wait_for_advance(vis_state_arc, StepGranularity::Sample) {
+                        return Ok(());
                     }
                     if should_break {
                         break;
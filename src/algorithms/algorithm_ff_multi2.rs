@@ -1,7 +1,8 @@
-use super::Algorithm;
+use super::{Algorithm, prefixed};
+use super::checkpoint::{self, CheckpointPolicy, Checkpointer};
 use crate::environment::Environment;
 use crate::models::ff_multi_model::FFMultiModel;
-use crate::visualization::VisualizationState;
+use crate::visualization::{StepGranularity, VisualizationState, wait_for_advance};
 use candle_core::{Device, Tensor};
 use rand::Rng;
 use rand::seq::SliceRandom;
@@ -13,9 +14,6 @@ use std::time::{Duration, Instant};
 /// Number of discrete return classes the model predicts.
 const NUM_RETURN_CLASSES: usize = 50;
 
-/// How often (in episodes) to auto-save a checkpoint.
-const AUTO_SAVE_INTERVAL: usize = 25;
-
 /// Default checkpoint directory for this algorithm.
 const CHECKPOINT_DIR: &str = "checkpoints/ff_multi2";
 
@@ -53,6 +51,9 @@ struct TrainingState {
     completed_episode: usize,
     /// Epoch reward history for the training graph.
     epoch_rewards: Vec<(usize, f32)>,
+    /// Host RNG seed the run continued from after this checkpoint
+    #[serde(default)]
+    rng_seed: Option<u64>,
 }
 
 // ─────────────────────────────────────────────────────────────
@@ -123,6 +124,7 @@ pub struct AlgorithmFFMulti2 {
     pub target_sync_interval: usize,
     /// Episode number to start from (0 = fresh run, >0 = resumed).
     pub start_episode: usize,
+    pub checkpoints: Checkpointer,
 }
 
 impl AlgorithmFFMulti2 {
@@ -167,6 +169,7 @@ impl AlgorithmFFMulti2 {
             bounds_initialized: false,
             target_sync_interval: 10, // sync every N episodes
             start_episode: 0,
+            checkpoints: Checkpointer::new(CHECKPOINT_DIR, CheckpointPolicy::every(25)),
         })
     }
 
@@ -197,6 +200,7 @@ impl AlgorithmFFMulti2 {
             bounds_initialized: self.bounds_initialized,
             completed_episode,
             epoch_rewards: epoch_rewards.to_vec(),
+            rng_seed: Some(crate::seed::fork(&self.device)?),
         };
         let json = serde_json::to_string(&state)?;
         std::fs::write(dir.join("training_state.json"), json)?;
@@ -211,13 +215,28 @@ impl AlgorithmFFMulti2 {
         Ok(())
     }
 
+    /// Save into the rotation under `checkpoints`; `metric` competes for `best/`
+    fn save_rotating(
+        &mut self,
+        episode: usize,
+        metric: Option<f32>,
+        epoch_rewards: &[(usize, f32)],
+    ) -> Result<(), Box<dyn Error>> {
+        let dir = self.checkpoints.begin(episode)?;
+        self.save_checkpoint(&dir, episode, epoch_rewards)?;
+        self.checkpoints.commit(metric)?;
+        Ok(())
+    }
+
     /// Load a checkpoint, restoring model weights and all training state.
     /// Returns the epoch_rewards history so the caller can feed it to the
     /// visualisation state.
+    /// Accepts a checkpoint directory, or the newest one in a rotation root.
     pub fn load_checkpoint(
         &mut self,
         dir: &std::path::Path,
     ) -> Result<Vec<(usize, f32)>, Box<dyn Error>> {
+        let dir = checkpoint::resolve(dir);
         // 1. Load model weights.
         let main_dir = dir.join("main_model");
         self.main_model.load(&main_dir)?;
@@ -233,6 +252,9 @@ impl AlgorithmFFMulti2 {
         self.max_return = state.max_return;
         self.bounds_initialized = state.bounds_initialized;
         self.start_episode = state.completed_episode;
+        if let Some(seed) = state.rng_seed {
+            crate::seed::reseed(seed, &self.device)?;
+        }
 
         log::info!(
             "Checkpoint loaded from {:?} (resuming after episode {}, buffer size {}, return range [{:.4}, {:.4}])",
@@ -289,6 +311,20 @@ fn normalize_state(raw: &[f64]) -> Vec<f32> {
 // ─────────────────────────────────────────────────────────────
 
 impl Algorithm for AlgorithmFFMulti2 {
+    fn checkpoints_mut(&mut self) -> Option<&mut Checkpointer> {
+        Some(&mut self.checkpoints)
+    }
+
+    fn named_tensors(&self) -> Result<Vec<(String, Tensor)>, Box<dyn Error>> {
+        let mut tensors = prefixed("main", self.main_model.named_tensors()?);
+        tensors.extend(prefixed("target", self.target_model.named_tensors()?));
+        Ok(tensors)
+    }
+
+    fn restore(&mut self, path: &std::path::Path) -> Result<Vec<(usize, f32)>, Box<dyn Error>> {
+        self.load_checkpoint(path)
+    }
+
     fn run(
         &mut self,
         env: &mut dyn Environment,
@@ -313,7 +349,7 @@ impl Algorithm for AlgorithmFFMulti2 {
         let gamma = 0.9f32;
         let tau = 0.5f32; // Boltzmann temperature for action selection
 
-        let checkpoint_dir = std::path::Path::new(CHECKPOINT_DIR);
+        let checkpoint_dir = self.checkpoints.root().to_path_buf();
 
         // Episode range: if resuming, start after the last completed episode.
         let mut episode = self.start_episode + 1;
@@ -346,7 +382,7 @@ impl Algorithm for AlgorithmFFMulti2 {
 
             let mut raw_rewards = vec![0.0f64; n_envs];
             let inference_start = Instant::now();
-            let mut rng = rand::thread_rng();
+            let mut rng = crate::seed::rng();
 
             // ═══════════════════════════════════════════════════
             // Phase 1: Environmental Interaction (Inference)
@@ -557,21 +593,8 @@ impl Algorithm for AlgorithmFFMulti2 {
 
                 // ── Pause / delay / close ──
                 if let Some(ref vs) = vis_state {
-                    loop {
-                        let (is_paused, should_close, delay) = vs
-                            .try_lock()
-                            .map(|s| (s.is_paused, s.should_close, s.delay_ms))
-                            .unwrap_or((false, false, 0));
-                        if should_close {
-                            return Ok(());
-                        }
-                        if !is_paused {
-                            if delay > 0 {
-                                std::thread::sleep(Duration::from_millis(delay));
-                            }
-                            break;
-                        }
-                        std::thread::sleep(Duration::from_millis(50));
+                    if !wait_for_advance(vs, StepGranularity::Sample) {
+                        return Ok(());
                     }
                 }
             }
@@ -580,10 +603,10 @@ impl Algorithm for AlgorithmFFMulti2 {
             total_inference_time += inference_elapsed;
 
             // ── Record episode reward ──
+            let avg_reward = raw_rewards.iter().sum::<f64>() as f32
+                / (n_envs as f32 * self.n_steps_per_episode as f32);
             if let Some(ref vs) = vis_state
                 && let Ok(mut state) = vs.try_lock() {
-                    let avg_reward = raw_rewards.iter().sum::<f64>() as f32
-                        / (n_envs as f32 * self.n_steps_per_episode as f32);
                     state.epoch_rewards.push((episode, avg_reward));
                     state.runtime_stats.epoch = episode;
                     state.total_epochs = episode_end;
@@ -733,7 +756,7 @@ impl Algorithm for AlgorithmFFMulti2 {
                         state.save_requested = false;
                         drop(state);
                         if let Err(e) =
-                            self.save_checkpoint(checkpoint_dir, episode, &epoch_rewards)
+                            self.save_rotating(episode, None, &epoch_rewards)
                         {
                             log::error!("Manual save failed: {}", e);
                         }
@@ -741,7 +764,7 @@ impl Algorithm for AlgorithmFFMulti2 {
                         log::info!("Manual load requested...");
                         state.load_requested = false;
                         drop(state);
-                        match self.load_checkpoint(checkpoint_dir) {
+                        match self.load_checkpoint(&checkpoint_dir) {
                             Ok(epoch_rewards) => {
                                 // Push restored rewards into vis state.
                                 if let Some(ref vs2) = vis_state
@@ -766,12 +789,12 @@ impl Algorithm for AlgorithmFFMulti2 {
                 }
 
             // ── Periodic auto-save ──
-            if episode.is_multiple_of(AUTO_SAVE_INTERVAL) {
+            if self.checkpoints.policy.is_due(episode) {
                 let epoch_rewards = vis_state
                     .as_ref()
                     .and_then(|vs| vs.try_lock().ok().map(|s| s.epoch_rewards.clone()))
                     .unwrap_or_default();
-                if let Err(e) = self.save_checkpoint(checkpoint_dir, episode, &epoch_rewards) {
+                if let Err(e) = self.save_rotating(episode, Some(avg_reward), &epoch_rewards) {
                     log::error!("Auto-save failed at episode {}: {}", episode, e);
                 }
             }
@@ -809,7 +832,7 @@ impl Algorithm for AlgorithmFFMulti2 {
             .as_ref()
             .and_then(|vs| vs.try_lock().ok().map(|s| s.epoch_rewards.clone()))
             .unwrap_or_default();
-        if let Err(e) = self.save_checkpoint(checkpoint_dir, episode_end, &epoch_rewards) {
+        if let Err(e) = self.save_rotating(episode_end, None, &epoch_rewards) {
             log::error!("Final save failed: {}", e);
         }
 
@@ -0,0 +1,208 @@
+//! Periodic checkpointing with rotation.
+//!
+//! Every checkpoint is written to `<root>/episode_<n>.tmp/` and renamed to `<root>/episode_<n>/`
+//! once complete, so a crash mid-save never leaves a truncated checkpoint behind. Only the
+//! newest `keep_last` episode directories are kept, and the best checkpoint by metric (higher is
+//! better) is copied to `<root>/best/`.
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const EPISODE_PREFIX: &str = "episode_";
+const BEST_DIR: &str = "best";
+const BEST_MARKER: &str = "best.json";
+
+#[derive(Debug, Clone, Copy)]
+pub struct CheckpointPolicy {
+    /// Save every N episodes; 0 disables periodic saves
+    pub every: usize,
+    /// Number of periodic checkpoints to keep
+    pub keep_last: usize,
+    /// Also keep the best checkpoint by metric in `best/`
+    pub keep_best: bool,
+}
+
+impl CheckpointPolicy {
+    pub fn every(every: usize) -> Self {
+        Self {
+            every,
+            keep_last: 3,
+            keep_best: true,
+        }
+    }
+
+    pub fn is_due(&self, episode: usize) -> bool {
+        self.every > 0 && episode.is_multiple_of(self.every)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BestMarker {
+    episode: usize,
+    metric: f32,
+}
+
+pub struct Checkpointer {
+    root: PathBuf,
+    pub policy: CheckpointPolicy,
+    best_metric: Option<f32>,
+    pending: Option<usize>,
+}
+
+impl Checkpointer {
+    /// Picks up the best metric of an earlier run in `root`, so a resumed run only replaces
+    /// `best/` when it actually improves on it
+    pub fn new(root: impl Into<PathBuf>, policy: CheckpointPolicy) -> Self {
+        let root = root.into();
+        let best_metric = fs::read_to_string(root.join(BEST_DIR).join(BEST_MARKER))
+            .ok()
+            .and_then(|json| serde_json::from_str::<BestMarker>(&json).ok())
+            .map(|marker| marker.metric);
+        Self {
+            root,
+            policy,
+            best_metric,
+            pending: None,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Move future checkpoints to `root`, e.g. a per-run output directory
+    pub fn set_root(&mut self, root: impl Into<PathBuf>) {
+        *self = Self::new(root, self.policy);
+    }
+
+    /// The newest completed checkpoint, if any
+    pub fn latest(&self) -> Option<PathBuf> {
+        list_episodes(&self.root).ok()?.pop().map(|(_, dir)| dir)
+    }
+
+    /// The `best/` checkpoint, if one was kept
+    pub fn best(&self) -> Option<PathBuf> {
+        let best = self.root.join(BEST_DIR);
+        best.is_dir().then_some(best)
+    }
+
+    /// Metric of the `best/` checkpoint, including one kept by an earlier run in `root`
+    pub fn best_metric(&self) -> Option<f32> {
+        self.best_metric
+    }
+
+    fn episode_dir(&self, episode: usize) -> PathBuf {
+        self.root.join(format!("{}{:06}", EPISODE_PREFIX, episode))
+    }
+
+    fn staging_dir(&self, episode: usize) -> PathBuf {
+        self.root
+            .join(format!("{}{:06}.tmp", EPISODE_PREFIX, episode))
+    }
+
+    /// Start a checkpoint for `episode` and return the directory to write it into.
+    /// Finish with [`Checkpointer::commit`].
+    pub fn begin(&mut self, episode: usize) -> io::Result<PathBuf> {
+        let staging = self.staging_dir(episode);
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        fs::create_dir_all(&staging)?;
+        self.pending = Some(episode);
+        Ok(staging)
+    }
+
+    /// Publish the checkpoint started by [`Checkpointer::begin`], prune old ones and update
+    /// `best/` if `metric` beats every earlier checkpoint
+    pub fn commit(&mut self, metric: Option<f32>) -> Result<PathBuf, Box<dyn Error>> {
+        let episode = self.pending.take().ok_or("no checkpoint in progress")?;
+        let staging = self.staging_dir(episode);
+        let dir = self.episode_dir(episode);
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::rename(&staging, &dir)?;
+        self.prune()?;
+
+        if self.policy.keep_best
+            && let Some(metric) = metric
+            && self.best_metric.is_none_or(|best| metric > best)
+        {
+            let best = self.root.join(BEST_DIR);
+            if best.exists() {
+                fs::remove_dir_all(&best)?;
+            }
+            copy_dir(&dir, &best)?;
+            let marker = serde_json::to_string(&BestMarker { episode, metric })?;
+            fs::write(best.join(BEST_MARKER), marker)?;
+            self.best_metric = Some(metric);
+            log::info!(
+                "New best checkpoint (episode {}, metric {:.4})",
+                episode,
+                metric
+            );
+        }
+
+        log::info!("Checkpoint saved to {:?}", dir);
+        Ok(dir)
+    }
+
+    fn prune(&self) -> io::Result<()> {
+        let episodes = list_episodes(&self.root)?;
+        let excess = episodes.len().saturating_sub(self.policy.keep_last.max(1));
+        for (_, dir) in episodes.into_iter().take(excess) {
+            fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
+
+/// Completed `episode_<n>` directories under `root`, oldest first
+fn list_episodes(root: &Path) -> io::Result<Vec<(usize, PathBuf)>> {
+    if !root.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut episodes = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        let episode = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix(EPISODE_PREFIX))
+            .and_then(|n| n.parse::<usize>().ok());
+        if let Some(episode) = episode
+            && path.is_dir()
+        {
+            episodes.push((episode, path));
+        }
+    }
+    episodes.sort();
+    Ok(episodes)
+}
+
+/// The newest rotated checkpoint under `path`, or `path` itself when it holds no rotated
+/// checkpoints (a single checkpoint directory or file, including ones from before rotation)
+pub fn resolve(path: &Path) -> PathBuf {
+    list_episodes(path)
+        .ok()
+        .and_then(|episodes| episodes.into_iter().last())
+        .map(|(_, dir)| dir)
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
@@ -12,6 +12,9 @@ pub mod algorithm_ff_multi1;
 pub mod algorithm_ff_multi2;
 pub mod algorithm_ff_ppo;
 pub mod algorithm_ffsac;
+pub mod checkpoint;
+pub mod realtime_rl;
+pub mod validation;
 
 pub use algorithm_csdp1::Algorithm1;
 pub use algorithm_csdp2::Algorithm2;
@@ -21,8 +24,12 @@ pub use algorithm_ff1::AlgorithmFF1;
 pub use algorithm_ff2::AlgorithmFF2;
 
 use crate::environment::Environment;
-use crate::visualization::VisualizationState;
+use checkpoint::Checkpointer;
+use validation::Validator;
+use crate::visualization::{ModelStructure, VisualizationState};
+use candle_core::Tensor;
 use std::error::Error;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 pub trait Algorithm {
@@ -32,4 +39,55 @@ pub trait Algorithm {
         visualize: bool,
         vis_state: Option<Arc<Mutex<VisualizationState>>>,
     ) -> Result<(), Box<dyn Error>>;
+
+    /// Restore weights from a checkpoint written by this algorithm. Returns the saved
+    /// per-episode reward history, which is empty when the checkpoint doesn't carry one.
+    fn restore(&mut self, _path: &Path) -> Result<Vec<(usize, f32)>, Box<dyn Error>> {
+        Err("this algorithm does not support checkpoints".into())
+    }
+
+    /// Structure of the model for the visualizer, e.g. after `restore` to show a checkpoint
+    /// next to the live model
+    fn visualization_snapshot(&self) -> Result<ModelStructure, Box<dyn Error>> {
+        Err("this algorithm does not support visualization snapshots".into())
+    }
+
+    /// Save the current state to `dir` in a layout `restore` reads, outside the checkpoint
+    /// schedule, e.g. to recover from a failed device
+    fn save_to(&mut self, _dir: &Path) -> Result<(), Box<dyn Error>> {
+        Err("this algorithm does not support saving on demand".into())
+    }
+
+    /// Periodic checkpoint rotation, for algorithms that save during training
+    fn checkpoints_mut(&mut self) -> Option<&mut Checkpointer> {
+        None
+    }
+
+    /// Learned weights and layer parameters under descriptive names, for export
+    fn named_tensors(&self) -> Result<Vec<(String, Tensor)>, Box<dyn Error>> {
+        Err("this algorithm does not support export".into())
+    }
+
+    /// Run `n_episodes` greedily with learning disabled and return the total reward of each
+    fn evaluate(
+        &mut self,
+        _env: &mut dyn Environment,
+        _n_episodes: usize,
+    ) -> Result<Vec<f32>, Box<dyn Error>> {
+        Err("this algorithm does not support evaluation".into())
+    }
+
+    /// Validate on a held-out environment during `run`; the validation reward then decides
+    /// which checkpoint is kept as the best
+    fn set_validator(&mut self, _validator: Validator) -> Result<(), Box<dyn Error>> {
+        Err("this algorithm does not support validation".into())
+    }
+}
+
+/// Prefix every tensor name with `<prefix>.`, for algorithms that export several models
+fn prefixed(prefix: &str, tensors: Vec<(String, Tensor)>) -> Vec<(String, Tensor)> {
+    tensors
+        .into_iter()
+        .map(|(name, tensor)| (format!("{}.{}", prefix, name), tensor))
+        .collect()
 }
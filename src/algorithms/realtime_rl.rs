@@ -0,0 +1,204 @@
+//! Online reinforcement learning from a live reward stream.
+//!
+//! `RealtimeLeader` (in `dataset::realtime_leader`) yields the leader arm's joint positions
+//! together with a reward the operator gives by closing the gripper. [`RealtimeRL`] consumes
+//! that stream: every observation is encoded into spike probabilities, the model picks an
+//! action (with epsilon-greedy exploration), and the reward that arrives with the next
+//! observation gates the plasticity of that observation/action pair through the
+//! reward-modulated modulatory signal of [`RLModel2`], the third factor of the CSDP rule.
+
+use crate::models::rl_model2::RLModel2;
+use candle_core::{Device, Result as CandleResult, Tensor};
+use rand::Rng;
+use std::error::Error;
+use std::f32::consts::PI;
+
+/// Bookkeeping of one episode of [`RealtimeRL::run`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpisodeStats {
+    pub episode: usize,
+    /// Scored actions, i.e. rewards received
+    pub steps: usize,
+    pub total_reward: f32,
+    /// Exploration rate used during the episode
+    pub epsilon: f32,
+}
+
+pub struct RealtimeRL {
+    pub model: RLModel2,
+    pub state_size: usize,
+    pub action_size: usize,
+    pub n_timesteps: usize,
+    pub steps_per_episode: usize,
+    /// Probability of taking a random action instead of the greedy one
+    pub epsilon: f32,
+    /// `epsilon` is multiplied by this after every episode
+    pub epsilon_decay: f32,
+    pub min_epsilon: f32,
+    pub device: Device,
+}
+
+impl RealtimeRL {
+    pub fn new(
+        state_size: usize,
+        action_size: usize,
+        hidden_sizes: Vec<usize>,
+        dt: f32,
+        device: Device,
+    ) -> Result<Self, Box<dyn Error>> {
+        // Input: encoded state followed by a one-hot action; context: the sample label
+        let model = RLModel2::new(state_size + action_size, 1, hidden_sizes, &device, dt, None)?;
+        Ok(Self {
+            model,
+            state_size,
+            action_size,
+            n_timesteps: 20,
+            steps_per_episode: 100,
+            epsilon: 1.0,
+            epsilon_decay: 0.95,
+            min_epsilon: 0.05,
+            device,
+        })
+    }
+
+    /// Joint angles in radians mapped onto [0, 1] spike probabilities
+    fn encode(&self, observation: &Tensor) -> CandleResult<Vec<f32>> {
+        let angles = observation.flatten_all()?.to_vec1::<f32>()?;
+        if angles.len() != self.state_size {
+            return Err(candle_core::Error::Msg(format!(
+                "expected {} observation values, got {}",
+                self.state_size,
+                angles.len()
+            )));
+        }
+        Ok(angles
+            .iter()
+            .map(|a| ((a + PI) / (2.0 * PI)).clamp(0.0, 1.0))
+            .collect())
+    }
+
+    /// (state + action, actions) batch pairing `state` with each of `actions`
+    fn input_batch(&self, state: &[f32], actions: &[usize]) -> CandleResult<Tensor> {
+        let batch = actions.len();
+        let mut data = Vec::with_capacity((self.state_size + self.action_size) * batch);
+        for &s in state {
+            data.extend(std::iter::repeat_n(s, batch));
+        }
+        for a in 0..self.action_size {
+            data.extend(actions.iter().map(|&b| if a == b { 1.0 } else { 0.0 }));
+        }
+        Tensor::from_vec(
+            data,
+            (self.state_size + self.action_size, batch),
+            &self.device,
+        )
+    }
+
+    /// Epsilon-greedy action: greedy picks the action whose input drives the most activity
+    pub fn select_action(&mut self, state: &[f32]) -> CandleResult<usize> {
+        let mut rng = crate::seed::rng();
+        if rng.r#gen::<f32>() < self.epsilon {
+            return Ok(rng.gen_range(0..self.action_size));
+        }
+
+        self.model.disable_learning();
+        let actions: Vec<usize> = (0..self.action_size).collect();
+        let batch = self.input_batch(state, &actions)?;
+        let activity = self
+            .model
+            .process(&batch, self.n_timesteps)?
+            .to_vec2::<f32>()?;
+        Ok(activity[0]
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map_or(0, |(a, _)| a))
+    }
+
+    /// Reward-gated update: a rewarded pair is a positive sample whose update is scaled by the
+    /// reward, an unrewarded one a negative sample
+    pub fn learn(&mut self, state: &[f32], action: usize, reward: f32) -> CandleResult<()> {
+        let input = self.input_batch(state, &[action])?;
+        let label = if reward > 0.0 { 1.0f32 } else { 0.0 };
+        let label_tensor = Tensor::from_vec(vec![label], (1, 1), &self.device)?;
+        let reward_tensor = Tensor::from_vec(vec![reward], (1, 1), &self.device)?;
+        for layer in self.model.layers.iter_mut() {
+            layer.set_positive_sample(&label_tensor);
+        }
+        self.model.set_reward(&reward_tensor);
+
+        self.model.enable_learning();
+        self.model.reset(1)?;
+        for _ in 0..self.n_timesteps {
+            self.model.step(&input, Some(&label_tensor))?;
+        }
+        self.model.disable_learning();
+        Ok(())
+    }
+
+    /// Train on `stream` (e.g. `RealtimeLeader::iter`) for up to `n_episodes` episodes of
+    /// `steps_per_episode` rewards each, calling `act` with every chosen action. The reward
+    /// that comes with an observation scores the action chosen for the previous one. Stops
+    /// early when the stream ends.
+    pub fn run<I>(
+        &mut self,
+        stream: I,
+        n_episodes: usize,
+        mut act: impl FnMut(usize) -> Result<(), Box<dyn Error>>,
+    ) -> Result<Vec<EpisodeStats>, Box<dyn Error>>
+    where
+        I: IntoIterator<Item = CandleResult<(Tensor, Tensor, f32)>>,
+    {
+        let mut stream = stream.into_iter();
+        let mut history = Vec::new();
+
+        for episode in 1..=n_episodes {
+            let mut previous: Option<(Vec<f32>, usize)> = None;
+            let mut steps = 0;
+            let mut total_reward = 0.0;
+            let mut exhausted = true;
+
+            for item in stream.by_ref() {
+                let (observation, _label, reward) = item?;
+                let state = self.encode(&observation)?;
+                if let Some((prev_state, prev_action)) = previous.take() {
+                    self.learn(&prev_state, prev_action, reward)?;
+                    steps += 1;
+                    total_reward += reward;
+                }
+                if steps == self.steps_per_episode {
+                    exhausted = false;
+                    break;
+                }
+
+                let action = self.select_action(&state)?;
+                act(action)?;
+                previous = Some((state, action));
+            }
+
+            if exhausted && steps == 0 {
+                break;
+            }
+            log::info!(
+                "[Episode {}] reward: {:.3} over {} steps (epsilon {:.3})",
+                episode,
+                total_reward,
+                steps,
+                self.epsilon
+            );
+            history.push(EpisodeStats {
+                episode,
+                steps,
+                total_reward,
+                epsilon: self.epsilon,
+            });
+            self.epsilon = (self.epsilon * self.epsilon_decay).max(self.min_epsilon);
+
+            if exhausted {
+                log::info!("Reward stream ended after {} episodes", episode);
+                break;
+            }
+        }
+        Ok(history)
+    }
+}
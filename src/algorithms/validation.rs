@@ -0,0 +1,63 @@
+//! Periodic validation during training.
+//!
+//! Every `every` episodes the algorithm pauses learning and runs `episodes` greedy episodes on
+//! a separate environment instance that it never trains on. The mean reward of those episodes
+//! is logged and, instead of the training reward, decides which checkpoint ends up in `best/`,
+//! so model selection doesn't depend on where training happened to stop.
+
+use crate::environment::Environment;
+use std::error::Error;
+
+pub struct Validator {
+    /// Validate every N episodes; 0 disables validation
+    pub every: usize,
+    /// Greedy episodes per validation
+    pub episodes: usize,
+    env: Box<dyn Environment>,
+    /// (episode, mean validation reward) of every validation so far
+    pub history: Vec<(usize, f32)>,
+}
+
+impl Validator {
+    pub fn new(env: Box<dyn Environment>, every: usize, episodes: usize) -> Self {
+        Self {
+            every,
+            episodes: episodes.max(1),
+            env,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn is_due(&self, episode: usize) -> bool {
+        self.every > 0 && episode > 0 && episode.is_multiple_of(self.every)
+    }
+
+    /// Episode and reward of the best validation so far
+    pub fn best(&self) -> Option<(usize, f32)> {
+        self.history
+            .iter()
+            .copied()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Run `evaluate` (normally [`super::Algorithm::evaluate`]) on the validation environment,
+    /// log and record the mean reward, and return it
+    pub fn run(
+        &mut self,
+        episode: usize,
+        evaluate: impl FnOnce(&mut dyn Environment, usize) -> Result<Vec<f32>, Box<dyn Error>>,
+    ) -> Result<f32, Box<dyn Error>> {
+        let rewards = evaluate(self.env.as_mut(), self.episodes)?;
+        let mean = rewards.iter().sum::<f32>() / rewards.len().max(1) as f32;
+        let is_best = self.best().is_none_or(|(_, best)| mean > best);
+        self.history.push((episode, mean));
+        log::info!(
+            "[Episode {}] Validation reward {:.4} over {} episodes{}",
+            episode,
+            mean,
+            rewards.len(),
+            if is_best { " (best so far)" } else { "" }
+        );
+        Ok(mean)
+    }
+}
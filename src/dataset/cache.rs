@@ -0,0 +1,176 @@
+//! Device-resident dataset cache.
+//!
+//! Static datasets are otherwise encoded to tensors and moved to the device on every pass, and
+//! the input layer redraws its Bernoulli spikes on every step. A [`DatasetCache`] stores all
+//! samples once on the device, as `(features, samples)` columns batches are gathered from.
+//! With [`DatasetCache::with_spike_trains`] it also pre-generates a Bernoulli spike train per
+//! sample, stored as `u8` to keep MNIST-scale data in memory, so the encoding is drawn once
+//! rather than every epoch. [`DatasetCache::resample_spike_trains`] draws fresh trains, e.g.
+//! every few epochs.
+
+use crate::error::Result;
+use crate::models::preflight::{InputEncoding, InputReport, PreflightConfig, check_inputs};
+use candle_core::{DType, Device, Result as CandleResult, Tensor};
+
+pub struct DatasetCache {
+    /// firing probabilities, (features, samples)
+    inputs: Tensor,
+    /// (label size, samples)
+    labels: Tensor,
+    /// pre-generated spikes, (steps, features, samples)
+    spike_trains: Option<Tensor>,
+}
+
+impl DatasetCache {
+    /// Cache `inputs`, `(features, samples)` with values in `[0, 1]`, and their `labels`,
+    /// `(label size, samples)`, on `device`
+    pub fn new(inputs: &Tensor, labels: &Tensor, device: &Device) -> CandleResult<Self> {
+        let (_, n) = inputs.dims2()?;
+        if labels.dims2()?.1 != n {
+            return Err(candle_core::Error::Msg(format!(
+                "{} inputs but {} labels",
+                n,
+                labels.dims()[1]
+            )));
+        }
+        Ok(Self {
+            inputs: inputs.to_dtype(DType::F32)?.to_device(device)?,
+            labels: labels.to_dtype(DType::F32)?.to_device(device)?,
+            spike_trains: None,
+        })
+    }
+
+    /// Cache single samples such as those of [`super::xor::XorDataset`], each a
+    /// `(features, 1)` input and a `(label size, 1)` label
+    pub fn from_samples(
+        inputs: &[Tensor],
+        labels: &[Tensor],
+        device: &Device,
+    ) -> CandleResult<Self> {
+        if inputs.is_empty() || inputs.len() != labels.len() {
+            return Err(candle_core::Error::Msg(format!(
+                "{} inputs but {} labels",
+                inputs.len(),
+                labels.len()
+            )));
+        }
+        let gather = |samples: &[Tensor]| -> CandleResult<Tensor> {
+            let columns = samples
+                .iter()
+                .map(|s| s.to_device(device))
+                .collect::<CandleResult<Vec<_>>>()?;
+            Tensor::cat(&columns, 1)
+        };
+        Self::new(&gather(inputs)?, &gather(labels)?, device)
+    }
+
+    /// Pre-generate a Bernoulli spike train of `steps` steps for every sample
+    pub fn with_spike_trains(mut self, steps: usize) -> CandleResult<Self> {
+        self.spike_trains = Some(self.draw_spike_trains(steps)?);
+        Ok(self)
+    }
+
+    /// Replace the spike trains with freshly drawn ones of the same length
+    pub fn resample_spike_trains(&mut self) -> CandleResult<()> {
+        if let Some(trains) = &self.spike_trains {
+            let steps = trains.dims()[0];
+            self.spike_trains = Some(self.draw_spike_trains(steps)?);
+        }
+        Ok(())
+    }
+
+    fn draw_spike_trains(&self, steps: usize) -> CandleResult<Tensor> {
+        if steps == 0 {
+            return Err(candle_core::Error::Msg(
+                "spike trains need at least one step".to_string(),
+            ));
+        }
+        let probs = self.inputs.clamp(0.0, 1.0)?;
+        let trains = (0..steps)
+            .map(|_| {
+                // 1 where the draw falls below the probability, as in the Bernoulli layer
+                crate::seed::rand_uniform(0.0, 1.0, probs.dims(), probs.device())?.lt(&probs)
+            })
+            .collect::<CandleResult<Vec<_>>>()?;
+        Tensor::stack(&trains, 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.dims()[1]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn device(&self) -> &Device {
+        self.inputs.device()
+    }
+
+    /// Steps of the pre-generated spike trains, if any
+    pub fn spike_train_steps(&self) -> Option<usize> {
+        self.spike_trains.as_ref().map(|t| t.dims()[0])
+    }
+
+    /// Input and label of one sample, `(features, 1)` and `(label size, 1)`
+    pub fn sample(&self, index: usize) -> CandleResult<(Tensor, Tensor)> {
+        Ok((
+            self.inputs.narrow(1, index, 1)?,
+            self.labels.narrow(1, index, 1)?,
+        ))
+    }
+
+    /// Inputs and labels of the samples at `indices`, `(features, batch)` and
+    /// `(label size, batch)`
+    pub fn batch(&self, indices: &[usize]) -> CandleResult<(Tensor, Tensor)> {
+        let indices = self.indices(indices)?;
+        Ok((
+            self.inputs.index_select(&indices, 1)?,
+            self.labels.index_select(&indices, 1)?,
+        ))
+    }
+
+    /// Pre-generated spikes of the samples at `indices`, one `(features, batch)` tensor per
+    /// step. Presenting them with `Schedule::sequence(&train, 1, 0)` replaces the input
+    /// layer's own draws, since a Bernoulli layer passes 0/1 inputs through (up to its
+    /// `1e-4` clamp).
+    pub fn spike_train(&self, indices: &[usize]) -> CandleResult<Vec<Tensor>> {
+        let Some(trains) = &self.spike_trains else {
+            return Err(candle_core::Error::Msg(
+                "the cache holds no spike trains, see DatasetCache::with_spike_trains".to_string(),
+            ));
+        };
+        let batch = trains
+            .index_select(&self.indices(indices)?, 2)?
+            .to_dtype(DType::F32)?;
+        (0..batch.dims()[0]).map(|t| batch.get(t)).collect()
+    }
+
+    /// Memory held on the device, in bytes
+    pub fn memory_bytes(&self) -> usize {
+        [&self.inputs, &self.labels]
+            .into_iter()
+            .chain(self.spike_trains.as_ref())
+            .map(|t| t.elem_count() * t.dtype().size_in_bytes())
+            .sum()
+    }
+
+    /// Check the cached inputs as firing probabilities of a Bernoulli input layer, before
+    /// training on them (see [`crate::models::preflight`])
+    pub fn check_inputs(&self, config: &PreflightConfig) -> Result<InputReport> {
+        check_inputs(&self.inputs, InputEncoding::Rates, config)
+    }
+
+    fn indices(&self, indices: &[usize]) -> CandleResult<Tensor> {
+        if let Some(&index) = indices.iter().find(|&&i| i >= self.len()) {
+            return Err(candle_core::Error::Msg(format!(
+                "sample {} is out of range for {} samples",
+                index,
+                self.len()
+            )));
+        }
+        let n = indices.len();
+        let indices: Vec<u32> = indices.iter().map(|&i| i as u32).collect();
+        Tensor::from_vec(indices, n, self.device())
+    }
+}
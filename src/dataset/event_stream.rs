@@ -0,0 +1,375 @@
+//! Live input from an event camera (DVS/DAVIS).
+//!
+//! Event cameras report per-pixel brightness changes as they happen instead of frames. An
+//! [`EventInput`] drains whatever events arrived since the last timestep from an
+//! [`EventSource`] and turns them into the input of one `Model::step`: every input neuron
+//! stands for a (pooled) pixel and polarity and gets 1 if it saw at least `threshold` events,
+//! else 0. The Bernoulli input layer fires deterministically on 0 and 1, so events become
+//! input spikes directly, one timestep after they arrive.
+//!
+//! Events are read in the AEDAT 2.0 format: big-endian pairs of a 32-bit address and a 32-bit
+//! timestamp in microseconds. [`UdpEventSource`] receives them as UDP datagrams, as streamed by
+//! jAER; [`StreamEventSource`] reads them from any byte stream, such as a TCP connection, the
+//! output of a USB camera driver piped through stdin, or a recording.
+
+use crate::error::{CsdpError, Result};
+use candle_core::{Device, Tensor};
+use std::io::{ErrorKind, Read};
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+/// Bytes of one AEDAT 2.0 event
+pub const EVENT_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DvsEvent {
+    pub x: u16,
+    pub y: u16,
+    /// brightness increase (ON) or decrease (OFF)
+    pub on: bool,
+    pub timestamp_us: u32,
+}
+
+/// Sensor that produced the events, which fixes the address layout and resolution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sensor {
+    /// 128x128; polarity in bit 0, x in bits 1-7, y in bits 8-14, bit 15 marks external events
+    Dvs128,
+    /// DAVIS family; polarity in bit 11, x in bits 12-21, y in bits 22-30, bit 31 marks frame
+    /// and IMU samples
+    Davis { width: u16, height: u16 },
+}
+
+impl Sensor {
+    /// DAVIS240 (240x180)
+    pub fn davis240() -> Self {
+        Sensor::Davis {
+            width: 240,
+            height: 180,
+        }
+    }
+
+    /// DAVIS346 (346x260)
+    pub fn davis346() -> Self {
+        Sensor::Davis {
+            width: 346,
+            height: 260,
+        }
+    }
+
+    pub fn resolution(&self) -> (usize, usize) {
+        match *self {
+            Sensor::Dvs128 => (128, 128),
+            Sensor::Davis { width, height } => (width as usize, height as usize),
+        }
+    }
+
+    /// The pixel event at `address`, or `None` for other events and pixels off the sensor
+    pub fn decode(&self, address: u32, timestamp_us: u32) -> Option<DvsEvent> {
+        let (x, y, on) = match self {
+            Sensor::Dvs128 => {
+                if address & 0x8000 != 0 {
+                    return None;
+                }
+                (
+                    (address >> 1) & 0x7f,
+                    (address >> 8) & 0x7f,
+                    address & 1 == 1,
+                )
+            }
+            Sensor::Davis { .. } => {
+                if address & 0x8000_0000 != 0 {
+                    return None;
+                }
+                (
+                    (address >> 12) & 0x3ff,
+                    (address >> 22) & 0x1ff,
+                    (address >> 11) & 1 == 1,
+                )
+            }
+        };
+        let (width, height) = self.resolution();
+        if x as usize >= width || y as usize >= height {
+            return None;
+        }
+        Some(DvsEvent {
+            x: x as u16,
+            y: y as u16,
+            on,
+            timestamp_us,
+        })
+    }
+
+    /// Decode AEDAT 2.0 events from `bytes`, appending them to `events`; a trailing partial
+    /// event is ignored
+    pub fn parse(&self, bytes: &[u8], events: &mut Vec<DvsEvent>) {
+        for chunk in bytes.chunks_exact(EVENT_SIZE) {
+            let address = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            let timestamp = u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+            events.extend(self.decode(address, timestamp));
+        }
+    }
+}
+
+/// Where live events come from
+pub trait EventSource: Send {
+    /// Append the events that arrived since the last call to `events`, without blocking
+    fn poll(&mut self, events: &mut Vec<DvsEvent>) -> Result<()>;
+}
+
+/// Events received as UDP datagrams of AEDAT 2.0 events
+pub struct UdpEventSource {
+    socket: UdpSocket,
+    sensor: Sensor,
+    /// whether every datagram starts with a 4-byte sequence number (jAER's default)
+    sequence_numbers: bool,
+    buffer: Vec<u8>,
+    /// sequence number expected next, to count lost datagrams
+    next_sequence: Option<u32>,
+    dropped: u64,
+}
+
+impl UdpEventSource {
+    pub fn bind(address: impl ToSocketAddrs, sensor: Sensor) -> Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            sensor,
+            sequence_numbers: true,
+            buffer: vec![0; 65536],
+            next_sequence: None,
+            dropped: 0,
+        })
+    }
+
+    /// For senders whose datagrams hold only events
+    pub fn without_sequence_numbers(mut self) -> Self {
+        self.sequence_numbers = false;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<std::net::SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Datagrams lost so far, from gaps in the sequence numbers
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl EventSource for UdpEventSource {
+    fn poll(&mut self, events: &mut Vec<DvsEvent>) -> Result<()> {
+        loop {
+            let len = match self.socket.recv(&mut self.buffer) {
+                Ok(len) => len,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e.into()),
+            };
+            let mut payload = &self.buffer[..len];
+            if self.sequence_numbers {
+                let Some((header, rest)) = payload.split_first_chunk::<4>() else {
+                    continue;
+                };
+                let sequence = u32::from_be_bytes(*header);
+                // A datagram from behind the expected one arrived out of order, not after a gap
+                let gap = self.next_sequence.map_or(0, |n| sequence.wrapping_sub(n));
+                if gap < u32::MAX / 2 {
+                    self.dropped += gap as u64;
+                    self.next_sequence = Some(sequence.wrapping_add(1));
+                }
+                payload = rest;
+            }
+            self.sensor.parse(payload, events);
+        }
+    }
+}
+
+/// Events read from a byte stream of AEDAT 2.0 events by a background thread
+pub struct StreamEventSource {
+    events: Receiver<Vec<DvsEvent>>,
+    finished: bool,
+}
+
+impl StreamEventSource {
+    /// Read `reader` on a background thread until it ends; an AEDAT file's `#` header lines
+    /// must already have been skipped
+    pub fn spawn(mut reader: impl Read + Send + 'static, sensor: Sensor) -> Self {
+        let (sender, events) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut buffer = vec![0u8; 4096 * EVENT_SIZE];
+            // bytes of an event split across reads
+            let mut carried = 0;
+            loop {
+                let len = match reader.read(&mut buffer[carried..]) {
+                    Ok(0) => break,
+                    Ok(len) => carried + len,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(e) => {
+                        log::warn!("event stream failed: {}", e);
+                        break;
+                    }
+                };
+                let whole = len - len % EVENT_SIZE;
+                let mut batch = Vec::with_capacity(whole / EVENT_SIZE);
+                sensor.parse(&buffer[..whole], &mut batch);
+                buffer.copy_within(whole..len, 0);
+                carried = len - whole;
+                if sender.send(batch).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            events,
+            finished: false,
+        }
+    }
+
+    /// Whether the stream has ended and every event read from it was polled
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+impl EventSource for StreamEventSource {
+    fn poll(&mut self, events: &mut Vec<DvsEvent>) -> Result<()> {
+        loop {
+            match self.events.try_recv() {
+                Ok(batch) => events.extend(batch),
+                Err(TryRecvError::Empty) => return Ok(()),
+                Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// How events map onto input neurons
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EventInputConfig {
+    pub sensor: Sensor,
+    /// side of the square of pixels pooled into one input neuron; 1 keeps every pixel
+    pub pool: usize,
+    /// separate ON and OFF neurons per pooled pixel instead of one for both
+    pub split_polarity: bool,
+    /// events a neuron needs within a timestep to spike
+    pub threshold: u32,
+}
+
+impl EventInputConfig {
+    pub fn new(sensor: Sensor) -> Self {
+        Self {
+            sensor,
+            pool: 1,
+            split_polarity: true,
+            threshold: 1,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.pool == 0 || self.threshold == 0 {
+            return Err(CsdpError::Config(format!(
+                "event input needs a pool size and a threshold of at least 1, got {} and {}",
+                self.pool, self.threshold
+            )));
+        }
+        Ok(())
+    }
+
+    /// Pooled (width, height)
+    pub fn grid(&self) -> (usize, usize) {
+        let (width, height) = self.sensor.resolution();
+        (width.div_ceil(self.pool), height.div_ceil(self.pool))
+    }
+
+    /// Input neurons, in the order polarity (OFF then ON, if split), row, column
+    pub fn input_size(&self) -> usize {
+        let (width, height) = self.grid();
+        let channels = if self.split_polarity { 2 } else { 1 };
+        channels * width * height
+    }
+
+    /// Input neuron of `event`
+    pub fn neuron(&self, event: &DvsEvent) -> usize {
+        let (width, height) = self.grid();
+        let x = event.x as usize / self.pool;
+        let y = event.y as usize / self.pool;
+        let channel = if self.split_polarity && event.on {
+            1
+        } else {
+            0
+        };
+        (channel * height + y) * width + x
+    }
+
+    /// Input spikes for the events of one timestep, 1 for every neuron with at least
+    /// `threshold` events
+    pub fn spikes(&self, events: &[DvsEvent]) -> Vec<f32> {
+        let mut counts = vec![0u32; self.input_size()];
+        for event in events {
+            counts[self.neuron(event)] += 1;
+        }
+        counts
+            .into_iter()
+            .map(|c| if c >= self.threshold { 1.0 } else { 0.0 })
+            .collect()
+    }
+}
+
+/// Turns a live event source into one model input per timestep
+pub struct EventInput {
+    source: Box<dyn EventSource>,
+    pub config: EventInputConfig,
+    device: Device,
+    events: Vec<DvsEvent>,
+    /// events turned into input so far
+    total_events: u64,
+}
+
+impl EventInput {
+    pub fn new(
+        source: Box<dyn EventSource>,
+        config: EventInputConfig,
+        device: &Device,
+    ) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            source,
+            config,
+            device: device.clone(),
+            events: Vec::new(),
+            total_events: 0,
+        })
+    }
+
+    pub fn input_size(&self) -> usize {
+        self.config.input_size()
+    }
+
+    pub fn total_events(&self) -> u64 {
+        self.total_events
+    }
+
+    /// Input of the next timestep, (input_size, 1), from the events that arrived since the
+    /// previous one
+    pub fn next_input(&mut self) -> Result<Tensor> {
+        self.events.clear();
+        self.source.poll(&mut self.events)?;
+        self.total_events += self.events.len() as u64;
+        let spikes = self.config.spikes(&self.events);
+        Ok(Tensor::from_vec(
+            spikes,
+            (self.input_size(), 1),
+            &self.device,
+        )?)
+    }
+
+    /// Events of the last timestep
+    pub fn last_events(&self) -> &[DvsEvent] {
+        &self.events
+    }
+}
@@ -1,3 +1,9 @@
 pub mod andor;
+pub mod cache;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod event_stream;
+#[cfg(all(feature = "robot", not(target_arch = "wasm32")))]
 pub mod realtime_leader;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod trajectory;
 pub mod xor;
@@ -0,0 +1,414 @@
+//! Recorded trajectories as CSV tables.
+//!
+//! A trajectory is a header row followed by one row per frame: an optional `timestamp_ms`
+//! column and one column per channel. Robot recordings (`record`) use this layout with the six
+//! joints `j1`..`j6` as channels, but any numeric CSV dataset loads the same way.
+
+use crate::error::{CsdpError, Result};
+use crate::repro::{FNV_OFFSET, fnv1a};
+use std::path::Path;
+
+/// Name of the timestamp column in robot recordings
+pub const TIME_COLUMN: &str = "timestamp_ms";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectory {
+    /// Channel names in column order, without the timestamp column
+    pub channels: Vec<String>,
+    /// Frame timestamps in milliseconds, if the file has a `timestamp_ms` column
+    pub timestamps_ms: Option<Vec<u64>>,
+    /// `[frame][channel]` values
+    pub frames: Vec<Vec<f64>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChannelStats {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std: f64,
+}
+
+/// Irregularities in a trajectory's timestamps
+#[derive(Debug, Clone, Default)]
+pub struct TimingReport {
+    pub median_interval_ms: f64,
+    /// `(frame, interval_ms)` for frames that arrive more than `gap_factor` median intervals
+    /// after the previous one
+    pub gaps: Vec<(usize, u64)>,
+    /// Frames whose timestamp does not advance past the previous frame's
+    pub out_of_order: Vec<usize>,
+}
+
+impl Trajectory {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader = csv::Reader::from_path(path)?;
+        let headers = reader.headers()?.clone();
+        let time_index = headers.iter().position(|h| h.trim() == TIME_COLUMN);
+        let channels = headers
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| Some(i) != time_index)
+            .map(|(_, h)| h.trim().to_string())
+            .collect();
+
+        let mut trajectory = Trajectory {
+            channels,
+            timestamps_ms: time_index.map(|_| Vec::new()),
+            frames: Vec::new(),
+        };
+        for (row, record) in reader.records().enumerate() {
+            let record = record?;
+            let mut values = Vec::with_capacity(record.len());
+            for (i, field) in record.iter().enumerate() {
+                let value = field.trim().parse::<f64>().map_err(|e| {
+                    CsdpError::Data(format!(
+                        "{}: row {}, column '{}': {}",
+                        path.display(),
+                        row + 1,
+                        &headers[i],
+                        e
+                    ))
+                })?;
+                if Some(i) == time_index {
+                    if let Some(timestamps) = trajectory.timestamps_ms.as_mut() {
+                        timestamps.push(value as u64);
+                    }
+                } else {
+                    values.push(value);
+                }
+            }
+            trajectory.frames.push(values);
+        }
+        Ok(trajectory)
+    }
+
+    /// Write the trajectory back in the layout it was loaded from
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = csv::Writer::from_path(path)?;
+        let mut header: Vec<&str> = Vec::with_capacity(self.channels.len() + 1);
+        if self.timestamps_ms.is_some() {
+            header.push(TIME_COLUMN);
+        }
+        header.extend(self.channels.iter().map(String::as_str));
+        writer.write_record(&header)?;
+
+        for (i, frame) in self.frames.iter().enumerate() {
+            let mut row: Vec<String> = Vec::with_capacity(frame.len() + 1);
+            if let Some(timestamps) = &self.timestamps_ms {
+                row.push(timestamps[i].to_string());
+            }
+            row.extend(frame.iter().map(|v| v.to_string()));
+            writer.write_record(&row)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Values of one channel over time
+    pub fn channel(&self, index: usize) -> impl Iterator<Item = f64> + '_ {
+        self.frames.iter().map(move |frame| frame[index])
+    }
+
+    /// Time from the first to the last frame
+    pub fn duration_ms(&self) -> Option<u64> {
+        let timestamps = self.timestamps_ms.as_ref()?;
+        Some(timestamps.last()?.saturating_sub(*timestamps.first()?))
+    }
+
+    /// Hash of the channel names, the timestamps relative to the first frame and the exact
+    /// values, so a copy of a recording hashes the same even after it was shifted in time
+    pub fn content_hash(&self) -> u64 {
+        let mut hash = FNV_OFFSET;
+        for name in &self.channels {
+            hash = fnv1a(hash, name.as_bytes());
+            hash = fnv1a(hash, &[0]);
+        }
+        if let Some(timestamps) = &self.timestamps_ms {
+            let start = timestamps.first().copied().unwrap_or(0);
+            for &t in timestamps {
+                hash = fnv1a(hash, &t.saturating_sub(start).to_le_bytes());
+            }
+        }
+        for frame in &self.frames {
+            for value in frame {
+                hash = fnv1a(hash, &value.to_bits().to_le_bytes());
+            }
+        }
+        hash
+    }
+
+    pub fn channel_stats(&self) -> Vec<ChannelStats> {
+        let n = self.len().max(1) as f64;
+        self.channels
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let (mut min, mut max, mut sum) = (f64::INFINITY, f64::NEG_INFINITY, 0.0);
+                for v in self.channel(i) {
+                    min = min.min(v);
+                    max = max.max(v);
+                    sum += v;
+                }
+                let mean = sum / n;
+                let var = self.channel(i).map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+                ChannelStats {
+                    name: name.clone(),
+                    min,
+                    max,
+                    mean,
+                    std: var.sqrt(),
+                }
+            })
+            .collect()
+    }
+
+    /// Find gaps and out-of-order frames; `None` without timestamps or with fewer than two
+    /// frames
+    pub fn timing(&self, gap_factor: f64) -> Option<TimingReport> {
+        let timestamps = self.timestamps_ms.as_ref()?;
+        if timestamps.len() < 2 {
+            return None;
+        }
+        let mut intervals: Vec<u64> = timestamps
+            .windows(2)
+            .map(|w| w[1].saturating_sub(w[0]))
+            .collect();
+        let mut report = TimingReport::default();
+        for (i, w) in timestamps.windows(2).enumerate() {
+            if w[1] <= w[0] {
+                report.out_of_order.push(i + 1);
+            }
+        }
+
+        intervals.sort_unstable();
+        let mid = intervals.len() / 2;
+        report.median_interval_ms = if intervals.len().is_multiple_of(2) {
+            (intervals[mid - 1] + intervals[mid]) as f64 / 2.0
+        } else {
+            intervals[mid] as f64
+        };
+
+        let limit = report.median_interval_ms * gap_factor;
+        for (i, w) in timestamps.windows(2).enumerate() {
+            let interval = w[1].saturating_sub(w[0]);
+            if interval as f64 > limit {
+                report.gaps.push((i + 1, interval));
+            }
+        }
+        Some(report)
+    }
+}
+
+/// Editing operations. They all return a new trajectory, rebased to start at `0 ms` like a
+/// fresh recording.
+impl Trajectory {
+    fn require_timestamps(&self, operation: &str) -> Result<&[u64]> {
+        let timestamps = self.timestamps_ms.as_deref().ok_or_else(|| {
+            CsdpError::Data(format!("{} needs a `{}` column", operation, TIME_COLUMN))
+        })?;
+        if timestamps.windows(2).any(|w| w[1] <= w[0]) {
+            return Err(CsdpError::Data(format!(
+                "{} needs strictly increasing timestamps",
+                operation
+            )));
+        }
+        Ok(timestamps)
+    }
+
+    /// Frames `range`, with timestamps shifted so the first one is 0
+    fn slice(&self, range: std::ops::Range<usize>) -> Trajectory {
+        let timestamps_ms = self.timestamps_ms.as_ref().map(|timestamps| {
+            let start = timestamps.get(range.start).copied().unwrap_or(0);
+            timestamps[range.clone()]
+                .iter()
+                .map(|t| t.saturating_sub(start))
+                .collect()
+        });
+        Trajectory {
+            channels: self.channels.clone(),
+            timestamps_ms,
+            frames: self.frames[range].to_vec(),
+        }
+    }
+
+    /// Whether any channel of frame `b` is more than `threshold` away from frame `a`
+    fn moved(&self, a: usize, b: usize, threshold: f64) -> bool {
+        self.frames[a]
+            .iter()
+            .zip(&self.frames[b])
+            .any(|(x, y)| (x - y).abs() > threshold)
+    }
+
+    /// Append trajectories with the same channels. Each part starts one median frame
+    /// interval after the previous part's last frame.
+    pub fn concat(parts: &[Trajectory]) -> Result<Trajectory> {
+        let first = parts
+            .first()
+            .ok_or_else(|| CsdpError::Data("nothing to concatenate".to_string()))?;
+        let mut out = first.slice(0..first.len());
+        for part in &parts[1..] {
+            if part.channels != out.channels {
+                return Err(CsdpError::Data(format!(
+                    "cannot concatenate channels {:?} with {:?}",
+                    part.channels, out.channels
+                )));
+            }
+            match (out.timestamps_ms.as_mut(), part.timestamps_ms.as_ref()) {
+                (Some(timestamps), Some(next)) => {
+                    let interval = part
+                        .timing(f64::INFINITY)
+                        .map(|t| t.median_interval_ms.round() as u64)
+                        .unwrap_or(0);
+                    let offset = timestamps.last().map_or(0, |t| t + interval);
+                    let start = next.first().copied().unwrap_or(0);
+                    timestamps.extend(next.iter().map(|t| t.saturating_sub(start) + offset));
+                }
+                (None, None) => {}
+                _ => {
+                    return Err(CsdpError::Data(
+                        "cannot concatenate trajectories with and without timestamps".to_string(),
+                    ));
+                }
+            }
+            out.frames.extend(part.frames.iter().cloned());
+        }
+        Ok(out)
+    }
+
+    /// Drop the frames at the start and end where no channel moves more than `threshold`
+    /// from the first (or last) frame, keeping one resting frame on each side
+    pub fn trim_still(&self, threshold: f64) -> Trajectory {
+        let n = self.len();
+        if n == 0 {
+            return self.clone();
+        }
+        let Some(first_move) = (1..n).find(|&i| self.moved(0, i, threshold)) else {
+            return self.slice(0..1);
+        };
+        let last_move = (0..n - 1)
+            .rev()
+            .find(|&i| self.moved(n - 1, i, threshold))
+            .unwrap_or(n - 1);
+        self.slice(first_move - 1..(last_move + 2).min(n))
+    }
+
+    /// Linearly interpolate every channel onto a fixed `rate_hz` grid
+    pub fn resample(&self, rate_hz: f64) -> Result<Trajectory> {
+        if rate_hz <= 0.0 {
+            return Err(CsdpError::Data(
+                "resample rate must be positive".to_string(),
+            ));
+        }
+        let timestamps = self.require_timestamps("resampling")?;
+        let (Some(&start), Some(&end)) = (timestamps.first(), timestamps.last()) else {
+            return Ok(self.clone());
+        };
+
+        let period_ms = 1000.0 / rate_hz;
+        let steps = ((end - start) as f64 / period_ms).floor() as usize;
+        let mut out = Trajectory {
+            channels: self.channels.clone(),
+            timestamps_ms: Some(Vec::with_capacity(steps + 1)),
+            frames: Vec::with_capacity(steps + 1),
+        };
+        let mut j = 0;
+        for k in 0..=steps {
+            let t = start as f64 + k as f64 * period_ms;
+            while j + 2 < timestamps.len() && (timestamps[j + 1] as f64) < t {
+                j += 1;
+            }
+            let frame = if j + 1 < timestamps.len() {
+                let (t0, t1) = (timestamps[j] as f64, timestamps[j + 1] as f64);
+                let w = ((t - t0) / (t1 - t0)).clamp(0.0, 1.0);
+                self.frames[j]
+                    .iter()
+                    .zip(&self.frames[j + 1])
+                    .map(|(a, b)| a + (b - a) * w)
+                    .collect()
+            } else {
+                self.frames[j].clone()
+            };
+            if let Some(timestamps) = out.timestamps_ms.as_mut() {
+                timestamps.push((t - start as f64).round() as u64);
+            }
+            out.frames.push(frame);
+        }
+        Ok(out)
+    }
+
+    /// First-order low-pass filter with the given cutoff, run forwards and backwards so the
+    /// smoothed trajectory is not delayed
+    pub fn low_pass(&self, cutoff_hz: f64) -> Result<Trajectory> {
+        if cutoff_hz <= 0.0 {
+            return Err(CsdpError::Data(
+                "cutoff frequency must be positive".to_string(),
+            ));
+        }
+        let timestamps = self.require_timestamps("low-pass filtering")?;
+        let rc = 1.0 / (2.0 * std::f64::consts::PI * cutoff_hz);
+        let alpha = |i: usize, j: usize| {
+            let dt = timestamps[i].abs_diff(timestamps[j]) as f64 / 1000.0;
+            dt / (rc + dt)
+        };
+
+        let mut frames = self.frames.clone();
+        for i in 1..frames.len() {
+            let a = alpha(i, i - 1);
+            for c in 0..frames[i].len() {
+                frames[i][c] = frames[i - 1][c] + a * (frames[i][c] - frames[i - 1][c]);
+            }
+        }
+        for i in (0..frames.len().saturating_sub(1)).rev() {
+            let a = alpha(i, i + 1);
+            for c in 0..frames[i].len() {
+                frames[i][c] = frames[i + 1][c] + a * (frames[i][c] - frames[i + 1][c]);
+            }
+        }
+        let filtered = Trajectory {
+            channels: self.channels.clone(),
+            timestamps_ms: self.timestamps_ms.clone(),
+            frames,
+        };
+        Ok(filtered.slice(0..filtered.len()))
+    }
+
+    /// Split into episodes wherever the arm rests (no channel moves more than `threshold`) or
+    /// the recording has a gap, for at least `min_pause_ms`. Rest at the start and end of
+    /// every episode is trimmed and episodes without motion are dropped.
+    pub fn split_at_pauses(&self, threshold: f64, min_pause_ms: u64) -> Result<Vec<Trajectory>> {
+        let timestamps = self.require_timestamps("splitting")?;
+        let mut bounds = Vec::new();
+        let (mut segment_start, mut anchor) = (0, 0);
+        for i in 1..self.len() {
+            if timestamps[i] - timestamps[i - 1] >= min_pause_ms {
+                bounds.push(segment_start..i);
+                segment_start = i;
+                anchor = i;
+            } else if self.moved(anchor, i, threshold) {
+                if timestamps[i - 1] - timestamps[anchor] >= min_pause_ms {
+                    bounds.push(segment_start..anchor + 1);
+                    segment_start = i - 1;
+                }
+                anchor = i;
+            }
+        }
+        bounds.push(segment_start..self.len());
+
+        Ok(bounds
+            .into_iter()
+            .map(|range| self.slice(range).trim_still(threshold))
+            .filter(|episode| episode.len() > 1)
+            .collect())
+    }
+}
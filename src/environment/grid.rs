@@ -18,7 +18,7 @@ impl Default for GridEnvironment {
 
 impl GridEnvironment {
     pub fn new() -> Self {
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::seed::rng();
         Self {
             player_x: rng.gen_range(0..50),
             player_y: rng.gen_range(0..50),
@@ -98,8 +98,13 @@ impl Environment for GridEnvironment {
         Ok(())
     }
 
+    /// The episode ends on the goal; the first two state values are the offset to it
+    fn is_done(&self, state: &[f64]) -> bool {
+        state.len() >= 2 && state[0] == 0.0 && state[1] == 0.0
+    }
+
     fn reset(&mut self) -> Result<(), Box<dyn Error>> {
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::seed::rng();
         self.player_x = rng.gen_range(0..50);
         self.player_y = rng.gen_range(0..50);
         self.goal_x = rng.gen_range(0..50);
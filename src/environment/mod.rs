@@ -1,9 +1,24 @@
 pub mod grid;
+#[cfg(feature = "robot")]
 pub mod robot;
 pub mod rocketsim;
+pub mod shaped;
 
+use crate::visualization::RobotVisInfo;
 use std::error::Error;
 
+/// Outcome of [`Environment::step`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub observation: Vec<f64>,
+    pub reward: f64,
+    /// The episode has ended; call [`Environment::start_episode`] before stepping again
+    pub done: bool,
+}
+
+/// A task an agent interacts with. Besides the primitive getters below, it offers a
+/// Gym-style loop of [`Environment::start_episode`] and [`Environment::step`], so RL and
+/// imitation loops written against it run unchanged on simulators and the real arm.
 pub trait Environment {
     fn state_size(&self) -> usize;
     fn action_size(&self) -> usize;
@@ -23,4 +38,35 @@ pub trait Environment {
 
     /// Reset the environment to its initial state
     fn reset(&mut self) -> Result<(), Box<dyn Error>>;
+
+    /// Whether an episode ends in `state`; episodes never end on their own by default
+    fn is_done(&self, _state: &[f64]) -> bool {
+        false
+    }
+
+    /// Gym-style reset: start a new episode and return its first observation
+    fn start_episode(&mut self) -> Result<Vec<f64>, Box<dyn Error>> {
+        self.reset()?;
+        self.get_state()
+    }
+
+    /// Gym-style step: apply `action_idx` and return the next observation, the reward of the
+    /// action and whether the episode ended
+    fn step(&mut self, action_idx: usize) -> Result<Step, Box<dyn Error>> {
+        let state = self.get_state()?;
+        let reward = self.evaluate_action(&state, action_idx);
+        self.apply_action(action_idx)?;
+        let observation = self.get_state()?;
+        let done = self.is_done(&observation);
+        Ok(Step {
+            observation,
+            reward,
+            done,
+        })
+    }
+
+    /// Live status of any physical arms driven by this environment (empty for simulators)
+    fn robot_status(&mut self) -> Vec<RobotVisInfo> {
+        Vec::new()
+    }
 }
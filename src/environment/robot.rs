@@ -1,33 +1,110 @@
 use super::Environment;
-use crate::robot::real_lerobot::LeRobot;
+use crate::models::curriculum::ActionCurriculum;
+use crate::robot::Arm;
+use crate::robot::profile::RobotProfile;
+use crate::robot::real_lerobot::RobotResult;
+use crate::robot::sim_lerobot::SimLeRobot;
+use crate::visualization::RobotVisInfo;
 use std::error::Error;
+use std::time::{Duration, Instant};
 
 const NUM_ACTIONS: usize = 12;
-const ACTION_DELTA: f64 = 0.05; // radians
+/// Default joint move of an action, in radians
+const ACTION_DELTA: f64 = 0.05;
 const NUM_JOINTS: usize = 6;
 const TARGET_POSITION: [f64; NUM_JOINTS] = [0.0, -1.0, 1.0, 0.5, 0.0, 0.5];
+/// The episode ends once every joint is within about this many radians of the target
+const DONE_TOLERANCE: f64 = 0.05;
+/// `robot_status` reads goals and loads off the bus at most this often
+const STATUS_INTERVAL: Duration = Duration::from_millis(500);
 
+/// Reach `TARGET_POSITION` with the follower arm, real or simulated
 pub struct RobotEnvironment {
-    follower: LeRobot,
+    follower: Box<dyn Arm>,
     target_position: [f64; NUM_JOINTS],
+    /// joint move of an action, in radians
+    action_delta: f64,
+    /// refines `action_delta` at every reset, see [`RobotEnvironment::with_curriculum`]
+    curriculum: Option<ActionCurriculum>,
+    /// an episode was started since the arm was connected, so a reset ends one
+    in_episode: bool,
+    /// joint positions of the newest `get_state`, shown by `robot_status` without a read
+    last_positions: Vec<f64>,
+    /// goals and loads `robot_status` last read, and when
+    status_reads: Option<(Instant, Vec<f64>, Vec<f64>)>,
 }
 
 impl RobotEnvironment {
     pub fn new() -> Result<Self, Box<dyn Error>> {
-        let mut follower = LeRobot::new(
-            "/dev/ttyACM0",
-            [-0.0276, -1.6, 1.29, 1.1, 0.254, 0.0],
-            [-1.3, -1.6, -1.94, -2.0, -1.5, -0.0122],
-            [1.0, 1.7, 1.29, 1.2, 1.5, 1.1],
-        )?;
+        Self::with_profile(&RobotProfile::follower())
+    }
 
-        follower.enable()?;
+    pub fn with_profile(profile: &RobotProfile) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::from_arm(Box::new(profile.connect()?))?)
+    }
 
+    /// Same task on any [`Arm`], e.g. one wrapped in an
+    /// [`EnvelopedArm`](crate::robot::envelope::EnvelopedArm)
+    pub fn from_arm(mut follower: Box<dyn Arm>) -> RobotResult<Self> {
+        follower.enable()?;
         Ok(Self {
             follower,
             target_position: TARGET_POSITION,
+            action_delta: ACTION_DELTA,
+            curriculum: None,
+            in_episode: false,
+            last_positions: Vec::new(),
+            status_reads: None,
         })
     }
+
+    /// Same task on a [`SimLeRobot`] with the profile's limits, so training loops can be
+    /// tried without hardware
+    pub fn simulated(profile: &RobotProfile) -> Self {
+        let mut follower = SimLeRobot::from_profile(profile);
+        // Enabling the simulated arm cannot fail
+        let _ = follower.enable();
+        Self {
+            follower: Box::new(follower),
+            target_position: TARGET_POSITION,
+            action_delta: ACTION_DELTA,
+            curriculum: None,
+            in_episode: false,
+            last_positions: Vec::new(),
+            status_reads: None,
+        }
+    }
+
+    /// Start with the curriculum's coarse actions and refine them at every reset as the
+    /// distance to the target at the end of the episodes comes down
+    pub fn with_curriculum(mut self, curriculum: ActionCurriculum) -> Self {
+        self.action_delta = curriculum.step_size();
+        self.curriculum = Some(curriculum);
+        self
+    }
+
+    pub fn curriculum(&self) -> Option<&ActionCurriculum> {
+        self.curriculum.as_ref()
+    }
+
+    /// Joint move of an action, in radians
+    pub fn action_delta(&self) -> f64 {
+        self.action_delta
+    }
+
+    pub fn set_action_delta(&mut self, delta: f64) {
+        self.action_delta = delta;
+    }
+
+    /// Root mean square distance of the joints in `state` from the target, in radians
+    pub fn tracking_error(&self, state: &[f64]) -> f64 {
+        let dist_sq: f64 = state
+            .iter()
+            .zip(self.target_position)
+            .map(|(s, t)| (s - t).powi(2))
+            .sum();
+        (dist_sq / NUM_JOINTS as f64).sqrt()
+    }
 }
 
 impl Drop for RobotEnvironment {
@@ -45,6 +122,17 @@ impl Environment for RobotEnvironment {
     }
 
     fn clone_box(&self) -> Box<dyn Environment> {
+        if let Some(follower) = self.follower.try_clone() {
+            return Box::new(Self {
+                follower,
+                target_position: self.target_position,
+                action_delta: self.action_delta,
+                curriculum: self.curriculum.clone(),
+                in_episode: self.in_episode,
+                last_positions: Vec::new(),
+                status_reads: None,
+            });
+        }
         panic!(
             "RobotEnvironment cannot be cloned securely across processes/vectors! Disable vectorization or utilize Simulator proxies."
         );
@@ -52,14 +140,15 @@ impl Environment for RobotEnvironment {
 
     fn get_state(&mut self) -> Result<Vec<f64>, Box<dyn Error>> {
         let pos = self.follower.get_motor_positions()?;
-        Ok(pos.to_vec())
+        self.last_positions = pos.clone();
+        Ok(pos)
     }
 
     fn evaluate_action(&self, state: &[f64], action_idx: usize) -> f64 {
         let mut next_state = state.to_vec();
         let joint_idx = action_idx % NUM_JOINTS;
         let sign = if action_idx < NUM_JOINTS { 1.0 } else { -1.0 };
-        next_state[joint_idx] += sign * ACTION_DELTA;
+        next_state[joint_idx] += sign * self.action_delta;
 
         let mut dist_sq = 0.0;
         for (i, &val) in next_state.iter().enumerate().take(NUM_JOINTS) {
@@ -75,13 +164,57 @@ impl Environment for RobotEnvironment {
         let mut next_state = current_state.clone();
         let joint_idx = action_idx % NUM_JOINTS;
         let sign = if action_idx < NUM_JOINTS { 1.0 } else { -1.0 };
-        next_state[joint_idx] += sign * ACTION_DELTA;
+        next_state[joint_idx] += sign * self.action_delta;
         self.follower.set_goal_positions(&next_state)?;
         Ok(())
     }
 
     fn reset(&mut self) -> Result<(), Box<dyn Error>> {
+        // The pose the last episode ended in tells the curriculum how well the arm tracks
+        if self.in_episode && self.curriculum.is_some() {
+            let error = self.tracking_error(&self.follower.get_motor_positions()?);
+            if let Some(curriculum) = self.curriculum.as_mut()
+                && let Some(step) = curriculum.observe(error)
+            {
+                self.action_delta = step;
+            }
+        }
         self.follower.go_to_home_positions()?;
+        self.in_episode = true;
         Ok(())
     }
+
+    fn is_done(&self, state: &[f64]) -> bool {
+        let dist_sq: f64 = state
+            .iter()
+            .zip(self.target_position)
+            .map(|(s, t)| (s - t).powi(2))
+            .sum();
+        dist_sq < DONE_TOLERANCE * DONE_TOLERANCE * NUM_JOINTS as f64
+    }
+
+    fn robot_status(&mut self) -> Vec<RobotVisInfo> {
+        // Every read blocks on the serial bus, so the positions come from the last state and
+        // goals and loads are refreshed only every STATUS_INTERVAL. Failed reads leave the
+        // corresponding column empty rather than aborting the run.
+        if self
+            .status_reads
+            .as_ref()
+            .is_none_or(|(read_at, _, _)| read_at.elapsed() >= STATUS_INTERVAL)
+        {
+            let goals = self.follower.get_goal_positions().unwrap_or_default();
+            let loads = self.follower.get_motor_loads().unwrap_or_default();
+            self.status_reads = Some((Instant::now(), goals, loads));
+        }
+        let (goals, loads) = match &self.status_reads {
+            Some((_, goals, loads)) => (goals.clone(), loads.clone()),
+            None => (Vec::new(), Vec::new()),
+        };
+        vec![RobotVisInfo {
+            name: "Follower".to_string(),
+            positions: self.last_positions.clone(),
+            goals,
+            loads,
+        }]
+    }
 }
@@ -0,0 +1,70 @@
+use super::Environment;
+use crate::shaping::RewardShaping;
+use crate::visualization::RobotVisInfo;
+use std::error::Error;
+
+/// Any environment with its rewards passed through a [`RewardShaping`], so every training
+/// loop sees the shaped rewards without changes to the loop. Greedy action selection over
+/// `evaluate_action` is unaffected by scaling; clipping can tie actions that differ only
+/// beyond the clip range.
+pub struct ShapedEnvironment {
+    inner: Box<dyn Environment>,
+    pub shaping: RewardShaping,
+}
+
+impl ShapedEnvironment {
+    pub fn new(inner: Box<dyn Environment>, shaping: RewardShaping) -> Self {
+        Self { inner, shaping }
+    }
+
+    pub fn into_inner(self) -> Box<dyn Environment> {
+        self.inner
+    }
+}
+
+impl Environment for ShapedEnvironment {
+    fn state_size(&self) -> usize {
+        self.inner.state_size()
+    }
+
+    fn action_size(&self) -> usize {
+        self.inner.action_size()
+    }
+
+    fn state_bounds(&self) -> Option<Vec<usize>> {
+        self.inner.state_bounds()
+    }
+
+    fn clone_box(&self) -> Box<dyn Environment> {
+        Box::new(Self::new(self.inner.clone_box(), self.shaping))
+    }
+
+    fn get_state(&mut self) -> Result<Vec<f64>, Box<dyn Error>> {
+        self.inner.get_state()
+    }
+
+    fn evaluate_action(&self, state: &[f64], action_idx: usize) -> f64 {
+        self.shaping
+            .apply(self.inner.evaluate_action(state, action_idx))
+    }
+
+    fn apply_action(&mut self, action_idx: usize) -> Result<(), Box<dyn Error>> {
+        self.inner.apply_action(action_idx)
+    }
+
+    fn reset(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.reset()
+    }
+
+    fn is_done(&self, state: &[f64]) -> bool {
+        self.inner.is_done(state)
+    }
+
+    fn start_episode(&mut self) -> Result<Vec<f64>, Box<dyn Error>> {
+        self.inner.start_episode()
+    }
+
+    fn robot_status(&mut self) -> Vec<RobotVisInfo> {
+        self.inner.robot_status()
+    }
+}
@@ -0,0 +1,33 @@
+use thiserror::Error;
+
+/// Errors surfaced by the crate's public model, config and robot APIs. Tensor-level code
+/// (layers, synapses, `step`) still returns `candle_core::Result`, which converts into this.
+#[derive(Debug, Error)]
+pub enum CsdpError {
+    #[error("tensor error: {0}")]
+    Tensor(#[from] candle_core::Error),
+    #[cfg(all(feature = "robot", not(target_arch = "wasm32")))]
+    #[error("serial port error: {0}")]
+    Serial(#[from] serialport::Error),
+    #[error("servo bus error: {0}")]
+    Servo(String),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("csv error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("invalid config: {0}")]
+    Config(String),
+    #[error("invalid data: {0}")]
+    Data(String),
+}
+
+impl CsdpError {
+    /// rustypot reports bus failures as boxed errors, so keep only the message
+    pub fn servo(e: impl std::fmt::Display) -> Self {
+        CsdpError::Servo(e.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, CsdpError>;
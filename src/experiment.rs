@@ -0,0 +1,1100 @@
+//! Experiment setup shared by the `custom_framework` binary and the `train` tool: the
+//! experiment config file, device and environment selection, algorithm construction and the
+//! training loop driver.
+
+use candle_core::{Device, Result as CandleResult};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::algorithms::Algorithm;
+use crate::algorithms::algorithm_csdp_ppo::AlgorithmCSDPPPO;
+use crate::algorithms::algorithm_csdp1::Algorithm1;
+use crate::algorithms::algorithm_csdp2::Algorithm2;
+use crate::algorithms::algorithm_csdp3::Algorithm3;
+use crate::algorithms::algorithm_csdp4::Algorithm4;
+use crate::algorithms::algorithm_csdp5::AlgorithmCSDP5;
+use crate::algorithms::algorithm_ff_multi1::AlgorithmFFMulti1;
+use crate::algorithms::algorithm_ff_multi2::AlgorithmFFMulti2;
+use crate::algorithms::algorithm_ff_ppo::AlgorithmFFPPO;
+use crate::algorithms::algorithm_ff1::AlgorithmFF1;
+use crate::algorithms::algorithm_ff2::AlgorithmFF2;
+use crate::algorithms::algorithm_ff3::AlgorithmFF3;
+use crate::algorithms::algorithm_ff4::AlgorithmFF4;
+use crate::algorithms::algorithm_ffsac::AlgorithmFFSAC;
+use crate::algorithms::validation::Validator;
+use crate::environment::{self, Environment};
+use crate::environment::shaped::ShapedEnvironment;
+use crate::models::observation::ObservationSpace;
+use crate::models::preflight::{InputEncoding, InputReport, PreflightConfig, check_inputs};
+use crate::models::robot_model::{ObservationEncoder, RobotModel};
+use crate::models::summary::device_name;
+use crate::run::{RunDir, RunSummary};
+use crate::shaping::{RewardNormalizer, RewardShaping};
+#[cfg(feature = "robot")]
+use crate::robot::Arm;
+#[cfg(feature = "robot")]
+use crate::robot::envelope::{EnvelopedArm, SafetyEnvelope};
+#[cfg(feature = "robot")]
+use crate::robot::faults::{FaultConfig, FaultyArm};
+#[cfg(feature = "robot")]
+use crate::robot::profile::RobotProfile;
+#[cfg(feature = "robot")]
+use crate::robot::sim_lerobot::SimLeRobot;
+use crate::visualization::replay::SessionRecorder;
+use crate::visualization::{self, ModelStructure, VisualizationState};
+
+/// Hidden layers of [`ExperimentConfig::robot_model`] when `hidden_sizes` isn't given
+const DEFAULT_ROBOT_HIDDEN: [usize; 2] = [64, 64];
+
+/// Algorithms that drive the visualizer through `visualization::publisher::VisPublisher`,
+/// pausing between model timesteps and publishing snapshots within a sample
+const TIMESTEP_VISUALIZATION: [&str; 2] = ["csdp1", "csdp2"];
+
+/// Greedy episodes per validation when `validation_episodes` isn't given
+const DEFAULT_VALIDATION_EPISODES: usize = 5;
+
+/// Environment states the input pre-flight samples before training
+const PREFLIGHT_STEPS: usize = 200;
+
+/// Length of the pre-flight's episodes, so states fixed within an episode (such as a goal)
+/// still vary between samples
+const PREFLIGHT_EPISODE_STEPS: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvKind {
+    /// Physical LeRobot arm, falling back to the grid if it can't be opened
+    Robot,
+    Grid,
+    Rocketsim,
+    /// Simulated LeRobot arm with the robot profile's limits
+    Simrobot,
+}
+
+/// Experiment config file. Every field is optional; the `custom_framework` binary only reads
+/// the model fields (hidden sizes, dt, episode count, seed) and takes the rest from its flags.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExperimentConfig {
+    pub algo: Option<String>,
+    pub env: Option<EnvKind>,
+    pub device: Option<String>,
+    pub robot_profile: Option<String>,
+    pub hidden_sizes: Option<Vec<usize>>,
+    pub dt: Option<f32>,
+    pub n_episodes: Option<usize>,
+    pub seed: Option<u64>,
+    pub checkpoint_every: Option<usize>,
+    pub keep_last: Option<usize>,
+    pub validate_every: Option<usize>,
+    pub validation_episodes: Option<usize>,
+    /// Safety envelope JSON (see [`crate::robot::envelope`]) the robot environments keep
+    /// their goals inside
+    pub safety_envelope: Option<PathBuf>,
+    /// Fault rates JSON (see [`crate::robot::faults`]) injected between the robot
+    /// environments and their arm, to test against a flaky bus
+    pub fault_injection: Option<PathBuf>,
+    /// Scaling and clipping of every training reward (see [`crate::shaping`]); validation
+    /// episodes keep the raw rewards
+    pub reward_shaping: Option<RewardShaping>,
+    /// Smoothing of the context labels (see [`crate::shaping::smooth_labels`]); used by
+    /// `csdp2` and `csdp4`
+    pub label_smoothing: Option<f32>,
+    /// Standardize each episode's returns by their running statistics (see
+    /// [`crate::shaping::RewardNormalizer`]); used by `csdp4`
+    pub normalize_returns: Option<bool>,
+    /// Observation space JSON (see [`crate::models::observation`]) the joint-control model
+    /// of [`ExperimentConfig::robot_model`] senses instead of the joints alone
+    pub observation_space: Option<PathBuf>,
+}
+
+/// How to run one training session
+pub struct TrainOptions {
+    pub algo: String,
+    pub env: EnvKind,
+    pub robot_profile: String,
+    /// Show the Ratatui TUI; needs the `gui` feature
+    pub visualize: bool,
+    pub infinite_epochs: bool,
+    pub resume: bool,
+    /// Checkpoint file or directory to resume from, instead of the default location
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_every: Option<usize>,
+    pub keep_last: Option<usize>,
+    /// Validate on a separate environment every N episodes; the best validation reward picks
+    /// the best checkpoint
+    pub validate_every: Option<usize>,
+    /// Greedy episodes per validation
+    pub validation_episodes: Option<usize>,
+    /// Serve Prometheus metrics on this address
+    pub metrics_addr: Option<String>,
+    /// Output directory of this run, receiving the resolved config, the checkpoints, the
+    /// per-episode rewards and their plot (see [`crate::run`])
+    pub run_dir: Option<RunDir>,
+    /// Record the visualizer's snapshots to this file for `replay`
+    pub record_session: Option<PathBuf>,
+    /// Checkpoint shown next to the live model in the visualizer's comparison view
+    pub compare_checkpoint: Option<PathBuf>,
+    /// When the CUDA device fails mid-run, continue on the CPU from the recovery checkpoint
+    /// instead of stopping, see [`is_device_failure`]
+    pub cpu_fallback: bool,
+    /// Skip the input pre-flight on environment states before training, see [`preflight`]
+    pub skip_preflight: bool,
+}
+
+impl ExperimentConfig {
+    /// The config as [`train`] runs it with `options` on `device`, with the flags and defaults
+    /// filled in, for a run's `config.json`. Fields the algorithm chooses a default for, such
+    /// as `hidden_sizes`, stay unset.
+    pub fn resolved(&self, options: &TrainOptions, device: &Device) -> Self {
+        Self {
+            algo: Some(options.algo.clone()),
+            env: Some(options.env),
+            device: Some(device_name(device)),
+            robot_profile: Some(options.robot_profile.clone()),
+            dt: Some(self.dt.unwrap_or(0.1)),
+            checkpoint_every: options.checkpoint_every.or(self.checkpoint_every),
+            keep_last: options.keep_last.or(self.keep_last),
+            validate_every: options.validate_every.or(self.validate_every),
+            validation_episodes: options.validation_episodes.or(self.validation_episodes),
+            ..self.clone()
+        }
+    }
+
+    /// The joint-control model this config describes: sensing the modalities of the
+    /// `observation_space` file if set, driven with [`RobotModel::observe`], else the joints
+    /// through `encoder`, driven with [`RobotModel::control`]. Every hidden layer takes the
+    /// size of `hidden_sizes`, which must all be equal.
+    pub fn robot_model(
+        &self,
+        encoder: ObservationEncoder,
+        device: &Device,
+    ) -> Result<RobotModel, Box<dyn Error>> {
+        let hidden = self
+            .hidden_sizes
+            .clone()
+            .unwrap_or_else(|| DEFAULT_ROBOT_HIDDEN.to_vec());
+        let Some(&hidden_size) = hidden.first() else {
+            return Err("hidden_sizes of a robot model can't be empty".into());
+        };
+        if hidden.iter().any(|&size| size != hidden_size) {
+            return Err(format!(
+                "the hidden layers of a robot model share one size, got {:?}",
+                hidden
+            )
+            .into());
+        }
+        let dt = self.dt.unwrap_or(0.1);
+        Ok(match &self.observation_space {
+            Some(path) => {
+                let space = ObservationSpace::load(path)?;
+                log::info!(
+                    "Observation space from {:?}: {} inputs",
+                    path,
+                    space.input_size()
+                );
+                RobotModel::with_observation_space(space, hidden.len(), hidden_size, device, dt)
+            }
+            None => RobotModel::with_encoder(encoder, hidden.len(), hidden_size, device, dt),
+        })
+    }
+}
+
+/// An algorithm ready to run, with what the caller needs to drive it
+pub struct BuiltAlgorithm {
+    pub algo: Box<dyn Algorithm>,
+    pub n_episodes: usize,
+    pub snapshot: CandleResult<ModelStructure>,
+    /// where `--resume` looks when no `--checkpoint` is given
+    pub default_checkpoint: &'static str,
+}
+
+/// Parse `cpu`, `cuda` or `cuda:<ordinal>`
+pub fn parse_device(name: &str) -> Result<Device, Box<dyn Error>> {
+    match name {
+        "cpu" => Ok(Device::Cpu),
+        "cuda" => Ok(Device::new_cuda(0)?),
+        _ => match name.strip_prefix("cuda:") {
+            Some(ordinal) => Ok(Device::new_cuda(ordinal.parse()?)?),
+            None => Err(format!("unknown device '{}'", name).into()),
+        },
+    }
+}
+
+/// Parts of the messages of CUDA driver, cuBLAS and cuRAND errors and failed allocations,
+/// lowercased
+const DEVICE_FAILURE_MARKERS: &[&str] = &[
+    "cuda_error",
+    "cublas_status",
+    "curand_status",
+    "drivererror",
+    "out of memory",
+];
+
+/// Directory under the checkpoint root that receives the state at a device failure
+const RECOVERY_DIR: &str = "device_failure";
+
+/// Whether `err`, or an error it wraps, comes from the device rather than the model, e.g. a
+/// driver reset or running out of GPU memory
+pub fn is_device_failure(err: &(dyn Error + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        let message = err.to_string().to_lowercase();
+        if DEVICE_FAILURE_MARKERS
+            .iter()
+            .any(|marker| message.contains(marker))
+        {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+pub fn load_config(path: Option<&Path>) -> Result<ExperimentConfig, Box<dyn Error>> {
+    match path {
+        Some(path) => Ok(serde_json::from_reader(std::fs::File::open(path)?)?),
+        None => Ok(ExperimentConfig::default()),
+    }
+}
+
+/// The environment `kind`; robot arms get their goals checked against `safety_envelope` and
+/// the bus faults of `fault_injection` injected
+pub fn make_environment(
+    kind: EnvKind,
+    robot_profile: &str,
+    safety_envelope: Option<&Path>,
+    fault_injection: Option<&Path>,
+) -> Result<Box<dyn Environment>, Box<dyn Error>> {
+    let env: Box<dyn Environment> = match kind {
+        EnvKind::Grid => {
+            log::info!("Using Grid Environment.");
+            Box::new(environment::grid::GridEnvironment::new())
+        }
+        EnvKind::Rocketsim => {
+            log::info!("Using RocketSim Environment.");
+            Box::new(environment::rocketsim::RocketSimEnvironment::new(5)) // tickskip=5
+        }
+        EnvKind::Robot => make_robot_environment(robot_profile, safety_envelope, fault_injection)?,
+        EnvKind::Simrobot => {
+            make_sim_robot_environment(robot_profile, safety_envelope, fault_injection)?
+        }
+    };
+    Ok(env)
+}
+
+/// The safety envelope at `path`, if one is given
+#[cfg(feature = "robot")]
+fn load_envelope(path: Option<&Path>) -> Result<Option<SafetyEnvelope>, Box<dyn Error>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let envelope = SafetyEnvelope::load(path)?;
+    log::info!(
+        "Keeping robot goals inside the safety envelope {:?} ({:?} mode)",
+        path,
+        envelope.mode
+    );
+    Ok(Some(envelope))
+}
+
+/// The fault rates at `path`, if one is given
+#[cfg(feature = "robot")]
+fn load_faults(path: Option<&Path>) -> Result<Option<FaultConfig>, Box<dyn Error>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let faults = FaultConfig::load(path)?;
+    log::warn!("Injecting robot bus faults from {:?}: {:?}", path, faults);
+    Ok(Some(faults))
+}
+
+/// `arm` behind a bus with the `faults`, if any
+#[cfg(feature = "robot")]
+fn faulty(arm: Box<dyn Arm>, faults: Option<FaultConfig>) -> crate::error::Result<Box<dyn Arm>> {
+    let Some(faults) = faults else {
+        return Ok(arm);
+    };
+    Ok(Box::new(FaultyArm::new(arm, faults)?))
+}
+
+#[cfg(feature = "robot")]
+fn guarded(arm: Box<dyn Arm>, envelope: Option<SafetyEnvelope>) -> Box<dyn Arm> {
+    match envelope {
+        Some(envelope) => Box::new(EnvelopedArm::new(arm, envelope)),
+        None => arm,
+    }
+}
+
+#[cfg(feature = "robot")]
+fn make_robot_environment(
+    robot_profile: &str,
+    safety_envelope: Option<&Path>,
+    fault_injection: Option<&Path>,
+) -> Result<Box<dyn Environment>, Box<dyn Error>> {
+    let profile = RobotProfile::resolve(robot_profile)?;
+    let envelope = load_envelope(safety_envelope)?;
+    let faults = load_faults(fault_injection)?;
+    let robot_env = profile.connect().and_then(|arm| {
+        let arm = faulty(Box::new(arm), faults)?;
+        environment::robot::RobotEnvironment::from_arm(guarded(arm, envelope))
+    });
+    Ok(match robot_env {
+        Ok(robot_env) => {
+            log::info!("Using physical Robot Environment.");
+            Box::new(robot_env)
+        }
+        Err(e) => {
+            log::info!(
+                "Failed to construct RobotEnvironment: {}. Falling back to Grid Environment.",
+                e
+            );
+            Box::new(environment::grid::GridEnvironment::new())
+        }
+    })
+}
+
+#[cfg(feature = "robot")]
+fn make_sim_robot_environment(
+    robot_profile: &str,
+    safety_envelope: Option<&Path>,
+    fault_injection: Option<&Path>,
+) -> Result<Box<dyn Environment>, Box<dyn Error>> {
+    let profile = RobotProfile::resolve(robot_profile)?;
+    let envelope = load_envelope(safety_envelope)?;
+    let faults = load_faults(fault_injection)?;
+    log::info!("Using simulated Robot Environment.");
+    let arm = faulty(Box::new(SimLeRobot::from_profile(&profile)), faults)?;
+    let arm = guarded(arm, envelope);
+    Ok(Box::new(environment::robot::RobotEnvironment::from_arm(
+        arm,
+    )?))
+}
+
+#[cfg(not(feature = "robot"))]
+fn make_sim_robot_environment(
+    _robot_profile: &str,
+    _safety_envelope: Option<&Path>,
+    _fault_injection: Option<&Path>,
+) -> Result<Box<dyn Environment>, Box<dyn Error>> {
+    Err("built without the `robot` feature; rebuild with --features robot to use --env simrobot".into())
+}
+
+#[cfg(not(feature = "robot"))]
+fn make_robot_environment(
+    _robot_profile: &str,
+    _safety_envelope: Option<&Path>,
+    _fault_injection: Option<&Path>,
+) -> Result<Box<dyn Environment>, Box<dyn Error>> {
+    log::info!("Built without the `robot` feature. Falling back to Grid Environment.");
+    Ok(Box::new(environment::grid::GridEnvironment::new()))
+}
+
+fn no_snapshot() -> CandleResult<ModelStructure> {
+    Err(candle_core::Error::Msg(
+        "FF Model has no visualization".to_string(),
+    ))
+}
+
+/// Construct the algorithm named `algo_choice` for `env`
+pub fn build_algorithm(
+    algo_choice: &str,
+    env: &dyn Environment,
+    device: Device,
+    config: &ExperimentConfig,
+    n_episodes: Option<usize>,
+) -> Result<BuiltAlgorithm, Box<dyn Error>> {
+    let state_size = env.state_size();
+    let action_size = env.action_size();
+    let state_bounds = env.state_bounds();
+    let dt = config.dt.unwrap_or(0.1);
+    let hidden = |default: &[usize]| {
+        config
+            .hidden_sizes
+            .clone()
+            .unwrap_or_else(|| default.to_vec())
+    };
+
+    let (algo, eps, snapshot, num_layers, num_synapses, default_checkpoint): (
+        Box<dyn Algorithm>,
+        _,
+        _,
+        _,
+        _,
+        _,
+    ) = match algo_choice {
+        "csdp1" => {
+            log::info!("Using Algorithm CSDP1");
+            let mut algo = Algorithm1::new(
+                state_size,
+                action_size,
+                hidden(&[256, 128]),
+                dt,
+                device,
+                state_bounds,
+            )?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            let snap = algo.model.get_visualization_snapshot();
+            let (eps, layers, syns) = (
+                algo.n_episodes,
+                algo.model.layers.len(),
+                algo.model.synapses.len(),
+            );
+            (Box::new(algo), eps, snap, layers, syns, "checkpoints/csdp1")
+        }
+        "csdp2" => {
+            log::info!("Using Algorithm CSDP2");
+            let mut algo = Algorithm2::new(
+                state_size,
+                action_size,
+                hidden(&[256, 128]),
+                dt,
+                device,
+                state_bounds,
+            )?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            algo.model.set_label_smoothing(config.label_smoothing)?;
+            let snap = algo.model.get_visualization_snapshot();
+            let (eps, layers, syns) = (
+                algo.n_episodes,
+                algo.model.layers.len(),
+                algo.model.synapses.len(),
+            );
+            (Box::new(algo), eps, snap, layers, syns, "checkpoints/csdp2")
+        }
+        "csdp3" => {
+            log::info!("Using Algorithm CSDP3 (AC-CSDP)");
+            let mut algo = Algorithm3::new(
+                state_size,
+                action_size,
+                hidden(&[256, 128]),
+                dt,
+                device,
+                state_bounds,
+            )?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            let snap = algo.model.actor.get_visualization_snapshot();
+            let (eps, layers, syns) = (
+                algo.n_episodes,
+                algo.model.actor.layers.len() + algo.model.critic.layers.len(),
+                algo.model.actor.synapses.len() + algo.model.critic.synapses.len(),
+            );
+            (Box::new(algo), eps, snap, layers, syns, "checkpoints")
+        }
+        "csdp4" => {
+            log::info!("Using Algorithm CSDP4");
+            let mut algo = Algorithm4::new(
+                state_size,
+                action_size,
+                hidden(&[256, 128]),
+                dt,
+                device,
+                state_bounds,
+            )?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            algo.model.set_label_smoothing(config.label_smoothing)?;
+            if config.normalize_returns == Some(true) {
+                algo.return_normalizer = Some(RewardNormalizer::new());
+            }
+            let snap = algo.model.get_visualization_snapshot();
+            let (eps, layers, syns) = (
+                algo.n_episodes,
+                algo.model.layers.len(),
+                algo.model.synapses.len(),
+            );
+            (
+                Box::new(algo),
+                eps,
+                snap,
+                layers,
+                syns,
+                "checkpoints/model_final.safetensors",
+            )
+        }
+        "csdp5" => {
+            log::info!("Using Algorithm CSDP5 (Multi-Class MC SNN)");
+            let mut algo = AlgorithmCSDP5::new(
+                state_size,
+                action_size,
+                hidden(&[1000, 256]),
+                dt,
+                device,
+                state_bounds,
+            )?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            let snap = algo.model.get_visualization_snapshot();
+            let (eps, layers, syns) = (
+                algo.n_episodes,
+                algo.model.layers.len(),
+                algo.model.synapses.len(),
+            );
+            (Box::new(algo), eps, snap, layers, syns, "checkpoints/csdp5")
+        }
+        "ff1" => {
+            log::info!("Using Algorithm FF1 (FF Model - State/Action Iterator)");
+            let mut algo = AlgorithmFF1::new(state_size, action_size, hidden(&[256, 128]), device)?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            let (eps, layers) = (algo.n_episodes, algo.model.layers.len());
+            (Box::new(algo), eps, no_snapshot(), layers, 0, "checkpoints")
+        }
+        "ff2" => {
+            log::info!("Using Algorithm FF2 (FF Model - Transition Evaluator)");
+            let mut algo = AlgorithmFF2::new(state_size, action_size, hidden(&[256, 128]), device)?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            let (eps, layers) = (algo.n_episodes, algo.model.layers.len());
+            (Box::new(algo), eps, no_snapshot(), layers, 0, "checkpoints")
+        }
+        "ff3" => {
+            log::info!("Using Algorithm FF3 (FF Model - Probabilistic Rank Trajectory)");
+            let mut algo = AlgorithmFF3::new(state_size, action_size, hidden(&[256, 128]), device)?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            let (eps, layers) = (algo.n_episodes, algo.model.layers.len());
+            (Box::new(algo), eps, no_snapshot(), layers, 0, "checkpoints")
+        }
+        "ff4" => {
+            log::info!("Using Algorithm FF4 (FF Model - Temporal Contrastive RL)");
+            let mut algo = AlgorithmFF4::new(state_size, action_size, hidden(&[256, 128]), device)?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            let (eps, layers) = (algo.n_episodes, algo.model.layers.len());
+            (Box::new(algo), eps, no_snapshot(), layers, 0, "checkpoints")
+        }
+        "ffsac" => {
+            log::info!("Using Algorithm FFSAC (FF Model - Soft Actor-Critic)");
+            let mut algo =
+                AlgorithmFFSAC::new(state_size, action_size, hidden(&[256, 128]), device)?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            let (eps, layers) = (algo.n_episodes, algo.model.layers.len());
+            (Box::new(algo), eps, no_snapshot(), layers, 0, "checkpoints")
+        }
+        "ff_multi1" => {
+            log::info!("Using Algorithm FF Multi 1 (FF Multi Model - Temporal Contrastive RL)");
+            let mut algo =
+                AlgorithmFFMulti1::new(state_size, action_size, hidden(&[256, 128]), device)?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            let (eps, layers) = (algo.n_episodes, algo.model.layers.len());
+            (Box::new(algo), eps, no_snapshot(), layers, 0, "checkpoints")
+        }
+        "ff_multi2" => {
+            log::info!("Using Algorithm FF Multi 2 (Classification-Based RL)");
+            let mut algo =
+                AlgorithmFFMulti2::new(state_size, action_size, hidden(&[512, 256, 128]), device)?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            let (eps, layers) = (algo.n_episodes, algo.main_model.layers.len());
+            (
+                Box::new(algo),
+                eps,
+                no_snapshot(),
+                layers,
+                0,
+                "checkpoints/ff_multi2",
+            )
+        }
+        "ff_ppo" => {
+            log::info!("Using Algorithm FF PPO (PPO with Forward-Forward Models)");
+            let mut algo = AlgorithmFFPPO::new(state_size, action_size, device)?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            let (eps, layers) = (
+                algo.n_episodes,
+                algo.policy_model.layers.len() + algo.value_model.layers.len(),
+            );
+            (
+                Box::new(algo),
+                eps,
+                no_snapshot(),
+                layers,
+                0,
+                "checkpoints/ff_ppo",
+            )
+        }
+        "csdp_ppo" => {
+            log::info!("Using Algorithm CSDP PPO (PPO with CSDP Spiking Q-Function)");
+            let mut algo = AlgorithmCSDPPPO::new(state_size, action_size, device, dt)?;
+            if let Some(n) = n_episodes {
+                algo.n_episodes = n;
+            }
+            let snap = algo.policy_model.get_visualization_snapshot();
+            let (eps, layers, syns) = (
+                algo.n_episodes,
+                algo.policy_model.layers.len() + algo.value_model.layers.len(),
+                algo.policy_model.synapses.len() + algo.value_model.synapses.len(),
+            );
+            (
+                Box::new(algo),
+                eps,
+                snap,
+                layers,
+                syns,
+                "checkpoints/csdp_ppo",
+            )
+        }
+        other => return Err(format!("Unknown algorithm choice: {}", other).into()),
+    };
+    if config.label_smoothing.is_some() && !matches!(algo_choice, "csdp2" | "csdp4") {
+        log::warn!("label_smoothing is only used by csdp2 and csdp4, not {}", algo_choice);
+    }
+    if config.normalize_returns.is_some() && algo_choice != "csdp4" {
+        log::warn!("normalize_returns is only used by csdp4, not {}", algo_choice);
+    }
+
+    log::info!("layers len: {}, num_synapses: {}", num_layers, num_synapses);
+
+    Ok(BuiltAlgorithm {
+        algo,
+        n_episodes: eps,
+        snapshot,
+        default_checkpoint,
+    })
+}
+
+/// Validate `algo` on a separate environment every `--validate-every` episodes
+fn attach_validator(
+    algo: &mut dyn Algorithm,
+    options: &TrainOptions,
+    config: &ExperimentConfig,
+) -> Result<(), Box<dyn Error>> {
+    let Some(every) = options.validate_every.filter(|&every| every > 0) else {
+        return Ok(());
+    };
+    if options.env == EnvKind::Robot {
+        log::warn!("the robot has no held-out copy to validate on; ignoring --validate-every");
+        return Ok(());
+    }
+    let episodes = options
+        .validation_episodes
+        .unwrap_or(DEFAULT_VALIDATION_EPISODES);
+    let validation_env = make_environment(
+        options.env,
+        &options.robot_profile,
+        config.safety_envelope.as_deref(),
+        config.fault_injection.as_deref(),
+    )?;
+    if let Err(e) = algo.set_validator(Validator::new(validation_env, every, episodes)) {
+        log::warn!("{}: {}; ignoring --validate-every", options.algo, e);
+    }
+    Ok(())
+}
+
+/// Structure of the model in `checkpoint`, restored into a CPU copy of the algorithm of
+/// `options`, for the visualizer's comparison view. Building the copy draws initial weights,
+/// so it runs on a copy of the host RNG and leaves a seeded run as it was.
+fn checkpoint_snapshot(
+    options: &TrainOptions,
+    config: &ExperimentConfig,
+    env: &dyn Environment,
+    checkpoint: &Path,
+) -> Result<ModelStructure, Box<dyn Error>> {
+    crate::seed::isolated(|| {
+        let mut algo = build_algorithm(&options.algo, env, Device::Cpu, config, None)?.algo;
+        algo.restore(checkpoint)?;
+        algo.visualization_snapshot()
+    })
+}
+
+/// Save what `algo` can still be restored from after its device failed: the state at the
+/// failure if it can be copied off the device, under `root`, else its newest periodic
+/// checkpoint
+fn save_for_recovery(algo: &mut dyn Algorithm, root: &Path) -> Option<PathBuf> {
+    let dir = root.join(RECOVERY_DIR);
+    match algo.save_to(&dir) {
+        Ok(()) => {
+            log::info!("Saved the state at the failure to {:?}", dir);
+            return Some(dir);
+        }
+        Err(e) => log::warn!("Could not save the state at the failure: {}", e),
+    }
+    let latest = algo.checkpoints_mut()?.latest()?;
+    log::info!("Falling back to the newest checkpoint, {:?}", latest);
+    Some(latest)
+}
+
+/// Algorithm restored from a checkpoint, with the `(episode, reward)` history it was saved with
+type Restored = (Box<dyn Algorithm>, Vec<(usize, f32)>);
+
+/// The algorithm of `options` on the CPU, restored from `checkpoint` and keeping the
+/// checkpoint rotation of `failed`, with the reward history of the checkpoint
+fn rebuild_on_cpu(
+    failed: &mut dyn Algorithm,
+    options: &TrainOptions,
+    config: &ExperimentConfig,
+    env: &dyn Environment,
+    n_episodes: usize,
+    checkpoint: &Path,
+) -> Result<Restored, Box<dyn Error>> {
+    let rotation = failed
+        .checkpoints_mut()
+        .map(|checkpoints| (checkpoints.root().to_path_buf(), checkpoints.policy));
+    let mut algo =
+        build_algorithm(&options.algo, env, Device::Cpu, config, Some(n_episodes))?.algo;
+    if let (Some((root, policy)), Some(checkpoints)) = (rotation, algo.checkpoints_mut()) {
+        checkpoints.set_root(root);
+        checkpoints.policy = policy;
+    }
+    attach_validator(algo.as_mut(), options, config)?;
+    let rewards = algo.restore(checkpoint)?;
+    Ok((algo, rewards))
+}
+
+/// Check the scaling of the states `env` hands to the algorithms, as sampled over `steps`
+/// random actions on a copy of it, in short episodes: against the [0, 1] of firing
+/// probabilities, or only for constant and non-finite features if the environment's states
+/// are bounded indices for a one-hot input layer (see [`crate::models::preflight`]). The
+/// host RNG is left as it was, so a seeded run is the same with or without the check.
+pub fn preflight(env: &dyn Environment, steps: usize) -> Result<InputReport, Box<dyn Error>> {
+    crate::seed::isolated(|| sample_states(env, steps))
+}
+
+fn sample_states(env: &dyn Environment, steps: usize) -> Result<InputReport, Box<dyn Error>> {
+    use rand::Rng;
+
+    let mut env = env.clone_box();
+    let action_size = env.action_size();
+    let mut rng = crate::seed::rng();
+    let mut values = Vec::with_capacity(steps * env.state_size());
+    let mut state = env.start_episode()?;
+    for step in 1..=steps {
+        values.extend(state.iter().map(|&v| v as f32));
+        if env.is_done(&state) || step % PREFLIGHT_EPISODE_STEPS == 0 {
+            state = env.start_episode()?;
+            continue;
+        }
+        if action_size > 0 {
+            env.apply_action(rng.gen_range(0..action_size))?;
+        }
+        state = env.get_state()?;
+    }
+    let state_size = env.state_size();
+    let samples =
+        candle_core::Tensor::from_vec(values, (steps, state_size), &Device::Cpu)?.t()?;
+    let encoding = if env.state_bounds().is_some() {
+        InputEncoding::Rescaled
+    } else {
+        InputEncoding::Rates
+    };
+    Ok(check_inputs(&samples, encoding, &PreflightConfig::default())?)
+}
+
+/// Build the environment and algorithm from `options` and `config` and train until the
+/// algorithm finishes or the TUI is closed
+pub fn train(
+    options: TrainOptions,
+    config: &ExperimentConfig,
+    device: Device,
+) -> Result<(), Box<dyn Error>> {
+    let started = std::time::Instant::now();
+    if let Some(seed) = config.seed {
+        crate::seed::set_global_seed(seed, &device)?;
+        log::info!("Seeded run with {}", seed);
+    }
+    if options.visualize && !cfg!(feature = "gui") {
+        return Err(
+            "built without the `gui` feature; rebuild with --features gui to use --visualize"
+                .into(),
+        );
+    }
+    let mut env = make_environment(
+        options.env,
+        &options.robot_profile,
+        config.safety_envelope.as_deref(),
+        config.fault_injection.as_deref(),
+    )?;
+    if let Some(shaping) = config.reward_shaping {
+        log::info!("Shaping training rewards: {:?}", shaping);
+        env = Box::new(ShapedEnvironment::new(env, shaping));
+    }
+    if options.skip_preflight {
+        log::info!("Skipping the input pre-flight");
+    } else if options.env == EnvKind::Robot {
+        // A copy of the real environment would drive the same arm
+        log::info!("Skipping the input pre-flight on the real arm");
+    } else {
+        match preflight(env.as_ref(), PREFLIGHT_STEPS) {
+            Ok(report) => report.log("environment states"),
+            Err(e) => log::warn!("Input pre-flight failed: {}", e),
+        }
+    }
+
+    log::info!(
+        "Visualization: {}",
+        if options.visualize {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    if options.visualize && !TIMESTEP_VISUALIZATION.contains(&options.algo.as_str()) {
+        log::warn!(
+            "{} publishes to the visualizer once per sample; timestep stepping and \
+             per-timestep snapshots are only supported by {}",
+            options.algo,
+            TIMESTEP_VISUALIZATION.join(" and ")
+        );
+    }
+
+    let n_episodes = if options.infinite_epochs {
+        Some(usize::MAX - 1)
+    } else {
+        config.n_episodes
+    };
+    let resolved = options
+        .run_dir
+        .as_ref()
+        .map(|_| config.resolved(&options, &device));
+    let BuiltAlgorithm {
+        mut algo,
+        n_episodes,
+        snapshot,
+        default_checkpoint,
+    } = build_algorithm(&options.algo, env.as_ref(), device.clone(), config, n_episodes)?;
+
+    let mut resume_from = PathBuf::from(default_checkpoint);
+    if let (Some(run_dir), Some(mut resolved)) = (&options.run_dir, resolved) {
+        // A resumed run keeps the config it was started with
+        if !run_dir.config_path().exists() {
+            if !options.infinite_epochs {
+                resolved.n_episodes = Some(n_episodes);
+            }
+            run_dir.write_config(&resolved)?;
+        }
+        match algo.checkpoints_mut() {
+            Some(checkpoints) => {
+                checkpoints.set_root(run_dir.checkpoints_dir());
+                resume_from = checkpoints.root().to_path_buf();
+            }
+            None => log::warn!(
+                "{} writes its checkpoints to {:?}, not the run directory",
+                options.algo,
+                default_checkpoint
+            ),
+        }
+    }
+
+    if options.checkpoint_every.is_some() || options.keep_last.is_some() {
+        match algo.checkpoints_mut() {
+            Some(checkpoints) => {
+                if let Some(every) = options.checkpoint_every {
+                    checkpoints.policy.every = every;
+                }
+                if let Some(keep_last) = options.keep_last {
+                    checkpoints.policy.keep_last = keep_last;
+                }
+            }
+            None => log::warn!(
+                "{} does not checkpoint during training; ignoring --checkpoint-every/--keep-last",
+                options.algo
+            ),
+        }
+    }
+
+    attach_validator(algo.as_mut(), &options, config)?;
+
+    // Resume from checkpoint if --resume and a checkpoint exists.
+    let mut restored_rewards = Vec::new();
+    if options.resume {
+        let cp_path = options
+            .checkpoint
+            .clone()
+            .unwrap_or_else(|| resume_from.clone());
+        if cp_path.exists() {
+            match algo.restore(&cp_path) {
+                Ok(rewards) => {
+                    log::info!("Resumed from checkpoint {:?}", cp_path);
+                    restored_rewards = rewards;
+                }
+                Err(e) => {
+                    log::error!("Failed to load checkpoint: {}. Starting fresh.", e);
+                }
+            }
+        } else {
+            log::info!("No checkpoint found at {:?}. Starting fresh.", cp_path);
+        }
+    }
+
+    // The shared state backs the TUI, the metrics endpoint, the run's reward history and the
+    // session recording
+    let vis_state = if options.visualize
+        || options.metrics_addr.is_some()
+        || options.run_dir.is_some()
+        || options.record_session.is_some()
+    {
+        let vis_state = Arc::new(Mutex::new(VisualizationState::new(n_episodes)));
+
+        // Initialize model structure
+        if let Ok(mut state) = vis_state.lock() {
+            if let Some(path) = &options.record_session {
+                state.recorder = Some(SessionRecorder::create(path)?);
+                log::info!("Recording the session to {:?}", path);
+            }
+            if let Ok(snapshot) = snapshot {
+                log::info!(
+                    "Initial snapshot: {} layers, {} synapses",
+                    snapshot.layers.len(),
+                    snapshot.synapses.len()
+                );
+                state.update_from_snapshot(snapshot);
+            } else {
+                log::info!("Warning: Failed to get initial visualization snapshot");
+            }
+            // If we resumed from a checkpoint, inject the restored reward history
+            // into the visualization state so the graph picks up where it left off.
+            if !restored_rewards.is_empty() {
+                state.epoch_rewards = restored_rewards;
+            }
+            if let Some(path) = &options.compare_checkpoint {
+                match checkpoint_snapshot(&options, config, env.as_ref(), path) {
+                    Ok(snapshot) => {
+                        log::info!("Comparing against checkpoint {:?}", path);
+                        state.set_comparison(format!("Checkpoint {}", path.display()), snapshot);
+                    }
+                    Err(e) => log::warn!("Could not load {:?} for comparison: {}", path, e),
+                }
+            }
+            // Without the TUI nothing would ever unpause training
+            state.is_paused = options.visualize;
+        }
+        Some(vis_state)
+    } else {
+        None
+    };
+
+    if let (Some(addr), Some(vis_state)) = (&options.metrics_addr, &vis_state) {
+        visualization::metrics::start_metrics_server(addr.as_str(), vis_state.clone())?;
+    }
+
+    // Start visualization if requested
+    #[cfg(feature = "gui")]
+    let vis_handle = match &vis_state {
+        Some(vis_state) if options.visualize => Some((
+            visualization::start_visualization(vis_state.clone()),
+            vis_state.clone(),
+        )),
+        _ => None,
+    };
+    #[cfg(not(feature = "gui"))]
+    let vis_handle: Option<(std::thread::JoinHandle<()>, Arc<Mutex<VisualizationState>>)> = None;
+
+    let mut result = algo.run(env.as_mut(), options.visualize, vis_state.clone());
+    if let Err(e) = &result
+        && device.is_cuda()
+        && is_device_failure(e.as_ref())
+    {
+        log::error!("The device failed during training: {}", e);
+        match save_for_recovery(algo.as_mut(), &resume_from) {
+            Some(checkpoint) if options.cpu_fallback => {
+                log::warn!("Continuing on the CPU from {:?}", checkpoint);
+                let (cpu_algo, rewards) = rebuild_on_cpu(
+                    algo.as_mut(),
+                    &options,
+                    config,
+                    env.as_ref(),
+                    n_episodes,
+                    &checkpoint,
+                )?;
+                algo = cpu_algo;
+                if let Some(vis_state) = &vis_state
+                    && !rewards.is_empty()
+                    && let Ok(mut state) = vis_state.lock()
+                {
+                    state.epoch_rewards = rewards;
+                }
+                result = algo.run(env.as_mut(), options.visualize, vis_state.clone());
+            }
+            Some(checkpoint) => {
+                result = Err(format!(
+                    "{}; continue from {:?} with --resume --checkpoint, on the CPU if the \
+                     device stays unavailable (--cpu-fallback does this automatically)",
+                    e, checkpoint
+                )
+                .into());
+            }
+            None => log::error!("Nothing to recover from; rerun with --checkpoint-every"),
+        }
+    }
+
+    if let (Some(run_dir), Some(vis_state)) = (&options.run_dir, &vis_state)
+        && let Ok(state) = vis_state.lock()
+    {
+        let path = run_dir.metrics_path();
+        state.save_graphs_to_csv(&path)?;
+        log::info!("Saved reward history to {:?}", path);
+        let plot = run_dir.export_reward_plot(&state.epoch_rewards)?;
+        log::info!("Saved reward plot to {:?}", plot);
+
+        // Written for failed runs too, so a sweep can tell them from runs still going
+        let name = run_dir.info().map_or_else(|_| options.algo.clone(), |info| info.name);
+        let mut summary = RunSummary::new(&name, &state.epoch_rewards, started.elapsed());
+        if let Err(e) = &result {
+            summary.completed = false;
+            summary.error = Some(e.to_string());
+        }
+        summary.config_hash = run_dir.config_hash()?;
+        if let Some(checkpoints) = algo.checkpoints_mut() {
+            summary.latest_checkpoint = checkpoints.latest();
+            summary.best_checkpoint = checkpoints.best();
+            summary.best_checkpoint_metric = checkpoints.best_metric();
+        }
+        run_dir.write_summary(&summary)?;
+        log::info!("Saved run summary to {:?}", run_dir.summary_path());
+    }
+    result?;
+
+    if let Some((_, ref vis_state_arc)) = vis_handle {
+        loop {
+            let should_close = vis_state_arc
+                .try_lock()
+                .map(|state| state.should_close)
+                .unwrap_or(false);
+            if should_close {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+    }
+
+    // Signal visualization to close and wait for thread
+    if let Some((handle, vis_state)) = vis_handle {
+        log::info!("Closing visualization...");
+        while let Ok(state) = vis_state.lock() {
+            if state.should_close {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+
+        let _ = handle.join();
+    }
+
+    // drop env cleans up whatever depends on drops, e.g. RobotEnvironment::disable()
+    drop(env);
+
+    log::info!("Done");
+    Ok(())
+}
@@ -1,6 +1,8 @@
 use crate::layer::Layer;
+use crate::layer::scratch::InputCompartment;
 use candle_core::{DType, Device, Result as CandleResult, Tensor};
 
+#[derive(Clone)]
 pub struct BernoulliLayer {
     /// random uniform [0, 1]
     rng_vals: Tensor,
@@ -8,7 +10,7 @@ pub struct BernoulliLayer {
     probs: Tensor,
     /// output spikes
     spikes: Tensor,
-    inputs: Tensor,
+    inputs: InputCompartment,
     size: usize,
     current_label: Tensor,
     dummy_mod_signal: Tensor,
@@ -16,11 +18,11 @@ pub struct BernoulliLayer {
 
 impl BernoulliLayer {
     pub fn new(size: usize, device: &Device) -> CandleResult<Self> {
-        let rng_vals = Tensor::rand(0.0f32, 1.0, (size, 1), device)?;
-        let probs = Tensor::zeros((size, 1), DType::F32, device)?;
-        let spikes = Tensor::zeros((size, 1), DType::F32, device)?;
-        let inputs = Tensor::zeros((size, 1), DType::F32, device)?;
-        let dummy_mod_signal = Tensor::zeros((size, 1), DType::F32, device)?;
+        let rng_vals = crate::seed::rand_uniform(0.0, 1.0, (size, 1), device)?;
+        let inputs = InputCompartment::new(size, 1, device)?;
+        let probs = inputs.zeros().clone();
+        let spikes = inputs.zeros().clone();
+        let dummy_mod_signal = inputs.zeros().clone();
 
         Ok(Self {
             rng_vals,
@@ -38,11 +40,12 @@ impl Layer for BernoulliLayer {
     fn step(&mut self, _dt: f32) -> CandleResult<()> {
         // Just raw bernoulli distribution of the inputs
         let eps = 1e-4;
-        self.probs = self.inputs.clamp(eps, 1.0 - eps)?;
+        self.probs = self.inputs.get().clamp(eps, 1.0 - eps)?;
 
         // reroll rng
         let batch_size = self.probs.dims()[1];
-        self.rng_vals = Tensor::rand(0.0f32, 1.0, (self.size, batch_size), self.probs.device())?;
+        self.rng_vals =
+            crate::seed::rand_uniform(0.0, 1.0, (self.size, batch_size), self.probs.device())?;
 
         // 1 if prob > rng
         self.spikes = self
@@ -69,23 +72,21 @@ impl Layer for BernoulliLayer {
     }
 
     fn add_input(&mut self, input: &Tensor) -> CandleResult<()> {
-        self.inputs = self.inputs.add(input)?;
-        Ok(())
+        self.inputs.add(input)
     }
 
     fn reset_input(&mut self) -> CandleResult<()> {
-        let batch_size = self.probs.dims()[1];
-        self.inputs = Tensor::zeros((self.size, batch_size), DType::F32, self.probs.device())?;
+        self.inputs.clear();
         Ok(())
     }
 
     fn reset(&mut self, batch_size: usize) -> CandleResult<()> {
-        self.inputs = Tensor::zeros((self.size, batch_size), DType::F32, self.probs.device())?;
-        self.probs = Tensor::zeros((self.size, batch_size), DType::F32, self.probs.device())?;
-        self.spikes = Tensor::zeros((self.size, batch_size), DType::F32, self.probs.device())?;
-        self.rng_vals = Tensor::zeros((self.size, batch_size), DType::F32, self.probs.device())?;
-        self.dummy_mod_signal =
-            Tensor::zeros((self.size, batch_size), DType::F32, self.probs.device())?;
+        self.inputs.resize(batch_size)?;
+        let zeros = self.inputs.zeros();
+        self.probs = zeros.clone();
+        self.spikes = zeros.clone();
+        self.rng_vals = zeros.clone();
+        self.dummy_mod_signal = zeros.clone();
         Ok(())
     }
 
@@ -94,4 +95,8 @@ impl Layer for BernoulliLayer {
     }
 
     fn set_reward(&mut self, _reward: &Tensor) {}
+
+    fn box_clone(&self) -> Box<dyn Layer> {
+        Box::new(self.clone())
+    }
 }
@@ -1,25 +1,61 @@
-use crate::layer::Layer;
+use crate::layer::{Integrator, Layer};
 use crate::layer::mod_signal::ModSignalGenerator;
+use crate::layer::scratch::InputCompartment;
 use candle_core::{DType, Device, Result as CandleResult, Tensor};
+use std::collections::HashMap;
+
+/// Adaptive firing threshold. On the CPU it is a plain float; on accelerators it stays a (1,)
+/// tensor on the device, so a step queues its kernels without waiting on a device-to-host copy
+/// of the spike count.
+#[derive(Clone)]
+enum Threshold {
+    Host(f32),
+    Device(Tensor),
+}
 
 #[allow(clippy::upper_case_acronyms)]
 pub struct LIFLayer {
     mod_signal: Box<dyn ModSignalGenerator>,
     /// input currents
-    inputs: Tensor,
+    inputs: InputCompartment,
     /// membrane potential
     state: Tensor,
+    /// integration step of the membrane potential in the last step
+    dv: Tensor,
     /// output spikes
     spikes: Tensor,
     /// current threshold value
-    thresh: f32,
+    thresh: Threshold,
     /// how fast threshold adapts
     thresh_lambda: f32,
     /// membrane time constant
     tau: f32,
+    integrator: Integrator,
     size: usize,
     current_label: Tensor,
     current_reward: Tensor,
+    /// target pattern the spikes are clamped to, (size, n) with n dividing the batch size
+    clamp: Option<Tensor>,
+}
+
+impl Clone for LIFLayer {
+    fn clone(&self) -> Self {
+        Self {
+            mod_signal: self.mod_signal.box_clone(),
+            inputs: self.inputs.clone(),
+            state: self.state.clone(),
+            dv: self.dv.clone(),
+            spikes: self.spikes.clone(),
+            thresh: self.thresh.clone(),
+            thresh_lambda: self.thresh_lambda,
+            tau: self.tau,
+            integrator: self.integrator,
+            size: self.size,
+            current_label: self.current_label.clone(),
+            current_reward: self.current_reward.clone(),
+            clamp: self.clamp.clone(),
+        }
+    }
 }
 
 impl LIFLayer {
@@ -31,50 +67,105 @@ impl LIFLayer {
         mod_signal_generator: Box<dyn ModSignalGenerator>,
         device: &Device,
     ) -> CandleResult<Self> {
-        let inputs = Tensor::zeros((size, 1), DType::F32, device)?;
-        let state = Tensor::zeros((size, 1), DType::F32, device)?;
-        let spikes = Tensor::zeros((size, 1), DType::F32, device)?;
+        let inputs = InputCompartment::new(size, 1, device)?;
+        let state = inputs.zeros().clone();
+        let dv = inputs.zeros().clone();
+        let spikes = inputs.zeros().clone();
+        let thresh = if device.is_cpu() {
+            Threshold::Host(thresh)
+        } else {
+            Threshold::Device(Tensor::new(&[thresh], device)?)
+        };
         Ok(Self {
             mod_signal: mod_signal_generator,
             inputs,
             state,
+            dv,
             spikes,
             tau,
+            integrator: Integrator::Euler,
             thresh,
             thresh_lambda,
             size,
             current_label: Tensor::ones((1, 1), DType::F32, device)?,
             current_reward: Tensor::zeros((1, 1), DType::F32, device)?,
+            clamp: None,
         })
     }
+
+    /// Keep the threshold as a device tensor even on the CPU, as accelerator layers do
+    pub fn keep_threshold_on_device(&mut self) -> CandleResult<()> {
+        if let Threshold::Host(thresh) = self.thresh {
+            self.thresh = Threshold::Device(Tensor::new(&[thresh], self.current_label.device())?);
+        }
+        Ok(())
+    }
 }
 
 impl Layer for LIFLayer {
     fn step(&mut self, dt: f32) -> CandleResult<()> {
-        let dv = (((dt / self.tau) as f64) * self.inputs.sub(&self.state)?)?;
-        self.state = self.state.add(&dv)?;
-        // spikes where state > thresh
-        self.spikes = self.state.gt(self.thresh)?.to_dtype(DType::F32)?;
-        self.state = self.state.sub(&((self.thresh as f64) * &self.spikes)?)?;
-
-        // adjust threshold adaptively
-        let batch_size = self.spikes.dims()[1];
-        self.thresh += dt
-            * self.thresh_lambda
-            * (self
-                .spikes
-                .sum_all()?
-                .to_device(&Device::Cpu)?
-                .to_scalar::<f32>()?
-                / batch_size as f32
-                - (self.size as f32 * 0.02)); // Target 2% firing rate
-
-        if self.thresh < 0.0 {
-            self.thresh = 0.0;
+        let rate = match self.integrator {
+            Integrator::Euler => dt / self.tau,
+            Integrator::ExponentialEuler => 1.0 - (-dt / self.tau).exp(),
+        };
+        self.dv = ((rate as f64) * self.inputs.get().sub(&self.state)?)?;
+        self.state = self.state.add(&self.dv)?;
+        let batch_size = self.state.dims()[1];
+        // Target 2% firing rate
+        let target = self.size as f32 * 0.02;
+        match &mut self.thresh {
+            Threshold::Host(thresh) => {
+                // spikes where state > thresh
+                self.spikes = self.state.gt(*thresh)?.to_dtype(DType::F32)?;
+                self.state = self.state.sub(&((*thresh as f64) * &self.spikes)?)?;
+
+                // adjust threshold adaptively
+                *thresh += dt
+                    * self.thresh_lambda
+                    * (self
+                        .spikes
+                        .sum_all()?
+                        .to_device(&Device::Cpu)?
+                        .to_scalar::<f32>()?
+                        / batch_size as f32
+                        - target);
+
+                if *thresh < 0.0 {
+                    *thresh = 0.0;
+                }
+            }
+            Threshold::Device(thresh) => {
+                self.spikes = self.state.broadcast_gt(thresh)?.to_dtype(DType::F32)?;
+                self.state = self.state.sub(&self.spikes.broadcast_mul(thresh)?)?;
+
+                let rate = self.spikes.sum_all()?.reshape(1)?;
+                let gain = (dt * self.thresh_lambda) as f64;
+                let delta = rate.affine(gain / batch_size as f64, -gain * target as f64)?;
+                *thresh = thresh.add(&delta)?.relu()?;
+            }
         }
 
-        let batch_size = self.spikes.dims()[1];
         let lab = self.current_label.broadcast_as((1, batch_size))?;
+        // Teacher forcing: positive samples spike with the target, negative samples with its
+        // complement, i.e. (1 - label) + target * (2 * label - 1). Membrane and threshold
+        // keep following the layer's own activity.
+        if let Some(target) = &self.clamp {
+            let n = target.dim(1)?;
+            if !batch_size.is_multiple_of(n) {
+                return Err(candle_core::Error::Msg(format!(
+                    "clamp target has {} columns, which don't divide the batch of {}",
+                    n, batch_size
+                )));
+            }
+            let target = if n == batch_size {
+                target.clone()
+            } else {
+                target.repeat((1, batch_size / n))?
+            };
+            self.spikes = target
+                .broadcast_mul(&lab.affine(2.0, -1.0)?)?
+                .broadcast_add(&lab.affine(-1.0, 1.0)?)?;
+        }
         let reward_expanded = self.current_reward.broadcast_as((1, batch_size))?;
         self.mod_signal
             .calc_mod_signal(&self.spikes, &lab, &reward_expanded, dt)?;
@@ -100,24 +191,32 @@ impl Layer for LIFLayer {
 
     /// Adds to the input compartment of the layer
     fn add_input(&mut self, input: &Tensor) -> CandleResult<()> {
-        self.inputs = self.inputs.add(input)?;
-        Ok(())
+        self.inputs.add(input)
     }
 
     /// resets input compartment to zero
     fn reset_input(&mut self) -> CandleResult<()> {
-        let batch_size = self.state.dims()[1];
-        self.inputs = Tensor::zeros((self.size, batch_size), DType::F32, self.state.device())?;
+        self.inputs.clear();
         Ok(())
     }
 
     /// resets internal state fully
     fn reset(&mut self, batch_size: usize) -> CandleResult<()> {
-        self.state = Tensor::zeros((self.size, batch_size), DType::F32, self.state.device())?;
-        self.spikes = Tensor::zeros((self.size, batch_size), DType::F32, self.state.device())?;
+        self.inputs.resize(batch_size)?;
+        self.state = self.inputs.zeros().clone();
+        self.dv = self.inputs.zeros().clone();
+        self.spikes = self.inputs.zeros().clone();
         Ok(())
     }
 
+    fn potential_change(&self) -> CandleResult<Option<Tensor>> {
+        Ok(Some(self.dv.abs()?.max_all()?))
+    }
+
+    fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+    }
+
     fn set_positive_sample(&mut self, label: &Tensor) {
         self.current_label = label.clone();
     }
@@ -125,4 +224,44 @@ impl Layer for LIFLayer {
     fn set_reward(&mut self, reward: &Tensor) {
         self.current_reward = reward.clone();
     }
+
+    fn clamp_output(&mut self, target: Option<&Tensor>) -> CandleResult<()> {
+        if let Some(target) = target.filter(|t| t.rank() != 2 || t.dims()[0] != self.size) {
+            return Err(candle_core::Error::Msg(format!(
+                "clamp target has shape {:?}, expected ({}, batch)",
+                target.dims(),
+                self.size
+            )));
+        }
+        self.clamp = target.cloned();
+        Ok(())
+    }
+
+    fn get_state(&self) -> CandleResult<HashMap<String, Tensor>> {
+        let device = self.current_label.device();
+        let mut state = HashMap::new();
+        let thresh = match &self.thresh {
+            Threshold::Host(thresh) => Tensor::new(&[*thresh], device)?,
+            Threshold::Device(thresh) => thresh.clone(),
+        };
+        state.insert("thresh".to_string(), thresh);
+        Ok(state)
+    }
+
+    fn set_state(&mut self, state: &HashMap<String, Tensor>) -> CandleResult<()> {
+        if let Some(thresh) = state.get("thresh") {
+            let value = thresh.flatten_all()?.to_vec1::<f32>()?[0];
+            self.thresh = match self.thresh {
+                Threshold::Host(_) => Threshold::Host(value),
+                Threshold::Device(_) => {
+                    Threshold::Device(Tensor::new(&[value], self.current_label.device())?)
+                }
+            };
+        }
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Layer> {
+        Box::new(self.clone())
+    }
 }
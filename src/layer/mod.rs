@@ -1,9 +1,15 @@
 pub mod bernoulli;
 pub mod lif;
 pub mod mod_signal;
+pub mod normalization;
 pub mod one_hot;
+pub mod predictive;
+pub mod scratch;
+pub mod topography;
 
 use candle_core::{Result as CandleResult, Tensor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 pub trait Layer: Send + Sync {
     /// update internal state and calculated output
@@ -36,10 +42,50 @@ pub trait Layer: Send + Sync {
 
     /// sets the environmental reward for the layer
     fn set_reward(&mut self, reward: &Tensor);
+
+    /// Clamp the spikes to `target`, (size, n) with n dividing the batch size, from the next
+    /// step on (teacher forcing); None releases the clamp. Layers that don't spike ignore it.
+    fn clamp_output(&mut self, _target: Option<&Tensor>) -> CandleResult<()> {
+        Ok(())
+    }
+
+    /// Largest membrane-potential change of the last step, excluding spike resets, as a
+    /// scalar tensor; used for adaptive timestep control. None for layers without a membrane
+    /// potential.
+    fn potential_change(&self) -> CandleResult<Option<Tensor>> {
+        Ok(None)
+    }
+
+    /// Scheme integrating the membrane potential over a step; layers without a membrane
+    /// potential ignore it
+    fn set_integrator(&mut self, _integrator: Integrator) {}
+
+    /// Learned state that is not part of any synapse (e.g. adaptive thresholds), for saving
+    fn get_state(&self) -> CandleResult<HashMap<String, Tensor>> {
+        Ok(HashMap::new())
+    }
+
+    /// Restore state produced by `get_state`
+    fn set_state(&mut self, _state: &HashMap<String, Tensor>) -> CandleResult<()> {
+        Ok(())
+    }
+
+    /// Independent copy of the layer, including its dynamic state
+    fn box_clone(&self) -> Box<dyn Layer>;
+}
+
+/// How a layer integrates its membrane potential `v` toward its input `I` over a step `dt`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Integrator {
+    /// `v += dt / tau * (I - v)`, which overshoots once `dt` approaches `tau`
+    #[default]
+    Euler,
+    /// `v += (1 - exp(-dt / tau)) * (I - v)`, exact for input held over the step
+    ExponentialEuler,
 }
 
 /// Position of a layer in visualization space
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct LayerPosition {
     pub x: f32,
     pub y: f32,
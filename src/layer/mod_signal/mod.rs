@@ -1,4 +1,5 @@
 pub mod multi_class;
+pub mod regularized;
 pub mod reward_modulated;
 pub mod standard;
 
@@ -21,4 +22,7 @@ pub trait ModSignalGenerator: Send + Sync {
 
     /// Retrieve the calculated modulatory signal.
     fn get_mod_signal(&self) -> &Tensor;
+
+    /// Independent copy of the generator, including its traces.
+    fn box_clone(&self) -> Box<dyn ModSignalGenerator>;
 }
@@ -3,6 +3,7 @@ use candle_core::{DType, Device, Result as CandleResult, Tensor};
 
 /// Calculates the modulatory goodness signal used for CSDP synapse adjustment.
 /// This uses the Multi-Class Contrastive approach (margin ranking loss).
+#[derive(Clone)]
 pub struct MultiClassModSignal {
     /// trace constant
     pub trace_tau: f32,
@@ -123,4 +124,8 @@ impl ModSignalGenerator for MultiClassModSignal {
     fn get_mod_signal(&self) -> &Tensor {
         &self.mod_signal
     }
+
+    fn box_clone(&self) -> Box<dyn ModSignalGenerator> {
+        Box::new(self.clone())
+    }
 }
@@ -0,0 +1,130 @@
+use super::ModSignalGenerator;
+use candle_core::{DType, Device, Result as CandleResult, Tensor};
+
+/// Penalties on a layer's activity, turned into modulatory signals so plasticity steers the
+/// layer away from runaway and synchronous firing.
+///
+/// - Rate: each neuron's firing rate is traced with time constant `rate_tau`; its deviation
+///   from `target_rate` depresses (or, below target, potentiates) the neuron's inputs.
+/// - Synchrony: in a step where more than `max_synchrony` of the layer fires together, the
+///   inputs of the neurons that fired are depressed in proportion to the excess.
+///
+/// A weight of 0 disables a penalty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActivityRegularizer {
+    /// firing probability per neuron and step the rate penalty pulls toward
+    pub target_rate: f32,
+    pub rate_weight: f32,
+    /// time constant of the per-neuron rate trace
+    pub rate_tau: f32,
+    /// fraction of the layer that may fire in the same step without penalty
+    pub max_synchrony: f32,
+    pub synchrony_weight: f32,
+}
+
+impl Default for ActivityRegularizer {
+    /// The 2% rate the adaptive thresholds of the LIF layers aim for, and at most a fifth of
+    /// a layer firing at once
+    fn default() -> Self {
+        Self {
+            target_rate: 0.02,
+            rate_weight: 0.01,
+            rate_tau: 20.0,
+            max_synchrony: 0.2,
+            synchrony_weight: 0.05,
+        }
+    }
+}
+
+/// Another generator's modulatory signal minus the gradients of an [`ActivityRegularizer`]'s
+/// penalties, since CSDP synapses move their weights along the modulatory signal
+pub struct RegularizedModSignal {
+    inner: Box<dyn ModSignalGenerator>,
+    pub regularizer: ActivityRegularizer,
+    /// per-neuron firing rate trace, (size, batch)
+    rate: Tensor,
+    mod_signal: Tensor,
+}
+
+impl Clone for RegularizedModSignal {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.box_clone(),
+            regularizer: self.regularizer,
+            rate: self.rate.clone(),
+            mod_signal: self.mod_signal.clone(),
+        }
+    }
+}
+
+impl RegularizedModSignal {
+    pub fn new(
+        inner: Box<dyn ModSignalGenerator>,
+        regularizer: ActivityRegularizer,
+        size: usize,
+        device: &Device,
+    ) -> CandleResult<Self> {
+        Ok(Self {
+            inner,
+            regularizer,
+            rate: Tensor::full(regularizer.target_rate, (size, 1), device)?,
+            mod_signal: Tensor::zeros((size, 1), DType::F32, device)?,
+        })
+    }
+
+    /// Per-neuron firing rate trace
+    pub fn rate(&self) -> &Tensor {
+        &self.rate
+    }
+}
+
+impl ModSignalGenerator for RegularizedModSignal {
+    fn calc_mod_signal(
+        &mut self,
+        spikes: &Tensor,
+        label: &Tensor,
+        reward: &Tensor,
+        dt: f32,
+    ) -> CandleResult<()> {
+        self.inner.calc_mod_signal(spikes, label, reward, dt)?;
+        let reg = self.regularizer;
+
+        let (size, batch_size) = spikes.dims2()?;
+        if self.rate.dims()[1] != batch_size {
+            // A new batch size starts a new sequence; the trace restarts at the target
+            self.rate = Tensor::full(reg.target_rate, (size, batch_size), spikes.device())?;
+        }
+        let drate = (((dt / reg.rate_tau) as f64) * spikes.sub(&self.rate)?)?;
+        self.rate = self.rate.add(&drate)?;
+
+        let mut signal = self.inner.get_mod_signal().clone();
+        if reg.rate_weight != 0.0 {
+            let excess = self.rate.affine(
+                reg.rate_weight as f64,
+                -(reg.rate_weight * reg.target_rate) as f64,
+            )?;
+            signal = signal.sub(&excess)?;
+        }
+        if reg.synchrony_weight != 0.0 {
+            // fraction of the layer firing in this step, (1, batch)
+            let excess = spikes
+                .mean_keepdim(0)?
+                .affine(1.0, -reg.max_synchrony as f64)?
+                .relu()?;
+            let penalty = spikes
+                .broadcast_mul(&excess)?
+                .affine(reg.synchrony_weight as f64, 0.0)?;
+            signal = signal.sub(&penalty)?;
+        }
+        self.mod_signal = signal;
+        Ok(())
+    }
+
+    fn get_mod_signal(&self) -> &Tensor {
+        &self.mod_signal
+    }
+
+    fn box_clone(&self) -> Box<dyn ModSignalGenerator> {
+        Box::new(self.clone())
+    }
+}
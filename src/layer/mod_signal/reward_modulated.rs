@@ -5,6 +5,7 @@ use candle_nn::ops::sigmoid;
 /// Calculates the modulatory goodness signal used for CSDP synapse adjustment.
 /// This calculates a cross-entropy loss that is modulated by the reward signal.
 /// C(z, y) = - [ y * log(p(y=1|z)) * R + (1-y) * log(p(y=0|z)) ]
+#[derive(Clone)]
 pub struct RewardModulatedModSignal {
     /// trace constant
     pub trace_tau: f32,
@@ -92,6 +93,13 @@ impl ModSignalGenerator for RewardModulatedModSignal {
         reward: &Tensor,
         dt: f32,
     ) -> CandleResult<()> {
+        let batch_size = spikes.dims()[1];
+        if self.z.dims()[1] != batch_size {
+            // A new batch size starts a new sequence, so the trace and loss restart
+            self.z = Tensor::zeros((self.z.dims()[0], batch_size), DType::F32, spikes.device())?;
+            self.prev_loss = Tensor::zeros((1, batch_size), DType::F32, spikes.device())?;
+        }
+
         let dz =
             (((dt / self.trace_tau) as f64) * (((self.max_z as f64) * spikes)?.sub(&self.z)?))?;
         self.z = self.z.add(&dz)?;
@@ -108,4 +116,8 @@ impl ModSignalGenerator for RewardModulatedModSignal {
     fn get_mod_signal(&self) -> &Tensor {
         &self.mod_signal
     }
+
+    fn box_clone(&self) -> Box<dyn ModSignalGenerator> {
+        Box::new(self.clone())
+    }
 }
@@ -4,6 +4,7 @@ use candle_nn::ops::sigmoid;
 
 /// Calculates the modulatory goodness signal used for CSDP synapse adjustment.
 /// This uses the standard Cross-Entropy approach and ignores external reward.
+#[derive(Clone)]
 pub struct StandardModSignal {
     /// trace constant
     pub trace_tau: f32,
@@ -79,6 +80,12 @@ impl ModSignalGenerator for StandardModSignal {
         _reward: &Tensor, // Reward is ignored for the standard case
         dt: f32,
     ) -> CandleResult<()> {
+        let batch_size = spikes.dims()[1];
+        if self.z.dims()[1] != batch_size {
+            // A new batch size starts a new sequence, so the trace restarts
+            self.z = Tensor::zeros((self.z.dims()[0], batch_size), DType::F32, spikes.device())?;
+        }
+
         let dz_dt =
             (((dt / self.trace_tau) as f64) * (((self.max_z as f64) * spikes)?.sub(&self.z)?))?;
         self.z = self.z.add(&dz_dt)?;
@@ -103,4 +110,8 @@ impl ModSignalGenerator for StandardModSignal {
     fn get_mod_signal(&self) -> &Tensor {
         &self.mod_signal
     }
+
+    fn box_clone(&self) -> Box<dyn ModSignalGenerator> {
+        Box::new(self.clone())
+    }
 }
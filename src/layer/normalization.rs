@@ -0,0 +1,227 @@
+use crate::layer::Layer;
+use crate::layer::scratch::InputCompartment;
+use candle_core::{DType, Device, Result as CandleResult, Tensor};
+use std::collections::HashMap;
+
+/// Added to the variance before dividing by the standard deviation
+const VARIANCE_EPS: f64 = 1e-6;
+
+/// Settings of a [`NormalizationLayer`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormalizationConfig {
+    /// standard deviations mapped to the ends of [0, 1]; inputs further out saturate
+    pub clip: f32,
+    /// cap on the number of samples the statistics weigh, so they keep tracking a drifting
+    /// signal instead of settling on its long-run average; None weighs every sample equally
+    pub max_count: Option<usize>,
+}
+
+impl Default for NormalizationConfig {
+    fn default() -> Self {
+        Self {
+            clip: 3.0,
+            max_count: None,
+        }
+    }
+}
+
+/// On-line input normalization, for raw signals such as live robot readings.
+///
+/// Keeps a running mean and variance of every input channel, updated from each step's batch
+/// with Welford's algorithm (in Chan's form for merging a batch), and outputs the normalized
+/// value as a firing probability: the running mean maps to 0.5, `clip` standard deviations to
+/// 0 and 1. Placed in front of a Bernoulli layer or a population encoder, it keeps signals with
+/// drifting offsets or scales from saturating the spike encoding. The statistics are learned
+/// state: `reset` keeps them, and they are saved with the model.
+#[derive(Clone)]
+pub struct NormalizationLayer {
+    /// normalized inputs, clipped to [0, 1]
+    probs: Tensor,
+    /// z-scores of the inputs
+    z: Tensor,
+    inputs: InputCompartment,
+    size: usize,
+    config: NormalizationConfig,
+    /// running mean, (size, 1)
+    mean: Tensor,
+    /// running sum of squared deviations from the mean, (size, 1)
+    m2: Tensor,
+    /// samples the statistics weigh
+    count: f64,
+    /// whether `step` updates the statistics
+    learning: bool,
+    dummy_mod_signal: Tensor,
+}
+
+impl NormalizationLayer {
+    pub fn new(size: usize, config: NormalizationConfig, device: &Device) -> CandleResult<Self> {
+        if !config.clip.is_finite() || config.clip <= 0.0 {
+            return Err(candle_core::Error::Msg(format!(
+                "normalization clip must be positive, got {}",
+                config.clip
+            )));
+        }
+        let inputs = InputCompartment::new(size, 1, device)?;
+        let stats = Tensor::zeros((size, 1), DType::F32, device)?;
+        Ok(Self {
+            probs: inputs.zeros().clone(),
+            z: inputs.zeros().clone(),
+            dummy_mod_signal: inputs.zeros().clone(),
+            inputs,
+            size,
+            config,
+            mean: stats.clone(),
+            m2: stats,
+            count: 0.0,
+            learning: true,
+        })
+    }
+
+    /// Freeze (`false`) or resume updating the statistics
+    pub fn set_learning(&mut self, learning: bool) {
+        self.learning = learning;
+    }
+
+    pub fn is_learning(&self) -> bool {
+        self.learning
+    }
+
+    pub fn config(&self) -> NormalizationConfig {
+        self.config
+    }
+
+    /// Running mean of every channel, (size, 1)
+    pub fn mean(&self) -> &Tensor {
+        &self.mean
+    }
+
+    /// Running variance of every channel, (size, 1); zero before any input
+    pub fn variance(&self) -> CandleResult<Tensor> {
+        self.m2.affine(1.0 / self.count.max(1.0), 0.0)
+    }
+
+    /// Samples the statistics weigh
+    pub fn count(&self) -> f64 {
+        self.count
+    }
+
+    /// Forget the statistics, e.g. after recalibrating the sensor
+    pub fn reset_statistics(&mut self) -> CandleResult<()> {
+        self.mean = self.mean.zeros_like()?;
+        self.m2 = self.m2.zeros_like()?;
+        self.count = 0.0;
+        Ok(())
+    }
+
+    /// Merge the columns of `x` into the statistics
+    fn update(&mut self, x: &Tensor) -> CandleResult<()> {
+        let n_b = x.dims()[1] as f64;
+        if n_b == 0.0 {
+            return Ok(());
+        }
+        let mut n_a = self.count;
+        if let Some(max_count) = self.config.max_count {
+            // Down-weight the history so it and the batch weigh at most `max_count` samples
+            let keep = (max_count as f64 - n_b).max(0.0);
+            if n_a > keep {
+                self.m2 = self.m2.affine(keep / n_a, 0.0)?;
+                n_a = keep;
+            }
+        }
+        let n = n_a + n_b;
+
+        let batch_mean = x.mean_keepdim(1)?;
+        let batch_m2 = x.broadcast_sub(&batch_mean)?.sqr()?.sum_keepdim(1)?;
+        let delta = batch_mean.sub(&self.mean)?;
+        self.mean = self.mean.add(&delta.affine(n_b / n, 0.0)?)?;
+        self.m2 = self
+            .m2
+            .add(&batch_m2)?
+            .add(&delta.sqr()?.affine(n_a * n_b / n, 0.0)?)?;
+        self.count = n;
+        Ok(())
+    }
+}
+
+impl Layer for NormalizationLayer {
+    fn step(&mut self, _dt: f32) -> CandleResult<()> {
+        let x = self.inputs.get().clone();
+        if self.learning {
+            self.update(&x)?;
+        }
+        let std = self.variance()?.affine(1.0, VARIANCE_EPS)?.sqrt()?;
+        self.z = x.broadcast_sub(&self.mean)?.broadcast_div(&std)?;
+        let half_width = 2.0 * self.config.clip as f64;
+        self.probs = self
+            .z
+            .affine(1.0 / half_width, 0.5)?
+            .clamp(0.0f32, 1.0f32)?;
+        Ok(())
+    }
+
+    fn activity(&self) -> CandleResult<&Tensor> {
+        Ok(&self.z)
+    }
+
+    fn get_mod_signal(&self) -> &Tensor {
+        &self.dummy_mod_signal
+    }
+
+    fn output(&self) -> CandleResult<&Tensor> {
+        Ok(&self.probs)
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn add_input(&mut self, input: &Tensor) -> CandleResult<()> {
+        self.inputs.add(input)
+    }
+
+    fn reset_input(&mut self) -> CandleResult<()> {
+        self.inputs.clear();
+        Ok(())
+    }
+
+    fn reset(&mut self, batch_size: usize) -> CandleResult<()> {
+        self.inputs.resize(batch_size)?;
+        self.probs = self.inputs.zeros().clone();
+        self.z = self.inputs.zeros().clone();
+        self.dummy_mod_signal = self.inputs.zeros().clone();
+        Ok(())
+    }
+
+    fn set_positive_sample(&mut self, _label: &Tensor) {}
+
+    fn set_reward(&mut self, _reward: &Tensor) {}
+
+    fn get_state(&self) -> CandleResult<HashMap<String, Tensor>> {
+        let mut state = HashMap::new();
+        state.insert("mean".to_string(), self.mean.clone());
+        state.insert("m2".to_string(), self.m2.clone());
+        state.insert(
+            "count".to_string(),
+            Tensor::new(&[self.count as f32], self.mean.device())?,
+        );
+        Ok(state)
+    }
+
+    fn set_state(&mut self, state: &HashMap<String, Tensor>) -> CandleResult<()> {
+        let device = self.mean.device().clone();
+        if let Some(mean) = state.get("mean") {
+            self.mean = mean.to_device(&device)?;
+        }
+        if let Some(m2) = state.get("m2") {
+            self.m2 = m2.to_device(&device)?;
+        }
+        if let Some(count) = state.get("count") {
+            self.count = count.to_dtype(DType::F64)?.to_vec1::<f64>()?[0];
+        }
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Layer> {
+        Box::new(self.clone())
+    }
+}
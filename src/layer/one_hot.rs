@@ -1,13 +1,15 @@
 use crate::layer::Layer;
+use crate::layer::scratch::InputCompartment;
 use candle_core::{DType, Device, Result as CandleResult, Tensor};
 
+#[derive(Clone)]
 pub struct OneHotLayer {
     /// current probabilities (for compatibility)
     probs: Tensor,
     /// output spikes
     spikes: Tensor,
     /// accumulated inputs
-    inputs: Tensor,
+    inputs: InputCompartment,
     /// total number of neurons (sum of all bounds)
     size: usize,
     /// max value for each variable
@@ -28,10 +30,10 @@ impl OneHotLayer {
             total_size += bound;
         }
 
-        let probs = Tensor::zeros((total_size, 1), DType::F32, device)?;
-        let spikes = Tensor::zeros((total_size, 1), DType::F32, device)?;
-        let inputs = Tensor::zeros((total_size, 1), DType::F32, device)?;
-        let dummy_mod_signal = Tensor::zeros((total_size, 1), DType::F32, device)?;
+        let inputs = InputCompartment::new(total_size, 1, device)?;
+        let probs = inputs.zeros().clone();
+        let spikes = inputs.zeros().clone();
+        let dummy_mod_signal = inputs.zeros().clone();
 
         Ok(Self {
             probs,
@@ -49,8 +51,8 @@ impl OneHotLayer {
 impl Layer for OneHotLayer {
     fn step(&mut self, _dt: f32) -> CandleResult<()> {
         // One-hot layer is deterministic: inputs are mapped to spikes directly
-        self.spikes = self.inputs.clone();
-        self.probs = self.inputs.clone();
+        self.spikes = self.inputs.get().clone();
+        self.probs = self.inputs.get().clone();
         Ok(())
     }
 
@@ -95,26 +97,24 @@ impl Layer for OneHotLayer {
             }
             let expanded_tensor =
                 Tensor::from_vec(expanded, (self.size, batch_size), input.device())?;
-            self.inputs = self.inputs.add(&expanded_tensor)?;
+            self.inputs.add(&expanded_tensor)
         } else {
             // standard addition if already expanded or from synapses
-            self.inputs = self.inputs.add(input)?;
+            self.inputs.add(input)
         }
-        Ok(())
     }
 
     fn reset_input(&mut self) -> CandleResult<()> {
-        let batch_size = self.probs.dims()[1];
-        self.inputs = Tensor::zeros((self.size, batch_size), DType::F32, self.probs.device())?;
+        self.inputs.clear();
         Ok(())
     }
 
     fn reset(&mut self, batch_size: usize) -> CandleResult<()> {
-        self.inputs = Tensor::zeros((self.size, batch_size), DType::F32, self.probs.device())?;
-        self.probs = Tensor::zeros((self.size, batch_size), DType::F32, self.probs.device())?;
-        self.spikes = Tensor::zeros((self.size, batch_size), DType::F32, self.probs.device())?;
-        self.dummy_mod_signal =
-            Tensor::zeros((self.size, batch_size), DType::F32, self.probs.device())?;
+        self.inputs.resize(batch_size)?;
+        let zeros = self.inputs.zeros();
+        self.probs = zeros.clone();
+        self.spikes = zeros.clone();
+        self.dummy_mod_signal = zeros.clone();
         Ok(())
     }
 
@@ -123,6 +123,10 @@ impl Layer for OneHotLayer {
     }
 
     fn set_reward(&mut self, _reward: &Tensor) {}
+
+    fn box_clone(&self) -> Box<dyn Layer> {
+        Box::new(self.clone())
+    }
 }
 
 #[cfg(test)]
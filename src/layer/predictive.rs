@@ -0,0 +1,76 @@
+use crate::layer::Layer;
+use crate::layer::scratch::InputCompartment;
+use candle_core::{Device, Result as CandleResult, Tensor};
+
+/// Rate layer holding the prediction of a [`crate::synapse::predictive::PredictiveSynapse`].
+///
+/// Its input is the synapse's estimate of the next timestep's activity of the layer the
+/// synapse reads from, and its output is that estimate clipped to [0, 1]. The layer has no
+/// plasticity of its own: the synapse learns from the prediction error with a delta rule, so
+/// the modulatory signal is always zero.
+#[derive(Clone)]
+pub struct PredictionLayer {
+    prediction: Tensor,
+    inputs: InputCompartment,
+    size: usize,
+    dummy_mod_signal: Tensor,
+}
+
+impl PredictionLayer {
+    pub fn new(size: usize, device: &Device) -> CandleResult<Self> {
+        let inputs = InputCompartment::new(size, 1, device)?;
+        Ok(Self {
+            prediction: inputs.zeros().clone(),
+            dummy_mod_signal: inputs.zeros().clone(),
+            inputs,
+            size,
+        })
+    }
+}
+
+impl Layer for PredictionLayer {
+    fn step(&mut self, _dt: f32) -> CandleResult<()> {
+        self.prediction = self.inputs.get().clamp(0.0f32, 1.0f32)?;
+        Ok(())
+    }
+
+    fn activity(&self) -> CandleResult<&Tensor> {
+        Ok(&self.prediction)
+    }
+
+    fn get_mod_signal(&self) -> &Tensor {
+        &self.dummy_mod_signal
+    }
+
+    fn output(&self) -> CandleResult<&Tensor> {
+        Ok(&self.prediction)
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn add_input(&mut self, input: &Tensor) -> CandleResult<()> {
+        self.inputs.add(input)
+    }
+
+    fn reset_input(&mut self) -> CandleResult<()> {
+        self.inputs.clear();
+        Ok(())
+    }
+
+    fn reset(&mut self, batch_size: usize) -> CandleResult<()> {
+        self.inputs.resize(batch_size)?;
+        self.prediction = self.inputs.zeros().clone();
+        self.dummy_mod_signal = self.inputs.zeros().clone();
+        Ok(())
+    }
+
+    fn set_positive_sample(&mut self, _label: &Tensor) {}
+
+    fn set_reward(&mut self, _reward: &Tensor) {}
+
+    fn box_clone(&self) -> Box<dyn Layer> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,67 @@
+use candle_core::{DType, Device, Result as CandleResult, Tensor};
+
+/// Input compartment backed by a cached zero tensor.
+///
+/// Candle tensors are immutable and reference counted, so one zero tensor per batch size can
+/// back every `reset_input` (and every `reset` of the layer state) instead of allocating fresh
+/// zeros each timestep. The first `add` after a `clear` adopts the incoming tensor rather than
+/// adding it to zeros, so a layer with a single incoming synapse allocates nothing for its input.
+/// Only inputs are pooled: spike and weight-update temporaries come from candle ops, which always
+/// allocate their outputs.
+#[derive(Clone)]
+pub struct InputCompartment {
+    size: usize,
+    zeros: Tensor,
+    inputs: Tensor,
+    is_zero: bool,
+}
+
+impl InputCompartment {
+    pub fn new(size: usize, batch_size: usize, device: &Device) -> CandleResult<Self> {
+        let zeros = Tensor::zeros((size, batch_size), DType::F32, device)?;
+        Ok(Self {
+            size,
+            inputs: zeros.clone(),
+            zeros,
+            is_zero: true,
+        })
+    }
+
+    /// Zero tensor of the current shape; cloning it does not allocate
+    pub fn zeros(&self) -> &Tensor {
+        &self.zeros
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.zeros.dims()[1]
+    }
+
+    /// Clear for a new batch, reallocating the zero tensor only if the batch size changed
+    pub fn resize(&mut self, batch_size: usize) -> CandleResult<()> {
+        if batch_size != self.batch_size() {
+            self.zeros = Tensor::zeros((self.size, batch_size), DType::F32, self.zeros.device())?;
+        }
+        self.clear();
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.inputs = self.zeros.clone();
+        self.is_zero = true;
+    }
+
+    pub fn add(&mut self, input: &Tensor) -> CandleResult<()> {
+        // A mismatched shape falls through to `add` so it still errors
+        self.inputs = if self.is_zero && input.shape() == self.zeros.shape() {
+            input.clone()
+        } else {
+            self.inputs.add(input)?
+        };
+        self.is_zero = false;
+        Ok(())
+    }
+
+    pub fn get(&self) -> &Tensor {
+        &self.inputs
+    }
+}
@@ -0,0 +1,45 @@
+//! Spatial arrangement of the neurons within a layer.
+//!
+//! A [`Topography`] gives every neuron of a layer a coordinate in the unit square. Each axis
+//! is scaled to `[0, 1]` independently of the number of neurons along it, so layers of
+//! different resolution cover the same field: neuron `(3, 3)` of an 8 x 8 grid lies over
+//! neuron `(1, 1)` of a 4 x 4 grid. Distance-dependent connectivity between layers
+//! ([`crate::synapse::topographic`]) is defined on these coordinates.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Topography {
+    /// `len` neurons evenly spaced along a horizontal line through the middle of the square
+    Line { len: usize },
+    /// `width` x `height` neurons on a grid, numbered row by row from the top left
+    Grid { width: usize, height: usize },
+}
+
+impl Topography {
+    /// Number of neurons the topography places
+    pub fn size(&self) -> usize {
+        match *self {
+            Topography::Line { len } => len,
+            Topography::Grid { width, height } => width * height,
+        }
+    }
+
+    /// `(x, y)` of every neuron, in neuron order; each neuron sits in the middle of its cell
+    pub fn coordinates(&self) -> Vec<(f32, f32)> {
+        let center = |i: usize, n: usize| (i as f32 + 0.5) / n as f32;
+        match *self {
+            Topography::Line { len } => (0..len).map(|i| (center(i, len), 0.5)).collect(),
+            Topography::Grid { width, height } => (0..height)
+                .flat_map(|row| {
+                    (0..width).map(move |col| (center(col, width), center(row, height)))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Euclidean distance between two coordinates
+pub fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
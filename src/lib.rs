@@ -1,10 +1,48 @@
+//! Spiking network framework built around CSDP (contrastive signal-dependent plasticity).
+//!
+//! The `custom_framework` binary and the tools under `src/tools` are all thin consumers of
+//! this library; external projects can depend on it the same way.
+//!
+//! On `wasm32` only the network core (layers, synapses, models) is built, plus the
+//! browser-facing API in [`wasm`]; robots, environments, algorithms and the TUI are native-only.
+//!
+//! The LeRobot arm (`robot` feature) and the training TUI (`gui` feature) are on by default.
+//! Build with `--no-default-features` for headless machines without serial or terminal support.
+
+#[cfg(not(target_arch = "wasm32"))]
 pub mod algorithms;
 pub mod dataset;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod environment;
+pub mod error;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod experiment;
 pub mod flat;
 pub mod layer;
 pub mod models;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod raster;
+pub mod repro;
+#[cfg(all(feature = "robot", not(target_arch = "wasm32")))]
 pub mod robot;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod run;
+pub mod seed;
+pub mod shaping;
+pub mod spike_metrics;
 pub mod synapse;
 pub mod utils;
 pub mod visualization;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use algorithms::Algorithm;
+#[cfg(not(target_arch = "wasm32"))]
+pub use environment::Environment;
+pub use error::CsdpError;
+pub use layer::Layer;
+pub use models::{Model, ModelConfig};
+pub use synapse::{LayerId, SynapseConnection, SynapseId, SynapseOps};
+pub use visualization::VisualizationState;
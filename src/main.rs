@@ -1,538 +1,643 @@
 #[cfg(feature = "mkl")]
 extern crate intel_mkl_src;
 
-use candle_core::Device;
+use candle_core::{Device, Tensor};
+use clap::{Args, Parser, Subcommand};
 use std::error::Error;
-use std::sync::{Arc, Mutex};
+use std::path::PathBuf;
 
-use custom_framework::algorithms;
-use custom_framework::environment;
+#[cfg(feature = "robot")]
+use custom_framework::dataset::trajectory::Trajectory;
+use custom_framework::experiment::{
+    self, EnvKind, TrainOptions, build_algorithm, load_config, make_environment, parse_device,
+};
+#[cfg(feature = "robot")]
+use custom_framework::robot::bilateral::{ForceFeedback, ForceFeedbackConfig};
+#[cfg(feature = "robot")]
+use custom_framework::robot::corpus::{Appended, Corpus, MANIFEST_FILE};
+#[cfg(feature = "robot")]
+use custom_framework::robot::envelope::{EnvelopeMode, SafetyEnvelope};
+#[cfg(feature = "robot")]
+use custom_framework::robot::interpolation::Interpolation;
+#[cfg(feature = "robot")]
+use custom_framework::robot::profile::RobotProfile;
+#[cfg(feature = "robot")]
+use custom_framework::robot::retarget::{RetargetMode, Retargeting};
+#[cfg(feature = "robot")]
+use custom_framework::robot::{realtime, routines};
+use custom_framework::run::RunDir;
 use custom_framework::visualization;
+#[cfg(feature = "gui")]
+use custom_framework::visualization::replay::SessionReplay;
 
-use algorithms::Algorithm;
-use algorithms::algorithm_csdp1::Algorithm1;
-use algorithms::algorithm_csdp2::Algorithm2;
-use algorithms::algorithm_csdp3::Algorithm3;
-use algorithms::algorithm_csdp4::Algorithm4;
-use algorithms::algorithm_csdp5::AlgorithmCSDP5;
-use algorithms::algorithm_csdp_ppo::AlgorithmCSDPPPO;
-use algorithms::algorithm_ff_multi1::AlgorithmFFMulti1;
-use algorithms::algorithm_ff_multi2::AlgorithmFFMulti2;
-use algorithms::algorithm_ff_ppo::AlgorithmFFPPO;
-use algorithms::algorithm_ff1::AlgorithmFF1;
-use algorithms::algorithm_ff2::AlgorithmFF2;
-use algorithms::algorithm_ff3::AlgorithmFF3;
-use algorithms::algorithm_ff4::AlgorithmFF4;
-use algorithms::algorithm_ffsac::AlgorithmFFSAC;
-use environment::Environment;
-use visualization::VisualizationState;
-
-struct VisLogger;
-impl log::Log for VisLogger {
-    fn enabled(&self, _metadata: &log::Metadata) -> bool {
-        true
-    }
-    fn log(&self, record: &log::Record) {
-        if self.enabled(record.metadata()) {
-            let msg = format!("[{}] {}", record.level(), record.args());
-            if let Ok(mut logs) = custom_framework::visualization::GLOBAL_LOGS.lock() {
-                logs.push(msg);
-                if logs.len() > 100 {
-                    logs.remove(0);
-                }
-            }
-            // Optional: Also print to stderr if visualizing? It would disrupt Ratatui, so no.
-        }
-    }
-    fn flush(&self) {}
+#[derive(Parser)]
+#[command(
+    name = "csdp",
+    about = "Train and run CSDP spiking networks and the LeRobot arm"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
 }
-static LOGGER: VisLogger = VisLogger;
 
-fn parse_args() -> (bool, String, String, bool, bool) {
-    let args: Vec<String> = std::env::args().collect();
-    let visualize = args.contains(&"--visualize".to_string()) || args.contains(&"-v".to_string());
+#[derive(Subcommand)]
+enum Command {
+    /// Train an algorithm on an environment
+    Train(TrainArgs),
+    /// Run a trained checkpoint greedily with learning disabled
+    Eval(EvalArgs),
+    /// Write a checkpoint's weights and layer parameters to .safetensors or .npz
+    Export(ExportArgs),
+    #[cfg(feature = "gui")]
+    /// Scrub through a session recorded with `train --record-session` in the TUI
+    Replay(ReplayArgs),
+    #[cfg(feature = "robot")]
+    /// Mirror a hand-moved leader arm onto the follower arm
+    Teleop(TeleopArgs),
+    #[cfg(feature = "robot")]
+    /// Record a demonstration trajectory to CSV
+    Record(RecordArgs),
+    #[cfg(feature = "robot")]
+    /// Replay a recorded trajectory on the robot
+    Playback(PlaybackArgs),
+    #[cfg(feature = "robot")]
+    /// Measure home offsets and joint limits and save them as a robot profile
+    Calibrate(CalibrateArgs),
+    #[cfg(feature = "robot")]
+    /// Build a joint-space safety envelope from recorded demonstrations
+    Envelope(EnvelopeArgs),
+    #[cfg(feature = "robot")]
+    /// Map recorded trajectories from one arm's calibration onto another's
+    Retarget(RetargetArgs),
+    #[cfg(feature = "robot")]
+    /// Add recorded episodes or whole sessions to a dataset, skipping duplicates
+    AppendData(AppendDataArgs),
+}
 
-    let mut env_type = "robot".to_string();
-    if args.contains(&"--grid".to_string()) {
-        env_type = "grid".to_string();
-    } else if args.contains(&"--rocketsim".to_string()) {
-        env_type = "rocketsim".to_string();
-    }
+/// Options shared by every subcommand that builds a model
+#[derive(Args)]
+struct ModelArgs {
+    /// Algorithm to run
+    #[arg(long, default_value = "csdp2")]
+    algo: String,
+    #[arg(long, value_enum, default_value_t = EnvKind::Robot)]
+    env: EnvKind,
+    /// `cpu`, `cuda` or `cuda:<ordinal>`
+    #[arg(long, default_value = "cuda:0")]
+    device: String,
+    /// JSON file overriding hidden sizes, dt and episode count
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Built-in profile name (`follower`, `leader`) or path to a profile JSON
+    #[arg(long, default_value = "follower")]
+    robot_profile: String,
+    /// Seed for weight init, spike sampling and host-side randomness
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Safety envelope JSON written by `envelope`; robot goals outside it are clamped or
+    /// rejected
+    #[arg(long)]
+    safety_envelope: Option<PathBuf>,
+    /// Fault rates JSON (see `robot::faults`); the robot environments' arm times out, drops
+    /// writes and reads stale positions at these rates
+    #[arg(long)]
+    fault_injection: Option<PathBuf>,
+}
 
-    let infinite_epochs = args.contains(&"--infinite-epochs".to_string());
-    let resume = args.contains(&"--resume".to_string());
+#[derive(Args)]
+struct TrainArgs {
+    #[command(flatten)]
+    model: ModelArgs,
+    /// Enable the Ratatui TUI with live training graphs and layer activity
+    #[arg(short, long)]
+    visualize: bool,
+    /// Run until interrupted
+    #[arg(long)]
+    infinite_epochs: bool,
+    /// Resume from the checkpoint (the algorithm's default location unless --checkpoint is given)
+    #[arg(long)]
+    resume: bool,
+    /// Checkpoint file or directory to resume from
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Save a checkpoint every N episodes (0 disables periodic saves)
+    #[arg(long)]
+    checkpoint_every: Option<usize>,
+    /// Number of periodic checkpoints to keep, besides the best one
+    #[arg(long)]
+    keep_last: Option<usize>,
+    /// Validate on a separate environment every N episodes and keep the best validated
+    /// checkpoint as `best/`
+    #[arg(long)]
+    validate_every: Option<usize>,
+    /// Greedy episodes per validation (default: 5)
+    #[arg(long)]
+    validation_episodes: Option<usize>,
+    /// Serve Prometheus metrics on this address (e.g. 0.0.0.0:9100)
+    #[arg(long)]
+    metrics_addr: Option<String>,
+    /// Write the run to a new timestamped directory under this one, named after the
+    /// algorithm; with --resume, continue the newest such run
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+    /// Record every snapshot the visualizer receives to this file, for `replay`
+    #[arg(long)]
+    record_session: Option<PathBuf>,
+    /// Show this checkpoint next to the live model in the visualizer's comparison view
+    #[arg(long)]
+    compare_checkpoint: Option<PathBuf>,
+    /// If the CUDA device fails mid-run, continue on the CPU from the state at the failure or
+    /// the newest checkpoint
+    #[arg(long)]
+    cpu_fallback: bool,
+    /// Don't check the scaling of the environment's states before training
+    #[arg(long)]
+    no_preflight: bool,
+}
 
-    let mut algo = "csdp2".to_string();
-    if let Some(idx) = args.iter().position(|r| r == "--algo")
-        && idx + 1 < args.len() {
-            algo = args[idx + 1].clone();
-        }
+#[derive(Args)]
+struct EvalArgs {
+    #[command(flatten)]
+    model: ModelArgs,
+    /// Checkpoint file or directory to evaluate
+    #[arg(long)]
+    checkpoint: PathBuf,
+    #[arg(long, default_value_t = 10)]
+    episodes: usize,
+}
 
-    (visualize, env_type, algo, infinite_epochs, resume)
+#[derive(Args)]
+struct ExportArgs {
+    #[command(flatten)]
+    model: ModelArgs,
+    /// Checkpoint file or directory to export
+    #[arg(long)]
+    checkpoint: PathBuf,
+    /// Output file; the format follows the extension (`.safetensors` or `.npz`)
+    #[arg(long)]
+    output: PathBuf,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    // let device = Device::Cpu;
-    let device = Device::new_cuda(0)?;
+#[cfg(feature = "gui")]
+#[derive(Args)]
+struct ReplayArgs {
+    /// Session file written by `train --record-session`
+    session: PathBuf,
+}
 
-    let (visualize, env_type, algo_choice, infinite_epochs, resume) = parse_args();
+#[cfg(feature = "robot")]
+#[derive(Args)]
+struct TeleopArgs {
+    #[arg(long, default_value = "leader")]
+    leader_profile: String,
+    #[arg(long, default_value = "/dev/ttyACM2")]
+    leader_port: String,
+    #[arg(long, default_value = "follower")]
+    follower_profile: String,
+    #[arg(long, default_value = "/dev/ttyACM1")]
+    follower_port: String,
+    /// Run the control loop at real-time thread priority (needs `CAP_SYS_NICE` on Linux)
+    #[arg(long)]
+    realtime: bool,
+    /// Feed the follower's loads back to the leader as a push-back the operator can feel
+    #[arg(long)]
+    force_feedback: bool,
+    /// Radians of push-back per unit of follower load beyond the deadband
+    #[arg(long, requires = "force_feedback")]
+    feedback_gain: Option<f64>,
+    /// Torque limit of the leader while feedback is on, in raw units out of 1000
+    #[arg(long, requires = "force_feedback")]
+    feedback_torque_limit: Option<u16>,
+}
 
+#[cfg(feature = "robot")]
+#[derive(Args)]
+struct RecordArgs {
+    #[arg(long, default_value = "leader")]
+    robot_profile: String,
+    /// Overrides the profile's serial port
+    #[arg(long)]
+    port: Option<String>,
+    #[arg(long, default_value = "data/training_data.csv")]
+    output: PathBuf,
+    /// Record several episodes into this directory, one CSV each plus `manifest.json`,
+    /// instead of a single `--output` file
+    #[arg(long)]
+    session: Option<PathBuf>,
+    /// Task label stored in the session manifest
+    #[arg(long, default_value = "demo")]
+    task: String,
+    /// Run the control loop at real-time thread priority (needs `CAP_SYS_NICE` on Linux)
+    #[arg(long)]
+    realtime: bool,
+}
+
+#[cfg(feature = "robot")]
+#[derive(Args)]
+struct PlaybackArgs {
+    #[arg(long, default_value = "leader")]
+    robot_profile: String,
+    /// Overrides the profile's serial port
+    #[arg(long)]
+    port: Option<String>,
+    #[arg(long, default_value = "data/training_data.csv")]
+    input: PathBuf,
+    /// How to move between recorded frames
+    #[arg(long, value_enum, default_value_t = Interpolation::Cubic)]
+    interpolation: Interpolation,
+    /// Rate goals are sent at when interpolating
+    #[arg(long, default_value_t = 50.0)]
+    rate_hz: f64,
+    /// Joint speed limit in rad/s; faster parts of the recording are slowed down
+    #[arg(long)]
+    max_velocity: Option<f64>,
+    /// Run the control loop at real-time thread priority (needs `CAP_SYS_NICE` on Linux)
+    #[arg(long)]
+    realtime: bool,
+}
+
+#[cfg(feature = "robot")]
+#[derive(Args)]
+struct CalibrateArgs {
+    /// Profile whose port and current limits are used to open the arm
+    #[arg(long, default_value = "follower")]
+    robot_profile: String,
+    /// Overrides the profile's serial port
+    #[arg(long)]
+    port: Option<String>,
+    #[arg(long, default_value = "profiles/robot.json")]
+    output: PathBuf,
+}
+
+#[cfg(feature = "robot")]
+#[derive(Args)]
+struct EnvelopeArgs {
+    /// Demonstration CSVs written by `record`
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    #[arg(long, default_value = "profiles/envelope.json")]
+    output: PathBuf,
+    /// Radians to widen every joint range by on both sides
+    #[arg(long, default_value_t = 0.0)]
+    margin: f64,
+    /// Factor on the demonstrated joint speeds
+    #[arg(long, default_value_t = 1.0)]
+    velocity_scale: f64,
+    /// What to do with goals outside the envelope
+    #[arg(long, value_enum, default_value_t = EnvelopeMode::Clamp)]
+    mode: EnvelopeMode,
+}
+
+#[cfg(feature = "robot")]
+#[derive(Args)]
+struct RetargetArgs {
+    /// Trajectory CSVs recorded on the source arm
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Profile the recordings were made with
+    #[arg(long, default_value = "leader")]
+    from: String,
+    /// Profile of the arm to replay or train on
+    #[arg(long, default_value = "follower")]
+    to: String,
+    #[arg(long, value_enum, default_value_t = RetargetMode::Angle)]
+    mode: RetargetMode,
+    /// Joints (1-6, as in the `j1`..`j6` columns) that turn the other way on the target
+    #[arg(long, value_delimiter = ',')]
+    flip: Vec<usize>,
+    /// Radians added to each of the six joints after mapping, e.g. `0,0,0,0,3.1416,0`
+    #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+    offsets: Option<Vec<f64>>,
+    /// Directory the retargeted CSVs are written to, under their input file names
+    #[arg(long, default_value = "data/retargeted")]
+    output_dir: PathBuf,
+}
+
+#[cfg(feature = "robot")]
+#[derive(Args)]
+struct AppendDataArgs {
+    /// Dataset directory; created with a new manifest if it has none
+    #[arg(long)]
+    dataset: PathBuf,
+    /// Episode CSVs, or session directories written by `record --session`
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Task label of a new dataset
+    #[arg(long, default_value = "demo")]
+    task: String,
+    /// Profile the episodes were recorded with, stored in a new dataset's manifest
+    #[arg(long, default_value = "leader")]
+    robot_profile: String,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+
+    // The TUI takes over the terminal, so logs go to its log panel
+    let visualize = match &cli.command {
+        Command::Train(args) => args.visualize,
+        #[cfg(feature = "gui")]
+        Command::Replay(_) => true,
+        _ => false,
+    };
     if visualize {
-        let _ = log::set_logger(&LOGGER);
-        log::set_max_level(log::LevelFilter::Info);
+        visualization::install_tui_logger();
     } else {
         env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
     }
 
-    let mut env: Box<dyn Environment> = if env_type == "grid" {
-        log::info!("Using Grid Environment.");
-        Box::new(environment::grid::GridEnvironment::new())
-    } else if env_type == "rocketsim" {
-        log::info!("Using RocketSim Environment.");
-        Box::new(environment::rocketsim::RocketSimEnvironment::new(5)) // tickskip=5
-    } else {
-        match environment::robot::RobotEnvironment::new() {
-            Ok(robot_env) => {
-                log::info!("Using physical Robot Environment.");
-                Box::new(robot_env)
-            }
-            Err(e) => {
-                log::info!(
-                    "Failed to construct RobotEnvironment: {}. Falling back to Grid Environment.",
-                    e
-                );
-                Box::new(environment::grid::GridEnvironment::new())
-            }
-        }
-    };
-
-    log::info!(
-        "Visualization: {}",
-        if visualize { "enabled" } else { "disabled" }
-    );
-    log::info!("Use --visualize or -v flag to enable visualization");
-
-    let state_size = env.state_size();
-    let action_size = env.action_size();
-    let state_bounds = env.state_bounds();
-    let dt = 0.1;
-
-    let mut algo1_opt = None;
-    let mut algo2_opt = None;
-    let mut algo3_opt = None;
-    let mut algo4_opt = None;
-    let mut algo5_opt = None;
-    let mut algo_ff1_opt = None;
-    let mut algo_ff2_opt = None;
-    let mut algo_ff3_opt = None;
-    let mut algo_ff4_opt = None;
-    let mut algo_ffsac_opt = None;
-    let mut algo_ff_multi1_opt = None;
-    let mut algo_ff_multi2_opt = None;
-    let mut algo_ff_ppo_opt: Option<(AlgorithmFFPPO, Option<Vec<(usize, f32)>>)> = None;
-    let mut algo_csdp_ppo_opt: Option<(AlgorithmCSDPPPO, Option<Vec<(usize, f32)>>)> = None;
-
-    let (n_episodes, snapshot_result, num_layers, num_synapses) = if algo_choice == "csdp1" {
-        log::info!("Using Algorithm CSDP1");
-        let mut algo = Algorithm1::new(
-            state_size,
-            action_size,
-            vec![256, 128],
-            dt,
-            device,
-            state_bounds,
-        )
-        .expect("Failed to create Algorithm1");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
-        }
-        let snap = algo.model.get_visualization_snapshot();
-        let eps = algo.n_episodes;
-        let layers = algo.model.layers.len();
-        let syns = algo.model.synapses.len();
-        algo1_opt = Some(algo);
-        (eps, snap, layers, syns)
-    } else if algo_choice == "csdp2" {
-        log::info!("Using Algorithm CSDP2");
-        let mut algo = Algorithm2::new(
-            state_size,
-            action_size,
-            vec![256, 128],
-            dt,
-            device,
-            state_bounds,
-        )
-        .expect("Failed to create Algorithm2");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
-        }
-        let snap = algo.model.get_visualization_snapshot();
-        let eps = algo.n_episodes;
-        let layers = algo.model.layers.len();
-        let syns = algo.model.synapses.len();
-        algo2_opt = Some(algo);
-        (eps, snap, layers, syns)
-    } else if algo_choice == "csdp3" {
-        log::info!("Using Algorithm CSDP3 (AC-CSDP)");
-        let mut algo = Algorithm3::new(
-            state_size,
-            action_size,
-            vec![256, 128],
-            dt,
-            device.clone(),
-            state_bounds.clone(),
-        )
-        .expect("Failed to create Algorithm3");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
-        }
-        let snap = algo.model.actor.get_visualization_snapshot();
-        let eps = algo.n_episodes;
-        let layers = algo.model.actor.layers.len() + algo.model.critic.layers.len();
-        let syns = algo.model.actor.synapses.len() + algo.model.critic.synapses.len();
-        algo3_opt = Some(algo);
-        (eps, snap, layers, syns)
-    } else if algo_choice == "csdp4" {
-        log::info!("Using Algorithm CSDP4");
-        let mut algo = Algorithm4::new(
-            state_size,
-            action_size,
-            vec![256, 128],
-            dt,
-            device.clone(),
-            state_bounds.clone(),
-        )
-        .expect("Failed to create Algorithm4");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
-        }
-        let snap = algo.model.get_visualization_snapshot();
-        let eps = algo.n_episodes;
-        let layers = algo.model.layers.len();
-        let syns = algo.model.synapses.len();
-        algo4_opt = Some(algo);
-        (eps, snap, layers, syns)
-    } else if algo_choice == "csdp5" {
-        log::info!("Using Algorithm CSDP5 (Multi-Class MC SNN)");
-        let mut algo = AlgorithmCSDP5::new(
-            state_size,
-            action_size,
-            vec![1000, 256],
-            dt,
-            device.clone(),
-            state_bounds.clone(),
-        )
-        .expect("Failed to create AlgorithmCSDP5");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
-        }
-        let snap = algo.model.get_visualization_snapshot();
-        let eps = algo.n_episodes;
-        let layers = algo.model.layers.len();
-        let syns = algo.model.synapses.len();
-        algo5_opt = Some(algo);
-        (eps, snap, layers, syns)
-    } else if algo_choice == "ff1" {
-        log::info!("Using Algorithm FF1 (FF Model - State/Action Iterator)");
-        let mut algo = AlgorithmFF1::new(state_size, action_size, vec![256, 128], device.clone())
-            .expect("Failed to create AlgorithmFF1");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
-        }
-        let snap = Err(candle_core::Error::Msg(
-            "FF Model has no visualization".to_string(),
-        ));
-        let eps = algo.n_episodes;
-        let layers = algo.model.layers.len();
-        let syns = 0;
-        algo_ff1_opt = Some(algo);
-        (eps, snap, layers, syns)
-    } else if algo_choice == "ff2" {
-        log::info!("Using Algorithm FF2 (FF Model - Transition Evaluator)");
-        let mut algo = AlgorithmFF2::new(state_size, action_size, vec![256, 128], device.clone())
-            .expect("Failed to create AlgorithmFF2");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
+    match cli.command {
+        Command::Train(args) => train(args),
+        Command::Eval(args) => eval(args),
+        Command::Export(args) => export(args),
+        #[cfg(feature = "gui")]
+        Command::Replay(args) => {
+            let replay = SessionReplay::load(&args.session)?;
+            log::info!("Replaying {} frames of {:?}", replay.len(), args.session);
+            visualization::replay_session(replay);
+            Ok(())
         }
-        let snap = Err(candle_core::Error::Msg(
-            "FF Model has no visualization".to_string(),
-        ));
-        let eps = algo.n_episodes;
-        let layers = algo.model.layers.len();
-        let syns = 0;
-        algo_ff2_opt = Some(algo);
-        (eps, snap, layers, syns)
-    } else if algo_choice == "ff3" {
-        log::info!("Using Algorithm FF3 (FF Model - Probabilistic Rank Trajectory)");
-        let mut algo = AlgorithmFF3::new(state_size, action_size, vec![256, 128], device.clone())
-            .expect("Failed to create AlgorithmFF3");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
-        }
-        let snap = Err(candle_core::Error::Msg(
-            "FF Model has no visualization".to_string(),
-        ));
-        let eps = algo.n_episodes;
-        let layers = algo.model.layers.len();
-        let syns = 0;
-        algo_ff3_opt = Some(algo);
-        (eps, snap, layers, syns)
-    } else if algo_choice == "ff4" {
-        log::info!("Using Algorithm FF4 (FF Model - Temporal Contrastive RL)");
-        let mut algo = AlgorithmFF4::new(state_size, action_size, vec![256, 128], device.clone())
-            .expect("Failed to create AlgorithmFF4");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
-        }
-        let snap = Err(candle_core::Error::Msg(
-            "FF Model has no visualization".to_string(),
-        ));
-        let eps = algo.n_episodes;
-        let layers = algo.model.layers.len();
-        let syns = 0;
-        algo_ff4_opt = Some(algo);
-        (eps, snap, layers, syns)
-    } else if algo_choice == "ffsac" {
-        log::info!("Using Algorithm FFSAC (FF Model - Soft Actor-Critic)");
-        let mut algo = AlgorithmFFSAC::new(state_size, action_size, vec![256, 128], device.clone())
-            .expect("Failed to create AlgorithmFFSAC");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
-        }
-        let snap = Err(candle_core::Error::Msg(
-            "FF Model has no visualization".to_string(),
-        ));
-        let eps = algo.n_episodes;
-        let layers = algo.model.layers.len();
-        let syns = 0;
-        algo_ffsac_opt = Some(algo);
-        (eps, snap, layers, syns)
-    } else if algo_choice == "ff_multi1" {
-        log::info!("Using Algorithm FF Multi 1 (FF Multi Model - Temporal Contrastive RL)");
-        let mut algo =
-            AlgorithmFFMulti1::new(state_size, action_size, vec![256, 128], device.clone())
-                .expect("Failed to create AlgorithmFFMulti1");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
-        }
-        let snap = Err(candle_core::Error::Msg(
-            "FF Model has no visualization".to_string(),
-        ));
-        let eps = algo.n_episodes;
-        let layers = algo.model.layers.len();
-        let syns = 0;
-        algo_ff_multi1_opt = Some(algo);
-        (eps, snap, layers, syns)
-    } else if algo_choice == "ff_multi2" {
-        log::info!("Using Algorithm FF Multi 2 (Classification-Based RL)");
-        let mut algo =
-            AlgorithmFFMulti2::new(state_size, action_size, vec![512, 256, 128], device.clone())
-                .expect("Failed to create AlgorithmFFMulti2");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
-        }
-        // Resume from checkpoint if --resume and a checkpoint exists.
-        let mut restored_rewards = None;
-        if resume {
-            let cp_dir = std::path::Path::new("checkpoints/ff_multi2");
-            if cp_dir.join("training_state.json").exists() {
-                match algo.load_checkpoint(cp_dir) {
-                    Ok(rewards) => {
-                        log::info!("Resumed from checkpoint (episode {})", algo.start_episode);
-                        restored_rewards = Some(rewards);
-                    }
-                    Err(e) => {
-                        log::error!("Failed to load checkpoint: {}. Starting fresh.", e);
-                    }
-                }
+        #[cfg(feature = "robot")]
+        Command::Teleop(args) => {
+            let mut leader = RobotProfile::resolve(&args.leader_profile)?
+                .with_port(Some(args.leader_port))
+                .connect()?;
+            let mut follower = RobotProfile::resolve(&args.follower_profile)?
+                .with_port(Some(args.follower_port))
+                .connect()?;
+            let feedback = if args.force_feedback {
+                let defaults = ForceFeedbackConfig::default();
+                Some(ForceFeedback::new(ForceFeedbackConfig {
+                    gain: args.feedback_gain.unwrap_or(defaults.gain),
+                    torque_limit: args.feedback_torque_limit.unwrap_or(defaults.torque_limit),
+                    ..defaults
+                })?)
             } else {
-                log::info!("No checkpoint found at {:?}. Starting fresh.", cp_dir);
-            }
-        }
-        let snap = Err(candle_core::Error::Msg(
-            "FF Model has no visualization".to_string(),
-        ));
-        let eps = algo.n_episodes;
-        let layers = algo.main_model.layers.len();
-        let syns = 0;
-        algo_ff_multi2_opt = Some((algo, restored_rewards));
-        (eps, snap, layers, syns)
-    } else if algo_choice == "ff_ppo" {
-        log::info!("Using Algorithm FF PPO (PPO with Forward-Forward Models)");
-        let mut algo = AlgorithmFFPPO::new(state_size, action_size, device.clone())
-            .expect("Failed to create AlgorithmFFPPO");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
+                None
+            };
+            elevate_priority(args.realtime);
+            Ok(routines::teleoperate(&mut leader, &mut follower, feedback)?)
         }
-        let mut restored_rewards = None;
-        if resume {
-            let cp_dir = std::path::Path::new("checkpoints/ff_ppo");
-            if cp_dir.join("training_state.json").exists() {
-                match algo.load_checkpoint(cp_dir) {
-                    Ok(rewards) => {
-                        log::info!("Resumed from checkpoint (episode {})", algo.start_episode);
-                        restored_rewards = Some(rewards);
-                    }
-                    Err(e) => {
-                        log::error!("Failed to load checkpoint: {}. Starting fresh.", e);
-                    }
+        #[cfg(feature = "robot")]
+        Command::Record(args) => {
+            let profile = RobotProfile::resolve(&args.robot_profile)?.with_port(args.port);
+            let mut robot = profile.connect()?;
+            elevate_priority(args.realtime);
+            match &args.session {
+                Some(dir) => {
+                    routines::record_session(
+                        &mut robot,
+                        dir,
+                        &args.task,
+                        &args.robot_profile,
+                        &profile,
+                    )?;
+                    Ok(())
                 }
-            } else {
-                log::info!("No checkpoint found at {:?}. Starting fresh.", cp_dir);
+                None => Ok(routines::record(&mut robot, &args.output)?),
             }
         }
-        let snap = Err(candle_core::Error::Msg(
-            "FF Model has no visualization".to_string(),
-        ));
-        let eps = algo.n_episodes;
-        let layers = algo.policy_model.layers.len() + algo.value_model.layers.len();
-        let syns = 0;
-        algo_ff_ppo_opt = Some((algo, restored_rewards));
-        (eps, snap, layers, syns)
-    } else if algo_choice == "csdp_ppo" {
-        log::info!("Using Algorithm CSDP PPO (PPO with CSDP Spiking Q-Function)");
-        let mut algo = AlgorithmCSDPPPO::new(
-            state_size,
-            action_size,
-            device.clone(),
-            dt,
-        )
-        .expect("Failed to create AlgorithmCSDPPPO");
-        if infinite_epochs {
-            algo.n_episodes = usize::MAX - 1;
+        #[cfg(feature = "robot")]
+        Command::Playback(args) => {
+            let profile = RobotProfile::resolve(&args.robot_profile)?.with_port(args.port);
+            let mut robot = profile.connect()?;
+            let options = routines::PlaybackOptions {
+                interpolation: args.interpolation,
+                rate_hz: args.rate_hz,
+                max_velocity: args.max_velocity,
+            };
+            elevate_priority(args.realtime);
+            Ok(routines::playback(
+                &mut robot,
+                &args.input,
+                &profile,
+                &options,
+            )?)
         }
-        let mut restored_rewards = None;
-        if resume {
-            let cp_dir = std::path::Path::new("checkpoints/csdp_ppo");
-            if cp_dir.join("training_state.json").exists() {
-                match algo.load_checkpoint(cp_dir) {
-                    Ok(rewards) => {
-                        log::info!("Resumed from checkpoint (episode {})", algo.start_episode);
-                        restored_rewards = Some(rewards);
-                    }
-                    Err(e) => {
-                        log::error!("Failed to load checkpoint: {}. Starting fresh.", e);
-                    }
-                }
-            } else {
-                log::info!("No checkpoint found at {:?}. Starting fresh.", cp_dir);
-            }
+        #[cfg(feature = "robot")]
+        Command::Calibrate(args) => {
+            let profile = RobotProfile::resolve(&args.robot_profile)?.with_port(args.port);
+            let mut robot = profile.connect()?;
+            let calibrated = routines::calibrate(&mut robot, &profile.port)?;
+            calibrated.save(&args.output)?;
+            log::info!("Saved calibrated profile to {:?}", args.output);
+            Ok(())
         }
-        let snap = algo.policy_model.get_visualization_snapshot();
-        let eps = algo.n_episodes;
-        let layers = algo.policy_model.layers.len() + algo.value_model.layers.len();
-        let syns = algo.policy_model.synapses.len() + algo.value_model.synapses.len();
-        algo_csdp_ppo_opt = Some((algo, restored_rewards));
-        (eps, snap, layers, syns)
-    } else {
-        panic!("Unknown algorithm choice: {}", algo_choice);
-    };
+        #[cfg(feature = "robot")]
+        Command::Retarget(args) => retarget(args),
+        #[cfg(feature = "robot")]
+        Command::AppendData(args) => append_data(args),
+        #[cfg(feature = "robot")]
+        Command::Envelope(args) => {
+            let envelope = SafetyEnvelope::from_csvs(&args.inputs)?
+                .with_margin(args.margin)
+                .with_velocity_scale(args.velocity_scale)
+                .with_mode(args.mode);
+            envelope.save(&args.output)?;
+            log::info!(
+                "Saved the envelope of {} demonstrations to {:?}: min {:?}, max {:?}, max velocities {:?}",
+                args.inputs.len(),
+                args.output,
+                envelope.min_positions,
+                envelope.max_positions,
+                envelope.max_velocities
+            );
+            Ok(())
+        }
+    }
+}
 
-    log::info!("layers len: {}, num_synapses: {}", num_layers, num_synapses);
+#[cfg(feature = "robot")]
+fn retarget(args: RetargetArgs) -> Result<(), Box<dyn Error>> {
+    let source = RobotProfile::resolve(&args.from)?;
+    let target = RobotProfile::resolve(&args.to)?;
+    let flips = args
+        .flip
+        .iter()
+        .map(|&joint| match joint {
+            1..=6 => Ok(joint - 1),
+            _ => Err(format!("--flip takes joints 1 to 6, got {}", joint)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut retargeting = Retargeting::new(&source, &target, args.mode).with_flips(&flips)?;
+    if let Some(offsets) = &args.offsets {
+        let offsets: [f64; 6] = offsets
+            .as_slice()
+            .try_into()
+            .map_err(|_| format!("--offsets takes 6 values, got {}", offsets.len()))?;
+        retargeting = retargeting.with_offsets(offsets);
+    }
 
-    // Start visualization if requested
-    let vis_handle = if visualize {
-        let vis_state = Arc::new(Mutex::new(VisualizationState::new(n_episodes)));
+    std::fs::create_dir_all(&args.output_dir)?;
+    for input in &args.inputs {
+        let Some(name) = input.file_name() else {
+            return Err(format!("{:?} is not a file", input).into());
+        };
+        let output = args.output_dir.join(name);
+        let trajectory = Trajectory::load(input)?;
+        retargeting.apply(&trajectory)?.save(&output)?;
+        log::info!(
+            "Retargeted {} frames from {} to {}: {:?}",
+            trajectory.len(),
+            args.from,
+            args.to,
+            output
+        );
+    }
+    Ok(())
+}
 
-        // Initialize model structure
-        if let Ok(mut state) = vis_state.lock() {
-            if let Ok(snapshot) = snapshot_result {
+#[cfg(feature = "robot")]
+fn append_data(args: AppendDataArgs) -> Result<(), Box<dyn Error>> {
+    let profile = RobotProfile::resolve(&args.robot_profile)?;
+    let mut corpus =
+        Corpus::open_or_create(&args.dataset, &args.task, &args.robot_profile, &profile)?;
+    let before = corpus.len();
+    let mut duplicates = 0;
+    for input in &args.inputs {
+        let outcomes = if input.join(MANIFEST_FILE).exists() {
+            corpus.append_session(input)?
+        } else {
+            vec![corpus.append_file(input)?]
+        };
+        for outcome in outcomes {
+            if let Appended::Duplicate { index } = outcome {
                 log::info!(
-                    "Initial snapshot: {} layers, {} synapses",
-                    snapshot.layers.len(),
-                    snapshot.synapses.len()
+                    "Skipped an episode of {:?}: duplicate of episode {}",
+                    input,
+                    index
                 );
-                state.update_from_snapshot(snapshot);
-            } else {
-                log::info!("Warning: Failed to get initial visualization snapshot");
+                duplicates += 1;
             }
         }
+    }
+    log::info!(
+        "Added {} episodes to {:?} ({} duplicates skipped), {} in total",
+        corpus.len() - before,
+        args.dataset,
+        duplicates,
+        corpus.len()
+    );
+    Ok(())
+}
 
-        let handle = visualization::start_visualization(vis_state.clone());
-        Some((handle, vis_state))
-    } else {
-        None
-    };
-
-    let vis_state_arg = vis_handle.as_ref().map(|(_, state)| state.clone());
-
-    if let Some(mut algo) = algo1_opt {
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
-    } else if let Some(mut algo) = algo2_opt {
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
-    } else if let Some(mut algo) = algo3_opt {
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
-    } else if let Some(mut algo) = algo4_opt {
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
-    } else if let Some(mut algo) = algo5_opt {
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
-    } else if let Some(mut algo) = algo_ff1_opt {
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
-    } else if let Some(mut algo) = algo_ff2_opt {
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
-    } else if let Some(mut algo) = algo_ff3_opt {
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
-    } else if let Some(mut algo) = algo_ff4_opt {
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
-    } else if let Some(mut algo) = algo_ffsac_opt {
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
-    } else if let Some(mut algo) = algo_ff_multi1_opt {
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
-    } else if let Some((mut algo, restored_rewards)) = algo_ff_multi2_opt {
-        // If we resumed from a checkpoint, inject the restored reward history
-        // into the visualization state so the graph picks up where it left off.
-        if let Some(rewards) = restored_rewards
-            && let Some(ref vs) = vis_state_arg
-                && let Ok(mut state) = vs.lock() {
-                    state.epoch_rewards = rewards;
-                }
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
-    } else if let Some((mut algo, restored_rewards)) = algo_ff_ppo_opt {
-        if let Some(rewards) = restored_rewards
-            && let Some(ref vs) = vis_state_arg
-                && let Ok(mut state) = vs.lock() {
-                    state.epoch_rewards = rewards;
-                }
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
-    } else if let Some((mut algo, restored_rewards)) = algo_csdp_ppo_opt {
-        if let Some(rewards) = restored_rewards
-            && let Some(ref vs) = vis_state_arg
-                && let Ok(mut state) = vs.lock()
-        {
-            state.epoch_rewards = rewards;
-        }
-        algo.run(env.as_mut(), visualize, vis_state_arg)?;
+/// Move the control loop to real-time priority if `enabled`, or warn and go on without it
+#[cfg(feature = "robot")]
+fn elevate_priority(enabled: bool) {
+    if !enabled {
+        return;
+    }
+    match realtime::elevate_priority() {
+        Ok(()) => log::info!("Running at real-time priority"),
+        Err(e) => log::warn!("Could not elevate to real-time priority: {}", e),
     }
+}
 
-    if let Some((_, ref vis_state_arc)) = vis_handle {
-        loop {
-            let should_close = vis_state_arc
-                .try_lock()
-                .map(|state| state.should_close)
-                .unwrap_or(false);
-            if should_close {
-                break;
-            }
-            std::thread::sleep(std::time::Duration::from_millis(50));
-        }
+fn eval(args: EvalArgs) -> Result<(), Box<dyn Error>> {
+    let device = parse_device(&args.model.device)?;
+    let config = load_config(args.model.config.as_deref())?;
+    if let Some(seed) = args.model.seed.or(config.seed) {
+        custom_framework::seed::set_global_seed(seed, &device)?;
+        log::info!("Seeded run with {}", seed);
     }
+    let mut env = make_environment(
+        args.model.env,
+        &args.model.robot_profile,
+        args.model
+            .safety_envelope
+            .as_deref()
+            .or(config.safety_envelope.as_deref()),
+        args.model
+            .fault_injection
+            .as_deref()
+            .or(config.fault_injection.as_deref()),
+    )?;
 
-    // Signal visualization to close and wait for thread
-    if let Some((handle, vis_state)) = vis_handle {
-        log::info!("Closing visualization...");
-        while let Ok(state) = vis_state.lock() {
-            if state.should_close {
-                break;
-            }
-            std::thread::sleep(std::time::Duration::from_millis(100));
-        }
+    let mut built = build_algorithm(&args.model.algo, env.as_ref(), device, &config, None)?;
+    built.algo.restore(&args.checkpoint)?;
+    log::info!("Loaded checkpoint {:?}", args.checkpoint);
 
-        let _ = handle.join();
-    }
+    let rewards = built.algo.evaluate(env.as_mut(), args.episodes)?;
+    let mean = rewards.iter().sum::<f32>() / rewards.len().max(1) as f32;
+    log::info!("Mean reward over {} episodes: {:.3}", rewards.len(), mean);
 
-    // drop env cleans up whatever depends on drops, e.g. RobotEnvironment::disable()
     drop(env);
+    Ok(())
+}
+
+fn export(args: ExportArgs) -> Result<(), Box<dyn Error>> {
+    let npz = match args.output.extension().and_then(|e| e.to_str()) {
+        Some("npz") => true,
+        Some("safetensors") => false,
+        _ => return Err("--output must end in .safetensors or .npz".into()),
+    };
+    let device = parse_device(&args.model.device)?;
+    let config = load_config(args.model.config.as_deref())?;
+    let env = make_environment(args.model.env, &args.model.robot_profile, None, None)?;
+
+    let mut built = build_algorithm(&args.model.algo, env.as_ref(), device, &config, None)?;
+    built.algo.restore(&args.checkpoint)?;
+    let tensors = built
+        .algo
+        .named_tensors()?
+        .into_iter()
+        .map(|(name, tensor)| Ok((name, tensor.to_device(&Device::Cpu)?)))
+        .collect::<Result<Vec<_>, candle_core::Error>>()?;
 
-    log::info!("Done");
+    if npz {
+        Tensor::write_npz(&tensors, &args.output)?;
+    } else {
+        let map: std::collections::HashMap<_, _> = tensors.iter().cloned().collect();
+        candle_core::safetensors::save(&map, &args.output)?;
+    }
+    for (name, tensor) in &tensors {
+        log::info!("{} {:?}", name, tensor.dims());
+    }
+    log::info!("Exported {} tensors to {:?}", tensors.len(), args.output);
+
+    drop(env);
     Ok(())
 }
+
+fn train(args: TrainArgs) -> Result<(), Box<dyn Error>> {
+    let device = parse_device(&args.model.device)?;
+    let mut config = load_config(args.model.config.as_deref())?;
+    config.seed = args.model.seed.or(config.seed);
+    config.safety_envelope = args.model.safety_envelope.or(config.safety_envelope);
+    config.fault_injection = args.model.fault_injection.or(config.fault_injection);
+    log::info!("Use --visualize or -v flag to enable visualization");
+
+    let run_dir = match &args.output_dir {
+        Some(output_dir) if args.resume => Some(
+            RunDir::latest(output_dir, &args.model.algo)?.ok_or_else(|| {
+                format!("no {} run in {:?} to resume", args.model.algo, output_dir)
+            })?,
+        ),
+        Some(output_dir) => Some(RunDir::create(output_dir, &args.model.algo)?),
+        None => None,
+    };
+    if let Some(run_dir) = &run_dir {
+        log::info!("Run directory: {:?}", run_dir.path());
+    }
+
+    let options = TrainOptions {
+        algo: args.model.algo,
+        env: args.model.env,
+        robot_profile: args.model.robot_profile,
+        visualize: args.visualize,
+        infinite_epochs: args.infinite_epochs,
+        resume: args.resume,
+        checkpoint: args.checkpoint,
+        checkpoint_every: args.checkpoint_every,
+        keep_last: args.keep_last,
+        validate_every: args.validate_every,
+        validation_episodes: args.validation_episodes,
+        metrics_addr: args.metrics_addr,
+        run_dir,
+        record_session: args.record_session,
+        compare_checkpoint: args.compare_checkpoint,
+        cpu_fallback: args.cpu_fallback,
+        skip_preflight: args.no_preflight,
+    };
+    experiment::train(options, &config, device)
+}
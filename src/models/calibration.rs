@@ -0,0 +1,376 @@
+//! Readout calibration of a frozen model.
+//!
+//! The standard way to evaluate features learned without supervision is to keep the network
+//! fixed and fit only a linear readout on top of it. [`readout_features`] runs a frozen
+//! [`Model`] on a batch and returns, per sample, the spike rates of the layers that feed the
+//! output layer. [`LinearReadout::fit`] fits class scores to one-hot labels on these rates,
+//! either by ridge regression or by the delta rule, and [`LinearReadout::apply`] writes the
+//! result into the output synapses, so the spiking output layer ranks the classes by the
+//! fitted scores. Nothing else in the model changes.
+
+use super::Model;
+use crate::error::{CsdpError, Result};
+use crate::synapse::{LayerId, SynapseId};
+use candle_core::{DType, Device, Result as CandleResult, Tensor};
+use rand::seq::SliceRandom;
+
+/// How [`LinearReadout::fit`] finds the weights
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReadoutFit {
+    /// Ridge regression of the one-hot labels on the rates, solved in closed form; `ridge`
+    /// penalizes the squared weights (not the biases)
+    LeastSquares { ridge: f64 },
+    /// Widrow-Hoff delta rule, one sample at a time in shuffled order
+    Delta { epochs: usize, learning_rate: f64 },
+}
+
+impl Default for ReadoutFit {
+    fn default() -> Self {
+        ReadoutFit::LeastSquares { ridge: 1e-3 }
+    }
+}
+
+/// The enabled synapses into the output layer and the layers they read, in synapse order
+pub fn readout_layers(model: &Model) -> Vec<(SynapseId, LayerId)> {
+    let output = model.layers.len() - 1;
+    model
+        .synapses
+        .iter()
+        .map(|s| &s.metadata)
+        .filter(|m| m.post_layer == output && m.enabled)
+        .map(|m| (m.id, m.pre_layer))
+        .collect()
+}
+
+/// Spike rates of the readout layers (see [`readout_layers`]) over `timesteps` steps of
+/// `input`, (input_size, batch), one row per sample with the layers concatenated. The model is
+/// reset first; disable its learning to keep it frozen.
+pub fn readout_features(
+    model: &mut Model,
+    input: &Tensor,
+    timesteps: usize,
+) -> CandleResult<Vec<Vec<f32>>> {
+    let layers = readout_layers(model);
+    let batch_size = input.dims().get(1).copied().unwrap_or(1);
+    model.reset(batch_size)?;
+    let mut sums = layers
+        .iter()
+        .map(|&(_, id)| {
+            Tensor::zeros(
+                (model.layers[id].size(), batch_size),
+                DType::F32,
+                &model.device,
+            )
+        })
+        .collect::<CandleResult<Vec<_>>>()?;
+    for _ in 0..timesteps {
+        model.step(input, None)?;
+        for (sum, &(_, id)) in sums.iter_mut().zip(&layers) {
+            *sum = sum.add(&model.layers[id].output()?.to_device(&model.device)?)?;
+        }
+    }
+    Tensor::cat(&sums, 0)?
+        .affine(1.0 / timesteps.max(1) as f64, 0.0)?
+        .t()?
+        .to_vec2::<f32>()
+}
+
+/// Class scores as an affine function of the readout rates
+#[derive(Debug, Clone)]
+pub struct LinearReadout {
+    num_classes: usize,
+    num_features: usize,
+    /// (num_classes, num_features + 1), row-major, the last column holding the biases
+    weights: Vec<f64>,
+}
+
+impl LinearReadout {
+    /// Fit the readout to `features` (one row per sample, e.g. from [`readout_features`]) and
+    /// their class `labels`
+    pub fn fit(
+        features: &[Vec<f32>],
+        labels: &[usize],
+        num_classes: usize,
+        fit: ReadoutFit,
+    ) -> Result<Self> {
+        let num_features = features.first().map_or(0, Vec::len);
+        if features.is_empty() || features.len() != labels.len() {
+            return Err(CsdpError::Data(format!(
+                "need one label per sample, got {} samples and {} labels",
+                features.len(),
+                labels.len()
+            )));
+        }
+        if let Some(row) = features.iter().find(|row| row.len() != num_features) {
+            return Err(CsdpError::Data(format!(
+                "expected {} features per sample, got {}",
+                num_features,
+                row.len()
+            )));
+        }
+        if let Some(label) = labels.iter().find(|&&l| l >= num_classes) {
+            return Err(CsdpError::Data(format!(
+                "label {} is out of range for {} classes",
+                label, num_classes
+            )));
+        }
+        let weights = match fit {
+            ReadoutFit::LeastSquares { ridge } => {
+                if !ridge.is_finite() || ridge < 0.0 {
+                    return Err(CsdpError::Config(format!(
+                        "ridge must be finite and non-negative, got {}",
+                        ridge
+                    )));
+                }
+                least_squares(features, labels, num_classes, ridge)?
+            }
+            ReadoutFit::Delta {
+                epochs,
+                learning_rate,
+            } => {
+                if epochs == 0 || !learning_rate.is_finite() || learning_rate <= 0.0 {
+                    return Err(CsdpError::Config(format!(
+                        "the delta rule needs at least one epoch and a positive learning \
+                         rate, got {} and {}",
+                        epochs, learning_rate
+                    )));
+                }
+                delta_rule(features, labels, num_classes, epochs, learning_rate)
+            }
+        };
+        Ok(Self {
+            num_classes,
+            num_features,
+            weights,
+        })
+    }
+
+    pub fn num_classes(&self) -> usize {
+        self.num_classes
+    }
+
+    pub fn num_features(&self) -> usize {
+        self.num_features
+    }
+
+    /// Score of every class for one sample
+    pub fn scores(&self, features: &[f32]) -> Vec<f64> {
+        self.weights
+            .chunks_exact(self.num_features + 1)
+            .map(|row| {
+                let (bias, weights) = row.split_last().unwrap();
+                bias + weights
+                    .iter()
+                    .zip(features)
+                    .map(|(w, &x)| w * x as f64)
+                    .sum::<f64>()
+            })
+            .collect()
+    }
+
+    /// Class with the highest score
+    pub fn predict(&self, features: &[f32]) -> usize {
+        self.scores(features)
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map_or(0, |(class, _)| class)
+    }
+
+    /// Fraction of `features` whose predicted class is their label
+    pub fn accuracy(&self, features: &[Vec<f32>], labels: &[usize]) -> f64 {
+        let correct = features
+            .iter()
+            .zip(labels)
+            .filter(|(row, label)| self.predict(row) == **label)
+            .count();
+        correct as f64 / features.len().max(1) as f64
+    }
+
+    /// Weights of the readout, (num_classes, num_features), and biases, (num_classes, 1), on
+    /// `device`
+    pub fn tensors(&self, device: &Device) -> CandleResult<(Tensor, Tensor)> {
+        let columns = self.num_features + 1;
+        let weights: Vec<f32> = self
+            .weights
+            .chunks_exact(columns)
+            .flat_map(|row| row[..self.num_features].iter().map(|&w| w as f32))
+            .collect();
+        let biases: Vec<f32> = self
+            .weights
+            .chunks_exact(columns)
+            .map(|row| row[self.num_features] as f32)
+            .collect();
+        Ok((
+            Tensor::from_vec(weights, (self.num_classes, self.num_features), device)?,
+            Tensor::from_vec(biases, (self.num_classes, 1), device)?,
+        ))
+    }
+
+    /// Write the readout, scaled by `gain`, into the output synapses of `model`, which must
+    /// be the model the features came from: each synapse gets the columns of the layer it
+    /// reads, the first one also the biases and the others zero biases. Returns the synapses
+    /// changed.
+    pub fn apply(&self, model: &mut Model, gain: f32) -> Result<Vec<SynapseId>> {
+        let layers = readout_layers(model);
+        let sizes: Vec<usize> = layers
+            .iter()
+            .map(|&(_, id)| model.layers[id].size())
+            .collect();
+        let output = model.layers.len() - 1;
+        if sizes.iter().sum::<usize>() != self.num_features
+            || model.layers[output].size() != self.num_classes
+        {
+            return Err(CsdpError::Config(format!(
+                "the readout maps {} features to {} classes, the model's output layer reads \
+                 {} neurons and has {}",
+                self.num_features,
+                self.num_classes,
+                sizes.iter().sum::<usize>(),
+                model.layers[output].size()
+            )));
+        }
+        let device = model.layer_device(output).clone();
+        let (weights, biases) = self.tensors(&device)?;
+        let weights = weights.affine(gain as f64, 0.0)?;
+        let biases = biases.affine(gain as f64, 0.0)?;
+
+        let mut offset = 0;
+        for (i, (&(id, _), &size)) in layers.iter().zip(&sizes).enumerate() {
+            let conn = model
+                .synapses
+                .iter_mut()
+                .find(|s| s.metadata.id == id)
+                .unwrap();
+            let mut state = conn.synapse.get_state()?;
+            let dense =
+                |key: &str, dims: &[usize]| state.get(key).is_some_and(|t| t.dims() == dims);
+            if !dense("weights", &[self.num_classes, size])
+                || !dense("biases", &[self.num_classes, 1])
+            {
+                return Err(CsdpError::Config(format!(
+                    "output synapse {} ({}) has no dense weights to calibrate",
+                    id, conn.metadata.synapse_type
+                )));
+            }
+            state.insert("weights".to_string(), weights.narrow(1, offset, size)?);
+            let bias = if i == 0 {
+                biases.clone()
+            } else {
+                biases.zeros_like()?
+            };
+            state.insert("biases".to_string(), bias);
+            conn.synapse.set_state(&state)?;
+            offset += size;
+        }
+        // feedback tied to the output synapses follows them
+        model.sync_tied_weights()?;
+        Ok(layers.into_iter().map(|(id, _)| id).collect())
+    }
+}
+
+/// `(num_classes, num_features + 1)` weights minimizing the squared error to the one-hot
+/// labels plus `ridge` times the squared weights
+fn least_squares(
+    features: &[Vec<f32>],
+    labels: &[usize],
+    num_classes: usize,
+    ridge: f64,
+) -> Result<Vec<f64>> {
+    let n = features[0].len() + 1;
+    let rows: Vec<f64> = features
+        .iter()
+        .flat_map(|row| row.iter().map(|&x| x as f64).chain([1.0]))
+        .collect();
+    let targets: Vec<f64> = labels
+        .iter()
+        .flat_map(|&l| (0..num_classes).map(move |c| if c == l { 1.0 } else { 0.0 }))
+        .collect();
+    let x = Tensor::from_vec(rows, (features.len(), n), &Device::Cpu)?;
+    let y = Tensor::from_vec(targets, (labels.len(), num_classes), &Device::Cpu)?;
+    let xt = x.t()?.contiguous()?;
+    let mut gram = xt.matmul(&x)?.flatten_all()?.to_vec1::<f64>()?;
+    for i in 0..n - 1 {
+        gram[i * n + i] += ridge;
+    }
+    let mut solution = xt.matmul(&y)?.flatten_all()?.to_vec1::<f64>()?;
+    if !solve_spd(&mut gram, n, &mut solution, num_classes) {
+        return Err(CsdpError::Data(
+            "the readout rates are linearly dependent; use a positive ridge".to_string(),
+        ));
+    }
+    // (n, num_classes) -> (num_classes, n)
+    Ok((0..num_classes)
+        .flat_map(|c| {
+            (0..n)
+                .map(|i| solution[i * num_classes + c])
+                .collect::<Vec<_>>()
+        })
+        .collect())
+}
+
+/// Solve `a x = b` in place for every column of `b`, (n, m) row-major, where `a`, (n, n)
+/// row-major, is symmetric positive definite; `a` is overwritten with its Cholesky factor.
+/// False if `a` isn't positive definite.
+fn solve_spd(a: &mut [f64], n: usize, b: &mut [f64], m: usize) -> bool {
+    for j in 0..n {
+        let original = a[j * n + j];
+        let diagonal = original - (0..j).map(|k| a[j * n + k].powi(2)).sum::<f64>();
+        // Relative to the entry, to tell rounding error from a positive pivot
+        if diagonal.is_nan() || diagonal <= 1e-10 * original.abs() {
+            return false;
+        }
+        let diagonal = diagonal.sqrt();
+        a[j * n + j] = diagonal;
+        for i in j + 1..n {
+            let dot: f64 = (0..j).map(|k| a[i * n + k] * a[j * n + k]).sum();
+            a[i * n + j] = (a[i * n + j] - dot) / diagonal;
+        }
+    }
+    for c in 0..m {
+        // L y = b
+        for i in 0..n {
+            let dot: f64 = (0..i).map(|k| a[i * n + k] * b[k * m + c]).sum();
+            b[i * m + c] = (b[i * m + c] - dot) / a[i * n + i];
+        }
+        // L^T x = y
+        for i in (0..n).rev() {
+            let dot: f64 = (i + 1..n).map(|k| a[k * n + i] * b[k * m + c]).sum();
+            b[i * m + c] = (b[i * m + c] - dot) / a[i * n + i];
+        }
+    }
+    true
+}
+
+/// `(num_classes, num_features + 1)` weights from the delta rule, starting at zero
+fn delta_rule(
+    features: &[Vec<f32>],
+    labels: &[usize],
+    num_classes: usize,
+    epochs: usize,
+    learning_rate: f64,
+) -> Vec<f64> {
+    let columns = features[0].len() + 1;
+    let mut weights = vec![0.0; num_classes * columns];
+    let mut order: Vec<usize> = (0..features.len()).collect();
+    let mut rng = crate::seed::rng();
+    for _ in 0..epochs {
+        order.shuffle(&mut rng);
+        for &sample in &order {
+            let x: Vec<f64> = features[sample]
+                .iter()
+                .map(|&v| v as f64)
+                .chain([1.0])
+                .collect();
+            for (class, row) in weights.chunks_exact_mut(columns).enumerate() {
+                let target = if class == labels[sample] { 1.0 } else { 0.0 };
+                let score: f64 = row.iter().zip(&x).map(|(w, v)| w * v).sum();
+                let step = learning_rate * (target - score);
+                for (w, v) in row.iter_mut().zip(&x) {
+                    *w += step * v;
+                }
+            }
+        }
+    }
+    weights
+}
@@ -0,0 +1,227 @@
+//! Elastic weight consolidation, for learning tasks one after another.
+//!
+//! While a task is trained, [`Consolidation`] sums the squares of the plasticity updates of
+//! every weight, a local stand-in for the Fisher information of EWC: weights the rule keeps
+//! pushing matter to the task. [`Consolidation::consolidate`] ends the task, normalizes the sums
+//! to [0, 1] per tensor, adds them to the importance of earlier tasks and anchors the weights at
+//! their current values. From then on every plasticity step is followed by pulling each weight
+//! back toward its anchor by the fraction `min(strength * importance, 1)`, so important weights
+//! barely move while unimportant ones stay free to learn the new task.
+
+use crate::synapse::SynapseConnection;
+use candle_core::{Result as CandleResult, Tensor};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConsolidationConfig {
+    /// fraction of its deviation from the anchor a weight of importance 1 loses every step; 1
+    /// pins the most important weights of every consolidated task
+    pub strength: f32,
+}
+
+impl Default for ConsolidationConfig {
+    fn default() -> Self {
+        Self { strength: 0.5 }
+    }
+}
+
+/// Importance and anchor of one plastic tensor, from the tasks consolidated so far
+#[derive(Clone)]
+struct Anchor {
+    importance: Tensor,
+    /// `min(strength * importance, 1)`
+    pull: Tensor,
+    weights: Tensor,
+}
+
+#[derive(Clone)]
+pub struct Consolidation {
+    pub config: ConsolidationConfig,
+    /// squared updates summed over the current task, per synapse and plastic tensor
+    estimate: Vec<Vec<Tensor>>,
+    /// plasticity steps summed into `estimate`
+    steps: usize,
+    /// per synapse and plastic tensor; empty before the first consolidation
+    anchors: Vec<Vec<Anchor>>,
+}
+
+impl Consolidation {
+    pub fn new(config: ConsolidationConfig) -> Self {
+        Self {
+            config,
+            estimate: Vec::new(),
+            steps: 0,
+            anchors: Vec::new(),
+        }
+    }
+
+    /// Plasticity steps of the current task so far
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Whether a consolidation has anchored the weights; before it nothing is protected
+    pub fn is_anchored(&self) -> bool {
+        !self.anchors.is_empty()
+    }
+
+    /// Consolidated importance of the plastic tensors of synapse `index`, in the order of
+    /// [`crate::synapse::SynapseOps::plastic_tensors_mut`]; empty before the first consolidation
+    pub fn importance(&self, index: usize) -> Vec<&Tensor> {
+        self.anchors
+            .get(index)
+            .map(|tensors| tensors.iter().map(|a| &a.importance).collect())
+            .unwrap_or_default()
+    }
+
+    /// The plastic tensors of every synapse before a plasticity step, for [`Self::after_update`]
+    pub fn before_update(&self, synapses: &mut [SynapseConnection]) -> Vec<Vec<Tensor>> {
+        synapses
+            .iter_mut()
+            .map(|conn| {
+                conn.synapse
+                    .plastic_tensors_mut()
+                    .into_iter()
+                    .map(|t| t.clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Fold the updates of a plasticity step into the importance estimate, then pull every
+    /// weight toward its anchor. Frozen and disabled synapses are left alone.
+    pub fn after_update(
+        &mut self,
+        synapses: &mut [SynapseConnection],
+        before: Vec<Vec<Tensor>>,
+    ) -> CandleResult<()> {
+        self.estimate.resize_with(synapses.len(), Vec::new);
+        for (i, (conn, before)) in synapses.iter_mut().zip(before).enumerate() {
+            if !conn.metadata.is_learning || !conn.metadata.enabled {
+                continue;
+            }
+            let anchors = self.anchors.get(i);
+            let estimate = &mut self.estimate[i];
+            for (j, (tensor, old)) in conn
+                .synapse
+                .plastic_tensors_mut()
+                .into_iter()
+                .zip(before)
+                .enumerate()
+            {
+                let squared = tensor.sub(&old)?.sqr()?;
+                match estimate.get_mut(j) {
+                    Some(sum) => *sum = sum.add(&squared)?,
+                    None => estimate.push(squared),
+                }
+                if let Some(anchor) = anchors.and_then(|a| a.get(j)) {
+                    let pulled = tensor.sub(&anchor.weights)?.mul(&anchor.pull)?;
+                    *tensor = tensor.sub(&pulled)?;
+                }
+            }
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// End the current task: add its normalized importance to that of earlier tasks, anchor
+    /// every weight at its current value and start a new estimate
+    pub fn consolidate(&mut self, synapses: &mut [SynapseConnection]) -> CandleResult<()> {
+        let mut anchors = Vec::with_capacity(synapses.len());
+        for (i, conn) in synapses.iter_mut().enumerate() {
+            let mut tensors = Vec::new();
+            for (j, weights) in conn.synapse.plastic_tensors_mut().into_iter().enumerate() {
+                let task = match self.estimate.get(i).and_then(|e| e.get(j)) {
+                    Some(sum) => normalized(sum)?,
+                    None => weights.zeros_like()?,
+                };
+                let importance = match self.anchors.get(i).and_then(|a| a.get(j)) {
+                    Some(previous) => previous.importance.add(&task)?,
+                    None => task,
+                };
+                tensors.push(self.anchor(importance, weights.clone())?);
+            }
+            anchors.push(tensors);
+        }
+        self.anchors = anchors;
+        self.estimate.clear();
+        self.steps = 0;
+        Ok(())
+    }
+
+    /// Forget every consolidated task and the current estimate
+    pub fn clear(&mut self) {
+        self.anchors.clear();
+        self.estimate.clear();
+        self.steps = 0;
+    }
+
+    fn anchor(&self, importance: Tensor, weights: Tensor) -> CandleResult<Anchor> {
+        let pull = importance
+            .affine(self.config.strength as f64, 0.0)?
+            .clamp(0.0f32, 1.0f32)?;
+        Ok(Anchor {
+            importance,
+            pull,
+            weights,
+        })
+    }
+
+    /// Importance and anchors as `<synapse>_<tensor>_importance` and `<synapse>_<tensor>_anchor`
+    pub fn get_state(&self) -> HashMap<String, Tensor> {
+        let mut state = HashMap::new();
+        for (i, tensors) in self.anchors.iter().enumerate() {
+            for (j, anchor) in tensors.iter().enumerate() {
+                let key = format!("{}_{}", i, j);
+                state.insert(format!("{}_importance", key), anchor.importance.clone());
+                state.insert(format!("{}_anchor", key), anchor.weights.clone());
+            }
+        }
+        state
+    }
+
+    /// Restore the state of [`Self::get_state`] onto the devices of the plastic tensors of
+    /// `synapses`; an empty state leaves nothing anchored
+    pub fn set_state(
+        &mut self,
+        synapses: &mut [SynapseConnection],
+        state: &HashMap<String, Tensor>,
+    ) -> CandleResult<()> {
+        self.clear();
+        if state.is_empty() {
+            return Ok(());
+        }
+        let mut anchors = Vec::with_capacity(synapses.len());
+        for (i, conn) in synapses.iter_mut().enumerate() {
+            let mut tensors = Vec::new();
+            for (j, weights) in conn.synapse.plastic_tensors_mut().into_iter().enumerate() {
+                let get = |what: &str| {
+                    let key = format!("{}_{}_{}", i, j, what);
+                    state
+                        .get(&key)
+                        .ok_or_else(|| {
+                            candle_core::Error::Msg(format!(
+                                "{} tensor missing from consolidation state",
+                                key
+                            ))
+                        })?
+                        .to_device(weights.device())
+                };
+                tensors.push(self.anchor(get("importance")?, get("anchor")?)?);
+            }
+            anchors.push(tensors);
+        }
+        self.anchors = anchors;
+        Ok(())
+    }
+}
+
+/// `sum` divided by its largest entry, or zeros if it has none above zero
+fn normalized(sum: &Tensor) -> CandleResult<Tensor> {
+    let max = sum.flatten_all()?.max(0)?.to_scalar::<f32>()?;
+    if max > 0.0 {
+        sum.affine(1.0 / max as f64, 0.0)
+    } else {
+        sum.zeros_like()
+    }
+}
@@ -0,0 +1,134 @@
+//! Context-dependent gating, for one network that holds several tasks.
+//!
+//! Every task gets its own random subset of the neurons of each hidden layer. While a task is
+//! the current context (see [`super::Model::set_context`]), the neurons outside its subset
+//! receive a constant inhibitory input that keeps them from spiking; they then carry no
+//! modulatory signal either, so training one task leaves most of the weights of the others
+//! alone. Switching the context at inference time switches the behaviour the network shows.
+
+use crate::synapse::LayerId;
+use candle_core::{Device, Result as CandleResult, Tensor};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContextGatingConfig {
+    pub num_tasks: usize,
+    /// fraction of every hidden layer a task keeps active
+    pub keep: f32,
+    /// strength of the constant inhibitory input of the gated neurons
+    pub inhibition: f32,
+}
+
+impl ContextGatingConfig {
+    pub fn new(num_tasks: usize) -> Self {
+        Self {
+            num_tasks,
+            keep: 0.5,
+            inhibition: 5.0,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ContextGating {
+    pub config: ContextGatingConfig,
+    /// per gated layer, its input under every task, (size, num_tasks): 0 for the neurons the
+    /// task keeps and `-inhibition` for the rest
+    gates: Vec<(LayerId, Tensor)>,
+    task: usize,
+    /// the current task's column of every gate, (size, 1)
+    active: Vec<(LayerId, Tensor)>,
+}
+
+impl ContextGating {
+    /// Draw a random subset of every layer in `layers` (id, size and device) for every task;
+    /// task 0 starts as the context
+    pub fn new(
+        config: ContextGatingConfig,
+        layers: &[(LayerId, usize, Device)],
+    ) -> CandleResult<Self> {
+        let valid_keep = config.keep > 0.0 && config.keep <= 1.0;
+        if config.num_tasks == 0 || !valid_keep || !config.inhibition.is_finite() {
+            return Err(candle_core::Error::Msg(format!(
+                "context gating needs at least one task, keep in (0, 1] and a finite \
+                 inhibition, got {}, {} and {}",
+                config.num_tasks, config.keep, config.inhibition
+            )));
+        }
+        let mut rng = crate::seed::rng();
+        let mut gates = Vec::with_capacity(layers.len());
+        for (id, size, device) in layers {
+            let kept = ((config.keep * *size as f32).round() as usize).clamp(1, *size);
+            // Row-major (size, num_tasks)
+            let mut gate = vec![-config.inhibition; size * config.num_tasks];
+            for task in 0..config.num_tasks {
+                for neuron in rand::seq::index::sample(&mut rng, *size, kept).into_vec() {
+                    gate[neuron * config.num_tasks + task] = 0.0;
+                }
+            }
+            let gate = Tensor::from_vec(gate, (*size, config.num_tasks), device)?;
+            gates.push((*id, gate));
+        }
+        let mut gating = Self {
+            config,
+            gates,
+            task: 0,
+            active: Vec::new(),
+        };
+        gating.set_task(0)?;
+        Ok(gating)
+    }
+
+    pub fn task(&self) -> usize {
+        self.task
+    }
+
+    pub fn set_task(&mut self, task: usize) -> CandleResult<()> {
+        if task >= self.config.num_tasks {
+            return Err(candle_core::Error::Msg(format!(
+                "task {} is out of range for {} tasks",
+                task, self.config.num_tasks
+            )));
+        }
+        self.active = self
+            .gates
+            .iter()
+            .map(|(id, gate)| Ok((*id, gate.narrow(1, task, 1)?.contiguous()?)))
+            .collect::<CandleResult<_>>()?;
+        self.task = task;
+        Ok(())
+    }
+
+    /// Input of every gated layer under the current task, (size, 1)
+    pub fn active(&self) -> &[(LayerId, Tensor)] {
+        &self.active
+    }
+
+    /// Gates as `layer_<id>`
+    pub fn get_state(&self) -> HashMap<String, Tensor> {
+        self.gates
+            .iter()
+            .map(|(id, gate)| (format!("layer_{}", id), gate.clone()))
+            .collect()
+    }
+
+    /// Restore the gates of [`Self::get_state`]; each must have the shape of the gate it
+    /// replaces. Gates missing from `state` are kept.
+    pub fn set_state(&mut self, state: &HashMap<String, Tensor>) -> CandleResult<()> {
+        for (id, gate) in self.gates.iter_mut() {
+            let Some(saved) = state.get(&format!("layer_{}", id)) else {
+                continue;
+            };
+            if saved.dims() != gate.dims() {
+                return Err(candle_core::Error::Msg(format!(
+                    "saved context gate of layer {} has shape {:?}, expected {:?}",
+                    id,
+                    saved.dims(),
+                    gate.dims()
+                )));
+            }
+            *gate = saved.to_device(gate.device())?;
+        }
+        self.set_task(self.task)
+    }
+}
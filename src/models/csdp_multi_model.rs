@@ -1,3 +1,5 @@
+use super::parallel;
+use crate::error::Result;
 use crate::layer::bernoulli::BernoulliLayer;
 use crate::layer::lif::LIFLayer;
 use crate::layer::mod_signal::multi_class::MultiClassModSignal;
@@ -15,6 +17,7 @@ pub struct CSDPMultiModel {
     pub device: Device,
     pub num_classes: usize,
     pub timesteps: usize,
+    synapse_groups: parallel::SynapseGroups,
 }
 
 impl CSDPMultiModel {
@@ -92,6 +95,8 @@ impl CSDPMultiModel {
                     post_layer: i + 1,
                     synapse_type: "CSDP".to_string(),
                     is_learning: true,
+                    enabled: true,
+                    gain: 1.0,
                 },
                 synapse,
             });
@@ -107,6 +112,8 @@ impl CSDPMultiModel {
                         post_layer: i,
                         synapse_type: "CSDP".to_string(),
                         is_learning: true,
+                        enabled: true,
+                        gain: 1.0,
                     },
                     synapse: synapse_back,
                 });
@@ -114,6 +121,7 @@ impl CSDPMultiModel {
         }
 
         Ok(Self {
+            synapse_groups: parallel::SynapseGroups::new(&synapses),
             layers,
             layer_metadata,
             synapses,
@@ -162,36 +170,23 @@ impl CSDPMultiModel {
             }
         }
 
+        let par = parallel::enabled(&self.device);
+
         // Forward synapse pass
-        for syn_conn in self.synapses.iter_mut() {
-            let pre_layer_id = syn_conn.metadata.pre_layer;
-            let post_layer_id = syn_conn.metadata.post_layer;
-            let pre_act = self.layers[pre_layer_id].output()?;
-            let post_input = syn_conn.synapse.forward(pre_act)?;
-            self.layers[post_layer_id].add_input(&post_input)?;
-        }
+        parallel::forward_synapses(
+            &mut self.layers,
+            &self.synapses,
+            &mut self.synapse_groups,
+            false,
+            par,
+        )?;
 
         // Step layers
-        for layer in self.layers.iter_mut().skip(1) {
-            layer.step(self.dt)?;
-        }
+        parallel::step_layers(&mut self.layers[1..], self.dt, par)?;
 
         // STDP Weight Updates
-        for syn_conn in self.synapses.iter_mut() {
-            if self.is_learning && syn_conn.metadata.is_learning {
-                let pre_layer_id = syn_conn.metadata.pre_layer;
-                let post_layer_id = syn_conn.metadata.post_layer;
-                let pre_act = self.layers[pre_layer_id].output()?;
-
-                // Rust lifetime scope rule limitation requires us to extract pre_act,
-                // but we also need mutable access to layers. pre_act is cloned for safety.
-                let pre_act_cloned = pre_act.clone();
-                syn_conn.synapse.update_weights(
-                    &pre_act_cloned,
-                    &mut self.layers[post_layer_id],
-                    self.dt,
-                )?;
-            }
+        if self.is_learning {
+            parallel::update_synapses(&self.layers, &mut self.synapses, self.dt, par)?;
         }
 
         Ok(())
@@ -279,7 +274,12 @@ impl CSDPMultiModel {
         Ok(history)
     }
 
-    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> CandleResult<()> {
+    /// Learned parameters under descriptive names, for export
+    pub fn named_tensors(&self) -> CandleResult<Vec<(String, Tensor)>> {
+        super::named_tensors(&self.layers, &self.layer_metadata, &self.synapses)
+    }
+
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<()> {
         let mut tensor_map = std::collections::HashMap::new();
         for syn_conn in &self.synapses {
             let state = syn_conn.synapse.get_state()?;
@@ -288,11 +288,12 @@ impl CSDPMultiModel {
                 tensor_map.insert(format!("{}{}", prefix, key), tensor);
             }
         }
+        super::collect_layer_state(&self.layers, &mut tensor_map)?;
         candle_core::safetensors::save(&tensor_map, path)?;
         Ok(())
     }
 
-    pub fn load<P: AsRef<std::path::Path>>(&mut self, path: P) -> CandleResult<()> {
+    pub fn load<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<()> {
         let loaded_tensors = candle_core::safetensors::load(path, &self.device)?;
         for syn_conn in self.synapses.iter_mut() {
             let prefix = format!("synapse_{}_", syn_conn.metadata.id);
@@ -307,6 +308,7 @@ impl CSDPMultiModel {
                 syn_conn.synapse.set_state(&state)?;
             }
         }
+        super::restore_layer_state(&mut self.layers, &loaded_tensors)?;
         Ok(())
     }
 
@@ -326,6 +328,7 @@ impl CSDPMultiModel {
                 velocity: (0.0, 0.0),
                 current_activity: output_vec,
                 spike_count,
+                topography: None,
             });
         }
 
@@ -337,9 +340,15 @@ impl CSDPMultiModel {
                 post_layer: syn_conn.metadata.post_layer,
                 synapse_type: syn_conn.metadata.synapse_type.clone(),
                 weight_stats: syn_conn.synapse.weight_stats()?,
+                is_learning: syn_conn.metadata.is_learning,
+                enabled: syn_conn.metadata.enabled,
             });
         }
 
-        Ok(crate::visualization::ModelStructure { layers, synapses })
+        Ok(crate::visualization::ModelStructure {
+            layers,
+            synapses,
+            novelty: None,
+        })
     }
 }
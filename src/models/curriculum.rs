@@ -0,0 +1,140 @@
+//! Coarse-to-fine curriculum for the action granularity of closed-loop robot control.
+//!
+//! The decoders of a [`RobotModel`] turn the spike counts of a control window into joint moves
+//! of at most `step_size` radians. Early in training a small step makes the arm crawl, so an
+//! untrained network barely leaves home and sees little of the task; a large step reaches the
+//! target region quickly but can't settle on it. [`ActionCurriculum`] starts with a coarse
+//! step and refines it whenever the tracking error, the distance to the target at the end of
+//! an episode, has come down to a few steps. The current step size is passed to the decoder
+//! with [`RobotModel::set_step_size`] and to the environment with
+//! `RobotEnvironment::set_action_delta`, or `RobotEnvironment::with_curriculum` lets the
+//! environment refine its own step at every reset.
+
+use super::robot_model::RobotModel;
+use crate::error::{CsdpError, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CurriculumConfig {
+    /// Step size to start with, in radians per control step
+    pub initial_step: f64,
+    /// Finest step size; the curriculum ends once it is reached
+    pub final_step: f64,
+    /// Factor each refinement multiplies the step size by, in (0, 1)
+    pub refine_factor: f64,
+    /// Refine once the mean tracking error is below this many step sizes
+    pub advance_ratio: f64,
+    /// Number of tracking errors averaged before deciding, e.g. episodes
+    pub patience: usize,
+}
+
+impl Default for CurriculumConfig {
+    fn default() -> Self {
+        Self {
+            initial_step: 0.2,
+            final_step: 0.02,
+            refine_factor: 0.5,
+            advance_ratio: 2.0,
+            patience: 5,
+        }
+    }
+}
+
+/// Step size of the decoder and the environment, refined as tracking improves; see the module
+/// docs
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActionCurriculum {
+    pub config: CurriculumConfig,
+    step_size: f64,
+    stage: usize,
+    /// tracking errors seen since the last refinement
+    errors: Vec<f64>,
+}
+
+impl ActionCurriculum {
+    pub fn new(config: CurriculumConfig) -> Result<Self> {
+        if config.final_step <= 0.0
+            || config.final_step > config.initial_step
+            || !config.initial_step.is_finite()
+        {
+            return Err(CsdpError::Config(format!(
+                "curriculum step sizes must satisfy 0 < final <= initial, got {} and {}",
+                config.final_step, config.initial_step
+            )));
+        }
+        if !(config.refine_factor > 0.0 && config.refine_factor < 1.0) {
+            return Err(CsdpError::Config(format!(
+                "curriculum refine factor must be in (0, 1), got {}",
+                config.refine_factor
+            )));
+        }
+        if !(config.advance_ratio > 0.0 && config.advance_ratio.is_finite()) || config.patience == 0
+        {
+            return Err(CsdpError::Config(format!(
+                "curriculum advance ratio must be positive and patience at least 1, got {} and {}",
+                config.advance_ratio, config.patience
+            )));
+        }
+        Ok(Self {
+            config,
+            step_size: config.initial_step,
+            stage: 0,
+            errors: Vec::new(),
+        })
+    }
+
+    /// Current step size, in radians per control step
+    pub fn step_size(&self) -> f64 {
+        self.step_size
+    }
+
+    /// Number of refinements so far
+    pub fn stage(&self) -> usize {
+        self.stage
+    }
+
+    /// Whether the step size has reached `final_step`
+    pub fn is_final(&self) -> bool {
+        self.step_size <= self.config.final_step
+    }
+
+    /// Record the tracking error of an episode, in radians. Returns the new step size when
+    /// the mean of the last `patience` errors fell below `advance_ratio` steps and the step
+    /// was refined. Non-finite errors are ignored.
+    pub fn observe(&mut self, tracking_error: f64) -> Option<f64> {
+        if self.is_final() || !tracking_error.is_finite() {
+            return None;
+        }
+        self.errors.push(tracking_error.abs());
+        if self.errors.len() < self.config.patience {
+            return None;
+        }
+        let recent = &self.errors[self.errors.len() - self.config.patience..];
+        let mean = recent.iter().sum::<f64>() / recent.len() as f64;
+        if mean >= self.config.advance_ratio * self.step_size {
+            return None;
+        }
+        self.step_size = (self.step_size * self.config.refine_factor).max(self.config.final_step);
+        self.stage += 1;
+        // Errors at the coarser step say nothing about the finer one
+        self.errors.clear();
+        log::info!(
+            "Action curriculum: tracking error {:.4} rad, refining the step to {:.4} rad",
+            mean,
+            self.step_size
+        );
+        Some(self.step_size)
+    }
+
+    /// Pass the current step size to the decoder of `model`
+    pub fn apply(&self, model: &mut RobotModel) {
+        model.set_step_size(self.step_size);
+    }
+
+    /// Start over at `initial_step`
+    pub fn reset(&mut self) {
+        self.step_size = self.config.initial_step;
+        self.stage = 0;
+        self.errors.clear();
+    }
+}
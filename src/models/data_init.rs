@@ -0,0 +1,214 @@
+//! Data-driven initialization of the input synapses.
+//!
+//! Random weights leave a CSDP network to discover the structure of its input during a long
+//! unsupervised warm-up. [`init_from_data`] shortcuts this on structured inputs: it fits
+//! templates to a sample of the dataset, either k-means centroids or principal components,
+//! and writes one into each neuron fed by the input layer. The templates are taken relative
+//! to the mean sample, so a neuron responds to how an input differs from the average one,
+//! and every row keeps the norm of its random init so the layer's thresholds still fit.
+
+use super::Model;
+use crate::error::{CsdpError, Result};
+use crate::synapse::SynapseId;
+use candle_core::{DType, Tensor};
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+/// Templates [`init_from_data`] fits to the samples
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataInit {
+    /// One k-means centroid per neuron, refined for up to `iterations` Lloyd steps from
+    /// distinct samples drawn with the global seed
+    KMeans { iterations: usize },
+    /// The principal components in order of variance, one per neuron. Neurons beyond the
+    /// rank of the samples keep their random weights.
+    Pca,
+}
+
+impl Default for DataInit {
+    fn default() -> Self {
+        DataInit::KMeans { iterations: 20 }
+    }
+}
+
+/// Power iterations per principal component
+const POWER_ITERATIONS: usize = 200;
+
+/// Components with less variance than this fraction of the total are treated as noise
+const MIN_EXPLAINED_VARIANCE: f64 = 1e-6;
+
+/// Initialize the weights of every enabled synapse leaving the input layer from `samples`,
+/// (input_size, n) as passed to [`Model::step`]. Connections absent from a synapse drawn by
+/// distance stay absent. Returns the synapses changed.
+pub fn init_from_data(
+    model: &mut Model,
+    samples: &Tensor,
+    init: DataInit,
+) -> Result<Vec<SynapseId>> {
+    let input_size = model.layers[0].size();
+    let (size, count) = samples.dims2()?;
+    if size != input_size || count == 0 {
+        return Err(CsdpError::Data(format!(
+            "expected samples of shape ({}, n) with n > 0, got {:?}",
+            input_size,
+            samples.dims()
+        )));
+    }
+    // One sample per row, centered
+    let rows = samples.to_dtype(DType::F32)?.t()?.to_vec2::<f32>()?;
+    let mut mean = vec![0.0f32; size];
+    for row in &rows {
+        for (m, &x) in mean.iter_mut().zip(row) {
+            *m += x / count as f32;
+        }
+    }
+    let centered: Vec<Vec<f32>> = rows
+        .iter()
+        .map(|row| row.iter().zip(&mean).map(|(x, m)| x - m).collect())
+        .collect();
+
+    let mut changed = Vec::new();
+    for conn in model.synapses.iter_mut() {
+        if conn.metadata.pre_layer != 0 || !conn.metadata.enabled {
+            continue;
+        }
+        let id = conn.metadata.id;
+        let mut state = conn.synapse.get_state()?;
+        let Some(weights) = state.get("weights") else {
+            return Err(CsdpError::Config(format!(
+                "input synapse {} ({}) has no dense weights to initialize",
+                id, conn.metadata.synapse_type
+            )));
+        };
+        let neurons = weights.dim(0)?;
+        let templates = match init {
+            DataInit::KMeans { iterations } => {
+                if count < neurons {
+                    return Err(CsdpError::Data(format!(
+                        "k-means for the {} neurons of synapse {} needs at least as many \
+                         samples, got {}",
+                        neurons, id, count
+                    )));
+                }
+                k_means(&centered, neurons, iterations)
+            }
+            DataInit::Pca => principal_components(&centered, neurons),
+        };
+
+        let device = weights.device().clone();
+        let mut rows = weights.to_vec2::<f32>()?;
+        for (row, template) in rows.iter_mut().zip(&templates) {
+            let template_norm = norm(template);
+            if template_norm > 0.0 {
+                let scale = norm(row) / template_norm;
+                *row = template.iter().map(|x| x * scale).collect();
+            }
+        }
+        let mut weights = Tensor::from_vec(rows.concat(), (neurons, size), &device)?;
+        if let Some(mask) = state.get("mask") {
+            weights = weights.mul(&mask.to_dtype(DType::F32)?.to_device(&device)?)?;
+        }
+        state.insert("weights".to_string(), weights);
+        conn.synapse.set_state(&state)?;
+        changed.push(id);
+    }
+    if changed.is_empty() {
+        return Err(CsdpError::Config(
+            "the model has no enabled synapse leaving the input layer".to_string(),
+        ));
+    }
+    // feedback tied to the input synapses follows them
+    model.sync_tied_weights()?;
+    Ok(changed)
+}
+
+/// `k` centroids of `rows` by Lloyd's algorithm; `rows` has at least `k` entries
+fn k_means(rows: &[Vec<f32>], k: usize, iterations: usize) -> Vec<Vec<f32>> {
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    order.shuffle(&mut crate::seed::rng());
+    let mut centroids: Vec<Vec<f32>> = order[..k].iter().map(|&i| rows[i].clone()).collect();
+    let mut assignment = vec![usize::MAX; rows.len()];
+    for _ in 0..iterations {
+        let mut moved = false;
+        for (row, assigned) in rows.iter().zip(assignment.iter_mut()) {
+            let nearest = (0..k)
+                .min_by(|&a, &b| {
+                    squared_distance(row, &centroids[a])
+                        .total_cmp(&squared_distance(row, &centroids[b]))
+                })
+                .unwrap_or(0);
+            moved |= *assigned != nearest;
+            *assigned = nearest;
+        }
+        if !moved {
+            break;
+        }
+        let mut sums = vec![vec![0.0f32; rows[0].len()]; k];
+        let mut counts = vec![0usize; k];
+        for (row, &c) in rows.iter().zip(&assignment) {
+            counts[c] += 1;
+            for (s, &x) in sums[c].iter_mut().zip(row) {
+                *s += x;
+            }
+        }
+        // An empty cluster keeps its centroid
+        for ((centroid, sum), &n) in centroids.iter_mut().zip(sums).zip(&counts) {
+            if n > 0 {
+                *centroid = sum.into_iter().map(|s| s / n as f32).collect();
+            }
+        }
+    }
+    centroids
+}
+
+fn norm(v: &[f32]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+/// Up to `k` principal components of the centered `rows`, by power iteration on the
+/// covariance with deflation. Stops early at the rank of the samples.
+fn principal_components(rows: &[Vec<f32>], k: usize) -> Vec<Vec<f32>> {
+    let size = rows[0].len();
+    let mut covariance = vec![0.0f64; size * size];
+    for row in rows {
+        for (i, &a) in row.iter().enumerate() {
+            for (j, &b) in row.iter().enumerate() {
+                covariance[i * size + j] += a as f64 * b as f64 / rows.len() as f64;
+            }
+        }
+    }
+    let total: f64 = (0..size).map(|i| covariance[i * size + i]).sum();
+
+    let mut rng = crate::seed::rng();
+    let mut components = Vec::new();
+    while components.len() < k.min(size) {
+        let mut v: Vec<f64> = (0..size).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        let mut variance = 0.0;
+        for _ in 0..POWER_ITERATIONS {
+            let next: Vec<f64> = covariance
+                .chunks_exact(size)
+                .map(|row| row.iter().zip(&v).map(|(c, x)| c * x).sum())
+                .collect();
+            let norm = next.iter().map(|x| x * x).sum::<f64>().sqrt();
+            if norm == 0.0 {
+                break;
+            }
+            variance = norm;
+            v = next.into_iter().map(|x| x / norm).collect();
+        }
+        if variance <= MIN_EXPLAINED_VARIANCE * total {
+            break;
+        }
+        for (row, &vi) in covariance.chunks_exact_mut(size).zip(&v) {
+            for (c, &vj) in row.iter_mut().zip(&v) {
+                *c -= variance * vi * vj;
+            }
+        }
+        components.push(v.into_iter().map(|x| x as f32).collect());
+    }
+    components
+}
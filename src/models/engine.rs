@@ -0,0 +1,143 @@
+//! Simulation engines: how [`Model::step`] advances the network by one timestep.
+//!
+//! A timestep is made of phases [`Model`] exposes as building blocks: presenting the input
+//! ([`Model::begin_step`]), propagating spikes through the synapses
+//! ([`Model::propagate`]), integrating the layers ([`Model::step_layers`]), plasticity
+//! ([`Model::apply_plasticity`]) and bookkeeping ([`Model::end_step`]). An engine strings them
+//! together and chooses how propagation and integration are done, so alternative backends
+//! plug in with [`Model::set_engine`] without changes to model code, and can be benchmarked
+//! against each other (`cargo bench --bench kernels -- engines`). Adaptive timestep control
+//! (see [`super::AdaptiveDt`]) is part of the bookkeeping and works with every engine.
+
+use super::Model;
+use crate::layer::Integrator;
+use candle_core::{Result as CandleResult, Tensor};
+
+pub trait SimulationEngine: Send + Sync {
+    /// Short name for logs and benchmarks
+    fn name(&self) -> &'static str;
+
+    /// Called when the engine is installed, e.g. to configure the layers
+    fn attach(&mut self, _model: &mut Model) -> CandleResult<()> {
+        Ok(())
+    }
+
+    /// Called when the engine is replaced; undoes whatever `attach` changed
+    fn detach(&mut self, _model: &mut Model) -> CandleResult<()> {
+        Ok(())
+    }
+
+    /// Advance `model` by one timestep
+    fn step(
+        &mut self,
+        model: &mut Model,
+        input: &Tensor,
+        context: Option<&Tensor>,
+    ) -> CandleResult<()>;
+
+    fn box_clone(&self) -> Box<dyn SimulationEngine>;
+}
+
+/// All phases in order, propagating sparsely with `event_driven`
+fn clocked_step(
+    model: &mut Model,
+    input: &Tensor,
+    context: Option<&Tensor>,
+    event_driven: bool,
+) -> CandleResult<()> {
+    let dt = model.begin_step(input, context)?;
+    model.propagate(event_driven)?;
+    model.step_layers(dt)?;
+    model.apply_plasticity(dt)?;
+    model.end_step(dt)
+}
+
+/// Every synapse and layer is updated every step, with forward-Euler integration. Synapses
+/// propagate sparsely when [`Model::event_driven`] is set. The default engine.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClockDriven;
+
+impl SimulationEngine for ClockDriven {
+    fn name(&self) -> &'static str {
+        "clock-driven"
+    }
+
+    fn step(
+        &mut self,
+        model: &mut Model,
+        input: &Tensor,
+        context: Option<&Tensor>,
+    ) -> CandleResult<()> {
+        let event_driven = model.event_driven;
+        clocked_step(model, input, context, event_driven)
+    }
+
+    fn box_clone(&self) -> Box<dyn SimulationEngine> {
+        Box::new(*self)
+    }
+}
+
+/// Synapses only propagate the neurons that spiked (see
+/// [`crate::synapse::SynapseOps::forward_active`]), whatever [`Model::event_driven`] says.
+/// Layers that are too active for it to pay off still take the dense pass.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EventDriven;
+
+impl SimulationEngine for EventDriven {
+    fn name(&self) -> &'static str {
+        "event-driven"
+    }
+
+    fn step(
+        &mut self,
+        model: &mut Model,
+        input: &Tensor,
+        context: Option<&Tensor>,
+    ) -> CandleResult<()> {
+        clocked_step(model, input, context, true)
+    }
+
+    fn box_clone(&self) -> Box<dyn SimulationEngine> {
+        Box::new(*self)
+    }
+}
+
+/// Clock-driven with exponential-Euler integration of the membrane potentials (see
+/// [`Integrator::ExponentialEuler`]), which stays stable for timesteps close to the membrane
+/// time constant
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExponentialEuler;
+
+impl SimulationEngine for ExponentialEuler {
+    fn name(&self) -> &'static str {
+        "exponential-euler"
+    }
+
+    fn attach(&mut self, model: &mut Model) -> CandleResult<()> {
+        for layer in model.layers.iter_mut() {
+            layer.set_integrator(Integrator::ExponentialEuler);
+        }
+        Ok(())
+    }
+
+    fn detach(&mut self, model: &mut Model) -> CandleResult<()> {
+        for layer in model.layers.iter_mut() {
+            layer.set_integrator(Integrator::Euler);
+        }
+        Ok(())
+    }
+
+    fn step(
+        &mut self,
+        model: &mut Model,
+        input: &Tensor,
+        context: Option<&Tensor>,
+    ) -> CandleResult<()> {
+        let event_driven = model.event_driven;
+        clocked_step(model, input, context, event_driven)
+    }
+
+    fn box_clone(&self) -> Box<dyn SimulationEngine> {
+        Box::new(*self)
+    }
+}
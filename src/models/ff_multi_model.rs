@@ -1,3 +1,4 @@
+use crate::error::{CsdpError, Result};
 use candle_core::{DType, Device, Result as CandleResult, Tensor};
 use candle_nn::{
     Linear, Module, Optimizer, VarBuilder, VarMap, linear,
@@ -397,10 +398,26 @@ impl FFMultiModel {
         Ok(result)
     }
 
-    pub fn save<P: AsRef<std::path::Path>>(&self, dir: P) -> CandleResult<()> {
+    /// Learned parameters as `layer_<i>.<var>`, for export
+    pub fn named_tensors(&self) -> CandleResult<Vec<(String, Tensor)>> {
+        let mut tensors = Vec::new();
+        for (i, vm) in self.varmaps.iter().enumerate() {
+            let data = vm
+                .data()
+                .lock()
+                .map_err(|e| candle_core::Error::Msg(e.to_string()))?;
+            let mut vars: Vec<_> = data.iter().collect();
+            vars.sort_by(|a, b| a.0.cmp(b.0));
+            for (name, var) in vars {
+                tensors.push((format!("layer_{}.{}", i, name), var.as_tensor().clone()));
+            }
+        }
+        Ok(tensors)
+    }
+
+    pub fn save<P: AsRef<std::path::Path>>(&self, dir: P) -> Result<()> {
         let dir = dir.as_ref();
-        std::fs::create_dir_all(dir)
-            .map_err(|e| candle_core::Error::Msg(format!("mkdir: {}", e)))?;
+        std::fs::create_dir_all(dir)?;
         for (i, vm) in self.varmaps.iter().enumerate() {
             let path = dir.join(format!("layer_{}.safetensors", i));
             vm.save(&path)?;
@@ -408,16 +425,16 @@ impl FFMultiModel {
         Ok(())
     }
 
-    pub fn load<P: AsRef<std::path::Path>>(&mut self, dir: P) -> CandleResult<()> {
+    pub fn load<P: AsRef<std::path::Path>>(&mut self, dir: P) -> Result<()> {
         let dir = dir.as_ref();
         for (i, vm) in self.varmaps.iter_mut().enumerate() {
             let path = dir.join(format!("layer_{}.safetensors", i));
             if path.exists() {
                 vm.load(&path)?;
             } else {
-                return Err(candle_core::Error::Msg(format!(
-                    "Missing weight file: {:?}",
-                    path
+                return Err(CsdpError::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("Missing weight file: {:?}", path),
                 )));
             }
         }
@@ -1,25 +1,198 @@
+use crate::error::{CsdpError, Result};
 use crate::layer::bernoulli::BernoulliLayer;
 use crate::layer::lif::LIFLayer;
+use crate::layer::mod_signal::ModSignalGenerator;
+use crate::layer::mod_signal::regularized::{ActivityRegularizer, RegularizedModSignal};
 use crate::layer::mod_signal::standard::StandardModSignal;
+use crate::layer::normalization::{NormalizationConfig, NormalizationLayer};
+use crate::layer::predictive::PredictionLayer;
+use crate::layer::topography::Topography;
 use crate::layer::{Layer, LayerMetadata, LayerPosition};
 use crate::synapse::csdp::CSDP;
-use crate::synapse::{LayerId, SynapseConnection, SynapseMetadata, SynapseOps};
-use crate::visualization::{LayerVisInfo, SynapseVisInfo};
+use crate::synapse::eligibility::{MultiTraceCSDP, TraceConfig};
+use crate::synapse::offload::Offloaded;
+use crate::synapse::predictive::PredictiveSynapse;
+use crate::synapse::sparse::SparseCSDP;
+use crate::synapse::topographic::{DistanceConnectivity, Topographic};
+use crate::synapse::{LayerId, SynapseConnection, SynapseId, SynapseMetadata, SynapseOps};
+use crate::visualization::{LayerVisInfo, PerfStats, SynapseVisInfo};
 use candle_core::{DType, Device, Result as CandleResult, Tensor};
-
+use rayon::prelude::*;
+use std::time::Duration;
+// std's Instant panics on wasm32-unknown-unknown
+use web_time::Instant;
+
+pub mod calibration;
+pub mod consolidation;
+pub mod context;
 pub mod csdp_multi_model;
+pub mod curriculum;
+pub mod data_init;
+pub mod engine;
 pub mod ff_model;
 pub mod ff_multi_model;
+pub mod novelty;
+pub mod observation;
+pub mod parallel;
+pub mod preflight;
+pub mod profiler;
+pub mod reference;
 pub mod rl_model1;
 pub mod rl_model2;
 pub mod rl_model3;
-mod robot_model;
+pub mod robot_model;
+pub mod schedule;
+pub mod summary;
 
 /// Configuration for creating a model
 pub struct ModelConfig {
     pub layer_configs: Vec<LayerConfig>,
     pub synapse_configs: Vec<SynapseConfig>,
     pub dt: f32,
+    /// Reseed before weights are drawn, independent of the global seed
+    pub seed: Option<u64>,
+    /// Adapt the timestep to the membrane-potential dynamics, see [`AdaptiveDt`]
+    pub adaptive_dt: Option<AdaptiveDt>,
+    /// Device of each layer, for models too large for one GPU; `None` puts every layer on the
+    /// model's device. See [`ModelConfig::with_layer_devices`].
+    pub layer_devices: Option<Vec<Device>>,
+    /// Activity penalties added to the modulatory signal of every LIF layer
+    pub activity_regularizer: Option<ActivityRegularizer>,
+    /// Rows of the label appended to the input, see [`ModelConfig::with_label_input`]
+    pub label_input: Option<usize>,
+    /// Top-down connections, created after `synapse_configs`; see [`ModelConfig::with_feedback`]
+    pub feedback_configs: Vec<FeedbackConfig>,
+    /// Whether the synapses from hidden layers to the output layer learn, see
+    /// [`ModelConfig::with_readout`]
+    pub readout_plastic: bool,
+    /// Running normalization of the raw input, see [`ModelConfig::with_input_normalization`]
+    pub input_normalization: Option<NormalizationConfig>,
+    /// Novelty signal modulating plasticity, see [`ModelConfig::with_novelty`]
+    pub novelty: Option<novelty::NoveltyConfig>,
+    /// `(pre, post)` layers of forward connections whose backward connection `post -> pre`
+    /// uses their transposed weights, see [`ModelConfig::with_tied_weights`]
+    pub tied_weights: Vec<(usize, usize)>,
+    /// `(pre, post)` layers of connections whose weights are kept on the CPU, see
+    /// [`ModelConfig::with_offloaded_synapse`]
+    pub offloaded_synapses: Vec<(usize, usize)>,
+    /// Spatial arrangement of the neurons of some layers, see [`ModelConfig::with_topography`]
+    pub topographies: Vec<(usize, Topography)>,
+    /// `(pre, post)` layers of connections drawn by distance, see
+    /// [`ModelConfig::with_distance_connectivity`]
+    pub distance_connectivity: Vec<(usize, usize, DistanceConnectivity)>,
+}
+
+/// Adaptive timestep control for [`Model`].
+///
+/// After every step the timestep is rescaled so that the largest membrane-potential change of
+/// the next step is about `tolerance`: quiescent phases are run with long steps, rapid changes
+/// with short ones, always within `[min_dt, max_dt]` and by at most a factor of two per step.
+/// `process` and `process_contrastive` then simulate `timesteps` nominal steps of `dt` worth of
+/// time in however many steps that takes, and plasticity is scaled by each step's length
+/// relative to `dt`. Measuring the change costs a device synchronization per step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveDt {
+    pub min_dt: f32,
+    pub max_dt: f32,
+    /// Target largest membrane-potential change per step
+    pub tolerance: f32,
+}
+
+impl AdaptiveDt {
+    /// Steps between `dt / 4` and `4 * dt`
+    pub fn around(dt: f32, tolerance: f32) -> Self {
+        Self {
+            min_dt: dt / 4.0,
+            max_dt: dt * 4.0,
+            tolerance,
+        }
+    }
+
+    /// Length of the step after one of length `dt` whose largest potential change was `change`
+    pub fn next_dt(&self, dt: f32, change: f32) -> f32 {
+        let factor = if change > 0.0 {
+            (self.tolerance / change).clamp(0.5, 2.0)
+        } else {
+            2.0
+        };
+        (dt * factor).clamp(self.min_dt, self.max_dt)
+    }
+}
+
+/// Early exit for [`Model::process`], which otherwise always runs `timesteps` steps.
+///
+/// Once at least `min_steps` steps have run, `process` stops as soon as no output neuron's rate
+/// estimate (its spikes so far per step) has moved by more than `tolerance` over the last
+/// `window` steps, for every sample of the batch. `timesteps` remains the upper bound, and
+/// `Model::step_count` tells how many steps were run. Checking reads a scalar back from the
+/// device every step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EarlyExit {
+    /// Largest change of a rate estimate over `window` steps that counts as settled
+    pub tolerance: f32,
+    pub min_steps: usize,
+    pub window: usize,
+}
+
+impl EarlyExit {
+    /// Settled rates over a window of 5 steps
+    pub fn new(tolerance: f32, min_steps: usize) -> Self {
+        Self {
+            tolerance,
+            min_steps,
+            window: 5,
+        }
+    }
+}
+
+/// Output rate estimate of a run under an [`EarlyExit`]
+pub(crate) struct RateConvergence {
+    config: EarlyExit,
+    /// output spikes so far, each step weighted by its length relative to `dt`
+    counts: Option<Tensor>,
+    elapsed: f32,
+    steps: usize,
+    /// rate estimates of the last `window + 1` steps, oldest first
+    history: std::collections::VecDeque<Tensor>,
+}
+
+impl RateConvergence {
+    pub(crate) fn new(config: EarlyExit) -> Self {
+        Self {
+            config,
+            counts: None,
+            elapsed: 0.0,
+            steps: 0,
+            history: std::collections::VecDeque::new(),
+        }
+    }
+
+    /// Add the output spikes of a step of relative length `weight`; true once the rates
+    /// have settled
+    pub(crate) fn update(&mut self, spikes: &Tensor, weight: f32) -> CandleResult<bool> {
+        let spikes = spikes.affine(weight as f64, 0.0)?;
+        let counts = match self.counts.take() {
+            Some(counts) => counts.add(&spikes)?,
+            None => spikes,
+        };
+        self.elapsed += weight;
+        self.steps += 1;
+        self.history.push_back(counts.affine(1.0 / self.elapsed as f64, 0.0)?);
+        self.counts = Some(counts);
+        let window = self.config.window.max(1);
+        if self.history.len() > window + 1 {
+            self.history.pop_front();
+        }
+        if self.steps < self.config.min_steps || self.history.len() <= window {
+            return Ok(false);
+        }
+        let change = self.history[window]
+            .sub(&self.history[0])?
+            .abs()?
+            .max_all()?
+            .to_scalar::<f32>()?;
+        Ok(change <= self.config.tolerance)
+    }
 }
 
 /// Configuration for a single layer
@@ -42,6 +215,23 @@ pub enum LayerConfig {
         trace_tau: f32,
         name: Option<String>,
     },
+    /// Prediction of the next input, fed by a [`SynapseType::Predictive`] synapse
+    Prediction {
+        size: usize,
+        name: Option<String>,
+    },
+}
+
+impl LayerConfig {
+    /// Number of neurons of the layer
+    pub fn size(&self) -> usize {
+        match self {
+            LayerConfig::OneHot { bounds, .. } => bounds.iter().sum(),
+            LayerConfig::Bernoulli { size, .. }
+            | LayerConfig::LIF { size, .. }
+            | LayerConfig::Prediction { size, .. } => *size,
+        }
+    }
 }
 
 /// Configuration for a synapse connection
@@ -52,11 +242,82 @@ pub struct SynapseConfig {
     pub synapse_type: SynapseType,
 }
 
-/// Types of synapses available
+/// A top-down connection with its own learning rule and gain
+#[derive(Debug, Clone)]
+pub struct FeedbackConfig {
+    pub pre_layer: usize,
+    pub post_layer: usize,
+    pub synapse_type: SynapseType,
+    /// scale of the connection's input to its post layer
+    pub gain: f32,
+}
+
+/// Which layers of a [`ModelConfig::standard`] network feed back to which
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedbackTopology {
+    /// feedforward only
+    None,
+    /// every layer above the first hidden layer feeds back to the layer directly below it
+    Adjacent,
+    /// the output layer feeds back to every hidden layer
+    FromOutput,
+    /// `Adjacent` and `FromOutput` together, the topology `ModelConfig::standard` builds
+    Full,
+}
+
+/// Which hidden layers of a [`ModelConfig::standard`] network project to the output layer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Readout {
+    /// only the last hidden layer
+    LastOnly,
+    /// every hidden layer, as `ModelConfig::standard` builds it
+    All,
+    /// the hidden layers at these indices, 0 being the first hidden layer
+    Layers(Vec<usize>),
+}
+
+/// Types of synapses available
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum SynapseType {
     CSDP,
+    /// Block-sparse CSDP keeping `density` of the `block_size` x `block_size` weight blocks,
+    /// for layers too large for a dense weight matrix
+    SparseCSDP { density: f32, block_size: usize },
+    /// Delta-rule synapse that learns to predict the next activity of its pre-synaptic layer;
+    /// pre and post layers must be the same size
+    Predictive { learning_rate: f32 },
+    /// CSDP whose updates pass through a fast and a slow eligibility trace per weight
+    MultiTrace(TraceConfig),
+}
+
+/// Wall-clock time accumulated in each phase of `Model::step` while profiling is enabled
+#[derive(Debug, Clone)]
+pub struct StepTimings {
+    pub steps: usize,
+    /// time spent stepping each layer, indexed by layer id
+    pub layer_step: Vec<Duration>,
+    pub synapse_forward: Duration,
+    pub synapse_update: Duration,
+    /// part of `synapse_forward` spent in each synapse, indexed like `Model::synapses`
+    pub synapse_forward_each: Vec<Duration>,
+    /// part of `synapse_update` spent in each synapse
+    pub synapse_update_each: Vec<Duration>,
+    pub since: Instant,
+}
+
+impl StepTimings {
+    fn new(num_layers: usize, num_synapses: usize) -> Self {
+        Self {
+            steps: 0,
+            layer_step: vec![Duration::ZERO; num_layers],
+            synapse_forward: Duration::ZERO,
+            synapse_update: Duration::ZERO,
+            synapse_forward_each: vec![Duration::ZERO; num_synapses],
+            synapse_update_each: vec![Duration::ZERO; num_synapses],
+            since: Instant::now(),
+        }
+    }
 }
 
 pub struct Model {
@@ -64,8 +325,54 @@ pub struct Model {
     pub layer_metadata: Vec<LayerMetadata>,
     pub synapses: Vec<SynapseConnection>,
     pub is_learning: bool,
+    /// Propagate only the neurons that spiked instead of running dense matmuls, for layers
+    /// whose activity is sparse (see [`parallel::EVENT_DENSITY_LIMIT`]); followed by the
+    /// default [`engine::ClockDriven`] engine
+    pub event_driven: bool,
     pub dt: f32,
     pub device: Device,
+    /// per-phase step timings, only collected when profiling is enabled
+    pub timings: Option<StepTimings>,
+    /// timings of the windows already taken by `take_perf_stats`, for
+    /// [`Model::profile_report`]
+    run_timings: Option<StepTimings>,
+    pub adaptive_dt: Option<AdaptiveDt>,
+    /// Ends `process` once the output rates have settled, see [`EarlyExit`]
+    pub early_exit: Option<EarlyExit>,
+    /// Spread this fraction of every context's mass evenly over the context neurons before
+    /// it reaches the context layer, see [`Model::set_label_smoothing`]
+    label_smoothing: Option<f32>,
+    /// `(forward, backward)` synapses whose backward weights are the transposed forward
+    /// weights, see [`ModelConfig::with_tied_weights`]
+    tied_weights: Vec<(SynapseId, SynapseId)>,
+    /// Debug mode: after every layer step and synapse update, check activity, modulatory
+    /// signals and weights for NaN/Inf and fail the step naming the component and timestep.
+    /// Each check synchronizes with the device.
+    pub check_finite: bool,
+    /// steps since the last reset
+    step_count: usize,
+    /// rows of the label appended to every input, if the input is label-conditioned
+    label_input: Option<usize>,
+    /// label appended to the input, (rows, batch) or (rows, 1); zeros when None
+    input_label: Option<Tensor>,
+    /// applied to every input before the label is appended and the input layer encodes it
+    input_normalization: Option<NormalizationLayer>,
+    /// scores every step's novelty and scales plasticity by it
+    novelty: Option<novelty::NoveltyDetector>,
+    /// protects the weights of earlier tasks, see [`Model::enable_consolidation`]
+    consolidation: Option<consolidation::Consolidation>,
+    /// inhibits the hidden neurons outside the current task's subset, see
+    /// [`Model::set_context`]
+    context_gating: Option<context::ContextGating>,
+    /// device of each layer; synapses live on their post layer's device
+    layer_devices: Vec<Device>,
+    /// spatial arrangement of each layer's neurons, if it has one
+    topographies: Vec<Option<Topography>>,
+    /// length of the next step; `dt` unless adaptive timestep control is on
+    step_dt: f32,
+    /// how `step` advances the network, see [`engine`]
+    engine: Box<dyn engine::SimulationEngine>,
+    synapse_groups: parallel::SynapseGroups,
 }
 
 /// Legacy Model structure (kept for reference, can be removed)
@@ -86,16 +393,31 @@ pub struct ProcessOutput {
     pub final_output: Tensor,
 }
 
-impl Model {
-    /// Create a new CSDP model with default configuration (backward compatible).
-    pub fn new(
+impl ProcessOutput {
+    /// `output_activity` as one time-major raster, (steps, output_size, batch), for vectorized
+    /// decoding with [`crate::raster`]
+    pub fn raster(&self) -> CandleResult<Tensor> {
+        crate::raster::stack(&self.output_activity)
+    }
+}
+
+/// Per-sample goodness of each phase from [`Model::process_contrastive`]: mean squared
+/// hidden-layer activity, averaged over timesteps and hidden layers, shape `(batch,)`
+pub struct ContrastiveOutput {
+    pub positive_goodness: Tensor,
+    pub negative_goodness: Tensor,
+}
+
+impl ModelConfig {
+    /// Layers and synapses of [`Model::new`]: input, context, LIF hidden layers and an LIF
+    /// output layer, with CSDP synapses between them
+    pub fn standard(
         input_size: usize,
         output_size: usize,
         hidden_sizes: Vec<usize>,
-        device: &Device,
         dt: f32,
         input_bounds: Option<Vec<usize>>,
-    ) -> Option<Self> {
+    ) -> Result<Self> {
         // Lower default LIF parameter g_thr to 0.5 allows sparse input traces to cross the
         // baseline and jumpstart adaptive homeostatic adaptation.
         let g_thr = 0.5;
@@ -104,7 +426,9 @@ impl Model {
         let thresh_lambda = 0.01;
 
         if hidden_sizes.is_empty() {
-            return None;
+            return Err(CsdpError::Config(
+                "at least one hidden layer is required".to_string(),
+            ));
         }
 
         // Build layer configs
@@ -200,61 +524,653 @@ impl Model {
             });
         }
 
-        let config = ModelConfig {
+        Ok(Self {
             layer_configs,
             synapse_configs,
             dt,
+            seed: None,
+            adaptive_dt: None,
+            layer_devices: None,
+            activity_regularizer: None,
+            label_input: None,
+            feedback_configs: vec![],
+            readout_plastic: true,
+            input_normalization: None,
+            novelty: None,
+            tied_weights: vec![],
+            offloaded_synapses: vec![],
+            topographies: vec![],
+            distance_connectivity: vec![],
+        })
+    }
+
+    /// Place layer `i` on `devices[i]`. A synapse lives on the device of its post layer: the
+    /// pre-synaptic spikes are copied over when they cross devices, so only activity, never
+    /// weights, moves between devices during a step. Inputs and contexts are moved to the
+    /// devices of the input and context layers, and `process` returns its outputs on the
+    /// model's device.
+    pub fn with_layer_devices(mut self, devices: Vec<Device>) -> Result<Self> {
+        if devices.len() != self.layer_configs.len() {
+            return Err(CsdpError::Config(format!(
+                "{} layer devices given for {} layers",
+                devices.len(),
+                self.layer_configs.len()
+            )));
+        }
+        self.layer_devices = Some(devices);
+        Ok(self)
+    }
+
+    /// Condition the input on a label: the Bernoulli input layer grows by `num_labels` neurons,
+    /// and the model appends the label set with [`Model::set_input_label`] (or
+    /// [`Model::set_input_classes`]) to every input, as FF/CSDP-style supervised training
+    /// presents data and label together. Inputs keep their original size.
+    pub fn with_label_input(mut self, num_labels: usize) -> Result<Self> {
+        match self.layer_configs.first_mut() {
+            Some(LayerConfig::Bernoulli { size, .. }) => *size += num_labels,
+            _ => {
+                return Err(CsdpError::Config(
+                    "label-conditioned input needs a Bernoulli input layer".to_string(),
+                ));
+            }
+        }
+        self.label_input = Some(num_labels);
+        Ok(self)
+    }
+
+    /// Replace the feedback synapses of a [`ModelConfig::standard`] network (those from a
+    /// hidden or output layer to a lower hidden layer) with `topology`, each using
+    /// `synapse_type` and scaling its input to the post layer by `gain`. Layer ids are those
+    /// of `standard`, so call it before [`ModelConfig::with_predictive_front_end`].
+    pub fn with_feedback(
+        mut self,
+        topology: FeedbackTopology,
+        synapse_type: SynapseType,
+        gain: f32,
+    ) -> Result<Self> {
+        let output = self.layer_configs.len().saturating_sub(1);
+        if output < 3 {
+            return Err(CsdpError::Config(
+                "feedback needs input, context, hidden and output layers".to_string(),
+            ));
+        }
+        self.synapse_configs
+            .retain(|syn| !(syn.post_layer >= 2 && syn.pre_layer > syn.post_layer));
+        self.feedback_configs.clear();
+
+        let mut connections = vec![];
+        if matches!(topology, FeedbackTopology::Adjacent | FeedbackTopology::Full) {
+            connections.extend((2..output).map(|post| (post + 1, post)));
+        }
+        if matches!(topology, FeedbackTopology::FromOutput | FeedbackTopology::Full) {
+            // the output already feeds the last hidden layer through the adjacent connection
+            let last = if topology == FeedbackTopology::Full {
+                output - 1
+            } else {
+                output
+            };
+            connections.extend((2..last).map(|post| (output, post)));
+        }
+        for (pre_layer, post_layer) in connections {
+            self = self.with_feedback_connection(pre_layer, post_layer, synapse_type, gain)?;
+        }
+        Ok(self)
+    }
+
+    /// Add one feedback connection from `pre_layer` down to `post_layer`, for topologies the
+    /// [`FeedbackTopology`] presets don't cover
+    pub fn with_feedback_connection(
+        mut self,
+        pre_layer: usize,
+        post_layer: usize,
+        synapse_type: SynapseType,
+        gain: f32,
+    ) -> Result<Self> {
+        let layers = self.layer_configs.len();
+        if pre_layer >= layers || post_layer >= layers || pre_layer <= post_layer {
+            return Err(CsdpError::Config(format!(
+                "feedback {} -> {} must run from a higher to a lower layer of {}",
+                pre_layer, post_layer, layers
+            )));
+        }
+        self.feedback_configs.push(FeedbackConfig {
+            pre_layer,
+            post_layer,
+            synapse_type,
+            gain,
+        });
+        Ok(self)
+    }
+
+    /// Tie the backward connection `post_layer -> pre_layer` to the forward connection
+    /// `pre_layer -> post_layer`: its weights are the transpose of the forward weights instead
+    /// of an independent matrix, as in predictive-coding formulations with symmetric
+    /// feedback. The backward synapse no longer learns; its weights follow the forward
+    /// synapse after every update and load, and its biases keep their initial values. Both
+    /// connections must exist when the model is built (see [`Model::tied_weights`]).
+    pub fn with_tied_weights(mut self, pre_layer: usize, post_layer: usize) -> Result<Self> {
+        let layers = self.layer_configs.len();
+        if pre_layer >= layers || post_layer >= layers || pre_layer == post_layer {
+            return Err(CsdpError::Config(format!(
+                "cannot tie connections between layers {} and {} of {}",
+                pre_layer, post_layer, layers
+            )));
+        }
+        let pair = (pre_layer.min(post_layer), pre_layer.max(post_layer));
+        if self
+            .tied_weights
+            .iter()
+            .any(|&(a, b)| (a.min(b), a.max(b)) == pair)
+        {
+            return Err(CsdpError::Config(format!(
+                "layers {} and {} are already tied",
+                pre_layer, post_layer
+            )));
+        }
+        self.tied_weights.push((pre_layer, post_layer));
+        Ok(self)
+    }
+
+    /// Keep the weights of the connection `pre_layer -> post_layer` on the CPU while its post
+    /// layer runs on an accelerator, to fit larger models into accelerator memory. The
+    /// synapse computes its forward pass on the CPU, so only activity crosses devices every
+    /// step, and copies its weights to the post layer's device for each plasticity update:
+    /// offload frozen or rarely-updated synapses, such as a static readout or a large input
+    /// projection. Only CSDP and sparse CSDP synapses can be offloaded, and the connection
+    /// must exist when the model is built. See [`crate::synapse::offload`].
+    pub fn with_offloaded_synapse(mut self, pre_layer: usize, post_layer: usize) -> Result<Self> {
+        let layers = self.layer_configs.len();
+        if pre_layer >= layers || post_layer >= layers {
+            return Err(CsdpError::Config(format!(
+                "cannot offload the connection {} -> {} of {} layers",
+                pre_layer, post_layer, layers
+            )));
+        }
+        if !self.offloaded_synapses.contains(&(pre_layer, post_layer)) {
+            self.offloaded_synapses.push((pre_layer, post_layer));
+        }
+        Ok(self)
+    }
+
+    /// Arrange the neurons of `layer` on a line or grid (see [`crate::layer::topography`]),
+    /// for distance-dependent connectivity and the neuron map of the visualizer. The
+    /// topography must place exactly the layer's neurons.
+    pub fn with_topography(mut self, layer: usize, topography: Topography) -> Result<Self> {
+        let Some(config) = self.layer_configs.get(layer) else {
+            return Err(CsdpError::Config(format!(
+                "cannot arrange layer {} of {}",
+                layer,
+                self.layer_configs.len()
+            )));
+        };
+        if topography.size() != config.size() {
+            return Err(CsdpError::Config(format!(
+                "{:?} places {} neurons but layer {} has {}",
+                topography,
+                topography.size(),
+                layer,
+                config.size()
+            )));
+        }
+        self.topographies.retain(|&(l, _)| l != layer);
+        self.topographies.push((layer, topography));
+        Ok(self)
+    }
+
+    /// Draw the connection `pre_layer -> post_layer` by the distance between its neurons
+    /// instead of connecting all to all, e.g. local receptive fields of a hidden layer on an
+    /// input grid (see [`crate::synapse::topographic`]). Both layers need a topography and the
+    /// connection must exist, with dense weights, when the model is built. Absent connections
+    /// stay absent while the weights learn, and are saved with the model.
+    pub fn with_distance_connectivity(
+        mut self,
+        pre_layer: usize,
+        post_layer: usize,
+        connectivity: DistanceConnectivity,
+    ) -> Result<Self> {
+        let layers = self.layer_configs.len();
+        if pre_layer >= layers || post_layer >= layers {
+            return Err(CsdpError::Config(format!(
+                "cannot connect layers {} -> {} of {} by distance",
+                pre_layer, post_layer, layers
+            )));
+        }
+        connectivity.validate().map_err(CsdpError::Config)?;
+        self.distance_connectivity
+            .retain(|&(pre, post, _)| (pre, post) != (pre_layer, post_layer));
+        self.distance_connectivity
+            .push((pre_layer, post_layer, connectivity));
+        Ok(self)
+    }
+
+    /// Replace the projections from the hidden layers to the output layer of a
+    /// [`ModelConfig::standard`] network with those of `readout`, e.g. for deep stacks where
+    /// the all-to-output default lets the lower layers dominate the readout. Without
+    /// `plastic`, the projections keep their initial weights. Call it before
+    /// [`ModelConfig::with_predictive_front_end`].
+    pub fn with_readout(mut self, readout: Readout, plastic: bool) -> Result<Self> {
+        let output = self.layer_configs.len().saturating_sub(1);
+        if output < 3 {
+            return Err(CsdpError::Config(
+                "a readout needs input, context, hidden and output layers".to_string(),
+            ));
+        }
+        let num_hidden = output - 2;
+        let hidden = match readout {
+            Readout::LastOnly => vec![num_hidden - 1],
+            Readout::All => (0..num_hidden).collect(),
+            Readout::Layers(mut hidden) => {
+                hidden.sort_unstable();
+                hidden.dedup();
+                if hidden.is_empty() || hidden.iter().any(|&i| i >= num_hidden) {
+                    return Err(CsdpError::Config(format!(
+                        "readout layers {:?} must be a non-empty subset of the {} hidden layers",
+                        hidden, num_hidden
+                    )));
+                }
+                hidden
+            }
+        };
+
+        self.synapse_configs
+            .retain(|syn| !(syn.post_layer == output && (2..output).contains(&syn.pre_layer)));
+        for i in hidden {
+            self.synapse_configs.push(SynapseConfig {
+                pre_layer: 2 + i,
+                post_layer: output,
+                synapse_type: SynapseType::CSDP,
+            });
+        }
+        self.readout_plastic = plastic;
+        Ok(self)
+    }
+
+    /// Normalize every input with running per-channel statistics before the input layer
+    /// encodes it (see [`NormalizationLayer`]), for raw streams such as live robot readings
+    /// whose offsets drift. The statistics are saved with the model;
+    /// [`Model::input_normalization_mut`] freezes or resets them.
+    pub fn with_input_normalization(mut self, config: NormalizationConfig) -> Result<Self> {
+        if !config.clip.is_finite() || config.clip <= 0.0 {
+            return Err(CsdpError::Config(format!(
+                "normalization clip must be positive, got {}",
+                config.clip
+            )));
+        }
+        self.input_normalization = Some(config);
+        Ok(self)
+    }
+
+    /// Score every step's novelty from a prediction error or a layer's activity and scale
+    /// plasticity by it (see [`novelty`]). Layer indices refer to the final topology, so add
+    /// a predictive front-end before this.
+    pub fn with_novelty(mut self, config: novelty::NoveltyConfig) -> Result<Self> {
+        if let Some(&layer) = config
+            .layers()
+            .iter()
+            .find(|&&layer| layer >= self.layer_configs.len())
+        {
+            return Err(CsdpError::Config(format!(
+                "novelty source layer {} doesn't exist",
+                layer
+            )));
+        }
+        if let novelty::NoveltySource::PredictionError { prediction, target } = config.source
+            && self.layer_configs[prediction].size() != self.layer_configs[target].size()
+        {
+            return Err(CsdpError::Config(format!(
+                "prediction layer {} and target layer {} differ in size",
+                prediction, target
+            )));
+        }
+        if !config.tau.is_finite() || config.tau <= 0.0 || config.max_novelty < 0.0 {
+            return Err(CsdpError::Config(
+                "novelty needs a positive tau and a non-negative max_novelty".to_string(),
+            ));
+        }
+        self.novelty = Some(config);
+        Ok(self)
+    }
+
+    /// Regularize the activity of every LIF layer, e.g. to keep deep stacks from settling into
+    /// runaway synchronous firing; see [`ActivityRegularizer`]
+    pub fn with_activity_regularizer(mut self, regularizer: ActivityRegularizer) -> Self {
+        self.activity_regularizer = Some(regularizer);
+        self
+    }
+
+    /// Put a predictive-coding front-end between the input layer and the rest of the network:
+    /// a [`LayerConfig::Prediction`] layer at index 2 learns to predict the next input through
+    /// a [`SynapseType::Predictive`] synapse, and every synapse that read the input layer reads
+    /// the prediction instead. Layers from index 2 on move up by one.
+    pub fn with_predictive_front_end(mut self, learning_rate: f32) -> Result<Self> {
+        let Some(input) = self.layer_configs.first() else {
+            return Err(CsdpError::Config(
+                "a predictive front-end needs an input layer".to_string(),
+            ));
         };
+        let size = input.size();
+        let front_end = 2.min(self.layer_configs.len());
+
+        let connections = self
+            .synapse_configs
+            .iter_mut()
+            .map(|syn| (&mut syn.pre_layer, &mut syn.post_layer))
+            .chain(
+                self.feedback_configs
+                    .iter_mut()
+                    .map(|fb| (&mut fb.pre_layer, &mut fb.post_layer)),
+            );
+        for (pre_layer, post_layer) in connections {
+            for layer in [&mut *pre_layer, post_layer] {
+                if *layer >= front_end {
+                    *layer += 1;
+                }
+            }
+            if *pre_layer == 0 {
+                *pre_layer = front_end;
+            }
+        }
+        let pairs = self.tied_weights.iter_mut().chain(&mut self.offloaded_synapses);
+        let distance_pairs = self
+            .distance_connectivity
+            .iter_mut()
+            .map(|(pre, post, _)| (pre, post));
+        for layer in pairs
+            .map(|pair| (&mut pair.0, &mut pair.1))
+            .chain(distance_pairs)
+            .flat_map(|(a, b)| [a, b])
+        {
+            if *layer >= front_end {
+                *layer += 1;
+            }
+        }
+        for (layer, _) in self.topographies.iter_mut() {
+            if *layer >= front_end {
+                *layer += 1;
+            }
+        }
+        // The front-end takes the input's place as a source, neurons arranged alike
+        for (pre, _, _) in self.distance_connectivity.iter_mut() {
+            if *pre == 0 {
+                *pre = front_end;
+            }
+        }
+        if let Some(&(_, topography)) = self.topographies.iter().find(|&&(l, _)| l == 0) {
+            self.topographies.push((front_end, topography));
+        }
+        self.layer_configs.insert(
+            front_end,
+            LayerConfig::Prediction {
+                size,
+                name: Some("Prediction".to_string()),
+            },
+        );
+        // The front-end shares the input layer's device
+        if let Some(devices) = self.layer_devices.as_mut() {
+            let input_device = devices[0].clone();
+            devices.insert(front_end, input_device);
+        }
+        self.synapse_configs.push(SynapseConfig {
+            pre_layer: 0,
+            post_layer: front_end,
+            synapse_type: SynapseType::Predictive { learning_rate },
+        });
+        Ok(self)
+    }
+}
 
-        Self::from_config(config, device).ok()
+impl Model {
+    /// Create a new CSDP model with default configuration (backward compatible).
+    pub fn new(
+        input_size: usize,
+        output_size: usize,
+        hidden_sizes: Vec<usize>,
+        device: &Device,
+        dt: f32,
+        input_bounds: Option<Vec<usize>>,
+    ) -> Result<Self> {
+        let config =
+            ModelConfig::standard(input_size, output_size, hidden_sizes, dt, input_bounds)?;
+        Self::from_config(config, device)
     }
 
     /// Create a model from a configuration
-    pub fn from_config(config: ModelConfig, device: &Device) -> CandleResult<Self> {
+    pub fn from_config(config: ModelConfig, device: &Device) -> Result<Self> {
+        let layer_devices = match config.layer_devices {
+            Some(devices) if devices.len() != config.layer_configs.len() => {
+                return Err(CsdpError::Config(format!(
+                    "{} layer devices given for {} layers",
+                    devices.len(),
+                    config.layer_configs.len()
+                )));
+            }
+            Some(devices) => devices,
+            None => vec![device.clone(); config.layer_configs.len()],
+        };
+        if let Some(seed) = config.seed {
+            crate::seed::reseed(seed, device)?;
+            for layer_device in &layer_devices {
+                if !layer_device.same_device(device) {
+                    crate::seed::reseed(seed, layer_device)?;
+                }
+            }
+        }
+
         let mut layers: Vec<Box<dyn Layer>> = vec![];
         let mut layer_metadata = vec![];
 
         // Create layers
         for (id, layer_config) in config.layer_configs.iter().enumerate() {
-            let (layer, metadata) = Self::create_layer(id, layer_config, device)?;
+            let (layer, metadata) = Self::create_layer(
+                id,
+                layer_config,
+                config.activity_regularizer,
+                &layer_devices[id],
+            )?;
             layers.push(layer);
             layer_metadata.push(metadata);
         }
+        // The raw input excludes the label rows the model appends
+        let input_normalization = match (config.input_normalization, layers.first()) {
+            (Some(normalization), Some(input)) => Some(NormalizationLayer::new(
+                input.size() - config.label_input.unwrap_or(0),
+                normalization,
+                &layer_devices[0],
+            )?),
+            _ => None,
+        };
 
         // Create synapses
         let mut synapses = vec![];
-
-        for (synapse_id, syn_config) in config.synapse_configs.iter().enumerate() {
-            let pre_size = layers[syn_config.pre_layer].size();
-            let post_size = layers[syn_config.post_layer].size();
-
-            // Forward synapse
-            let synapse =
-                Self::create_synapse(syn_config.synapse_type, pre_size, post_size, device)?;
+        let output = layers.len().saturating_sub(1);
+
+        // Feedback connections follow the configured synapses
+        let connections = config
+            .synapse_configs
+            .iter()
+            .map(|syn| (syn.pre_layer, syn.post_layer, syn.synapse_type, 1.0))
+            .chain(
+                config
+                    .feedback_configs
+                    .iter()
+                    .map(|fb| (fb.pre_layer, fb.post_layer, fb.synapse_type, fb.gain)),
+            );
+        for (synapse_id, (pre_layer, post_layer, synapse_type, gain)) in connections.enumerate() {
+            if pre_layer >= layers.len() || post_layer >= layers.len() {
+                return Err(CsdpError::Config(format!(
+                    "synapse {} connects layer {} -> {} but only {} layers exist",
+                    synapse_id,
+                    pre_layer,
+                    post_layer,
+                    layers.len()
+                )));
+            }
+            let pre_size = layers[pre_layer].size();
+            let post_size = layers[post_layer].size();
+
+            // Synapse on the device of the layer it feeds, unless it is offloaded; offloaded
+            // weights are drawn on the CPU, so they never occupy accelerator memory
+            let compute = &layer_devices[post_layer];
+            let host = Device::Cpu;
+            let offloaded = config.offloaded_synapses.contains(&(pre_layer, post_layer));
+            if offloaded
+                && !matches!(
+                    synapse_type,
+                    SynapseType::CSDP | SynapseType::SparseCSDP { .. }
+                )
+            {
+                return Err(CsdpError::Config(format!(
+                    "synapse {} ({:?}) keeps per-step state and can't be offloaded",
+                    synapse_id, synapse_type
+                )));
+            }
+            let device = if offloaded { &host } else { compute };
+            let mut synapse = Self::create_synapse(synapse_type, pre_size, post_size, device)?;
+            if let Some(&(_, _, connectivity)) = config
+                .distance_connectivity
+                .iter()
+                .find(|&&(pre, post, _)| (pre, post) == (pre_layer, post_layer))
+            {
+                let coordinates = |layer: usize| {
+                    topography_of(&config.topographies, layer)
+                        .map(|topography| topography.coordinates())
+                };
+                synapse = Box::new(Topographic::new(
+                    synapse,
+                    &coordinates(pre_layer)?,
+                    &coordinates(post_layer)?,
+                    &connectivity,
+                )?);
+            }
+            if offloaded {
+                synapse = Box::new(Offloaded::new(synapse, &host, compute)?);
+            }
             let metadata = SynapseMetadata {
                 id: synapse_id,
-                pre_layer: syn_config.pre_layer,
-                post_layer: syn_config.post_layer,
-                synapse_type: format!("{:?}", syn_config.synapse_type),
-                is_learning: true,
+                pre_layer,
+                post_layer,
+                synapse_type: format!("{:?}", synapse_type),
+                // a static readout projects from the hidden layers to the output layer
+                is_learning: config.readout_plastic
+                    || post_layer != output
+                    || !(2..output).contains(&pre_layer),
+                enabled: true,
+                gain,
             };
             log::info!("creating synapse: {:?}", metadata);
             synapses.push(SynapseConnection { metadata, synapse });
         }
+        if let Some(&(pre, post, _)) = config
+            .distance_connectivity
+            .iter()
+            .find(|&&(pre, post, _)| config.tied_weights.contains(&(post, pre)))
+        {
+            return Err(CsdpError::Config(format!(
+                "the connection {} -> {} takes its weights from the tied {} -> {} and can't be \
+                 drawn by distance",
+                pre, post, post, pre
+            )));
+        }
+        let tied_weights = Self::tie_synapses(&config.tied_weights, &mut synapses)?;
+        let unconnected = config.distance_connectivity.iter().find(|&&(pre, post, _)| {
+            !synapses
+                .iter()
+                .any(|s| s.metadata.pre_layer == pre && s.metadata.post_layer == post)
+        });
+        if let Some(&(pre, post, _)) = unconnected {
+            return Err(CsdpError::Config(format!(
+                "cannot connect layers {} -> {} by distance: there is no such synapse",
+                pre, post
+            )));
+        }
+        if let Some(&(pre, post)) = config.offloaded_synapses.iter().find(|&&(pre, post)| {
+            !synapses
+                .iter()
+                .any(|s| s.metadata.pre_layer == pre && s.metadata.post_layer == post)
+        }) {
+            return Err(CsdpError::Config(format!(
+                "cannot offload layers {} -> {}: there is no such synapse",
+                pre, post
+            )));
+        }
 
-        Ok(Self {
+        let topographies = (0..layers.len())
+            .map(|layer| topography_of(&config.topographies, layer).ok())
+            .collect();
+
+        let mut model = Self {
+            synapse_groups: parallel::SynapseGroups::new(&synapses),
             layers,
             layer_metadata,
             synapses,
             is_learning: true,
+            event_driven: false,
             dt: config.dt,
             device: device.clone(),
-        })
+            timings: None,
+            run_timings: None,
+            adaptive_dt: config.adaptive_dt,
+            early_exit: None,
+            label_smoothing: None,
+            tied_weights,
+            check_finite: false,
+            step_count: 0,
+            label_input: config.label_input,
+            input_label: None,
+            input_normalization,
+            novelty: config.novelty.map(novelty::NoveltyDetector::new),
+            consolidation: None,
+            context_gating: None,
+            layer_devices,
+            topographies,
+            step_dt: config.dt,
+            engine: Box::new(engine::ClockDriven),
+        };
+        model.sync_tied_weights()?;
+        Ok(model)
+    }
+
+    /// `(forward, backward)` synapse ids of the `(pre, post)` layer pairs of `tied`, freezing
+    /// each backward synapse
+    fn tie_synapses(
+        tied: &[(LayerId, LayerId)],
+        synapses: &mut [SynapseConnection],
+    ) -> Result<Vec<(SynapseId, SynapseId)>> {
+        fn find(synapses: &[SynapseConnection], pre: LayerId, post: LayerId) -> Result<SynapseId> {
+            let id = synapses
+                .iter()
+                .position(|s| s.metadata.pre_layer == pre && s.metadata.post_layer == post)
+                .ok_or_else(|| {
+                    CsdpError::Config(format!(
+                        "cannot tie layers {} and {}: there is no synapse {} -> {}",
+                        pre.min(post),
+                        pre.max(post),
+                        pre,
+                        post
+                    ))
+                })?;
+            if !synapses[id].synapse.get_state()?.contains_key("weights") {
+                return Err(CsdpError::Config(format!(
+                    "synapse {} ({}) has no dense weights to tie",
+                    id, synapses[id].metadata.synapse_type
+                )));
+            }
+            Ok(id)
+        }
+        let mut pairs = Vec::with_capacity(tied.len());
+        for &(pre, post) in tied {
+            let forward = find(synapses, pre, post)?;
+            let backward = find(synapses, post, pre)?;
+            synapses[backward].metadata.is_learning = false;
+            pairs.push((forward, backward));
+        }
+        Ok(pairs)
     }
 
     fn create_layer(
         id: usize,
         config: &LayerConfig,
+        regularizer: Option<ActivityRegularizer>,
         device: &Device,
     ) -> CandleResult<(Box<dyn Layer>, LayerMetadata)> {
         let (layer, layer_type, size, name) = match config {
@@ -288,13 +1204,22 @@ impl Model {
                 trace_tau,
                 name,
             } => {
-                let mod_signal = Box::new(StandardModSignal::new(
-                    *size,
-                    *trace_tau,
-                    1.0,
-                    (*size as f32) / 2.0, // approx omega
-                    device,
-                )?);
+                let mut mod_signal: Box<dyn ModSignalGenerator> =
+                    Box::new(StandardModSignal::new(
+                        *size,
+                        *trace_tau,
+                        1.0,
+                        (*size as f32) / 2.0, // approx omega
+                        device,
+                    )?);
+                if let Some(regularizer) = regularizer {
+                    mod_signal = Box::new(RegularizedModSignal::new(
+                        mod_signal,
+                        regularizer,
+                        *size,
+                        device,
+                    )?);
+                }
                 let layer = LIFLayer::new(*size, *tau, *g_thr, *thresh_lambda, mod_signal, device)?;
                 let name = name.clone().unwrap_or_else(|| format!("Layer_{}", id));
                 (
@@ -304,6 +1229,16 @@ impl Model {
                     name,
                 )
             }
+            LayerConfig::Prediction { size, name } => {
+                let layer = PredictionLayer::new(*size, device)?;
+                let name = name.clone().unwrap_or_else(|| format!("Layer_{}", id));
+                (
+                    Box::new(layer) as Box<dyn Layer>,
+                    "Prediction".to_string(),
+                    *size,
+                    name,
+                )
+            }
         };
 
         // Calculate position based on layer index
@@ -335,6 +1270,27 @@ impl Model {
                 let csdp = CSDP::new(pre_size, post_size, device)?;
                 Ok(Box::new(csdp))
             }
+            SynapseType::SparseCSDP {
+                density,
+                block_size,
+            } => {
+                let sparse = SparseCSDP::new(pre_size, post_size, density, block_size, device)?;
+                Ok(Box::new(sparse))
+            }
+            SynapseType::Predictive { learning_rate } => {
+                if pre_size != post_size {
+                    return Err(candle_core::Error::Msg(format!(
+                        "predictive synapse needs equal pre and post sizes, got {} and {}",
+                        pre_size, post_size
+                    )));
+                }
+                let predictive = PredictiveSynapse::new(pre_size, learning_rate, device)?;
+                Ok(Box::new(predictive))
+            }
+            SynapseType::MultiTrace(config) => {
+                let traced = MultiTraceCSDP::new(pre_size, post_size, config, device)?;
+                Ok(Box::new(traced))
+            }
         }
     }
 
@@ -348,64 +1304,704 @@ impl Model {
         self.is_learning = false;
     }
 
-    /// Run one timestep: update layers and synapses once.
+    /// Smooth every context passed to `step` by `epsilon` (see
+    /// [`crate::shaping::smooth_labels`]), or stop smoothing with None
+    pub fn set_label_smoothing(&mut self, epsilon: Option<f32>) -> CandleResult<()> {
+        if let Some(epsilon) = epsilon {
+            crate::shaping::check_label_smoothing(epsilon)?;
+        }
+        self.label_smoothing = epsilon;
+        Ok(())
+    }
+
+    pub fn label_smoothing(&self) -> Option<f32> {
+        self.label_smoothing
+    }
+
+    /// Start collecting per-layer and per-synapse timings in `step`, see [`profiler`]. Forces
+    /// a device synchronization around every layer and synapse and runs them sequentially, so
+    /// it slows down runs somewhat.
+    pub fn enable_profiling(&mut self) {
+        self.timings = Some(StepTimings::new(self.layers.len(), self.synapses.len()));
+        self.run_timings = Some(StepTimings::new(self.layers.len(), self.synapses.len()));
+    }
+
+    pub fn disable_profiling(&mut self) {
+        self.timings = None;
+        self.run_timings = None;
+    }
+
+    /// Average per-step phase timings since the last call, resetting the accumulators.
+    /// Returns None if profiling is disabled or no steps ran.
+    pub fn take_perf_stats(&mut self) -> Option<PerfStats> {
+        let timings = self.timings.as_mut()?;
+        if timings.steps == 0 {
+            return None;
+        }
+
+        let steps = timings.steps as f32;
+        let per_step_ms = |d: Duration| d.as_secs_f32() * 1000.0 / steps;
+        let elapsed = timings.since.elapsed().as_secs_f32();
+
+        let stats = PerfStats {
+            layer_step_ms: timings
+                .layer_step
+                .iter()
+                .zip(self.layer_metadata.iter())
+                .map(|(&d, meta)| (meta.name.clone(), per_step_ms(d)))
+                .collect(),
+            synapse_forward_ms: per_step_ms(timings.synapse_forward),
+            synapse_update_ms: per_step_ms(timings.synapse_update),
+            timesteps_per_second: if elapsed > 0.0 { steps / elapsed } else { 0.0 },
+            gpu_memory_mb: None,
+        };
+
+        if let Some(run) = self.run_timings.as_mut() {
+            run.add(timings);
+        }
+        *timings = StepTimings::new(self.layers.len(), self.synapses.len());
+        Some(stats)
+    }
+
+    /// Estimate how important every weight is to the task being trained, so that
+    /// [`Model::consolidate`] can protect it from later tasks (see [`consolidation`]). Replaces
+    /// any earlier consolidation state.
+    pub fn enable_consolidation(
+        &mut self,
+        config: consolidation::ConsolidationConfig,
+    ) -> Result<()> {
+        if !config.strength.is_finite() || config.strength < 0.0 {
+            return Err(CsdpError::Config(format!(
+                "consolidation strength must be non-negative, got {}",
+                config.strength
+            )));
+        }
+        self.consolidation = Some(consolidation::Consolidation::new(config));
+        Ok(())
+    }
+
+    /// Stop estimating importance and release the weights of every consolidated task
+    pub fn disable_consolidation(&mut self) {
+        self.consolidation = None;
+    }
+
+    /// End the current task: fold its importance estimate into the consolidated importance and
+    /// anchor the weights, which later training is then pulled back toward
+    pub fn consolidate(&mut self) -> Result<()> {
+        let Some(consolidation) = self.consolidation.as_mut() else {
+            return Err(CsdpError::Config(
+                "consolidation is not enabled, see Model::enable_consolidation".to_string(),
+            ));
+        };
+        consolidation.consolidate(&mut self.synapses)?;
+        Ok(())
+    }
+
+    pub fn consolidation(&self) -> Option<&consolidation::Consolidation> {
+        self.consolidation.as_ref()
+    }
+
+    /// Give every one of `config.num_tasks` tasks its own random subset of each hidden LIF
+    /// layer and make task 0 the context (see [`context`]). Replaces any earlier gates; gates
+    /// saved with the model are restored by a later `load`.
+    pub fn enable_context_gating(&mut self, config: context::ContextGatingConfig) -> Result<()> {
+        let output = self.layers.len() - 1;
+        let hidden: Vec<_> = (2..output)
+            .filter(|&id| self.layer_metadata[id].layer_type == "LIF")
+            .map(|id| (id, self.layers[id].size(), self.layer_devices[id].clone()))
+            .collect();
+        self.context_gating = Some(context::ContextGating::new(config, &hidden)?);
+        Ok(())
+    }
+
+    pub fn disable_context_gating(&mut self) {
+        self.context_gating = None;
+    }
+
+    /// Switch to the subsets of task `task_id`; takes effect from the next step
+    pub fn set_context(&mut self, task_id: usize) -> Result<()> {
+        let Some(gating) = self.context_gating.as_mut() else {
+            return Err(CsdpError::Config(
+                "context gating is not enabled, see Model::enable_context_gating".to_string(),
+            ));
+        };
+        gating.set_task(task_id)?;
+        Ok(())
+    }
+
+    /// The current task, if context gating is enabled
+    pub fn context(&self) -> Option<usize> {
+        self.context_gating.as_ref().map(|g| g.task())
+    }
+
+    pub fn context_gating(&self) -> Option<&context::ContextGating> {
+        self.context_gating.as_ref()
+    }
+
+    /// Spatial arrangement of a layer's neurons, see [`ModelConfig::with_topography`]
+    pub fn topography(&self, id: LayerId) -> Option<Topography> {
+        self.topographies.get(id).copied().flatten()
+    }
+
+    /// Device layer `id` and the synapses feeding it live on
+    pub fn layer_device(&self, id: LayerId) -> &Device {
+        &self.layer_devices[id]
+    }
+
+    /// Set the sample label of every layer, on the layer's device
+    pub fn set_positive_sample(&mut self, label: &Tensor) -> CandleResult<()> {
+        for (layer, device) in self.layers.iter_mut().zip(&self.layer_devices) {
+            layer.set_positive_sample(&label.to_device(device)?);
+        }
+        Ok(())
+    }
+
+    /// Metadata of synapse `id`, for per-connection changes
+    fn synapse_metadata_mut(&mut self, id: SynapseId) -> Result<&mut SynapseMetadata> {
+        self.synapses
+            .iter_mut()
+            .map(|s| &mut s.metadata)
+            .find(|m| m.id == id)
+            .ok_or_else(|| CsdpError::Config(format!("the model has no synapse {}", id)))
+    }
+
+    /// Stop plasticity of synapse `id`; it keeps propagating activity with its current weights
+    pub fn freeze_synapse(&mut self, id: SynapseId) -> Result<()> {
+        self.synapse_metadata_mut(id)?.is_learning = false;
+        Ok(())
+    }
+
+    /// Resume plasticity of a frozen synapse
+    pub fn unfreeze_synapse(&mut self, id: SynapseId) -> Result<()> {
+        self.synapse_metadata_mut(id)?.is_learning = true;
+        Ok(())
+    }
+
+    /// Ablate synapse `id` for lesion experiments: it no longer feeds its post layer and
+    /// doesn't learn. Its weights are kept, so `enable_connection` restores it.
+    pub fn disable_connection(&mut self, id: SynapseId) -> Result<()> {
+        self.synapse_metadata_mut(id)?.enabled = false;
+        Ok(())
+    }
+
+    /// Reconnect a synapse removed with `disable_connection`
+    pub fn enable_connection(&mut self, id: SynapseId) -> Result<()> {
+        self.synapse_metadata_mut(id)?.enabled = true;
+        Ok(())
+    }
+
+    /// Scale synapse `id`'s input to its post layer, e.g. to tune how strongly a feedback
+    /// connection drives the layer below
+    pub fn set_synapse_gain(&mut self, id: SynapseId, gain: f32) -> Result<()> {
+        self.synapse_metadata_mut(id)?.gain = gain;
+        Ok(())
+    }
+
+    /// `(forward, backward)` ids of the synapse pairs tied with
+    /// [`ModelConfig::with_tied_weights`]
+    pub fn tied_weights(&self) -> &[(SynapseId, SynapseId)] {
+        &self.tied_weights
+    }
+
+    /// Copy the transposed weights of every tied forward synapse into its backward synapse.
+    /// Runs after every weight update and load; call it after changing a forward synapse's
+    /// weights by other means.
+    pub fn sync_tied_weights(&mut self) -> CandleResult<()> {
+        for &(forward, backward) in &self.tied_weights {
+            let weights = self.synapses[forward].synapse.get_state()?["weights"]
+                .t()?
+                .contiguous()?
+                .to_device(&self.layer_devices[self.synapses[backward].metadata.post_layer])?;
+            let mut state = self.synapses[backward].synapse.get_state()?;
+            state.insert("weights".to_string(), weights);
+            self.synapses[backward].synapse.set_state(&state)?;
+        }
+        Ok(())
+    }
+
+    /// Teacher forcing for supervised tasks: clamp the output layer's spikes to `target`,
+    /// (output_size, n) with n dividing the batch size, for positive samples and to its
+    /// complement `1 - target` for negative ones, as labelled by `set_positive_sample`. A target
+    /// with fewer columns than the batch is repeated, so (output_size, batch) covers both
+    /// phases of [`Model::process_contrastive`]. None releases the clamp.
+    pub fn clamp_output(&mut self, target: Option<&Tensor>) -> CandleResult<()> {
+        let output = self.layers.len() - 1;
+        let target = target
+            .map(|t| t.to_device(&self.layer_devices[output]))
+            .transpose()?;
+        self.layers[output].clamp_output(target.as_ref())
+    }
+
+    /// Label appended to every input of a label-conditioned model (see
+    /// [`ModelConfig::with_label_input`]): a one-hot label or any other context embedding,
+    /// (num_labels, batch) or (num_labels, 1) for the whole batch. None presents zeros, e.g.
+    /// when classifying.
+    pub fn set_input_label(&mut self, label: Option<&Tensor>) -> CandleResult<()> {
+        let Some(rows) = self.label_input else {
+            return Err(candle_core::Error::Msg(
+                "the model's input isn't label-conditioned".to_string(),
+            ));
+        };
+        if let Some(label) = label
+            && label.dims()[0] != rows
+        {
+            return Err(candle_core::Error::Msg(format!(
+                "label has {} rows, expected {}",
+                label.dims()[0],
+                rows
+            )));
+        }
+        self.input_label = label
+            .map(|l| l.to_device(&self.layer_devices[0]))
+            .transpose()?;
+        Ok(())
+    }
+
+    /// [`Model::set_input_label`] with the one-hot labels of `classes`, one per sample
+    pub fn set_input_classes(&mut self, classes: &[usize]) -> CandleResult<()> {
+        let rows = self.label_input.unwrap_or(0);
+        let mut one_hot = vec![0.0f32; rows * classes.len()];
+        for (sample, &class) in classes.iter().enumerate() {
+            if class >= rows {
+                return Err(candle_core::Error::Msg(format!(
+                    "class {} is out of range for {} labels",
+                    class, rows
+                )));
+            }
+            one_hot[class * classes.len() + sample] = 1.0;
+        }
+        let label = Tensor::from_vec(one_hot, (rows, classes.len()), &self.layer_devices[0])?;
+        self.set_input_label(Some(&label))
+    }
+
+    /// Running input statistics, if the model normalizes its input (see
+    /// [`ModelConfig::with_input_normalization`])
+    pub fn input_normalization(&self) -> Option<&NormalizationLayer> {
+        self.input_normalization.as_ref()
+    }
+
+    /// Mutable input statistics, e.g. to freeze them for evaluation with
+    /// [`NormalizationLayer::set_learning`]
+    pub fn input_normalization_mut(&mut self) -> Option<&mut NormalizationLayer> {
+        self.input_normalization.as_mut()
+    }
+
+    /// `input` with the current input label (or zeros) appended below it
+    fn append_input_label(&self, input: &Tensor, rows: usize) -> CandleResult<Tensor> {
+        let batch_size = input.dim(1)?;
+        let label = match &self.input_label {
+            Some(label) if label.dim(1)? == batch_size => label.clone(),
+            Some(label) if label.dim(1)? == 1 => label.broadcast_as((rows, batch_size))?,
+            Some(label) => {
+                return Err(candle_core::Error::Msg(format!(
+                    "label batch of {} doesn't match the input batch of {}",
+                    label.dim(1)?,
+                    batch_size
+                )));
+            }
+            None => Tensor::zeros((rows, batch_size), input.dtype(), input.device())?,
+        };
+        Tensor::cat(&[input, &label.to_dtype(input.dtype())?], 0)
+    }
+
+    /// Length of the next step: `dt`, or the length chosen by adaptive timestep control
+    pub fn step_dt(&self) -> f32 {
+        self.step_dt
+    }
+
+    /// Run one timestep: update layers and synapses once, as the model's
+    /// [`engine::SimulationEngine`] does it.
     pub fn step(&mut self, input: &Tensor, context: Option<&Tensor>) -> CandleResult<()> {
-        // Reset inputs for all layers except input layer
+        // The engine is moved out for the step so it can drive the model
+        let mut engine = std::mem::replace(&mut self.engine, Box::new(engine::ClockDriven));
+        let result = engine.step(self, input, context);
+        self.engine = engine;
+        result
+    }
+
+    /// Replace the simulation engine, see [`engine`]
+    pub fn set_engine(
+        &mut self,
+        mut engine: Box<dyn engine::SimulationEngine>,
+    ) -> CandleResult<()> {
+        let mut previous = std::mem::replace(&mut self.engine, Box::new(engine::ClockDriven));
+        previous.detach(self)?;
+        engine.attach(self)?;
+        self.engine = engine;
+        Ok(())
+    }
+
+    /// Name of the simulation engine
+    pub fn engine_name(&self) -> &'static str {
+        self.engine.name()
+    }
+
+    /// First phase of a timestep: clear the layers' inputs, present `input` (with the label,
+    /// if conditioned on one) and `context`, and step the input and context layers. Returns
+    /// the length of the step.
+    pub fn begin_step(&mut self, input: &Tensor, context: Option<&Tensor>) -> CandleResult<f32> {
+        let dt = self.step_dt;
+
+        // Reset inputs for all layers
         for layer in self.layers.iter_mut() {
             layer.reset_input()?;
         }
 
+        let mut mark = Instant::now();
+
         // Add input to first layer and step it
-        self.layers[0].add_input(input)?;
-        self.layers[0].step(self.dt)?;
+        let input = input.to_device(&self.layer_devices[0])?;
+        let input = match self.input_normalization.as_mut() {
+            Some(normalization) => {
+                normalization.reset_input()?;
+                normalization.add_input(&input)?;
+                normalization.step(dt)?;
+                normalization.output()?.clone()
+            }
+            None => input,
+        };
+        let input = match self.label_input {
+            Some(rows) => self.append_input_label(&input, rows)?,
+            None => input,
+        };
+        self.layers[0].add_input(&input)?;
+        self.layers[0].step(dt)?;
+        if let Some(timings) = self.timings.as_mut() {
+            timings.layer_step[0] += lap(&self.device, &mut mark)?;
+        }
 
         // add context to second layer and step it
         if let Some(label) = context {
-            self.layers[1].add_input(label)?;
-            self.layers[1].step(self.dt)?;
+            let label = match self.label_smoothing {
+                Some(epsilon) => crate::shaping::spread_labels(label, epsilon)?,
+                None => label.clone(),
+            };
+            self.layers[1].add_input(&label.to_device(&self.layer_devices[1])?)?;
+            self.layers[1].step(dt)?;
+            if let Some(timings) = self.timings.as_mut() {
+                timings.layer_step[1] += lap(&self.device, &mut mark)?;
+            }
         }
 
-        // Synapse forward pass
-        for syn_conn in self.synapses.iter_mut() {
-            let pre_layer_id = syn_conn.metadata.pre_layer;
-            let post_layer_id = syn_conn.metadata.post_layer;
-            let pre_activity = self.layers[pre_layer_id].output()?.clone();
-            let post_input = syn_conn.synapse.forward(&pre_activity)?;
+        if let Some(gating) = &self.context_gating {
+            let batch_size = input.dim(1)?;
+            for (id, gate) in gating.active() {
+                let size = gate.dim(0)?;
+                self.layers[*id].add_input(&gate.broadcast_as((size, batch_size))?)?;
+            }
+        }
+        Ok(dt)
+    }
+
+    /// Synapse forward pass: add every enabled synapse's output to its post layer's input.
+    /// With `event_driven`, only the neurons that spiked are propagated.
+    pub fn propagate(&mut self, event_driven: bool) -> CandleResult<()> {
+        let mut mark = Instant::now();
+        if let Some(timings) = self.timings.as_mut() {
+            // Sequential so each synapse's time can be attributed
+            let each = &mut timings.synapse_forward_each;
+            each.resize(self.synapses.len(), Duration::ZERO);
+            parallel::forward_synapses_timed(
+                &mut self.layers,
+                &self.synapses,
+                &mut self.synapse_groups,
+                event_driven,
+                each,
+            )?;
+            timings.synapse_forward += lap(&self.device, &mut mark)?;
+        } else {
+            parallel::forward_synapses(
+                &mut self.layers,
+                &self.synapses,
+                &mut self.synapse_groups,
+                event_driven,
+                parallel::enabled(&self.device),
+            )?;
+        }
+        Ok(())
+    }
 
-            self.layers[post_layer_id].add_input(&post_input)?;
+    /// Step all layers except the input and context layer (stepped by `begin_step`)
+    pub fn step_layers(&mut self, dt: f32) -> CandleResult<()> {
+        let mut mark = Instant::now();
+        if let Some(timings) = self.timings.as_mut() {
+            // Sequential so each layer's time can be attributed
+            for (i, layer) in self.layers.iter_mut().enumerate().skip(2) {
+                layer.step(dt)?;
+                timings.layer_step[i] += lap(&self.device, &mut mark)?;
+            }
+        } else {
+            parallel::step_layers(&mut self.layers[2..], dt, parallel::enabled(&self.device))?;
+        }
+        if self.check_finite {
+            self.check_layers()?;
+        }
+        if let Some(novelty) = self.novelty.as_mut() {
+            novelty.observe(&self.layers, dt)?;
+        }
+        Ok(())
+    }
+
+    /// Synapse weight updates for a step of length `dt`, if learning is enabled
+    pub fn apply_plasticity(&mut self, dt: f32) -> CandleResult<()> {
+        let mut mark = Instant::now();
+        if self.is_learning {
+            let novelty_scale = match &self.novelty {
+                Some(novelty) => novelty.plasticity_scale()?,
+                None => 1.0,
+            };
+            let scale = dt / self.dt * novelty_scale;
+            let par = parallel::enabled(&self.device);
+            let before = self
+                .consolidation
+                .as_ref()
+                .map(|consolidation| consolidation.before_update(&mut self.synapses));
+            match self.timings.as_mut() {
+                Some(timings) => {
+                    let each = &mut timings.synapse_update_each;
+                    each.resize(self.synapses.len(), Duration::ZERO);
+                    parallel::update_synapses_timed(
+                        &self.layers,
+                        &mut self.synapses,
+                        dt,
+                        scale,
+                        each,
+                    )?
+                }
+                None => parallel::update_synapses_scaled(
+                    &self.layers,
+                    &mut self.synapses,
+                    dt,
+                    scale,
+                    par,
+                )?,
+            }
+            if let (Some(consolidation), Some(before)) = (self.consolidation.as_mut(), before) {
+                consolidation.after_update(&mut self.synapses, before)?;
+            }
+            self.sync_tied_weights()?;
+            if self.check_finite {
+                self.check_synapses()?;
+            }
+        }
+        if let Some(timings) = self.timings.as_mut() {
+            timings.synapse_update += lap(&self.device, &mut mark)?;
         }
+        Ok(())
+    }
 
-        // Step all layers except the input and context layer (already stepped)
-        for layer in self.layers.iter_mut().skip(2) {
-            layer.step(self.dt)?;
+    /// Last phase of a timestep of length `dt`: count it and choose the length of the next
+    /// one under adaptive timestep control
+    pub fn end_step(&mut self, dt: f32) -> CandleResult<()> {
+        if let Some(timings) = self.timings.as_mut() {
+            timings.steps += 1;
         }
+        if let Some(adaptive) = self.adaptive_dt {
+            self.step_dt = adaptive.next_dt(dt, self.potential_change()?);
+        }
+        self.step_count += 1;
+        Ok(())
+    }
 
-        // Synapse weight updates
-        // Update weights if learning is enabled
-        for syn_conn in self.synapses.iter_mut() {
-            if self.is_learning && syn_conn.metadata.is_learning {
-                let pre_layer_id = syn_conn.metadata.pre_layer;
-                let post_layer_id = syn_conn.metadata.post_layer;
-                let pre_activity = self.layers[pre_layer_id].output()?.clone();
+    /// Steps run since the last reset
+    pub fn step_count(&self) -> usize {
+        self.step_count
+    }
 
-                syn_conn.synapse.update_weights(
-                    &pre_activity,
-                    &mut self.layers[post_layer_id],
-                    self.dt,
-                )?;
+    /// Novelty of the last step per batch column, (1, batch), if the model scores novelty
+    /// (see [`ModelConfig::with_novelty`])
+    pub fn novelty(&self) -> Option<&Tensor> {
+        self.novelty.as_ref().and_then(|n| n.score())
+    }
+
+    /// Novelty of the last step averaged over the batch, e.g. as an intrinsic reward
+    pub fn novelty_score(&self) -> CandleResult<Option<f32>> {
+        match &self.novelty {
+            Some(novelty) => novelty.mean_score(),
+            None => Ok(None),
+        }
+    }
+
+    /// Error naming the first layer whose output, membrane potential or modulatory signal
+    /// holds a NaN or infinity
+    fn check_layers(&self) -> CandleResult<()> {
+        for (id, (layer, meta)) in self.layers.iter().zip(&self.layer_metadata).enumerate() {
+            let tensors = [
+                ("output", layer.output()?),
+                ("activity", layer.activity()?),
+                ("modulatory signal", layer.get_mod_signal()),
+            ];
+            for (what, tensor) in tensors {
+                if has_non_finite(tensor)? {
+                    let message = format!(
+                        "NaN/Inf in the {} of layer {} ({}) at timestep {} after reset",
+                        what, id, meta.name, self.step_count
+                    );
+                    log::error!("{}", message);
+                    return Err(candle_core::Error::Msg(message));
+                }
             }
         }
+        Ok(())
+    }
 
+    /// Error naming the first learning synapse whose parameters hold a NaN or infinity
+    fn check_synapses(&self) -> CandleResult<()> {
+        for syn_conn in &self.synapses {
+            let meta = &syn_conn.metadata;
+            if !meta.is_learning || !meta.enabled {
+                continue;
+            }
+            let mut state: Vec<_> = syn_conn.synapse.get_state()?.into_iter().collect();
+            state.sort_by(|a, b| a.0.cmp(&b.0));
+            for (key, tensor) in state {
+                if has_non_finite(&tensor)? {
+                    let message = format!(
+                        "NaN/Inf in the {} of synapse {} ({}->{}, {}) after the update of \
+                         timestep {} after reset",
+                        key,
+                        meta.id,
+                        self.layer_metadata[meta.pre_layer].name,
+                        self.layer_metadata[meta.post_layer].name,
+                        meta.synapse_type,
+                        self.step_count
+                    );
+                    log::error!("{}", message);
+                    return Err(candle_core::Error::Msg(message));
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Largest membrane-potential change of the last step over all layers
+    fn potential_change(&self) -> CandleResult<f32> {
+        let mut changes = Vec::new();
+        for layer in &self.layers {
+            if let Some(change) = layer.potential_change()? {
+                changes.push(change.to_device(&self.device)?.reshape(1)?);
+            }
+        }
+        if changes.is_empty() {
+            return Ok(0.0);
+        }
+        Tensor::cat(&changes, 0)?.max_all()?.to_scalar::<f32>()
+    }
+
+    /// Run `timesteps` steps of `dt`, or with adaptive timestep control the steps covering the
+    /// same simulated time, calling `after_step` with each step's length relative to `dt`.
+    /// `after_step` returning false ends the run early. Returns the number of steps run.
+    fn simulate(
+        &mut self,
+        input: &Tensor,
+        context: Option<&Tensor>,
+        timesteps: usize,
+        mut after_step: impl FnMut(&Self, f32) -> CandleResult<bool>,
+    ) -> CandleResult<usize> {
+        if self.adaptive_dt.is_none() {
+            for step in 0..timesteps {
+                self.step(input, context)?;
+                if !after_step(self, 1.0)? {
+                    return Ok(step + 1);
+                }
+            }
+            return Ok(timesteps);
+        }
+
+        let mut remaining = timesteps as f32 * self.dt;
+        let mut steps = 0;
+        // Stop short of float noise rather than run a vanishing extra step
+        while remaining > self.dt * 1e-3 {
+            self.step_dt = self.step_dt.min(remaining);
+            let dt = self.step_dt;
+            self.step(input, context)?;
+            let more = after_step(self, dt / self.dt)?;
+            remaining -= dt;
+            steps += 1;
+            if !more {
+                break;
+            }
+        }
+        Ok(steps)
+    }
+
     pub fn reset(&mut self, batch_size: usize) -> CandleResult<()> {
         for layer in self.layers.iter_mut() {
             layer.reset(batch_size)?;
         }
+        if let Some(normalization) = self.input_normalization.as_mut() {
+            normalization.reset(batch_size)?;
+        }
+        if let Some(novelty) = self.novelty.as_mut() {
+            novelty.reset();
+        }
+        for syn_conn in self.synapses.iter_mut() {
+            syn_conn.synapse.reset();
+        }
+        self.step_dt = match self.adaptive_dt {
+            Some(adaptive) => self.dt.max(adaptive.min_dt).min(adaptive.max_dt),
+            None => self.dt,
+        };
+        self.step_count = 0;
         Ok(())
     }
 
+    /// Independent copy of the model, including weights, adaptive thresholds and layer state.
+    /// Tensors are immutable and reference counted, so no weight data is copied until one of
+    /// the two models learns.
+    pub fn fork(&self) -> Self {
+        Self {
+            layers: self.layers.iter().map(|layer| layer.box_clone()).collect(),
+            layer_metadata: self.layer_metadata.clone(),
+            synapses: self.synapses.clone(),
+            is_learning: self.is_learning,
+            event_driven: self.event_driven,
+            dt: self.dt,
+            device: self.device.clone(),
+            timings: None,
+            run_timings: None,
+            adaptive_dt: self.adaptive_dt,
+            early_exit: self.early_exit,
+            label_smoothing: self.label_smoothing,
+            tied_weights: self.tied_weights.clone(),
+            check_finite: self.check_finite,
+            step_count: self.step_count,
+            label_input: self.label_input,
+            input_label: self.input_label.clone(),
+            input_normalization: self.input_normalization.clone(),
+            novelty: self.novelty.clone(),
+            consolidation: self.consolidation.clone(),
+            context_gating: self.context_gating.clone(),
+            layer_devices: self.layer_devices.clone(),
+            topographies: self.topographies.clone(),
+            step_dt: self.step_dt,
+            engine: self.engine.box_clone(),
+            synapse_groups: self.synapse_groups.clone(),
+        }
+    }
+
+    /// Run `process` on every input with its own fork of the model, concurrently on the CPU,
+    /// and return each final output. Unlike stacking samples along the batch dimension, the
+    /// forks share no adaptive thresholds, so each result is what a `process` of that input
+    /// alone would give. Learning is disabled in the forks and `self` is left untouched.
+    /// Under a global seed the inputs run one after another on the calling thread instead,
+    /// since the host RNG of [`crate::seed`] is per thread and a rayon worker's draws would
+    /// depend on scheduling.
+    pub fn process_many(&self, inputs: &[Tensor], timesteps: usize) -> CandleResult<Vec<Tensor>> {
+        let run = |input: &Tensor| -> CandleResult<Tensor> {
+            let mut model = self.fork();
+            model.disable_learning();
+            Ok(model
+                .process(input, timesteps, false, &self.device)?
+                .final_output)
+        };
+        if parallel::enabled(&self.device) && crate::seed::global_seed().is_none() {
+            inputs.par_iter().map(run).collect()
+        } else {
+            inputs.iter().map(run).collect()
+        }
+    }
+
     /// run for T timesteps, and return collected outputs (batched)
     pub fn process(
         &mut self,
@@ -420,22 +2016,139 @@ impl Model {
             final_output: Tensor::zeros((0, batch_size), DType::F32, &self.device)?,
         };
         self.reset(batch_size)?;
-        for _ in 0..timesteps {
-            self.step(input, None)?; // no labels provided during inference
-
-            if collect_data && !self.layers.is_empty() {
-                let output = self.layers.last().unwrap().output()?;
-                out.output_activity.push(output.clone());
+        let mut convergence = self.early_exit.map(RateConvergence::new);
+        // no labels provided during inference
+        self.simulate(input, None, timesteps, |model, weight| {
+            let Some(layer) = model.layers.last() else {
+                return Ok(true);
+            };
+            if collect_data {
+                out.output_activity.push(layer.output()?.to_device(&model.device)?);
             }
-        }
+            match convergence.as_mut() {
+                Some(convergence) => Ok(!convergence.update(layer.output()?, weight)?),
+                None => Ok(true),
+            }
+        })?;
 
         if !self.layers.is_empty() {
-            out.final_output = self.layers.last().unwrap().output()?.clone();
+            out.final_output = self.layers.last().unwrap().output()?.to_device(&self.device)?;
         }
 
         Ok(out)
     }
 
+    /// `process` returning the output spikes as one time-major raster, (steps, output_size,
+    /// batch); see [`crate::raster`]
+    pub fn process_raster(&mut self, input: &Tensor, timesteps: usize) -> CandleResult<Tensor> {
+        let device = self.device.clone();
+        self.process(input, timesteps, true, &device)?.raster()
+    }
+
+    /// `process` without the reset: membrane potentials, spikes, traces and thresholds carry
+    /// over from the previous call, for continuous input such as robot control where there is
+    /// no trial boundary. Only a change of batch size resets the model, since the old state
+    /// can't be carried over.
+    pub fn process_streaming(
+        &mut self,
+        input: &Tensor,
+        context: Option<&Tensor>,
+        timesteps: usize,
+        collect_data: bool,
+    ) -> CandleResult<ProcessOutput> {
+        let batch_size = input.dims().get(1).copied().unwrap_or(1);
+        if self.layers[0].output()?.dim(1)? != batch_size {
+            self.reset(batch_size)?;
+        }
+        let mut out = ProcessOutput {
+            output_activity: vec![],
+            final_output: Tensor::zeros((0, batch_size), DType::F32, &self.device)?,
+        };
+        self.simulate(input, context, timesteps, |model, _| {
+            if collect_data {
+                let output = model.layers.last().unwrap().output()?;
+                out.output_activity.push(output.to_device(&model.device)?);
+            }
+            Ok(true)
+        })?;
+        out.final_output = self.layers.last().unwrap().output()?.to_device(&self.device)?;
+        Ok(out)
+    }
+
+    /// Run the positive and negative CSDP phases in one pass. `positive` and `negative` are
+    /// `(features, batch)` inputs stacked along the batch dimension and labelled 1 and 0, so
+    /// every layer and synapse processes both phases together instead of in two serial runs.
+    /// Contexts are given for both phases or for neither. Plasticity (if learning is enabled)
+    /// averages each update over both phases and the adaptive thresholds see their combined
+    /// activity, so results are close to, not identical with, running the phases serially.
+    /// Every layer is labelled positive afterwards.
+    pub fn process_contrastive(
+        &mut self,
+        positive: &Tensor,
+        negative: &Tensor,
+        positive_context: Option<&Tensor>,
+        negative_context: Option<&Tensor>,
+        timesteps: usize,
+    ) -> CandleResult<ContrastiveOutput> {
+        let batch_size = positive.dim(1)?;
+        if negative.dim(1)? != batch_size {
+            return Err(candle_core::Error::Msg(format!(
+                "positive batch has {} samples but negative batch has {}",
+                batch_size,
+                negative.dim(1)?
+            )));
+        }
+        let input = Tensor::cat(&[positive, negative], 1)?;
+        let context = match (positive_context, negative_context) {
+            (Some(pos), Some(neg)) => Some(Tensor::cat(&[pos, neg], 1)?),
+            (None, None) => None,
+            _ => {
+                return Err(candle_core::Error::Msg(
+                    "contexts must be given for both phases or neither".to_string(),
+                ));
+            }
+        };
+        let label = Tensor::cat(
+            &[
+                Tensor::ones((1, batch_size), DType::F32, &self.device)?,
+                Tensor::zeros((1, batch_size), DType::F32, &self.device)?,
+            ],
+            1,
+        )?;
+        self.set_positive_sample(&label)?;
+
+        self.reset(2 * batch_size)?;
+        // Hidden LIF layers only, not a predictive front-end
+        let hidden: Vec<usize> = (2..self.layers.len() - 1)
+            .filter(|&i| self.layer_metadata[i].layer_type == "LIF")
+            .collect();
+        let mut goodness = Tensor::zeros(2 * batch_size, DType::F32, &self.device)?;
+        // Steps are weighted by their length, so adaptive steps average like nominal ones
+        self.simulate(&input, context.as_ref(), timesteps, |model, weight| {
+            for &i in &hidden {
+                let mut step_goodness = model.layers[i]
+                    .output()?
+                    .sqr()?
+                    .mean(0)?
+                    .to_device(&model.device)?;
+                if weight != 1.0 {
+                    step_goodness = step_goodness.affine(weight as f64, 0.0)?;
+                }
+                goodness = (&goodness + step_goodness)?;
+            }
+            Ok(true)
+        })?;
+        let goodness = (goodness / (timesteps.max(1) * hidden.len().max(1)) as f64)?;
+        // The stacked label only fits a batch of 2 * batch_size; label every layer positive
+        // again so later runs of any batch size work
+        self.set_positive_sample(&Tensor::ones((1, 1), DType::F32, &self.device)?)?;
+
+        Ok(ContrastiveOutput {
+            positive_goodness: goodness.narrow(0, 0, batch_size)?,
+            negative_goodness: goodness.narrow(0, batch_size, batch_size)?,
+        })
+    }
+
     /// Get a specific neuron's output value for visualization
     pub fn get_neuron_output(&self, layer_id: LayerId, neuron_idx: usize) -> CandleResult<f32> {
         if layer_id >= self.layers.len() {
@@ -489,6 +2202,7 @@ impl Model {
                 velocity: (0.0, 0.0),
                 current_activity: output_vec,
                 spike_count,
+                topography: self.topography(i),
             };
 
             layer_vis_infos.push(layer_info);
@@ -505,6 +2219,8 @@ impl Model {
                 post_layer: syn_conn.metadata.post_layer,
                 synapse_type: syn_conn.metadata.synapse_type.clone(),
                 weight_stats,
+                is_learning: syn_conn.metadata.is_learning,
+                enabled: syn_conn.metadata.enabled,
             };
 
             synapse_vis_infos.push(synapse_info);
@@ -513,6 +2229,7 @@ impl Model {
         Ok(crate::visualization::ModelStructure {
             layers: layer_vis_infos,
             synapses: synapse_vis_infos,
+            novelty: self.novelty_score()?,
         })
     }
 
@@ -528,8 +2245,13 @@ impl Model {
         Ok(output_vec)
     }
 
+    /// Learned parameters under descriptive names, for export
+    pub fn named_tensors(&self) -> CandleResult<Vec<(String, Tensor)>> {
+        named_tensors(&self.layers, &self.layer_metadata, &self.synapses)
+    }
+
     /// Save the model parameters to a safetensors file
-    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> CandleResult<()> {
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<()> {
         let mut tensor_map = std::collections::HashMap::new();
 
         for syn_conn in &self.synapses {
@@ -540,30 +2262,215 @@ impl Model {
             }
         }
 
+        collect_layer_state(&self.layers, &mut tensor_map)?;
+        if let Some(normalization) = &self.input_normalization {
+            for (key, tensor) in normalization.get_state()? {
+                tensor_map.insert(format!("{}{}", NORMALIZATION_PREFIX, key), tensor);
+            }
+        }
+        if let Some(consolidation) = &self.consolidation {
+            for (key, tensor) in consolidation.get_state() {
+                tensor_map.insert(format!("{}{}", CONSOLIDATION_PREFIX, key), tensor);
+            }
+        }
+        if let Some(gating) = &self.context_gating {
+            for (key, tensor) in gating.get_state() {
+                tensor_map.insert(format!("{}{}", CONTEXT_GATING_PREFIX, key), tensor);
+            }
+        }
         candle_core::safetensors::save(&tensor_map, path)?;
         Ok(())
     }
 
-    /// Load the model parameters from a safetensors file
-    pub fn load<P: AsRef<std::path::Path>>(&mut self, path: P) -> CandleResult<()> {
-        let loaded_tensors = candle_core::safetensors::load(path, &self.device)?;
+    /// Load the model parameters from a safetensors file; every tensor goes to the device of
+    /// the layer that owns it
+    pub fn load<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<()> {
+        let loaded_tensors = candle_core::safetensors::load(path, &Device::Cpu)?;
 
         for syn_conn in self.synapses.iter_mut() {
             let prefix = format!("synapse_{}_", syn_conn.metadata.id);
-            let mut state = std::collections::HashMap::new();
-
-            for (key, tensor) in &loaded_tensors {
-                if key.starts_with(&prefix) {
-                    let local_key = key.strip_prefix(&prefix).unwrap().to_string();
-                    state.insert(local_key, tensor.clone());
-                }
+            let device = &self.layer_devices[syn_conn.metadata.post_layer];
+            let state = state_on(&loaded_tensors, &prefix, |_| device)?;
+            if !state.is_empty() {
+                syn_conn.synapse.set_state(&state)?;
             }
+        }
 
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            let prefix = format!("layer_{}_", i);
+            let state = state_on(&loaded_tensors, &prefix, |_| &self.layer_devices[i])?;
             if !state.is_empty() {
-                syn_conn.synapse.set_state(&state)?;
+                layer.set_state(&state)?;
             }
         }
+        if let Some(normalization) = self.input_normalization.as_mut() {
+            let device = &self.layer_devices[0];
+            let state = state_on(&loaded_tensors, NORMALIZATION_PREFIX, |_| device)?;
+            normalization.set_state(&state)?;
+        }
+        if let Some(consolidation) = self.consolidation.as_mut() {
+            // Keyed `<synapse index>_<tensor>_<what>`, on the post layer of the synapse
+            let state = state_on(&loaded_tensors, CONSOLIDATION_PREFIX, |key| {
+                key.split('_')
+                    .next()
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .and_then(|index| self.synapses.get(index))
+                    .map_or(&self.device, |conn| {
+                        &self.layer_devices[conn.metadata.post_layer]
+                    })
+            })?;
+            consolidation.set_state(&mut self.synapses, &state)?;
+        }
+        if let Some(gating) = self.context_gating.as_mut() {
+            // Keyed `layer_<id>`
+            let state = state_on(&loaded_tensors, CONTEXT_GATING_PREFIX, |key| {
+                key.strip_prefix("layer_")
+                    .and_then(|id| id.parse::<usize>().ok())
+                    .and_then(|id| self.layer_devices.get(id))
+                    .unwrap_or(&self.device)
+            })?;
+            gating.set_state(&state)?;
+        }
+        self.sync_tied_weights()?;
 
         Ok(())
     }
 }
+
+/// The entries of `loaded` under `prefix`, keyed without it and each moved to the device
+/// `device_of` picks for its key
+fn state_on<'a>(
+    loaded: &std::collections::HashMap<String, Tensor>,
+    prefix: &str,
+    device_of: impl Fn(&str) -> &'a Device,
+) -> CandleResult<std::collections::HashMap<String, Tensor>> {
+    let mut state = std::collections::HashMap::new();
+    for (key, tensor) in loaded {
+        if let Some(local) = key.strip_prefix(prefix) {
+            state.insert(local.to_string(), tensor.to_device(device_of(local))?);
+        }
+    }
+    Ok(state)
+}
+
+/// The topography of `layer`, which a distance-dependent connection needs
+fn topography_of(topographies: &[(usize, Topography)], layer: LayerId) -> Result<Topography> {
+    topographies
+        .iter()
+        .find(|&&(l, _)| l == layer)
+        .map(|&(_, topography)| topography)
+        .ok_or_else(|| {
+            CsdpError::Config(format!(
+                "layer {} needs a topography to be connected by distance",
+                layer
+            ))
+        })
+}
+
+/// Whether a float `tensor` holds a NaN or infinity: `x - x` is NaN exactly there, and a NaN
+/// survives the sum
+fn has_non_finite(tensor: &Tensor) -> CandleResult<bool> {
+    if !tensor.dtype().is_float() {
+        return Ok(false);
+    }
+    let sum = tensor.sub(tensor)?.sum_all()?.to_dtype(DType::F32)?;
+    Ok(sum.to_scalar::<f32>()?.is_nan())
+}
+
+/// Synapse weights/biases as `<pre>-><post>.<key>` and layer parameters as `<layer>.<key>`,
+/// using the layer names from `layer_metadata`
+pub(crate) fn named_tensors(
+    layers: &[Box<dyn Layer>],
+    layer_metadata: &[LayerMetadata],
+    synapses: &[SynapseConnection],
+) -> CandleResult<Vec<(String, Tensor)>> {
+    let name = |id: LayerId| {
+        layer_metadata
+            .get(id)
+            .map(|m| m.name.clone())
+            .unwrap_or_else(|| format!("layer_{}", id))
+    };
+    let mut tensors = Vec::new();
+    for syn_conn in synapses {
+        let prefix = format!(
+            "{}->{}",
+            name(syn_conn.metadata.pre_layer),
+            name(syn_conn.metadata.post_layer)
+        );
+        let mut state: Vec<_> = syn_conn.synapse.get_state()?.into_iter().collect();
+        state.sort_by(|a, b| a.0.cmp(&b.0));
+        for (key, tensor) in state {
+            tensors.push((format!("{}.{}", prefix, key), tensor));
+        }
+    }
+    for (i, layer) in layers.iter().enumerate() {
+        let mut state: Vec<_> = layer.get_state()?.into_iter().collect();
+        state.sort_by(|a, b| a.0.cmp(&b.0));
+        for (key, tensor) in state {
+            tensors.push((format!("{}.{}", name(i), key), tensor));
+        }
+    }
+    Ok(tensors)
+}
+
+/// Prefix of the input normalization statistics in saved models
+const NORMALIZATION_PREFIX: &str = "input_normalization_";
+
+/// Prefix of the consolidated importance and anchors in saved models
+const CONSOLIDATION_PREFIX: &str = "consolidation_";
+
+/// Prefix of the context gates in saved models
+const CONTEXT_GATING_PREFIX: &str = "context_gating_";
+
+/// Add the state of every layer to `tensor_map` as `layer_<index>_<key>`
+pub(crate) fn collect_layer_state(
+    layers: &[Box<dyn Layer>],
+    tensor_map: &mut std::collections::HashMap<String, Tensor>,
+) -> CandleResult<()> {
+    for (i, layer) in layers.iter().enumerate() {
+        for (key, tensor) in layer.get_state()? {
+            tensor_map.insert(format!("layer_{}_{}", i, key), tensor);
+        }
+    }
+    Ok(())
+}
+
+/// Restore layer state written by [`collect_layer_state`]; layers missing from `loaded`
+/// (e.g. files saved before layer state was stored) keep their current state
+pub(crate) fn restore_layer_state(
+    layers: &mut [Box<dyn Layer>],
+    loaded: &std::collections::HashMap<String, Tensor>,
+) -> CandleResult<()> {
+    for (i, layer) in layers.iter_mut().enumerate() {
+        let prefix = format!("layer_{}_", i);
+        let state: std::collections::HashMap<String, Tensor> = loaded
+            .iter()
+            .filter_map(|(key, tensor)| {
+                key.strip_prefix(&prefix)
+                    .map(|local| (local.to_string(), tensor.clone()))
+            })
+            .collect();
+        if !state.is_empty() {
+            layer.set_state(&state)?;
+        }
+    }
+    Ok(())
+}
+
+/// Time since `mark`, after waiting for queued device work, then move `mark` to now
+fn lap(device: &Device, mark: &mut Instant) -> CandleResult<Duration> {
+    device.synchronize()?;
+    let elapsed = mark.elapsed();
+    *mark = Instant::now();
+    Ok(elapsed)
+}
+
+impl crate::visualization::publisher::SnapshotSource for Model {
+    fn snapshot(&self) -> CandleResult<crate::visualization::ModelStructure> {
+        self.get_visualization_snapshot()
+    }
+
+    fn layer_activity(&self, layer_id: LayerId) -> CandleResult<Vec<f32>> {
+        self.get_layer_activity(layer_id)
+    }
+}
@@ -0,0 +1,159 @@
+//! Novelty (surprise) signal for curiosity-driven exploration.
+//!
+//! A [`NoveltyDetector`] watches either the error of a prediction layer (such as the front-end
+//! of [`super::ModelConfig::with_predictive_front_end`]) or the population activity of a
+//! layer, and scores each step by how far that measure rises above its running baseline, in
+//! baseline standard deviations. The score modulates plasticity: with a nonzero `gain`, every
+//! synapse update of the step is scaled by `1 + gain * novelty`, so surprising input is learned
+//! faster than familiar input. It is also published with the model snapshot as the
+//! `csdp_novelty` metric, and [`super::Model::novelty_score`] returns it for use as an
+//! intrinsic reward.
+
+use crate::layer::Layer;
+use crate::synapse::LayerId;
+use candle_core::{Result as CandleResult, Tensor};
+
+/// Added to the baseline variance before dividing by its standard deviation
+const VARIANCE_EPS: f64 = 1e-4;
+
+/// What a [`NoveltyDetector`] measures
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoveltySource {
+    /// Mean absolute error between the output of layer `prediction` at the previous step and
+    /// the output of layer `target` now; the layers must have the same size
+    PredictionError {
+        prediction: LayerId,
+        target: LayerId,
+    },
+    /// Fraction of the neurons of `layer` that spiked
+    Activity { layer: LayerId },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoveltyConfig {
+    pub source: NoveltySource,
+    /// time constant of the running baseline, in units of `dt`
+    pub tau: f32,
+    /// plasticity is scaled by `1 + gain * novelty`; 0 only reports the score
+    pub gain: f32,
+    /// scores are clipped to `[0, max_novelty]`
+    pub max_novelty: f32,
+}
+
+impl NoveltyConfig {
+    pub fn new(source: NoveltySource) -> Self {
+        Self {
+            source,
+            tau: 100.0,
+            gain: 1.0,
+            max_novelty: 5.0,
+        }
+    }
+
+    /// Layers the source reads
+    pub fn layers(&self) -> Vec<LayerId> {
+        match self.source {
+            NoveltySource::PredictionError { prediction, target } => vec![prediction, target],
+            NoveltySource::Activity { layer } => vec![layer],
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct NoveltyDetector {
+    pub config: NoveltyConfig,
+    /// output of the prediction layer at the previous step, scored against this step
+    previous_prediction: Option<Tensor>,
+    /// running mean and variance of the measure, (1, batch); None until the first measure
+    baseline: Option<(Tensor, Tensor)>,
+    /// novelty of the last step, (1, batch)
+    score: Option<Tensor>,
+}
+
+impl NoveltyDetector {
+    pub fn new(config: NoveltyConfig) -> Self {
+        Self {
+            config,
+            previous_prediction: None,
+            baseline: None,
+            score: None,
+        }
+    }
+
+    /// Novelty of the last step, one value per batch column
+    pub fn score(&self) -> Option<&Tensor> {
+        self.score.as_ref()
+    }
+
+    /// Novelty of the last step, averaged over the batch
+    pub fn mean_score(&self) -> CandleResult<Option<f32>> {
+        self.score
+            .as_ref()
+            .map(|s| s.mean_all()?.to_scalar::<f32>())
+            .transpose()
+    }
+
+    /// Factor the plasticity of the last step is scaled by
+    pub fn plasticity_scale(&self) -> CandleResult<f32> {
+        if self.config.gain == 0.0 {
+            return Ok(1.0);
+        }
+        Ok(1.0 + self.config.gain * self.mean_score()?.unwrap_or(0.0))
+    }
+
+    /// Forget the last prediction, e.g. between sequences; the baseline is kept
+    pub fn reset(&mut self) {
+        self.previous_prediction = None;
+        self.score = None;
+    }
+
+    /// Score the step the layers just took, then fold it into the baseline
+    pub fn observe(&mut self, layers: &[Box<dyn Layer>], dt: f32) -> CandleResult<()> {
+        let Some(measure) = self.measure(layers)? else {
+            self.score = None;
+            return Ok(());
+        };
+        let batch_size = measure.dims()[1];
+        let (mean, var) = match self.baseline.take() {
+            Some((mean, var)) if mean.dims()[1] == batch_size => (mean, var),
+            // The first measure (or a new batch size) starts the baseline; nothing is novel yet
+            _ => (measure.clone(), measure.zeros_like()?),
+        };
+
+        let deviation = measure.sub(&mean)?;
+        let std = var.affine(1.0, VARIANCE_EPS)?.sqrt()?;
+        self.score = Some(
+            deviation
+                .div(&std)?
+                .clamp(0.0f32, self.config.max_novelty)?,
+        );
+
+        let alpha = (dt / self.config.tau).min(1.0) as f64;
+        let mean = mean.add(&deviation.affine(alpha, 0.0)?)?;
+        let var = var.add(&deviation.sqr()?.sub(&var)?.affine(alpha, 0.0)?)?;
+        self.baseline = Some((mean, var));
+        Ok(())
+    }
+
+    /// The configured measure of this step, (1, batch); None while there is nothing to score
+    fn measure(&mut self, layers: &[Box<dyn Layer>]) -> CandleResult<Option<Tensor>> {
+        match self.config.source {
+            NoveltySource::Activity { layer } => Ok(Some(layers[layer].output()?.mean_keepdim(0)?)),
+            NoveltySource::PredictionError { prediction, target } => {
+                let actual = layers[target].output()?;
+                let error = match self.previous_prediction.take() {
+                    Some(predicted) if predicted.dims() == actual.dims() => Some(
+                        predicted
+                            .to_device(actual.device())?
+                            .sub(actual)?
+                            .abs()?
+                            .mean_keepdim(0)?,
+                    ),
+                    _ => None,
+                };
+                self.previous_prediction = Some(layers[prediction].output()?.clone());
+                Ok(error)
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Composite observation spaces for [`RobotModel`](super::robot_model::RobotModel).
+//!
+//! An [`ObservationSpace`] lists the modalities the robot senses, each under a name, and
+//! concatenates their encodings into one input layer. Every modality occupies a contiguous
+//! slice of the input, in the order listed: population-coded joint angles, a camera frame
+//! average-pooled to a small grid of intensities, the gripper's open/closed state, the
+//! previous command and the reward. The space is plain data that round-trips through JSON, so
+//! a model for another set of sensors is a config file rather than a code change:
+//!
+//! ```json
+//! { "slices": [
+//!     { "name": "arm", "kind": "joints", "lower": [-1, -1, -1, -1, -1, -1],
+//!       "upper": [1, 1, 1, 1, 1, 1], "population": 5 },
+//!     { "name": "wrist_cam", "kind": "camera", "width": 64, "height": 48,
+//!       "out_width": 16, "out_height": 12 },
+//!     { "name": "gripper", "kind": "gripper", "threshold": 0.5 } ] }
+//! ```
+
+use super::robot_model::{NUM_MOTORS, ObservationEncoder, population_code};
+use crate::error::{CsdpError, Result};
+use candle_core::{Device, Result as CandleResult, Tensor};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::Path;
+
+fn one() -> usize {
+    1
+}
+
+fn unit() -> f64 {
+    1.0
+}
+
+/// How one named slice of an [`ObservationSpace`] is sensed and encoded
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Modality {
+    /// Joint angles scaled to [0, 1] between `lower` and `upper` and coded by `population`
+    /// Gaussian tuning curves each, as by [`ObservationEncoder`]
+    Joints {
+        lower: Vec<f64>,
+        upper: Vec<f64>,
+        #[serde(default = "one")]
+        population: usize,
+    },
+    /// Grayscale frame, `width * height` row-major intensities in `[0, max_value]`,
+    /// average-pooled to `out_width * out_height` firing probabilities
+    Camera {
+        width: usize,
+        height: usize,
+        out_width: usize,
+        out_height: usize,
+        #[serde(default = "unit")]
+        max_value: f64,
+    },
+    /// One reading, closed above `threshold`, coded as two neurons (open, closed)
+    Gripper { threshold: f64 },
+    /// The previous command of every motor in units of the step size, mapped from [-1, 1] to
+    /// [0, 1]; filled in by
+    /// [`RobotModel::observe`](super::robot_model::RobotModel::observe)
+    PreviousAction,
+    /// The reward in [-1, 1], mapped to [0, 1]; filled in by
+    /// [`RobotModel::observe`](super::robot_model::RobotModel::observe)
+    Reward,
+}
+
+impl Modality {
+    /// Number of values a reading of this modality has
+    pub fn reading_size(&self) -> usize {
+        match self {
+            Modality::Joints { lower, .. } => lower.len(),
+            Modality::Camera { width, height, .. } => width * height,
+            Modality::Gripper { .. } | Modality::Reward => 1,
+            Modality::PreviousAction => NUM_MOTORS,
+        }
+    }
+
+    /// Number of input neurons the encoded reading takes
+    pub fn input_size(&self) -> usize {
+        match self {
+            Modality::Joints {
+                lower, population, ..
+            } => lower.len() * (*population).max(1),
+            Modality::Camera {
+                out_width,
+                out_height,
+                ..
+            } => out_width * out_height,
+            Modality::Gripper { .. } => 2,
+            Modality::PreviousAction => NUM_MOTORS,
+            Modality::Reward => 1,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        let valid = match self {
+            Modality::Joints { lower, upper, .. } => {
+                !lower.is_empty() && lower.len() == upper.len()
+            }
+            Modality::Camera {
+                width,
+                height,
+                out_width,
+                out_height,
+                max_value,
+            } => {
+                (1..=*width).contains(out_width)
+                    && (1..=*height).contains(out_height)
+                    && *max_value > 0.0
+            }
+            Modality::Gripper { threshold } => threshold.is_finite(),
+            Modality::PreviousAction | Modality::Reward => true,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(CsdpError::Config(format!("invalid modality {:?}", self)))
+        }
+    }
+
+    /// Append the encoding of `reading` to `values`
+    fn encode_into(&self, reading: &[f64], values: &mut Vec<f32>) {
+        let signed = |v: f64| ((v + 1.0) / 2.0).clamp(0.0, 1.0) as f32;
+        match self {
+            Modality::Joints {
+                lower,
+                upper,
+                population,
+            } => {
+                let normalized =
+                    ObservationEncoder::new(lower.clone(), upper.clone()).normalize(reading);
+                values.extend(population_code(&normalized, *population));
+            }
+            Modality::Camera {
+                width,
+                height,
+                out_width,
+                out_height,
+                max_value,
+            } => {
+                let mut sums = vec![0.0f64; out_width * out_height];
+                let mut counts = vec![0usize; out_width * out_height];
+                for (i, &pixel) in reading.iter().enumerate() {
+                    let (x, y) = (i % width, i / width);
+                    let cell = (y * out_height / height) * out_width + x * out_width / width;
+                    sums[cell] += pixel;
+                    counts[cell] += 1;
+                }
+                values.extend(
+                    sums.iter().zip(&counts).map(|(&sum, &n)| {
+                        (sum / n.max(1) as f64 / max_value).clamp(0.0, 1.0) as f32
+                    }),
+                );
+            }
+            Modality::Gripper { threshold } => {
+                let closed = reading[0] > *threshold;
+                values.extend([f32::from(u8::from(!closed)), f32::from(u8::from(closed))]);
+            }
+            Modality::PreviousAction | Modality::Reward => {
+                values.extend(reading.iter().map(|&v| signed(v)));
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ObservationSlice {
+    pub name: String,
+    #[serde(flatten)]
+    pub modality: Modality,
+}
+
+/// Named modalities concatenated into one input layer; see the module docs
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ObservationSpace {
+    pub slices: Vec<ObservationSlice>,
+}
+
+impl ObservationSpace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a modality under `name`, which must be unique
+    pub fn with(mut self, name: &str, modality: Modality) -> Result<Self> {
+        modality.validate()?;
+        if self.slice(name).is_some() {
+            return Err(CsdpError::Config(format!(
+                "observation space already has a slice named {}",
+                name
+            )));
+        }
+        self.slices.push(ObservationSlice {
+            name: name.to_string(),
+            modality,
+        });
+        Ok(self)
+    }
+
+    /// Number of input neurons of the whole observation
+    pub fn input_size(&self) -> usize {
+        self.slices.iter().map(|s| s.modality.input_size()).sum()
+    }
+
+    /// Input neurons of every slice, in order
+    pub fn ranges(&self) -> Vec<(&str, Range<usize>)> {
+        let mut start = 0;
+        self.slices
+            .iter()
+            .map(|s| {
+                let end = start + s.modality.input_size();
+                let range = start..end;
+                start = end;
+                (s.name.as_str(), range)
+            })
+            .collect()
+    }
+
+    /// Input neurons of the slice `name`
+    pub fn slice(&self, name: &str) -> Option<Range<usize>> {
+        self.ranges()
+            .into_iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, range)| range)
+    }
+
+    /// (input_size, 1) tensor for one set of `readings`, given by slice name. Every slice
+    /// needs a reading of its `reading_size`, except previous-action and reward slices,
+    /// which encode a missing reading as zero, i.e. 0.5.
+    pub fn encode(&self, readings: &[(&str, &[f64])], device: &Device) -> CandleResult<Tensor> {
+        let mut values = Vec::with_capacity(self.input_size());
+        for slice in &self.slices {
+            let reading = readings
+                .iter()
+                .find(|(name, _)| *name == slice.name)
+                .map(|(_, reading)| *reading);
+            let zeros = vec![0.0; slice.modality.reading_size()];
+            let reading = match (reading, &slice.modality) {
+                (Some(reading), _) => reading,
+                (None, Modality::PreviousAction | Modality::Reward) => &zeros,
+                (None, _) => {
+                    return Err(candle_core::Error::Msg(format!(
+                        "no reading for observation slice {}",
+                        slice.name
+                    )));
+                }
+            };
+            if reading.len() != slice.modality.reading_size() {
+                return Err(candle_core::Error::Msg(format!(
+                    "observation slice {} expects {} values, got {}",
+                    slice.name,
+                    slice.modality.reading_size(),
+                    reading.len()
+                )));
+            }
+            slice.modality.encode_into(reading, &mut values);
+        }
+        let size = values.len();
+        Tensor::from_vec(values, (size, 1), device)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Load and validate a space written by [`ObservationSpace::save`] or by hand
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let loaded: Self = serde_json::from_reader(file)?;
+        let mut space = Self::new();
+        for slice in loaded.slices {
+            space = space.with(&slice.name, slice.modality)?;
+        }
+        if space.slices.is_empty() {
+            return Err(CsdpError::Config(
+                "observation space has no slices".to_string(),
+            ));
+        }
+        Ok(space)
+    }
+}
@@ -0,0 +1,447 @@
+//! Per-timestep phases shared by the CSDP models. Within a phase every layer/synapse only reads
+//! outputs produced by an earlier phase, so on the CPU each phase is spread over the rayon pool.
+//! Results are identical to the sequential order (post-synaptic inputs are still summed in
+//! synapse order), up to the rounding of the fused forward passes of [`SynapseGroups`]. Set `RAYON_NUM_THREADS=1` to force sequential execution; seeded runs of
+//! configs with a `Bernoulli` layer past the input/context slots need it, since the host RNG in
+//! [`crate::seed`] is per thread.
+
+use crate::layer::Layer;
+use crate::synapse::{LayerId, SynapseConnection};
+use candle_core::{Device, Result as CandleResult, Tensor, TensorId};
+use rayon::prelude::*;
+use std::time::Duration;
+// std's Instant panics on wasm32-unknown-unknown
+use web_time::Instant;
+
+/// CUDA kernels already run on a single stream, so only the CPU benefits
+pub fn enabled(device: &Device) -> bool {
+    cfg!(not(target_arch = "wasm32")) && device.is_cpu() && rayon::current_num_threads() > 1
+}
+
+/// In event-driven mode, layers with more than this fraction of neurons active in any batch
+/// column take the dense forward pass, since gathering their weight columns would cost more
+pub const EVENT_DENSITY_LIMIT: f32 = 0.2;
+
+/// Synapse indices grouped by post layer, each group in synapse order, and the dense synapses
+/// grouped by pre layer. Built once and reused every timestep; `forward_synapses` rebuilds it
+/// if the synapse list changed length.
+///
+/// Dense synapses (see [`crate::synapse::SynapseOps::dense_weights`]) reading the same pre
+/// layer run as one matmul of their stacked weights, whose output rows are split by post layer.
+/// The stacked weights are rebuilt only when a member's weights changed, so frozen or inference
+/// steps reuse them.
+#[derive(Debug, Clone, Default)]
+pub struct SynapseGroups {
+    by_post: Vec<(LayerId, Vec<usize>)>,
+    /// every layer that is the pre layer of some synapse
+    pre_layers: Vec<LayerId>,
+    fused: Vec<FusedForward>,
+    num_synapses: usize,
+    unfused: bool,
+}
+
+impl SynapseGroups {
+    pub fn new(synapses: &[SynapseConnection]) -> Self {
+        let mut by_post: Vec<(LayerId, Vec<usize>)> = Vec::new();
+        let mut by_pre: Vec<(LayerId, Vec<usize>)> = Vec::new();
+        for (i, syn_conn) in synapses.iter().enumerate() {
+            let post = syn_conn.metadata.post_layer;
+            match by_post.iter_mut().find(|(id, _)| *id == post) {
+                Some((_, group)) => group.push(i),
+                None => by_post.push((post, vec![i])),
+            }
+            if syn_conn.synapse.dense_weights().is_none() {
+                continue;
+            }
+            let pre = syn_conn.metadata.pre_layer;
+            match by_pre.iter_mut().find(|(id, _)| *id == pre) {
+                Some((_, group)) => group.push(i),
+                None => by_pre.push((pre, vec![i])),
+            }
+        }
+        let fused = by_pre
+            .into_iter()
+            .filter(|(_, members)| members.len() > 1)
+            .map(|(pre, members)| FusedForward {
+                pre,
+                members,
+                stacked: None,
+            })
+            .collect();
+        let mut pre_layers: Vec<LayerId> =
+            synapses.iter().map(|s| s.metadata.pre_layer).collect();
+        pre_layers.sort_unstable();
+        pre_layers.dedup();
+        Self {
+            by_post,
+            pre_layers,
+            fused,
+            num_synapses: synapses.len(),
+            unfused: false,
+        }
+    }
+
+    /// Groups that run every synapse's forward pass on its own, as a baseline for the fused
+    /// forward passes
+    pub fn unfused(synapses: &[SynapseConnection]) -> Self {
+        Self {
+            unfused: true,
+            ..Self::new(synapses)
+        }
+    }
+
+    /// Number of pre layers whose dense synapses share one matmul
+    pub fn num_fused(&self) -> usize {
+        if self.unfused { 0 } else { self.fused.len() }
+    }
+
+    fn refresh(&mut self, synapses: &[SynapseConnection]) {
+        if self.num_synapses != synapses.len() {
+            *self = Self {
+                unfused: self.unfused,
+                ..Self::new(synapses)
+            };
+        }
+    }
+}
+
+/// Dense synapses reading the same pre layer
+#[derive(Debug, Clone)]
+struct FusedForward {
+    pre: LayerId,
+    /// indices of the synapses, in synapse order
+    members: Vec<usize>,
+    /// ids of the weights and biases of the enabled members, and those stacked by rows
+    stacked: Option<(Vec<TensorId>, Tensor, Tensor)>,
+}
+
+impl FusedForward {
+    /// The output of every enabled member as (synapse index, post input), from one matmul.
+    /// Empty if fewer than two members are enabled or they are not on one device, which leaves
+    /// them to their own forward pass.
+    fn forward(
+        &mut self,
+        layers: &[Box<dyn Layer>],
+        synapses: &[SynapseConnection],
+    ) -> CandleResult<Vec<(usize, Tensor)>> {
+        let live: Vec<(usize, &Tensor, &Tensor)> = self
+            .members
+            .iter()
+            .filter(|&&i| synapses[i].metadata.enabled)
+            .filter_map(|&i| {
+                let (weights, biases) = synapses[i].synapse.dense_weights()?;
+                Some((i, weights, biases))
+            })
+            .collect();
+        let Some(&(_, first, _)) = live.first() else {
+            return Ok(Vec::new());
+        };
+        let device = first.device();
+        if live.len() < 2 || live.iter().any(|(_, w, _)| !w.device().same_device(device)) {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<TensorId> = live.iter().flat_map(|(_, w, b)| [w.id(), b.id()]).collect();
+        let (weights, biases) = match self.stacked.take() {
+            Some((cached, weights, biases)) if cached == ids => (weights, biases),
+            _ => {
+                let weights: Vec<&Tensor> = live.iter().map(|&(_, w, _)| w).collect();
+                let biases: Vec<&Tensor> = live.iter().map(|&(_, _, b)| b).collect();
+                (Tensor::cat(&weights, 0)?, Tensor::cat(&biases, 0)?)
+            }
+        };
+
+        let pre_activity = layers[self.pre].output()?.to_device(device)?;
+        let output = weights.matmul(&pre_activity)?.broadcast_add(&biases)?;
+        self.stacked = Some((ids, weights, biases));
+        let mut offset = 0;
+        let mut outputs = Vec::with_capacity(live.len());
+        for &(i, w, _) in &live {
+            let rows = w.dim(0)?;
+            let post_input = output.narrow(0, offset, rows)?;
+            offset += rows;
+            outputs.push((i, with_gain(&synapses[i], post_input)?));
+        }
+        Ok(outputs)
+    }
+}
+
+/// Indices of the rows of `activity` that are non-zero in some batch column, or None if there
+/// are more than [`EVENT_DENSITY_LIMIT`] of them
+fn active_rows(activity: &Tensor) -> CandleResult<Option<Tensor>> {
+    let magnitude = activity.abs()?.sum(1)?.to_vec1::<f32>()?;
+    let active: Vec<u32> = magnitude
+        .iter()
+        .enumerate()
+        .filter(|&(_, &m)| m > 0.0)
+        .map(|(i, _)| i as u32)
+        .collect();
+    if active.len() as f32 > EVENT_DENSITY_LIMIT * magnitude.len() as f32 {
+        return Ok(None);
+    }
+    let count = active.len();
+    Tensor::from_vec(active, count, activity.device()).map(Some)
+}
+
+/// Run `f` and add its time to `total`. Waits for the work queued on `device` before and
+/// after, so the time is that of `f` alone.
+fn timed<T>(
+    device: &Device,
+    total: &mut Duration,
+    f: impl FnOnce() -> CandleResult<T>,
+) -> CandleResult<T> {
+    device.synchronize()?;
+    let start = Instant::now();
+    let result = f()?;
+    device.synchronize()?;
+    *total += start.elapsed();
+    Ok(result)
+}
+
+/// Input of one synapse to its post layer. `active` holds the [`active_rows`] of each layer
+/// in event-driven mode and is empty otherwise.
+fn forward_one(
+    layers: &[Box<dyn Layer>],
+    syn_conn: &SynapseConnection,
+    active: &[Option<Tensor>],
+) -> CandleResult<Tensor> {
+    let pre_layer = syn_conn.metadata.pre_layer;
+    // Synapses live on their post layer's device; a no-op unless the layers are split
+    let device = layers[syn_conn.metadata.post_layer].output()?.device();
+    let pre_activity = layers[pre_layer].output()?.to_device(device)?;
+    let post_input = match active.get(pre_layer).and_then(Option::as_ref) {
+        Some(rows) => syn_conn
+            .synapse
+            .forward_active(&pre_activity, &rows.to_device(device)?)?,
+        None => syn_conn.synapse.forward(&pre_activity)?,
+    };
+    with_gain(syn_conn, post_input)
+}
+
+/// `post_input` scaled by the synapse's gain
+fn with_gain(syn_conn: &SynapseConnection, post_input: Tensor) -> CandleResult<Tensor> {
+    if syn_conn.metadata.gain != 1.0 {
+        post_input.affine(syn_conn.metadata.gain as f64, 0.0)
+    } else {
+        Ok(post_input)
+    }
+}
+
+/// Sum of the outputs of the synapses in `group`, in synapse order, taking those in `fused`
+/// (indexed like `synapses`) from their fused forward pass. With `timings`, the time of each
+/// synapse is added to its entry, indexed like `synapses`.
+fn forward_group(
+    layers: &[Box<dyn Layer>],
+    synapses: &[SynapseConnection],
+    group: &[usize],
+    active: &[Option<Tensor>],
+    fused: &[Option<Tensor>],
+    mut timings: Option<&mut [Duration]>,
+) -> CandleResult<Option<Tensor>> {
+    let mut sum: Option<Tensor> = None;
+    for &i in group {
+        let syn_conn = &synapses[i];
+        if !syn_conn.metadata.enabled {
+            continue;
+        }
+        let post_input = match (
+            fused.get(i).and_then(Option::as_ref),
+            timings.as_deref_mut(),
+        ) {
+            (Some(post_input), _) => post_input.clone(),
+            (None, Some(timings)) => {
+                let device = layers[syn_conn.metadata.post_layer].output()?.device();
+                timed(device, &mut timings[i], || {
+                    forward_one(layers, syn_conn, active)
+                })?
+            }
+            (None, None) => forward_one(layers, syn_conn, active)?,
+        };
+        sum = Some(match sum {
+            Some(sum) => sum.add(&post_input)?,
+            None => post_input,
+        });
+    }
+    Ok(sum)
+}
+
+/// Add every synapse's output to the input compartment of its post layer. Inputs are summed
+/// per post layer inside the (possibly parallel) group task, so each layer receives a single
+/// `add_input` per timestep. With `event_driven`, synapses whose pre layer is sparsely active
+/// only propagate the neurons that are active (see
+/// [`crate::synapse::SynapseOps::forward_active`]) and are not fused.
+pub fn forward_synapses(
+    layers: &mut [Box<dyn Layer>],
+    synapses: &[SynapseConnection],
+    groups: &mut SynapseGroups,
+    event_driven: bool,
+    parallel: bool,
+) -> CandleResult<()> {
+    forward_synapses_with(layers, synapses, groups, event_driven, parallel, None)
+}
+
+/// [`forward_synapses`] run sequentially and unfused, adding the time of each synapse's forward
+/// pass to its entry of `timings`, indexed like `synapses`
+pub fn forward_synapses_timed(
+    layers: &mut [Box<dyn Layer>],
+    synapses: &[SynapseConnection],
+    groups: &mut SynapseGroups,
+    event_driven: bool,
+    timings: &mut [Duration],
+) -> CandleResult<()> {
+    forward_synapses_with(layers, synapses, groups, event_driven, false, Some(timings))
+}
+
+fn forward_synapses_with(
+    layers: &mut [Box<dyn Layer>],
+    synapses: &[SynapseConnection],
+    groups: &mut SynapseGroups,
+    event_driven: bool,
+    parallel: bool,
+    mut timings: Option<&mut [Duration]>,
+) -> CandleResult<()> {
+    groups.refresh(synapses);
+
+    let shared: &[Box<dyn Layer>] = layers;
+    let mut active = Vec::new();
+    if event_driven {
+        active.resize(shared.len(), None);
+        for &pre in &groups.pre_layers {
+            active[pre] = active_rows(shared[pre].output()?)?;
+        }
+    }
+
+    let mut fused = Vec::new();
+    if timings.is_none() && !groups.unfused {
+        fused.resize(synapses.len(), None);
+        let fuse = |f: &mut FusedForward| {
+            // Sparse pre layers take the event-driven path instead
+            if active.get(f.pre).is_some_and(Option::is_some) {
+                return Ok(Vec::new());
+            }
+            f.forward(shared, synapses)
+        };
+        let outputs = if parallel {
+            groups
+                .fused
+                .par_iter_mut()
+                .map(fuse)
+                .collect::<CandleResult<Vec<_>>>()?
+        } else {
+            groups
+                .fused
+                .iter_mut()
+                .map(fuse)
+                .collect::<CandleResult<Vec<_>>>()?
+        };
+        for (i, post_input) in outputs.into_iter().flatten() {
+            fused[i] = Some(post_input);
+        }
+    }
+
+    let post_inputs = if parallel {
+        groups
+            .by_post
+            .par_iter()
+            .map(|(post, group)| {
+                Ok((
+                    *post,
+                    forward_group(shared, synapses, group, &active, &fused, None)?,
+                ))
+            })
+            .collect::<CandleResult<Vec<_>>>()?
+    } else {
+        groups
+            .by_post
+            .iter()
+            .map(|(post, group)| {
+                let timings = timings.as_deref_mut();
+                Ok((
+                    *post,
+                    forward_group(shared, synapses, group, &active, &fused, timings)?,
+                ))
+            })
+            .collect::<CandleResult<Vec<_>>>()?
+    };
+
+    for (post_layer_id, post_input) in post_inputs {
+        if let Some(post_input) = post_input {
+            layers[post_layer_id].add_input(&post_input)?;
+        }
+    }
+    Ok(())
+}
+
+/// Step every layer in `layers`
+pub fn step_layers(layers: &mut [Box<dyn Layer>], dt: f32, parallel: bool) -> CandleResult<()> {
+    if parallel {
+        layers.par_iter_mut().try_for_each(|layer| layer.step(dt))
+    } else {
+        layers.iter_mut().try_for_each(|layer| layer.step(dt))
+    }
+}
+
+/// Apply plasticity to every enabled synapse with learning enabled
+pub fn update_synapses(
+    layers: &[Box<dyn Layer>],
+    synapses: &mut [SynapseConnection],
+    dt: f32,
+    parallel: bool,
+) -> CandleResult<()> {
+    update_synapses_scaled(layers, synapses, dt, 1.0, parallel)
+}
+
+/// [`update_synapses`] for a step `scale` times the nominal timestep, see
+/// [`crate::synapse::SynapseOps::update_weights_scaled`]
+pub fn update_synapses_scaled(
+    layers: &[Box<dyn Layer>],
+    synapses: &mut [SynapseConnection],
+    dt: f32,
+    scale: f32,
+    parallel: bool,
+) -> CandleResult<()> {
+    let update = |syn_conn: &mut SynapseConnection| update_one(layers, syn_conn, dt, scale);
+    if parallel {
+        synapses.par_iter_mut().try_for_each(update)
+    } else {
+        synapses.iter_mut().try_for_each(update)
+    }
+}
+
+/// [`update_synapses_scaled`] run sequentially, adding the time of each synapse's update to
+/// its entry of `timings`, indexed like `synapses`
+pub fn update_synapses_timed(
+    layers: &[Box<dyn Layer>],
+    synapses: &mut [SynapseConnection],
+    dt: f32,
+    scale: f32,
+    timings: &mut [Duration],
+) -> CandleResult<()> {
+    for (syn_conn, total) in synapses.iter_mut().zip(timings.iter_mut()) {
+        if !syn_conn.metadata.is_learning || !syn_conn.metadata.enabled {
+            continue;
+        }
+        let device = layers[syn_conn.metadata.post_layer].output()?.device();
+        timed(device, total, || update_one(layers, syn_conn, dt, scale))?;
+    }
+    Ok(())
+}
+
+/// Apply plasticity to one synapse, if it is enabled and learning
+fn update_one(
+    layers: &[Box<dyn Layer>],
+    syn_conn: &mut SynapseConnection,
+    dt: f32,
+    scale: f32,
+) -> CandleResult<()> {
+    if !syn_conn.metadata.is_learning || !syn_conn.metadata.enabled {
+        return Ok(());
+    }
+    let post_layer = layers[syn_conn.metadata.post_layer].as_ref();
+    let pre_activity = layers[syn_conn.metadata.pre_layer]
+        .output()?
+        .to_device(post_layer.output()?.device())?;
+    syn_conn
+        .synapse
+        .update_weights_scaled(&pre_activity, post_layer, dt, scale)
+}
@@ -0,0 +1,243 @@
+//! Pre-flight check of a dataset's scaling for the input layer.
+//!
+//! A Bernoulli input layer reads every value as a firing probability and clamps it to [0, 1],
+//! so raw joint angles, pixel intensities in 0..255 or standardized features with negative
+//! values saturate or silence the input without any error, and the network trained on them
+//! stays inert. [`check_inputs`] passes a sample of the data through the same encoding and
+//! reports features outside [0, 1], features that never change, non-finite values and an
+//! expected input spike rate too low to drive the hidden layers. [`Model::check_inputs`] picks
+//! the encoding from the model's input layer, and `experiment::train` runs the check on states
+//! of the environment before the first episode.
+
+use super::Model;
+use crate::error::{CsdpError, Result};
+use candle_core::{DType, Tensor};
+use std::fmt;
+
+/// Probabilities the Bernoulli layer clamps its inputs to
+const BERNOULLI_EPS: f64 = 1e-4;
+
+/// Features a warning names before it only counts the rest
+const LISTED_FEATURES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreflightConfig {
+    /// Warn when the mean firing probability over all features and samples is below this
+    pub min_rate: f32,
+    /// Features whose values span less than this are constant
+    pub constant_tolerance: f32,
+}
+
+impl Default for PreflightConfig {
+    fn default() -> Self {
+        Self {
+            min_rate: 0.01,
+            constant_tolerance: 1e-6,
+        }
+    }
+}
+
+/// How the input layer reads the values it is given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEncoding {
+    /// Firing probabilities of a Bernoulli layer; every check applies
+    Rates,
+    /// Values the model rescales itself: indices of a one-hot layer, or raw values in front
+    /// of input normalization. Only constant and non-finite features are reported.
+    Rescaled,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputWarning {
+    /// Features with NaN or infinite values
+    NonFinite { features: Vec<usize> },
+    /// Features with values outside [0, 1], which the input layer clamps; `min` and `max`
+    /// are taken over all of them
+    OutOfRange {
+        features: Vec<usize>,
+        min: f32,
+        max: f32,
+    },
+    /// Features with the same value in every sample, which carry no information
+    Constant { features: Vec<usize> },
+    /// The mean firing probability over all features and samples is below `min_rate`
+    LowRate { rate: f32, min_rate: f32 },
+}
+
+/// `features` as a short list, e.g. "0, 3, 7 and 12 more"
+fn listed(features: &[usize]) -> String {
+    let shown = features
+        .iter()
+        .take(LISTED_FEATURES)
+        .map(|f| f.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    match features.len().saturating_sub(LISTED_FEATURES) {
+        0 => shown,
+        rest => format!("{} and {} more", shown, rest),
+    }
+}
+
+impl fmt::Display for InputWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputWarning::NonFinite { features } => write!(
+                f,
+                "features {} hold NaN or infinite values",
+                listed(features)
+            ),
+            InputWarning::OutOfRange { features, min, max } => write!(
+                f,
+                "features {} range over [{}, {}], outside the [0, 1] of firing probabilities; \
+                 the input layer clamps them, so rescale the data",
+                listed(features),
+                min,
+                max
+            ),
+            InputWarning::Constant { features } => write!(
+                f,
+                "features {} have the same value in every sample",
+                listed(features)
+            ),
+            InputWarning::LowRate { rate, min_rate } => write!(
+                f,
+                "the expected input spike rate is {:.4} per neuron and step, below {}; the \
+                 hidden layers will barely fire",
+                rate, min_rate
+            ),
+        }
+    }
+}
+
+/// Findings of [`check_inputs`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputReport {
+    pub features: usize,
+    pub samples: usize,
+    /// Mean firing probability of every feature after the input layer's clamp; only for
+    /// [`InputEncoding::Rates`]
+    pub rates: Option<Vec<f32>>,
+    pub warnings: Vec<InputWarning>,
+}
+
+impl InputReport {
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    /// Mean firing probability over all features and samples
+    pub fn mean_rate(&self) -> Option<f32> {
+        let rates = self.rates.as_ref()?;
+        Some(rates.iter().sum::<f32>() / rates.len().max(1) as f32)
+    }
+
+    /// Log every warning, or that the inputs look fine, naming the data as `what`
+    pub fn log(&self, what: &str) {
+        if self.is_clean() {
+            log::info!(
+                "Input pre-flight of {}: {} features over {} samples look fine",
+                what,
+                self.features,
+                self.samples
+            );
+        }
+        for warning in &self.warnings {
+            log::warn!("Input pre-flight of {}: {}", what, warning);
+        }
+    }
+}
+
+/// Check `samples`, `(features, n)` as passed to `step`, against the scaling `encoding`
+/// expects; see the module docs
+pub fn check_inputs(
+    samples: &Tensor,
+    encoding: InputEncoding,
+    config: &PreflightConfig,
+) -> Result<InputReport> {
+    let (features, n) = samples.dims2()?;
+    if n == 0 {
+        return Err(CsdpError::Data("no samples to check".to_string()));
+    }
+    let samples = samples.to_dtype(DType::F32)?;
+    // x - x is NaN exactly where x is NaN or infinite
+    let diff = samples.sub(&samples)?;
+    let non_finite: Vec<f32> = diff.ne(&diff)?.to_dtype(DType::F32)?.max(1)?.to_vec1()?;
+    let samples = diff
+        .eq(&diff)?
+        .where_cond(&samples, &samples.zeros_like()?)?;
+    let min: Vec<f32> = samples.min(1)?.to_vec1()?;
+    let max: Vec<f32> = samples.max(1)?.to_vec1()?;
+    let finite = |f: &usize| non_finite[*f] == 0.0;
+
+    let mut warnings = Vec::new();
+    let bad: Vec<usize> = (0..features).filter(|f| !finite(f)).collect();
+    if !bad.is_empty() {
+        warnings.push(InputWarning::NonFinite { features: bad });
+    }
+    let mut rates = None;
+    if encoding == InputEncoding::Rates {
+        let outside: Vec<usize> = (0..features)
+            .filter(|f| finite(f) && (min[*f] < 0.0 || max[*f] > 1.0))
+            .collect();
+        if !outside.is_empty() {
+            let low = outside
+                .iter()
+                .map(|&f| min[f])
+                .fold(f32::INFINITY, f32::min);
+            let high = outside
+                .iter()
+                .map(|&f| max[f])
+                .fold(f32::NEG_INFINITY, f32::max);
+            warnings.push(InputWarning::OutOfRange {
+                features: outside,
+                min: low,
+                max: high,
+            });
+        }
+    }
+    if n > 1 {
+        let constant: Vec<usize> = (0..features)
+            .filter(|f| finite(f) && max[*f] - min[*f] < config.constant_tolerance)
+            .collect();
+        if !constant.is_empty() {
+            warnings.push(InputWarning::Constant { features: constant });
+        }
+    }
+    if encoding == InputEncoding::Rates {
+        let per_feature: Vec<f32> = samples
+            .clamp(BERNOULLI_EPS, 1.0 - BERNOULLI_EPS)?
+            .mean(1)?
+            .to_vec1()?;
+        let rate = per_feature.iter().sum::<f32>() / features.max(1) as f32;
+        if rate < config.min_rate {
+            warnings.push(InputWarning::LowRate {
+                rate,
+                min_rate: config.min_rate,
+            });
+        }
+        rates = Some(per_feature);
+    }
+    Ok(InputReport {
+        features,
+        samples: n,
+        rates,
+        warnings,
+    })
+}
+
+impl Model {
+    /// [`check_inputs`] with the encoding of this model's input layer: rates for a Bernoulli
+    /// layer, unless input normalization rescales the samples first
+    pub fn check_inputs(&self, samples: &Tensor, config: &PreflightConfig) -> Result<InputReport> {
+        let bernoulli = self
+            .layer_metadata
+            .first()
+            .is_some_and(|meta| meta.layer_type == "Bernoulli");
+        let encoding = if bernoulli && self.input_normalization.is_none() {
+            InputEncoding::Rates
+        } else {
+            InputEncoding::Rescaled
+        };
+        check_inputs(samples, encoding, config)
+    }
+}
@@ -0,0 +1,186 @@
+//! Where the time of a profiled run went.
+//!
+//! [`Model::enable_profiling`] times every layer step, every synapse's forward pass and every
+//! synapse's plasticity update of [`Model::step`], synchronizing with the device around each,
+//! so the timings attribute GPU work to the component that queued it. The per-window averages
+//! of [`Model::take_perf_stats`] feed the visualizer; [`Model::profile_report`] adds up the
+//! whole run since profiling was enabled into a [`ProfileReport`], whose `Display` prints the
+//! breakdown as a table, largest share first.
+
+use super::{Model, StepTimings};
+use std::fmt;
+use std::time::Duration;
+
+/// Phase of `Model::step` a [`ProfileEntry`] belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilePhase {
+    LayerStep,
+    SynapseForward,
+    SynapseUpdate,
+}
+
+impl fmt::Display for ProfilePhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProfilePhase::LayerStep => "layer step",
+            ProfilePhase::SynapseForward => "synapse forward",
+            ProfilePhase::SynapseUpdate => "plasticity",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileEntry {
+    pub phase: ProfilePhase,
+    /// the layer's name, `pre->post` for a synapse, or what the phase spent outside its
+    /// components, e.g. summing the inputs of a layer
+    pub component: String,
+    pub total: Duration,
+}
+
+/// Returned by [`Model::profile_report`]; `Display` prints it as a table
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileReport {
+    pub steps: usize,
+    /// wall-clock time since profiling was enabled, including time outside `step`
+    pub wall: Duration,
+    /// largest total first; components that took no time are left out
+    pub entries: Vec<ProfileEntry>,
+}
+
+impl ProfileReport {
+    /// Time spent in the profiled phases of `step`
+    pub fn measured(&self) -> Duration {
+        self.entries.iter().map(|e| e.total).sum()
+    }
+
+    /// Total time of `phase`
+    pub fn phase_total(&self, phase: ProfilePhase) -> Duration {
+        self.entries
+            .iter()
+            .filter(|e| e.phase == phase)
+            .map(|e| e.total)
+            .sum()
+    }
+
+    fn per_step_ms(&self, total: Duration) -> f64 {
+        total.as_secs_f64() * 1000.0 / self.steps.max(1) as f64
+    }
+}
+
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let measured = self.measured();
+        writeln!(
+            f,
+            "{} steps in {:.2} s, {:.3} ms per step in the profiled phases",
+            self.steps,
+            self.wall.as_secs_f64(),
+            self.per_step_ms(measured)
+        )?;
+        writeln!(
+            f,
+            "{:<16} {:<28} {:>12} {:>10} {:>7}",
+            "phase", "component", "total ms", "ms/step", "share"
+        )?;
+        for entry in &self.entries {
+            writeln!(
+                f,
+                "{:<16} {:<28} {:>12.2} {:>10.4} {:>6.1}%",
+                entry.phase.to_string(),
+                entry.component,
+                entry.total.as_secs_f64() * 1000.0,
+                self.per_step_ms(entry.total),
+                100.0 * entry.total.as_secs_f64() / measured.as_secs_f64().max(f64::EPSILON)
+            )?;
+        }
+        let outside = self.wall.saturating_sub(measured);
+        write!(
+            f,
+            "{:.2} s ({:.1}%) of the run were spent outside the profiled phases",
+            outside.as_secs_f64(),
+            100.0 * outside.as_secs_f64() / self.wall.as_secs_f64().max(f64::EPSILON)
+        )
+    }
+}
+
+impl Model {
+    /// Breakdown of the time of every step since [`Model::enable_profiling`], or None if
+    /// profiling is disabled or no steps ran. Unlike [`Model::take_perf_stats`] it resets
+    /// nothing.
+    pub fn profile_report(&self) -> Option<ProfileReport> {
+        let mut run = self.run_timings.clone()?;
+        run.add(self.timings.as_ref()?);
+        if run.steps == 0 {
+            return None;
+        }
+
+        let synapse_name = |i: usize| {
+            let meta = &self.synapses[i].metadata;
+            format!(
+                "{}->{}",
+                self.layer_metadata[meta.pre_layer].name, self.layer_metadata[meta.post_layer].name
+            )
+        };
+        let mut entries = Vec::new();
+        let mut push = |phase, component: String, total| {
+            entries.push(ProfileEntry {
+                phase,
+                component,
+                total,
+            })
+        };
+        for (meta, &total) in self.layer_metadata.iter().zip(&run.layer_step) {
+            push(ProfilePhase::LayerStep, meta.name.clone(), total);
+        }
+        for (i, &total) in run.synapse_forward_each.iter().enumerate() {
+            push(ProfilePhase::SynapseForward, synapse_name(i), total);
+        }
+        push(
+            ProfilePhase::SynapseForward,
+            "(summing inputs)".to_string(),
+            remainder(run.synapse_forward, &run.synapse_forward_each),
+        );
+        for (i, &total) in run.synapse_update_each.iter().enumerate() {
+            push(ProfilePhase::SynapseUpdate, synapse_name(i), total);
+        }
+        push(
+            ProfilePhase::SynapseUpdate,
+            "(outside the synapses)".to_string(),
+            remainder(run.synapse_update, &run.synapse_update_each),
+        );
+        entries.retain(|e| !e.total.is_zero());
+        entries.sort_by_key(|e| std::cmp::Reverse(e.total));
+
+        Some(ProfileReport {
+            steps: run.steps,
+            wall: run.since.elapsed(),
+            entries,
+        })
+    }
+}
+
+/// Time of a phase not spent in its components
+fn remainder(phase: Duration, components: &[Duration]) -> Duration {
+    phase.saturating_sub(components.iter().sum())
+}
+
+impl StepTimings {
+    /// Add the steps and times of `other`, a later window of the same run
+    pub(super) fn add(&mut self, other: &StepTimings) {
+        fn add_each(total: &mut Vec<Duration>, other: &[Duration]) {
+            if total.len() < other.len() {
+                total.resize(other.len(), Duration::ZERO);
+            }
+            for (t, &o) in total.iter_mut().zip(other) {
+                *t += o;
+            }
+        }
+        self.steps += other.steps;
+        add_each(&mut self.layer_step, &other.layer_step);
+        self.synapse_forward += other.synapse_forward;
+        self.synapse_update += other.synapse_update;
+        add_each(&mut self.synapse_forward_each, &other.synapse_forward_each);
+        add_each(&mut self.synapse_update_each, &other.synapse_update_each);
+    }
+}
@@ -0,0 +1,258 @@
+//! Import of weights trained with the reference Python CSDP implementation.
+//!
+//! A [`ReferenceMapping`] says which parameter of a reference checkpoint goes to which synapse
+//! or layer of a [`Model`]. [`Model::import_reference`] reads the checkpoint (a PyTorch
+//! `.pt`/`.pth` pickle, `.npz` or `.safetensors`), converts each parameter to this crate's
+//! layout and reports what was imported, what the mapping expected but the file lacks, and
+//! what the file holds that the mapping doesn't use (e.g. the reference's lateral synapses,
+//! which this crate doesn't have). Synapses and layers without a parameter in the checkpoint
+//! keep their current values.
+
+use super::Model;
+use crate::error::{CsdpError, Result};
+use crate::synapse::LayerId;
+use candle_core::{DType, Tensor};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Where a reference parameter goes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Target {
+    /// `param` (`weights` or `biases`) of the synapse from layer `pre` to layer `post`
+    Synapse {
+        pre: LayerId,
+        post: LayerId,
+        param: String,
+    },
+    /// `param` of a layer, e.g. `thresh` of an LIF layer
+    Layer { layer: LayerId, param: String },
+}
+
+/// Reference parameter names and where they go in a [`Model`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReferenceMapping {
+    /// Key of the state dict inside the checkpoint, e.g. `state_dict`; `None` when the file
+    /// is the state dict itself. Only used for PyTorch pickles.
+    #[serde(default)]
+    pub state_dict_key: Option<String>,
+    /// Weight matrices are stored (in, out), as for the reference's `z @ W`; this crate
+    /// stores them (out, in)
+    #[serde(default)]
+    pub weights_in_out: bool,
+    pub params: BTreeMap<String, Target>,
+}
+
+/// Outcome of [`Model::import_reference`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    pub imported: Vec<String>,
+    /// Mapped parameters the checkpoint doesn't contain
+    pub missing: Vec<String>,
+    /// Checkpoint parameters the mapping doesn't use
+    pub unused: Vec<String>,
+}
+
+impl ReferenceMapping {
+    /// Naming of the paper's model for the layout of [`Model::new`] with `num_hidden` hidden
+    /// layers. With `z0` the input and `z1..zL` the hidden layers:
+    ///
+    /// - `W{l}`: bottom-up synapse from `z{l-1}` to `z{l}`
+    /// - `V{l}`: top-down synapse from `z{l+1}` to `z{l}`
+    /// - `Y1`: context (label) synapse into `z1`
+    /// - `C{l}` / `E{l}`: synapses from `z{l}` to the output layer and back
+    /// - `z{l}.thr` and `out.thr`: adaptive thresholds
+    ///
+    /// Synapses are stored as `torch.nn.Linear` modules, i.e. `<name>.weight` (out, in) and
+    /// `<name>.bias`.
+    pub fn csdp_paper(num_hidden: usize) -> Self {
+        let hidden = |l: usize| l + 1; // z{l} -> layer index, for l >= 1
+        let output = 2 + num_hidden;
+        let mut params = BTreeMap::new();
+        let mut linear = |name: String, pre: LayerId, post: LayerId| {
+            for (suffix, param) in [("weight", "weights"), ("bias", "biases")] {
+                params.insert(
+                    format!("{}.{}", name, suffix),
+                    Target::Synapse {
+                        pre,
+                        post,
+                        param: param.to_string(),
+                    },
+                );
+            }
+        };
+
+        linear("W1".to_string(), 0, hidden(1));
+        linear("Y1".to_string(), 1, hidden(1));
+        for l in 2..=num_hidden {
+            linear(format!("W{}", l), hidden(l - 1), hidden(l));
+            linear(format!("V{}", l - 1), hidden(l), hidden(l - 1));
+        }
+        for l in 1..=num_hidden {
+            linear(format!("C{}", l), hidden(l), output);
+            linear(format!("E{}", l), output, hidden(l));
+        }
+
+        for l in 1..=num_hidden {
+            params.insert(
+                format!("z{}.thr", l),
+                Target::Layer {
+                    layer: hidden(l),
+                    param: "thresh".to_string(),
+                },
+            );
+        }
+        params.insert(
+            "out.thr".to_string(),
+            Target::Layer {
+                layer: output,
+                param: "thresh".to_string(),
+            },
+        );
+
+        Self {
+            state_dict_key: None,
+            weights_in_out: false,
+            params,
+        }
+    }
+
+    /// Mapping from a JSON file with the fields of this struct
+    pub fn from_json_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// All tensors of a reference checkpoint; the format follows the extension
+fn read_checkpoint(path: &Path, state_dict_key: Option<&str>) -> Result<HashMap<String, Tensor>> {
+    let tensors = match path.extension().and_then(|e| e.to_str()) {
+        Some("safetensors") => {
+            return Ok(candle_core::safetensors::load(
+                path,
+                &candle_core::Device::Cpu,
+            )?);
+        }
+        Some("npz") => Tensor::read_npz(path)?,
+        _ => candle_core::pickle::read_all_with_key(path, state_dict_key)?,
+    };
+    Ok(tensors.into_iter().collect())
+}
+
+/// `tensor` moved to `like`'s device and reshaped to its shape, which may only add or drop
+/// dimensions of size 1 (e.g. a `(n,)` bias becomes `(n, 1)`); a per-neuron threshold vector is
+/// averaged when this crate keeps a single threshold per layer
+fn fit(name: &str, tensor: &Tensor, like: &Tensor, param: &str) -> Result<Tensor> {
+    let tensor = &tensor.to_device(like.device())?;
+    let squeezed =
+        |t: &Tensor| -> Vec<usize> { t.dims().iter().copied().filter(|&n| n != 1).collect() };
+    if squeezed(tensor) == squeezed(like) {
+        return Ok(tensor.reshape(like.shape())?);
+    }
+    if param == "thresh" && like.elem_count() == 1 {
+        log::warn!(
+            "{}: averaging {} thresholds into the layer's single threshold",
+            name,
+            tensor.elem_count()
+        );
+        return Ok(tensor.mean_all()?.reshape(like.shape())?);
+    }
+    Err(CsdpError::Data(format!(
+        "{} has shape {:?}, expected {:?}",
+        name,
+        tensor.dims(),
+        like.dims()
+    )))
+}
+
+impl Model {
+    /// Load the parameters of a reference checkpoint at `path` as described by `mapping`
+    pub fn import_reference(
+        &mut self,
+        path: impl AsRef<Path>,
+        mapping: &ReferenceMapping,
+    ) -> Result<ImportReport> {
+        let loaded = read_checkpoint(path.as_ref(), mapping.state_dict_key.as_deref())?;
+        let mut report = ImportReport::default();
+
+        let mut synapse_states = HashMap::new();
+        let mut layer_states = HashMap::new();
+        for (name, target) in &mapping.params {
+            let Some(tensor) = loaded.get(name) else {
+                report.missing.push(name.clone());
+                continue;
+            };
+            let tensor = tensor.to_dtype(DType::F32)?;
+
+            match target {
+                Target::Synapse { pre, post, param } => {
+                    let index = self
+                        .synapses
+                        .iter()
+                        .position(|s| {
+                            s.metadata.pre_layer == *pre && s.metadata.post_layer == *post
+                        })
+                        .ok_or_else(|| {
+                            CsdpError::Config(format!(
+                                "{}: the model has no synapse from layer {} to layer {}",
+                                name, pre, post
+                            ))
+                        })?;
+                    let state: &mut HashMap<String, Tensor> = match synapse_states.entry(index) {
+                        Entry::Occupied(e) => e.into_mut(),
+                        Entry::Vacant(e) => e.insert(self.synapses[index].synapse.get_state()?),
+                    };
+                    let like = state.get(param).ok_or_else(|| {
+                        CsdpError::Config(format!(
+                            "{}: the synapse has no {} parameter",
+                            name, param
+                        ))
+                    })?;
+                    let tensor = if mapping.weights_in_out && param == "weights" {
+                        tensor.t()?.contiguous()?
+                    } else {
+                        tensor
+                    };
+                    let value = fit(name, &tensor, like, param)?;
+                    state.insert(param.clone(), value);
+                }
+                Target::Layer { layer, param } => {
+                    let state: &mut HashMap<String, Tensor> = match layer_states.entry(*layer) {
+                        Entry::Occupied(e) => e.into_mut(),
+                        Entry::Vacant(e) => {
+                            let layer = self.layers.get(*layer).ok_or_else(|| {
+                                CsdpError::Config(format!(
+                                    "{}: the model has no layer {}",
+                                    name, layer
+                                ))
+                            })?;
+                            e.insert(layer.get_state()?)
+                        }
+                    };
+                    let like = state.get(param).ok_or_else(|| {
+                        CsdpError::Config(format!("{}: the layer has no {} parameter", name, param))
+                    })?;
+                    let value = fit(name, &tensor, like, param)?;
+                    state.insert(param.clone(), value);
+                }
+            }
+            report.imported.push(name.clone());
+        }
+
+        for (index, state) in synapse_states {
+            self.synapses[index].synapse.set_state(&state)?;
+        }
+        for (index, state) in layer_states {
+            self.layers[index].set_state(&state)?;
+        }
+        self.sync_tied_weights()?;
+
+        report.unused = loaded
+            .into_keys()
+            .filter(|name| !mapping.params.contains_key(name))
+            .collect();
+        report.unused.sort();
+        Ok(report)
+    }
+}
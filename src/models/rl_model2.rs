@@ -1,8 +1,11 @@
+use super::parallel;
+use crate::error::{CsdpError, Result};
 use crate::layer::bernoulli::BernoulliLayer;
 use crate::layer::lif::LIFLayer;
 use crate::layer::mod_signal::reward_modulated::RewardModulatedModSignal;
 use crate::layer::one_hot::OneHotLayer;
 use crate::layer::{Layer, LayerMetadata, LayerPosition};
+use crate::shaping::spread_labels;
 use crate::synapse::csdp::CSDP;
 use crate::synapse::{LayerId, SynapseConnection, SynapseMetadata, SynapseOps};
 use crate::visualization::{LayerVisInfo, SynapseVisInfo};
@@ -13,6 +16,8 @@ pub struct ModelConfig {
     pub layer_configs: Vec<LayerConfig>,
     pub synapse_configs: Vec<SynapseConfig>,
     pub dt: f32,
+    /// Reseed before weights are drawn, independent of the global seed
+    pub seed: Option<u64>,
 }
 
 /// Configuration for a single layer
@@ -59,6 +64,9 @@ pub struct RLModel2 {
     pub is_learning: bool,
     pub dt: f32,
     pub device: Device,
+    synapse_groups: parallel::SynapseGroups,
+    /// Smoothing of every context passed to `step`, see [`RLModel2::set_label_smoothing`]
+    label_smoothing: Option<f32>,
 }
 
 impl RLModel2 {
@@ -70,7 +78,7 @@ impl RLModel2 {
         device: &Device,
         dt: f32,
         input_bounds: Option<Vec<usize>>,
-    ) -> Option<Self> {
+    ) -> Result<Self> {
         // Default LIF parameters
         let g_thr = 2.0;
         let tau_lif = 13.0;
@@ -78,7 +86,9 @@ impl RLModel2 {
         let thresh_lambda = 0.01;
 
         if hidden_sizes.is_empty() {
-            return None;
+            return Err(CsdpError::Config(
+                "at least one hidden layer is required".to_string(),
+            ));
         }
 
         // Build layer configs
@@ -153,13 +163,18 @@ impl RLModel2 {
             layer_configs,
             synapse_configs,
             dt,
+            seed: None,
         };
 
-        Self::from_config(config, device).ok()
+        Self::from_config(config, device)
     }
 
     /// Create a model from a configuration
-    pub fn from_config(config: ModelConfig, device: &Device) -> CandleResult<Self> {
+    pub fn from_config(config: ModelConfig, device: &Device) -> Result<Self> {
+        if let Some(seed) = config.seed {
+            crate::seed::reseed(seed, device)?;
+        }
+
         let mut layers: Vec<Box<dyn Layer>> = vec![];
         let mut layer_metadata = vec![];
 
@@ -174,6 +189,15 @@ impl RLModel2 {
         let mut synapses = vec![];
 
         for (synapse_id, syn_config) in config.synapse_configs.iter().enumerate() {
+            if syn_config.pre_layer >= layers.len() || syn_config.post_layer >= layers.len() {
+                return Err(CsdpError::Config(format!(
+                    "synapse {} connects layer {} -> {} but only {} layers exist",
+                    synapse_id,
+                    syn_config.pre_layer,
+                    syn_config.post_layer,
+                    layers.len()
+                )));
+            }
             let pre_size = layers[syn_config.pre_layer].size();
             let post_size = layers[syn_config.post_layer].size();
 
@@ -186,18 +210,22 @@ impl RLModel2 {
                 post_layer: syn_config.post_layer,
                 synapse_type: format!("{:?}", syn_config.synapse_type),
                 is_learning: true,
+                enabled: true,
+                gain: 1.0,
             };
             log::info!("creating synapse: {:?}", metadata);
             synapses.push(SynapseConnection { metadata, synapse });
         }
 
         Ok(Self {
+            synapse_groups: parallel::SynapseGroups::new(&synapses),
             layers,
             layer_metadata,
             synapses,
             is_learning: true,
             dt: config.dt,
             device: device.clone(),
+            label_smoothing: None,
         })
     }
 
@@ -296,6 +324,16 @@ impl RLModel2 {
         self.is_learning = false;
     }
 
+    /// Smooth every context passed to `step` by `epsilon` (see
+    /// [`crate::shaping::smooth_labels`]), or stop smoothing with None
+    pub fn set_label_smoothing(&mut self, epsilon: Option<f32>) -> CandleResult<()> {
+        if let Some(epsilon) = epsilon {
+            crate::shaping::check_label_smoothing(epsilon)?;
+        }
+        self.label_smoothing = epsilon;
+        Ok(())
+    }
+
     pub fn set_reward(&mut self, reward: &Tensor) {
         for layer in self.layers.iter_mut() {
             layer.set_reward(reward);
@@ -315,39 +353,31 @@ impl RLModel2 {
 
         // add context to second layer and step it
         if let Some(ctx) = context {
-            self.layers[1].add_input(ctx)?;
+            match self.label_smoothing {
+                Some(epsilon) => self.layers[1].add_input(&spread_labels(ctx, epsilon)?)?,
+                None => self.layers[1].add_input(ctx)?,
+            }
             self.layers[1].step(self.dt)?;
         }
 
-        // Synapse forward pass
-        for syn_conn in self.synapses.iter_mut() {
-            let pre_layer_id = syn_conn.metadata.pre_layer;
-            let post_layer_id = syn_conn.metadata.post_layer;
-            let pre_activity = self.layers[pre_layer_id].output()?.clone();
-            let post_input = syn_conn.synapse.forward(&pre_activity)?;
+        let par = parallel::enabled(&self.device);
 
-            self.layers[post_layer_id].add_input(&post_input)?;
-        }
+        // Synapse forward pass
+        parallel::forward_synapses(
+            &mut self.layers,
+            &self.synapses,
+            &mut self.synapse_groups,
+            false,
+            par,
+        )?;
 
         // Step all layers except the input and context layer (already stepped)
-        for layer in self.layers.iter_mut().skip(2) {
-            layer.step(self.dt)?;
-        }
+        parallel::step_layers(&mut self.layers[2..], self.dt, par)?;
 
         // Synapse weight updates
         // Update weights if learning is enabled
-        for syn_conn in self.synapses.iter_mut() {
-            if self.is_learning && syn_conn.metadata.is_learning {
-                let pre_layer_id = syn_conn.metadata.pre_layer;
-                let post_layer_id = syn_conn.metadata.post_layer;
-                let pre_activity = self.layers[pre_layer_id].output()?.clone();
-
-                syn_conn.synapse.update_weights(
-                    &pre_activity,
-                    &mut self.layers[post_layer_id],
-                    self.dt,
-                )?;
-            }
+        if self.is_learning {
+            parallel::update_synapses(&self.layers, &mut self.synapses, self.dt, par)?;
         }
 
         Ok(())
@@ -360,6 +390,20 @@ impl RLModel2 {
         Ok(())
     }
 
+    /// Independent copy of the model, including weights and layer state
+    pub fn fork(&self) -> Self {
+        Self {
+            layers: self.layers.iter().map(|layer| layer.box_clone()).collect(),
+            layer_metadata: self.layer_metadata.clone(),
+            synapses: self.synapses.clone(),
+            is_learning: self.is_learning,
+            dt: self.dt,
+            device: self.device.clone(),
+            synapse_groups: self.synapse_groups.clone(),
+            label_smoothing: self.label_smoothing,
+        }
+    }
+
     /// run for T timesteps, and return collected outputs (batched)
     pub fn process(&mut self, input: &Tensor, timesteps: usize) -> CandleResult<Tensor> {
         let batch_size = input.dims().get(1).copied().unwrap_or(1);
@@ -424,6 +468,7 @@ impl RLModel2 {
                 velocity: (0.0, 0.0),
                 current_activity: output_vec,
                 spike_count,
+                topography: None,
             };
 
             layer_vis_infos.push(layer_info);
@@ -440,6 +485,8 @@ impl RLModel2 {
                 post_layer: syn_conn.metadata.post_layer,
                 synapse_type: syn_conn.metadata.synapse_type.clone(),
                 weight_stats,
+                is_learning: syn_conn.metadata.is_learning,
+                enabled: syn_conn.metadata.enabled,
             };
 
             synapse_vis_infos.push(synapse_info);
@@ -448,6 +495,7 @@ impl RLModel2 {
         Ok(crate::visualization::ModelStructure {
             layers: layer_vis_infos,
             synapses: synapse_vis_infos,
+            novelty: None,
         })
     }
 
@@ -478,8 +526,13 @@ impl RLModel2 {
         Ok(total_activity)
     }
 
+    /// Learned parameters under descriptive names, for export
+    pub fn named_tensors(&self) -> CandleResult<Vec<(String, Tensor)>> {
+        super::named_tensors(&self.layers, &self.layer_metadata, &self.synapses)
+    }
+
     /// Save the model parameters to a safetensors file
-    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> CandleResult<()> {
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<()> {
         let mut tensor_map = std::collections::HashMap::new();
 
         for syn_conn in &self.synapses {
@@ -490,12 +543,13 @@ impl RLModel2 {
             }
         }
 
+        super::collect_layer_state(&self.layers, &mut tensor_map)?;
         candle_core::safetensors::save(&tensor_map, path)?;
         Ok(())
     }
 
     /// Load the model parameters from a safetensors file
-    pub fn load<P: AsRef<std::path::Path>>(&mut self, path: P) -> CandleResult<()> {
+    pub fn load<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<()> {
         let loaded_tensors = candle_core::safetensors::load(path, &self.device)?;
 
         for syn_conn in self.synapses.iter_mut() {
@@ -514,6 +568,18 @@ impl RLModel2 {
             }
         }
 
+        super::restore_layer_state(&mut self.layers, &loaded_tensors)?;
+
         Ok(())
     }
 }
+
+impl crate::visualization::publisher::SnapshotSource for RLModel2 {
+    fn snapshot(&self) -> CandleResult<crate::visualization::ModelStructure> {
+        self.get_visualization_snapshot()
+    }
+
+    fn layer_activity(&self, layer_id: LayerId) -> CandleResult<Vec<f32>> {
+        self.get_layer_activity(layer_id)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::Result;
 use crate::models::Model;
 use candle_core::{Device, Result as CandleResult};
 
@@ -17,7 +18,7 @@ impl RLModel3 {
         device: &Device,
         dt: f32,
         input_bounds: Option<Vec<usize>>,
-    ) -> Option<Self> {
+    ) -> Result<Self> {
         let actor = Model::new(
             state_size,
             action_size,
@@ -31,7 +32,7 @@ impl RLModel3 {
         // The context size for critic is 1 (the label).
         let critic = Model::new(state_size + action_size, 1, critic_hidden, device, dt, None)?;
 
-        Some(Self {
+        Ok(Self {
             actor,
             critic,
             device: device.clone(),
@@ -1,30 +1,723 @@
 use candle_core::{Device, Result as CandleResult, Tensor};
+use std::f64::consts::PI;
 
-use crate::models::Model;
+use crate::error::Result;
+use crate::layer::Layer;
+use crate::layer::normalization::{NormalizationConfig, NormalizationLayer};
+use crate::models::consolidation::ConsolidationConfig;
+use crate::models::context::ContextGatingConfig;
+use crate::models::observation::{Modality, ObservationSpace};
+use crate::models::{EarlyExit, Model, RateConvergence};
 // wrapper around the general CSDP model specifically for controlling the robots
 
-#[allow(dead_code)]
+/// Motors of the arm, one output group each
+pub const NUM_MOTORS: usize = 6;
+/// Output neurons per motor: stay, left (negative), right (positive)
+pub const GROUP_SIZE: usize = 3;
+
+/// How the spike counts of a group become a joint velocity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Vote {
+    /// The neuron with the most spikes wins; ties keep the joint still
+    Majority,
+    /// Softmax over the counts scaled by `1 / temperature`; the velocity is
+    /// `P(right) - P(left)`, so close votes give small moves
+    Softmax { temperature: f32 },
+}
+
+/// Turns output spikes of a [`RobotModel`] into joint commands.
+///
+/// Spikes are summed per neuron over a control window of `window` timesteps. At the end of
+/// the window each motor's group of three votes for staying, moving left or moving right,
+/// and the result is scaled by `step_size` (radians per control step).
+#[derive(Debug, Clone)]
+pub struct ActionDecoder {
+    pub window: usize,
+    pub step_size: f64,
+    pub vote: Vote,
+    counts: [f32; NUM_MOTORS * GROUP_SIZE],
+    steps: usize,
+}
+
+impl ActionDecoder {
+    pub fn new(window: usize, step_size: f64, vote: Vote) -> Self {
+        Self {
+            window: window.max(1),
+            step_size,
+            vote,
+            counts: [0.0; NUM_MOTORS * GROUP_SIZE],
+            steps: 0,
+        }
+    }
+
+    /// Add one timestep of output spikes, (18, batch); the batch is summed
+    pub fn accumulate(&mut self, spikes: &Tensor) -> CandleResult<()> {
+        let per_neuron = spikes.sum(1)?.to_vec1::<f32>()?;
+        if per_neuron.len() != self.counts.len() {
+            return Err(candle_core::Error::Msg(format!(
+                "expected {} output neurons, got {}",
+                self.counts.len(),
+                per_neuron.len()
+            )));
+        }
+        for (count, spikes) in self.counts.iter_mut().zip(per_neuron) {
+            *count += spikes;
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// Whether a full control window has been accumulated
+    pub fn ready(&self) -> bool {
+        self.steps >= self.window
+    }
+
+    /// Joint velocity deltas for the accumulated window; starts a new window
+    pub fn decode(&mut self) -> [f64; NUM_MOTORS] {
+        let mut command = [0.0; NUM_MOTORS];
+        for (motor, group) in self.counts.chunks_exact(GROUP_SIZE).enumerate() {
+            let (stay, left, right) = (group[0], group[1], group[2]);
+            let direction = match self.vote {
+                Vote::Majority if right > left && right > stay => 1.0,
+                Vote::Majority if left > right && left > stay => -1.0,
+                Vote::Majority => 0.0,
+                Vote::Softmax { temperature } => {
+                    let t = temperature.max(f32::EPSILON);
+                    let max = stay.max(left).max(right);
+                    let [stay, left, right] = [stay, left, right].map(|c| ((c - max) / t).exp());
+                    ((right - left) / (stay + left + right)) as f64
+                }
+            };
+            command[motor] = direction * self.step_size;
+        }
+        self.counts = [0.0; NUM_MOTORS * GROUP_SIZE];
+        self.steps = 0;
+        command
+    }
+}
+
+/// Shape of the tuning curves of a [`PopulationDecoder`], as a function of the distance `d`
+/// between a velocity and a neuron's preferred velocity, in units of the spacing of the
+/// preferred velocities
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TuningCurve {
+    /// `exp(-d^2 / (2 width^2))`
+    Gaussian { width: f64 },
+    /// `(1 + cos(pi d / width)) / 2` within `width`, zero beyond it
+    Cosine { width: f64 },
+}
+
+impl TuningCurve {
+    /// Firing probability of a neuron at distance `d` from its preferred velocity
+    pub fn response(&self, d: f64) -> f64 {
+        match *self {
+            TuningCurve::Gaussian { width } => {
+                let d = d / width.max(f64::EPSILON);
+                (-0.5 * d * d).exp()
+            }
+            TuningCurve::Cosine { width } => {
+                let width = width.max(f64::EPSILON);
+                if d.abs() >= width {
+                    0.0
+                } else {
+                    0.5 * (1.0 + (PI * d / width).cos())
+                }
+            }
+        }
+    }
+}
+
+impl Default for TuningCurve {
+    fn default() -> Self {
+        TuningCurve::Gaussian { width: 1.0 }
+    }
+}
+
+/// Turns output spikes of a [`RobotModel`] into continuous joint velocities.
+///
+/// Each motor has a population of neurons whose preferred velocities are spread evenly over
+/// `[-max_velocity, max_velocity]`. Spikes are summed per neuron over a control window of
+/// `window` timesteps, and each motor's velocity is the population vector: the preferred
+/// velocities averaged with the spike counts as weights. A silent population decodes as zero.
+/// With `smoothing`, each command is blended with the previous one. The tuning curves describe
+/// how strongly each neuron should fire for a velocity; [`PopulationDecoder::target`] turns a
+/// velocity into that pattern, e.g. for [`Model::clamp_output`].
+#[derive(Debug, Clone)]
+pub struct PopulationDecoder {
+    pub window: usize,
+    /// velocity (radians per control step) preferred by the last neuron of a population
+    pub max_velocity: f64,
+    pub tuning: TuningCurve,
+    /// weight of the previous command in the next one, in [0, 1); 0 decodes every window on
+    /// its own
+    pub smoothing: f64,
+    neurons_per_motor: usize,
+    counts: Vec<f32>,
+    steps: usize,
+    last: [f64; NUM_MOTORS],
+}
+
+impl PopulationDecoder {
+    pub fn new(
+        neurons_per_motor: usize,
+        window: usize,
+        max_velocity: f64,
+        tuning: TuningCurve,
+    ) -> Self {
+        let neurons_per_motor = neurons_per_motor.max(2);
+        Self {
+            window: window.max(1),
+            max_velocity,
+            tuning,
+            smoothing: 0.0,
+            neurons_per_motor,
+            counts: vec![0.0; NUM_MOTORS * neurons_per_motor],
+            steps: 0,
+            last: [0.0; NUM_MOTORS],
+        }
+    }
+
+    pub fn with_smoothing(mut self, smoothing: f64) -> Self {
+        self.smoothing = smoothing.clamp(0.0, 0.999);
+        self
+    }
+
+    pub fn neurons_per_motor(&self) -> usize {
+        self.neurons_per_motor
+    }
+
+    /// Number of output neurons the decoder reads
+    pub fn output_size(&self) -> usize {
+        NUM_MOTORS * self.neurons_per_motor
+    }
+
+    /// Preferred velocity of neuron `i` of a population, as a fraction of `max_velocity`
+    pub fn preferred(&self, i: usize) -> f64 {
+        -1.0 + 2.0 * i as f64 / (self.neurons_per_motor - 1) as f64
+    }
+
+    /// Add one timestep of output spikes, (output_size, batch); the batch is summed
+    pub fn accumulate(&mut self, spikes: &Tensor) -> CandleResult<()> {
+        let per_neuron = spikes.sum(1)?.to_vec1::<f32>()?;
+        if per_neuron.len() != self.counts.len() {
+            return Err(candle_core::Error::Msg(format!(
+                "expected {} output neurons, got {}",
+                self.counts.len(),
+                per_neuron.len()
+            )));
+        }
+        for (count, spikes) in self.counts.iter_mut().zip(per_neuron) {
+            *count += spikes;
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// Whether a full control window has been accumulated
+    pub fn ready(&self) -> bool {
+        self.steps >= self.window
+    }
+
+    /// Joint velocities for the accumulated window; starts a new window
+    pub fn decode(&mut self) -> [f64; NUM_MOTORS] {
+        let mut command = [0.0; NUM_MOTORS];
+        for (motor, counts) in self.counts.chunks_exact(self.neurons_per_motor).enumerate() {
+            let total: f64 = counts.iter().map(|&c| c as f64).sum();
+            let velocity = if total > 0.0 {
+                let weighted: f64 = counts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &c)| c as f64 * self.preferred(i))
+                    .sum();
+                weighted / total * self.max_velocity
+            } else {
+                0.0
+            };
+            command[motor] = self.smoothing * self.last[motor] + (1.0 - self.smoothing) * velocity;
+        }
+        self.counts.iter_mut().for_each(|c| *c = 0.0);
+        self.steps = 0;
+        self.last = command;
+        command
+    }
+
+    /// Forget the previous command, so smoothing starts over
+    pub fn reset(&mut self) {
+        self.last = [0.0; NUM_MOTORS];
+    }
+
+    /// Firing probabilities of the output neurons for `velocities` (one per motor, in radians
+    /// per control step), (output_size, 1), from the tuning curves
+    pub fn target(&self, velocities: &[f64], device: &Device) -> CandleResult<Tensor> {
+        if velocities.len() != NUM_MOTORS {
+            return Err(candle_core::Error::Msg(format!(
+                "expected {} velocities, got {}",
+                NUM_MOTORS,
+                velocities.len()
+            )));
+        }
+        let spacing = 2.0 / (self.neurons_per_motor - 1) as f64;
+        let max_velocity = self.max_velocity.abs().max(f64::EPSILON);
+        let mut values = Vec::with_capacity(self.output_size());
+        for &v in velocities {
+            let v = (v / max_velocity).clamp(-1.0, 1.0);
+            values.extend((0..self.neurons_per_motor).map(|i| {
+                let d = (v - self.preferred(i)) / spacing;
+                self.tuning.response(d) as f32
+            }));
+        }
+        Tensor::from_vec(values, (self.output_size(), 1), device)
+    }
+}
+
+/// Turns joint readings into the input of a [`RobotModel`].
+///
+/// Angles (relative to the home position, as returned by `LeRobot::get_motor_positions`) are
+/// normalized to [0, 1] between the calibrated joint limits. With `population > 1` each joint
+/// is represented by that many neurons with Gaussian tuning curves spread evenly over the
+/// range. Optionally the previous command and the reward, both in [-1, 1], are appended as
+/// extra channels mapped to [0, 1].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObservationEncoder {
+    /// joint limits relative to the home position
+    pub lower: Vec<f64>,
+    pub upper: Vec<f64>,
+    /// neurons per joint; 1 feeds the normalized angle directly
+    pub population: usize,
+    /// append one channel per motor with the previous command
+    pub previous_action: bool,
+    /// append one channel with the reward
+    pub reward: bool,
+}
+
+impl ObservationEncoder {
+    pub fn new(lower: Vec<f64>, upper: Vec<f64>) -> Self {
+        Self {
+            lower,
+            upper,
+            population: 1,
+            previous_action: false,
+            reward: false,
+        }
+    }
+
+    /// Limits from a calibration profile, whose home offsets and limits are raw servo positions
+    pub fn from_calibration(home: &[f64], min: &[f64], max: &[f64]) -> Self {
+        let relative =
+            |limits: &[f64]| -> Vec<f64> { limits.iter().zip(home).map(|(l, h)| l - h).collect() };
+        Self::new(relative(min), relative(max))
+    }
+
+    pub fn with_population(mut self, population: usize) -> Self {
+        self.population = population.max(1);
+        self
+    }
+
+    pub fn with_previous_action(mut self) -> Self {
+        self.previous_action = true;
+        self
+    }
+
+    pub fn with_reward(mut self) -> Self {
+        self.reward = true;
+        self
+    }
+
+    pub fn num_joints(&self) -> usize {
+        self.lower.len()
+    }
+
+    /// Number of input neurons the encoded observation needs
+    pub fn input_size(&self) -> usize {
+        self.num_joints() * self.population
+            + if self.previous_action { NUM_MOTORS } else { 0 }
+            + usize::from(self.reward)
+    }
+
+    /// Joint angles scaled to [0, 1] between the limits
+    pub fn normalize(&self, positions: &[f64]) -> Vec<f64> {
+        positions
+            .iter()
+            .zip(self.lower.iter().zip(&self.upper))
+            .map(|(p, (lo, hi))| ((p - lo) / (hi - lo).max(f64::EPSILON)).clamp(0.0, 1.0))
+            .collect()
+    }
+
+    /// (input_size, 1) tensor for one reading. A missing previous action or reward encodes as
+    /// zero, i.e. 0.5.
+    pub fn encode(
+        &self,
+        positions: &[f64],
+        previous_action: Option<&[f64]>,
+        reward: Option<f64>,
+        device: &Device,
+    ) -> CandleResult<Tensor> {
+        if positions.len() != self.num_joints() {
+            return Err(candle_core::Error::Msg(format!(
+                "expected {} joint positions, got {}",
+                self.num_joints(),
+                positions.len()
+            )));
+        }
+        self.encode_normalized(&self.normalize(positions), previous_action, reward, device)
+    }
+
+    /// [`ObservationEncoder::encode`] for joint angles already scaled to [0, 1], e.g. by a
+    /// [`NormalizationLayer`] instead of the limits
+    pub fn encode_normalized(
+        &self,
+        normalized: &[f64],
+        previous_action: Option<&[f64]>,
+        reward: Option<f64>,
+        device: &Device,
+    ) -> CandleResult<Tensor> {
+        let signed = |v: f64| ((v + 1.0) / 2.0).clamp(0.0, 1.0) as f32;
+
+        let mut values = Vec::with_capacity(self.input_size());
+        values.extend(population_code(normalized, self.population));
+        if self.previous_action {
+            let action = previous_action.unwrap_or(&[0.0; NUM_MOTORS]);
+            values.extend((0..NUM_MOTORS).map(|m| signed(action.get(m).copied().unwrap_or(0.0))));
+        }
+        if self.reward {
+            values.push(signed(reward.unwrap_or(0.0)));
+        }
+        let size = values.len();
+        Tensor::from_vec(values, (size, 1), device)
+    }
+}
+
+/// Values in [0, 1] coded by `population` Gaussian tuning curves each, spread evenly over
+/// [0, 1]; a population of 1 passes the values through
+pub(crate) fn population_code(normalized: &[f64], population: usize) -> Vec<f32> {
+    let mut values = Vec::with_capacity(normalized.len() * population);
+    for &x in normalized {
+        if population <= 1 {
+            values.push(x as f32);
+            continue;
+        }
+        // Neighbouring tuning curves cross at about 60% of their peak
+        let spacing = 1.0 / (population - 1) as f64;
+        values.extend((0..population).map(|i| {
+            let d = 2.0 * (x - i as f64 * spacing) / spacing;
+            (-0.5 * d * d).exp() as f32
+        }));
+    }
+    values
+}
+
+impl Default for ObservationEncoder {
+    /// One neuron per motor, angles in [-pi, pi]
+    fn default() -> Self {
+        Self::new(vec![-PI; NUM_MOTORS], vec![PI; NUM_MOTORS])
+    }
+}
+
 pub struct RobotModel {
     model: Model,
+    pub encoder: ObservationEncoder,
+    pub decoder: ActionDecoder,
+    /// continuous decoder used in place of `decoder`, see [`RobotModel::with_population_decoder`]
+    population_decoder: Option<PopulationDecoder>,
+    /// running normalization of the joint readings in place of the encoder's limits
+    normalization: Option<NormalizationLayer>,
+    /// composite input used in place of `encoder`, see [`RobotModel::with_observation_space`]
+    observation_space: Option<ObservationSpace>,
+    last_command: [f64; NUM_MOTORS],
 }
 
-#[allow(dead_code)]
 impl RobotModel {
     pub fn new(num_hidden: usize, hidden_size: usize, device: &Device, dt: f32) -> Self {
+        Self::with_encoder(
+            ObservationEncoder::default(),
+            num_hidden,
+            hidden_size,
+            device,
+            dt,
+        )
+    }
+
+    pub fn with_encoder(
+        encoder: ObservationEncoder,
+        num_hidden: usize,
+        hidden_size: usize,
+        device: &Device,
+        dt: f32,
+    ) -> Self {
+        // Inputs:
+        //   - the encoded observation, see `ObservationEncoder`
+        // Outputs:
+        //   - 18 neurons
+        //     - Broken apart into 6 groups of 3 for each motor (do nothing, spin left, spin
+        //     right)
+        // TODO: image input neurons and handle option
+        Self::build(
+            encoder.input_size(),
+            encoder,
+            NUM_MOTORS * GROUP_SIZE,
+            None,
+            num_hidden,
+            hidden_size,
+            device,
+            dt,
+        )
+    }
+
+    /// Decode smooth joint velocities from one population of output neurons per motor instead
+    /// of the 3-way groups (see [`PopulationDecoder`]); the output layer has
+    /// `decoder.output_size()` neurons
+    pub fn with_population_decoder(
+        encoder: ObservationEncoder,
+        decoder: PopulationDecoder,
+        num_hidden: usize,
+        hidden_size: usize,
+        device: &Device,
+        dt: f32,
+    ) -> Self {
+        Self::build(
+            encoder.input_size(),
+            encoder,
+            decoder.output_size(),
+            Some(decoder),
+            num_hidden,
+            hidden_size,
+            device,
+            dt,
+        )
+    }
+
+    /// Sense the named modalities of `space`, e.g. joints, a camera and the gripper, instead
+    /// of the joints alone; drive it with [`RobotModel::observe`]
+    pub fn with_observation_space(
+        space: ObservationSpace,
+        num_hidden: usize,
+        hidden_size: usize,
+        device: &Device,
+        dt: f32,
+    ) -> Self {
+        let mut model = Self::build(
+            space.input_size(),
+            ObservationEncoder::default(),
+            NUM_MOTORS * GROUP_SIZE,
+            None,
+            num_hidden,
+            hidden_size,
+            device,
+            dt,
+        );
+        model.observation_space = Some(space);
+        model
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build(
+        input_size: usize,
+        encoder: ObservationEncoder,
+        output_size: usize,
+        population_decoder: Option<PopulationDecoder>,
+        num_hidden: usize,
+        hidden_size: usize,
+        device: &Device,
+        dt: f32,
+    ) -> Self {
         RobotModel {
-            // Inputs:
-            //   - 6 neurons (1 for each motor's position)
-            //     - Experiment with 'place neuron' style encoding using multiple neurons per motor
-            // Outputs:
-            //   - 18 neurons
-            //     - Broken apart into 6 groups of 3 for each motor (do nothing, spin left, spin
-            //     right)
-            // TODO: image input neurons and handle option
-            model: Model::new(6, 18, vec![hidden_size; num_hidden], device, dt, None).unwrap(),
+            model: Model::new(
+                input_size,
+                output_size,
+                vec![hidden_size; num_hidden],
+                device,
+                dt,
+                None,
+            )
+            .unwrap(),
+            encoder,
+            decoder: ActionDecoder::new(10, 0.05, Vote::Majority),
+            population_decoder,
+            normalization: None,
+            observation_space: None,
+            last_command: [0.0; NUM_MOTORS],
+        }
+    }
+
+    /// Largest joint move of a control window, in radians: the decoder's `step_size`, or
+    /// `max_velocity` of the population decoder
+    pub fn step_size(&self) -> f64 {
+        match &self.population_decoder {
+            Some(decoder) => decoder.max_velocity,
+            None => self.decoder.step_size,
         }
     }
 
+    /// Change the largest joint move of a control window, e.g. as an
+    /// [`ActionCurriculum`](super::curriculum::ActionCurriculum) refines it
+    pub fn set_step_size(&mut self, step_size: f64) {
+        match self.population_decoder.as_mut() {
+            Some(decoder) => decoder.max_velocity = step_size,
+            None => self.decoder.step_size = step_size,
+        }
+    }
+
+    pub fn observation_space(&self) -> Option<&ObservationSpace> {
+        self.observation_space.as_ref()
+    }
+
+    pub fn population_decoder(&self) -> Option<&PopulationDecoder> {
+        self.population_decoder.as_ref()
+    }
+
+    pub fn population_decoder_mut(&mut self) -> Option<&mut PopulationDecoder> {
+        self.population_decoder.as_mut()
+    }
+
+    /// Scale joint readings by their running mean and variance (see [`NormalizationLayer`])
+    /// instead of the encoder's fixed limits, so `control` follows sensors whose offsets drift
+    pub fn with_input_normalization(mut self, config: NormalizationConfig) -> CandleResult<Self> {
+        self.normalization = Some(NormalizationLayer::new(
+            self.encoder.num_joints(),
+            config,
+            &self.model.device,
+        )?);
+        Ok(self)
+    }
+
+    pub fn input_normalization_mut(&mut self) -> Option<&mut NormalizationLayer> {
+        self.normalization.as_mut()
+    }
+
+    /// Protect learned motions from the ones trained after them: call `consolidate` after each
+    /// motion (see [`Model::enable_consolidation`])
+    pub fn enable_consolidation(&mut self, config: ConsolidationConfig) -> Result<()> {
+        self.model.enable_consolidation(config)
+    }
+
+    /// Anchor the weights that matter to the motion just trained
+    pub fn consolidate(&mut self) -> Result<()> {
+        self.model.consolidate()
+    }
+
+    /// Hold `num_tasks` motions in one network, each in its own subset of the hidden neurons
+    /// (see [`Model::enable_context_gating`])
+    pub fn enable_context_gating(&mut self, num_tasks: usize) -> Result<()> {
+        self.model
+            .enable_context_gating(ContextGatingConfig::new(num_tasks))
+    }
+
+    /// Switch to the motion `task_id`
+    pub fn set_context(&mut self, task_id: usize) -> Result<()> {
+        self.model.set_context(task_id)
+    }
+
     pub fn step(&mut self, input: &Tensor, context: Option<&Tensor>) -> CandleResult<()> {
         self.model.step(input, context)
     }
+
+    /// End a control window early once the output rates have settled (see [`EarlyExit`]);
+    /// the decoder's window stays the upper bound
+    pub fn with_early_exit(mut self, early_exit: EarlyExit) -> Self {
+        self.model.early_exit = Some(early_exit);
+        self
+    }
+
+    /// Run one control window on `input` and return the decoded joint velocity deltas
+    pub fn act(&mut self, input: &Tensor) -> CandleResult<[f64; NUM_MOTORS]> {
+        let mut convergence = self.model.early_exit.map(RateConvergence::new);
+        let mut settled = |spikes: &Tensor| match convergence.as_mut() {
+            Some(convergence) => convergence.update(spikes, 1.0),
+            None => Ok(false),
+        };
+        if let Some(decoder) = self.population_decoder.as_mut() {
+            while !decoder.ready() {
+                self.model.step(input, None)?;
+                let spikes = self.model.layers.last().unwrap().output()?;
+                decoder.accumulate(spikes)?;
+                if settled(spikes)? {
+                    break;
+                }
+            }
+            return Ok(decoder.decode());
+        }
+        while !self.decoder.ready() {
+            self.model.step(input, None)?;
+            let spikes = self.model.layers.last().unwrap().output()?;
+            self.decoder.accumulate(spikes)?;
+            if settled(spikes)? {
+                break;
+            }
+        }
+        Ok(self.decoder.decode())
+    }
+
+    /// Encode a joint reading (with the previous command and `reward`, if the encoder uses
+    /// them) and run one control window on it. A model with an observation space is driven
+    /// with [`RobotModel::observe`] instead.
+    pub fn control(
+        &mut self,
+        positions: &[f64],
+        reward: Option<f64>,
+    ) -> CandleResult<[f64; NUM_MOTORS]> {
+        if self.observation_space.is_some() {
+            return Err(candle_core::Error::Msg(
+                "the model senses an observation space, see RobotModel::observe".to_string(),
+            ));
+        }
+        let step_size = self.step_size().abs().max(f64::EPSILON);
+        let previous = self.last_command.map(|c| c / step_size);
+        let device = &self.model.device;
+        let input = match self.normalization.as_mut() {
+            Some(normalization) => {
+                let reading: Vec<f32> = positions.iter().map(|&p| p as f32).collect();
+                normalization.reset_input()?;
+                normalization.add_input(&Tensor::from_vec(
+                    reading,
+                    (positions.len(), 1),
+                    device,
+                )?)?;
+                normalization.step(0.0)?;
+                let normalized: Vec<f64> = normalization
+                    .output()?
+                    .flatten_all()?
+                    .to_vec1::<f32>()?
+                    .into_iter()
+                    .map(f64::from)
+                    .collect();
+                self.encoder
+                    .encode_normalized(&normalized, Some(&previous), reward, device)?
+            }
+            None => self
+                .encoder
+                .encode(positions, Some(&previous), reward, device)?,
+        };
+        self.last_command = self.act(&input)?;
+        Ok(self.last_command)
+    }
+
+    /// Encode one set of `readings` by the observation space, given by slice name, and run
+    /// one control window on it. Previous-action and reward slices are filled in from the
+    /// last command and `reward` unless `readings` has them.
+    pub fn observe(
+        &mut self,
+        readings: &[(&str, &[f64])],
+        reward: Option<f64>,
+    ) -> CandleResult<[f64; NUM_MOTORS]> {
+        let Some(space) = &self.observation_space else {
+            return Err(candle_core::Error::Msg(
+                "the model has no observation space, see RobotModel::with_observation_space"
+                    .to_string(),
+            ));
+        };
+        let step_size = self.step_size().abs().max(f64::EPSILON);
+        let previous = self.last_command.map(|c| c / step_size);
+        let reward = reward.map(|r| [r]);
+        let mut all = readings.to_vec();
+        for slice in &space.slices {
+            match (&slice.modality, &reward) {
+                (Modality::PreviousAction, _) => all.push((slice.name.as_str(), &previous[..])),
+                (Modality::Reward, Some(reward)) => all.push((slice.name.as_str(), &reward[..])),
+                _ => {}
+            }
+        }
+        let input = space.encode(&all, &self.model.device)?;
+        self.last_command = self.act(&input)?;
+        Ok(self.last_command)
+    }
 }
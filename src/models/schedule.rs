@@ -0,0 +1,174 @@
+//! Presentation schedules for temporal tasks.
+//!
+//! [`Model::process`] holds one input for the whole run. A [`Schedule`] instead lays out an
+//! episode as a sequence of segments: stimuli held for a number of timesteps, optionally with
+//! a context, and blank intervals with zero input in between. [`Model::process_schedule`]
+//! resets the model once and runs the segments back to back, so membrane potentials and
+//! traces carry over from one stimulus to the next.
+
+use super::{Model, ProcessOutput};
+use candle_core::{DType, Result as CandleResult, Tensor};
+
+/// One part of a [`Schedule`]
+#[derive(Debug, Clone)]
+pub enum Segment {
+    /// `input` (and `context`, if any) held for `steps` timesteps
+    Stimulus {
+        input: Tensor,
+        context: Option<Tensor>,
+        steps: usize,
+    },
+    /// `steps` timesteps without input
+    Blank { steps: usize },
+}
+
+/// Sequence of stimuli and blank intervals making up one episode
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    pub segments: Vec<Segment>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold `input`, `(features, batch)`, for `steps` timesteps
+    pub fn present(mut self, input: &Tensor, steps: usize) -> Self {
+        self.segments.push(Segment::Stimulus {
+            input: input.clone(),
+            context: None,
+            steps,
+        });
+        self
+    }
+
+    /// Hold `input` together with a context (e.g. a label) for `steps` timesteps
+    pub fn present_with_context(mut self, input: &Tensor, context: &Tensor, steps: usize) -> Self {
+        self.segments.push(Segment::Stimulus {
+            input: input.clone(),
+            context: Some(context.clone()),
+            steps,
+        });
+        self
+    }
+
+    /// Zero input for `steps` timesteps
+    pub fn blank(mut self, steps: usize) -> Self {
+        if steps > 0 {
+            self.segments.push(Segment::Blank { steps });
+        }
+        self
+    }
+
+    /// `input` switched on at step `onset` and off at step `offset` of a `total`-step episode,
+    /// with blank intervals around it
+    pub fn hold(input: &Tensor, onset: usize, offset: usize, total: usize) -> Self {
+        let offset = offset.clamp(onset, total.max(onset));
+        Self::new()
+            .blank(onset)
+            .present(input, offset - onset)
+            .blank(total.saturating_sub(offset))
+    }
+
+    /// The same stimuli one after another, each held for `steps` timesteps and followed by
+    /// `interval` blank timesteps (none after the last one)
+    pub fn sequence(inputs: &[Tensor], steps: usize, interval: usize) -> Self {
+        let mut schedule = Self::new();
+        for (i, input) in inputs.iter().enumerate() {
+            if i > 0 {
+                schedule = schedule.blank(interval);
+            }
+            schedule = schedule.present(input, steps);
+        }
+        schedule
+    }
+
+    /// Nominal timesteps of the whole episode
+    pub fn total_steps(&self) -> usize {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Stimulus { steps, .. } | Segment::Blank { steps } => *steps,
+            })
+            .sum()
+    }
+}
+
+impl Model {
+    /// Run the episode laid out by `schedule`. The model is reset once at the start, not
+    /// between segments. Stimuli must all have the same shape; blank segments present zeros
+    /// of that shape, and zero context if any stimulus has a context. With `collect_data`, the
+    /// output activity of every step is collected.
+    pub fn process_schedule(
+        &mut self,
+        schedule: &Schedule,
+        collect_data: bool,
+    ) -> CandleResult<ProcessOutput> {
+        let Some(shape) = schedule.segments.iter().find_map(|segment| match segment {
+            Segment::Stimulus { input, .. } => Some(input.shape().clone()),
+            Segment::Blank { .. } => None,
+        }) else {
+            return Err(candle_core::Error::Msg(
+                "a schedule needs at least one stimulus".to_string(),
+            ));
+        };
+        let batch_size = shape.dims().get(1).copied().unwrap_or(1);
+        for segment in &schedule.segments {
+            if let Segment::Stimulus { input, .. } = segment
+                && input.shape() != &shape
+            {
+                return Err(candle_core::Error::Msg(format!(
+                    "stimulus has shape {:?}, expected {:?}",
+                    input.dims(),
+                    shape.dims()
+                )));
+            }
+        }
+
+        let blank = Tensor::zeros(shape.clone(), DType::F32, &self.device)?;
+        let blank_context = schedule
+            .segments
+            .iter()
+            .find_map(|segment| match segment {
+                Segment::Stimulus {
+                    context: Some(context),
+                    ..
+                } => Some(context.zeros_like()),
+                _ => None,
+            })
+            .transpose()?;
+        let mut out = ProcessOutput {
+            output_activity: vec![],
+            final_output: Tensor::zeros((0, batch_size), DType::F32, &self.device)?,
+        };
+        self.reset(batch_size)?;
+        for segment in &schedule.segments {
+            let (input, context, steps) = match segment {
+                Segment::Stimulus {
+                    input,
+                    context,
+                    steps,
+                } => (input, context.as_ref(), *steps),
+                Segment::Blank { steps } => (&blank, blank_context.as_ref(), *steps),
+            };
+            self.simulate(input, context, steps, |model, _| {
+                if collect_data && !model.layers.is_empty() {
+                    let output = model.layers.last().unwrap().output()?;
+                    out.output_activity.push(output.to_device(&model.device)?);
+                }
+                Ok(true)
+            })?;
+        }
+
+        if !self.layers.is_empty() {
+            out.final_output = self
+                .layers
+                .last()
+                .unwrap()
+                .output()?
+                .to_device(&self.device)?;
+        }
+        Ok(out)
+    }
+}
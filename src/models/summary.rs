@@ -0,0 +1,174 @@
+//! Structured description of a constructed [`Model`].
+
+use super::Model;
+use crate::synapse::{LayerId, SynapseId};
+use candle_core::{Device, DeviceLocation, Result as CandleResult, Tensor};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerSummary {
+    pub id: LayerId,
+    pub name: String,
+    pub layer_type: String,
+    pub size: usize,
+    /// learned values that aren't synapse weights, e.g. adaptive thresholds
+    pub parameters: usize,
+    pub device: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynapseSummary {
+    pub id: SynapseId,
+    pub pre_layer: LayerId,
+    pub post_layer: LayerId,
+    /// learning rule, e.g. `CSDP`
+    pub rule: String,
+    /// (post, pre) shape of the full weight matrix
+    pub shape: (usize, usize),
+    /// stored weights and biases; fewer than the full matrix for sparse synapses
+    pub parameters: usize,
+    pub is_learning: bool,
+    pub enabled: bool,
+    pub device: String,
+}
+
+/// Returned by [`Model::summary`]; `Display` prints it as tables
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelSummary {
+    pub layers: Vec<LayerSummary>,
+    pub synapses: Vec<SynapseSummary>,
+    pub total_parameters: usize,
+    /// memory held by the parameters; activity buffers come on top and scale with the batch
+    pub parameter_bytes: usize,
+}
+
+/// `cpu`, `cuda:N` or `metal:N`, as the tools take devices on the command line
+pub fn device_name(device: &Device) -> String {
+    match device.location() {
+        DeviceLocation::Cpu => "cpu".to_string(),
+        DeviceLocation::Cuda { gpu_id } => format!("cuda:{}", gpu_id),
+        DeviceLocation::Metal { gpu_id } => format!("metal:{}", gpu_id),
+    }
+}
+
+/// Number of learned values and their size in bytes; integer tensors, such as the block
+/// layout of a sparse synapse, describe structure and aren't counted
+fn count(state: &HashMap<String, Tensor>) -> (usize, usize) {
+    state
+        .values()
+        .filter(|t| t.dtype().is_float())
+        .fold((0, 0), |(n, bytes), t| {
+            (
+                n + t.elem_count(),
+                bytes + t.elem_count() * t.dtype().size_in_bytes(),
+            )
+        })
+}
+
+impl Model {
+    /// Layers, synapses, parameter counts, parameter memory and device placement
+    pub fn summary(&self) -> CandleResult<ModelSummary> {
+        let mut total_parameters = 0;
+        let mut parameter_bytes = 0;
+
+        let mut layers = Vec::with_capacity(self.layers.len());
+        for (id, (layer, meta)) in self.layers.iter().zip(&self.layer_metadata).enumerate() {
+            let (parameters, bytes) = count(&layer.get_state()?);
+            total_parameters += parameters;
+            parameter_bytes += bytes;
+            layers.push(LayerSummary {
+                id,
+                name: meta.name.clone(),
+                layer_type: meta.layer_type.clone(),
+                size: layer.size(),
+                parameters,
+                device: device_name(self.layer_device(id)),
+            });
+        }
+
+        let mut synapses = Vec::with_capacity(self.synapses.len());
+        for syn_conn in &self.synapses {
+            let meta = &syn_conn.metadata;
+            let state = syn_conn.synapse.get_state()?;
+            let (parameters, bytes) = count(&state);
+            total_parameters += parameters;
+            parameter_bytes += bytes;
+            synapses.push(SynapseSummary {
+                id: meta.id,
+                pre_layer: meta.pre_layer,
+                post_layer: meta.post_layer,
+                rule: meta.synapse_type.clone(),
+                shape: (
+                    self.layers[meta.post_layer].size(),
+                    self.layers[meta.pre_layer].size(),
+                ),
+                parameters,
+                is_learning: meta.is_learning,
+                enabled: meta.enabled,
+                // where the weights are stored, which differs for offloaded synapses
+                device: device_name(
+                    state
+                        .get("weights")
+                        .or_else(|| state.get("biases"))
+                        .map_or(self.layer_device(meta.post_layer), |t| t.device()),
+                ),
+            });
+        }
+
+        Ok(ModelSummary {
+            layers,
+            synapses,
+            total_parameters,
+            parameter_bytes,
+        })
+    }
+}
+
+impl fmt::Display for ModelSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>3}  {:<16} {:<10} {:>8} {:>8}  device",
+            "id", "layer", "type", "size", "params"
+        )?;
+        for layer in &self.layers {
+            writeln!(
+                f,
+                "{:>3}  {:<16} {:<10} {:>8} {:>8}  {}",
+                layer.id, layer.name, layer.layer_type, layer.size, layer.parameters, layer.device
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:>3}  {:<9} {:<28} {:>13} {:>10}  device  state",
+            "id", "pre->post", "rule", "shape", "params"
+        )?;
+        for syn in &self.synapses {
+            let state = match (syn.enabled, syn.is_learning) {
+                (false, _) => "ablated",
+                (true, false) => "frozen",
+                (true, true) => "learning",
+            };
+            writeln!(
+                f,
+                "{:>3}  {:<9} {:<28} {:>13} {:>10}  {:<6}  {}",
+                syn.id,
+                format!("{}->{}", syn.pre_layer, syn.post_layer),
+                syn.rule,
+                format!("{}x{}", syn.shape.0, syn.shape.1),
+                syn.parameters,
+                syn.device,
+                state
+            )?;
+        }
+        writeln!(f)?;
+        write!(
+            f,
+            "{} parameters, {:.2} MiB",
+            self.total_parameters,
+            self.parameter_bytes as f64 / (1024.0 * 1024.0)
+        )
+    }
+}
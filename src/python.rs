@@ -0,0 +1,190 @@
+//! Python bindings (`--features python`), built with maturin:
+//!
+//! ```text
+//! maturin develop --release --features python
+//! ```
+//!
+//! ```python
+//! import csdp
+//! model = csdp.Model(784, 10, [500, 500], dt=0.1)
+//! out = model.process([[0.0] * 784], timesteps=40)  # one row of 10 outputs per sample
+//! model.save("model.safetensors")
+//! ```
+//!
+//! Inputs and outputs are batch-major (`[sample][neuron]`) as Python code expects; they are
+//! transposed to the column layout the network uses internally.
+
+use crate::error::CsdpError;
+use crate::models::Model;
+use candle_core::{Device, Tensor};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+
+impl From<CsdpError> for PyErr {
+    fn from(e: CsdpError) -> Self {
+        match e {
+            CsdpError::Config(msg) => PyValueError::new_err(msg),
+            e => PyRuntimeError::new_err(e.to_string()),
+        }
+    }
+}
+
+fn to_py(e: candle_core::Error) -> PyErr {
+    CsdpError::from(e).into()
+}
+
+fn parse_device(name: &str) -> PyResult<Device> {
+    let device = match name {
+        "cpu" => Device::Cpu,
+        "cuda" => Device::new_cuda(0).map_err(to_py)?,
+        _ => match name.strip_prefix("cuda:").and_then(|o| o.parse().ok()) {
+            Some(ordinal) => Device::new_cuda(ordinal).map_err(to_py)?,
+            None => {
+                return Err(PyValueError::new_err(format!("unknown device '{}'", name)));
+            }
+        },
+    };
+    Ok(device)
+}
+
+/// `[sample][neuron]` rows -> `(neurons, batch)` tensor
+fn rows_to_tensor(rows: &[Vec<f32>], width: usize, device: &Device) -> PyResult<Tensor> {
+    if let Some(row) = rows.iter().find(|r| r.len() != width) {
+        return Err(PyValueError::new_err(format!(
+            "expected rows of length {}, got {}",
+            width,
+            row.len()
+        )));
+    }
+    let flat: Vec<f32> = rows.iter().flatten().copied().collect();
+    Tensor::from_vec(flat, (rows.len(), width), device)
+        .and_then(|t| t.t())
+        .map_err(to_py)
+}
+
+/// `(neurons, batch)` tensor -> `[sample][neuron]` rows
+fn tensor_to_rows(t: &Tensor) -> PyResult<Vec<Vec<f32>>> {
+    t.t().and_then(|t| t.to_vec2::<f32>()).map_err(to_py)
+}
+
+#[pyclass(name = "Model", unsendable)]
+pub struct PyModel {
+    model: Model,
+    input_size: usize,
+    output_size: usize,
+}
+
+#[pymethods]
+impl PyModel {
+    #[new]
+    #[pyo3(signature = (input_size, output_size, hidden_sizes, dt=0.1, device="cpu", input_bounds=None))]
+    fn new(
+        input_size: usize,
+        output_size: usize,
+        hidden_sizes: Vec<usize>,
+        dt: f32,
+        device: &str,
+        input_bounds: Option<Vec<usize>>,
+    ) -> PyResult<Self> {
+        let device = parse_device(device)?;
+        let model = Model::new(
+            input_size,
+            output_size,
+            hidden_sizes,
+            &device,
+            dt,
+            input_bounds,
+        )?;
+        Ok(Self {
+            model,
+            input_size,
+            output_size,
+        })
+    }
+
+    /// Run `timesteps` steps on a batch of samples and return the final output spikes.
+    /// With `collect=True`, returns the output of every timestep instead.
+    #[pyo3(signature = (inputs, timesteps, collect=false))]
+    fn process(
+        &mut self,
+        py: Python<'_>,
+        inputs: Vec<Vec<f32>>,
+        timesteps: usize,
+        collect: bool,
+    ) -> PyResult<PyObject> {
+        let input = rows_to_tensor(&inputs, self.input_size, &self.model.device)?;
+        let device = self.model.device.clone();
+        let out = self
+            .model
+            .process(&input, timesteps, collect, &device)
+            .map_err(to_py)?;
+
+        if collect {
+            let per_step = out
+                .output_activity
+                .iter()
+                .map(tensor_to_rows)
+                .collect::<PyResult<Vec<_>>>()?;
+            Ok(per_step.into_py(py))
+        } else {
+            Ok(tensor_to_rows(&out.final_output)?.into_py(py))
+        }
+    }
+
+    /// Reset all layer state for a new batch
+    fn reset(&mut self, batch_size: usize) -> PyResult<()> {
+        self.model.reset(batch_size).map_err(to_py)
+    }
+
+    /// Advance one timestep. `labels` (one row of `output_size` per sample) drive the context
+    /// layer, which is how supervised samples are presented while learning is enabled.
+    #[pyo3(signature = (inputs, labels=None))]
+    fn step(&mut self, inputs: Vec<Vec<f32>>, labels: Option<Vec<Vec<f32>>>) -> PyResult<()> {
+        let input = rows_to_tensor(&inputs, self.input_size, &self.model.device)?;
+        let labels = labels
+            .map(|l| rows_to_tensor(&l, self.output_size, &self.model.device))
+            .transpose()?;
+        self.model.step(&input, labels.as_ref()).map_err(to_py)
+    }
+
+    fn enable_learning(&mut self) {
+        self.model.enable_learning();
+    }
+
+    fn disable_learning(&mut self) {
+        self.model.disable_learning();
+    }
+
+    #[getter]
+    fn is_learning(&self) -> bool {
+        self.model.is_learning
+    }
+
+    #[getter]
+    fn num_layers(&self) -> usize {
+        self.model.layers.len()
+    }
+
+    /// Spike output of one layer, as `[sample][neuron]` rows
+    fn layer_output(&self, layer_id: usize) -> PyResult<Vec<Vec<f32>>> {
+        let layer =
+            self.model.layers.get(layer_id).ok_or_else(|| {
+                PyValueError::new_err(format!("layer {} does not exist", layer_id))
+            })?;
+        tensor_to_rows(layer.output().map_err(to_py)?)
+    }
+
+    fn save(&self, path: &str) -> PyResult<()> {
+        Ok(self.model.save(path)?)
+    }
+
+    fn load(&mut self, path: &str) -> PyResult<()> {
+        Ok(self.model.load(path)?)
+    }
+}
+
+#[pymodule]
+fn csdp(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyModel>()?;
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! Time-major spike rasters.
+//!
+//! A raster holds a layer's activity over a run as one tensor of shape `(T, neurons, batch)`,
+//! with `raster[t]` the `(neurons, batch)` output of step `t` (see
+//! [`crate::models::ProcessOutput::raster`] and [`crate::models::Model::process_raster`]).
+//! The functions here decode and score whole runs with tensor operations instead of loops over
+//! per-step tensors: spike counts and rates, sums over sliding windows, causal filtering with
+//! any kernel or an exponential one, and the van Rossum distance of populations.
+
+use candle_core::{DType, Result as CandleResult, Tensor};
+
+/// Raster of per-step `(neurons, batch)` outputs, `(T, neurons, batch)`
+pub fn stack(steps: &[Tensor]) -> CandleResult<Tensor> {
+    if steps.is_empty() {
+        return Err(candle_core::Error::Msg("no timesteps given".to_string()));
+    }
+    Tensor::stack(steps, 0)
+}
+
+/// Per-step `(neurons, batch)` outputs of a raster, for the step-wise functions of
+/// [`crate::spike_metrics`]
+pub fn unstack(raster: &Tensor) -> CandleResult<Vec<Tensor>> {
+    (0..raster.dim(0)?).map(|t| raster.get(t)).collect()
+}
+
+/// Spikes of each neuron over the run, `(neurons, batch)`
+pub fn counts(raster: &Tensor) -> CandleResult<Tensor> {
+    raster.sum(0)
+}
+
+/// Spikes per step of each neuron over the run, `(neurons, batch)`
+pub fn rates(raster: &Tensor) -> CandleResult<Tensor> {
+    raster.mean(0)
+}
+
+/// Spike counts in windows of `window` steps starting every `stride` steps, `(windows,
+/// neurons, batch)`; windows that would run past the end are left out
+pub fn window_sums(raster: &Tensor, window: usize, stride: usize) -> CandleResult<Tensor> {
+    let steps = raster.dim(0)?;
+    if window == 0 || stride == 0 || window > steps {
+        return Err(candle_core::Error::Msg(format!(
+            "cannot fit windows of {} steps every {} steps into {} steps",
+            window, stride, steps
+        )));
+    }
+    // Running totals with a leading zero row, so window [s, s + window) is c[s + window] - c[s]
+    let totals = Tensor::cat(
+        &[
+            raster.get(0)?.zeros_like()?.unsqueeze(0)?,
+            raster.cumsum(0)?,
+        ],
+        0,
+    )?;
+    let starts: Vec<u32> = (0..=steps - window)
+        .step_by(stride)
+        .map(|s| s as u32)
+        .collect();
+    let ends: Vec<u32> = starts.iter().map(|&s| s + window as u32).collect();
+    let index = |values: Vec<u32>| Tensor::new(values, raster.device());
+    totals
+        .index_select(&index(ends)?, 0)?
+        .sub(&totals.index_select(&index(starts)?, 0)?)
+}
+
+/// Causal convolution over time, `out[t] = sum_k kernel[k] * raster[t - k]` with silence
+/// before the first step; same shape as `raster`
+pub fn causal_convolve(raster: &Tensor, kernel: &[f32]) -> CandleResult<Tensor> {
+    let steps = raster.dim(0)?;
+    let mut out = raster.zeros_like()?;
+    for (lag, &weight) in kernel.iter().enumerate().take(steps) {
+        if weight == 0.0 {
+            continue;
+        }
+        let shifted = if lag == 0 {
+            raster.clone()
+        } else {
+            Tensor::cat(
+                &[
+                    raster.narrow(0, steps - lag, lag)?.zeros_like()?,
+                    raster.narrow(0, 0, steps - lag)?,
+                ],
+                0,
+            )?
+        };
+        out = out.add(&shifted.affine(weight as f64, 0.0)?)?;
+    }
+    Ok(out)
+}
+
+/// Traces of a causal exponential filter with time constant `tau` for steps of `dt`, each
+/// spike adding 1, as used by the van Rossum distance; same shape as `raster`
+pub fn exponential_filter(raster: &Tensor, tau: f32, dt: f32) -> CandleResult<Tensor> {
+    let decay = (-dt / tau).exp() as f64;
+    let mut trace = raster.get(0)?.zeros_like()?;
+    let mut traces = Vec::with_capacity(raster.dim(0)?);
+    for t in 0..raster.dim(0)? {
+        trace = trace.affine(decay, 0.0)?.add(&raster.get(t)?)?;
+        traces.push(trace.clone());
+    }
+    stack(&traces)
+}
+
+/// [`crate::spike_metrics::population_van_rossum`] of two rasters of the same shape, per
+/// sample
+pub fn population_van_rossum(
+    output: &Tensor,
+    target: &Tensor,
+    tau: f32,
+    dt: f32,
+) -> CandleResult<Vec<f32>> {
+    if output.dims() != target.dims() {
+        return Err(candle_core::Error::Msg(format!(
+            "output raster has shape {:?}, target {:?}",
+            output.dims(),
+            target.dims()
+        )));
+    }
+    let target = target.to_device(output.device())?.to_dtype(DType::F32)?;
+    exponential_filter(&output.sub(&target)?, tau, dt)?
+        .sqr()?
+        .sum((0, 1))?
+        .affine((dt / tau) as f64, 0.0)?
+        .sqrt()?
+        .to_vec1::<f32>()
+}
@@ -0,0 +1,251 @@
+//! Reproducibility checks by state hashing.
+//!
+//! A [`ReproducibilityLog`] records a [`Checkpoint`] of a model at chosen points of a run: a
+//! 64-bit hash of every component of its state, i.e. each synapse tensor and layer parameter
+//! (weights, biases, adaptive thresholds), each layer's current output and the host RNG.
+//! Hashes cover the exact bits, so two runs with the same seed that agree on every checkpoint
+//! are bit-exact up to the last one. [`ReproducibilityLog::first_divergence`] names the first
+//! component that differs, which points at the operation that broke determinism, such as a
+//! nondeterministic CUDA reduction. Logs are saved as JSON, so runs on different machines can
+//! be compared (see the `reproducibility` tool).
+//!
+//! The device generator of an accelerator can't be read back, so on the GPU only the host RNG
+//! is hashed; its effect shows up in the next checkpoint's tensors instead.
+
+use crate::error::Result;
+use crate::models::Model;
+use candle_core::{DType, Device, Result as CandleResult, Tensor};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+pub(crate) const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+pub(crate) const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a, which unlike `std`'s hashers is fixed across Rust versions and platforms
+pub(crate) fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |h, &b| (h ^ b as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Hash of the shape, dtype and exact bits of `tensor`'s values
+pub fn hash_tensor(tensor: &Tensor) -> CandleResult<u64> {
+    let mut hash = fnv1a(FNV_OFFSET, tensor.dtype().as_str().as_bytes());
+    for &dim in tensor.dims() {
+        hash = fnv1a(hash, &(dim as u64).to_le_bytes());
+    }
+    let values = tensor.flatten_all()?;
+    let bytes: Vec<u8> = match tensor.dtype() {
+        DType::U8 => values.to_vec1::<u8>()?,
+        DType::U32 => values
+            .to_vec1::<u32>()?
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect(),
+        DType::I64 => values
+            .to_vec1::<i64>()?
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect(),
+        DType::F64 => values
+            .to_vec1::<f64>()?
+            .iter()
+            .flat_map(|v| v.to_bits().to_le_bytes())
+            .collect(),
+        // every other float type converts to f32 exactly
+        _ => values
+            .to_dtype(DType::F32)?
+            .to_vec1::<f32>()?
+            .iter()
+            .flat_map(|v| v.to_bits().to_le_bytes())
+            .collect(),
+    };
+    Ok(fnv1a(hash, &bytes))
+}
+
+/// Hashes of a model's state at one point of a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub label: String,
+    /// `(component, hash)` in a fixed order: the named tensors of [`Model::named_tensors`],
+    /// `<layer>.output` for every layer and `rng` last
+    pub components: Vec<(String, u64)>,
+}
+
+impl Checkpoint {
+    /// Hash every component of `model`'s state
+    pub fn of_model(label: impl Into<String>, model: &Model) -> CandleResult<Self> {
+        let mut components = Vec::new();
+        for (name, tensor) in model.named_tensors()? {
+            components.push((name, hash_tensor(&tensor)?));
+        }
+        for (layer, metadata) in model.layers.iter().zip(&model.layer_metadata) {
+            components.push((
+                format!("{}.output", metadata.name),
+                hash_tensor(layer.output()?)?,
+            ));
+        }
+        components.push(("rng".to_string(), crate::seed::fingerprint()));
+        Ok(Self {
+            label: label.into(),
+            components,
+        })
+    }
+
+    /// Hash of all components together
+    pub fn digest(&self) -> u64 {
+        self.components
+            .iter()
+            .fold(FNV_OFFSET, |hash, (name, value)| {
+                fnv1a(fnv1a(hash, name.as_bytes()), &value.to_le_bytes())
+            })
+    }
+}
+
+/// The first difference between two logs, see [`ReproducibilityLog::first_divergence`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// `component` of checkpoint `index` hashes differently, or exists in only one run (its
+    /// hash there is None)
+    Component {
+        index: usize,
+        label: String,
+        component: String,
+        expected: Option<u64>,
+        actual: Option<u64>,
+    },
+    /// All shared checkpoints match, but the runs recorded different numbers of them
+    Length { expected: usize, actual: usize },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hash = |h: &Option<u64>| match h {
+            Some(h) => format!("{:016x}", h),
+            None => "missing".to_string(),
+        };
+        match self {
+            Divergence::Component {
+                index,
+                label,
+                component,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "checkpoint {} ({}): {} differs, {} expected, {} found",
+                index,
+                label,
+                component,
+                hash(expected),
+                hash(actual)
+            ),
+            Divergence::Length { expected, actual } => {
+                write!(f, "{} checkpoints expected, {} recorded", expected, actual)
+            }
+        }
+    }
+}
+
+/// Checkpoints of one run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReproducibilityLog {
+    /// Seed of the run, for reference
+    pub seed: Option<u64>,
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+impl ReproducibilityLog {
+    pub fn new(seed: Option<u64>) -> Self {
+        Self {
+            seed,
+            checkpoints: Vec::new(),
+        }
+    }
+
+    /// Append a checkpoint of `model`'s current state
+    pub fn record(&mut self, label: impl Into<String>, model: &Model) -> CandleResult<()> {
+        self.checkpoints.push(Checkpoint::of_model(label, model)?);
+        Ok(())
+    }
+
+    /// The first component, in checkpoint and then component order, where `actual` differs
+    /// from this log; None if both runs are bit-exact at every checkpoint
+    pub fn first_divergence(&self, actual: &ReproducibilityLog) -> Option<Divergence> {
+        for (index, (expected, found)) in
+            self.checkpoints.iter().zip(&actual.checkpoints).enumerate()
+        {
+            if expected.digest() == found.digest() {
+                continue;
+            }
+            let divergence = |component: &str, hash, other| Divergence::Component {
+                index,
+                label: expected.label.clone(),
+                component: component.to_string(),
+                expected: hash,
+                actual: other,
+            };
+            let lookup = |checkpoint: &Checkpoint, name: &str| {
+                checkpoint
+                    .components
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .map(|&(_, h)| h)
+            };
+            for (name, hash) in &expected.components {
+                let other = lookup(found, name);
+                if other != Some(*hash) {
+                    return Some(divergence(name, Some(*hash), other));
+                }
+            }
+            for (name, hash) in &found.components {
+                if lookup(expected, name).is_none() {
+                    return Some(divergence(name, None, Some(*hash)));
+                }
+            }
+        }
+        if self.checkpoints.len() != actual.checkpoints.len() {
+            return Some(Divergence::Length {
+                expected: self.checkpoints.len(),
+                actual: actual.checkpoints.len(),
+            });
+        }
+        None
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+}
+
+/// Run `run` twice from `seed`, each time reseeding the host RNG and `device`'s generator
+/// first, and compare the checkpoints it records. Returns the first run's log and the first
+/// divergence of the second run from it.
+pub fn compare_runs<F>(
+    seed: u64,
+    device: &Device,
+    mut run: F,
+) -> Result<(ReproducibilityLog, Option<Divergence>)>
+where
+    F: FnMut(&mut ReproducibilityLog) -> Result<()>,
+{
+    let mut logs = Vec::with_capacity(2);
+    for _ in 0..2 {
+        crate::seed::set_global_seed(seed, device)?;
+        let mut log = ReproducibilityLog::new(Some(seed));
+        run(&mut log)?;
+        logs.push(log);
+    }
+    let second = logs.pop().unwrap();
+    let first = logs.pop().unwrap();
+    let divergence = first.first_divergence(&second);
+    Ok((first, divergence))
+}
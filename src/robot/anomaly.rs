@@ -0,0 +1,492 @@
+//! Anomaly detection on the arm's sensor stream.
+//!
+//! An [`AnomalyDetector`] learns what healthy operation of the arm looks like and flags
+//! readings that don't fit, one control cycle at a time. Each cycle's joint velocities and
+//! loads are standardized against a recording of healthy operation and population-coded,
+//! like the input of a `RobotModel`, into the firing rates of a group of signature neurons.
+//! [`AnomalyDetector::fit`] clusters the signatures of the recording into a handful of
+//! prototypes, which come to cover the distinct phases of normal operation (resting, moving,
+//! holding a load). A reading is scored against the closest prototype feature by feature:
+//! how far the neurons of each feature are from the prototype's, in standard deviations of
+//! how far they were for the healthy readings closest to it. A reading whose worst feature
+//! scores above a threshold is an anomaly, and which features score high classifies it as an
+//! [`AnomalyKind`]. Failed or malformed readings are reported as sensor dropouts.
+//!
+//! [`AnomalyDetector::observe`] returns the events of a cycle to the caller, which decides
+//! what to do about them: the policy of a [`super::pipeline::Pipeline`] can stop the arm by
+//! returning an error, a teleoperation loop can disable the torque.
+
+use super::Arm;
+use super::envelope::DEFAULT_CONTROL_PERIOD_S;
+use super::real_lerobot::RobotResult;
+use crate::error::{CsdpError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// Standardized features are population-coded over `[-Z_RANGE, Z_RANGE]`
+const Z_RANGE: f64 = 4.0;
+/// Smallest standard deviation of a joint velocity, in rad/s, so joints that never moved
+/// while learning don't make every tremor an anomaly
+const MIN_VELOCITY_STD: f64 = 0.01;
+/// Smallest standard deviation of a load, in raw servo units
+const MIN_LOAD_STD: f64 = 1.0;
+/// Added to the standard deviation of a feature's mismatch before dividing by it
+const MISMATCH_FLOOR: f64 = 0.1;
+/// Assignment and update rounds when clustering the signatures
+const CLUSTER_ITERATIONS: usize = 20;
+/// Prototypes closest to fewer than this fraction of the healthy readings are dropped, so
+/// glitches in the recording don't become normal
+const MIN_PROTOTYPE_SHARE: f64 = 0.01;
+
+/// One control cycle of sensor readings, positions in radians relative to the home position
+#[derive(Clone, Debug, PartialEq)]
+pub struct SensorReading {
+    pub positions: Vec<f64>,
+    pub loads: Vec<f64>,
+}
+
+impl SensorReading {
+    /// Read the positions and loads of `arm`
+    pub fn read<A: Arm + ?Sized>(arm: &mut A) -> RobotResult<Self> {
+        Ok(Self {
+            positions: arm.get_motor_positions()?,
+            loads: arm.get_motor_loads()?,
+        })
+    }
+}
+
+/// What an [`AnomalyEvent`] reports
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnomalyKind {
+    /// The load of `joint` left its normal pattern while the joint's motion changed with it,
+    /// as when the arm runs into something
+    Collision { joint: usize },
+    /// The load of `joint` left its normal pattern while the joint kept moving as usual
+    LoadSpike { joint: usize },
+    /// The last `cycles` readings failed or were malformed
+    SensorDropout { cycles: usize },
+    /// The motion is unlike any learned one while the loads are normal
+    Unfamiliar,
+}
+
+/// An anomaly flagged by [`AnomalyDetector::observe`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AnomalyEvent {
+    /// Cycles observed before this one
+    pub cycle: u64,
+    pub kind: AnomalyKind,
+    /// Score of the worst feature, see the module docs; infinite for dropouts
+    pub score: f64,
+}
+
+impl fmt::Display for AnomalyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            AnomalyKind::Collision { joint } => write!(f, "collision at joint {}", joint + 1)?,
+            AnomalyKind::LoadSpike { joint } => write!(f, "load spike at joint {}", joint + 1)?,
+            AnomalyKind::SensorDropout { cycles } => {
+                return write!(
+                    f,
+                    "sensor dropout for {} cycles (cycle {})",
+                    cycles, self.cycle
+                );
+            }
+            AnomalyKind::Unfamiliar => write!(f, "unfamiliar sensor pattern")?,
+        }
+        write!(f, " (cycle {}, score {:.1})", self.cycle, self.score)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnomalyConfig {
+    /// Signature neurons per feature (a velocity or load of one joint); at least 2
+    pub population: usize,
+    /// Prototype signatures of normal operation
+    pub prototypes: usize,
+    /// Score from which a reading is an anomaly
+    pub threshold: f64,
+    /// Consecutive failed readings before a dropout is reported
+    pub dropout_cycles: usize,
+    /// Seconds between two readings, to turn position changes into velocities
+    pub period_s: f64,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            population: 5,
+            prototypes: 8,
+            threshold: 4.0,
+            dropout_cycles: 3,
+            period_s: DEFAULT_CONTROL_PERIOD_S,
+        }
+    }
+}
+
+/// Mean and standard deviation of one feature over the healthy readings
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+struct FeatureStats {
+    mean: f64,
+    std: f64,
+}
+
+/// A learned signature with the mean and standard deviation, per feature, of the mismatch of
+/// the healthy readings closest to it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Prototype {
+    rates: Vec<f64>,
+    mismatch_mean: Vec<f64>,
+    mismatch_std: Vec<f64>,
+}
+
+/// Learns normal signatures of the arm and flags anomalies, see the module docs
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnomalyDetector {
+    pub config: AnomalyConfig,
+    joints: usize,
+    /// velocity of every joint, then load of every joint
+    features: Vec<FeatureStats>,
+    prototypes: Vec<Prototype>,
+    #[serde(skip)]
+    previous_positions: Option<Vec<f64>>,
+    #[serde(skip)]
+    failed_reads: usize,
+    #[serde(skip)]
+    cycle: u64,
+    #[serde(skip)]
+    score: Option<f64>,
+}
+
+impl AnomalyDetector {
+    /// Learn the normal signatures of an arm with `joints` joints from consecutive readings
+    /// of healthy operation, None for failed reads
+    pub fn fit(
+        joints: usize,
+        config: AnomalyConfig,
+        readings: &[Option<SensorReading>],
+    ) -> Result<Self> {
+        if joints == 0 || config.population < 2 || config.prototypes == 0 {
+            return Err(CsdpError::Config(
+                "an anomaly detector needs joints, 2 neurons per feature and a prototype"
+                    .to_string(),
+            ));
+        }
+        if !config.period_s.is_finite() || config.period_s <= 0.0 {
+            return Err(CsdpError::Config(format!(
+                "invalid period {} s",
+                config.period_s
+            )));
+        }
+        let mut detector = Self {
+            config,
+            joints,
+            features: Vec::new(),
+            prototypes: Vec::new(),
+            previous_positions: None,
+            failed_reads: 0,
+            cycle: 0,
+            score: None,
+        };
+
+        let features: Vec<Vec<f64>> = readings
+            .iter()
+            .filter_map(|reading| detector.features_of(reading.as_ref()))
+            .collect();
+        detector.previous_positions = None;
+        if features.len() < detector.config.prototypes {
+            return Err(CsdpError::Data(format!(
+                "{} usable readings are too few for {} prototypes",
+                features.len(),
+                detector.config.prototypes
+            )));
+        }
+        detector.features = (0..2 * joints)
+            .map(|i| {
+                let n = features.len() as f64;
+                let mean = features.iter().map(|f| f[i]).sum::<f64>() / n;
+                let var = features.iter().map(|f| (f[i] - mean).powi(2)).sum::<f64>() / n;
+                let min_std = if i < joints {
+                    MIN_VELOCITY_STD
+                } else {
+                    MIN_LOAD_STD
+                };
+                FeatureStats {
+                    mean,
+                    std: var.sqrt().max(min_std),
+                }
+            })
+            .collect();
+        let signatures: Vec<Vec<f64>> = features.iter().map(|f| detector.signature(f)).collect();
+        detector.prototypes = cluster(
+            &signatures,
+            detector.config.prototypes,
+            detector.config.population,
+        );
+        Ok(detector)
+    }
+
+    pub fn num_joints(&self) -> usize {
+        self.joints
+    }
+
+    /// Number of learned prototypes, at most `config.prototypes`
+    pub fn num_prototypes(&self) -> usize {
+        self.prototypes.len()
+    }
+
+    /// Score of the last observed reading; None if it couldn't be scored
+    pub fn score(&self) -> Option<f64> {
+        self.score
+    }
+
+    /// Forget the previous reading, e.g. after the arm was moved by hand
+    pub fn reset(&mut self) {
+        self.previous_positions = None;
+        self.failed_reads = 0;
+        self.score = None;
+    }
+
+    /// Score one reading (None for a failed read) and return the anomalies it shows. The
+    /// first reading, and the first after a failed one, only gives the positions later
+    /// velocities are measured from.
+    pub fn observe(&mut self, reading: Option<&SensorReading>) -> Vec<AnomalyEvent> {
+        let cycle = self.cycle;
+        self.cycle += 1;
+        self.score = None;
+
+        let malformed = reading.is_some_and(|r| !self.is_valid(r));
+        if reading.is_none() || malformed {
+            self.failed_reads += 1;
+            self.previous_positions = None;
+            if self.failed_reads < self.config.dropout_cycles {
+                return vec![];
+            }
+            return vec![AnomalyEvent {
+                cycle,
+                kind: AnomalyKind::SensorDropout {
+                    cycles: self.failed_reads,
+                },
+                score: f64::INFINITY,
+            }];
+        }
+        self.failed_reads = 0;
+
+        let Some(features) = self.features_of(reading) else {
+            return vec![];
+        };
+        let signature = self.signature(&features);
+        let prototype = &self.prototypes[self.closest(&signature).0];
+        let scores: Vec<f64> = mismatches(&signature, &prototype.rates, self.config.population)
+            .zip(prototype.mismatch_mean.iter().zip(&prototype.mismatch_std))
+            .map(|(mismatch, (mean, std))| (mismatch - mean).max(0.0) / (std + MISMATCH_FLOOR))
+            .collect();
+        let score = scores.iter().copied().fold(0.0, f64::max);
+        self.score = Some(score);
+        if score < self.config.threshold {
+            return vec![];
+        }
+        let kind = self.classify(&scores);
+        vec![AnomalyEvent { cycle, kind, score }]
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let detector: Self = serde_json::from_reader(file)?;
+        let size = 2 * detector.joints * detector.config.population;
+        if detector.features.len() != 2 * detector.joints
+            || detector.prototypes.is_empty()
+            || detector.prototypes.iter().any(|p| {
+                p.rates.len() != size
+                    || p.mismatch_mean.len() != 2 * detector.joints
+                    || p.mismatch_std.len() != 2 * detector.joints
+            })
+        {
+            return Err(CsdpError::Config(
+                "anomaly detector state doesn't match its joints and config".to_string(),
+            ));
+        }
+        Ok(detector)
+    }
+
+    fn is_valid(&self, reading: &SensorReading) -> bool {
+        reading.positions.len() == self.joints
+            && reading.loads.len() == self.joints
+            && reading
+                .positions
+                .iter()
+                .chain(&reading.loads)
+                .all(|v| v.is_finite())
+    }
+
+    /// Velocities and loads of a valid reading; None for the first reading after a gap, whose
+    /// velocities are unknown. Remembers the positions for the next reading.
+    fn features_of(&mut self, reading: Option<&SensorReading>) -> Option<Vec<f64>> {
+        let Some(reading) = reading.filter(|r| self.is_valid(r)) else {
+            self.previous_positions = None;
+            return None;
+        };
+        let previous = self.previous_positions.replace(reading.positions.clone())?;
+        let velocities = reading
+            .positions
+            .iter()
+            .zip(&previous)
+            .map(|(p, q)| (p - q) / self.config.period_s);
+        Some(velocities.chain(reading.loads.iter().copied()).collect())
+    }
+
+    /// Firing rates of the signature neurons for one cycle's features
+    fn signature(&self, features: &[f64]) -> Vec<f64> {
+        let population = self.config.population;
+        let spacing = 2.0 * Z_RANGE / (population - 1) as f64;
+        let mut rates = Vec::with_capacity(features.len() * population);
+        for (&x, stats) in features.iter().zip(&self.features) {
+            let z = ((x - stats.mean) / stats.std).clamp(-Z_RANGE, Z_RANGE);
+            rates.extend((0..population).map(|n| {
+                let d = 2.0 * (z + Z_RANGE - n as f64 * spacing) / spacing;
+                (-0.5 * d * d).exp()
+            }));
+        }
+        rates
+    }
+
+    /// Index of the prototype closest to `signature`, and the distance to it
+    fn closest(&self, signature: &[f64]) -> (usize, f64) {
+        closest(
+            self.prototypes.iter().map(|p| p.rates.as_slice()),
+            signature,
+        )
+    }
+
+    /// Classify an anomaly by its feature scores: the joint whose load scores highest is
+    /// blamed if that load is anomalous too, as a collision if the joint's velocity is off by
+    /// more than a standard deviation as well
+    fn classify(&self, scores: &[f64]) -> AnomalyKind {
+        let (velocities, loads) = scores.split_at(self.joints);
+        let (joint, &load) = loads
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .expect("at least one joint");
+        if load < self.config.threshold {
+            AnomalyKind::Unfamiliar
+        } else if velocities[joint] >= 1.0 {
+            AnomalyKind::Collision { joint }
+        } else {
+            AnomalyKind::LoadSpike { joint }
+        }
+    }
+}
+
+/// k-means clustering of `signatures` into at most `prototypes` prototypes, seeded with the first
+/// signature and then repeatedly the one farthest from all seeds
+fn cluster(signatures: &[Vec<f64>], prototypes: usize, population: usize) -> Vec<Prototype> {
+    let mut centers = vec![signatures[0].clone()];
+    while centers.len() < prototypes {
+        let (farthest, d) = signatures
+            .iter()
+            .map(|s| closest(centers.iter().map(Vec::as_slice), s).1)
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("signatures aren't empty");
+        if d == 0.0 {
+            // Fewer distinct signatures than prototypes
+            break;
+        }
+        centers.push(signatures[farthest].clone());
+    }
+
+    let assign = |centers: &[Vec<f64>]| -> Vec<usize> {
+        signatures
+            .iter()
+            .map(|s| closest(centers.iter().map(Vec::as_slice), s).0)
+            .collect()
+    };
+    let mut assignment = assign(&centers);
+    for _ in 0..CLUSTER_ITERATIONS {
+        for (c, center) in centers.iter_mut().enumerate() {
+            let mut sum = vec![0.0; center.len()];
+            let mut count = 0;
+            for (s, _) in signatures.iter().zip(&assignment).filter(|(_, a)| **a == c) {
+                sum.iter_mut().zip(s).for_each(|(t, x)| *t += x);
+                count += 1;
+            }
+            if count > 0 {
+                *center = sum.into_iter().map(|t| t / count as f64).collect();
+            }
+        }
+        assignment = assign(&centers);
+    }
+
+    let min_count = (MIN_PROTOTYPE_SHARE * signatures.len() as f64).ceil() as usize;
+    let members = |c: usize| assignment.iter().filter(|&&a| a == c).count();
+    let kept: Vec<usize> = (0..centers.len())
+        .filter(|&c| members(c) >= min_count.max(1))
+        .collect();
+    if !kept.is_empty() && kept.len() < centers.len() {
+        centers = kept.into_iter().map(|c| centers[c].clone()).collect();
+        assignment = assign(&centers);
+    }
+
+    centers
+        .into_iter()
+        .enumerate()
+        .map(|(c, rates)| {
+            let members: Vec<Vec<f64>> = signatures
+                .iter()
+                .zip(&assignment)
+                .filter(|(_, a)| **a == c)
+                .map(|(s, _)| mismatches(s, &rates, population).collect())
+                .collect();
+            let n = members.len() as f64;
+            let features = rates.len() / population;
+            let mismatch_mean: Vec<f64> = (0..features)
+                .map(|f| members.iter().map(|m| m[f]).sum::<f64>() / n)
+                .collect();
+            let mismatch_std = (0..features)
+                .map(|f| {
+                    let var = members
+                        .iter()
+                        .map(|m| (m[f] - mismatch_mean[f]).powi(2))
+                        .sum::<f64>()
+                        / n;
+                    var.sqrt()
+                })
+                .collect();
+            Prototype {
+                rates,
+                mismatch_mean,
+                mismatch_std,
+            }
+        })
+        .collect()
+}
+
+/// Index of the closest of `centers` to `signature`, and the distance to it
+fn closest<'a>(centers: impl Iterator<Item = &'a [f64]>, signature: &[f64]) -> (usize, f64) {
+    centers
+        .map(|c| distance(c, signature))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .expect("at least one prototype")
+}
+
+/// Distance between the neurons of every feature of two signatures
+fn mismatches<'a>(a: &'a [f64], b: &'a [f64], population: usize) -> impl Iterator<Item = f64> + 'a {
+    a.chunks(population)
+        .zip(b.chunks(population))
+        .map(|(a, b)| distance(a, b))
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f64>()
+        .sqrt()
+}
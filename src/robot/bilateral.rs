@@ -0,0 +1,108 @@
+//! Force feedback for bilateral teleoperation.
+//!
+//! In plain teleoperation the leader arm is limp and the operator feels nothing of what the
+//! follower touches. With [`ForceFeedback`] the leader is powered at a low torque limit and
+//! held at its present pose shifted against the follower's load: when the follower strains
+//! against an obstacle or lifts a weight, the leader pushes back in the opposite direction,
+//! weakly enough that the operator can still move it. Loads are low-pass filtered and loads
+//! within a deadband, such as the follower's friction and gravity load in free motion, give no
+//! feedback, so the leader doesn't buzz or drift.
+
+use crate::error::{CsdpError, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ForceFeedbackConfig {
+    /// Radians the leader is pushed back per unit of follower load beyond the deadband
+    pub gain: f64,
+    /// Load, in raw servo units, below which a joint gives no feedback
+    pub deadband: f64,
+    /// Largest push-back on any joint, in radians
+    pub max_offset: f64,
+    /// Weight of a new load reading in the filtered load, in (0, 1]; 1 disables filtering
+    pub smoothing: f64,
+    /// Torque limit of the leader's servos while feedback is on, in raw servo units
+    pub torque_limit: u16,
+}
+
+impl Default for ForceFeedbackConfig {
+    fn default() -> Self {
+        Self {
+            gain: 0.0005,
+            deadband: 80.0,
+            max_offset: 0.15,
+            smoothing: 0.3,
+            torque_limit: 150,
+        }
+    }
+}
+
+/// Turns follower loads into leader goals, see the module docs
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForceFeedback {
+    pub config: ForceFeedbackConfig,
+    /// filtered load of every joint; empty before the first reading
+    filtered: Vec<f64>,
+}
+
+impl ForceFeedback {
+    pub fn new(config: ForceFeedbackConfig) -> Result<Self> {
+        let finite_non_negative = |v: f64| v.is_finite() && v >= 0.0;
+        if !finite_non_negative(config.gain)
+            || !finite_non_negative(config.deadband)
+            || !finite_non_negative(config.max_offset)
+        {
+            return Err(CsdpError::Config(format!(
+                "force feedback gain, deadband and max offset must be finite and non-negative, \
+                 got {}, {} and {}",
+                config.gain, config.deadband, config.max_offset
+            )));
+        }
+        if !(config.smoothing > 0.0 && config.smoothing <= 1.0) {
+            return Err(CsdpError::Config(format!(
+                "force feedback smoothing must be in (0, 1], got {}",
+                config.smoothing
+            )));
+        }
+        Ok(Self {
+            config,
+            filtered: Vec::new(),
+        })
+    }
+
+    /// Push-back of every joint for the follower's `loads`, updating the filter. A load in
+    /// the positive direction pushes the leader in the negative one.
+    pub fn offsets(&mut self, loads: &[f64]) -> Vec<f64> {
+        if self.filtered.len() != loads.len() {
+            self.filtered = loads.to_vec();
+        } else {
+            let alpha = self.config.smoothing;
+            for (filtered, &load) in self.filtered.iter_mut().zip(loads) {
+                *filtered += alpha * (load - *filtered);
+            }
+        }
+        let config = &self.config;
+        self.filtered
+            .iter()
+            .map(|&load| {
+                let excess = (load.abs() - config.deadband).max(0.0);
+                (-load.signum() * excess * config.gain).clamp(-config.max_offset, config.max_offset)
+            })
+            .collect()
+    }
+
+    /// Goal of the leader: its present `positions` shifted by the push-back for the
+    /// follower's `loads`
+    pub fn leader_goal(&mut self, positions: &[f64], loads: &[f64]) -> Vec<f64> {
+        positions
+            .iter()
+            .zip(self.offsets(loads))
+            .map(|(p, offset)| p + offset)
+            .collect()
+    }
+
+    /// Forget the filtered loads, e.g. after the follower was repositioned
+    pub fn reset(&mut self) {
+        self.filtered.clear();
+    }
+}
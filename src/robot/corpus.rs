@@ -0,0 +1,208 @@
+//! Growing a recording session into a training corpus.
+//!
+//! A corpus is a directory of episode CSVs indexed by a [`SessionManifest`], the layout
+//! `record --session` writes. [`Corpus::append`] adds an episode under the next free
+//! `episode_<n>.csv` name and rewrites the manifest, so sessions recorded on different days,
+//! or whole other session directories ([`Corpus::append_session`]), accumulate into one
+//! dataset. Every entry stores the [`Trajectory::content_hash`] of its episode, and an
+//! episode whose hash is already in the corpus is skipped, so importing the same files twice
+//! doesn't duplicate them.
+
+use super::profile::RobotProfile;
+use crate::dataset::trajectory::Trajectory;
+use crate::error::{CsdpError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Name of the session manifest in a corpus directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Index of a multi-episode recording session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionManifest {
+    /// What the demonstrations show, e.g. `pick-cube`
+    pub task: String,
+    /// Profile name or path the session was recorded with
+    pub robot_profile: String,
+    /// Calibration in effect while recording
+    pub profile: RobotProfile,
+    pub episodes: Vec<EpisodeEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpisodeEntry {
+    /// CSV file, relative to the session directory
+    pub file: String,
+    pub frames: usize,
+    pub duration_ms: u64,
+    /// [`Trajectory::content_hash`] of the episode; missing in manifests written before
+    /// hashes were recorded
+    #[serde(default)]
+    pub hash: Option<u64>,
+}
+
+/// What [`Corpus::append`] did with an episode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Appended {
+    /// Written as episode `index` of the manifest, to `file`
+    Added { index: usize, file: String },
+    /// Already in the corpus as episode `index`
+    Duplicate { index: usize },
+}
+
+/// A session directory episodes are appended to, see the module docs
+#[derive(Debug)]
+pub struct Corpus {
+    dir: PathBuf,
+    manifest: SessionManifest,
+    /// episode index by content hash
+    hashes: HashMap<u64, usize>,
+}
+
+impl Corpus {
+    /// Open the corpus in `dir`
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let mut manifest: SessionManifest =
+            serde_json::from_reader(File::open(dir.join(MANIFEST_FILE))?)?;
+        let mut hashes = HashMap::new();
+        for (index, entry) in manifest.episodes.iter_mut().enumerate() {
+            // Episodes of older manifests are hashed once, so they are deduplicated too
+            let hash = match entry.hash {
+                Some(hash) => hash,
+                None => Trajectory::load(dir.join(&entry.file))?.content_hash(),
+            };
+            entry.hash = Some(hash);
+            hashes.entry(hash).or_insert(index);
+        }
+        Ok(Self {
+            dir,
+            manifest,
+            hashes,
+        })
+    }
+
+    /// Open the corpus in `dir`, or start an empty one for `task` recorded with `profile`
+    pub fn open_or_create<P: AsRef<Path>>(
+        dir: P,
+        task: &str,
+        robot_profile: &str,
+        profile: &RobotProfile,
+    ) -> Result<Self> {
+        let dir = dir.as_ref();
+        if dir.join(MANIFEST_FILE).exists() {
+            return Self::open(dir);
+        }
+        std::fs::create_dir_all(dir)?;
+        let corpus = Self {
+            dir: dir.to_path_buf(),
+            manifest: SessionManifest {
+                task: task.to_string(),
+                robot_profile: robot_profile.to_string(),
+                profile: profile.clone(),
+                episodes: Vec::new(),
+            },
+            hashes: HashMap::new(),
+        };
+        corpus.save_manifest()?;
+        Ok(corpus)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn manifest(&self) -> &SessionManifest {
+        &self.manifest
+    }
+
+    pub fn len(&self) -> usize {
+        self.manifest.episodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.manifest.episodes.is_empty()
+    }
+
+    /// Path of episode `index`
+    pub fn episode_path(&self, index: usize) -> Option<PathBuf> {
+        let entry = self.manifest.episodes.get(index)?;
+        Some(self.dir.join(&entry.file))
+    }
+
+    /// Write `episode` as the next episode and update the manifest, unless an identical
+    /// episode is already in the corpus
+    pub fn append(&mut self, episode: &Trajectory) -> Result<Appended> {
+        if episode.is_empty() {
+            return Err(CsdpError::Data(
+                "cannot append an empty episode".to_string(),
+            ));
+        }
+        let hash = episode.content_hash();
+        if let Some(&index) = self.hashes.get(&hash) {
+            return Ok(Appended::Duplicate { index });
+        }
+
+        let index = self.manifest.episodes.len();
+        // Numbered after the episode, skipping names taken by files the manifest doesn't list
+        let file = (index..)
+            .map(|n| format!("episode_{:03}.csv", n))
+            .find(|name| !self.dir.join(name).exists())
+            .expect("episode names are unbounded");
+        episode.save(self.dir.join(&file))?;
+        self.manifest.episodes.push(EpisodeEntry {
+            file: file.clone(),
+            frames: episode.len(),
+            duration_ms: episode.duration_ms().unwrap_or(0),
+            hash: Some(hash),
+        });
+        self.hashes.insert(hash, index);
+        self.save_manifest()?;
+        Ok(Appended::Added { index, file })
+    }
+
+    /// Append the episode stored in the CSV at `path`
+    pub fn append_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Appended> {
+        self.append(&Trajectory::load(path)?)
+    }
+
+    /// Append every episode of another session directory, in its manifest's order
+    pub fn append_session<P: AsRef<Path>>(&mut self, dir: P) -> Result<Vec<Appended>> {
+        let dir = dir.as_ref();
+        let other: SessionManifest = serde_json::from_reader(File::open(dir.join(MANIFEST_FILE))?)?;
+        if other.task != self.manifest.task {
+            log::warn!(
+                "Appending '{}' episodes to a '{}' corpus",
+                other.task,
+                self.manifest.task
+            );
+        }
+        if other.profile.home_positions != self.manifest.profile.home_positions
+            || other.profile.min_positions != self.manifest.profile.min_positions
+            || other.profile.max_positions != self.manifest.profile.max_positions
+        {
+            log::warn!(
+                "{} was recorded with a different calibration ({}); consider `retarget`",
+                dir.display(),
+                other.robot_profile
+            );
+        }
+        other
+            .episodes
+            .iter()
+            .map(|entry| self.append_file(dir.join(&entry.file)))
+            .collect()
+    }
+
+    /// Write the manifest through a temporary file, so an interrupted write never leaves a
+    /// truncated one
+    fn save_manifest(&self) -> Result<()> {
+        let path = self.dir.join(MANIFEST_FILE);
+        let tmp = path.with_extension("json.tmp");
+        serde_json::to_writer_pretty(File::create(&tmp)?, &self.manifest)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+//! Joint-space safety envelope learned from demonstrations.
+//!
+//! A [`SafetyEnvelope`] holds the range every joint covered in a set of recorded
+//! demonstrations and, when the recordings carry timestamps, the fastest each joint moved.
+//! [`EnvelopedArm`] wraps an [`Arm`] and checks every goal a model sends against the envelope
+//! before it reaches the arm, either clamping it into the envelope or dropping it. Positions
+//! are radians relative to the home position, as [`super::routines::record`] writes them.
+
+use super::Arm;
+use super::real_lerobot::RobotResult;
+use crate::dataset::trajectory::Trajectory;
+use crate::error::{CsdpError, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Period assumed between two commands when checking velocities, in seconds
+pub const DEFAULT_CONTROL_PERIOD_S: f64 = 0.05;
+
+/// What [`EnvelopedArm`] does with a goal outside the envelope
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvelopeMode {
+    /// Move every joint to the closest position inside the envelope
+    #[default]
+    Clamp,
+    /// Drop the goal; the arm keeps moving toward the last accepted one
+    Reject,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SafetyEnvelope {
+    pub min_positions: Vec<f64>,
+    pub max_positions: Vec<f64>,
+    /// Largest speed of each joint in rad/s; None if the demonstrations had no timestamps
+    pub max_velocities: Option<Vec<f64>>,
+    #[serde(default)]
+    pub mode: EnvelopeMode,
+}
+
+/// One joint of a goal outside the envelope
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Violation {
+    Position { joint: usize, value: f64 },
+    Velocity { joint: usize, velocity: f64 },
+}
+
+impl SafetyEnvelope {
+    /// Per-joint position range of `trajectories` and, if all of them have timestamps, the
+    /// largest speed between consecutive frames
+    pub fn from_trajectories(trajectories: &[Trajectory]) -> Result<Self> {
+        let Some(first) = trajectories.iter().find(|t| !t.is_empty()) else {
+            return Err(CsdpError::Data(
+                "no demonstration frames to build an envelope from".to_string(),
+            ));
+        };
+        let joints = first.channels.len();
+        let mut min_positions = vec![f64::INFINITY; joints];
+        let mut max_positions = vec![f64::NEG_INFINITY; joints];
+        let mut max_velocities = Some(vec![0.0; joints]);
+
+        for trajectory in trajectories {
+            if trajectory.channels.len() != joints {
+                return Err(CsdpError::Data(format!(
+                    "demonstrations have {} and {} joints",
+                    joints,
+                    trajectory.channels.len()
+                )));
+            }
+            for frame in &trajectory.frames {
+                for (joint, &value) in frame.iter().enumerate() {
+                    min_positions[joint] = min_positions[joint].min(value);
+                    max_positions[joint] = max_positions[joint].max(value);
+                }
+            }
+            let Some(timestamps) = &trajectory.timestamps_ms else {
+                max_velocities = None;
+                continue;
+            };
+            let Some(velocities) = max_velocities.as_mut() else {
+                continue;
+            };
+            for i in 1..trajectory.len() {
+                // Repeated or out-of-order timestamps carry no velocity information
+                let Some(dt_ms) = timestamps[i].checked_sub(timestamps[i - 1]) else {
+                    continue;
+                };
+                if dt_ms == 0 {
+                    continue;
+                }
+                let dt_s = dt_ms as f64 / 1000.0;
+                for (joint, velocity) in velocities.iter_mut().enumerate() {
+                    let step = trajectory.frames[i][joint] - trajectory.frames[i - 1][joint];
+                    *velocity = velocity.max(step.abs() / dt_s);
+                }
+            }
+        }
+
+        Ok(Self {
+            min_positions,
+            max_positions,
+            max_velocities,
+            mode: EnvelopeMode::Clamp,
+        })
+    }
+
+    /// Envelope of the demonstration CSVs at `paths`
+    pub fn from_csvs<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let trajectories = paths
+            .iter()
+            .map(Trajectory::load)
+            .collect::<Result<Vec<_>>>()?;
+        Self::from_trajectories(&trajectories)
+    }
+
+    /// Widen every joint range by `margin` radians on both sides
+    pub fn with_margin(mut self, margin: f64) -> Self {
+        for (min, max) in self.min_positions.iter_mut().zip(&mut self.max_positions) {
+            *min -= margin;
+            *max += margin;
+        }
+        self
+    }
+
+    /// Scale the velocity bounds, e.g. 1.2 to allow moving 20% faster than demonstrated
+    pub fn with_velocity_scale(mut self, scale: f64) -> Self {
+        if let Some(velocities) = self.max_velocities.as_mut() {
+            velocities.iter_mut().for_each(|v| *v *= scale);
+        }
+        self
+    }
+
+    pub fn with_mode(mut self, mode: EnvelopeMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn num_joints(&self) -> usize {
+        self.min_positions.len()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let envelope: Self = serde_json::from_reader(file)?;
+        let joints = envelope.num_joints();
+        if envelope.max_positions.len() != joints
+            || envelope
+                .max_velocities
+                .as_ref()
+                .is_some_and(|v| v.len() != joints)
+        {
+            return Err(CsdpError::Config(
+                "envelope bounds have different numbers of joints".to_string(),
+            ));
+        }
+        Ok(envelope)
+    }
+
+    /// Joints of `goal` outside the envelope when moving there from `previous` within
+    /// `dt_s` seconds. Velocities are only checked when `previous` is given.
+    pub fn check(&self, previous: Option<&[f64]>, goal: &[f64], dt_s: f64) -> Vec<Violation> {
+        let mut violations = vec![];
+        for (joint, &value) in goal.iter().enumerate().take(self.num_joints()) {
+            if value < self.min_positions[joint] || value > self.max_positions[joint] {
+                violations.push(Violation::Position { joint, value });
+            }
+        }
+        if let (Some(previous), Some(max_velocities)) = (previous, &self.max_velocities) {
+            for (joint, (&value, &max_velocity)) in goal.iter().zip(max_velocities).enumerate() {
+                let velocity = (value - previous[joint]) / dt_s;
+                if velocity.abs() > max_velocity {
+                    violations.push(Violation::Velocity { joint, velocity });
+                }
+            }
+        }
+        violations
+    }
+
+    /// Closest goal to `goal` inside the envelope: each joint is limited to its range and,
+    /// when `previous` is given, to the distance it may move within `dt_s` seconds
+    pub fn clamp(&self, previous: Option<&[f64]>, goal: &[f64], dt_s: f64) -> Vec<f64> {
+        goal.iter()
+            .enumerate()
+            .map(|(joint, &value)| {
+                if joint >= self.num_joints() {
+                    return value;
+                }
+                let mut value = value;
+                if let (Some(previous), Some(max_velocities)) = (previous, &self.max_velocities) {
+                    let max_step = max_velocities[joint] * dt_s;
+                    value = value.clamp(previous[joint] - max_step, previous[joint] + max_step);
+                }
+                value.clamp(self.min_positions[joint], self.max_positions[joint])
+            })
+            .collect()
+    }
+}
+
+/// An [`Arm`] whose goals are kept inside a [`SafetyEnvelope`]
+pub struct EnvelopedArm {
+    arm: Box<dyn Arm>,
+    envelope: SafetyEnvelope,
+    /// seconds between two commands, for the velocity bounds
+    control_period_s: f64,
+    /// last goal sent to the arm; None until the first command or after homing
+    last_goal: Option<Vec<f64>>,
+    clamped: usize,
+    rejected: usize,
+}
+
+impl EnvelopedArm {
+    pub fn new(arm: Box<dyn Arm>, envelope: SafetyEnvelope) -> Self {
+        Self {
+            arm,
+            envelope,
+            control_period_s: DEFAULT_CONTROL_PERIOD_S,
+            last_goal: None,
+            clamped: 0,
+            rejected: 0,
+        }
+    }
+
+    /// Seconds between two commands of the control loop (default
+    /// [`DEFAULT_CONTROL_PERIOD_S`]); a joint may move `max_velocity * period` per command
+    pub fn with_control_period(mut self, seconds: f64) -> Self {
+        self.control_period_s = seconds;
+        self
+    }
+
+    pub fn envelope(&self) -> &SafetyEnvelope {
+        &self.envelope
+    }
+
+    /// Goals that were moved into the envelope
+    pub fn clamped(&self) -> usize {
+        self.clamped
+    }
+
+    /// Goals that were dropped
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+}
+
+impl Arm for EnvelopedArm {
+    fn enable(&mut self) -> RobotResult<()> {
+        self.arm.enable()
+    }
+
+    fn disable(&mut self) -> RobotResult<()> {
+        self.arm.disable()
+    }
+
+    fn set_goal_positions(&mut self, positions: &[f64]) -> RobotResult<()> {
+        let previous = match self.last_goal.take() {
+            Some(goal) => goal,
+            None => self.arm.get_motor_positions()?,
+        };
+        let violations =
+            self.envelope
+                .check(Some(previous.as_slice()), positions, self.control_period_s);
+        if violations.is_empty() {
+            self.arm.set_goal_positions(positions)?;
+            self.last_goal = Some(positions.to_vec());
+            return Ok(());
+        }
+
+        match self.envelope.mode {
+            EnvelopeMode::Clamp => {
+                self.clamped += 1;
+                log::debug!(
+                    "Clamping goal {:?} into the envelope: {:?}",
+                    positions,
+                    violations
+                );
+                let goal = self.envelope.clamp(
+                    Some(previous.as_slice()),
+                    positions,
+                    self.control_period_s,
+                );
+                self.arm.set_goal_positions(&goal)?;
+                self.last_goal = Some(goal);
+            }
+            EnvelopeMode::Reject => {
+                self.rejected += 1;
+                log::warn!(
+                    "Rejected goal {:?} outside the envelope: {:?}",
+                    positions,
+                    violations
+                );
+                self.last_goal = Some(previous);
+            }
+        }
+        Ok(())
+    }
+
+    /// Homing is a trusted command and bypasses the envelope
+    fn go_to_home_positions(&mut self) -> RobotResult<()> {
+        self.last_goal = None;
+        self.arm.go_to_home_positions()
+    }
+
+    fn get_motor_positions(&mut self) -> RobotResult<Vec<f64>> {
+        self.arm.get_motor_positions()
+    }
+
+    fn get_goal_positions(&mut self) -> RobotResult<Vec<f64>> {
+        self.arm.get_goal_positions()
+    }
+
+    fn get_motor_loads(&mut self) -> RobotResult<Vec<f64>> {
+        self.arm.get_motor_loads()
+    }
+
+    fn try_clone(&self) -> Option<Box<dyn Arm>> {
+        let arm = self.arm.try_clone()?;
+        Some(Box::new(
+            Self::new(arm, self.envelope.clone()).with_control_period(self.control_period_s),
+        ))
+    }
+}
@@ -0,0 +1,255 @@
+//! Fault injection for the robot stack.
+//!
+//! The serial bus of a real arm is flaky: reads time out, writes get lost, and a servo can
+//! answer with a position it measured a while ago. [`FaultyArm`] wraps any [`Arm`], simulated
+//! or real, and injects these faults at random with the rates of a [`FaultConfig`], so the
+//! code above it (the control loop, the pipeline, the environments and recording) can be
+//! exercised against the failures it will see on hardware. A timed-out read blocks for
+//! `timeout_ms` like the serial port does and then fails with [`std::io::ErrorKind::TimedOut`].
+//! A dropped write reports success but never reaches the arm. A stale read still goes to the
+//! arm but returns the previous reading. Enabling and disabling torque are passed through
+//! unfaulted. Faults are drawn from their own seeded RNG, so a run with a fixed seed fails
+//! the same way every time.
+
+use super::Arm;
+use super::real_lerobot::RobotResult;
+use crate::error::{CsdpError, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Rates of the faults a [`FaultyArm`] injects, as probabilities per bus transfer
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FaultConfig {
+    /// A read (positions, goals or loads) times out
+    pub read_timeout: f64,
+    /// How long a timed-out read blocks before failing, in milliseconds
+    pub timeout_ms: u64,
+    /// A position read returns the previous reading instead of a fresh one
+    pub stale_position: f64,
+    /// A goal write is lost on the bus without an error
+    pub dropped_write: f64,
+    /// A goal write fails with an error
+    pub write_error: f64,
+    /// Seed of the fault draws; None takes one from the global seed
+    pub seed: Option<u64>,
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self {
+            read_timeout: 0.0,
+            // The serial port timeout of `LeRobot`
+            timeout_ms: 100,
+            stale_position: 0.0,
+            dropped_write: 0.0,
+            write_error: 0.0,
+            seed: None,
+        }
+    }
+}
+
+impl FaultConfig {
+    pub fn validate(&self) -> Result<()> {
+        let rates = [
+            ("read_timeout", self.read_timeout),
+            ("stale_position", self.stale_position),
+            ("dropped_write", self.dropped_write),
+            ("write_error", self.write_error),
+        ];
+        for (name, rate) in rates {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(CsdpError::Config(format!(
+                    "fault rate {} must be in [0, 1], got {}",
+                    name, rate
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let config: Self = serde_json::from_reader(file)?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Transfers and injected faults of a [`FaultyArm`] so far
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    pub reads: usize,
+    pub read_timeouts: usize,
+    pub stale_positions: usize,
+    pub writes: usize,
+    pub dropped_writes: usize,
+    pub write_errors: usize,
+}
+
+impl FaultStats {
+    /// Number of faults injected
+    pub fn faults(&self) -> usize {
+        self.read_timeouts + self.stale_positions + self.dropped_writes + self.write_errors
+    }
+}
+
+/// An [`Arm`] behind a flaky bus, see the module docs
+pub struct FaultyArm<A: Arm> {
+    arm: A,
+    config: FaultConfig,
+    /// seed of `rng`, from which the seeds of clones are derived
+    seed: u64,
+    rng: StdRng,
+    /// clones made so far, so every clone gets its own seed
+    clones: AtomicU64,
+    /// newest position reading that reached the caller, repeated by stale reads
+    last_positions: Option<Vec<f64>>,
+    /// whether faults are injected; transfers pass through unchanged while false
+    active: bool,
+    stats: FaultStats,
+}
+
+impl<A: Arm> FaultyArm<A> {
+    pub fn new(arm: A, config: FaultConfig) -> Result<Self> {
+        config.validate()?;
+        let seed = config.seed.unwrap_or_else(|| crate::seed::rng().next_u64());
+        Ok(Self {
+            arm,
+            config,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            clones: AtomicU64::new(0),
+            last_positions: None,
+            active: true,
+            stats: FaultStats::default(),
+        })
+    }
+
+    pub fn config(&self) -> &FaultConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> FaultStats {
+        self.stats
+    }
+
+    /// Stop or resume injecting faults, e.g. to home the arm reliably between episodes
+    pub fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn inner(&self) -> &A {
+        &self.arm
+    }
+
+    pub fn inner_mut(&mut self) -> &mut A {
+        &mut self.arm
+    }
+
+    pub fn into_inner(self) -> A {
+        self.arm
+    }
+
+    /// Whether a fault with probability `rate` strikes this transfer
+    fn strikes(&mut self, rate: f64) -> bool {
+        self.active && rate > 0.0 && self.rng.gen_bool(rate)
+    }
+
+    /// Count a read, and fail it after the timeout if it times out
+    fn read(&mut self) -> RobotResult<()> {
+        self.stats.reads += 1;
+        if !self.strikes(self.config.read_timeout) {
+            return Ok(());
+        }
+        self.stats.read_timeouts += 1;
+        std::thread::sleep(Duration::from_millis(self.config.timeout_ms));
+        Err(CsdpError::Io(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "injected read timeout",
+        )))
+    }
+
+    /// Send a write through `send` unless it is dropped or fails
+    fn write(&mut self, send: impl FnOnce(&mut A) -> RobotResult<()>) -> RobotResult<()> {
+        self.stats.writes += 1;
+        if self.strikes(self.config.write_error) {
+            self.stats.write_errors += 1;
+            return Err(CsdpError::Servo("injected write error".to_string()));
+        }
+        if self.strikes(self.config.dropped_write) {
+            self.stats.dropped_writes += 1;
+            return Ok(());
+        }
+        send(&mut self.arm)
+    }
+}
+
+impl<A: Arm> Arm for FaultyArm<A> {
+    fn enable(&mut self) -> RobotResult<()> {
+        self.arm.enable()
+    }
+
+    fn disable(&mut self) -> RobotResult<()> {
+        self.arm.disable()
+    }
+
+    fn set_goal_positions(&mut self, positions: &[f64]) -> RobotResult<()> {
+        self.write(|arm| arm.set_goal_positions(positions))
+    }
+
+    fn go_to_home_positions(&mut self) -> RobotResult<()> {
+        self.write(|arm| arm.go_to_home_positions())
+    }
+
+    fn get_motor_positions(&mut self) -> RobotResult<Vec<f64>> {
+        self.read()?;
+        let positions = self.arm.get_motor_positions()?;
+        if self.strikes(self.config.stale_position)
+            && let Some(last) = &self.last_positions
+        {
+            self.stats.stale_positions += 1;
+            return Ok(last.clone());
+        }
+        self.last_positions = Some(positions.clone());
+        Ok(positions)
+    }
+
+    fn get_goal_positions(&mut self) -> RobotResult<Vec<f64>> {
+        self.read()?;
+        self.arm.get_goal_positions()
+    }
+
+    fn get_motor_loads(&mut self) -> RobotResult<Vec<f64>> {
+        self.read()?;
+        self.arm.get_motor_loads()
+    }
+
+    /// A copy of the inner arm with the same fault rates. Each clone's seed is derived from
+    /// this arm's seed and the number of clones before it, so clones fail independently of
+    /// each other and the same way in every run.
+    fn try_clone(&self) -> Option<Box<dyn Arm>> {
+        let arm = self.arm.try_clone()?;
+        let n = self.clones.fetch_add(1, Ordering::Relaxed) + 1;
+        let config = FaultConfig {
+            seed: Some(StdRng::seed_from_u64(self.seed.wrapping_add(n)).next_u64()),
+            ..self.config
+        };
+        let mut clone = FaultyArm::new(arm, config).ok()?;
+        clone.active = self.active;
+        Some(Box::new(clone))
+    }
+}
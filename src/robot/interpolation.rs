@@ -0,0 +1,221 @@
+//! Smooth playback of recorded trajectories.
+//!
+//! A recording holds frames at whatever rate, and with whatever gaps, it was captured at.
+//! [`InterpolatedTrajectory`] turns it into a continuous joint-space path that playback samples
+//! at its own control rate. [`Interpolation::Linear`] connects the frames with straight
+//! segments. [`Interpolation::Cubic`] fits a monotone cubic (Fritsch-Butland tangents), which
+//! is smooth in velocity and never overshoots the frames on either side of a segment, so it
+//! stays inside any joint limits the frames respect. The path starts and ends at rest.
+//!
+//! Frames can be clamped into the calibrated joint limits, and segments are stretched in time
+//! until no joint moves faster than its velocity limit.
+
+use crate::dataset::trajectory::Trajectory;
+use crate::error::{CsdpError, Result};
+use clap::ValueEnum;
+use std::time::Duration;
+
+/// Passes of per-segment stretching before the whole path is slowed down uniformly
+const RETIME_PASSES: usize = 8;
+
+/// How playback moves between recorded frames
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Interpolation {
+    /// Hold each frame until the next one, jumping between them as recorded
+    Step,
+    /// Straight segments between frames
+    Linear,
+    /// Monotone cubic through the frames, continuous in velocity
+    #[default]
+    Cubic,
+}
+
+#[derive(Debug, Clone)]
+pub struct InterpolatedTrajectory {
+    /// frame times in seconds from the first frame, strictly increasing
+    times: Vec<f64>,
+    frames: Vec<Vec<f64>>,
+    method: Interpolation,
+}
+
+impl InterpolatedTrajectory {
+    /// Path through the frames of `trajectory`, which needs timestamps. A frame whose
+    /// timestamp doesn't advance past the previous one replaces it.
+    pub fn new(trajectory: &Trajectory, method: Interpolation) -> Result<Self> {
+        let Some(timestamps) = &trajectory.timestamps_ms else {
+            return Err(CsdpError::Data(
+                "interpolation needs a `timestamp_ms` column".to_string(),
+            ));
+        };
+        let Some(&start) = timestamps.first() else {
+            return Err(CsdpError::Data("no frames to interpolate".to_string()));
+        };
+
+        let mut times: Vec<f64> = Vec::with_capacity(timestamps.len());
+        let mut frames: Vec<Vec<f64>> = Vec::with_capacity(timestamps.len());
+        for (&timestamp, frame) in timestamps.iter().zip(&trajectory.frames) {
+            let t = timestamp.saturating_sub(start) as f64 / 1000.0;
+            if let (Some(&last), Some(previous)) = (times.last(), frames.last_mut())
+                && t <= last
+            {
+                *previous = frame.clone();
+                continue;
+            }
+            times.push(t);
+            frames.push(frame.clone());
+        }
+        Ok(Self {
+            times,
+            frames,
+            method,
+        })
+    }
+
+    /// Clamp every frame into `[lower, upper]`, e.g. a profile's limits relative to home
+    pub fn with_limits(mut self, lower: &[f64], upper: &[f64]) -> Self {
+        for frame in &mut self.frames {
+            for ((value, &lo), &hi) in frame.iter_mut().zip(lower).zip(upper) {
+                // min/max instead of clamp: an inverted calibration must not panic
+                *value = value.max(lo).min(hi);
+            }
+        }
+        self
+    }
+
+    /// Stretch segments until no joint moves faster than its limit in rad/s. Limits that
+    /// aren't positive leave their joint unconstrained. Step playback has no velocity to
+    /// limit and is left as is.
+    pub fn with_max_velocities(mut self, max_velocities: &[f64]) -> Self {
+        if self.method == Interpolation::Step || self.times.len() < 2 {
+            return self;
+        }
+        // Stretching one segment also changes the tangents of its neighbours, so slowing
+        // segments individually may not settle; a last uniform stretch scales every
+        // velocity down by the same factor and always does
+        for _ in 0..RETIME_PASSES {
+            let ratios: Vec<f64> = (0..self.times.len() - 1)
+                .map(|segment| self.speed_ratio(segment, max_velocities))
+                .collect();
+            if ratios.iter().all(|&r| r <= 1.0) {
+                return self;
+            }
+            let mut durations: Vec<f64> = self.times.windows(2).map(|w| w[1] - w[0]).collect();
+            for (duration, ratio) in durations.iter_mut().zip(ratios) {
+                *duration *= ratio.max(1.0);
+            }
+            let mut t = 0.0;
+            for (time, duration) in self.times.iter_mut().skip(1).zip(durations) {
+                t += duration;
+                *time = t;
+            }
+        }
+        let ratio = (0..self.times.len() - 1)
+            .map(|segment| self.speed_ratio(segment, max_velocities))
+            .fold(1.0, f64::max);
+        self.times.iter_mut().for_each(|t| *t *= ratio);
+        self
+    }
+
+    pub fn method(&self) -> Interpolation {
+        self.method
+    }
+
+    /// Time from the first frame to the last
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.times.last().copied().unwrap_or(0.0))
+    }
+
+    /// The frames after clamping and retiming, with their times
+    pub fn knots(&self) -> impl Iterator<Item = (Duration, &[f64])> + '_ {
+        self.times
+            .iter()
+            .zip(&self.frames)
+            .map(|(&t, frame)| (Duration::from_secs_f64(t), frame.as_slice()))
+    }
+
+    /// Joint positions `t` after the first frame; before and after the path, its end points
+    pub fn sample(&self, t: Duration) -> Vec<f64> {
+        let n = self.times.len();
+        let t = t.as_secs_f64();
+        if n == 1 || t <= 0.0 {
+            return self.frames[0].clone();
+        }
+        if t >= self.times[n - 1] {
+            return self.frames[n - 1].clone();
+        }
+        let segment = self.times.partition_point(|&time| time <= t) - 1;
+        let (t0, t1) = (self.times[segment], self.times[segment + 1]);
+        let (h, u) = (t1 - t0, (t - t0) / (t1 - t0));
+        let (p0, p1) = (&self.frames[segment], &self.frames[segment + 1]);
+        match self.method {
+            Interpolation::Step => p0.clone(),
+            Interpolation::Linear => p0.iter().zip(p1).map(|(a, b)| a + (b - a) * u).collect(),
+            Interpolation::Cubic => {
+                // Cubic Hermite basis
+                let (u2, u3) = (u * u, u * u * u);
+                let h00 = 2.0 * u3 - 3.0 * u2 + 1.0;
+                let h10 = u3 - 2.0 * u2 + u;
+                let h01 = -2.0 * u3 + 3.0 * u2;
+                let h11 = u3 - u2;
+                (0..p0.len().min(p1.len()))
+                    .map(|j| {
+                        let (m0, m1) = (self.tangent(segment, j), self.tangent(segment + 1, j));
+                        h00 * p0[j] + h10 * h * m0 + h01 * p1[j] + h11 * h * m1
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Velocity of `joint` at frame `k` in rad/s: zero at the ends and where the path turns,
+    /// else the weighted harmonic mean of the neighbouring slopes, which keeps the cubic
+    /// monotone between frames
+    fn tangent(&self, k: usize, joint: usize) -> f64 {
+        if k == 0 || k + 1 >= self.times.len() {
+            return 0.0;
+        }
+        let h0 = self.times[k] - self.times[k - 1];
+        let h1 = self.times[k + 1] - self.times[k];
+        let d0 = (self.frames[k][joint] - self.frames[k - 1][joint]) / h0;
+        let d1 = (self.frames[k + 1][joint] - self.frames[k][joint]) / h1;
+        if d0 * d1 <= 0.0 {
+            return 0.0;
+        }
+        3.0 * (h0 + h1) / ((2.0 * h1 + h0) / d0 + (h1 + 2.0 * h0) / d1)
+    }
+
+    /// Largest ratio of a joint's peak speed on `segment` to its limit
+    fn speed_ratio(&self, segment: usize, max_velocities: &[f64]) -> f64 {
+        let h = self.times[segment + 1] - self.times[segment];
+        let (p0, p1) = (&self.frames[segment], &self.frames[segment + 1]);
+        let mut ratio: f64 = 0.0;
+        for (j, &limit) in max_velocities.iter().enumerate().take(p0.len()) {
+            if limit <= 0.0 || !limit.is_finite() {
+                continue;
+            }
+            let peak = match self.method {
+                Interpolation::Step => 0.0,
+                Interpolation::Linear => ((p1[j] - p0[j]) / h).abs(),
+                Interpolation::Cubic => {
+                    // The velocity is a quadratic a u^2 + b u + c in the segment position
+                    // u, so its peak is at an end or at the vertex
+                    let (m0, m1) = (self.tangent(segment, j), self.tangent(segment + 1, j));
+                    let a = 6.0 * (p0[j] - p1[j]) / h + 3.0 * (m0 + m1);
+                    let b = 6.0 * (p1[j] - p0[j]) / h - 4.0 * m0 - 2.0 * m1;
+                    let c = m0;
+                    let at = |u: f64| (a * u * u + b * u + c).abs();
+                    let mut peak = at(0.0).max(at(1.0));
+                    if a != 0.0 {
+                        let vertex = -b / (2.0 * a);
+                        if vertex > 0.0 && vertex < 1.0 {
+                            peak = peak.max(at(vertex));
+                        }
+                    }
+                    peak
+                }
+            };
+            ratio = ratio.max(peak / limit);
+        }
+        ratio
+    }
+}
@@ -1,2 +1,76 @@
+pub mod anomaly;
+pub mod bilateral;
+pub mod corpus;
+pub mod envelope;
+pub mod faults;
+pub mod interpolation;
+pub mod pipeline;
+pub mod profile;
 pub mod real_lerobot;
+pub mod realtime;
+pub mod retarget;
+pub mod routines;
 pub mod sim_lerobot;
+
+use real_lerobot::RobotResult;
+
+/// Joint-space interface shared by the physical arm and its simulation, so environments and
+/// training loops can drive either. Positions are radians relative to the home position.
+pub trait Arm {
+    fn enable(&mut self) -> RobotResult<()>;
+
+    fn disable(&mut self) -> RobotResult<()>;
+
+    fn set_goal_positions(&mut self, positions: &[f64]) -> RobotResult<()>;
+
+    fn go_to_home_positions(&mut self) -> RobotResult<()> {
+        self.set_goal_positions(&[0.0; 6])
+    }
+
+    fn get_motor_positions(&mut self) -> RobotResult<Vec<f64>>;
+
+    fn get_goal_positions(&mut self) -> RobotResult<Vec<f64>>;
+
+    fn get_motor_loads(&mut self) -> RobotResult<Vec<f64>>;
+
+    /// Independent copy of the arm, for arms that can be duplicated (simulations)
+    fn try_clone(&self) -> Option<Box<dyn Arm>> {
+        None
+    }
+}
+
+/// Boxed arms, e.g. `Box<dyn Arm>`, work wherever an `Arm` is expected, such as inside
+/// wrappers generic over the arm
+impl<A: Arm + ?Sized> Arm for Box<A> {
+    fn enable(&mut self) -> RobotResult<()> {
+        (**self).enable()
+    }
+
+    fn disable(&mut self) -> RobotResult<()> {
+        (**self).disable()
+    }
+
+    fn set_goal_positions(&mut self, positions: &[f64]) -> RobotResult<()> {
+        (**self).set_goal_positions(positions)
+    }
+
+    fn go_to_home_positions(&mut self) -> RobotResult<()> {
+        (**self).go_to_home_positions()
+    }
+
+    fn get_motor_positions(&mut self) -> RobotResult<Vec<f64>> {
+        (**self).get_motor_positions()
+    }
+
+    fn get_goal_positions(&mut self) -> RobotResult<Vec<f64>> {
+        (**self).get_goal_positions()
+    }
+
+    fn get_motor_loads(&mut self) -> RobotResult<Vec<f64>> {
+        (**self).get_motor_loads()
+    }
+
+    fn try_clone(&self) -> Option<Box<dyn Arm>> {
+        (**self).try_clone()
+    }
+}
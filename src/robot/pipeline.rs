@@ -0,0 +1,440 @@
+//! Pipelined control loop.
+//!
+//! A [`Pipeline`] splits a control loop into four threads connected by bounded channels:
+//!
+//! - the reader reads the joint positions on a [`Scheduler`] at the control rate,
+//! - the policy thread turns each reading into goal positions (e.g. with a `RobotModel`),
+//! - the writer sends the goals to the arm,
+//! - the logger hands every completed cycle to a sink, such as a CSV file or a GUI.
+//!
+//! Every message carries its sequence number and the time it was produced, so the latency of
+//! each stage is known. No stage waits on a slower one: the policy and writer threads work on
+//! the newest message and skip older ones still queued, and a cycle record that finds the
+//! logger's queue full is dropped. A slow disk flush or GUI frame therefore delays only the
+//! log, and a slow policy step only makes the goals staler; the reader keeps its rate. The
+//! reader and writer share the arm through a mutex, as the bus serves one transfer at a time.
+
+use super::Arm;
+use super::realtime::{self, DeadlineStats, Scheduler};
+use crate::error::{CsdpError, Result};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long a stage waits for a message before checking whether the pipeline stopped
+const POLL: Duration = Duration::from_millis(50);
+
+/// A message with its sequence number (that of the reading it derives from) and the time
+/// it was produced
+#[derive(Debug, Clone)]
+pub struct Stamped<T> {
+    pub seq: u64,
+    pub at: Instant,
+    pub value: T,
+}
+
+/// Goal positions with the reading they answer, from the policy to the writer
+struct Goal {
+    read_at: Instant,
+    positions: Vec<f64>,
+    goals: Vec<f64>,
+}
+
+/// One cycle that reached the arm, as handed to the log sink. Times are since the pipeline
+/// started.
+#[derive(Debug, Clone)]
+pub struct CycleRecord {
+    pub seq: u64,
+    pub read_at: Duration,
+    pub computed_at: Duration,
+    pub written_at: Duration,
+    pub positions: Vec<f64>,
+    pub goals: Vec<f64>,
+}
+
+impl CycleRecord {
+    /// Time from reading the positions to writing the goals derived from them
+    pub fn latency(&self) -> Duration {
+        self.written_at.saturating_sub(self.read_at)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineConfig {
+    /// Rate the reader reads the joint positions at
+    pub rate_hz: f64,
+    /// Capacity of the reading and goal queues
+    pub capacity: usize,
+    /// Capacity of the logger's queue; cycles beyond it are dropped from the log
+    pub log_capacity: usize,
+    /// Run the reader and writer at real-time thread priority, see
+    /// [`realtime::elevate_priority`]
+    pub realtime: bool,
+}
+
+impl PipelineConfig {
+    pub fn new(rate_hz: f64) -> Self {
+        Self {
+            rate_hz,
+            capacity: 2,
+            log_capacity: 1024,
+            realtime: false,
+        }
+    }
+}
+
+/// Message counts of a [`Pipeline`], updated by its threads as they run
+#[derive(Debug, Default)]
+pub struct PipelineCounters {
+    pub reads: AtomicU64,
+    pub read_errors: AtomicU64,
+    /// readings the policy skipped because a newer one was queued, or that found its queue
+    /// full
+    pub stale_readings: AtomicU64,
+    pub writes: AtomicU64,
+    pub write_errors: AtomicU64,
+    /// goals the writer skipped because a newer one was queued, or that found its queue full
+    pub stale_goals: AtomicU64,
+    pub dropped_logs: AtomicU64,
+    pub log_errors: AtomicU64,
+}
+
+/// Summary of a stopped [`Pipeline`]
+#[derive(Debug, Clone)]
+pub struct PipelineReport {
+    /// Deadlines of the reader
+    pub deadlines: DeadlineStats,
+    pub reads: u64,
+    pub read_errors: u64,
+    pub stale_readings: u64,
+    pub writes: u64,
+    pub write_errors: u64,
+    pub stale_goals: u64,
+    pub dropped_logs: u64,
+    pub log_errors: u64,
+    /// Read-to-write latency of every cycle that reached the arm
+    pub latencies: Vec<Duration>,
+}
+
+/// A running pipelined control loop, see the module docs
+pub struct Pipeline<A: Arm + Send + 'static> {
+    arm: Arc<Mutex<A>>,
+    stop: Arc<AtomicBool>,
+    counters: Arc<PipelineCounters>,
+    reader: JoinHandle<DeadlineStats>,
+    policy: JoinHandle<Result<()>>,
+    writer: JoinHandle<Vec<Duration>>,
+    logger: JoinHandle<()>,
+}
+
+impl<A: Arm + Send + 'static> Pipeline<A> {
+    /// Start the loop on `arm`: `policy` maps each reading to goal positions and `sink`
+    /// receives every cycle that reached the arm. A policy error stops the pipeline and is
+    /// returned by [`Pipeline::stop`]; read, write and sink errors are counted and logged.
+    pub fn spawn<P, S>(arm: A, config: PipelineConfig, policy: P, sink: S) -> Result<Self>
+    where
+        P: FnMut(&Stamped<Vec<f64>>) -> Result<Vec<f64>> + Send + 'static,
+        S: FnMut(&CycleRecord) -> Result<()> + Send + 'static,
+    {
+        if config.rate_hz <= 0.0 || config.capacity == 0 {
+            return Err(CsdpError::Config(format!(
+                "a pipeline needs a positive rate and queue capacity, got {} Hz and {}",
+                config.rate_hz, config.capacity
+            )));
+        }
+        let arm = Arc::new(Mutex::new(arm));
+        let stop = Arc::new(AtomicBool::new(false));
+        let counters = Arc::new(PipelineCounters::default());
+        let start = Instant::now();
+
+        let (readings_tx, readings_rx) = mpsc::sync_channel(config.capacity);
+        let (goals_tx, goals_rx) = mpsc::sync_channel(config.capacity);
+        let (log_tx, log_rx) = mpsc::sync_channel(config.log_capacity);
+
+        // Downstream stages first, so if a spawn fails the stages already running see
+        // their channel disconnect and shut down
+        let logger = {
+            let counters = counters.clone();
+            thread::Builder::new()
+                .name("pipeline-logger".to_string())
+                .spawn(move || log_loop(sink, log_rx, counters))?
+        };
+        let writer = {
+            let (arm, counters) = (arm.clone(), counters.clone());
+            thread::Builder::new()
+                .name("pipeline-writer".to_string())
+                .spawn(move || write_loop(arm, config, start, goals_rx, log_tx, counters))?
+        };
+        let policy = {
+            let (stop, counters) = (stop.clone(), counters.clone());
+            thread::Builder::new()
+                .name("pipeline-policy".to_string())
+                .spawn(move || policy_loop(policy, readings_rx, goals_tx, stop, counters))?
+        };
+        let reader = {
+            let (arm, stop, counters) = (arm.clone(), stop.clone(), counters.clone());
+            thread::Builder::new()
+                .name("pipeline-reader".to_string())
+                .spawn(move || read_loop(arm, config, stop, counters, readings_tx))?
+        };
+        Ok(Self {
+            arm,
+            stop,
+            counters,
+            reader,
+            policy,
+            writer,
+            logger,
+        })
+    }
+
+    /// Counts of the running pipeline
+    pub fn counters(&self) -> &PipelineCounters {
+        &self.counters
+    }
+
+    /// Whether the loop still runs; false once the policy failed
+    pub fn is_running(&self) -> bool {
+        !self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Stop reading, let the queued messages drain through the writer and logger, and
+    /// return the arm with a report
+    pub fn stop(self) -> Result<(A, PipelineReport)> {
+        self.stop.store(true, Ordering::Relaxed);
+        let joined = |name: &str| CsdpError::Data(format!("the pipeline {} panicked", name));
+        let deadlines = self.reader.join().map_err(|_| joined("reader"))?;
+        let policy = self.policy.join().map_err(|_| joined("policy"))?;
+        let latencies = self.writer.join().map_err(|_| joined("writer"))?;
+        self.logger.join().map_err(|_| joined("logger"))?;
+        policy?;
+
+        let count = |c: &AtomicU64| c.load(Ordering::Relaxed);
+        let c = &self.counters;
+        let report = PipelineReport {
+            deadlines,
+            reads: count(&c.reads),
+            read_errors: count(&c.read_errors),
+            stale_readings: count(&c.stale_readings),
+            writes: count(&c.writes),
+            write_errors: count(&c.write_errors),
+            stale_goals: count(&c.stale_goals),
+            dropped_logs: count(&c.dropped_logs),
+            log_errors: count(&c.log_errors),
+            latencies,
+        };
+        let arm = Arc::try_unwrap(self.arm)
+            .map_err(|_| CsdpError::Data("the arm is still shared".to_string()))?
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        Ok((arm, report))
+    }
+}
+
+/// A sink writing every cycle as a CSV row: `seq`, the read, compute and write times in
+/// milliseconds since the start, then `p<i>` positions and `g<i>` goals
+pub fn csv_sink<P: AsRef<Path>>(
+    path: P,
+) -> Result<impl FnMut(&CycleRecord) -> Result<()> + Send + 'static> {
+    let mut writer = csv::Writer::from_path(path)?;
+    let mut header_written = false;
+    Ok(move |record: &CycleRecord| {
+        if !header_written {
+            let mut header: Vec<String> = ["seq", "read_ms", "computed_ms", "written_ms"]
+                .iter()
+                .map(|h| h.to_string())
+                .collect();
+            header.extend((0..record.positions.len()).map(|i| format!("p{}", i)));
+            header.extend((0..record.goals.len()).map(|i| format!("g{}", i)));
+            writer.write_record(&header)?;
+            header_written = true;
+        }
+        let ms = |d: Duration| format!("{:.3}", d.as_secs_f64() * 1000.0);
+        let mut row = vec![
+            record.seq.to_string(),
+            ms(record.read_at),
+            ms(record.computed_at),
+            ms(record.written_at),
+        ];
+        row.extend(record.positions.iter().map(|p| p.to_string()));
+        row.extend(record.goals.iter().map(|g| g.to_string()));
+        writer.write_record(&row)?;
+        Ok(())
+    })
+}
+
+fn lock<A>(arm: &Mutex<A>) -> MutexGuard<'_, A> {
+    // A panicking stage stops its own thread; the arm itself stays usable
+    arm.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn elevate(config: &PipelineConfig, stage: &str) {
+    if config.realtime
+        && let Err(e) = realtime::elevate_priority()
+    {
+        log::warn!(
+            "Pipeline {} could not elevate to real-time priority: {}",
+            stage,
+            e
+        );
+    }
+}
+
+/// The newest message of `rx`, waiting up to [`POLL`] for one; older queued messages are
+/// counted in `skipped`
+fn newest<T>(
+    rx: &Receiver<Stamped<T>>,
+    skipped: &AtomicU64,
+) -> std::result::Result<Stamped<T>, RecvTimeoutError> {
+    let mut message = rx.recv_timeout(POLL)?;
+    while let Ok(next) = rx.try_recv() {
+        skipped.fetch_add(1, Ordering::Relaxed);
+        message = next;
+    }
+    Ok(message)
+}
+
+fn read_loop<A: Arm>(
+    arm: Arc<Mutex<A>>,
+    config: PipelineConfig,
+    stop: Arc<AtomicBool>,
+    counters: Arc<PipelineCounters>,
+    readings: SyncSender<Stamped<Vec<f64>>>,
+) -> DeadlineStats {
+    elevate(&config, "reader");
+    let mut scheduler = Scheduler::at_rate(config.rate_hz);
+    let mut seq = 0;
+    while !stop.load(Ordering::Relaxed) {
+        let reading = lock(&arm).get_motor_positions();
+        match reading {
+            Ok(positions) => {
+                counters.reads.fetch_add(1, Ordering::Relaxed);
+                let message = Stamped {
+                    seq,
+                    at: Instant::now(),
+                    value: positions,
+                };
+                seq += 1;
+                match readings.try_send(message) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        counters.stale_readings.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(TrySendError::Disconnected(_)) => break,
+                }
+            }
+            Err(e) => {
+                if counters.read_errors.fetch_add(1, Ordering::Relaxed) == 0 {
+                    log::warn!("Pipeline read failed: {}", e);
+                }
+            }
+        }
+        scheduler.wait();
+    }
+    scheduler.stats().clone()
+}
+
+fn policy_loop<P>(
+    mut policy: P,
+    readings: Receiver<Stamped<Vec<f64>>>,
+    goals: SyncSender<Stamped<Goal>>,
+    stop: Arc<AtomicBool>,
+    counters: Arc<PipelineCounters>,
+) -> Result<()>
+where
+    P: FnMut(&Stamped<Vec<f64>>) -> Result<Vec<f64>>,
+{
+    loop {
+        let reading = match newest(&readings, &counters.stale_readings) {
+            Ok(reading) => reading,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        let goal = match policy(&reading) {
+            Ok(goal) => goal,
+            Err(e) => {
+                stop.store(true, Ordering::Relaxed);
+                return Err(e);
+            }
+        };
+        let message = Stamped {
+            seq: reading.seq,
+            at: Instant::now(),
+            value: Goal {
+                read_at: reading.at,
+                positions: reading.value,
+                goals: goal,
+            },
+        };
+        match goals.try_send(message) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                counters.stale_goals.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Disconnected(_)) => return Ok(()),
+        }
+    }
+}
+
+fn write_loop<A: Arm>(
+    arm: Arc<Mutex<A>>,
+    config: PipelineConfig,
+    start: Instant,
+    goals: Receiver<Stamped<Goal>>,
+    log: SyncSender<CycleRecord>,
+    counters: Arc<PipelineCounters>,
+) -> Vec<Duration> {
+    elevate(&config, "writer");
+    let mut latencies = Vec::new();
+    loop {
+        let message = match newest(&goals, &counters.stale_goals) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return latencies,
+        };
+        let Goal {
+            read_at,
+            positions,
+            goals,
+        } = message.value;
+        let written = lock(&arm).set_goal_positions(&goals);
+        let written_at = Instant::now();
+        if let Err(e) = written {
+            if counters.write_errors.fetch_add(1, Ordering::Relaxed) == 0 {
+                log::warn!("Pipeline write failed: {}", e);
+            }
+            continue;
+        }
+        counters.writes.fetch_add(1, Ordering::Relaxed);
+        latencies.push(written_at - read_at);
+
+        let record = CycleRecord {
+            seq: message.seq,
+            read_at: read_at - start,
+            computed_at: message.at - start,
+            written_at: written_at - start,
+            positions,
+            goals,
+        };
+        // Never wait for the logger
+        if log.try_send(record).is_err() {
+            counters.dropped_logs.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn log_loop<S>(mut sink: S, records: Receiver<CycleRecord>, counters: Arc<PipelineCounters>)
+where
+    S: FnMut(&CycleRecord) -> Result<()>,
+{
+    for record in records {
+        if let Err(e) = sink(&record)
+            && counters.log_errors.fetch_add(1, Ordering::Relaxed) == 0
+        {
+            log::warn!("Pipeline log sink failed: {}", e);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use super::real_lerobot::{LeRobot, RobotResult};
+use crate::models::robot_model::ObservationEncoder;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Serial port and calibration for one physical arm. Home offsets and angle limits are raw
+/// servo positions in radians, as written by `csdp calibrate`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RobotProfile {
+    pub port: String,
+    pub home_positions: [f64; 6],
+    pub min_positions: [f64; 6],
+    pub max_positions: [f64; 6],
+}
+
+impl RobotProfile {
+    /// The active arm driven by the training environment
+    pub fn follower() -> Self {
+        Self {
+            port: "/dev/ttyACM0".to_string(),
+            home_positions: [-0.0276, -1.6, 1.29, 1.1, 0.254, 0.0],
+            min_positions: [-1.3, -1.6, -1.94, -2.0, -1.5, -0.0122],
+            max_positions: [1.0, 1.7, 1.29, 1.2, 1.5, 1.1],
+        }
+    }
+
+    /// The passive arm moved by hand for teleoperation and recording
+    pub fn leader() -> Self {
+        Self {
+            port: "/dev/ttyACM0".to_string(),
+            home_positions: [
+                0.05982525072754008,
+                -0.32366994624387013,
+                0.08743690490948142,
+                -0.018407769454627854,
+                1.6659031356438065,
+                -1.0676506283684062,
+            ],
+            min_positions: [-1.77, -0.32, -3.0, -3.0, -3.0, -1.07],
+            max_positions: [2.22, 3.0, 0.085, -0.069, 3.0, 0.65],
+        }
+    }
+
+    /// Resolve a built-in profile name (`follower`, `leader`) or a path to a JSON profile
+    pub fn resolve(name_or_path: &str) -> RobotResult<Self> {
+        match name_or_path {
+            "follower" => Ok(Self::follower()),
+            "leader" => Ok(Self::leader()),
+            path => Self::load(path),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> RobotResult<Self> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> RobotResult<()> {
+        if let Some(parent) = path.as_ref().parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    pub fn with_port(mut self, port: Option<String>) -> Self {
+        if let Some(port) = port {
+            self.port = port;
+        }
+        self
+    }
+
+    /// Encoder normalizing this arm's joint readings between its calibrated limits
+    pub fn observation_encoder(&self) -> ObservationEncoder {
+        ObservationEncoder::from_calibration(
+            &self.home_positions,
+            &self.min_positions,
+            &self.max_positions,
+        )
+    }
+
+    pub fn connect(&self) -> RobotResult<LeRobot> {
+        LeRobot::new(
+            self.port.as_str(),
+            self.home_positions,
+            self.min_positions,
+            self.max_positions,
+        )
+    }
+}
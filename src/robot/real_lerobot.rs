@@ -1,12 +1,15 @@
+use crate::error::CsdpError;
 use rustypot::servo::feetech::sts3215::Sts3215Controller;
-use std::error::Error;
 use std::time::Duration;
 
 /// Hardcoded IDs assumed
 const MOTOR_IDS: [u8; 6] = [1, 2, 3, 4, 5, 6];
 
+/// Torque limit the servos are set to on connecting, in raw units out of 1000
+pub const DEFAULT_TORQUE_LIMIT: u16 = 400;
+
 // Type alias for concise return signatures
-pub type RobotResult<T> = Result<T, Box<dyn Error>>;
+pub type RobotResult<T> = crate::error::Result<T>;
 
 pub struct LeRobot {
     pub controller: Sts3215Controller,
@@ -29,13 +32,23 @@ impl LeRobot {
             .with_serial_port(serial_port);
 
         // Initialize limits and dead zones
-        controller.sync_write_min_angle_limit(&MOTOR_IDS, &min_positions)?;
-        controller.sync_write_max_angle_limit(&MOTOR_IDS, &max_positions)?;
-        controller.sync_write_cw_dead_zone(&MOTOR_IDS, &[5; 6])?;
-        controller.sync_write_ccw_dead_zone(&MOTOR_IDS, &[5; 6])?;
+        controller
+            .sync_write_min_angle_limit(&MOTOR_IDS, &min_positions)
+            .map_err(CsdpError::servo)?;
+        controller
+            .sync_write_max_angle_limit(&MOTOR_IDS, &max_positions)
+            .map_err(CsdpError::servo)?;
+        controller
+            .sync_write_cw_dead_zone(&MOTOR_IDS, &[5; 6])
+            .map_err(CsdpError::servo)?;
+        controller
+            .sync_write_ccw_dead_zone(&MOTOR_IDS, &[5; 6])
+            .map_err(CsdpError::servo)?;
 
         // Set max torque limit
-        controller.sync_write_torque_limit(&MOTOR_IDS, &[400; 6])?;
+        controller
+            .sync_write_torque_limit(&MOTOR_IDS, &[DEFAULT_TORQUE_LIMIT; 6])
+            .map_err(CsdpError::servo)?;
 
         Ok(LeRobot {
             controller,
@@ -45,19 +58,33 @@ impl LeRobot {
 
     pub fn enable(&mut self) -> RobotResult<()> {
         let arr = [true; 6];
-        self.controller.sync_write_torque_enable(&MOTOR_IDS, &arr)?;
+        self.controller
+            .sync_write_torque_enable(&MOTOR_IDS, &arr)
+            .map_err(CsdpError::servo)?;
         Ok(())
     }
 
     pub fn disable(&mut self) -> RobotResult<()> {
         let arr = [false; 6];
-        self.controller.sync_write_torque_enable(&MOTOR_IDS, &arr)?;
+        self.controller
+            .sync_write_torque_enable(&MOTOR_IDS, &arr)
+            .map_err(CsdpError::servo)?;
         Ok(())
     }
 
     pub fn set_max_speed_all(&mut self, speed: f64) -> RobotResult<()> {
         let arr = [speed; 6];
-        self.controller.sync_write_goal_speed(&MOTOR_IDS, &arr)?;
+        self.controller
+            .sync_write_goal_speed(&MOTOR_IDS, &arr)
+            .map_err(CsdpError::servo)?;
+        Ok(())
+    }
+
+    /// Torque limit of every servo, in raw units out of 1000
+    pub fn set_torque_limit_all(&mut self, limit: u16) -> RobotResult<()> {
+        self.controller
+            .sync_write_torque_limit(&MOTOR_IDS, &[limit; 6])
+            .map_err(CsdpError::servo)?;
         Ok(())
     }
 
@@ -70,7 +97,8 @@ impl LeRobot {
             .collect::<Vec<_>>();
 
         self.controller
-            .sync_write_goal_position(&MOTOR_IDS, &adjusted_positions)?;
+            .sync_write_goal_position(&MOTOR_IDS, &adjusted_positions)
+            .map_err(CsdpError::servo)?;
         Ok(())
     }
 
@@ -79,7 +107,32 @@ impl LeRobot {
     }
 
     pub fn get_motor_positions(&mut self) -> RobotResult<Vec<f64>> {
-        let positions = self.controller.sync_read_present_position(&MOTOR_IDS)?;
+        let positions = self
+            .controller
+            .sync_read_present_position(&MOTOR_IDS)
+            .map_err(CsdpError::servo)?;
+
+        let computed = positions
+            .iter()
+            .zip(self.home_positions.iter())
+            .map(|(p, h)| p - h)
+            .collect::<Vec<_>>();
+
+        Ok(computed)
+    }
+
+    /// Present positions without the home offset applied, as used for calibration
+    pub fn get_raw_positions(&mut self) -> RobotResult<Vec<f64>> {
+        self.controller
+            .sync_read_present_position(&MOTOR_IDS)
+            .map_err(CsdpError::servo)
+    }
+
+    pub fn get_goal_positions(&mut self) -> RobotResult<Vec<f64>> {
+        let positions = self
+            .controller
+            .sync_read_goal_position(&MOTOR_IDS)
+            .map_err(CsdpError::servo)?;
 
         let computed = positions
             .iter()
@@ -89,4 +142,43 @@ impl LeRobot {
 
         Ok(computed)
     }
+
+    /// Raw present load reported by each servo (signed, direction encoded in the sign)
+    pub fn get_motor_loads(&mut self) -> RobotResult<Vec<f64>> {
+        let loads = self
+            .controller
+            .sync_read_present_load(&MOTOR_IDS)
+            .map_err(CsdpError::servo)?;
+        Ok(loads.iter().map(|&l| l as f64).collect())
+    }
+}
+
+impl super::Arm for LeRobot {
+    fn enable(&mut self) -> RobotResult<()> {
+        LeRobot::enable(self)
+    }
+
+    fn disable(&mut self) -> RobotResult<()> {
+        LeRobot::disable(self)
+    }
+
+    fn set_goal_positions(&mut self, positions: &[f64]) -> RobotResult<()> {
+        LeRobot::set_goal_positions(self, positions)
+    }
+
+    fn go_to_home_positions(&mut self) -> RobotResult<()> {
+        LeRobot::go_to_home_positions(self)
+    }
+
+    fn get_motor_positions(&mut self) -> RobotResult<Vec<f64>> {
+        LeRobot::get_motor_positions(self)
+    }
+
+    fn get_goal_positions(&mut self) -> RobotResult<Vec<f64>> {
+        LeRobot::get_goal_positions(self)
+    }
+
+    fn get_motor_loads(&mut self) -> RobotResult<Vec<f64>> {
+        LeRobot::get_motor_loads(self)
+    }
 }
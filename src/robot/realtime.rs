@@ -0,0 +1,272 @@
+//! Soft real-time scheduling of control loops.
+//!
+//! A [`Scheduler`] paces a loop against absolute deadlines, `start + k * period`, instead of
+//! sleeping for "period minus the time this cycle took", so timing errors don't accumulate
+//! over a run. It sleeps until shortly before each deadline and spins for the rest, which
+//! keeps wake-up jitter well below the OS sleep granularity. A cycle whose work runs past its
+//! deadline is counted as missed and the loop resynchronizes with the next deadline still
+//! ahead rather than bursting to catch up. [`DeadlineStats`] summarizes both. Teleoperation,
+//! recording, playback, calibration and the `robot-latency` tool all run on it.
+//!
+//! [`elevate_priority`] additionally moves the calling thread to a real-time scheduling class
+//! where the OS allows it, so other processes can't preempt the loop.
+
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// How long before a deadline [`Scheduler`] stops sleeping and starts spinning
+pub const DEFAULT_SPIN: Duration = Duration::from_micros(500);
+
+/// Deadline statistics of a [`Scheduler`]
+#[derive(Debug, Clone, Default)]
+pub struct DeadlineStats {
+    /// Cycles waited for
+    pub cycles: u64,
+    /// Cycles whose work was still running at their deadline
+    pub missed: u64,
+    /// Periods dropped to resynchronize after a missed deadline
+    pub skipped: u64,
+    /// Longest time a cycle's work ran past its deadline
+    pub max_overrun: Duration,
+    /// Latest wake-up after a deadline that was met
+    pub max_lateness: Duration,
+    lateness_sum: f64,
+    lateness_sq_sum: f64,
+}
+
+impl DeadlineStats {
+    fn on_time(&mut self, lateness: Duration) {
+        self.cycles += 1;
+        self.max_lateness = self.max_lateness.max(lateness);
+        let secs = lateness.as_secs_f64();
+        self.lateness_sum += secs;
+        self.lateness_sq_sum += secs * secs;
+    }
+
+    fn missed(&mut self, overrun: Duration, skipped: u64) {
+        self.cycles += 1;
+        self.missed += 1;
+        self.skipped += skipped;
+        self.max_overrun = self.max_overrun.max(overrun);
+    }
+
+    /// Fraction of cycles that missed their deadline
+    pub fn miss_rate(&self) -> f64 {
+        if self.cycles == 0 {
+            0.0
+        } else {
+            self.missed as f64 / self.cycles as f64
+        }
+    }
+
+    /// Mean wake-up lateness of the cycles that met their deadline
+    pub fn mean_lateness(&self) -> Duration {
+        let met = self.cycles - self.missed;
+        if met == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.lateness_sum / met as f64)
+    }
+
+    /// Standard deviation of the wake-up lateness, the jitter of the loop
+    pub fn jitter(&self) -> Duration {
+        let met = self.cycles - self.missed;
+        if met == 0 {
+            return Duration::ZERO;
+        }
+        let mean = self.lateness_sum / met as f64;
+        let variance = (self.lateness_sq_sum / met as f64 - mean * mean).max(0.0);
+        Duration::from_secs_f64(variance.sqrt())
+    }
+}
+
+impl fmt::Display for DeadlineStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "{} cycles, {} missed deadlines ({:.1}%, worst overrun {:.3} ms, {} periods \
+             skipped), wake-up lateness mean {:.3} ms, jitter {:.3} ms, max {:.3} ms",
+            self.cycles,
+            self.missed,
+            100.0 * self.miss_rate(),
+            ms(self.max_overrun),
+            self.skipped,
+            ms(self.mean_lateness()),
+            ms(self.jitter()),
+            ms(self.max_lateness)
+        )
+    }
+}
+
+/// Paces a loop against absolute deadlines, see the module docs
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    period: Duration,
+    spin: Duration,
+    start: Instant,
+    /// index of the next periodic deadline, `start + next * period`
+    next: u64,
+    stats: DeadlineStats,
+}
+
+impl Scheduler {
+    /// Deadlines every `period`, counted from now
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            spin: DEFAULT_SPIN,
+            start: Instant::now(),
+            next: 1,
+            stats: DeadlineStats::default(),
+        }
+    }
+
+    /// Deadlines at `hz` cycles per second
+    pub fn at_rate(hz: f64) -> Self {
+        Self::new(Duration::from_secs_f64(1.0 / hz))
+    }
+
+    /// Spin instead of sleeping for the last `spin` before each deadline; zero only sleeps
+    pub fn with_spin(mut self, spin: Duration) -> Self {
+        self.spin = spin;
+        self
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Count deadlines from now, e.g. after a setup phase; statistics are kept
+    pub fn restart(&mut self) {
+        self.start = Instant::now();
+        self.next = 1;
+    }
+
+    /// Time since the scheduler was created or restarted
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// End the current cycle: wait for the next periodic deadline. If the cycle overran it,
+    /// return false without waiting and move on to the first deadline still ahead.
+    pub fn wait(&mut self) -> bool {
+        let deadline =
+            self.start + Duration::from_nanos((self.period.as_nanos() * self.next as u128) as u64);
+        let now = Instant::now();
+        if now <= deadline {
+            self.next += 1;
+            self.sleep_until(deadline);
+            return true;
+        }
+        let overrun = now - deadline;
+        let skipped = (overrun.as_nanos() / self.period.as_nanos().max(1)) as u64;
+        self.next += 1 + skipped;
+        self.stats.missed(overrun, skipped);
+        false
+    }
+
+    /// Wait until `offset` after the start, e.g. the timestamp of a recorded frame. Returns
+    /// false without waiting if that deadline has already passed.
+    pub fn wait_until(&mut self, offset: Duration) -> bool {
+        let deadline = self.start + offset;
+        let now = Instant::now();
+        if now <= deadline {
+            self.sleep_until(deadline);
+            return true;
+        }
+        self.stats.missed(now - deadline, 0);
+        false
+    }
+
+    pub fn stats(&self) -> &DeadlineStats {
+        &self.stats
+    }
+
+    fn sleep_until(&mut self, deadline: Instant) {
+        let now = Instant::now();
+        if deadline > now + self.spin {
+            std::thread::sleep(deadline - now - self.spin);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+        self.stats
+            .on_time(Instant::now().saturating_duration_since(deadline));
+    }
+}
+
+/// Move the calling thread to a real-time scheduling class: `SCHED_FIFO` on Linux, which
+/// needs root or `CAP_SYS_NICE` (or an `rtprio` limit), and time-critical priority on
+/// Windows. Fails with the OS error, or `Unsupported` elsewhere; the loop then keeps running
+/// at normal priority.
+pub fn elevate_priority() -> io::Result<()> {
+    os::elevate_priority()
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use std::io;
+
+    const SCHED_FIFO: i32 = 1;
+    /// Well above default threads, below the kernel's own real-time threads
+    const PRIORITY: i32 = 50;
+
+    #[repr(C)]
+    struct SchedParam {
+        sched_priority: i32,
+    }
+
+    unsafe extern "C" {
+        fn sched_setscheduler(pid: i32, policy: i32, param: *const SchedParam) -> i32;
+    }
+
+    pub fn elevate_priority() -> io::Result<()> {
+        let param = SchedParam {
+            sched_priority: PRIORITY,
+        };
+        // pid 0 is the calling thread
+        // SAFETY: `param` is a valid `sched_param` for the duration of the call
+        if unsafe { sched_setscheduler(0, SCHED_FIFO, &param) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+#[cfg(windows)]
+mod os {
+    use std::ffi::c_void;
+    use std::io;
+
+    const THREAD_PRIORITY_TIME_CRITICAL: i32 = 15;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetCurrentThread() -> *mut c_void;
+        fn SetThreadPriority(thread: *mut c_void, priority: i32) -> i32;
+    }
+
+    pub fn elevate_priority() -> io::Result<()> {
+        // SAFETY: the pseudo handle of the current thread is always valid
+        if unsafe { SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_TIME_CRITICAL) } != 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod os {
+    use std::io;
+
+    pub fn elevate_priority() -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "real-time priority is only supported on Linux and Windows",
+        ))
+    }
+}
@@ -0,0 +1,126 @@
+//! Retargeting recorded trajectories from one arm's calibration onto another's.
+//!
+//! Recordings store joint angles relative to the recording arm's home position, so a
+//! trajectory recorded on the leader doesn't mean the same pose on the follower when their
+//! calibrations differ. A [`Retargeting`] maps every frame from a source [`RobotProfile`] onto
+//! a target profile, either keeping the angles ([`RetargetMode::Angle`]) or stretching each
+//! joint's calibrated range onto the target's ([`RetargetMode::Range`]). Joints that turn the
+//! other way on the target are flipped, fixed offsets can be added (e.g. the half turn of the
+//! wrist that teleoperation applies), and the result is clamped into the target's limits.
+
+use super::profile::RobotProfile;
+use crate::dataset::trajectory::Trajectory;
+use crate::error::{CsdpError, Result};
+use clap::ValueEnum;
+
+const NUM_JOINTS: usize = 6;
+
+/// How a joint position carries over to the target arm
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum RetargetMode {
+    /// Keep the angle from home, for arms whose home poses match
+    #[default]
+    Angle,
+    /// Map the joint's position within the source range to the same fraction of the target
+    /// range
+    Range,
+}
+
+#[derive(Debug, Clone)]
+pub struct Retargeting {
+    mode: RetargetMode,
+    /// source and target limits relative to their home positions
+    source_lower: [f64; NUM_JOINTS],
+    source_upper: [f64; NUM_JOINTS],
+    target_lower: [f64; NUM_JOINTS],
+    target_upper: [f64; NUM_JOINTS],
+    flipped: [bool; NUM_JOINTS],
+    offsets: [f64; NUM_JOINTS],
+}
+
+impl Retargeting {
+    pub fn new(source: &RobotProfile, target: &RobotProfile, mode: RetargetMode) -> Self {
+        let relative = |profile: &RobotProfile, limits: [f64; NUM_JOINTS]| {
+            std::array::from_fn(|i| limits[i] - profile.home_positions[i])
+        };
+        Self {
+            mode,
+            source_lower: relative(source, source.min_positions),
+            source_upper: relative(source, source.max_positions),
+            target_lower: relative(target, target.min_positions),
+            target_upper: relative(target, target.max_positions),
+            flipped: [false; NUM_JOINTS],
+            offsets: [0.0; NUM_JOINTS],
+        }
+    }
+
+    /// Reverse the direction of these joints (0-based) on the target
+    pub fn with_flips(mut self, joints: &[usize]) -> Result<Self> {
+        for &joint in joints {
+            let flipped = self.flipped.get_mut(joint).ok_or_else(|| {
+                CsdpError::Config(format!(
+                    "cannot flip joint {}, the arm has {} joints",
+                    joint, NUM_JOINTS
+                ))
+            })?;
+            *flipped = true;
+        }
+        Ok(self)
+    }
+
+    /// Radians added to each joint after mapping and flipping
+    pub fn with_offsets(mut self, offsets: [f64; NUM_JOINTS]) -> Self {
+        self.offsets = offsets;
+        self
+    }
+
+    /// Target joint positions for source positions, both relative to home
+    pub fn map(&self, positions: &[f64]) -> Vec<f64> {
+        positions
+            .iter()
+            .take(NUM_JOINTS)
+            .enumerate()
+            .map(|(j, &p)| {
+                let mapped = match self.mode {
+                    RetargetMode::Angle => {
+                        if self.flipped[j] {
+                            -p
+                        } else {
+                            p
+                        }
+                    }
+                    RetargetMode::Range => {
+                        let (lo, hi) = (self.source_lower[j], self.source_upper[j]);
+                        // A joint without a calibrated range sits in the middle of the target's
+                        let mut fraction = if hi > lo { (p - lo) / (hi - lo) } else { 0.5 };
+                        if self.flipped[j] {
+                            fraction = 1.0 - fraction;
+                        }
+                        self.target_lower[j]
+                            + fraction * (self.target_upper[j] - self.target_lower[j])
+                    }
+                };
+                // min/max instead of clamp: an inverted calibration must not panic
+                (mapped + self.offsets[j])
+                    .max(self.target_lower[j])
+                    .min(self.target_upper[j])
+            })
+            .collect()
+    }
+
+    /// Copy of a six-joint recording with every frame mapped; timestamps are kept
+    pub fn apply(&self, trajectory: &Trajectory) -> Result<Trajectory> {
+        if trajectory.channels.len() != NUM_JOINTS {
+            return Err(CsdpError::Data(format!(
+                "retargeting needs {} joint columns, the recording has {}",
+                NUM_JOINTS,
+                trajectory.channels.len()
+            )));
+        }
+        Ok(Trajectory {
+            channels: trajectory.channels.clone(),
+            timestamps_ms: trajectory.timestamps_ms.clone(),
+            frames: trajectory.frames.iter().map(|f| self.map(f)).collect(),
+        })
+    }
+}
@@ -0,0 +1,438 @@
+use super::bilateral::ForceFeedback;
+use super::corpus::{Corpus, SessionManifest};
+use super::interpolation::{InterpolatedTrajectory, Interpolation};
+use super::profile::RobotProfile;
+use super::real_lerobot::{DEFAULT_TORQUE_LIMIT, LeRobot, RobotResult};
+use super::realtime::Scheduler;
+use crate::dataset::trajectory::Trajectory;
+use crate::error::CsdpError;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// One row of a recorded trajectory CSV
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RobotFrame {
+    pub timestamp_ms: u64,
+    pub j1: f64,
+    pub j2: f64,
+    pub j3: f64,
+    pub j4: f64,
+    pub j5: f64,
+    pub j6: f64,
+}
+
+impl RobotFrame {
+    pub fn positions(&self) -> [f64; 6] {
+        [self.j1, self.j2, self.j3, self.j4, self.j5, self.j6]
+    }
+}
+
+fn wait_for_enter(text: &str) -> io::Result<()> {
+    prompt(text).map(|_| ())
+}
+
+/// Print `text` and return the line the user enters
+fn prompt(text: &str) -> io::Result<String> {
+    print!("{}", text);
+    io::stdout().flush()?;
+    let mut input_buffer = String::new();
+    io::stdin().read_line(&mut input_buffer)?;
+    Ok(input_buffer)
+}
+
+/// Spawns a thread that clears the returned flag once ENTER is pressed
+fn spawn_stop_listener() -> Arc<AtomicBool> {
+    let keep_running = Arc::new(AtomicBool::new(true));
+    let r_handle = keep_running.clone();
+
+    thread::spawn(move || {
+        let mut s = String::new();
+        io::stdin().read_line(&mut s).ok();
+        r_handle.store(false, Ordering::Relaxed);
+    });
+
+    keep_running
+}
+
+/// Mirror the passive leader onto the active follower at 60Hz until ENTER is pressed. With
+/// `feedback`, the follower's loads are fed back to the leader, see [`super::bilateral`].
+pub fn teleoperate(
+    leader: &mut LeRobot,
+    follower: &mut LeRobot,
+    mut feedback: Option<ForceFeedback>,
+) -> RobotResult<()> {
+    log::info!("Enabling Follower torque...");
+    follower.enable()?; // Active
+
+    log::info!("Disabling Leader torque (ready for manual input)...");
+    leader.disable()?; // Passive
+
+    // Safety: Move Follower to match Leader's current position slowly
+    // This prevents the follower from snapping violently if the leader is in a different pose
+    log::info!("Syncing start positions...");
+    if let Ok(start_pos) = leader.get_motor_positions()
+        && start_pos.len() == 6
+    {
+        follower.set_goal_positions(&start_pos)?;
+        // Give it time to move there safely
+        thread::sleep(Duration::from_millis(2000));
+    }
+
+    wait_for_enter("Synced. Press ENTER to START teleoperation...")?;
+    if let Some(feedback) = &feedback {
+        // Hold the leader where it is before powering it, so it doesn't jump to a stale goal
+        leader.set_torque_limit_all(feedback.config.torque_limit)?;
+        let start = leader.get_motor_positions()?;
+        leader.set_goal_positions(&start)?;
+        leader.enable()?;
+        log::info!(
+            "Force feedback on (gain {}, torque limit {})",
+            feedback.config.gain,
+            feedback.config.torque_limit
+        );
+    }
+    log::info!("Teleoperation active! Press ENTER to STOP.");
+
+    let keep_running = spawn_stop_listener();
+    let mut scheduler = Scheduler::at_rate(60.0);
+
+    while keep_running.load(Ordering::Relaxed) {
+        if let Ok(positions) = leader.get_motor_positions()
+            && positions.len() == 6
+        {
+            // We map 1:1, assuming the robots are physically identical or compatible
+            follower.set_goal_positions(&[
+                positions[0],
+                positions[1],
+                positions[2],
+                positions[3],
+                positions[4] + std::f64::consts::PI,
+                positions[5],
+            ])?;
+
+            // A failed load read skips one cycle of feedback rather than the teleoperation
+            if let Some(feedback) = feedback.as_mut()
+                && let Ok(loads) = follower.get_motor_loads()
+            {
+                leader.set_goal_positions(&feedback.leader_goal(&positions, &loads))?;
+            }
+        }
+
+        scheduler.wait();
+    }
+
+    log::info!("Teleoperation timing: {}", scheduler.stats());
+    log::info!("Stopping...");
+    follower.disable()?;
+    leader.disable()?;
+    if feedback.is_some() {
+        leader.set_torque_limit_all(DEFAULT_TORQUE_LIMIT)?;
+    }
+    log::info!("Both robots disabled.");
+
+    Ok(())
+}
+
+/// Record joint positions at 30Hz with torque off until ENTER is pressed, then write them to `path`
+pub fn record<P: AsRef<Path>>(robot: &mut LeRobot, path: P) -> RobotResult<()> {
+    robot.go_to_home_positions()?;
+    thread::sleep(Duration::from_millis(1000));
+
+    robot.disable()?;
+    log::info!("Robot initialized and torque disabled.");
+
+    wait_for_enter("Press ENTER to START recording...")?;
+    log::info!("Recording started... Press ENTER to STOP.");
+    let records = capture(robot);
+
+    log::info!(
+        "Saving {} frames to {}...",
+        records.len(),
+        path.as_ref().display()
+    );
+    write_frames(path, &records)?;
+
+    log::info!("Done.");
+    Ok(())
+}
+
+/// Record one episode after another into `dir`, one CSV per episode, until `q` is entered.
+/// `manifest.json` is rewritten after every episode; an existing session in `dir` is
+/// continued.
+pub fn record_session<P: AsRef<Path>>(
+    robot: &mut LeRobot,
+    dir: P,
+    task: &str,
+    robot_profile: &str,
+    profile: &RobotProfile,
+) -> RobotResult<SessionManifest> {
+    let mut corpus = Corpus::open_or_create(dir, task, robot_profile, profile)?;
+    if !corpus.is_empty() {
+        log::info!(
+            "Continuing session with {} episodes of '{}'",
+            corpus.len(),
+            corpus.manifest().task
+        );
+    }
+
+    robot.go_to_home_positions()?;
+    thread::sleep(Duration::from_millis(1000));
+    robot.disable()?;
+    log::info!("Robot initialized and torque disabled.");
+
+    loop {
+        let episode = corpus.len();
+        let answer = prompt(&format!(
+            "Press ENTER to START episode {} (q + ENTER to finish)... ",
+            episode
+        ))?;
+        if answer.trim().eq_ignore_ascii_case("q") {
+            break;
+        }
+        log::info!("Recording episode {}... Press ENTER to STOP.", episode);
+        let records = capture(robot);
+        if records.is_empty() {
+            log::warn!("No frames were read; episode {} discarded", episode);
+            continue;
+        }
+
+        let duration_ms = records.last().map_or(0, |r| r.timestamp_ms);
+        corpus.append(&frames_to_trajectory(&records))?;
+        log::info!(
+            "Saved episode {}: {} frames, {:.1} s",
+            episode,
+            records.len(),
+            duration_ms as f64 / 1000.0
+        );
+    }
+
+    log::info!(
+        "Session has {} episodes in {}",
+        corpus.len(),
+        corpus.dir().display()
+    );
+    Ok(corpus.manifest().clone())
+}
+
+fn frames_to_trajectory(records: &[RobotFrame]) -> Trajectory {
+    Trajectory {
+        channels: (1..=6).map(|j| format!("j{}", j)).collect(),
+        timestamps_ms: Some(records.iter().map(|r| r.timestamp_ms).collect()),
+        frames: records.iter().map(|r| r.positions().to_vec()).collect(),
+    }
+}
+
+/// Sample joint positions at 30Hz until ENTER is pressed
+fn capture(robot: &mut LeRobot) -> Vec<RobotFrame> {
+    let keep_running = spawn_stop_listener();
+    let mut records = Vec::new();
+    let mut scheduler = Scheduler::at_rate(30.0);
+
+    while keep_running.load(Ordering::Relaxed) {
+        if let Ok(positions) = robot.get_motor_positions()
+            && positions.len() == 6
+        {
+            records.push(RobotFrame {
+                timestamp_ms: scheduler.elapsed().as_millis() as u64,
+                j1: positions[0],
+                j2: positions[1],
+                j3: positions[2],
+                j4: positions[3],
+                j5: positions[4],
+                j6: positions[5],
+            });
+        }
+
+        scheduler.wait();
+    }
+    log::info!("Recording timing: {}", scheduler.stats());
+    records
+}
+
+fn write_frames<P: AsRef<Path>>(path: P, records: &[RobotFrame]) -> RobotResult<()> {
+    let file = File::create(path)?;
+    let mut wtr = csv::Writer::from_writer(file);
+    for record in records {
+        wtr.serialize(record)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Options of [`playback`]
+#[derive(Debug, Clone)]
+pub struct PlaybackOptions {
+    pub interpolation: Interpolation,
+    /// Rate goals are sent at between frames; step playback sends each frame at its timestamp
+    pub rate_hz: f64,
+    /// Joint speed limit in rad/s; segments that would move faster are slowed down
+    pub max_velocity: Option<f64>,
+}
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        Self {
+            interpolation: Interpolation::default(),
+            rate_hz: 50.0,
+            max_velocity: None,
+        }
+    }
+}
+
+/// Replay a recorded trajectory with its original timing, interpolating between frames and
+/// keeping the goals inside the profile's joint limits. ENTER stops early.
+pub fn playback<P: AsRef<Path>>(
+    robot: &mut LeRobot,
+    path: P,
+    profile: &RobotProfile,
+    options: &PlaybackOptions,
+) -> RobotResult<()> {
+    log::info!("Loading data from {}...", path.as_ref().display());
+    let recording = Trajectory::load(path)?;
+
+    if recording.is_empty() {
+        log::info!("No records found.");
+        return Ok(());
+    }
+    if options.interpolation != Interpolation::Step && options.rate_hz <= 0.0 {
+        return Err(CsdpError::Config(format!(
+            "playback rate must be positive, got {} Hz",
+            options.rate_hz
+        )));
+    }
+    if recording.channels.len() != 6 {
+        return Err(CsdpError::Data(format!(
+            "expected 6 joints, the recording has {}",
+            recording.channels.len()
+        )));
+    }
+
+    let relative = |limits: &[f64; 6]| -> [f64; 6] {
+        std::array::from_fn(|i| limits[i] - profile.home_positions[i])
+    };
+    let mut trajectory = InterpolatedTrajectory::new(&recording, options.interpolation)?
+        .with_limits(
+            &relative(&profile.min_positions),
+            &relative(&profile.max_positions),
+        );
+    if let Some(max_velocity) = options.max_velocity {
+        let recorded = trajectory.duration();
+        trajectory = trajectory.with_max_velocities(&[max_velocity; 6]);
+        if trajectory.duration() > recorded {
+            log::info!(
+                "Slowed down from {:.1} s to {:.1} s to stay below {} rad/s",
+                recorded.as_secs_f64(),
+                trajectory.duration().as_secs_f64(),
+                max_velocity
+            );
+        }
+    }
+
+    log::info!("Moving to start position...");
+    robot.enable()?;
+    robot.set_goal_positions(&trajectory.sample(Duration::ZERO))?;
+    thread::sleep(Duration::from_millis(1500));
+
+    log::info!(
+        "Playback started ({:?} interpolation). Press ENTER to stop early.",
+        options.interpolation
+    );
+    let keep_running = spawn_stop_listener();
+
+    let mut scheduler = if options.interpolation == Interpolation::Step {
+        let mut scheduler = Scheduler::at_rate(30.0);
+        for (t, frame) in trajectory.knots() {
+            if !keep_running.load(Ordering::Relaxed) {
+                log::info!("Playback interrupted by user.");
+                break;
+            }
+
+            // Frames keep their recorded timestamps, whatever rate they were recorded at
+            scheduler.wait_until(t);
+            robot.set_goal_positions(frame)?;
+        }
+        scheduler
+    } else {
+        let mut scheduler = Scheduler::at_rate(options.rate_hz);
+        let end = trajectory.duration();
+        loop {
+            if !keep_running.load(Ordering::Relaxed) {
+                log::info!("Playback interrupted by user.");
+                break;
+            }
+
+            // Sample at the time actually reached, so a late cycle doesn't lag behind
+            let t = scheduler.elapsed().min(end);
+            robot.set_goal_positions(&trajectory.sample(t))?;
+            if t >= end {
+                break;
+            }
+            scheduler.wait();
+        }
+        scheduler
+    };
+    log::info!("Playback timing: {}", scheduler.stats());
+
+    robot.disable()?;
+    log::info!("Motors disabled. Done.");
+
+    Ok(())
+}
+
+/// Interactively measure home offsets and joint limits. The arm is left limp; the user poses it
+/// at home, then sweeps every joint through its full range.
+pub fn calibrate(robot: &mut LeRobot, port: &str) -> RobotResult<RobotProfile> {
+    robot.disable()?;
+
+    wait_for_enter("Torque disabled. Move the arm to its HOME pose and press ENTER...")?;
+    let home = robot.get_raw_positions()?;
+    if home.len() != 6 {
+        return Err(CsdpError::Servo(format!(
+            "expected 6 joints, read {}",
+            home.len()
+        )));
+    }
+
+    wait_for_enter("Press ENTER, then sweep every joint through its full range...")?;
+    log::info!("Recording joint range... Press ENTER to STOP.");
+
+    let keep_running = spawn_stop_listener();
+    let mut min_positions = [f64::INFINITY; 6];
+    let mut max_positions = [f64::NEG_INFINITY; 6];
+    let mut scheduler = Scheduler::at_rate(30.0);
+
+    while keep_running.load(Ordering::Relaxed) {
+        if let Ok(positions) = robot.get_raw_positions()
+            && positions.len() == 6
+        {
+            for (j, &p) in positions.iter().enumerate() {
+                min_positions[j] = min_positions[j].min(p);
+                max_positions[j] = max_positions[j].max(p);
+            }
+        }
+
+        scheduler.wait();
+    }
+
+    if min_positions.iter().any(|p| !p.is_finite()) {
+        return Err(CsdpError::Servo(
+            "no positions were read during the sweep".to_string(),
+        ));
+    }
+
+    let mut home_positions = [0.0; 6];
+    home_positions.copy_from_slice(&home);
+
+    Ok(RobotProfile {
+        port: port.to_string(),
+        home_positions,
+        min_positions,
+        max_positions,
+    })
+}
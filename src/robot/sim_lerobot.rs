@@ -1 +1,97 @@
+use super::Arm;
+use super::profile::RobotProfile;
+use super::real_lerobot::RobotResult;
 
+const NUM_JOINTS: usize = 6;
+
+/// Kinematic stand-in for [`super::real_lerobot::LeRobot`], for testing training loops
+/// without hardware.
+///
+/// Goals are clamped to the calibrated limits, and every position read moves each joint up
+/// to `max_step` radians toward its goal while torque is enabled. There are no dynamics and
+/// loads always read zero.
+#[derive(Debug, Clone)]
+pub struct SimLeRobot {
+    positions: [f64; NUM_JOINTS],
+    goals: [f64; NUM_JOINTS],
+    /// joint limits relative to the home position
+    lower: [f64; NUM_JOINTS],
+    upper: [f64; NUM_JOINTS],
+    /// radians a joint moves per position read
+    pub max_step: f64,
+    enabled: bool,
+}
+
+impl SimLeRobot {
+    /// Arm at its home position; `home`, `min` and `max` are raw positions as in a profile
+    pub fn new(
+        home_positions: [f64; NUM_JOINTS],
+        min_positions: [f64; NUM_JOINTS],
+        max_positions: [f64; NUM_JOINTS],
+    ) -> Self {
+        let relative =
+            |limits: [f64; NUM_JOINTS]| std::array::from_fn(|i| limits[i] - home_positions[i]);
+        Self {
+            positions: [0.0; NUM_JOINTS],
+            goals: [0.0; NUM_JOINTS],
+            lower: relative(min_positions),
+            upper: relative(max_positions),
+            max_step: 0.1,
+            enabled: false,
+        }
+    }
+
+    pub fn from_profile(profile: &RobotProfile) -> Self {
+        Self::new(
+            profile.home_positions,
+            profile.min_positions,
+            profile.max_positions,
+        )
+    }
+
+    fn advance(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        for (position, goal) in self.positions.iter_mut().zip(self.goals) {
+            *position += (goal - *position).clamp(-self.max_step, self.max_step);
+        }
+    }
+}
+
+impl Arm for SimLeRobot {
+    fn enable(&mut self) -> RobotResult<()> {
+        self.enabled = true;
+        Ok(())
+    }
+
+    fn disable(&mut self) -> RobotResult<()> {
+        self.enabled = false;
+        Ok(())
+    }
+
+    fn set_goal_positions(&mut self, positions: &[f64]) -> RobotResult<()> {
+        for (i, &p) in positions.iter().enumerate().take(NUM_JOINTS) {
+            // min/max instead of clamp: an inverted calibration must not panic
+            self.goals[i] = p.max(self.lower[i]).min(self.upper[i]);
+        }
+        Ok(())
+    }
+
+    fn get_motor_positions(&mut self) -> RobotResult<Vec<f64>> {
+        self.advance();
+        Ok(self.positions.to_vec())
+    }
+
+    fn get_goal_positions(&mut self) -> RobotResult<Vec<f64>> {
+        Ok(self.goals.to_vec())
+    }
+
+    fn get_motor_loads(&mut self) -> RobotResult<Vec<f64>> {
+        Ok(vec![0.0; NUM_JOINTS])
+    }
+
+    fn try_clone(&self) -> Option<Box<dyn Arm>> {
+        Some(Box::new(self.clone()))
+    }
+}
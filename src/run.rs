@@ -0,0 +1,369 @@
+//! Per-run output directories.
+//!
+//! Every training run gets its own timestamped directory, `<output-dir>/<name>-<YYYYMMDD-HHMMSS>`
+//! in UTC, so runs with different hyperparameters never overwrite each other:
+//!
+//! - `run.json`: name, start time, command line and the git commit of the working tree
+//! - `config.json`: the experiment config as resolved for the run
+//! - `checkpoints/`: the rotated checkpoints
+//! - `metrics.csv`: the reward of every episode
+//! - `plots/`: exported plots, e.g. `reward.svg`
+//! - `summary.json`: final and best rewards, config hash, wall time and checkpoint paths,
+//!   written when training ends so sweep scripts can rank runs without parsing logs
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Episodes averaged for [`RunSummary::final_mean_reward`]
+pub const FINAL_WINDOW: usize = 10;
+
+/// Contents of `run.json`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunInfo {
+    pub name: String,
+    /// start time, ISO 8601 in UTC
+    pub started: String,
+    pub command: Vec<String>,
+    /// commit of the working tree; None outside a git checkout
+    pub git_hash: Option<String>,
+    /// the working tree had uncommitted changes
+    pub git_dirty: bool,
+}
+
+/// Contents of `summary.json`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub name: String,
+    /// training ran to its end; false if it failed with `error`
+    pub completed: bool,
+    pub error: Option<String>,
+    /// end time, ISO 8601 in UTC
+    pub finished: String,
+    /// seconds spent in this session; a resumed run counts from its resumption
+    pub wall_time_s: f64,
+    /// FNV-1a hash of `config.json` in hex, equal for runs with the same resolved config
+    pub config_hash: Option<String>,
+    /// episodes in the reward history, including those of a resumed run
+    pub episodes: usize,
+    pub final_reward: Option<f32>,
+    /// mean reward of the last [`FINAL_WINDOW`] episodes, less noisy than the final reward
+    pub final_mean_reward: Option<f32>,
+    pub best_reward: Option<f32>,
+    pub best_episode: Option<usize>,
+    /// newest rotated checkpoint
+    pub latest_checkpoint: Option<PathBuf>,
+    /// checkpoint kept in `best/`, and the metric it was kept for: the validation reward when
+    /// validating, the training reward otherwise
+    pub best_checkpoint: Option<PathBuf>,
+    pub best_checkpoint_metric: Option<f32>,
+}
+
+impl RunSummary {
+    /// Summary of run `name` from its reward history of `(episode, reward)` pairs, with no
+    /// checkpoints or config hash yet. Non-finite rewards count as episodes but never as the
+    /// best.
+    pub fn new(name: &str, rewards: &[(usize, f32)], wall_time: Duration) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let best = rewards
+            .iter()
+            .filter(|(_, r)| r.is_finite())
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let window: Vec<f32> = rewards[rewards.len().saturating_sub(FINAL_WINDOW)..]
+            .iter()
+            .map(|&(_, r)| r)
+            .filter(|r| r.is_finite())
+            .collect();
+        Self {
+            name: name.to_string(),
+            completed: true,
+            error: None,
+            finished: iso_timestamp(now),
+            wall_time_s: wall_time.as_secs_f64(),
+            config_hash: None,
+            episodes: rewards.len(),
+            final_reward: rewards.last().map(|&(_, r)| r).filter(|r| r.is_finite()),
+            final_mean_reward: (!window.is_empty())
+                .then(|| window.iter().sum::<f32>() / window.len() as f32),
+            best_reward: best.map(|&(_, r)| r),
+            best_episode: best.map(|&(e, _)| e),
+            latest_checkpoint: None,
+            best_checkpoint: None,
+            best_checkpoint_metric: None,
+        }
+    }
+}
+
+/// Output directory of one run
+#[derive(Clone, Debug)]
+pub struct RunDir {
+    path: PathBuf,
+}
+
+impl RunDir {
+    /// Create a new directory for run `name` under `output_dir` and write its `run.json`.
+    /// Runs started within the same second get a numeric suffix.
+    pub fn create(output_dir: &Path, name: &str) -> io::Result<Self> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let base = format!("{}-{}", name, run_stamp(now));
+        std::fs::create_dir_all(output_dir)?;
+        let mut path = output_dir.join(&base);
+        let mut suffix = 1;
+        // create_dir fails if the directory exists, so concurrent runs can't share one
+        while let Err(e) = std::fs::create_dir(&path) {
+            if e.kind() != io::ErrorKind::AlreadyExists {
+                return Err(e);
+            }
+            suffix += 1;
+            path = output_dir.join(format!("{}-{}", base, suffix));
+        }
+
+        let (git_hash, git_dirty) = git_commit();
+        let info = RunInfo {
+            name: name.to_string(),
+            started: iso_timestamp(now),
+            command: std::env::args().collect(),
+            git_hash,
+            git_dirty,
+        };
+        let run = Self { path };
+        std::fs::write(
+            run.path.join("run.json"),
+            serde_json::to_string_pretty(&info)?,
+        )?;
+        Ok(run)
+    }
+
+    /// An existing run directory, e.g. to resume it
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        if !path.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("run directory {:?} doesn't exist", path),
+            ));
+        }
+        Ok(Self { path })
+    }
+
+    /// The most recently started run named `name` under `output_dir`, if any
+    pub fn latest(output_dir: &Path, name: &str) -> io::Result<Option<Self>> {
+        if !output_dir.is_dir() {
+            return Ok(None);
+        }
+        let prefix = format!("{}-", name);
+        let mut runs: Vec<(String, PathBuf)> = vec![];
+        for entry in std::fs::read_dir(output_dir)? {
+            let entry = entry?;
+            let dir_name = entry.file_name().to_string_lossy().into_owned();
+            // the stamp is `YYYYMMDD-HHMMSS`, optionally followed by `-N`
+            let is_run = dir_name
+                .strip_prefix(&prefix)
+                .is_some_and(|rest| rest.get(..15).is_some_and(is_stamp));
+            if is_run && entry.path().is_dir() {
+                runs.push((dir_name, entry.path()));
+            }
+        }
+        // Timestamps sort lexicographically; same-second suffixes by number
+        runs.sort_by_key(|(dir_name, _)| {
+            let rest = &dir_name[prefix.len()..];
+            let suffix = rest
+                .get(16..)
+                .and_then(|s| s.parse::<u32>().ok())
+                .unwrap_or(1);
+            (rest[..15].to_string(), suffix)
+        });
+        Ok(runs.pop().map(|(_, path)| Self { path }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn checkpoints_dir(&self) -> PathBuf {
+        self.path.join("checkpoints")
+    }
+
+    pub fn metrics_path(&self) -> PathBuf {
+        self.path.join("metrics.csv")
+    }
+
+    pub fn plots_dir(&self) -> PathBuf {
+        self.path.join("plots")
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.path.join("config.json")
+    }
+
+    pub fn summary_path(&self) -> PathBuf {
+        self.path.join("summary.json")
+    }
+
+    /// Write the resolved config to `config.json`
+    pub fn write_config<T: Serialize>(&self, config: &T) -> io::Result<()> {
+        std::fs::write(self.config_path(), serde_json::to_string_pretty(config)?)
+    }
+
+    /// FNV-1a hash of `config.json` in hex; None before the config is written
+    pub fn config_hash(&self) -> io::Result<Option<String>> {
+        match std::fs::read(self.config_path()) {
+            Ok(bytes) => Ok(Some(format!(
+                "{:016x}",
+                crate::repro::fnv1a(crate::repro::FNV_OFFSET, &bytes)
+            ))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write `summary.json` through a temporary file, so a sweep script polling for it never
+    /// reads a partial one
+    pub fn write_summary(&self, summary: &RunSummary) -> io::Result<()> {
+        let tmp = self.path.join("summary.json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(summary)?)?;
+        std::fs::rename(tmp, self.summary_path())
+    }
+
+    /// Read `summary.json`
+    pub fn summary(&self) -> io::Result<RunSummary> {
+        let file = std::fs::File::open(self.summary_path())?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    /// Read `run.json`
+    pub fn info(&self) -> io::Result<RunInfo> {
+        let file = std::fs::File::open(self.path.join("run.json"))?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    /// Plot the reward of every episode to `plots/reward.svg` and return its path
+    pub fn export_reward_plot(&self, rewards: &[(usize, f32)]) -> io::Result<PathBuf> {
+        std::fs::create_dir_all(self.plots_dir())?;
+        let path = self.plots_dir().join("reward.svg");
+        std::fs::write(&path, reward_svg(rewards))?;
+        Ok(path)
+    }
+}
+
+/// Commit hash of the working tree and whether it has uncommitted changes
+pub fn git_commit() -> (Option<String>, bool) {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+    };
+    match git(&["rev-parse", "HEAD"]) {
+        Some(hash) => {
+            let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+                .is_some_and(|status| !status.is_empty());
+            (Some(hash), dirty)
+        }
+        None => (None, false),
+    }
+}
+
+fn is_stamp(s: &str) -> bool {
+    s.len() == 15
+        && s.char_indices()
+            .all(|(i, c)| if i == 8 { c == '-' } else { c.is_ascii_digit() })
+}
+
+/// Year, month, day, hour, minute and second in UTC of `secs` since the Unix epoch
+fn utc(secs: u64) -> (i64, u32, u32, u64, u64, u64) {
+    // Howard Hinnant's civil_from_days
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let time = secs % 86400;
+    (year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+/// `YYYYMMDD-HHMMSS` in UTC, as run directories are named
+pub fn run_stamp(secs: u64) -> String {
+    let (y, mo, d, h, mi, s) = utc(secs);
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}", y, mo, d, h, mi, s)
+}
+
+fn iso_timestamp(secs: u64) -> String {
+    let (y, mo, d, h, mi, s) = utc(secs);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", y, mo, d, h, mi, s)
+}
+
+const PLOT_WIDTH: f32 = 800.0;
+const PLOT_HEIGHT: f32 = 300.0;
+const PLOT_MARGIN: f32 = 50.0;
+
+/// Reward per episode as a line plot, with the reward range on the y axis
+fn reward_svg(rewards: &[(usize, f32)]) -> String {
+    let finite: Vec<(usize, f32)> = rewards
+        .iter()
+        .copied()
+        .filter(|(_, r)| r.is_finite())
+        .collect();
+    let (min, max) = finite
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &(_, r)| {
+            (lo.min(r), hi.max(r))
+        });
+    let first = finite.first().map_or(0, |&(e, _)| e);
+    let x_span = (finite.last().map_or(0, |&(e, _)| e) - first).max(1) as f32;
+    let y_span = (max - min).max(f32::EPSILON);
+    let plot_width = PLOT_WIDTH - PLOT_MARGIN;
+    let plot_height = PLOT_HEIGHT - 20.0;
+
+    let points: Vec<String> = finite
+        .iter()
+        .map(|&(epoch, reward)| {
+            let x = PLOT_MARGIN + (epoch - first) as f32 / x_span * plot_width;
+            let y = 10.0 + (1.0 - (reward - min) / y_span) * plot_height;
+            format!("{:.1},{:.1}", x, y)
+        })
+        .collect();
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="monospace" font-size="11">"#,
+        PLOT_WIDTH, PLOT_HEIGHT
+    );
+    let _ = writeln!(
+        svg,
+        r##"<rect x="{}" y="10" width="{}" height="{}" fill="none" stroke="#ccc"/>"##,
+        PLOT_MARGIN, plot_width, plot_height
+    );
+    if !finite.is_empty() {
+        let _ = writeln!(svg, r#"<text x="4" y="20">{:.2}</text>"#, max);
+        let _ = writeln!(
+            svg,
+            r#"<text x="4" y="{}">{:.2}</text>"#,
+            PLOT_HEIGHT - 10.0,
+            min
+        );
+    }
+    let _ = writeln!(
+        svg,
+        r##"<polyline fill="none" stroke="#1f77b4" points="{}"/>"##,
+        points.join(" ")
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
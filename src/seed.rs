@@ -0,0 +1,141 @@
+//! Run-wide seeding. Host-side randomness (sample shuffling, exploration, environment resets)
+//! goes through [`rng`]. Tensor randomness (weight init, `BernoulliLayer` spikes) goes through
+//! [`rand_uniform`]/[`rand_normal`], which draw from the same host RNG on the CPU (candle's CPU
+//! backend can't be seeded) and from the seeded device generator on the GPU.
+//!
+//! CUDA reductions are not bitwise deterministic, so identical seeds give identical runs on
+//! the CPU and closely matching runs on the GPU.
+
+use candle_core::{Device, Result as CandleResult, Shape, Tensor};
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// u64::MAX marks "unseeded"
+static GLOBAL_SEED: AtomicU64 = AtomicU64::new(u64::MAX);
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(fresh_rng());
+}
+
+fn fresh_rng() -> StdRng {
+    match global_seed() {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// Record `seed` as the run-wide seed and [`reseed`] with it
+pub fn set_global_seed(seed: u64, device: &Device) -> CandleResult<()> {
+    GLOBAL_SEED.store(seed, Ordering::Relaxed);
+    reseed(seed, device)
+}
+
+/// Reseed the host RNG of the calling thread and, on accelerators, the generator of `device`
+pub fn reseed(seed: u64, device: &Device) -> CandleResult<()> {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+    if device.is_cpu() {
+        Ok(())
+    } else {
+        device.set_seed(seed)
+    }
+}
+
+/// Draw a seed from the host RNG and [`reseed`] with it. Storing the returned seed in a
+/// checkpoint and calling [`reseed`] with it on resume makes the resumed run draw the same
+/// random numbers as the uninterrupted one.
+pub fn fork(device: &Device) -> CandleResult<u64> {
+    let seed = rng().next_u64();
+    reseed(seed, device)?;
+    Ok(seed)
+}
+
+pub fn global_seed() -> Option<u64> {
+    match GLOBAL_SEED.load(Ordering::Relaxed) {
+        u64::MAX => None,
+        seed => Some(seed),
+    }
+}
+
+/// Fingerprint of the calling thread's host RNG state: the next `u64` it would draw, taken
+/// from a copy so the RNG itself doesn't advance
+pub fn fingerprint() -> u64 {
+    RNG.with(|rng| rng.borrow().clone().next_u64())
+}
+
+/// Run `f` on a copy of the calling thread's host RNG and put the original back afterwards,
+/// so side work like a pre-flight check draws what it needs without shifting a seeded run
+pub fn isolated<T>(f: impl FnOnce() -> T) -> T {
+    let saved = RNG.with(|rng| rng.borrow().clone());
+    let out = f();
+    RNG.with(|rng| *rng.borrow_mut() = saved);
+    out
+}
+
+/// Handle to the thread-local host RNG; a drop-in replacement for `rand::thread_rng()`
+pub fn rng() -> SeededRng {
+    SeededRng
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SeededRng;
+
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        RNG.with(|rng| rng.borrow_mut().next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        RNG.with(|rng| rng.borrow_mut().fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        RNG.with(|rng| rng.borrow_mut().try_fill_bytes(dest))
+    }
+}
+
+/// Uniform samples in `[lo, hi)`, reproducible under the global seed
+pub fn rand_uniform<S: Into<Shape>>(
+    lo: f32,
+    hi: f32,
+    shape: S,
+    device: &Device,
+) -> CandleResult<Tensor> {
+    let shape = shape.into();
+    if !device.is_cpu() {
+        return Tensor::rand(lo, hi, shape, device);
+    }
+    let mut rng = rng();
+    let values: Vec<f32> = (0..shape.elem_count())
+        .map(|_| rng.gen_range(lo..hi))
+        .collect();
+    Tensor::from_vec(values, shape, device)
+}
+
+/// Normal samples, reproducible under the global seed
+pub fn rand_normal<S: Into<Shape>>(
+    mean: f32,
+    std: f32,
+    shape: S,
+    device: &Device,
+) -> CandleResult<Tensor> {
+    let shape = shape.into();
+    if !device.is_cpu() {
+        return Tensor::randn(mean, std, shape, device);
+    }
+    let mut rng = rng();
+    // Box-Muller; the second value of each pair is discarded to keep this simple
+    let values: Vec<f32> = (0..shape.elem_count())
+        .map(|_| {
+            let u1: f32 = rng.gen_range(f32::EPSILON..1.0);
+            let u2: f32 = rng.r#gen();
+            mean + std * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+        })
+        .collect();
+    Tensor::from_vec(values, shape, device)
+}
@@ -0,0 +1,156 @@
+//! Shaping of supervision signals: labels for the context pathway and rewards for RL.
+//!
+//! A hard one-hot label drives the context layer's Bernoulli neurons at rates of exactly 0
+//! and 1, so the teacher signal never admits that a sample might belong to another class.
+//! [`smooth_labels`] moves `epsilon` of the probability mass onto all classes evenly;
+//! [`Model::set_label_smoothing`](crate::models::Model::set_label_smoothing) applies it to
+//! every context passed to `step`.
+//!
+//! On the RL side, raw environment rewards differ in scale by orders of magnitude between
+//! tasks. [`RewardShaping`] clips and scales each reward; it is stateless, so
+//! `environment::shaped::ShapedEnvironment` can apply it to every reward an environment
+//! hands to a training loop, including those of actions only considered. [`RewardNormalizer`]
+//! standardizes rewards or returns by their running statistics, and [`discounted_returns`]
+//! turns the rewards of an episode into Monte Carlo returns.
+
+use candle_core::{Result as CandleResult, Tensor};
+use serde::{Deserialize, Serialize};
+
+/// `label` with `epsilon` of its mass spread evenly over all classes: every entry becomes
+/// `(1 - epsilon) * label + epsilon / num_classes`, where the classes are along dim 0, e.g.
+/// (num_classes, batch). A single row is a binary label, the probability of the positive
+/// class, so it is smoothed toward 0.5.
+pub fn smooth_labels(label: &Tensor, epsilon: f32) -> CandleResult<Tensor> {
+    check_label_smoothing(epsilon)?;
+    spread_labels(label, epsilon)
+}
+
+/// Fails unless `epsilon` is a valid label smoothing, in [0, 1]
+pub fn check_label_smoothing(epsilon: f32) -> CandleResult<()> {
+    if !(0.0..=1.0).contains(&epsilon) {
+        return Err(candle_core::Error::Msg(format!(
+            "label smoothing must be in [0, 1], got {}",
+            epsilon
+        )));
+    }
+    Ok(())
+}
+
+/// [`smooth_labels`] for an `epsilon` already checked when it was configured
+pub(crate) fn spread_labels(label: &Tensor, epsilon: f32) -> CandleResult<Tensor> {
+    let num_classes = label.dim(0)?.max(2);
+    label.affine(1.0 - epsilon as f64, epsilon as f64 / num_classes as f64)
+}
+
+/// How [`RewardShaping`] bounds a reward, after scaling
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RewardClip {
+    #[default]
+    None,
+    /// Clamp into `[min, max]`
+    Range { min: f64, max: f64 },
+    /// Keep only the sign, -1, 0 or 1, as in the Atari DQN setup
+    Sign,
+}
+
+impl RewardClip {
+    pub fn apply(&self, reward: f64) -> f64 {
+        match *self {
+            RewardClip::None => reward,
+            // max/min instead of clamp: an inverted range must not panic
+            RewardClip::Range { min, max } => reward.max(min).min(max),
+            RewardClip::Sign if reward == 0.0 => 0.0,
+            RewardClip::Sign => reward.signum(),
+        }
+    }
+}
+
+/// Stateless transform of every reward: scaled by `scale`, then clipped
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RewardShaping {
+    pub scale: f64,
+    pub clip: RewardClip,
+}
+
+impl Default for RewardShaping {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            clip: RewardClip::None,
+        }
+    }
+}
+
+impl RewardShaping {
+    pub fn apply(&self, reward: f64) -> f64 {
+        self.clip.apply(reward * self.scale)
+    }
+}
+
+/// Monte Carlo returns of one episode's `rewards`: `G_t = r_t + gamma * G_{t+1}`
+pub fn discounted_returns(rewards: &[f32], gamma: f32) -> Vec<f32> {
+    let mut returns = vec![0.0; rewards.len()];
+    let mut running = 0.0;
+    for (ret, &reward) in returns.iter_mut().zip(rewards).rev() {
+        running = reward + gamma * running;
+        *ret = running;
+    }
+    returns
+}
+
+/// Standardizes rewards or returns by the running mean and variance of all values seen
+/// (Welford's algorithm)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RewardNormalizer {
+    count: u64,
+    mean: f64,
+    /// sum of squared deviations from the mean
+    m2: f64,
+}
+
+impl RewardNormalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `value` to the statistics
+    pub fn update(&mut self, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Standard deviation of the values seen; 1 before two values were seen
+    pub fn std(&self) -> f64 {
+        if self.count < 2 {
+            return 1.0;
+        }
+        (self.m2 / (self.count - 1) as f64).sqrt()
+    }
+
+    /// `value` standardized by the statistics so far, without updating them
+    pub fn normalize(&self, value: f64) -> f64 {
+        (value - self.mean) / self.std().max(f64::EPSILON)
+    }
+
+    /// Add every value to the statistics, then standardize them in place, e.g. the returns of
+    /// an episode before they become training targets
+    pub fn normalize_batch(&mut self, values: &mut [f32]) {
+        for &value in values.iter() {
+            self.update(value as f64);
+        }
+        for value in values.iter_mut() {
+            *value = self.normalize(*value as f64) as f32;
+        }
+    }
+}
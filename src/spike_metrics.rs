@@ -0,0 +1,130 @@
+//! Distances between spike trains and similarity of population activity to target patterns.
+//!
+//! Single trains are binned per timestep (`train[t]` is the number of spikes in step `t`, as
+//! read from a layer's output) or, for [`victor_purpura`], given as spike times. The
+//! population functions take a layer's outputs over a run, one `(neurons, batch)` tensor per
+//! step as in [`crate::models::ProcessOutput::output_activity`], and score every sample of the
+//! batch. They are used by the `evaluate` tool and can shape rewards, see [`distance_reward`].
+
+use candle_core::{DType, Result as CandleResult, Tensor};
+
+/// Times of the spikes of a binned train with steps of `dt`; a bin with `n` spikes gives `n`
+/// spikes at the start of the step
+pub fn spike_times(train: &[f32], dt: f32) -> Vec<f32> {
+    train
+        .iter()
+        .enumerate()
+        .flat_map(|(t, &n)| std::iter::repeat_n(t as f32 * dt, n.round().max(0.0) as usize))
+        .collect()
+}
+
+/// Difference of the spike counts of two trains
+pub fn spike_count_distance(a: &[f32], b: &[f32]) -> f32 {
+    (a.iter().sum::<f32>() - b.iter().sum::<f32>()).abs()
+}
+
+/// Van Rossum distance between two binned trains: both are filtered with a causal
+/// exponential kernel of time constant `tau` and the distance is
+/// `sqrt(1 / tau * integral (f - g)^2 dt)`. One spike on its own is at distance about
+/// `sqrt(1 / 2)`; small `tau` compares precise timing, large `tau` rates. A shorter train is
+/// padded with silence.
+pub fn van_rossum(a: &[f32], b: &[f32], tau: f32, dt: f32) -> f32 {
+    let decay = (-dt / tau).exp();
+    let mut trace = 0.0f32;
+    let mut sum = 0.0f32;
+    for t in 0..a.len().max(b.len()) {
+        trace = trace * decay + a.get(t).copied().unwrap_or(0.0) - b.get(t).copied().unwrap_or(0.0);
+        sum += trace * trace;
+    }
+    (sum * dt / tau).sqrt()
+}
+
+/// Victor-Purpura distance between two trains of spike times: the cheapest way to turn one
+/// into the other, where inserting or deleting a spike costs 1 and moving one by `d` costs
+/// `cost * d`. With `cost` 0 it is the spike count distance; spikes further apart than
+/// `2 / cost` are cheaper to delete and insert than to move.
+pub fn victor_purpura(a: &[f32], b: &[f32], cost: f32) -> f32 {
+    // previous[j]: distance between the first i - 1 spikes of `a` and the first j of `b`
+    let mut previous: Vec<f32> = (0..=b.len()).map(|j| j as f32).collect();
+    let mut current = vec![0.0; b.len() + 1];
+    for (i, &ta) in a.iter().enumerate() {
+        current[0] = (i + 1) as f32;
+        for (j, &tb) in b.iter().enumerate() {
+            current[j + 1] = (previous[j + 1] + 1.0)
+                .min(current[j] + 1.0)
+                .min(previous[j] + cost * (ta - tb).abs());
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Spike counts of each neuron over a run, `(neurons, batch)`
+fn counts(steps: &[Tensor]) -> CandleResult<Tensor> {
+    let Some(first) = steps.first() else {
+        return Err(candle_core::Error::Msg("no timesteps given".to_string()));
+    };
+    let mut sum = first.clone();
+    for step in &steps[1..] {
+        sum = sum.add(step)?;
+    }
+    Ok(sum)
+}
+
+/// Cosine similarity of each sample's spike counts over the run with its target pattern,
+/// `(neurons, batch)` (e.g. one-hot class labels); 0 for a silent output or an empty target
+pub fn population_similarity(output: &[Tensor], target: &Tensor) -> CandleResult<Vec<f32>> {
+    let counts = counts(output)?;
+    let target = target.to_device(counts.device())?.to_dtype(DType::F32)?;
+    let dot = counts.mul(&target)?.sum(0)?.to_vec1::<f32>()?;
+    let norms = |t: &Tensor| -> CandleResult<Vec<f32>> { t.sqr()?.sum(0)?.sqrt()?.to_vec1() };
+    Ok(dot
+        .iter()
+        .zip(norms(&counts)?.iter().zip(norms(&target)?))
+        .map(|(&d, (&a, b))| if a * b > 0.0 { d / (a * b) } else { 0.0 })
+        .collect())
+}
+
+/// [`van_rossum`] distance of each sample's population activity from a target raster of the
+/// same length, summed over neurons in quadrature, i.e. per sample
+/// `sqrt(1 / tau * sum_neurons integral (f - g)^2 dt)`
+pub fn population_van_rossum(
+    output: &[Tensor],
+    target: &[Tensor],
+    tau: f32,
+    dt: f32,
+) -> CandleResult<Vec<f32>> {
+    if output.len() != target.len() || output.is_empty() {
+        return Err(candle_core::Error::Msg(format!(
+            "{} output steps but {} target steps",
+            output.len(),
+            target.len()
+        )));
+    }
+    let decay = (-dt / tau).exp() as f64;
+    let mut trace = output[0].zeros_like()?;
+    let mut sum = output[0].zeros_like()?;
+    for (out, tgt) in output.iter().zip(target) {
+        trace = (trace.affine(decay, 0.0)? + out.sub(&tgt.to_device(out.device())?)?)?;
+        sum = (sum + trace.sqr()?)?;
+    }
+    sum.sum(0)?
+        .affine((dt / tau) as f64, 0.0)?
+        .sqrt()?
+        .to_vec1::<f32>()
+}
+
+/// Regular train with `rate` spikes per step (at most 1) over `steps` steps, for building
+/// target rasters
+pub fn regular_train(rate: f32, steps: usize) -> Vec<f32> {
+    let rate = rate.clamp(0.0, 1.0);
+    (0..steps)
+        .map(|t| ((t + 1) as f32 * rate).floor() - (t as f32 * rate).floor())
+        .collect()
+}
+
+/// Reward in (0, 1] that is 1 at distance 0 and halves every `half_distance`, for shaping
+/// rewards from how close the output is to a target pattern
+pub fn distance_reward(distance: f32, half_distance: f32) -> f32 {
+    0.5f32.powf(distance / half_distance.max(f32::EPSILON))
+}
@@ -3,6 +3,9 @@ use crate::layer::Layer;
 use super::{SynapseOps, WeightStats};
 use candle_core::{Result as CandleResult, Tensor};
 
+/// Synaptic decay factor applied to the weights at every update
+pub(crate) const LAMBDA_D: f64 = 0.00005;
+
 #[derive(Clone)]
 #[allow(clippy::upper_case_acronyms)]
 pub struct CSDP {
@@ -21,7 +24,7 @@ impl CSDP {
         // neurons to carve out distinct, orthogonal decision boundaries within dense continuous
         // input spaces rather than grouping uniformly into the all-positive quadrant.
         let w_bound = 2.0f32 / (pre_size as f32).sqrt();
-        let weights = Tensor::rand(-w_bound, w_bound, (post_size, pre_size), device)?;
+        let weights = crate::seed::rand_uniform(-w_bound, w_bound, (post_size, pre_size), device)?;
         let biases = Tensor::zeros((post_size, 1), candle_core::DType::F32, device)?;
         Ok(Self { weights, biases })
     }
@@ -34,38 +37,68 @@ impl SynapseOps for CSDP {
         Ok(out)
     }
 
+    fn forward_active(&self, pre: &Tensor, active: &Tensor) -> CandleResult<Tensor> {
+        let batch_size = pre.dim(1)?;
+        if active.dim(0)? == 0 {
+            return self
+                .biases
+                .broadcast_as((self.biases.dim(0)?, batch_size))?
+                .contiguous();
+        }
+        // Only the weight columns of active neurons contribute
+        let columns = self.weights.index_select(active, 1)?;
+        let rows = pre.index_select(active, 0)?;
+        columns.matmul(&rows)?.broadcast_add(&self.biases)
+    }
+
+    fn dense_weights(&self) -> Option<(&Tensor, &Tensor)> {
+        Some((&self.weights, &self.biases))
+    }
+
     fn update_weights(
         &mut self,
         pre_activity: &Tensor,
-        post_layer: &mut Box<dyn Layer>,
+        post_layer: &dyn Layer,
+        dt: f32,
+    ) -> CandleResult<()> {
+        self.update_weights_scaled(pre_activity, post_layer, dt, 1.0)
+    }
+
+    fn update_weights_scaled(
+        &mut self,
+        pre_activity: &Tensor,
+        post_layer: &dyn Layer,
         _dt: f32,
+        scale: f32,
     ) -> CandleResult<()> {
         let pre = pre_activity;
         let batch_size = pre.dims().get(1).copied().unwrap_or(1);
 
         let mod_signal = post_layer.get_mod_signal();
 
-        // synaptic decay factor
-        // TODO: put at top level
-        let lambda_d = 0.00005;
+        // Averaging over the batch is folded into the small (post, batch) signal and the decay
+        // into one affine, so each update allocates three weight-sized tensors instead of five
+        let mod_avg = mod_signal.affine(scale as f64 / (batch_size as f64), 0.0)?;
 
         // outer product (should be same shape as weight matrix)
-        let dw = mod_signal.matmul(&pre.t()?)?;
-        let dw_avg = dw.affine(1.0 / (batch_size as f64), 0.0)?;
-
-        let delta = dw_avg.sub(&(self.weights.affine(lambda_d as f64, 0.0)?))?;
+        let dw_avg = mod_avg.matmul(&pre.t()?)?;
 
-        self.weights = self.weights.add(&delta)?;
+        self.weights = self
+            .weights
+            .affine((1.0 - LAMBDA_D).powf(scale as f64), 0.0)?
+            .add(&dw_avg)?;
 
         // biases are treated as connections to a neuron that is always firing every timestep
-        let db_avg = mod_signal
-            .sum_keepdim(1)?
-            .affine(1.0 / (batch_size as f64), 0.0)?;
+        let db_avg = mod_avg.sum_keepdim(1)?;
         self.biases = self.biases.add(&db_avg)?;
 
         Ok(())
     }
 
+    fn plastic_tensors_mut(&mut self) -> Vec<&mut Tensor> {
+        vec![&mut self.weights, &mut self.biases]
+    }
+
     fn weight_stats(&self) -> CandleResult<WeightStats> {
         let weights_vec = self.weights.flatten_all()?.to_vec1::<f32>()?;
         let num_weights = weights_vec.len();
@@ -128,4 +161,8 @@ impl SynapseOps for CSDP {
 
         Ok(())
     }
+
+    fn box_clone(&self) -> Box<dyn SynapseOps> {
+        Box::new(self.clone())
+    }
 }
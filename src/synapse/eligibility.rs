@@ -0,0 +1,210 @@
+use crate::layer::Layer;
+
+use super::csdp::{CSDP, LAMBDA_D};
+use super::{SynapseOps, WeightStats};
+use candle_core::{Device, Result as CandleResult, Tensor};
+use std::collections::HashMap;
+
+/// Time constants and mixing weights of the two eligibility traces of a [`MultiTraceCSDP`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceConfig {
+    /// time constant of the fast trace, in the units of `dt`
+    pub tau_fast: f32,
+    /// time constant of the slow trace, in the units of `dt`
+    pub tau_slow: f32,
+    /// weight of the fast trace in the update
+    pub fast_weight: f32,
+    /// weight of the slow trace in the update
+    pub slow_weight: f32,
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        Self {
+            tau_fast: 5.0,
+            tau_slow: 500.0,
+            fast_weight: 1.0,
+            slow_weight: 0.1,
+        }
+    }
+}
+
+impl TraceConfig {
+    fn validate(&self) -> CandleResult<()> {
+        let valid_tau = |tau: f32| tau.is_finite() && tau > 0.0;
+        if !valid_tau(self.tau_fast) || !valid_tau(self.tau_slow) {
+            return Err(candle_core::Error::Msg(format!(
+                "trace time constants must be positive, got {} and {}",
+                self.tau_fast, self.tau_slow
+            )));
+        }
+        if !self.fast_weight.is_finite() || !self.slow_weight.is_finite() {
+            return Err(candle_core::Error::Msg(format!(
+                "trace weights must be finite, got {} and {}",
+                self.fast_weight, self.slow_weight
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// CSDP synapse whose updates pass through a fast and a slow eligibility trace per weight.
+///
+/// Every step the CSDP candidate update `mod_signal * pre^T` (averaged over the batch) is
+/// low-pass filtered into both traces, each with its own time constant, and the weights move by
+/// `fast_weight * fast + slow_weight * slow`. The fast trace follows the current update; the
+/// slow trace keeps a decaying tag of recent updates that goes on being written into the weights
+/// after the fast trace has faded, as in tag-and-capture models of consolidation. Biases learn
+/// as in plain CSDP. The traces are per-sequence state, cleared by `reset` and not saved.
+#[derive(Clone)]
+pub struct MultiTraceCSDP {
+    pub weights: Tensor,
+    pub biases: Tensor,
+    pub config: TraceConfig,
+    /// fast eligibility trace, same shape as `weights`
+    fast: Tensor,
+    /// slow eligibility trace, same shape as `weights`
+    slow: Tensor,
+}
+
+impl MultiTraceCSDP {
+    pub fn new(
+        pre_size: usize,
+        post_size: usize,
+        config: TraceConfig,
+        device: &Device,
+    ) -> CandleResult<Self> {
+        config.validate()?;
+        // Same initialization as the plain CSDP synapse
+        let CSDP { weights, biases } = CSDP::new(pre_size, post_size, device)?;
+        let traces = weights.zeros_like()?;
+        Ok(Self {
+            weights,
+            biases,
+            config,
+            fast: traces.clone(),
+            slow: traces,
+        })
+    }
+
+    /// Fast eligibility trace, (post, pre)
+    pub fn fast_trace(&self) -> &Tensor {
+        &self.fast
+    }
+
+    /// Slow eligibility trace, (post, pre)
+    pub fn slow_trace(&self) -> &Tensor {
+        &self.slow
+    }
+}
+
+/// Fraction of the gap to its input a trace with time constant `tau` closes in `dt`
+fn trace_rate(dt: f32, tau: f32) -> f64 {
+    1.0 - (-(dt as f64) / tau as f64).exp()
+}
+
+impl SynapseOps for MultiTraceCSDP {
+    fn forward(&self, pre: &Tensor) -> CandleResult<Tensor> {
+        self.weights.matmul(pre)?.broadcast_add(&self.biases)
+    }
+
+    fn dense_weights(&self) -> Option<(&Tensor, &Tensor)> {
+        Some((&self.weights, &self.biases))
+    }
+
+    fn update_weights(
+        &mut self,
+        pre_activity: &Tensor,
+        post_layer: &dyn Layer,
+        dt: f32,
+    ) -> CandleResult<()> {
+        self.update_weights_scaled(pre_activity, post_layer, dt, 1.0)
+    }
+
+    fn update_weights_scaled(
+        &mut self,
+        pre_activity: &Tensor,
+        post_layer: &dyn Layer,
+        dt: f32,
+        scale: f32,
+    ) -> CandleResult<()> {
+        let pre = pre_activity;
+        let batch_size = pre.dims().get(1).copied().unwrap_or(1);
+        let mod_avg = post_layer
+            .get_mod_signal()
+            .affine(1.0 / batch_size as f64, 0.0)?;
+        let candidate = mod_avg.matmul(&pre.t()?)?;
+
+        // A step `scale` times as long moves the traces further toward the candidate
+        let step_dt = dt * scale;
+        let fast_rate = trace_rate(step_dt, self.config.tau_fast);
+        let slow_rate = trace_rate(step_dt, self.config.tau_slow);
+        self.fast = self
+            .fast
+            .add(&candidate.sub(&self.fast)?.affine(fast_rate, 0.0)?)?;
+        self.slow = self
+            .slow
+            .add(&candidate.sub(&self.slow)?.affine(slow_rate, 0.0)?)?;
+
+        let scale = scale as f64;
+        let dw = self
+            .fast
+            .affine(self.config.fast_weight as f64 * scale, 0.0)?
+            .add(
+                &self
+                    .slow
+                    .affine(self.config.slow_weight as f64 * scale, 0.0)?,
+            )?;
+        self.weights = self
+            .weights
+            .affine((1.0 - LAMBDA_D).powf(scale), 0.0)?
+            .add(&dw)?;
+
+        let db = mod_avg.sum_keepdim(1)?.affine(scale, 0.0)?;
+        self.biases = self.biases.add(&db)?;
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        if let (Ok(fast), Ok(slow)) = (self.fast.zeros_like(), self.slow.zeros_like()) {
+            self.fast = fast;
+            self.slow = slow;
+        }
+    }
+
+    fn plastic_tensors_mut(&mut self) -> Vec<&mut Tensor> {
+        vec![&mut self.weights, &mut self.biases]
+    }
+
+    fn weight_stats(&self) -> CandleResult<WeightStats> {
+        CSDP {
+            weights: self.weights.clone(),
+            biases: self.biases.clone(),
+        }
+        .weight_stats()
+    }
+
+    fn get_state(&self) -> CandleResult<HashMap<String, Tensor>> {
+        let mut state = HashMap::new();
+        state.insert("weights".to_string(), self.weights.clone());
+        state.insert("biases".to_string(), self.biases.clone());
+        Ok(state)
+    }
+
+    fn set_state(&mut self, state: &HashMap<String, Tensor>) -> CandleResult<()> {
+        let get = |key: &str| {
+            state.get(key).cloned().ok_or_else(|| {
+                candle_core::Error::Msg(format!("{} tensor missing from state", key))
+            })
+        };
+        self.weights = get("weights")?;
+        self.biases = get("biases")?;
+        self.fast = self.weights.zeros_like()?;
+        self.slow = self.weights.zeros_like()?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn SynapseOps> {
+        Box::new(self.clone())
+    }
+}
@@ -1,7 +1,13 @@
 pub mod csdp;
+pub mod eligibility;
+pub mod offload;
+pub mod predictive;
+pub mod sparse;
+pub mod topographic;
 
 use crate::layer::Layer;
 use candle_core::{Result as CandleResult, Tensor};
+use serde::{Deserialize, Serialize};
 
 #[allow(dead_code)]
 pub trait SynapseUpdate: Send + Sync {
@@ -19,14 +25,55 @@ pub trait SynapseOps: Send + Sync {
     /// Forward pass: compute post-synaptic input from pre-synaptic activity
     fn forward(&self, pre: &Tensor) -> CandleResult<Tensor>;
 
-    /// Update weights based on pre and post activity
+    /// Forward pass that only propagates the pre-synaptic neurons in `active` (u32 row
+    /// indices of `pre`); every other row of `pre` must be zero. Used by event-driven
+    /// simulation; the default ignores `active` and runs the dense `forward`.
+    fn forward_active(&self, pre: &Tensor, active: &Tensor) -> CandleResult<Tensor> {
+        let _ = active;
+        self.forward(pre)
+    }
+
+    /// The (post, pre) weights and (post, 1) biases when `forward` is exactly
+    /// `weights.matmul(pre) + biases`, so synapses reading the same layer can share one matmul.
+    /// The default, None, keeps the synapse out of that fusion.
+    fn dense_weights(&self) -> Option<(&Tensor, &Tensor)> {
+        None
+    }
+
+    /// Update weights based on pre and post activity. The post layer is only read, so updates
+    /// of different synapses can run concurrently.
     fn update_weights(
         &mut self,
         pre_activity: &Tensor,
-        post_layer: &mut Box<dyn Layer>,
+        post_layer: &dyn Layer,
         dt: f32,
     ) -> CandleResult<()>;
 
+    /// `update_weights` for a step `scale` times as long as the model's nominal timestep, as
+    /// taken by adaptive timestep control, so plasticity per unit of simulated time stays the
+    /// same. The default ignores `scale`, for rules that already scale by `dt` or learn per step.
+    fn update_weights_scaled(
+        &mut self,
+        pre_activity: &Tensor,
+        post_layer: &dyn Layer,
+        dt: f32,
+        scale: f32,
+    ) -> CandleResult<()> {
+        let _ = scale;
+        self.update_weights(pre_activity, post_layer, dt)
+    }
+
+    /// Forget per-sequence state, such as the previous input of a predictive synapse. Called
+    /// when the model is reset; weights are kept.
+    fn reset(&mut self) {}
+
+    /// The tensors the plasticity rule changes, in a fixed order, for mechanisms applied on
+    /// top of any rule such as weight consolidation. The default exposes none, which leaves the
+    /// synapse out of them.
+    fn plastic_tensors_mut(&mut self) -> Vec<&mut Tensor> {
+        Vec::new()
+    }
+
     /// Get weight statistics for visualization
     fn weight_stats(&self) -> CandleResult<WeightStats>;
 
@@ -35,10 +82,13 @@ pub trait SynapseOps: Send + Sync {
 
     /// Restore the synapse state from named tensors
     fn set_state(&mut self, state: &std::collections::HashMap<String, Tensor>) -> CandleResult<()>;
+
+    /// Independent copy of the synapse
+    fn box_clone(&self) -> Box<dyn SynapseOps>;
 }
 
 /// Statistics about synapse weights for visualization
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct WeightStats {
     pub mean: f32,
@@ -60,7 +110,13 @@ pub struct SynapseMetadata {
     pub pre_layer: LayerId,
     pub post_layer: LayerId,
     pub synapse_type: String,
+    /// plasticity is applied; false freezes the weights
     pub is_learning: bool,
+    /// the synapse propagates activity; false ablates the connection, which then neither
+    /// feeds its post layer nor learns
+    pub enabled: bool,
+    /// scale of the synapse's input to its post layer; 1 unless configured, e.g. for feedback
+    pub gain: f32,
 }
 
 /// Wrapper for a synapse connection with metadata
@@ -69,6 +125,15 @@ pub struct SynapseConnection {
     pub synapse: Box<dyn SynapseOps>,
 }
 
+impl Clone for SynapseConnection {
+    fn clone(&self) -> Self {
+        Self {
+            metadata: self.metadata.clone(),
+            synapse: self.synapse.box_clone(),
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub struct Synapse {
     pub pre: usize,
@@ -88,7 +153,7 @@ impl Synapse {
         device: &candle_core::Device,
     ) -> CandleResult<Self> {
         // initialize weights small random
-        let w = Tensor::randn(0.0f32, 0.1, (post_size, pre_size), device)?;
+        let w = crate::seed::rand_normal(0.0, 0.1, (post_size, pre_size), device)?;
         Ok(Self {
             pre: pre_idx,
             post: post_idx,
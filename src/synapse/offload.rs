@@ -0,0 +1,120 @@
+//! Synapses kept in host memory.
+//!
+//! An [`Offloaded`] synapse stores the tensors of the synapse it wraps on a host device (the
+//! CPU) while its post layer stays on an accelerator, freeing accelerator memory for the hot
+//! parts of a large model. The forward pass runs on the host: the pre-synaptic activity is
+//! copied over and the post-synaptic input copied back, which moves `(neurons, batch)`
+//! activity rather than the weights. A plasticity update needs the post layer's modulatory
+//! signal, so it copies the state to the post layer's device, updates it there and copies
+//! it back; offloading pays off for frozen or rarely-updated synapses.
+
+use super::{SynapseOps, WeightStats};
+use crate::layer::Layer;
+use candle_core::{Device, Result as CandleResult, Tensor};
+use std::collections::HashMap;
+
+pub struct Offloaded {
+    inner: Box<dyn SynapseOps>,
+    /// where the wrapped synapse's tensors live
+    host: Device,
+    /// where its post layer lives, and its inputs and outputs are expected
+    compute: Device,
+}
+
+fn to_device(
+    state: HashMap<String, Tensor>,
+    device: &Device,
+) -> CandleResult<HashMap<String, Tensor>> {
+    state
+        .into_iter()
+        .map(|(key, tensor)| Ok((key, tensor.to_device(device)?)))
+        .collect()
+}
+
+impl Offloaded {
+    /// Move the state of `inner` to `host`; activity enters and leaves on `compute`
+    pub fn new(
+        mut inner: Box<dyn SynapseOps>,
+        host: &Device,
+        compute: &Device,
+    ) -> CandleResult<Self> {
+        inner.set_state(&to_device(inner.get_state()?, host)?)?;
+        Ok(Self {
+            inner,
+            host: host.clone(),
+            compute: compute.clone(),
+        })
+    }
+
+    pub fn host(&self) -> &Device {
+        &self.host
+    }
+}
+
+impl SynapseOps for Offloaded {
+    fn forward(&self, pre: &Tensor) -> CandleResult<Tensor> {
+        self.inner
+            .forward(&pre.to_device(&self.host)?)?
+            .to_device(&self.compute)
+    }
+
+    fn forward_active(&self, pre: &Tensor, active: &Tensor) -> CandleResult<Tensor> {
+        self.inner
+            .forward_active(&pre.to_device(&self.host)?, &active.to_device(&self.host)?)?
+            .to_device(&self.compute)
+    }
+
+    fn update_weights(
+        &mut self,
+        pre_activity: &Tensor,
+        post_layer: &dyn Layer,
+        dt: f32,
+    ) -> CandleResult<()> {
+        self.update_weights_scaled(pre_activity, post_layer, dt, 1.0)
+    }
+
+    fn update_weights_scaled(
+        &mut self,
+        pre_activity: &Tensor,
+        post_layer: &dyn Layer,
+        dt: f32,
+        scale: f32,
+    ) -> CandleResult<()> {
+        let state = to_device(self.inner.get_state()?, &self.compute)?;
+        self.inner.set_state(&state)?;
+        self.inner
+            .update_weights_scaled(pre_activity, post_layer, dt, scale)?;
+        let state = to_device(self.inner.get_state()?, &self.host)?;
+        self.inner.set_state(&state)
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset()
+    }
+
+    fn plastic_tensors_mut(&mut self) -> Vec<&mut Tensor> {
+        self.inner.plastic_tensors_mut()
+    }
+
+    fn weight_stats(&self) -> CandleResult<WeightStats> {
+        self.inner.weight_stats()
+    }
+
+    /// The state on the host device
+    fn get_state(&self) -> CandleResult<HashMap<String, Tensor>> {
+        self.inner.get_state()
+    }
+
+    /// Restore the state from tensors on any device; they are moved to the host
+    fn set_state(&mut self, state: &HashMap<String, Tensor>) -> CandleResult<()> {
+        self.inner.set_state(&to_device(state.clone(), &self.host)?)
+    }
+
+    fn box_clone(&self) -> Box<dyn SynapseOps> {
+        Box::new(Self {
+            inner: self.inner.box_clone(),
+            host: self.host.clone(),
+            compute: self.compute.clone(),
+        })
+    }
+}
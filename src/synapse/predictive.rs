@@ -0,0 +1,141 @@
+use crate::layer::Layer;
+
+use super::{SynapseOps, WeightStats};
+use candle_core::{DType, Device, Result as CandleResult, Tensor};
+use std::collections::HashMap;
+
+/// Synapse that learns to predict the next timestep's activity of its pre-synaptic layer.
+///
+/// `forward` maps the current pre-synaptic activity `x_t` to a prediction `W x_t + b` of
+/// `x_{t+1}`, which is fed into a [`crate::layer::predictive::PredictionLayer`] of the same
+/// size. Learning is a local delta rule on the prediction error: once `x_{t+1}` arrives,
+/// `W += lr * (x_{t+1} - W x_t - b) x_t^T`, averaged over the batch. No labels or rewards are
+/// involved, so it can be trained on raw state sequences ahead of the CSDP layers.
+#[derive(Clone)]
+pub struct PredictiveSynapse {
+    pub weights: Tensor,
+    pub biases: Tensor,
+    pub learning_rate: f32,
+    /// pre-synaptic activity of the previous update, the input of the prediction being scored
+    previous: Option<Tensor>,
+    /// prediction error of the last update, (size, batch)
+    error: Option<Tensor>,
+}
+
+impl PredictiveSynapse {
+    pub fn new(size: usize, learning_rate: f32, device: &Device) -> CandleResult<Self> {
+        // Small weights: the prediction starts close to the bias, i.e. close to zero
+        let w_bound = 0.1f32 / (size as f32).sqrt();
+        Ok(Self {
+            weights: crate::seed::rand_uniform(-w_bound, w_bound, (size, size), device)?,
+            biases: Tensor::zeros((size, 1), DType::F32, device)?,
+            learning_rate,
+            previous: None,
+            error: None,
+        })
+    }
+
+    /// Mean squared prediction error of the last update, if there has been one
+    pub fn prediction_error(&self) -> CandleResult<Option<f32>> {
+        self.error
+            .as_ref()
+            .map(|e| e.sqr()?.mean_all()?.to_scalar::<f32>())
+            .transpose()
+    }
+}
+
+impl SynapseOps for PredictiveSynapse {
+    fn forward(&self, pre: &Tensor) -> CandleResult<Tensor> {
+        self.weights.matmul(pre)?.broadcast_add(&self.biases)
+    }
+
+    fn dense_weights(&self) -> Option<(&Tensor, &Tensor)> {
+        Some((&self.weights, &self.biases))
+    }
+
+    fn update_weights(
+        &mut self,
+        pre_activity: &Tensor,
+        _post_layer: &dyn Layer,
+        _dt: f32,
+    ) -> CandleResult<()> {
+        let current = pre_activity.clone();
+        // The first step of a sequence (or a new batch size) has nothing to score yet
+        let Some(previous) = self
+            .previous
+            .replace(current.clone())
+            .filter(|p| p.shape() == current.shape())
+        else {
+            return Ok(());
+        };
+
+        let batch_size = current.dims().get(1).copied().unwrap_or(1);
+        let error = current.sub(&self.forward(&previous)?)?;
+        let scaled = error.affine(self.learning_rate as f64 / batch_size as f64, 0.0)?;
+        self.weights = self.weights.add(&scaled.matmul(&previous.t()?)?)?;
+        self.biases = self.biases.add(&scaled.sum_keepdim(1)?)?;
+        self.error = Some(error);
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.previous = None;
+        self.error = None;
+    }
+
+    fn plastic_tensors_mut(&mut self) -> Vec<&mut Tensor> {
+        vec![&mut self.weights, &mut self.biases]
+    }
+
+    fn weight_stats(&self) -> CandleResult<WeightStats> {
+        let weights_vec = self.weights.flatten_all()?.to_vec1::<f32>()?;
+        let num_weights = weights_vec.len();
+        if num_weights == 0 {
+            return Ok(WeightStats {
+                mean: 0.0,
+                std: 0.0,
+                min: 0.0,
+                max: 0.0,
+                num_weights: 0,
+            });
+        }
+
+        let mean = weights_vec.iter().sum::<f32>() / num_weights as f32;
+        let variance =
+            weights_vec.iter().map(|&w| (w - mean).powi(2)).sum::<f32>() / num_weights as f32;
+        Ok(WeightStats {
+            mean,
+            std: variance.sqrt(),
+            min: weights_vec.iter().cloned().fold(f32::INFINITY, f32::min),
+            max: weights_vec
+                .iter()
+                .cloned()
+                .fold(f32::NEG_INFINITY, f32::max),
+            num_weights,
+        })
+    }
+
+    fn get_state(&self) -> CandleResult<HashMap<String, Tensor>> {
+        let mut state = HashMap::new();
+        state.insert("weights".to_string(), self.weights.clone());
+        state.insert("biases".to_string(), self.biases.clone());
+        Ok(state)
+    }
+
+    fn set_state(&mut self, state: &HashMap<String, Tensor>) -> CandleResult<()> {
+        let get = |key: &str| {
+            state.get(key).cloned().ok_or_else(|| {
+                candle_core::Error::Msg(format!("{} tensor missing from state", key))
+            })
+        };
+        self.weights = get("weights")?;
+        self.biases = get("biases")?;
+        self.previous = None;
+        self.error = None;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn SynapseOps> {
+        Box::new(self.clone())
+    }
+}
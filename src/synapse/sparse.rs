@@ -0,0 +1,263 @@
+use crate::layer::Layer;
+
+use super::csdp::LAMBDA_D;
+use super::{SynapseOps, WeightStats};
+use candle_core::{DType, Device, Result as CandleResult, Tensor};
+use std::collections::HashMap;
+
+/// Block-sparse CSDP synapse for very large layers.
+///
+/// The weight matrix is tiled into `block_size` x `block_size` blocks and only a random
+/// `density` fraction of the blocks in every block row is stored, each as a small dense tensor.
+/// `forward` multiplies only the stored blocks and the plasticity rule only updates them, so
+/// memory and compute scale with the number of stored weights instead of `pre * post`. Every
+/// block row keeps at least one block, so no post-synaptic neuron is left without input.
+#[derive(Clone)]
+pub struct SparseCSDP {
+    pre_size: usize,
+    post_size: usize,
+    block_size: usize,
+    /// (row block, column block) of every stored block, sorted by row block
+    layout: Vec<(usize, usize)>,
+    /// dense weights of each block in `layout`
+    blocks: Vec<Tensor>,
+    /// biases are kept in a separate tensor
+    pub biases: Tensor,
+}
+
+impl SparseCSDP {
+    pub fn new(
+        pre_size: usize,
+        post_size: usize,
+        density: f32,
+        block_size: usize,
+        device: &Device,
+    ) -> CandleResult<Self> {
+        let valid_density = density > 0.0 && density <= 1.0;
+        if block_size == 0 || !valid_density {
+            return Err(candle_core::Error::Msg(format!(
+                "sparse synapse needs block_size > 0 and density in (0, 1], got {} and {}",
+                block_size, density
+            )));
+        }
+        let row_blocks = post_size.div_ceil(block_size);
+        let col_blocks = pre_size.div_ceil(block_size);
+        let per_row = ((density * col_blocks as f32).round() as usize).clamp(1, col_blocks);
+
+        let mut layout = Vec::with_capacity(row_blocks * per_row);
+        let mut rng = crate::seed::rng();
+        for r in 0..row_blocks {
+            let mut cols = rand::seq::index::sample(&mut rng, col_blocks, per_row).into_vec();
+            cols.sort_unstable();
+            layout.extend(cols.into_iter().map(|c| (r, c)));
+        }
+
+        // Same symmetric init as the dense CSDP, scaled by the fan-in actually stored
+        let fan_in = (per_row * block_size).min(pre_size);
+        let w_bound = 2.0f32 / (fan_in as f32).sqrt();
+        let blocks = layout
+            .iter()
+            .map(|&(r, c)| {
+                let shape = (
+                    block_len(r, block_size, post_size),
+                    block_len(c, block_size, pre_size),
+                );
+                crate::seed::rand_uniform(-w_bound, w_bound, shape, device)
+            })
+            .collect::<CandleResult<Vec<_>>>()?;
+
+        Ok(Self {
+            pre_size,
+            post_size,
+            block_size,
+            layout,
+            blocks,
+            biases: Tensor::zeros((post_size, 1), DType::F32, device)?,
+        })
+    }
+
+    /// Fraction of the dense weight matrix that is stored
+    pub fn density(&self) -> f32 {
+        let stored: usize = self.blocks.iter().map(|b| b.elem_count()).sum();
+        stored as f32 / (self.pre_size * self.post_size).max(1) as f32
+    }
+}
+
+/// Number of neurons in block `index` of a `total`-neuron side; the last block may be short
+fn block_len(index: usize, block_size: usize, total: usize) -> usize {
+    block_size.min(total - index * block_size)
+}
+
+impl SynapseOps for SparseCSDP {
+    fn forward(&self, pre: &Tensor) -> CandleResult<Tensor> {
+        let batch_size = pre.dim(1)?;
+        let bs = self.block_size;
+        let mut blocks = self.layout.iter().zip(&self.blocks).peekable();
+        let row_blocks = self.post_size.div_ceil(bs);
+        let mut rows = Vec::with_capacity(row_blocks);
+        for r in 0..row_blocks {
+            let mut sum: Option<Tensor> = None;
+            while let Some((&(_, c), weights)) = blocks.next_if(|((br, _), _)| *br == r) {
+                let pre_block = pre.narrow(0, c * bs, block_len(c, bs, self.pre_size))?;
+                let part = weights.matmul(&pre_block)?;
+                sum = Some(match sum {
+                    Some(sum) => sum.add(&part)?,
+                    None => part,
+                });
+            }
+            rows.push(match sum {
+                Some(sum) => sum,
+                None => {
+                    let height = block_len(r, bs, self.post_size);
+                    Tensor::zeros((height, batch_size), DType::F32, pre.device())?
+                }
+            });
+        }
+        Tensor::cat(&rows, 0)?.broadcast_add(&self.biases)
+    }
+
+    fn update_weights(
+        &mut self,
+        pre_activity: &Tensor,
+        post_layer: &dyn Layer,
+        dt: f32,
+    ) -> CandleResult<()> {
+        self.update_weights_scaled(pre_activity, post_layer, dt, 1.0)
+    }
+
+    fn update_weights_scaled(
+        &mut self,
+        pre_activity: &Tensor,
+        post_layer: &dyn Layer,
+        _dt: f32,
+        scale: f32,
+    ) -> CandleResult<()> {
+        let pre = pre_activity;
+        let batch_size = pre.dims().get(1).copied().unwrap_or(1);
+        let mod_avg = post_layer
+            .get_mod_signal()
+            .affine(scale as f64 / (batch_size as f64), 0.0)?;
+        let decay = (1.0 - LAMBDA_D).powf(scale as f64);
+
+        // Only stored blocks are updated, so the sparsity pattern never fills in
+        let bs = self.block_size;
+        for (&(r, c), weights) in self.layout.iter().zip(self.blocks.iter_mut()) {
+            let mod_block = mod_avg.narrow(0, r * bs, block_len(r, bs, self.post_size))?;
+            let pre_block = pre.narrow(0, c * bs, block_len(c, bs, self.pre_size))?;
+            let dw_avg = mod_block.matmul(&pre_block.t()?)?;
+            *weights = weights.affine(decay, 0.0)?.add(&dw_avg)?;
+        }
+
+        // biases are treated as connections to a neuron that is always firing every timestep
+        let db_avg = mod_avg.sum_keepdim(1)?;
+        self.biases = self.biases.add(&db_avg)?;
+
+        Ok(())
+    }
+
+    fn plastic_tensors_mut(&mut self) -> Vec<&mut Tensor> {
+        self.blocks
+            .iter_mut()
+            .chain(std::iter::once(&mut self.biases))
+            .collect()
+    }
+
+    fn weight_stats(&self) -> CandleResult<WeightStats> {
+        let mut weights_vec = Vec::new();
+        for block in &self.blocks {
+            weights_vec.extend(block.flatten_all()?.to_vec1::<f32>()?);
+        }
+        let num_weights = weights_vec.len();
+        if num_weights == 0 {
+            return Ok(WeightStats {
+                mean: 0.0,
+                std: 0.0,
+                min: 0.0,
+                max: 0.0,
+                num_weights: 0,
+            });
+        }
+
+        let mean = weights_vec.iter().sum::<f32>() / num_weights as f32;
+        let variance =
+            weights_vec.iter().map(|&w| (w - mean).powi(2)).sum::<f32>() / num_weights as f32;
+        Ok(WeightStats {
+            mean,
+            std: variance.sqrt(),
+            min: weights_vec.iter().cloned().fold(f32::INFINITY, f32::min),
+            max: weights_vec
+                .iter()
+                .cloned()
+                .fold(f32::NEG_INFINITY, f32::max),
+            num_weights,
+        })
+    }
+
+    /// `biases`, `layout` ((blocks, 2) u32 of row/column block indices), `block_size` and one
+    /// `block_<n>` tensor per stored block
+    fn get_state(&self) -> CandleResult<HashMap<String, Tensor>> {
+        let device = self.biases.device();
+        let layout: Vec<u32> = self
+            .layout
+            .iter()
+            .flat_map(|&(r, c)| [r as u32, c as u32])
+            .collect();
+
+        let mut state = HashMap::new();
+        state.insert("biases".to_string(), self.biases.clone());
+        state.insert(
+            "layout".to_string(),
+            Tensor::from_vec(layout, (self.layout.len(), 2), device)?,
+        );
+        state.insert(
+            "block_size".to_string(),
+            Tensor::new(&[self.block_size as u32], device)?,
+        );
+        for (i, block) in self.blocks.iter().enumerate() {
+            state.insert(format!("block_{:06}", i), block.clone());
+        }
+        Ok(state)
+    }
+
+    fn set_state(&mut self, state: &HashMap<String, Tensor>) -> CandleResult<()> {
+        let get = |key: &str| {
+            state.get(key).ok_or_else(|| {
+                candle_core::Error::Msg(format!("{} tensor missing from state", key))
+            })
+        };
+
+        let block_size = get("block_size")?.to_vec1::<u32>()?[0] as usize;
+        let layout: Vec<(usize, usize)> = get("layout")?
+            .to_vec2::<u32>()?
+            .into_iter()
+            .map(|rc| (rc[0] as usize, rc[1] as usize))
+            .collect();
+        let blocks = (0..layout.len())
+            .map(|i| get(&format!("block_{:06}", i)).cloned())
+            .collect::<CandleResult<Vec<_>>>()?;
+
+        let row_blocks = self.post_size.div_ceil(block_size.max(1));
+        let col_blocks = self.pre_size.div_ceil(block_size.max(1));
+        if block_size == 0
+            || !layout.is_sorted()
+            || layout
+                .iter()
+                .any(|&(r, c)| r >= row_blocks || c >= col_blocks)
+        {
+            return Err(candle_core::Error::Msg(format!(
+                "sparse layout does not fit a {}x{} synapse",
+                self.post_size, self.pre_size
+            )));
+        }
+
+        self.biases = get("biases")?.clone();
+        self.block_size = block_size;
+        self.layout = layout;
+        self.blocks = blocks;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn SynapseOps> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,267 @@
+//! Distance-dependent connectivity between layers with a [`Topography`].
+//!
+//! A [`Topographic`] synapse wraps a dense synapse and connects each pre-synaptic neuron to
+//! each post-synaptic neuron with a probability falling off with the distance between their
+//! coordinates, e.g. to give every hidden neuron a local receptive field on an input grid
+//! without weight sharing. The connections are drawn once when the synapse is built and kept
+//! as a mask: weights outside it start at zero and are zeroed again after every plasticity
+//! update, so learning never grows new connections.
+//!
+//! [`Topography`]: crate::layer::topography::Topography
+
+use super::{SynapseOps, WeightStats};
+use crate::layer::Layer;
+use crate::layer::topography::distance;
+use candle_core::{DType, Result as CandleResult, Tensor};
+use rand::Rng;
+use std::collections::HashMap;
+
+/// How connection probability and initial weight fall off with distance. Distances are in
+/// the unit coordinates of [`crate::layer::topography`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistanceConnectivity {
+    /// probability of a connection between neurons at the same coordinates
+    pub peak_probability: f32,
+    /// width of the Gaussian fall-off of the probability
+    pub sigma: f32,
+    /// no connections between neurons further apart than this
+    pub radius: Option<f32>,
+    /// scale the initial weights by a Gaussian of the distance of this width; without it
+    /// every connection is drawn with the same scale
+    pub weight_sigma: Option<f32>,
+}
+
+impl DistanceConnectivity {
+    /// Connection probability `peak_probability * exp(-d² / 2σ²)`, unscaled weights
+    pub fn gaussian(peak_probability: f32, sigma: f32) -> Self {
+        Self {
+            peak_probability,
+            sigma,
+            radius: None,
+            weight_sigma: None,
+        }
+    }
+
+    /// Every pre-synaptic neuron within `radius` connected and no other
+    pub fn receptive_field(radius: f32) -> Self {
+        Self {
+            peak_probability: 1.0,
+            sigma: f32::INFINITY,
+            radius: Some(radius),
+            weight_sigma: None,
+        }
+    }
+
+    pub fn with_weight_sigma(mut self, weight_sigma: f32) -> Self {
+        self.weight_sigma = Some(weight_sigma);
+        self
+    }
+
+    /// Why the profile can't be used, if it can't
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |v: f32| v > 0.0 && !v.is_nan();
+        if !(positive(self.peak_probability) && self.peak_probability <= 1.0) {
+            return Err(format!(
+                "peak probability must be in (0, 1], got {}",
+                self.peak_probability
+            ));
+        }
+        if !positive(self.sigma) {
+            return Err(format!("sigma must be positive, got {}", self.sigma));
+        }
+        if let Some(radius) = self.radius
+            && !positive(radius)
+        {
+            return Err(format!("radius must be positive, got {}", radius));
+        }
+        if let Some(weight_sigma) = self.weight_sigma
+            && !positive(weight_sigma)
+        {
+            return Err(format!(
+                "weight sigma must be positive, got {}",
+                weight_sigma
+            ));
+        }
+        Ok(())
+    }
+
+    /// Probability of a connection between neurons `d` apart
+    pub fn probability(&self, d: f32) -> f32 {
+        if self.radius.is_some_and(|radius| d > radius) {
+            return 0.0;
+        }
+        self.peak_probability * gaussian(d, self.sigma)
+    }
+
+    /// Factor on the initial weight of a connection between neurons `d` apart
+    pub fn weight_scale(&self, d: f32) -> f32 {
+        self.weight_sigma.map_or(1.0, |sigma| gaussian(d, sigma))
+    }
+}
+
+fn gaussian(d: f32, sigma: f32) -> f32 {
+    (-d * d / (2.0 * sigma * sigma)).exp()
+}
+
+/// A synapse restricted to the connections drawn from a [`DistanceConnectivity`]
+pub struct Topographic {
+    inner: Box<dyn SynapseOps>,
+    /// (post, pre), 1 where a connection exists and 0 elsewhere
+    mask: Tensor,
+}
+
+impl Topographic {
+    /// Draw the connections between neurons at `pre` and `post` coordinates and apply them
+    /// to the dense `weights` of `inner`. Every post-synaptic neuron keeps at least its
+    /// nearest pre-synaptic neuron, and its weights are scaled up by `sqrt(pre / fan_in)`
+    /// for the inputs it lost, as a sparse CSDP scales its init by the stored fan-in.
+    pub fn new(
+        mut inner: Box<dyn SynapseOps>,
+        pre: &[(f32, f32)],
+        post: &[(f32, f32)],
+        connectivity: &DistanceConnectivity,
+    ) -> CandleResult<Self> {
+        let mut state = inner.get_state()?;
+        let weights = state.get("weights").ok_or_else(|| {
+            candle_core::Error::Msg("distance-dependent connectivity needs dense weights".into())
+        })?;
+        if weights.dims() != [post.len(), pre.len()] {
+            return Err(candle_core::Error::Msg(format!(
+                "weights of shape {:?} don't match {} post and {} pre coordinates",
+                weights.dims(),
+                post.len(),
+                pre.len()
+            )));
+        }
+
+        let mut rng = crate::seed::rng();
+        let mut mask = vec![0.0f32; post.len() * pre.len()];
+        let mut init = vec![0.0f32; post.len() * pre.len()];
+        for (i, &p) in post.iter().enumerate() {
+            let row = i * pre.len();
+            let distances: Vec<f32> = pre.iter().map(|&q| distance(p, q)).collect();
+            for (j, &d) in distances.iter().enumerate() {
+                if rng.gen_range(0.0..1.0f32) < connectivity.probability(d) {
+                    mask[row + j] = 1.0;
+                }
+            }
+            if let Some(nearest) =
+                (0..pre.len()).min_by(|&a, &b| distances[a].total_cmp(&distances[b]))
+            {
+                mask[row + nearest] = 1.0;
+            }
+            let fan_in = mask[row..row + pre.len()].iter().sum::<f32>().max(1.0);
+            let boost = (pre.len() as f32 / fan_in).sqrt();
+            for (j, &d) in distances.iter().enumerate() {
+                init[row + j] = mask[row + j] * boost * connectivity.weight_scale(d);
+            }
+        }
+
+        let device = weights.device();
+        let mask = Tensor::from_vec(mask, (post.len(), pre.len()), device)?;
+        let init = Tensor::from_vec(init, (post.len(), pre.len()), device)?;
+        let weights = weights.mul(&init)?;
+        state.insert("weights".to_string(), weights);
+        inner.set_state(&state)?;
+        Ok(Self { inner, mask })
+    }
+
+    /// The connections, (post, pre) with 1 where one exists
+    pub fn mask(&self) -> &Tensor {
+        &self.mask
+    }
+
+    /// Fraction of the possible connections that exist
+    pub fn density(&self) -> CandleResult<f32> {
+        self.mask.mean_all()?.to_scalar::<f32>()
+    }
+
+    /// Zero the weights outside the mask
+    fn apply_mask(&mut self) -> CandleResult<()> {
+        let mut state = self.inner.get_state()?;
+        if let Some(weights) = state.get("weights") {
+            let masked = weights.mul(&self.mask.to_device(weights.device())?)?;
+            state.insert("weights".to_string(), masked);
+            self.inner.set_state(&state)?;
+        }
+        Ok(())
+    }
+}
+
+impl SynapseOps for Topographic {
+    fn forward(&self, pre: &Tensor) -> CandleResult<Tensor> {
+        self.inner.forward(pre)
+    }
+
+    fn forward_active(&self, pre: &Tensor, active: &Tensor) -> CandleResult<Tensor> {
+        self.inner.forward_active(pre, active)
+    }
+
+    fn dense_weights(&self) -> Option<(&Tensor, &Tensor)> {
+        self.inner.dense_weights()
+    }
+
+    fn update_weights(
+        &mut self,
+        pre_activity: &Tensor,
+        post_layer: &dyn Layer,
+        dt: f32,
+    ) -> CandleResult<()> {
+        self.update_weights_scaled(pre_activity, post_layer, dt, 1.0)
+    }
+
+    fn update_weights_scaled(
+        &mut self,
+        pre_activity: &Tensor,
+        post_layer: &dyn Layer,
+        dt: f32,
+        scale: f32,
+    ) -> CandleResult<()> {
+        self.inner
+            .update_weights_scaled(pre_activity, post_layer, dt, scale)?;
+        self.apply_mask()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset()
+    }
+
+    fn plastic_tensors_mut(&mut self) -> Vec<&mut Tensor> {
+        self.inner.plastic_tensors_mut()
+    }
+
+    fn weight_stats(&self) -> CandleResult<WeightStats> {
+        self.inner.weight_stats()
+    }
+
+    /// The wrapped synapse's state plus the mask, so a loaded model keeps its connections.
+    /// The mask is stored as u8, as structure rather than learned values.
+    fn get_state(&self) -> CandleResult<HashMap<String, Tensor>> {
+        let mut state = self.inner.get_state()?;
+        state.insert("mask".to_string(), self.mask.to_dtype(DType::U8)?);
+        Ok(state)
+    }
+
+    /// Restore the state, and the connections if `state` has a mask
+    fn set_state(&mut self, state: &HashMap<String, Tensor>) -> CandleResult<()> {
+        let mut state = state.clone();
+        if let Some(mask) = state.remove("mask") {
+            if mask.dims() != self.mask.dims() {
+                return Err(candle_core::Error::Msg(format!(
+                    "connection mask of shape {:?} doesn't fit {:?}",
+                    mask.dims(),
+                    self.mask.dims()
+                )));
+            }
+            self.mask = mask.to_dtype(DType::F32)?;
+        }
+        self.inner.set_state(&state)
+    }
+
+    fn box_clone(&self) -> Box<dyn SynapseOps> {
+        Box::new(Self {
+            inner: self.inner.box_clone(),
+            mask: self.mask.clone(),
+        })
+    }
+}
@@ -0,0 +1,301 @@
+//! Readout calibration of a checkpointed model.
+//!
+//! Loads a CSDP `Model` checkpoint and a labelled dataset, runs the frozen model over the data
+//! and fits only the output synapses to the spike rates of the layers feeding the output layer
+//! (see `models::calibration`), by ridge regression or the delta rule. It prints the accuracy
+//! of the fitted linear readout and of the spiking output layer before and after calibration,
+//! on `--test-data` if given and on the training data otherwise. With `--output`, the
+//! calibrated model is saved.
+//!
+//! The datasets are `xor` or CSV files with one sample per row: the feature columns followed
+//! by an integer class label in the last column.
+
+use candle_core::{Device, Tensor};
+use clap::{Parser, ValueEnum};
+use custom_framework::dataset::xor::XorDataset;
+use custom_framework::models::Model;
+use custom_framework::models::calibration::{LinearReadout, ReadoutFit, readout_features};
+use custom_framework::raster;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Method {
+    /// Ridge regression, solved in closed form
+    LeastSquares,
+    /// Delta rule over `--epochs` passes
+    Delta,
+}
+
+#[derive(Parser)]
+#[command(about = "Fit the output synapses of a frozen model to a labelled dataset")]
+struct Args {
+    /// Model checkpoint (`.safetensors`) to calibrate
+    #[arg(long)]
+    checkpoint: PathBuf,
+    /// `xor`, or a CSV file whose last column is the class label, to fit the readout on
+    #[arg(long)]
+    data: String,
+    /// Held-out dataset in the same format to report accuracy on
+    #[arg(long)]
+    test_data: Option<String>,
+    /// The CSV files have no header row
+    #[arg(long)]
+    no_header: bool,
+    /// Number of classes (output neurons); defaults to the largest label + 1
+    #[arg(long)]
+    num_classes: Option<usize>,
+    /// Hidden layer sizes, e.g. `64,64`
+    #[arg(long, value_delimiter = ',', default_value = "64,64")]
+    hidden_sizes: Vec<usize>,
+    #[arg(long, default_value_t = 0.1)]
+    dt: f32,
+    /// Number of timesteps to run per sample
+    #[arg(long, default_value_t = 40)]
+    timesteps: usize,
+    #[arg(long, default_value_t = 256)]
+    batch_size: usize,
+    #[arg(long, value_enum, default_value_t = Method::LeastSquares)]
+    method: Method,
+    /// Penalty on the squared readout weights for `least-squares`
+    #[arg(long, default_value_t = 1e-3)]
+    ridge: f64,
+    /// Passes over the data for `delta`
+    #[arg(long, default_value_t = 50)]
+    epochs: usize,
+    /// Learning rate for `delta`
+    #[arg(long, default_value_t = 0.01)]
+    learning_rate: f64,
+    /// Scale of the readout written into the output synapses
+    #[arg(long, default_value_t = 1.0)]
+    gain: f32,
+    /// Where to save the calibrated model
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// cpu, cuda or cuda:N
+    #[arg(long, default_value = "cpu")]
+    device: String,
+}
+
+/// Labelled samples, one feature row per sample
+struct Samples {
+    features: Vec<Vec<f32>>,
+    labels: Vec<usize>,
+}
+
+fn parse_device(name: &str) -> Result<Device, Box<dyn Error>> {
+    match name {
+        "cpu" => Ok(Device::Cpu),
+        "cuda" => Ok(Device::new_cuda(0)?),
+        _ => match name.strip_prefix("cuda:") {
+            Some(ordinal) => Ok(Device::new_cuda(ordinal.parse()?)?),
+            None => Err(format!("unknown device '{}'", name).into()),
+        },
+    }
+}
+
+fn load_samples(data: &str, has_headers: bool) -> Result<Samples, Box<dyn Error>> {
+    let mut samples = Samples {
+        features: Vec::new(),
+        labels: Vec::new(),
+    };
+
+    if data == "xor" {
+        for (input, label) in XorDataset::new(&Device::Cpu)?.iter() {
+            samples
+                .features
+                .push(input.flatten_all()?.to_vec1::<f32>()?);
+            samples
+                .labels
+                .push(label.flatten_all()?.to_vec1::<f32>()?[0] as usize);
+        }
+        return Ok(samples);
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(has_headers)
+        .from_path(data)?;
+    for (row, record) in reader.records().enumerate() {
+        let record = record?;
+        let values = record
+            .iter()
+            .map(|v| v.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("{}: row {}: {}", data, row + 1, e))?;
+        let Some((&label, features)) = values.split_last() else {
+            continue;
+        };
+        if label < 0.0 || label.fract() != 0.0 {
+            return Err(format!(
+                "{}: row {}: label {} is not a class index",
+                data,
+                row + 1,
+                label
+            )
+            .into());
+        }
+        if let Some(first) = samples.features.first()
+            && first.len() != features.len()
+        {
+            return Err(format!(
+                "{}: row {}: expected {} features, got {}",
+                data,
+                row + 1,
+                first.len(),
+                features.len()
+            )
+            .into());
+        }
+        samples.features.push(features.to_vec());
+        samples.labels.push(label as usize);
+    }
+    if samples.labels.is_empty() {
+        return Err(format!("{} contains no samples", data).into());
+    }
+    Ok(samples)
+}
+
+/// `(input_size, batch)` inputs of `samples`, `batch_size` at a time
+fn batches(
+    samples: &Samples,
+    batch_size: usize,
+    device: &Device,
+) -> impl Iterator<Item = candle_core::Result<Tensor>> {
+    samples.features.chunks(batch_size).map(move |rows| {
+        let input_size = rows[0].len();
+        let values: Vec<f32> = rows.iter().flatten().copied().collect();
+        Tensor::from_vec(values, (rows.len(), input_size), device)?
+            .t()?
+            .contiguous()
+    })
+}
+
+/// Readout rates of every sample
+fn features(
+    model: &mut Model,
+    samples: &Samples,
+    args: &Args,
+) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+    let device = model.device.clone();
+    let mut features = Vec::with_capacity(samples.labels.len());
+    for input in batches(samples, args.batch_size, &device) {
+        features.extend(readout_features(model, &input?, args.timesteps)?);
+    }
+    Ok(features)
+}
+
+/// Fraction of samples whose output neuron with the most spikes is their label
+fn spiking_accuracy(
+    model: &mut Model,
+    samples: &Samples,
+    args: &Args,
+) -> Result<f64, Box<dyn Error>> {
+    let device = model.device.clone();
+    let mut correct = 0;
+    let mut labels = samples.labels.iter();
+    for input in batches(samples, args.batch_size, &device) {
+        let counts = raster::counts(&model.process_raster(&input?, args.timesteps)?)?;
+        let predicted = counts.argmax(0)?.to_vec1::<u32>()?;
+        correct += predicted
+            .iter()
+            .zip(labels.by_ref())
+            .filter(|&(&p, &l)| p as usize == l)
+            .count();
+    }
+    Ok(correct as f64 / samples.labels.len() as f64)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    if args.timesteps == 0 || args.batch_size == 0 {
+        return Err("--timesteps and --batch-size must be at least 1".into());
+    }
+
+    let train = load_samples(&args.data, !args.no_header)?;
+    let test = match &args.test_data {
+        Some(path) => Some(load_samples(path, !args.no_header)?),
+        None => None,
+    };
+    let input_size = train.features[0].len();
+    let max_label = train
+        .labels
+        .iter()
+        .chain(test.iter().flat_map(|t| &t.labels))
+        .copied()
+        .max()
+        .unwrap_or(0);
+    let num_classes = args.num_classes.unwrap_or(max_label + 1);
+    if max_label >= num_classes {
+        return Err(format!(
+            "label {} is out of range for {} classes",
+            max_label, num_classes
+        )
+        .into());
+    }
+    log::info!(
+        "Loaded {} samples with {} features and {} classes from {}",
+        train.labels.len(),
+        input_size,
+        num_classes,
+        args.data
+    );
+
+    let device = parse_device(&args.device)?;
+    let mut model = Model::new(
+        input_size,
+        num_classes,
+        args.hidden_sizes.clone(),
+        &device,
+        args.dt,
+        None,
+    )?;
+    model.load(&args.checkpoint)?;
+    model.disable_learning();
+    log::info!("Loaded {:?}", args.checkpoint);
+
+    let fit = match args.method {
+        Method::LeastSquares => ReadoutFit::LeastSquares { ridge: args.ridge },
+        Method::Delta => ReadoutFit::Delta {
+            epochs: args.epochs,
+            learning_rate: args.learning_rate,
+        },
+    };
+    let train_features = features(&mut model, &train, &args)?;
+    let readout = LinearReadout::fit(&train_features, &train.labels, num_classes, fit)?;
+    println!(
+        "Readout accuracy on {}: {:.2}%",
+        args.data,
+        100.0 * readout.accuracy(&train_features, &train.labels)
+    );
+
+    let (name, eval) = match (&args.test_data, &test) {
+        (Some(path), Some(test)) => {
+            let test_features = features(&mut model, test, &args)?;
+            println!(
+                "Readout accuracy on {}: {:.2}%",
+                path,
+                100.0 * readout.accuracy(&test_features, &test.labels)
+            );
+            (path.as_str(), test)
+        }
+        _ => (args.data.as_str(), &train),
+    };
+
+    let before = spiking_accuracy(&mut model, eval, &args)?;
+    let synapses = readout.apply(&mut model, args.gain)?;
+    log::info!("Calibrated output synapses {:?}", synapses);
+    let after = spiking_accuracy(&mut model, eval, &args)?;
+    println!(
+        "Spiking output accuracy on {}: {:.2}% before, {:.2}% after calibration",
+        name,
+        100.0 * before,
+        100.0 * after
+    );
+
+    if let Some(path) = &args.output {
+        model.save(path)?;
+        println!("Saved the calibrated model to {:?}", path);
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! Clean up recorded trajectories before training or playback.
+//!
+//! The inputs are concatenated in order, then trimmed, filtered, resampled and split, each
+//! step only if requested. Output keeps the input's CSV columns, so edited recordings can be
+//! replayed with `playback` and inspected with `inspect-data`.
+
+use clap::Parser;
+use custom_framework::dataset::trajectory::Trajectory;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(about = "Trim, resample, filter, concatenate and split recorded trajectories")]
+struct Args {
+    /// Recordings to edit; several are concatenated in order
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Output CSV; when splitting, episodes are written to `<stem>_<n>.csv` next to it
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Drop the resting frames at the start and end
+    #[arg(long)]
+    trim: bool,
+    /// Largest per-joint change (in the recording's units) still counted as resting
+    #[arg(long, default_value_t = 0.01)]
+    still_threshold: f64,
+    /// Low-pass filter cutoff in Hz, to remove jitter
+    #[arg(long)]
+    lowpass_hz: Option<f64>,
+    /// Resample to this fixed rate in Hz
+    #[arg(long)]
+    resample_hz: Option<f64>,
+    /// Split into episodes at rests or recording gaps of at least this many milliseconds
+    #[arg(long)]
+    split_pause_ms: Option<u64>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+
+    let parts = args
+        .inputs
+        .iter()
+        .map(Trajectory::load)
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut trajectory = Trajectory::concat(&parts)?;
+    log::info!(
+        "Loaded {} frames from {} file(s)",
+        trajectory.len(),
+        parts.len()
+    );
+
+    if args.trim {
+        trajectory = trajectory.trim_still(args.still_threshold);
+        log::info!("Trimmed to {} frames", trajectory.len());
+    }
+    if let Some(cutoff) = args.lowpass_hz {
+        trajectory = trajectory.low_pass(cutoff)?;
+        log::info!("Low-pass filtered at {} Hz", cutoff);
+    }
+    if let Some(rate) = args.resample_hz {
+        trajectory = trajectory.resample(rate)?;
+        log::info!("Resampled to {} frames at {} Hz", trajectory.len(), rate);
+    }
+
+    let Some(min_pause_ms) = args.split_pause_ms else {
+        trajectory.save(&args.output)?;
+        log::info!("Wrote {} frames to {:?}", trajectory.len(), args.output);
+        return Ok(());
+    };
+
+    let episodes = trajectory.split_at_pauses(args.still_threshold, min_pause_ms)?;
+    if episodes.is_empty() {
+        return Err("no motion found; nothing to write".into());
+    }
+    let stem = args
+        .output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("episode");
+    for (i, episode) in episodes.iter().enumerate() {
+        let path = args.output.with_file_name(format!("{}_{:03}.csv", stem, i));
+        episode.save(&path)?;
+        log::info!(
+            "Wrote episode {} ({} frames, {:.1} s) to {:?}",
+            i,
+            episode.len(),
+            episode.duration_ms().unwrap_or(0) as f64 / 1000.0,
+            path
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,453 @@
+//! Offline evaluation of a checkpointed classifier.
+//!
+//! Loads a CSDP `Model` or `CSDPMultiModel` checkpoint and a labelled dataset, runs it with
+//! learning disabled and prints accuracy, mean goodness, the mean per-class spike rate for
+//! each true class, and the confusion matrix. It also reports how close the output activity is
+//! to each sample's target pattern (its class neuron firing at `--target-rate`): the cosine
+//! similarity of the spike counts and, for `csdp` models, the van Rossum distance of the
+//! spike trains. With `--reference`, the `Model` weights are
+//! imported from a checkpoint of the reference Python implementation instead. `--profile`
+//! prints where the time of the `Model` steps went.
+//!
+//! The dataset is either `xor` or a CSV file with one sample per row: the feature columns
+//! followed by an integer class label in the last column.
+
+use candle_core::{Device, Tensor};
+use clap::{Parser, ValueEnum};
+use custom_framework::dataset::xor::XorDataset;
+use custom_framework::models::Model;
+use custom_framework::models::context::ContextGatingConfig;
+use custom_framework::models::csdp_multi_model::CSDPMultiModel;
+use custom_framework::models::reference::ReferenceMapping;
+use custom_framework::spike_metrics;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ModelKind {
+    /// `Model`: the output layer's spike rate per neuron scores each class
+    Csdp,
+    /// `CSDPMultiModel`: each class's share of the hidden layers scores it
+    Multi,
+}
+
+#[derive(Parser)]
+#[command(about = "Evaluate a checkpointed classifier on a labelled dataset")]
+struct Args {
+    /// Model checkpoint (`.safetensors`) to evaluate
+    #[arg(long)]
+    checkpoint: PathBuf,
+    #[arg(long, value_enum, default_value_t = ModelKind::Csdp)]
+    model: ModelKind,
+    /// The checkpoint was trained with the reference Python CSDP implementation (`.pt`, `.npz`
+    /// or `.safetensors`); its parameters are imported into a `csdp` model
+    #[arg(long)]
+    reference: bool,
+    /// JSON `ReferenceMapping` for `--reference`; defaults to the paper's naming
+    #[arg(long)]
+    mapping: Option<PathBuf>,
+    /// Number of tasks the checkpoint's context gates were drawn for (see
+    /// `Model::enable_context_gating`)
+    #[arg(long)]
+    context_tasks: Option<usize>,
+    /// Task whose context gates are applied
+    #[arg(long, requires = "context_tasks", default_value_t = 0)]
+    task: usize,
+    /// `xor`, or a CSV file whose last column is the class label
+    #[arg(long)]
+    data: String,
+    /// The CSV file has no header row
+    #[arg(long)]
+    no_header: bool,
+    /// Number of classes (output neurons); defaults to the largest label + 1
+    #[arg(long)]
+    num_classes: Option<usize>,
+    /// Hidden layer sizes, e.g. `64,64`
+    #[arg(long, value_delimiter = ',', default_value = "64,64")]
+    hidden_sizes: Vec<usize>,
+    #[arg(long, default_value_t = 0.1)]
+    dt: f32,
+    /// Number of timesteps to run per sample
+    #[arg(long, default_value_t = 40)]
+    timesteps: usize,
+    #[arg(long, default_value_t = 256)]
+    batch_size: usize,
+    /// Spikes per timestep of the class neuron in the target pattern
+    #[arg(long, default_value_t = 0.5)]
+    target_rate: f32,
+    /// Time constant of the van Rossum distance
+    #[arg(long, default_value_t = 1.0)]
+    van_rossum_tau: f32,
+    /// cpu, cuda or cuda:N
+    #[arg(long, default_value = "cpu")]
+    device: String,
+    /// Time every layer and synapse of a `csdp` model and print the breakdown
+    #[arg(long)]
+    profile: bool,
+}
+
+/// Labelled samples, one feature row per sample
+struct Samples {
+    features: Vec<Vec<f32>>,
+    labels: Vec<usize>,
+}
+
+/// Per-sample results of one batch
+struct BatchScores {
+    /// `[sample][class]` spike rates
+    class_rates: Vec<Vec<f32>>,
+    /// Mean squared hidden activity per sample (of the winning class for `CSDPMultiModel`)
+    goodness: Vec<f32>,
+    /// Van Rossum distance of the output spike trains from the target pattern; only for
+    /// `Model`, whose output spikes are observed step by step
+    van_rossum: Option<Vec<f32>>,
+}
+
+enum Classifier {
+    Csdp(Model),
+    Multi(CSDPMultiModel),
+}
+
+fn parse_device(name: &str) -> Result<Device, Box<dyn Error>> {
+    match name {
+        "cpu" => Ok(Device::Cpu),
+        "cuda" => Ok(Device::new_cuda(0)?),
+        _ => match name.strip_prefix("cuda:") {
+            Some(ordinal) => Ok(Device::new_cuda(ordinal.parse()?)?),
+            None => Err(format!("unknown device '{}'", name).into()),
+        },
+    }
+}
+
+fn load_samples(data: &str, has_headers: bool) -> Result<Samples, Box<dyn Error>> {
+    let mut samples = Samples {
+        features: Vec::new(),
+        labels: Vec::new(),
+    };
+
+    if data == "xor" {
+        for (input, label) in XorDataset::new(&Device::Cpu)?.iter() {
+            samples
+                .features
+                .push(input.flatten_all()?.to_vec1::<f32>()?);
+            samples
+                .labels
+                .push(label.flatten_all()?.to_vec1::<f32>()?[0] as usize);
+        }
+        return Ok(samples);
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(has_headers)
+        .from_path(data)?;
+    for (row, record) in reader.records().enumerate() {
+        let record = record?;
+        let values = record
+            .iter()
+            .map(|v| v.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("{}: row {}: {}", data, row + 1, e))?;
+        let Some((&label, features)) = values.split_last() else {
+            continue;
+        };
+        if label < 0.0 || label.fract() != 0.0 {
+            return Err(format!(
+                "{}: row {}: label {} is not a class index",
+                data,
+                row + 1,
+                label
+            )
+            .into());
+        }
+        if let Some(first) = samples.features.first()
+            && first.len() != features.len()
+        {
+            return Err(format!(
+                "{}: row {}: expected {} features, got {}",
+                data,
+                row + 1,
+                first.len(),
+                features.len()
+            )
+            .into());
+        }
+        samples.features.push(features.to_vec());
+        samples.labels.push(label as usize);
+    }
+    if samples.labels.is_empty() {
+        return Err(format!("{} contains no samples", data).into());
+    }
+    Ok(samples)
+}
+
+impl Classifier {
+    fn load(
+        args: &Args,
+        input_size: usize,
+        num_classes: usize,
+        device: &Device,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut classifier = match args.model {
+            ModelKind::Csdp => Classifier::Csdp(Model::new(
+                input_size,
+                num_classes,
+                args.hidden_sizes.clone(),
+                device,
+                args.dt,
+                None,
+            )?),
+            ModelKind::Multi => Classifier::Multi(CSDPMultiModel::new(
+                input_size,
+                &args.hidden_sizes,
+                num_classes,
+                device,
+                args.dt,
+                args.timesteps,
+            )?),
+        };
+        match &mut classifier {
+            Classifier::Csdp(model) if args.reference => {
+                let mapping = match &args.mapping {
+                    Some(path) => ReferenceMapping::from_json_file(path)?,
+                    None => ReferenceMapping::csdp_paper(args.hidden_sizes.len()),
+                };
+                let report = model.import_reference(&args.checkpoint, &mapping)?;
+                log::info!("Imported {} reference parameters", report.imported.len());
+                if !report.missing.is_empty() {
+                    log::warn!("Not in the checkpoint: {}", report.missing.join(", "));
+                }
+                if !report.unused.is_empty() {
+                    log::warn!("Not mapped: {}", report.unused.join(", "));
+                }
+                model.disable_learning();
+            }
+            Classifier::Csdp(model) => {
+                if let Some(num_tasks) = args.context_tasks {
+                    model.enable_context_gating(ContextGatingConfig::new(num_tasks))?;
+                }
+                model.load(&args.checkpoint)?;
+                if args.context_tasks.is_some() {
+                    model.set_context(args.task)?;
+                }
+                model.disable_learning();
+            }
+            Classifier::Multi(_) if args.reference => {
+                return Err("--reference is only supported for --model csdp".into());
+            }
+            Classifier::Multi(_) if args.context_tasks.is_some() => {
+                return Err("--context-tasks is only supported for --model csdp".into());
+            }
+            Classifier::Multi(model) => {
+                model.load(&args.checkpoint)?;
+                model.disable_learning();
+            }
+        }
+        if let Classifier::Csdp(model) = &classifier {
+            log::info!("Model:\n{}", model.summary()?);
+        }
+        Ok(classifier)
+    }
+
+    /// Score a `(batch, input_size)` batch with the given `labels`
+    fn score(
+        &mut self,
+        batch: &Tensor,
+        labels: &[usize],
+        args: &Args,
+    ) -> candle_core::Result<BatchScores> {
+        let timesteps = args.timesteps;
+        match self {
+            Classifier::Csdp(model) => {
+                let input = batch.t()?.contiguous()?;
+                let batch_size = input.dim(1)?;
+                let hidden = 2..model.layers.len() - 1;
+                model.reset(batch_size)?;
+
+                let mut output_rates = Tensor::zeros(
+                    (model.layers[model.layers.len() - 1].size(), batch_size),
+                    candle_core::DType::F32,
+                    &model.device,
+                )?;
+                let mut goodness =
+                    Tensor::zeros(batch_size, candle_core::DType::F32, &model.device)?;
+                let mut output = Vec::with_capacity(timesteps);
+                for _ in 0..timesteps {
+                    model.step(&input, None)?;
+                    let spikes = model.layers.last().unwrap().output()?;
+                    output_rates = (output_rates + spikes)?;
+                    output.push(spikes.clone());
+                    for layer in &model.layers[hidden.clone()] {
+                        goodness = (goodness + layer.output()?.sqr()?.mean(0)?)?;
+                    }
+                }
+                let num_classes = output_rates.dim(0)?;
+                let train = spike_metrics::regular_train(args.target_rate, timesteps);
+                let target: Vec<Tensor> = train
+                    .iter()
+                    .map(|&spike| {
+                        let pattern: Vec<f32> = (0..num_classes)
+                            .flat_map(|c| {
+                                labels
+                                    .iter()
+                                    .map(move |&l| if l == c { spike } else { 0.0 })
+                            })
+                            .collect();
+                        Tensor::from_vec(pattern, (num_classes, batch_size), &model.device)
+                    })
+                    .collect::<candle_core::Result<_>>()?;
+                let van_rossum = spike_metrics::population_van_rossum(
+                    &output,
+                    &target,
+                    args.van_rossum_tau,
+                    args.dt,
+                )?;
+
+                let scale = timesteps as f64;
+                let layers = hidden.len() as f64;
+                Ok(BatchScores {
+                    class_rates: (output_rates / scale)?.t()?.to_vec2::<f32>()?,
+                    goodness: (goodness / (scale * layers))?.to_vec1::<f32>()?,
+                    van_rossum: Some(van_rossum),
+                })
+            }
+            Classifier::Multi(model) => {
+                let class_rates = model.predict_scores(&[batch.clone()])?.to_vec2::<f32>()?;
+                let goodness = class_rates
+                    .iter()
+                    .map(|row| row.iter().copied().fold(0.0, f32::max))
+                    .collect();
+                Ok(BatchScores {
+                    class_rates,
+                    goodness,
+                    van_rossum: None,
+                })
+            }
+        }
+    }
+}
+
+fn argmax(row: &[f32]) -> usize {
+    row.iter()
+        .enumerate()
+        .fold(
+            (0, f32::MIN),
+            |best, (i, &v)| if v > best.1 { (i, v) } else { best },
+        )
+        .0
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    if args.timesteps == 0 || args.batch_size == 0 {
+        return Err("--timesteps and --batch-size must be at least 1".into());
+    }
+
+    let samples = load_samples(&args.data, !args.no_header)?;
+    let input_size = samples.features[0].len();
+    let max_label = samples.labels.iter().copied().max().unwrap_or(0);
+    let num_classes = args.num_classes.unwrap_or(max_label + 1);
+    if max_label >= num_classes {
+        return Err(format!(
+            "label {} is out of range for {} classes",
+            max_label, num_classes
+        )
+        .into());
+    }
+    log::info!(
+        "Loaded {} samples with {} features and {} classes from {}",
+        samples.labels.len(),
+        input_size,
+        num_classes,
+        args.data
+    );
+
+    let device = parse_device(&args.device)?;
+    let mut classifier = Classifier::load(&args, input_size, num_classes, &device)?;
+    log::info!("Loaded {:?}", args.checkpoint);
+    if args.profile {
+        match &mut classifier {
+            Classifier::Csdp(model) => model.enable_profiling(),
+            Classifier::Multi(_) => {
+                return Err("--profile is only supported for --model csdp".into());
+            }
+        }
+    }
+
+    let mut confusion = vec![vec![0usize; num_classes]; num_classes];
+    let mut rate_sums = vec![vec![0.0f32; num_classes]; num_classes];
+    let mut goodness_sum = 0.0;
+    let mut similarity_sum = 0.0;
+    let mut van_rossum_sum: Option<f32> = None;
+    for (features, labels) in samples
+        .features
+        .chunks(args.batch_size)
+        .zip(samples.labels.chunks(args.batch_size))
+    {
+        let rows: Vec<f32> = features.iter().flatten().copied().collect();
+        let batch = Tensor::from_vec(rows, (features.len(), input_size), &device)?;
+        let scores = classifier.score(&batch, labels, &args)?;
+        if let Some(distances) = &scores.van_rossum {
+            *van_rossum_sum.get_or_insert(0.0) += distances.iter().sum::<f32>();
+        }
+        for ((rates, goodness), &label) in
+            scores.class_rates.iter().zip(&scores.goodness).zip(labels)
+        {
+            let rates_tensor = Tensor::new(rates.as_slice(), &Device::Cpu)?.unsqueeze(1)?;
+            let target = Tensor::from_vec(
+                (0..num_classes).map(|c| f32::from(c == label)).collect(),
+                (num_classes, 1),
+                &Device::Cpu,
+            )?;
+            similarity_sum += spike_metrics::population_similarity(&[rates_tensor], &target)?[0];
+            confusion[label][argmax(rates)] += 1;
+            for (sum, rate) in rate_sums[label].iter_mut().zip(rates) {
+                *sum += rate;
+            }
+            goodness_sum += goodness;
+        }
+    }
+
+    let total = samples.labels.len();
+    let correct: usize = (0..num_classes).map(|c| confusion[c][c]).sum();
+    println!(
+        "Accuracy: {} / {} ({:.2}%)",
+        correct,
+        total,
+        100.0 * correct as f64 / total as f64
+    );
+    println!("Mean goodness: {:.4}", goodness_sum / total as f32);
+    println!(
+        "Mean similarity to target: {:.4}",
+        similarity_sum / total as f32
+    );
+    if let Some(sum) = van_rossum_sum {
+        println!(
+            "Mean van Rossum distance to target: {:.4}",
+            sum / total as f32
+        );
+    }
+
+    println!("\nMean spike rate per class (rows: true class, columns: class neuron)");
+    for (class, sums) in rate_sums.iter().enumerate() {
+        let count = confusion[class].iter().sum::<usize>().max(1) as f32;
+        let rates: Vec<String> = sums.iter().map(|s| format!("{:.3}", s / count)).collect();
+        println!("  {:>3}: {}", class, rates.join(" "));
+    }
+
+    println!("\nConfusion matrix (rows: true class, columns: predicted class)");
+    let width = total.to_string().len().max(3);
+    let header: Vec<String> = (0..num_classes).map(|c| format!("{:>width$}", c)).collect();
+    println!("       {}", header.join(" "));
+    for (class, row) in confusion.iter().enumerate() {
+        let cells: Vec<String> = row.iter().map(|n| format!("{:>width$}", n)).collect();
+        println!("  {:>3}: {}", class, cells.join(" "));
+    }
+
+    if let Classifier::Csdp(model) = &classifier
+        && let Some(report) = model.profile_report()
+    {
+        println!("\nProfile\n{}", report);
+    }
+    Ok(())
+}
@@ -0,0 +1,173 @@
+//! Sanity checks for recorded trajectories and other CSV datasets before training on them.
+//!
+//! For every file, prints per-channel statistics, flags channels that never change, reports
+//! timestamp gaps and out-of-order frames, and optionally writes an SVG plot of every channel
+//! over time.
+
+use clap::Parser;
+use custom_framework::dataset::trajectory::Trajectory;
+use std::error::Error;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+const PLOT_WIDTH: f64 = 900.0;
+const PLOT_ROW_HEIGHT: f64 = 90.0;
+const PLOT_MARGIN: f64 = 60.0;
+
+#[derive(Parser)]
+#[command(about = "Inspect recorded trajectories and CSV datasets")]
+struct Args {
+    /// CSV files to inspect
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Intervals longer than this many median intervals are reported as gaps
+    #[arg(long, default_value_t = 3.0)]
+    gap_factor: f64,
+    /// Write `<stem>.svg` trajectory plots into this directory
+    #[arg(long)]
+    plot_dir: Option<PathBuf>,
+}
+
+/// Print the report for one file and return the number of problems found
+fn inspect(path: &Path, trajectory: &Trajectory, gap_factor: f64) -> usize {
+    let mut problems = 0;
+    print!(
+        "{}: {} frames, {} channels",
+        path.display(),
+        trajectory.len(),
+        trajectory.channels.len()
+    );
+    match trajectory.duration_ms() {
+        Some(ms) => println!(", {:.1} s", ms as f64 / 1000.0),
+        None => println!(", no timestamps"),
+    }
+    if trajectory.is_empty() {
+        println!("  no frames");
+        return 1;
+    }
+
+    println!(
+        "  {:<12} {:>12} {:>12} {:>12} {:>12}",
+        "channel", "min", "max", "mean", "std"
+    );
+    for stats in trajectory.channel_stats() {
+        let flag = if !stats.mean.is_finite() {
+            problems += 1;
+            "  <- non-finite values"
+        } else if stats.std == 0.0 {
+            problems += 1;
+            "  <- never changes"
+        } else {
+            ""
+        };
+        println!(
+            "  {:<12} {:>12.4} {:>12.4} {:>12.4} {:>12.4}{}",
+            stats.name, stats.min, stats.max, stats.mean, stats.std, flag
+        );
+    }
+
+    if let Some(timing) = trajectory.timing(gap_factor) {
+        println!(
+            "  timing: median interval {:.1} ms, {} gaps, {} out of order",
+            timing.median_interval_ms,
+            timing.gaps.len(),
+            timing.out_of_order.len()
+        );
+        let timestamps = trajectory.timestamps_ms.as_deref().unwrap_or_default();
+        for &(frame, interval) in &timing.gaps {
+            println!(
+                "    gap of {} ms before frame {} (t = {} ms)",
+                interval, frame, timestamps[frame]
+            );
+        }
+        for &frame in &timing.out_of_order {
+            println!(
+                "    frame {} at {} ms does not advance past {} ms",
+                frame,
+                timestamps[frame],
+                timestamps[frame - 1]
+            );
+        }
+        problems += timing.gaps.len() + timing.out_of_order.len();
+    }
+    problems
+}
+
+/// One row per channel, each scaled to its own range, against time (or frame index)
+fn render_svg(trajectory: &Trajectory) -> String {
+    let height = PLOT_ROW_HEIGHT * trajectory.channels.len() as f64;
+    let xs: Vec<f64> = match &trajectory.timestamps_ms {
+        Some(timestamps) => timestamps.iter().map(|&t| t as f64).collect(),
+        None => (0..trajectory.len()).map(|i| i as f64).collect(),
+    };
+    let x_min = xs.first().copied().unwrap_or(0.0);
+    let x_span = (xs.last().copied().unwrap_or(0.0) - x_min).max(1.0);
+    let plot_width = PLOT_WIDTH - PLOT_MARGIN;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="monospace" font-size="11">"#,
+        PLOT_WIDTH, height
+    );
+    for (row, stats) in trajectory.channel_stats().iter().enumerate() {
+        let top = row as f64 * PLOT_ROW_HEIGHT;
+        let y_span = (stats.max - stats.min).max(f64::EPSILON);
+        let points: Vec<String> = xs
+            .iter()
+            .zip(trajectory.channel(row))
+            .filter(|(_, v)| v.is_finite())
+            .map(|(&x, v)| {
+                let px = PLOT_MARGIN + (x - x_min) / x_span * plot_width;
+                let py = top + 5.0 + (1.0 - (v - stats.min) / y_span) * (PLOT_ROW_HEIGHT - 10.0);
+                format!("{:.1},{:.1}", px, py)
+            })
+            .collect();
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="#ccc"/>"##,
+            PLOT_MARGIN, top, plot_width, PLOT_ROW_HEIGHT
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="4" y="{}">{}</text>"#,
+            top + PLOT_ROW_HEIGHT / 2.0,
+            stats.name
+        );
+        let _ = writeln!(
+            svg,
+            r##"<polyline fill="none" stroke="#1f77b4" points="{}"/>"##,
+            points.join(" ")
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    if let Some(dir) = &args.plot_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut total_problems = 0;
+    for path in &args.files {
+        let trajectory = Trajectory::load(path)?;
+        total_problems += inspect(path, &trajectory, args.gap_factor);
+
+        if let Some(dir) = &args.plot_dir
+            && !trajectory.is_empty()
+        {
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("plot");
+            let plot = dir.join(format!("{}.svg", stem));
+            std::fs::write(&plot, render_svg(&trajectory))?;
+            println!("  plot: {}", plot.display());
+        }
+        println!();
+    }
+
+    if total_problems > 0 {
+        return Err(format!("found {} problems", total_problems).into());
+    }
+    Ok(())
+}
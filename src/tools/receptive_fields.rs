@@ -0,0 +1,250 @@
+//! Render what each hidden neuron has learned from its input.
+//!
+//! Reads a weight file written by `custom_framework export` (`.safetensors` or `.npz`) and,
+//! for every synapse leaving the `Input` layer, draws each post-synaptic neuron's incoming
+//! weight vector. Image inputs become a PNG grid with one tile per neuron; low-dimensional
+//! inputs become an SVG of per-neuron bar plots. Weights are drawn red for positive and blue
+//! for negative values, scaled by the largest magnitude of the synapse.
+
+use candle_core::{DType, Device, Tensor};
+use clap::Parser;
+use custom_framework::utils::write_png_rgb;
+use std::error::Error;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+const BAR_PANEL_WIDTH: f64 = 160.0;
+const BAR_PANEL_HEIGHT: f64 = 80.0;
+
+#[derive(Parser)]
+#[command(about = "Draw each hidden neuron's incoming weights as images or bar plots")]
+struct Args {
+    /// Weights written by `export` (`.safetensors` or `.npz`)
+    weights: PathBuf,
+    /// Output directory
+    #[arg(short, long, default_value = "receptive_fields")]
+    output: PathBuf,
+    /// Weight tensors to draw; defaults to every `Input->*.weights`
+    #[arg(long)]
+    tensor: Vec<String>,
+    /// Input image shape as `HxW`; inputs that are a perfect square are drawn as images anyway
+    #[arg(long)]
+    image_shape: Option<String>,
+    /// Draw at most this many neurons per synapse
+    #[arg(long, default_value_t = 256)]
+    max_neurons: usize,
+    /// Pixels per weight in image tiles
+    #[arg(long, default_value_t = 3)]
+    scale: usize,
+}
+
+fn load_weights(path: &Path) -> Result<Vec<(String, Tensor)>, Box<dyn Error>> {
+    let mut tensors = match path.extension().and_then(|e| e.to_str()) {
+        Some("npz") => Tensor::read_npz(path)?,
+        Some("safetensors") => candle_core::safetensors::load(path, &Device::Cpu)?
+            .into_iter()
+            .collect(),
+        _ => return Err("weights must be a .safetensors or .npz file".into()),
+    };
+    tensors.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(tensors)
+}
+
+/// `policy.Input->Hidden_0.weights` -> true
+fn is_input_weights(name: &str) -> bool {
+    let Some(synapse) = name.strip_suffix(".weights") else {
+        return false;
+    };
+    let pre = synapse.split("->").next().unwrap_or_default();
+    pre.rsplit('.').next() == Some("Input")
+}
+
+fn parse_shape(shape: &str) -> Result<(usize, usize), Box<dyn Error>> {
+    let (h, w) = shape
+        .split_once('x')
+        .ok_or_else(|| format!("image shape '{}' is not HxW", shape))?;
+    Ok((h.trim().parse()?, w.trim().parse()?))
+}
+
+/// The image shape for `inputs` weights per neuron, if they should be drawn as an image
+fn image_shape(inputs: usize, requested: Option<(usize, usize)>) -> Option<(usize, usize)> {
+    match requested {
+        Some((h, w)) if h * w == inputs => Some((h, w)),
+        Some(_) => None,
+        None => {
+            let side = (inputs as f64).sqrt().round() as usize;
+            (inputs >= 16 && side * side == inputs).then_some((side, side))
+        }
+    }
+}
+
+/// Diverging color map: blue for -1, white for 0, red for +1
+fn color(v: f32) -> [u8; 3] {
+    let v = v.clamp(-1.0, 1.0);
+    let fade = (255.0 * (1.0 - v.abs())) as u8;
+    if v >= 0.0 {
+        [255, fade, fade]
+    } else {
+        [fade, fade, 255]
+    }
+}
+
+fn max_abs(rows: &[Vec<f32>]) -> f32 {
+    rows.iter()
+        .flatten()
+        .fold(0.0f32, |m, v| m.max(v.abs()))
+        .max(f32::EPSILON)
+}
+
+fn write_image_grid(
+    path: &Path,
+    rows: &[Vec<f32>],
+    (h, w): (usize, usize),
+    scale: usize,
+) -> std::io::Result<()> {
+    let cols = (rows.len() as f64).sqrt().ceil().max(1.0) as usize;
+    let grid_rows = rows.len().div_ceil(cols);
+    // One pixel of gray border around every tile
+    let tile_w = w * scale + 1;
+    let tile_h = h * scale + 1;
+    let width = cols * tile_w + 1;
+    let height = grid_rows * tile_h + 1;
+    let mut pixels = vec![128u8; width * height * 3];
+
+    let norm = max_abs(rows);
+    for (n, weights) in rows.iter().enumerate() {
+        let (x0, y0) = ((n % cols) * tile_w + 1, (n / cols) * tile_h + 1);
+        for (i, &v) in weights.iter().enumerate() {
+            let rgb = color(v / norm);
+            let (py, px) = (i / w, i % w);
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let x = x0 + px * scale + dx;
+                    let y = y0 + py * scale + dy;
+                    let at = (y * width + x) * 3;
+                    pixels[at..at + 3].copy_from_slice(&rgb);
+                }
+            }
+        }
+    }
+    write_png_rgb(path, width as u32, height as u32, &pixels)
+}
+
+fn write_bar_plots(path: &Path, name: &str, rows: &[Vec<f32>]) -> std::io::Result<()> {
+    let cols = (rows.len() as f64).sqrt().ceil().max(1.0) as usize;
+    let grid_rows = rows.len().div_ceil(cols);
+    let width = cols as f64 * BAR_PANEL_WIDTH;
+    let height = grid_rows as f64 * BAR_PANEL_HEIGHT + 20.0;
+    let norm = max_abs(rows);
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="monospace" font-size="10">"#,
+        width, height
+    );
+    let _ = writeln!(svg, r#"<text x="4" y="14">{}</text>"#, name);
+    for (n, weights) in rows.iter().enumerate() {
+        let x0 = (n % cols) as f64 * BAR_PANEL_WIDTH;
+        let y0 = (n / cols) as f64 * BAR_PANEL_HEIGHT + 20.0;
+        let mid = y0 + BAR_PANEL_HEIGHT / 2.0;
+        let bar = (BAR_PANEL_WIDTH - 10.0) / weights.len().max(1) as f64;
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="#999"/><text x="{}" y="{}">{}</text>"##,
+            x0 + 5.0,
+            mid,
+            x0 + BAR_PANEL_WIDTH - 5.0,
+            mid,
+            x0 + 5.0,
+            y0 + 10.0,
+            n
+        );
+        for (i, &v) in weights.iter().enumerate() {
+            let v = (v / norm) as f64;
+            let len = v.abs() * (BAR_PANEL_HEIGHT / 2.0 - 12.0);
+            let [r, g, b] = color(v as f32);
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="rgb({},{},{})"/>"#,
+                x0 + 5.0 + i as f64 * bar,
+                if v >= 0.0 { mid - len } else { mid },
+                (bar - 1.0).max(0.5),
+                len,
+                r,
+                g,
+                b
+            );
+        }
+    }
+    svg.push_str("</svg>\n");
+    std::fs::write(path, svg)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    let requested_shape = args.image_shape.as_deref().map(parse_shape).transpose()?;
+
+    let tensors = load_weights(&args.weights)?;
+    let selected: Vec<&(String, Tensor)> = tensors
+        .iter()
+        .filter(|(name, _)| {
+            if args.tensor.is_empty() {
+                is_input_weights(name)
+            } else {
+                args.tensor.contains(name)
+            }
+        })
+        .collect();
+    if selected.is_empty() {
+        return Err(format!("no matching weight tensors in {:?}", args.weights).into());
+    }
+    std::fs::create_dir_all(&args.output)?;
+
+    for (name, tensor) in selected {
+        if tensor.rank() != 2 {
+            log::warn!(
+                "Skipping {}: expected a 2-D weight matrix, got {:?}",
+                name,
+                tensor.dims()
+            );
+            continue;
+        }
+        // Exported weights are (post, pre): row i is the receptive field of neuron i
+        let rows = tensor.to_dtype(DType::F32)?.to_vec2::<f32>()?;
+        let inputs = rows.first().map_or(0, |r| r.len());
+        let rows = &rows[..rows.len().min(args.max_neurons)];
+        let file_name: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        let path = match image_shape(inputs, requested_shape) {
+            Some(shape) => {
+                let path = args.output.join(format!("{}.png", file_name));
+                write_image_grid(&path, rows, shape, args.scale.max(1))?;
+                path
+            }
+            None => {
+                let path = args.output.join(format!("{}.svg", file_name));
+                write_bar_plots(&path, name, rows)?;
+                path
+            }
+        };
+        log::info!(
+            "{}: {} neurons x {} inputs -> {:?}",
+            name,
+            rows.len(),
+            inputs,
+            path
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! Reproducibility check of CSDP training.
+//!
+//! Trains a standard `Model` on XOR from a fixed seed with contrastive (positive and negative
+//! label) phases and hashes its state after every sample (see `repro`). Without
+//! `--compare`, it trains twice in this process and reports whether the runs are bit-exact or
+//! the first component that diverged. `--output` saves the log of the run, and `--compare`
+//! checks this run against a log saved on another machine, build or device.
+
+use candle_core::{Device, Tensor};
+use clap::Parser;
+use custom_framework::dataset::xor::XorDataset;
+use custom_framework::models::{Model, ModelConfig};
+use custom_framework::repro::{self, ReproducibilityLog};
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(about = "Check that seeded CSDP training is bit-exact across runs and machines")]
+struct Args {
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    /// Hidden layer sizes, e.g. `32,32`
+    #[arg(long, value_delimiter = ',', default_value = "32,32")]
+    hidden_sizes: Vec<usize>,
+    #[arg(long, default_value_t = 0.1)]
+    dt: f32,
+    /// Number of timesteps per sample
+    #[arg(long, default_value_t = 20)]
+    timesteps: usize,
+    /// Passes over the four XOR samples
+    #[arg(long, default_value_t = 5)]
+    epochs: usize,
+    /// Save the log of the run as JSON
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Compare against a log saved with `--output` instead of running twice
+    #[arg(long)]
+    compare: Option<PathBuf>,
+    /// cpu, cuda or cuda:N
+    #[arg(long, default_value = "cpu")]
+    device: String,
+}
+
+fn parse_device(name: &str) -> Result<Device, Box<dyn Error>> {
+    match name {
+        "cpu" => Ok(Device::Cpu),
+        "cuda" => Ok(Device::new_cuda(0)?),
+        _ => match name.strip_prefix("cuda:") {
+            Some(ordinal) => Ok(Device::new_cuda(ordinal.parse()?)?),
+            None => Err(format!("unknown device '{}'", name).into()),
+        },
+    }
+}
+
+/// One seeded training run, checkpointed after the initialization and every sample
+fn train(
+    args: &Args,
+    device: &Device,
+    log: &mut ReproducibilityLog,
+) -> custom_framework::error::Result<()> {
+    let config = ModelConfig::standard(2, 2, args.hidden_sizes.clone(), args.dt, None)?;
+    let mut model = Model::from_config(config, device)?;
+    log.record("init", &model)?;
+
+    let data = XorDataset::new(device)?;
+    for epoch in 0..args.epochs {
+        for (i, (input, label)) in data.iter().enumerate() {
+            let class = label.flatten_all()?.to_vec1::<f32>()?[0] as usize;
+            let one_hot = |c: usize| {
+                let values: Vec<f32> = (0..2).map(|k| if k == c { 1.0 } else { 0.0 }).collect();
+                Tensor::from_vec(values, (2, 1), device)
+            };
+            model.process_contrastive(
+                input,
+                input,
+                Some(&one_hot(class)?),
+                Some(&one_hot(1 - class)?),
+                args.timesteps,
+            )?;
+            log.record(format!("epoch {} sample {}", epoch, i), &model)?;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    let args = Args::parse();
+    let device = parse_device(&args.device)?;
+
+    let (log, divergence) = match &args.compare {
+        Some(path) => {
+            let expected = ReproducibilityLog::load(path)?;
+            if expected.seed.is_some_and(|seed| seed != args.seed) {
+                log::warn!(
+                    "{:?} was recorded with seed {:?}, this run uses {}",
+                    path,
+                    expected.seed,
+                    args.seed
+                );
+            }
+            custom_framework::seed::set_global_seed(args.seed, &device)?;
+            let mut log = ReproducibilityLog::new(Some(args.seed));
+            train(&args, &device, &mut log)?;
+            let divergence = expected.first_divergence(&log);
+            (log, divergence)
+        }
+        None => repro::compare_runs(args.seed, &device, |log| train(&args, &device, log))?,
+    };
+
+    if let Some(path) = &args.output {
+        log.save(path)?;
+        println!("Saved {} checkpoints to {:?}", log.checkpoints.len(), path);
+    }
+    match divergence {
+        None => {
+            let digest = log.checkpoints.last().map_or(0, |c| c.digest());
+            println!(
+                "Bit-exact over {} checkpoints (final state {:016x})",
+                log.checkpoints.len(),
+                digest
+            );
+            Ok(())
+        }
+        Some(divergence) => {
+            println!("First divergence: {}", divergence);
+            std::process::exit(1);
+        }
+    }
+}
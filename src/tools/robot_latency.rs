@@ -0,0 +1,252 @@
+//! Control-loop latency benchmark for the LeRobot arm.
+//!
+//! At each requested rate, runs read-positions → model step → write-goal for a fixed time and
+//! reports latency percentiles of every phase, the end-to-end latency, the jitter of the loop
+//! period and how many cycles missed their deadline. The loop is paced by
+//! `robot::realtime::Scheduler` like the teleoperation and playback loops, so the jitter
+//! measured is theirs. The model sees the joint positions but
+//! its output is not applied: the goal written is the position just read, so the arm holds
+//! still (torque stays off unless `--torque` is given).
+
+use candle_core::{Device, Tensor};
+use clap::Parser;
+use custom_framework::models::Model;
+use custom_framework::models::context::ContextGatingConfig;
+use custom_framework::robot::profile::RobotProfile;
+use custom_framework::robot::real_lerobot::LeRobot;
+use custom_framework::robot::realtime::{self, DeadlineStats, Scheduler};
+use std::error::Error;
+use std::f64::consts::PI;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+#[derive(Parser)]
+#[command(about = "Measure read -> model step -> write latency of the robot control loop")]
+struct Args {
+    /// Built-in profile name (`follower`, `leader`) or path to a profile JSON
+    #[arg(long, default_value = "follower")]
+    robot_profile: String,
+    /// Overrides the profile's serial port
+    #[arg(long)]
+    port: Option<String>,
+    /// Control rates to test, in Hz
+    #[arg(long, value_delimiter = ',', default_value = "10,30,60,100,200")]
+    rates: Vec<f64>,
+    /// Seconds to run at each rate
+    #[arg(long, default_value_t = 5.0)]
+    duration_s: f64,
+    /// Hidden layer sizes of the model stepped every cycle
+    #[arg(long, value_delimiter = ',', default_value = "256,128")]
+    hidden_sizes: Vec<usize>,
+    /// Model timesteps per control cycle
+    #[arg(long, default_value_t = 1)]
+    timesteps: usize,
+    /// Load the model weights from this checkpoint instead of a random init
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Number of tasks the checkpoint's context gates were drawn for (see
+    /// `Model::enable_context_gating`)
+    #[arg(long)]
+    context_tasks: Option<usize>,
+    /// Task whose context gates are applied
+    #[arg(long, requires = "context_tasks", default_value_t = 0)]
+    task: usize,
+    /// cpu, cuda or cuda:N
+    #[arg(long, default_value = "cpu")]
+    device: String,
+    /// Enable torque so the servos actively hold the written goal
+    #[arg(long)]
+    torque: bool,
+    /// Skip the serial bus and time only the model step
+    #[arg(long)]
+    model_only: bool,
+    /// Run the loop at real-time thread priority (needs `CAP_SYS_NICE` on Linux)
+    #[arg(long)]
+    realtime: bool,
+}
+
+/// Per-cycle timings in milliseconds
+#[derive(Default)]
+struct Samples {
+    read: Vec<f64>,
+    step: Vec<f64>,
+    write: Vec<f64>,
+    total: Vec<f64>,
+    period: Vec<f64>,
+    deadlines: DeadlineStats,
+}
+
+fn parse_device(name: &str) -> Result<Device, Box<dyn Error>> {
+    match name {
+        "cpu" => Ok(Device::Cpu),
+        "cuda" => Ok(Device::new_cuda(0)?),
+        _ => match name.strip_prefix("cuda:") {
+            Some(ordinal) => Ok(Device::new_cuda(ordinal.parse()?)?),
+            None => Err(format!("unknown device '{}'", name).into()),
+        },
+    }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// `p`-th percentile (0..=1) by nearest rank
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+}
+
+fn print_row(name: &str, values: &[f64]) {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    println!(
+        "  {:<8} {:>8.3} {:>8.3} {:>8.3} {:>8.3}",
+        name,
+        percentile(&sorted, 0.5),
+        percentile(&sorted, 0.9),
+        percentile(&sorted, 0.99),
+        sorted.last().copied().unwrap_or(f64::NAN)
+    );
+}
+
+/// Joint angles in radians mapped onto [0, 1] spike probabilities
+fn encode(positions: &[f64], device: &Device) -> candle_core::Result<Tensor> {
+    let probs: Vec<f32> = positions
+        .iter()
+        .map(|p| ((p + PI) / (2.0 * PI)).clamp(0.0, 1.0) as f32)
+        .collect();
+    Tensor::from_vec(probs, (positions.len(), 1), device)
+}
+
+fn run_rate(
+    rate: f64,
+    args: &Args,
+    robot: &mut Option<LeRobot>,
+    model: &mut Model,
+) -> Result<Samples, Box<dyn Error>> {
+    let end = Instant::now() + Duration::from_secs_f64(args.duration_s);
+    let mut samples = Samples::default();
+    let mut positions = vec![0.0; 6];
+    let mut scheduler = Scheduler::at_rate(rate);
+    let mut last_start: Option<Instant> = None;
+
+    while Instant::now() < end {
+        let start = Instant::now();
+        if let Some(last) = last_start {
+            samples.period.push(ms(start - last));
+        }
+        last_start = Some(start);
+
+        if let Some(robot) = robot.as_mut() {
+            positions = robot.get_motor_positions()?;
+        }
+        let read_done = Instant::now();
+
+        let input = encode(&positions, &model.device)?;
+        for _ in 0..args.timesteps {
+            model.step(&input, None)?;
+        }
+        // Wait for queued GPU work so the step is timed, not just launched
+        model.device.synchronize()?;
+        let step_done = Instant::now();
+
+        if let Some(robot) = robot.as_mut() {
+            robot.set_goal_positions(&positions)?;
+        }
+        let write_done = Instant::now();
+
+        samples.read.push(ms(read_done - start));
+        samples.step.push(ms(step_done - read_done));
+        samples.write.push(ms(write_done - step_done));
+        samples.total.push(ms(write_done - start));
+
+        // Behind schedule, the next cycle starts at the next deadline instead of bursting
+        scheduler.wait();
+    }
+    samples.deadlines = scheduler.stats().clone();
+    Ok(samples)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    if args.rates.iter().any(|&r| r <= 0.0) {
+        return Err("--rates must be positive".into());
+    }
+
+    let device = parse_device(&args.device)?;
+    let mut model = Model::new(6, 6, args.hidden_sizes.clone(), &device, 0.1, None)?;
+    if let Some(num_tasks) = args.context_tasks {
+        model.enable_context_gating(ContextGatingConfig::new(num_tasks))?;
+    }
+    if let Some(checkpoint) = &args.checkpoint {
+        model.load(checkpoint)?;
+    }
+    if args.context_tasks.is_some() {
+        model.set_context(args.task)?;
+    }
+    model.disable_learning();
+    model.reset(1)?;
+    if args.realtime {
+        match realtime::elevate_priority() {
+            Ok(()) => log::info!("Running at real-time priority"),
+            Err(e) => log::warn!("Could not elevate to real-time priority: {}", e),
+        }
+    }
+
+    let mut robot = if args.model_only {
+        None
+    } else {
+        let profile = RobotProfile::resolve(&args.robot_profile)?.with_port(args.port.clone());
+        let mut robot = profile.connect()?;
+        if args.torque {
+            robot.enable()?;
+        } else {
+            robot.disable()?;
+        }
+        Some(robot)
+    };
+
+    for &rate in &args.rates {
+        let samples = run_rate(rate, &args, &mut robot, &mut model)?;
+        let period_ms = 1000.0 / rate;
+        let achieved = if samples.period.is_empty() {
+            0.0
+        } else {
+            1000.0 * samples.period.len() as f64 / samples.period.iter().sum::<f64>()
+        };
+        let jitter: Vec<f64> = samples
+            .period
+            .iter()
+            .map(|p| (p - period_ms).abs())
+            .collect();
+
+        println!(
+            "{} Hz ({:.2} ms period): {} cycles, achieved {:.1} Hz, {} missed deadlines",
+            rate,
+            period_ms,
+            samples.total.len(),
+            achieved,
+            samples.deadlines.missed
+        );
+        println!("  scheduler: {}", samples.deadlines);
+        println!(
+            "  {:<8} {:>8} {:>8} {:>8} {:>8}",
+            "ms", "p50", "p90", "p99", "max"
+        );
+        print_row("read", &samples.read);
+        print_row("step", &samples.step);
+        print_row("write", &samples.write);
+        print_row("total", &samples.total);
+        print_row("jitter", &jitter);
+        println!();
+    }
+
+    if let Some(robot) = robot.as_mut() {
+        robot.disable()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,336 @@
+//! HTTP inference server for a checkpointed CSDP `Model`.
+//!
+//! `POST /process` with `{"inputs": [[...], ...]}` (one row of `input_size` values per sample)
+//! answers `{"rates": [[...], ...]}`, the output layer's spike rate per sample over
+//! `--timesteps` steps. Requests that arrive within `--batch-window-ms` of each other are run
+//! as one batch. `GET /health` reports the model shape.
+
+use candle_core::{Device, Tensor};
+use clap::Parser;
+use custom_framework::models::context::ContextGatingConfig;
+use custom_framework::models::{EarlyExit, Model};
+use custom_framework::raster;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+/// Larger request bodies are rejected before they are read
+const MAX_BODY_BYTES: usize = 16 << 20;
+
+#[derive(Parser)]
+#[command(about = "Serve a checkpointed CSDP model over HTTP")]
+struct Args {
+    /// Model checkpoint (`model.safetensors`) to serve
+    #[arg(long)]
+    checkpoint: PathBuf,
+    #[arg(long)]
+    input_size: usize,
+    #[arg(long)]
+    output_size: usize,
+    /// Hidden layer sizes, e.g. `64,64`
+    #[arg(long, value_delimiter = ',', default_value = "64,64")]
+    hidden_sizes: Vec<usize>,
+    #[arg(long, default_value_t = 0.1)]
+    dt: f32,
+    /// Number of timesteps to run per request
+    #[arg(long, default_value_t = 40)]
+    timesteps: usize,
+    /// Stop a batch early once no output rate moves by more than this over 5 steps (see
+    /// `EarlyExit`); `--timesteps` stays the upper bound
+    #[arg(long)]
+    early_exit: Option<f32>,
+    /// Steps to run before `--early-exit` may stop a batch
+    #[arg(long, requires = "early_exit", default_value_t = 10)]
+    min_timesteps: usize,
+    /// cpu, cuda or cuda:N
+    #[arg(long, default_value = "cpu")]
+    device: String,
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    addr: String,
+    /// Maximum number of samples per batch
+    #[arg(long, default_value_t = 64)]
+    max_batch: usize,
+    /// How long to wait for more requests before running a batch
+    #[arg(long, default_value_t = 5)]
+    batch_window_ms: u64,
+    /// Number of tasks the checkpoint's context gates were drawn for (see
+    /// `Model::enable_context_gating`)
+    #[arg(long)]
+    context_tasks: Option<usize>,
+    /// Task whose context gates are applied
+    #[arg(long, requires = "context_tasks", default_value_t = 0)]
+    task: usize,
+}
+
+#[derive(Deserialize)]
+struct ProcessRequest {
+    inputs: Vec<Vec<f32>>,
+}
+
+#[derive(Serialize)]
+struct ProcessResponse {
+    rates: Vec<Vec<f32>>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Serialize)]
+struct HealthResponse {
+    status: &'static str,
+    input_size: usize,
+    output_size: usize,
+    timesteps: usize,
+}
+
+/// One request waiting for the model thread
+struct Job {
+    inputs: Vec<Vec<f32>>,
+    reply: Sender<Result<Vec<Vec<f32>>, String>>,
+}
+
+fn parse_device(name: &str) -> Result<Device, Box<dyn Error>> {
+    match name {
+        "cpu" => Ok(Device::Cpu),
+        "cuda" => Ok(Device::new_cuda(0)?),
+        _ => match name.strip_prefix("cuda:") {
+            Some(ordinal) => Ok(Device::new_cuda(ordinal.parse()?)?),
+            None => Err(format!("unknown device '{}'", name).into()),
+        },
+    }
+}
+
+/// Run the jobs as one batch and return the spike rates of each job's samples
+fn run_batch(
+    model: &mut Model,
+    jobs: &[Job],
+    input_size: usize,
+    timesteps: usize,
+) -> candle_core::Result<Vec<Vec<Vec<f32>>>> {
+    let rows: Vec<f32> = jobs
+        .iter()
+        .flat_map(|j| j.inputs.iter().flatten())
+        .copied()
+        .collect();
+    let batch = rows.len() / input_size;
+    let input = Tensor::from_vec(rows, (batch, input_size), &model.device)?.t()?;
+
+    let device = model.device.clone();
+    let out = model.process(&input, timesteps, true, &device)?;
+    let rates = raster::rates(&out.raster()?)?.t()?.to_vec2::<f32>()?;
+
+    let mut rates = rates.into_iter();
+    Ok(jobs
+        .iter()
+        .map(|j| rates.by_ref().take(j.inputs.len()).collect())
+        .collect())
+}
+
+/// Model loop: block for the first job, then gather more until the window closes or the
+/// batch is full
+fn serve_model(model: &mut Model, jobs: Receiver<Job>, args: &Args) {
+    let window = Duration::from_millis(args.batch_window_ms);
+    while let Ok(first) = jobs.recv() {
+        let deadline = Instant::now() + window;
+        let mut samples = first.inputs.len();
+        let mut batch = vec![first];
+        while samples < args.max_batch {
+            match jobs.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(job) => {
+                    samples += job.inputs.len();
+                    batch.push(job);
+                }
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        match run_batch(model, &batch, args.input_size, args.timesteps) {
+            Ok(results) => {
+                for (job, rates) in batch.into_iter().zip(results) {
+                    let _ = job.reply.send(Ok(rates));
+                }
+            }
+            Err(e) => {
+                log::error!("Batch of {} samples failed: {}", samples, e);
+                for job in batch {
+                    let _ = job.reply.send(Err(e.to_string()));
+                }
+            }
+        }
+    }
+}
+
+/// Read a request and return its method, path and body
+fn read_request(stream: &TcpStream) -> std::io::Result<(String, String, Vec<u8>)> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("request body of {} bytes is too large", content_length),
+        ));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok((method, path, body))
+}
+
+fn respond(mut stream: TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn error_body(message: impl Into<String>) -> String {
+    serde_json::to_string(&ErrorResponse {
+        error: message.into(),
+    })
+    .unwrap_or_default()
+}
+
+fn handle(
+    stream: TcpStream,
+    jobs: Sender<Job>,
+    health: &str,
+    input_size: usize,
+) -> std::io::Result<()> {
+    let (method, path, body) = read_request(&stream)?;
+    match (method.as_str(), path.as_str()) {
+        ("GET", "/health") => respond(stream, "200 OK", health),
+        ("POST", "/process") => {
+            let request: ProcessRequest = match serde_json::from_slice(&body) {
+                Ok(request) => request,
+                Err(e) => return respond(stream, "400 Bad Request", &error_body(e.to_string())),
+            };
+            if request.inputs.is_empty() {
+                return respond(stream, "400 Bad Request", &error_body("no inputs"));
+            }
+            if let Some(row) = request.inputs.iter().find(|r| r.len() != input_size) {
+                let message = format!("expected rows of length {}, got {}", input_size, row.len());
+                return respond(stream, "400 Bad Request", &error_body(message));
+            }
+
+            let (reply, result) = mpsc::channel();
+            let job = Job {
+                inputs: request.inputs,
+                reply,
+            };
+            if jobs.send(job).is_err() {
+                return respond(
+                    stream,
+                    "503 Service Unavailable",
+                    &error_body("model stopped"),
+                );
+            }
+            match result.recv() {
+                Ok(Ok(rates)) => {
+                    let body =
+                        serde_json::to_string(&ProcessResponse { rates }).unwrap_or_default();
+                    respond(stream, "200 OK", &body)
+                }
+                Ok(Err(e)) => respond(stream, "500 Internal Server Error", &error_body(e)),
+                Err(_) => respond(
+                    stream,
+                    "503 Service Unavailable",
+                    &error_body("model stopped"),
+                ),
+            }
+        }
+        _ => respond(stream, "404 Not Found", &error_body("not found")),
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    if args.timesteps == 0 {
+        return Err("--timesteps must be at least 1".into());
+    }
+
+    let device = parse_device(&args.device)?;
+    let mut model = Model::new(
+        args.input_size,
+        args.output_size,
+        args.hidden_sizes.clone(),
+        &device,
+        args.dt,
+        None,
+    )?;
+    if let Some(num_tasks) = args.context_tasks {
+        model.enable_context_gating(ContextGatingConfig::new(num_tasks))?;
+    }
+    model.load(&args.checkpoint)?;
+    if args.context_tasks.is_some() {
+        model.set_context(args.task)?;
+        log::info!("Context: task {}", args.task);
+    }
+    model.disable_learning();
+    model.early_exit = args
+        .early_exit
+        .map(|tolerance| EarlyExit::new(tolerance, args.min_timesteps));
+    log::info!("Loaded {:?}", args.checkpoint);
+
+    let health = serde_json::to_string(&HealthResponse {
+        status: "ok",
+        input_size: args.input_size,
+        output_size: args.output_size,
+        timesteps: args.timesteps,
+    })?;
+
+    let listener = TcpListener::bind(&args.addr)?;
+    log::info!("Serving on http://{}", listener.local_addr()?);
+
+    // Connections are handled on their own threads; the model stays on this one
+    let (jobs_tx, jobs_rx) = mpsc::channel();
+    let input_size = args.input_size;
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::warn!("Failed to accept connection: {}", e);
+                    continue;
+                }
+            };
+            let jobs = jobs_tx.clone();
+            let health = health.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = handle(stream, jobs, &health, input_size) {
+                    log::warn!("Request failed: {}", e);
+                }
+            });
+        }
+    });
+
+    serve_model(&mut model, jobs_rx, &args);
+    Ok(())
+}
@@ -0,0 +1,115 @@
+//! Training driven by an experiment config file.
+//!
+//! Every run gets its own timestamped directory, `<output-dir>/<name>-<YYYYMMDD-HHMMSS>` (the
+//! name defaults to the config file's stem), holding the resolved config, the git commit, the
+//! rotated checkpoints, the per-episode reward history in `metrics.csv` and its plot, see
+//! [`custom_framework::run`]. `--resume` continues the newest run of that name.
+//!
+//! ```json
+//! { "algo": "csdp5", "env": "grid", "hidden_sizes": [1000, 256], "n_episodes": 500, "seed": 7 }
+//! ```
+
+use clap::Parser;
+use custom_framework::experiment::{self, EnvKind, TrainOptions};
+use custom_framework::run::RunDir;
+use custom_framework::visualization;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(about = "Train an algorithm from an experiment config")]
+struct Args {
+    /// Experiment config (JSON)
+    config: PathBuf,
+    /// Runs are written to `<output-dir>/<name>-<timestamp>`
+    #[arg(long, default_value = "runs")]
+    output_dir: PathBuf,
+    /// Run name; defaults to the config file's stem
+    #[arg(long)]
+    name: Option<String>,
+    /// Continue the newest run of this name from its latest checkpoint
+    #[arg(long)]
+    resume: bool,
+    /// `cpu`, `cuda` or `cuda:<ordinal>`; overrides the config's `device`
+    #[arg(long)]
+    device: Option<String>,
+    /// Train headless instead of showing the TUI
+    #[arg(long)]
+    no_viz: bool,
+    /// Seed for weight init, spike sampling and host-side randomness; overrides the config's `seed`
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Serve Prometheus metrics on this address (e.g. 0.0.0.0:9100)
+    #[arg(long)]
+    metrics_addr: Option<String>,
+    /// If the CUDA device fails mid-run, continue on the CPU from the state at the failure or
+    /// the newest checkpoint
+    #[arg(long)]
+    cpu_fallback: bool,
+    /// Don't check the scaling of the environment's states before training
+    #[arg(long)]
+    no_preflight: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let visualize = !args.no_viz && cfg!(feature = "gui");
+    if visualize {
+        visualization::install_tui_logger();
+    } else {
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+        if !args.no_viz {
+            log::warn!("Built without the `gui` feature; training headless");
+        }
+    }
+
+    let mut config = experiment::load_config(Some(&args.config))?;
+    config.seed = args.seed.or(config.seed);
+    let device = experiment::parse_device(
+        args.device
+            .as_deref()
+            .or(config.device.as_deref())
+            .unwrap_or("cuda:0"),
+    )?;
+
+    let name = match args.name {
+        Some(name) => name,
+        None => args
+            .config
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or("cannot derive a run name from the config path; pass --name")?
+            .to_string(),
+    };
+    let run_dir = if args.resume {
+        RunDir::latest(&args.output_dir, &name)?
+            .ok_or_else(|| format!("no run named {} in {:?} to resume", name, args.output_dir))?
+    } else {
+        RunDir::create(&args.output_dir, &name)?
+    };
+    log::info!("Run directory: {:?}", run_dir.path());
+
+    let options = TrainOptions {
+        algo: config.algo.clone().unwrap_or_else(|| "csdp2".to_string()),
+        env: config.env.unwrap_or(EnvKind::Robot),
+        robot_profile: config
+            .robot_profile
+            .clone()
+            .unwrap_or_else(|| "follower".to_string()),
+        visualize,
+        infinite_epochs: false,
+        resume: args.resume,
+        checkpoint: None,
+        checkpoint_every: config.checkpoint_every,
+        keep_last: config.keep_last,
+        validate_every: config.validate_every,
+        validation_episodes: config.validation_episodes,
+        metrics_addr: args.metrics_addr,
+        run_dir: Some(run_dir),
+        record_session: None,
+        compare_checkpoint: None,
+        cpu_fallback: args.cpu_fallback,
+        skip_preflight: args.no_preflight,
+    };
+    experiment::train(options, &config, device)
+}
@@ -14,3 +14,68 @@ pub fn save_tensor_flat_csv(path: &str, t: &Tensor) -> CandleResult<()> {
     }
     Ok(())
 }
+
+/// Used memory of the first GPU in MiB, as reported by `nvidia-smi`.
+/// Returns None when the tool is unavailable (e.g. CPU-only machines).
+pub fn query_gpu_memory_mb() -> Option<f32> {
+    let output = std::process::Command::new("nvidia-smi")
+        .args(["--query-gpu=memory.used", "--format=csv,noheader,nounits"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()?
+        .trim()
+        .parse::<f32>()
+        .ok()
+}
+
+/// Write an 8-bit RGB image (`width * height * 3` bytes, row-major) as a PNG file.
+/// Only what the weight-visualization tools need: no palette, alpha or interlacing.
+pub fn write_png_rgb(
+    path: impl AsRef<std::path::Path>,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> std::io::Result<()> {
+    use flate2::Compression;
+    use flate2::write::ZlibEncoder;
+
+    let row_bytes = width as usize * 3;
+    if pixels.len() != row_bytes * height as usize {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} bytes for a {}x{} RGB image", pixels.len(), width, height),
+        ));
+    }
+
+    // Every scanline is prefixed with filter type 0 (none)
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in pixels.chunks(row_bytes.max(1)) {
+        encoder.write_all(&[0])?;
+        encoder.write_all(row)?;
+    }
+    let idat = encoder.finish()?;
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // bit depth 8, color type 2 (RGB), deflate, adaptive filtering, no interlace
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut file = std::io::BufWriter::new(File::create(path)?);
+    file.write_all(b"\x89PNG\r\n\x1a\n")?;
+    for (kind, data) in [(b"IHDR", &ihdr), (b"IDAT", &idat), (b"IEND", &Vec::new())] {
+        let mut crc = flate2::Crc::new();
+        crc.update(kind);
+        crc.update(data);
+        file.write_all(&(data.len() as u32).to_be_bytes())?;
+        file.write_all(kind)?;
+        file.write_all(data)?;
+        file.write_all(&crc.sum().to_be_bytes())?;
+    }
+    file.flush()
+}
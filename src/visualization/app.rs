@@ -1,4 +1,7 @@
-use super::{ModelStructure, VisualizationState};
+use super::layout::{self, CANVAS_HEIGHT, CANVAS_WIDTH, ForceLayout, LayoutMode};
+use super::replay::SessionReplay;
+use super::selection::parse_neuron_selection;
+use super::{LayerVisInfo, ModelStructure, StepGranularity, TrainingCommand, VisualizationState};
 use crate::synapse::LayerId;
 use crossterm::event::{self, Event, KeyCode, MouseEventKind};
 use ratatui::{
@@ -16,13 +19,28 @@ use ratatui::{
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+const TRACE_COLORS: [Color; 6] = [
+    Color::Yellow,
+    Color::Cyan,
+    Color::Magenta,
+    Color::Green,
+    Color::LightRed,
+    Color::LightBlue,
+];
+
+/// Time between frames of a playing replay, on top of the throttling delay
+const REPLAY_FRAME_MS: u64 = 200;
+
+/// A neuron of the selected layer whose activity is plotted as an individual trace
+struct TrackedTrace {
+    neuron: usize,
+    color_idx: usize,
+}
+
 pub struct NeuralNetworkVisualizerApp {
     vis_state: Arc<Mutex<VisualizationState>>,
-    layer_velocities: std::collections::HashMap<LayerId, (f32, f32)>,
-    repel_force: f32,
-    link_force: f32,
-    center_force: f32,
-    link_distance: f32,
+    layout: ForceLayout,
+    layout_mode: LayoutMode,
 
     selected_layer_id: Option<LayerId>,
     spike_history: Vec<Vec<f32>>,
@@ -32,17 +50,25 @@ pub struct NeuralNetworkVisualizerApp {
     show_help: bool,
     active_tab: usize,
     user_scrolled: bool,
+
+    tracked_traces: Vec<TrackedTrace>,
+    focused_trace: usize,
+    selection_input: Option<String>,
+
+    show_perf: bool,
+    last_gpu_poll: Option<std::time::Instant>,
+
+    /// Recorded session shown instead of a live training run
+    replay: Option<SessionReplay>,
+    last_replay_frame: std::time::Instant,
 }
 
 impl NeuralNetworkVisualizerApp {
     pub fn new(vis_state: Arc<Mutex<VisualizationState>>) -> Self {
         Self {
             vis_state,
-            layer_velocities: std::collections::HashMap::new(),
-            repel_force: 5000.0,
-            link_force: 0.01,
-            center_force: 0.005,
-            link_distance: 150.0,
+            layout: ForceLayout::default(),
+            layout_mode: LayoutMode::default(),
             selected_layer_id: None,
             spike_history: Vec::new(),
             displayed_epoch: 0,
@@ -50,9 +76,23 @@ impl NeuralNetworkVisualizerApp {
             show_help: false,
             active_tab: 0,
             user_scrolled: false,
+            tracked_traces: Vec::new(),
+            focused_trace: 0,
+            selection_input: None,
+            show_perf: false,
+            last_gpu_poll: None,
+            replay: None,
+            last_replay_frame: std::time::Instant::now(),
         }
     }
 
+    /// Show a recorded session, starting at its first frame, instead of live training
+    pub fn with_replay(mut self, replay: SessionReplay) -> Self {
+        self.replay = Some(replay);
+        self.show_replay_frame();
+        self
+    }
+
     pub fn run<B: Backend>(
         &mut self,
         terminal: &mut ratatui::Terminal<B>,
@@ -82,16 +122,99 @@ impl NeuralNetworkVisualizerApp {
             }
 
             if last_tick.elapsed() >= tick_rate {
+                self.advance_replay();
                 let vis_state = Arc::clone(&self.vis_state);
                 if let Ok(mut state) = vis_state.lock() {
-                    self.update_force_layout(&mut state.model_structure);
+                    self.update_layout(&mut state.model_structure);
                 }
+                self.poll_gpu_memory();
                 terminal.draw(|f| self.draw(f))?;
                 last_tick = std::time::Instant::now();
             }
         }
     }
 
+    /// Publish the replay's current frame to the shared state
+    fn show_replay_frame(&mut self) {
+        if let Some(replay) = &self.replay
+            && let Ok(mut state) = self.vis_state.lock()
+        {
+            replay.show(&mut state);
+        }
+        self.last_replay_frame = std::time::Instant::now();
+    }
+
+    /// Move a playing replay on by a frame once the frame time has passed; it stops at the end
+    fn advance_replay(&mut self) {
+        let delay = self.vis_state.lock().map_or(0, |state| state.delay_ms);
+        let Some(replay) = self.replay.as_mut() else {
+            return;
+        };
+        if !replay.playing
+            || self.last_replay_frame.elapsed() < Duration::from_millis(REPLAY_FRAME_MS + delay)
+        {
+            return;
+        }
+        replay.step(1);
+        if replay.at_end() {
+            replay.playing = false;
+        }
+        self.show_replay_frame();
+    }
+
+    /// Scrub through a replay. Returns false for keys the replay doesn't take over, which are
+    /// handled as usual; the training controls do nothing while replaying.
+    fn handle_replay_key(&mut self, code: KeyCode) -> bool {
+        let Some(replay) = self.replay.as_mut() else {
+            return false;
+        };
+        let jump = (replay.len() / 10).max(1) as isize;
+        match code {
+            KeyCode::Char('p' | ' ') => {
+                if replay.at_end() {
+                    replay.seek(0);
+                }
+                replay.playing = !replay.playing;
+            }
+            KeyCode::Char('n') => {
+                replay.playing = false;
+                replay.step(1);
+            }
+            KeyCode::Char('m') => {
+                replay.playing = false;
+                replay.step(-1);
+            }
+            KeyCode::PageDown => replay.step(jump),
+            KeyCode::PageUp => replay.step(-jump),
+            KeyCode::Home => replay.seek(0),
+            KeyCode::End => replay.seek(replay.len()),
+            KeyCode::Char('t' | 'r' | 's' | 'l') => return true,
+            _ => return false,
+        }
+        self.show_replay_frame();
+        true
+    }
+
+    /// Refresh GPU memory usage for the perf overlay; shelling out is slow, so only every 2s
+    fn poll_gpu_memory(&mut self) {
+        if !self.show_perf
+            || self
+                .last_gpu_poll
+                .is_some_and(|t| t.elapsed() < Duration::from_secs(2))
+        {
+            return;
+        }
+        self.last_gpu_poll = Some(std::time::Instant::now());
+
+        let mem = crate::utils::query_gpu_memory_mb();
+        if let Ok(mut state) = self.vis_state.lock() {
+            state
+                .perf_stats
+                .get_or_insert_with(Default::default)
+                .gpu_memory_mb = mem;
+        }
+    }
+
     fn cycle_selection(&mut self, dir: isize) {
         if let Ok(mut state) = self.vis_state.lock() {
             let layers = &state.model_structure.layers;
@@ -116,11 +239,103 @@ impl NeuralNetworkVisualizerApp {
             self.selected_layer_id = state.selected_layer_id;
             state.epoch_spike_history = None;
             self.spike_history.clear();
+            self.tracked_traces.clear();
+            self.focused_trace = 0;
+        }
+    }
+
+    /// Replace the tracked traces with a parsed selection such as "0-15, 42"
+    fn apply_selection(&mut self, input: &str) {
+        let layer_size = self.vis_state.lock().ok().and_then(|state| {
+            let id = state.selected_layer_id?;
+            state
+                .model_structure
+                .layers
+                .iter()
+                .find(|l| l.id == id)
+                .map(|l| l.size)
+        });
+
+        let Some(layer_size) = layer_size else {
+            log::warn!("Select a layer before tracking neurons");
+            return;
+        };
+
+        match parse_neuron_selection(input, layer_size) {
+            Ok(neurons) => {
+                self.tracked_traces = neurons
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, neuron)| TrackedTrace {
+                        neuron,
+                        color_idx: i % TRACE_COLORS.len(),
+                    })
+                    .collect();
+                self.focused_trace = 0;
+            }
+            Err(e) => log::warn!("Invalid neuron selection: {}", e),
+        }
+    }
+
+    /// Dump the tracked neuron traces of the displayed epoch to CSV, one column per neuron
+    fn export_tracked_traces(&self, path: &std::path::Path) -> std::io::Result<()> {
+        use std::io::Write;
+        if let Some(parent) = path.parent()
+            && !parent.exists()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::File::create(path)?;
+
+        let header = self
+            .tracked_traces
+            .iter()
+            .map(|t| format!("n{}", t.neuron))
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(file, "timestep,{}", header)?;
+
+        for (t, activity) in self.spike_history.iter().enumerate() {
+            let row = self
+                .tracked_traces
+                .iter()
+                .map(|tr| activity.get(tr.neuron).copied().unwrap_or(0.0).to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            writeln!(file, "{},{}", t, row)?;
+        }
+        Ok(())
+    }
+
+    fn handle_selection_input(&mut self, code: KeyCode) {
+        let Some(buffer) = self.selection_input.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Enter => {
+                let input = std::mem::take(buffer);
+                self.selection_input = None;
+                self.apply_selection(&input);
+            }
+            KeyCode::Esc => {
+                self.selection_input = None;
+            }
+            KeyCode::Backspace => {
+                buffer.pop();
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() || c == ',' || c == '-' || c == ' ' => {
+                buffer.push(c);
+            }
+            _ => {}
         }
     }
 
     fn handle_event(&mut self, event: Event) {
         match event {
+            Event::Key(key) if self.selection_input.is_some() => {
+                self.handle_selection_input(key.code);
+            }
+            Event::Key(key) if self.handle_replay_key(key.code) => {}
             Event::Key(key) => match key.code {
                 KeyCode::Char('q') => {
                     if let Ok(mut state) = self.vis_state.lock() {
@@ -129,16 +344,45 @@ impl NeuralNetworkVisualizerApp {
                 }
                 KeyCode::Char('p') => {
                     if let Ok(mut state) = self.vis_state.lock() {
-                        state.is_paused = !state.is_paused;
+                        let command = if state.is_paused {
+                            TrainingCommand::Resume
+                        } else {
+                            TrainingCommand::Pause
+                        };
+                        state.send_command(command);
+                    }
+                }
+                KeyCode::Char('t') => {
+                    if let Ok(mut state) = self.vis_state.lock() {
+                        state.send_command(TrainingCommand::Stop);
+                    }
+                }
+                KeyCode::Char('r') => {
+                    if let Ok(mut state) = self.vis_state.lock() {
+                        state.send_command(TrainingCommand::ResetModel);
                     }
                 }
                 KeyCode::Char('s') => {
                     if let Ok(mut state) = self.vis_state.lock() {
-                        state.save_requested = true;
+                        state.send_command(TrainingCommand::SaveCheckpoint);
                         let path = std::path::Path::new("checkpoints/epoch_rewards.csv");
                         let _ = state.save_graphs_to_csv(path);
                     }
                 }
+                KeyCode::Char('n') => {
+                    if let Ok(mut state) = self.vis_state.lock()
+                        && state.is_paused
+                    {
+                        state.step_request = Some(StepGranularity::Sample);
+                    }
+                }
+                KeyCode::Char('m') => {
+                    if let Ok(mut state) = self.vis_state.lock()
+                        && state.is_paused
+                    {
+                        state.step_request = Some(StepGranularity::Timestep);
+                    }
+                }
                 KeyCode::Char('l') => {
                     if let Ok(mut state) = self.vis_state.lock() {
                         state.load_requested = true;
@@ -149,6 +393,33 @@ impl NeuralNetworkVisualizerApp {
                         state.sort_probabilities = !state.sort_probabilities;
                     }
                 }
+                KeyCode::Char('/') => {
+                    self.selection_input = Some(String::new());
+                }
+                KeyCode::Char(',') => {
+                    self.focused_trace = self.focused_trace.saturating_sub(1);
+                }
+                KeyCode::Char('.') if self.focused_trace + 1 < self.tracked_traces.len() => {
+                    self.focused_trace += 1;
+                }
+                KeyCode::Char('c') => {
+                    if let Some(trace) = self.tracked_traces.get_mut(self.focused_trace) {
+                        trace.color_idx = (trace.color_idx + 1) % TRACE_COLORS.len();
+                    }
+                }
+                KeyCode::Char('x') if self.focused_trace < self.tracked_traces.len() => {
+                    self.tracked_traces.remove(self.focused_trace);
+                    self.focused_trace = self
+                        .focused_trace
+                        .min(self.tracked_traces.len().saturating_sub(1));
+                }
+                KeyCode::Char('e') => {
+                    let path = std::path::Path::new("checkpoints/tracked_traces.csv");
+                    match self.export_tracked_traces(path) {
+                        Ok(_) => log::info!("Exported tracked traces to {:?}", path),
+                        Err(e) => log::warn!("Failed to export tracked traces: {}", e),
+                    }
+                }
                 KeyCode::Esc => {
                     self.show_help = false;
                 }
@@ -165,8 +436,38 @@ impl NeuralNetworkVisualizerApp {
                         state.delay_ms = state.delay_ms.saturating_sub(10);
                     }
                 }
+                KeyCode::Char('f') => {
+                    self.show_perf = !self.show_perf;
+                    if let Ok(mut state) = self.vis_state.lock() {
+                        state.profiling_requested = self.show_perf;
+                    }
+                }
+                KeyCode::Char('g') => {
+                    self.layout_mode = self.layout_mode.toggled();
+                    // The force layout starts over from wherever the layers were drawn
+                    self.layout.reset_velocities();
+                }
+                KeyCode::Char('k') => {
+                    if let Some(id) = self.selected_layer_id {
+                        if self.layout.is_pinned(id) {
+                            self.layout.unpin(id);
+                        } else {
+                            self.layout.pin(id);
+                        }
+                    }
+                }
+                KeyCode::Char('b') => {
+                    if let Ok(mut state) = self.vis_state.lock() {
+                        state.pin_live_as_comparison();
+                    }
+                }
+                KeyCode::Char('B') => {
+                    if let Ok(mut state) = self.vis_state.lock() {
+                        state.comparison = None;
+                    }
+                }
                 KeyCode::Tab => {
-                    self.active_tab = (self.active_tab + 1) % 3;
+                    self.active_tab = (self.active_tab + 1) % 4;
                 }
                 KeyCode::Right => {
                     self.cycle_selection(1);
@@ -203,74 +504,10 @@ impl NeuralNetworkVisualizerApp {
         }
     }
 
-    // Force layout physics for beautiful representation
-    fn update_force_layout(&mut self, model: &mut ModelStructure) {
-        const DAMPING: f32 = 0.8;
-        const MIN_DISTANCE: f32 = 50.0;
-
-        let num_layers = model.layers.len();
-        if num_layers == 0 {
-            return;
-        }
-
-        for layer in &model.layers {
-            self.layer_velocities.entry(layer.id).or_insert((0.0, 0.0));
-        }
-
-        let mut forces: Vec<(f32, f32)> = vec![(0.0, 0.0); num_layers];
-        let center_x = 500.0;
-        let center_y = 200.0;
-
-        for i in 0..num_layers {
-            for j in (i + 1)..num_layers {
-                let dx = model.layers[j].position.x - model.layers[i].position.x;
-                let dy = model.layers[j].position.y - model.layers[i].position.y;
-                let dist_sq = dx * dx + dy * dy;
-                let dist = dist_sq.sqrt().max(MIN_DISTANCE);
-                let force = self.repel_force / dist_sq;
-                let fx = force * dx / dist;
-                let fy = force * dy / dist;
-                forces[i].0 -= fx;
-                forces[i].1 -= fy;
-                forces[j].0 += fx;
-                forces[j].1 += fy;
-            }
-        }
-
-        for synapse in &model.synapses {
-            if let (Some(pre_idx), Some(post_idx)) = (
-                model.layers.iter().position(|l| l.id == synapse.pre_layer),
-                model.layers.iter().position(|l| l.id == synapse.post_layer),
-            ) {
-                let dx = model.layers[post_idx].position.x - model.layers[pre_idx].position.x;
-                let dy = model.layers[post_idx].position.y - model.layers[pre_idx].position.y;
-                let dist = (dx * dx + dy * dy).sqrt();
-                let force = self.link_force * (dist - self.link_distance);
-                let fx = force * dx / dist.max(1.0);
-                let fy = force * dy / dist.max(1.0);
-                forces[pre_idx].0 += fx;
-                forces[pre_idx].1 += fy;
-                forces[post_idx].0 -= fx;
-                forces[post_idx].1 -= fy;
-            }
-        }
-
-        for (i, force) in forces.iter_mut().enumerate().take(num_layers) {
-            let dx = center_x - model.layers[i].position.x;
-            let dy = center_y - model.layers[i].position.y;
-            force.0 += dx * self.center_force;
-            force.1 += dy * self.center_force;
-        }
-
-        for (i, layer) in model.layers.iter_mut().enumerate() {
-            let vel = self.layer_velocities.get_mut(&layer.id).unwrap();
-            vel.0 = (vel.0 + forces[i].0) * DAMPING;
-            vel.1 = (vel.1 + forces[i].1) * DAMPING;
-            layer.position.x += vel.0;
-            layer.position.y += vel.1;
-            // Bound within Canvas space (0..1000, 0..400)
-            layer.position.x = layer.position.x.clamp(50.0, 950.0);
-            layer.position.y = layer.position.y.clamp(50.0, 350.0);
+    fn update_layout(&mut self, model: &mut ModelStructure) {
+        match self.layout_mode {
+            LayoutMode::Force => self.layout.step(model),
+            LayoutMode::Layered => layout::apply_layered(model),
         }
     }
 
@@ -300,7 +537,7 @@ impl NeuralNetworkVisualizerApp {
 
             self.draw_header(f, chunks[0], &state);
 
-            let titles = vec!["Network Topology", "Environment", "Analysis"]
+            let titles = vec!["Network Topology", "Environment", "Analysis", "Compare"]
                 .into_iter()
                 .map(|t| Line::from(Span::styled(t, Style::default().fg(Color::White))))
                 .collect::<Vec<_>>();
@@ -323,7 +560,22 @@ impl NeuralNetworkVisualizerApp {
                         .constraints([Constraint::Percentage(75), Constraint::Percentage(25)])
                         .split(chunks[2]);
                     self.draw_network(f, main_chunks[0], &state.model_structure);
-                    self.draw_details(f, main_chunks[1], &state.model_structure);
+                    if state.robot_status.is_empty() {
+                        self.draw_details(f, main_chunks[1], &state.model_structure);
+                    } else {
+                        // A name and a header row per arm, a row per joint, and the borders
+                        let rows: usize =
+                            state.robot_status.iter().map(|arm| arm.joints() + 2).sum();
+                        let side_chunks = Layout::default()
+                            .direction(Direction::Vertical)
+                            .constraints([
+                                Constraint::Min(5),
+                                Constraint::Length(rows as u16 + 2),
+                            ])
+                            .split(main_chunks[1]);
+                        self.draw_details(f, side_chunks[0], &state.model_structure);
+                        self.draw_robot_status(f, side_chunks[1], &state);
+                    }
                 }
                 1 => {
                     let env_chunks = Layout::default()
@@ -342,15 +594,45 @@ impl NeuralNetworkVisualizerApp {
                             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
                             .split(chunks[2]);
                         self.draw_rewards(f, side_chunks[0], &state);
-                        self.draw_raster(f, side_chunks[1], &state.model_structure);
+                        if self.tracked_traces.is_empty() {
+                            self.draw_raster(f, side_chunks[1], &state.model_structure);
+                        } else {
+                            let trace_chunks = Layout::default()
+                                .direction(Direction::Vertical)
+                                .constraints([
+                                    Constraint::Percentage(50),
+                                    Constraint::Percentage(50),
+                                ])
+                                .split(side_chunks[1]);
+                            self.draw_raster(f, trace_chunks[0], &state.model_structure);
+                            self.draw_traces(f, trace_chunks[1]);
+                        }
                     }
                 }
+                3 => {
+                    self.draw_comparison(f, chunks[2], &state);
+                }
                 _ => {}
             }
+
+            if self.show_perf {
+                self.draw_perf_overlay(f, chunks[2], &state);
+            }
         }
 
         self.draw_logs(f, chunks[3]);
 
+        if let Some(input) = &self.selection_input {
+            let area = centered_rect(50, 20, size);
+            let p = Paragraph::new(format!("{}_", input)).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Track Neurons (e.g. 0-15, 42) - Enter to apply, Esc to cancel"),
+            );
+            f.render_widget(ratatui::widgets::Clear, area);
+            f.render_widget(p, area);
+        }
+
         if self.show_help {
             self.draw_help(f, size);
         }
@@ -499,20 +781,36 @@ impl NeuralNetworkVisualizerApp {
     }
 
     fn draw_header(&self, f: &mut Frame, area: Rect, state: &VisualizationState) {
+        let run_state = match &self.replay {
+            Some(replay) => format!(
+                "REPLAY {}/{}{}",
+                replay.position() + 1,
+                replay.len(),
+                if replay.playing { " PLAYING" } else { "" }
+            ),
+            None if state.is_paused => "PAUSED".to_string(),
+            None => "RUNNING".to_string(),
+        };
         let text = format!(
             "Epoch: {}/{} | Iter: {} | Speed: {:.1} it/s | State: {} | Delay: {}ms | Press '?' for Help",
             state.runtime_stats.epoch,
             state.total_epochs,
             state.runtime_stats.iteration,
             state.runtime_stats.iterations_per_second,
-            if state.is_paused { "PAUSED" } else { "RUNNING" },
+            run_state,
             state.delay_ms
         );
 
-        let progress = if state.total_epochs > 0 {
-            (state.runtime_stats.epoch as f32 / state.total_epochs as f32).clamp(0.0, 1.0)
-        } else {
-            0.0
+        // A replay's gauge shows how far into the recording the current frame is
+        let progress = match &self.replay {
+            Some(replay) if replay.len() > 1 => {
+                replay.position() as f32 / (replay.len() - 1) as f32
+            }
+            Some(_) => 1.0,
+            None if state.total_epochs > 0 => {
+                (state.runtime_stats.epoch as f32 / state.total_epochs as f32).clamp(0.0, 1.0)
+            }
+            None => 0.0,
         };
 
         let gauge = Gauge::default()
@@ -534,14 +832,14 @@ impl NeuralNetworkVisualizerApp {
     }
 
     fn draw_network(&self, f: &mut Frame, area: Rect, model: &ModelStructure) {
+        let title = match self.layout_mode {
+            LayoutMode::Force => "Network Topology (Blue=Frozen, Red=Ablated)",
+            LayoutMode::Layered => "Network Topology, Layered (Blue=Frozen, Red=Ablated)",
+        };
         let canvas = Canvas::default()
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Network Topology"),
-            )
-            .x_bounds([0.0, 1000.0])
-            .y_bounds([0.0, 400.0])
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .x_bounds([0.0, CANVAS_WIDTH as f64])
+            .y_bounds([0.0, CANVAS_HEIGHT as f64])
             .paint(|ctx| {
                 // Draw Curved Synapses
                 for synapse in &model.synapses {
@@ -560,6 +858,15 @@ impl NeuralNetworkVisualizerApp {
                         let ctrl_x = mid_x + nx;
                         let ctrl_y = mid_y + ny;
 
+                        // Ablated connections in red, frozen ones in blue
+                        let color = if !synapse.enabled {
+                            Color::Red
+                        } else if !synapse.is_learning {
+                            Color::Blue
+                        } else {
+                            Color::DarkGray
+                        };
+
                         let mut prev_x = pre.position.x as f64;
                         let mut prev_y = pre.position.y as f64;
 
@@ -578,7 +885,7 @@ impl NeuralNetworkVisualizerApp {
                                 y1: prev_y,
                                 x2: cur_x,
                                 y2: cur_y,
-                                color: Color::DarkGray,
+                                color,
                             });
                             prev_x = cur_x;
                             prev_y = cur_y;
@@ -624,9 +931,19 @@ impl NeuralNetworkVisualizerApp {
 
     fn draw_details(&self, f: &mut Frame, area: Rect, model: &ModelStructure) {
         let mut items = Vec::new();
+        let mut area = area;
 
         if let Some(layer_id) = self.selected_layer_id {
             if let Some(layer) = model.layers.iter().find(|l| l.id == layer_id) {
+                // Layers with a topography get a map of their neurons below the details
+                if layer.topography.is_some() {
+                    let chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Length(6), Constraint::Min(0)])
+                        .split(area);
+                    area = chunks[0];
+                    self.draw_neuron_map(f, chunks[1], layer);
+                }
                 items.push(ListItem::new(format!("Selected Layer: {}", layer.name)));
                 items.push(ListItem::new(format!("Type: {}", layer.layer_type)));
                 items.push(ListItem::new(format!("Size: {} neurons", layer.size)));
@@ -655,6 +972,235 @@ impl NeuralNetworkVisualizerApp {
         f.render_widget(list, area);
     }
 
+    /// The selected layer's neurons at their topography's coordinates, spiking ones highlighted
+    fn draw_neuron_map(&self, f: &mut Frame, area: Rect, layer: &LayerVisInfo) {
+        let Some(topography) = layer.topography else {
+            return;
+        };
+        let (mut spiking, mut silent) = (Vec::new(), Vec::new());
+        for (i, (x, y)) in topography.coordinates().into_iter().enumerate() {
+            // Row 0 of a grid is at the top
+            let point = (x as f64, 1.0 - y as f64);
+            if layer.current_activity.get(i).is_some_and(|&a| a > 0.5) {
+                spiking.push(point);
+            } else {
+                silent.push(point);
+            }
+        }
+        let canvas = Canvas::default()
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Neuron Map {:?} (Yellow=Spiking)", topography)),
+            )
+            .x_bounds([0.0, 1.0])
+            .y_bounds([0.0, 1.0])
+            .paint(move |ctx| {
+                ctx.draw(&Points {
+                    coords: &silent,
+                    color: Color::DarkGray,
+                });
+                ctx.draw(&Points {
+                    coords: &spiking,
+                    color: Color::Yellow,
+                });
+            });
+        f.render_widget(canvas, area);
+    }
+
+    fn draw_robot_status(&self, f: &mut Frame, area: Rect, state: &VisualizationState) {
+        let mut items = Vec::new();
+
+        for arm in &state.robot_status {
+            items.push(ListItem::new(Span::styled(
+                arm.name.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+            items.push(ListItem::new(" J   Pos    Goal   Load"));
+            for j in 0..arm.joints() {
+                let fmt = |v: Option<&f64>| match v {
+                    Some(v) => format!("{:>6.2}", v),
+                    None => format!("{:>6}", "-"),
+                };
+                let load = arm.loads.get(j).copied().unwrap_or(0.0);
+                let load_color = if load.abs() > 500.0 {
+                    Color::Red
+                } else if load.abs() > 250.0 {
+                    Color::Yellow
+                } else {
+                    Color::White
+                };
+                items.push(ListItem::new(Line::from(vec![
+                    Span::raw(format!(
+                        " {} {} {} ",
+                        j,
+                        fmt(arm.positions.get(j)),
+                        fmt(arm.goals.get(j))
+                    )),
+                    Span::styled(fmt(arm.loads.get(j)), Style::default().fg(load_color)),
+                ])));
+            }
+        }
+
+        let list =
+            List::new(items).block(Block::default().borders(Borders::ALL).title("Robot Status"));
+        f.render_widget(list, area);
+    }
+
+    fn draw_perf_overlay(&self, f: &mut Frame, main_area: Rect, state: &VisualizationState) {
+        let mut lines = Vec::new();
+
+        match &state.perf_stats {
+            Some(perf) => {
+                let phase = |name: &str, ms: f32| {
+                    let rate = if ms > 0.0 { 1000.0 / ms } else { 0.0 };
+                    Line::from(format!("{:<14}{:>8.3} ms {:>9.0}/s", name, ms, rate))
+                };
+                for (name, ms) in &perf.layer_step_ms {
+                    lines.push(phase(name, *ms));
+                }
+                lines.push(phase("syn forward", perf.synapse_forward_ms));
+                lines.push(phase("syn update", perf.synapse_update_ms));
+                lines.push(Line::from(""));
+                lines.push(Line::from(format!(
+                    "Timesteps/sec: {:.1}",
+                    perf.timesteps_per_second
+                )));
+                lines.push(Line::from(match perf.gpu_memory_mb {
+                    Some(mb) => format!("GPU memory:    {:.0} MiB", mb),
+                    None => "GPU memory:    n/a".to_string(),
+                }));
+            }
+            // Only csdp3 profiles its steps for the overlay
+            None => lines.push(Line::from("No profiling data (csdp3 only)")),
+        }
+
+        let width = 44.min(main_area.width);
+        let height = (lines.len() as u16 + 2).min(main_area.height);
+        let area = Rect {
+            x: main_area.x + main_area.width - width,
+            y: main_area.y,
+            width,
+            height,
+        };
+
+        let p = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Performance (per step)"),
+        );
+        f.render_widget(ratatui::widgets::Clear, area);
+        f.render_widget(p, area);
+    }
+
+    fn draw_comparison(&self, f: &mut Frame, area: Rect, state: &VisualizationState) {
+        let Some((label, reference)) = &state.comparison else {
+            let p = Paragraph::new(
+                "No reference snapshot. Press 'b' to pin the live model as the reference.",
+            )
+            .block(Block::default().borders(Borders::ALL).title("Compare"));
+            f.render_widget(p, area);
+            return;
+        };
+        let live = &state.model_structure;
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+
+        // Diff coloring is relative to the other side: green where a value grew, red where it shrank
+        let diff_style = |this: f32, other: Option<f32>| {
+            let color = match other {
+                Some(o) if this > o + 1e-4 => Color::Green,
+                Some(o) if this < o - 1e-4 => Color::Red,
+                Some(_) => Color::White,
+                None => Color::DarkGray,
+            };
+            Style::default().fg(color)
+        };
+        let firing_rate = |size: usize, spikes: usize| {
+            if size > 0 {
+                spikes as f32 / size as f32 * 100.0
+            } else {
+                0.0
+            }
+        };
+
+        for (col, (title, this, other)) in [
+            (label.as_str(), reference, live),
+            ("Live", live, reference),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut items = vec![ListItem::new(Span::styled(
+                "Layers (firing rate)",
+                Style::default().add_modifier(Modifier::BOLD),
+            ))];
+            for layer in &this.layers {
+                let rate = firing_rate(layer.size, layer.spike_count);
+                let other_rate = other
+                    .layers
+                    .iter()
+                    .find(|l| l.id == layer.id)
+                    .map(|l| firing_rate(l.size, l.spike_count));
+                items.push(ListItem::new(Span::styled(
+                    format!("{:<12} {:>6.1}%", layer.name, rate),
+                    diff_style(rate, other_rate),
+                )));
+            }
+
+            items.push(ListItem::new(""));
+            items.push(ListItem::new(Span::styled(
+                "Synapses (mean / std / min / max; * frozen, x ablated)",
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+            for syn in &this.synapses {
+                let other_stats = other
+                    .synapses
+                    .iter()
+                    .find(|s| s.id == syn.id)
+                    .map(|s| &s.weight_stats);
+                let w = &syn.weight_stats;
+                items.push(ListItem::new(Line::from(vec![
+                    Span::raw(format!(
+                        "{:>2}->{:<2}{} ",
+                        syn.pre_layer,
+                        syn.post_layer,
+                        if !syn.enabled {
+                            "x"
+                        } else if !syn.is_learning {
+                            "*"
+                        } else {
+                            " "
+                        }
+                    )),
+                    Span::styled(
+                        format!("{:>8.4} ", w.mean),
+                        diff_style(w.mean, other_stats.map(|o| o.mean)),
+                    ),
+                    Span::styled(
+                        format!("{:>8.4} ", w.std),
+                        diff_style(w.std, other_stats.map(|o| o.std)),
+                    ),
+                    Span::styled(
+                        format!("{:>8.4} ", w.min),
+                        diff_style(w.min, other_stats.map(|o| o.min)),
+                    ),
+                    Span::styled(
+                        format!("{:>8.4}", w.max),
+                        diff_style(w.max, other_stats.map(|o| o.max)),
+                    ),
+                ])));
+            }
+
+            let list =
+                List::new(items).block(Block::default().borders(Borders::ALL).title(title));
+            f.render_widget(list, columns[col]);
+        }
+    }
+
     fn draw_raster(&self, f: &mut Frame, area: Rect, model: &ModelStructure) {
         let title = format!("Spike Raster (Epoch {})", self.displayed_epoch);
         if let Some(layer_id) = self.selected_layer_id
@@ -711,6 +1257,64 @@ impl NeuralNetworkVisualizerApp {
         f.render_widget(p, area);
     }
 
+    fn draw_traces(&self, f: &mut Frame, area: Rect) {
+        let num_timesteps = self.spike_history.len() as f64;
+        let num_traces = self.tracked_traces.len() as f64;
+
+        // Stack traces vertically so binary spike trains stay readable
+        let series: Vec<Vec<(f64, f64)>> = self
+            .tracked_traces
+            .iter()
+            .enumerate()
+            .map(|(k, trace)| {
+                let offset = num_traces - 1.0 - k as f64;
+                self.spike_history
+                    .iter()
+                    .enumerate()
+                    .map(|(t, activity)| {
+                        let v = activity.get(trace.neuron).copied().unwrap_or(0.0) as f64;
+                        (t as f64, offset + v.clamp(0.0, 1.0) * 0.8)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let datasets = self
+            .tracked_traces
+            .iter()
+            .zip(series.iter())
+            .enumerate()
+            .map(|(k, (trace, points))| {
+                let mut style = Style::default().fg(TRACE_COLORS[trace.color_idx]);
+                if k == self.focused_trace {
+                    style = style.add_modifier(Modifier::BOLD);
+                }
+                let marker = if k == self.focused_trace { ">" } else { " " };
+                ratatui::widgets::Dataset::default()
+                    .name(format!("{}n{}", marker, trace.neuron))
+                    .marker(ratatui::symbols::Marker::Braille)
+                    .graph_type(ratatui::widgets::GraphType::Line)
+                    .style(style)
+                    .data(points)
+            })
+            .collect::<Vec<_>>();
+
+        let chart = ratatui::widgets::Chart::new(datasets)
+            .block(
+                Block::default()
+                    .title("Tracked Neurons (,/. focus, c color, x remove, e export)")
+                    .borders(Borders::ALL),
+            )
+            .x_axis(
+                ratatui::widgets::Axis::default()
+                    .bounds([0.0, num_timesteps.max(1.0)])
+                    .labels(vec![Span::raw("0"), Span::raw(format!("{}", num_timesteps))]),
+            )
+            .y_axis(ratatui::widgets::Axis::default().bounds([0.0, num_traces.max(1.0)]));
+
+        f.render_widget(chart, area);
+    }
+
     fn draw_rewards(&self, f: &mut Frame, area: Rect, state: &VisualizationState) {
         let history = &state.epoch_rewards;
         if history.is_empty() {
@@ -840,12 +1444,28 @@ impl NeuralNetworkVisualizerApp {
             Line::from("  ?       Toggle this help menu"),
             Line::from("  q   Quit application"),
             Line::from("  p       Pause/Resume Training"),
+            Line::from("  t       Stop Training (keeps the visualizer open)"),
+            Line::from("  n       Step One Sample (while paused)"),
+            Line::from("  m       Step One Timestep (while paused; csdp1/csdp2)"),
             Line::from("  [/]     Decrease/Increase Simulation Throttling Delay"),
             Line::from("  s       Save Model Checkpoint"),
+            Line::from("  r       Reset Model to its Initial Weights"),
             Line::from("  l       Load Local Checkpoint"),
             Line::from("  o       Toggle Sorting Model Probabilities"),
             Line::from("  <-/->   Select / Cycle Layer"),
+            Line::from("  /       Track Neurons of Selected Layer (e.g. 0-15, 42)"),
+            Line::from("  ,/.     Focus Previous/Next Tracked Trace"),
+            Line::from("  c/x     Cycle Color / Remove Focused Trace"),
+            Line::from("  e       Export Tracked Traces to CSV"),
+            Line::from("  b/B     Pin Live Model as Comparison Reference / Clear It"),
+            Line::from("  Tab     Cycle Views"),
+            Line::from("  f       Toggle Performance Overlay"),
+            Line::from("  g       Toggle Force-Directed / Layered Layout"),
+            Line::from("  k       Pin/Unpin Selected Layer in the Force Layout"),
             Line::from("  Up/Down Scroll Execution Logs"),
+            Line::from(""),
+            Line::from("  Replay: p/Space Play/Pause, n/m Next/Previous Frame,"),
+            Line::from("          PgDn/PgUp Jump 10%, Home/End First/Last Frame"),
         ];
 
         let p = Paragraph::new(help_text)
@@ -0,0 +1,448 @@
+//! Placement of layers in the network view.
+//!
+//! [`ForceLayout`] is a spring embedder: layers repel each other, synapses pull the layers they
+//! connect toward a fixed distance, and a weak pull keeps everything near the center of the
+//! canvas. A step depends only on the current positions and velocities, so the same model
+//! always settles into the same picture. Pinned layers stay put but still push and pull the
+//! others. Layers the layout hasn't seen yet start from [`layered_positions`], which places
+//! them in columns from left to right by their depth in the model graph; the same function
+//! backs the static [`LayoutMode::Layered`] view.
+
+use super::ModelStructure;
+use crate::layer::LayerPosition;
+use crate::synapse::LayerId;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Extent of the canvas the network is drawn on
+pub const CANVAS_WIDTH: f32 = 1000.0;
+pub const CANVAS_HEIGHT: f32 = 400.0;
+/// Layers are kept at least this far from the edges of the canvas
+pub const CANVAS_MARGIN: f32 = 50.0;
+
+/// How the network view places layers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LayoutMode {
+    /// Animated spring embedder, see [`ForceLayout`]
+    #[default]
+    Force,
+    /// Static columns by depth, see [`layered_positions`]
+    Layered,
+}
+
+impl LayoutMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Force => Self::Layered,
+            Self::Layered => Self::Force,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ForceParams {
+    /// strength of the repulsion between every pair of layers
+    pub repel: f32,
+    /// stiffness of the springs along synapses
+    pub link: f32,
+    /// strength of the pull toward the center of the canvas
+    pub center: f32,
+    /// rest length of the springs
+    pub link_distance: f32,
+    /// fraction of its velocity a layer keeps per step
+    pub damping: f32,
+    /// repulsion is computed as if closer layers were this far apart
+    pub min_distance: f32,
+}
+
+impl Default for ForceParams {
+    fn default() -> Self {
+        Self {
+            repel: 5000.0,
+            link: 0.01,
+            center: 0.005,
+            link_distance: 150.0,
+            damping: 0.8,
+            min_distance: 50.0,
+        }
+    }
+}
+
+/// Force-directed layout of a [`ModelStructure`], see the module docs
+#[derive(Clone, Debug, Default)]
+pub struct ForceLayout {
+    pub params: ForceParams,
+    /// velocity of every layer the layout has placed
+    velocities: HashMap<LayerId, (f32, f32)>,
+    pinned: HashSet<LayerId>,
+}
+
+impl ForceLayout {
+    pub fn new(params: ForceParams) -> Self {
+        Self {
+            params,
+            velocities: HashMap::new(),
+            pinned: HashSet::new(),
+        }
+    }
+
+    /// Keep layer `id` where it is
+    pub fn pin(&mut self, id: LayerId) {
+        self.pinned.insert(id);
+        self.velocities.insert(id, (0.0, 0.0));
+    }
+
+    pub fn unpin(&mut self, id: LayerId) {
+        self.pinned.remove(&id);
+    }
+
+    pub fn is_pinned(&self, id: LayerId) -> bool {
+        self.pinned.contains(&id)
+    }
+
+    /// Stop every layer, e.g. after the positions were set by another layout
+    pub fn reset_velocities(&mut self) {
+        self.velocities.values_mut().for_each(|v| *v = (0.0, 0.0));
+    }
+
+    /// Place the layers of `model` the layout hasn't seen yet at their [`layered_positions`]
+    pub fn place_new_layers(&mut self, model: &mut ModelStructure) {
+        if model
+            .layers
+            .iter()
+            .all(|l| self.velocities.contains_key(&l.id))
+        {
+            return;
+        }
+        let positions = layered_positions(model);
+        for layer in &mut model.layers {
+            if let Entry::Vacant(velocity) = self.velocities.entry(layer.id) {
+                layer.position = positions[&layer.id];
+                velocity.insert((0.0, 0.0));
+            }
+        }
+    }
+
+    /// Advance the simulation by one step
+    pub fn step(&mut self, model: &mut ModelStructure) {
+        self.place_new_layers(model);
+        let p = self.params;
+        let num_layers = model.layers.len();
+        let mut forces: Vec<(f32, f32)> = vec![(0.0, 0.0); num_layers];
+
+        for i in 0..num_layers {
+            for j in (i + 1)..num_layers {
+                let (mut dx, mut dy) = offset(model, i, j);
+                if dx == 0.0 && dy == 0.0 {
+                    // Coincident layers have no direction to separate along; split them
+                    // horizontally in model order
+                    (dx, dy) = (1.0, 0.0);
+                }
+                let dist = (dx * dx + dy * dy).sqrt().max(p.min_distance);
+                let force = p.repel / (dist * dist);
+                let fx = force * dx / dist;
+                let fy = force * dy / dist;
+                forces[i].0 -= fx;
+                forces[i].1 -= fy;
+                forces[j].0 += fx;
+                forces[j].1 += fy;
+            }
+        }
+
+        let index: HashMap<LayerId, usize> = model
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(i, l)| (l.id, i))
+            .collect();
+        for synapse in &model.synapses {
+            let (Some(&pre), Some(&post)) = (
+                index.get(&synapse.pre_layer),
+                index.get(&synapse.post_layer),
+            ) else {
+                continue;
+            };
+            if pre == post {
+                continue;
+            }
+            let (dx, dy) = offset(model, pre, post);
+            let dist = (dx * dx + dy * dy).sqrt();
+            let force = p.link * (dist - p.link_distance);
+            let fx = force * dx / dist.max(1.0);
+            let fy = force * dy / dist.max(1.0);
+            forces[pre].0 += fx;
+            forces[pre].1 += fy;
+            forces[post].0 -= fx;
+            forces[post].1 -= fy;
+        }
+
+        let (center_x, center_y) = (CANVAS_WIDTH / 2.0, CANVAS_HEIGHT / 2.0);
+        for (force, layer) in forces.iter_mut().zip(&model.layers) {
+            force.0 += (center_x - layer.position.x) * p.center;
+            force.1 += (center_y - layer.position.y) * p.center;
+        }
+
+        for (force, layer) in forces.iter().zip(&mut model.layers) {
+            if self.pinned.contains(&layer.id) {
+                continue;
+            }
+            let velocity = self.velocities.entry(layer.id).or_insert((0.0, 0.0));
+            velocity.0 = (velocity.0 + force.0) * p.damping;
+            velocity.1 = (velocity.1 + force.1) * p.damping;
+            layer.position = clamp_to_canvas(LayerPosition {
+                x: layer.position.x + velocity.0,
+                y: layer.position.y + velocity.1,
+            });
+        }
+    }
+
+    /// Step until no layer moves faster than `tolerance` per step, or `max_steps` steps were
+    /// taken; returns the number of steps
+    pub fn settle(
+        &mut self,
+        model: &mut ModelStructure,
+        max_steps: usize,
+        tolerance: f32,
+    ) -> usize {
+        for steps in 1..=max_steps {
+            self.step(model);
+            let fastest = self
+                .velocities
+                .values()
+                .map(|(vx, vy)| (vx * vx + vy * vy).sqrt())
+                .fold(0.0, f32::max);
+            if fastest < tolerance {
+                return steps;
+            }
+        }
+        max_steps
+    }
+}
+
+/// Depth of every layer of `model`: 0 for layers no other layer feeds (the inputs), otherwise
+/// the fewest synapses on a path from one of them. Feedback synapses therefore don't push
+/// layers deeper. Layers that only receive from a cycle without an input are numbered from
+/// the first of them in model order.
+pub fn layer_depths(model: &ModelStructure) -> HashMap<LayerId, usize> {
+    let mut successors: HashMap<LayerId, Vec<LayerId>> = HashMap::new();
+    let mut fed: HashSet<LayerId> = HashSet::new();
+    for synapse in &model.synapses {
+        if synapse.pre_layer != synapse.post_layer {
+            successors
+                .entry(synapse.pre_layer)
+                .or_default()
+                .push(synapse.post_layer);
+            fed.insert(synapse.post_layer);
+        }
+    }
+
+    let mut depths: HashMap<LayerId, usize> = HashMap::new();
+    let mut queue: VecDeque<LayerId> = VecDeque::new();
+    for layer in &model.layers {
+        if !fed.contains(&layer.id) {
+            depths.insert(layer.id, 0);
+            queue.push_back(layer.id);
+        }
+    }
+    loop {
+        while let Some(id) = queue.pop_front() {
+            let depth = depths[&id];
+            for &next in successors.get(&id).into_iter().flatten() {
+                if let Entry::Vacant(entry) = depths.entry(next) {
+                    entry.insert(depth + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        match model.layers.iter().find(|l| !depths.contains_key(&l.id)) {
+            Some(layer) => {
+                depths.insert(layer.id, 0);
+                queue.push_back(layer.id);
+            }
+            None => return depths,
+        }
+    }
+}
+
+/// Layers of `model` in columns by [`layer_depths`], inputs on the left, spread
+/// evenly over the canvas; layers of one column are stacked in model order
+pub fn layered_positions(model: &ModelStructure) -> HashMap<LayerId, LayerPosition> {
+    let depths = layer_depths(model);
+    let columns = depths.values().max().map_or(1, |&d| d + 1);
+    let mut rows: Vec<Vec<LayerId>> = vec![Vec::new(); columns];
+    for layer in &model.layers {
+        rows[depths[&layer.id]].push(layer.id);
+    }
+
+    // Position `i` of `n` evenly spaced over `[CANVAS_MARGIN, extent - CANVAS_MARGIN]`
+    let spread = |i: usize, n: usize, extent: f32| {
+        if n <= 1 {
+            return extent / 2.0;
+        }
+        CANVAS_MARGIN + i as f32 * (extent - 2.0 * CANVAS_MARGIN) / (n - 1) as f32
+    };
+    let mut positions = HashMap::new();
+    for (column, ids) in rows.iter().enumerate() {
+        for (row, &id) in ids.iter().enumerate() {
+            let position = LayerPosition {
+                x: spread(column, columns, CANVAS_WIDTH),
+                // The canvas's y axis points up; the first layer of a column goes on top
+                y: CANVAS_HEIGHT - spread(row, ids.len(), CANVAS_HEIGHT),
+            };
+            positions.insert(id, position);
+        }
+    }
+    positions
+}
+
+/// Move every layer of `model` to its [`layered_positions`]
+pub fn apply_layered(model: &mut ModelStructure) {
+    let positions = layered_positions(model);
+    for layer in &mut model.layers {
+        layer.position = positions[&layer.id];
+    }
+}
+
+/// Offset from layer `i` to layer `j` of `model`
+fn offset(model: &ModelStructure, i: usize, j: usize) -> (f32, f32) {
+    let (a, b) = (model.layers[i].position, model.layers[j].position);
+    (b.x - a.x, b.y - a.y)
+}
+
+fn clamp_to_canvas(position: LayerPosition) -> LayerPosition {
+    LayerPosition {
+        x: position
+            .x
+            .clamp(CANVAS_MARGIN, CANVAS_WIDTH - CANVAS_MARGIN),
+        y: position
+            .y
+            .clamp(CANVAS_MARGIN, CANVAS_HEIGHT - CANVAS_MARGIN),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synapse::WeightStats;
+    use crate::visualization::{LayerVisInfo, SynapseVisInfo};
+
+    /// Layers `0..layers` at the origin, connected by `links` (pre, post)
+    fn model(layers: usize, links: &[(LayerId, LayerId)]) -> ModelStructure {
+        ModelStructure {
+            layers: (0..layers)
+                .map(|id| LayerVisInfo {
+                    id,
+                    name: format!("layer {}", id),
+                    layer_type: "LIF".to_string(),
+                    size: 4,
+                    position: LayerPosition { x: 0.0, y: 0.0 },
+                    velocity: (0.0, 0.0),
+                    current_activity: vec![],
+                    spike_count: 0,
+                    topography: None,
+                })
+                .collect(),
+            synapses: links
+                .iter()
+                .enumerate()
+                .map(|(id, &(pre_layer, post_layer))| SynapseVisInfo {
+                    id,
+                    pre_layer,
+                    post_layer,
+                    synapse_type: "CSDP".to_string(),
+                    weight_stats: WeightStats {
+                        mean: 0.0,
+                        std: 0.0,
+                        min: 0.0,
+                        max: 0.0,
+                        num_weights: 0,
+                    },
+                    is_learning: true,
+                    enabled: true,
+                })
+                .collect(),
+            novelty: None,
+        }
+    }
+
+    fn positions(model: &ModelStructure) -> Vec<(f32, f32)> {
+        model
+            .layers
+            .iter()
+            .map(|l| (l.position.x, l.position.y))
+            .collect()
+    }
+
+    #[test]
+    fn test_depths_ignore_feedback() {
+        // 0 -> 2 -> 3 -> 4 with 1 -> 3 and feedback 4 -> 2, plus a source-less cycle 5 <-> 6
+        let model = model(7, &[(0, 2), (2, 3), (1, 3), (3, 4), (4, 2), (5, 6), (6, 5)]);
+        let depths = layer_depths(&model);
+        let expected = [(0, 0), (1, 0), (2, 1), (3, 1), (4, 2), (5, 0), (6, 1)];
+        for (id, depth) in expected {
+            assert_eq!(depths[&id], depth, "layer {}", id);
+        }
+    }
+
+    #[test]
+    fn test_layered_positions_run_left_to_right() {
+        let mut model = model(4, &[(0, 2), (1, 2), (2, 3)]);
+        apply_layered(&mut model);
+        let p = positions(&model);
+        assert_eq!(p[0].0, CANVAS_MARGIN);
+        assert_eq!(p[0].0, p[1].0);
+        assert!(p[0].1 > p[1].1);
+        assert_eq!(p[2], (CANVAS_WIDTH / 2.0, CANVAS_HEIGHT / 2.0));
+        assert_eq!(p[3].0, CANVAS_WIDTH - CANVAS_MARGIN);
+    }
+
+    #[test]
+    fn test_stepping_is_deterministic_and_bounded() {
+        let links = [(0, 1), (1, 2), (2, 3), (3, 1)];
+        let mut a = model(4, &links);
+        let mut b = model(4, &links);
+        let (mut layout_a, mut layout_b) = (ForceLayout::default(), ForceLayout::default());
+        for _ in 0..200 {
+            layout_a.step(&mut a);
+            layout_b.step(&mut b);
+        }
+        assert_eq!(positions(&a), positions(&b));
+        for (x, y) in positions(&a) {
+            assert!(x.is_finite() && y.is_finite());
+            assert!((CANVAS_MARGIN..=CANVAS_WIDTH - CANVAS_MARGIN).contains(&x));
+            assert!((CANVAS_MARGIN..=CANVAS_HEIGHT - CANVAS_MARGIN).contains(&y));
+        }
+    }
+
+    #[test]
+    fn test_coincident_layers_separate() {
+        let mut model = model(2, &[]);
+        let mut layout = ForceLayout::new(ForceParams::default());
+        // Mark both as placed so they stay on top of each other until the first step
+        layout.place_new_layers(&mut model);
+        for layer in &mut model.layers {
+            layer.position = LayerPosition { x: 500.0, y: 200.0 };
+        }
+        layout.step(&mut model);
+        let p = positions(&model);
+        assert!(p[0].0 < p[1].0, "{:?}", p);
+        assert!(p.iter().all(|(x, y)| x.is_finite() && y.is_finite()));
+    }
+
+    #[test]
+    fn test_pinned_layers_stay_put() {
+        let mut model = model(3, &[(0, 1), (1, 2)]);
+        let mut layout = ForceLayout::new(ForceParams::default());
+        layout.place_new_layers(&mut model);
+        let start = positions(&model);
+        layout.pin(1);
+        let steps = layout.settle(&mut model, 500, 0.01);
+        assert!(steps < 500);
+        let end = positions(&model);
+        assert_eq!(end[1], start[1]);
+        assert_ne!(end[0], start[0]);
+
+        layout.unpin(1);
+        assert!(!layout.is_pinned(1));
+    }
+}
@@ -0,0 +1,338 @@
+//! Prometheus text-format metrics endpoint for long training runs.
+//!
+//! Serves `GET /metrics` from the shared [`VisualizationState`] that training loops already
+//! publish to, so it works with or without the TUI. Scrape it with Prometheus and graph it in
+//! Grafana (or just `curl` it).
+
+use super::{RobotVisInfo, SynapseVisInfo, VisualizationState};
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+/// Tracks epoch throughput between scrapes
+pub struct EpochRate {
+    start: Instant,
+    start_epoch: Option<usize>,
+}
+
+impl EpochRate {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            start_epoch: None,
+        }
+    }
+
+    /// Average epochs per second since the first observed epoch
+    pub fn observe(&mut self, epoch: usize) -> f32 {
+        let start_epoch = *self.start_epoch.get_or_insert_with(|| {
+            self.start = Instant::now();
+            epoch
+        });
+        let elapsed = self.start.elapsed().as_secs_f32();
+        if elapsed > 0.0 {
+            epoch.saturating_sub(start_epoch) as f32 / elapsed
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Default for EpochRate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bind `addr` and serve metrics from `state` on a background thread
+pub fn start_metrics_server(
+    addr: impl ToSocketAddrs,
+    state: Arc<Mutex<VisualizationState>>,
+) -> io::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)?;
+    log::info!(
+        "Serving metrics on http://{}/metrics",
+        listener.local_addr()?
+    );
+    Ok(std::thread::spawn(move || {
+        let mut rate = EpochRate::new();
+        for stream in listener.incoming() {
+            let result = stream.and_then(|stream| handle(stream, &state, &mut rate));
+            if let Err(e) = result {
+                log::warn!("Metrics request failed: {}", e);
+            }
+        }
+    }))
+}
+
+fn handle(
+    mut stream: TcpStream,
+    state: &Arc<Mutex<VisualizationState>>,
+    rate: &mut EpochRate,
+) -> io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+
+    let (status, body) = if path == "/metrics" {
+        // Shelling out to nvidia-smi is slow, so do it before taking the lock
+        let gpu_memory_mb = crate::utils::query_gpu_memory_mb();
+        let body = match state.lock() {
+            Ok(state) => render(&state, rate, gpu_memory_mb),
+            Err(_) => String::new(),
+        };
+        ("200 OK", body)
+    } else {
+        ("404 Not Found", "not found\n".to_string())
+    };
+
+    write!(
+        stream,
+        "HTTP/1.0 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Render the current state in the Prometheus text exposition format
+pub fn render(
+    state: &VisualizationState,
+    rate: &mut EpochRate,
+    gpu_memory_mb: Option<f32>,
+) -> String {
+    let mut out = String::new();
+    let stats = &state.runtime_stats;
+
+    gauge(&mut out, "csdp_epoch", "Current training epoch");
+    sample(&mut out, "csdp_epoch", "", stats.epoch as f32);
+    gauge(&mut out, "csdp_total_epochs", "Configured number of epochs");
+    sample(&mut out, "csdp_total_epochs", "", state.total_epochs as f32);
+    gauge(
+        &mut out,
+        "csdp_epochs_per_second",
+        "Average epochs per second since the endpoint started",
+    );
+    sample(
+        &mut out,
+        "csdp_epochs_per_second",
+        "",
+        rate.observe(stats.epoch),
+    );
+    gauge(
+        &mut out,
+        "csdp_iterations_per_second",
+        "Samples per second in the current epoch",
+    );
+    sample(
+        &mut out,
+        "csdp_iterations_per_second",
+        "",
+        stats.iterations_per_second,
+    );
+
+    if let Some(&(epoch, reward)) = state.epoch_rewards.last() {
+        gauge(
+            &mut out,
+            "csdp_epoch_reward",
+            "Reward of the last finished epoch",
+        );
+        sample(
+            &mut out,
+            "csdp_epoch_reward",
+            &labels(&[("epoch", &epoch.to_string())]),
+            reward,
+        );
+    }
+
+    if let Some(perf) = &state.perf_stats {
+        gauge(
+            &mut out,
+            "csdp_timesteps_per_second",
+            "Model timesteps per second",
+        );
+        sample(
+            &mut out,
+            "csdp_timesteps_per_second",
+            "",
+            perf.timesteps_per_second,
+        );
+    }
+
+    if let Some(mb) = gpu_memory_mb.or(state.perf_stats.as_ref().and_then(|p| p.gpu_memory_mb)) {
+        gauge(
+            &mut out,
+            "csdp_gpu_memory_mb",
+            "Used memory of the first GPU in MiB",
+        );
+        sample(&mut out, "csdp_gpu_memory_mb", "", mb);
+    }
+
+    let layers = &state.model_structure.layers;
+    if !layers.is_empty() {
+        gauge(
+            &mut out,
+            "csdp_layer_firing_rate",
+            "Fraction of neurons that spiked in the last published timestep",
+        );
+        for layer in layers {
+            let rate = if layer.size > 0 {
+                layer.spike_count as f32 / layer.size as f32
+            } else {
+                0.0
+            };
+            sample(
+                &mut out,
+                "csdp_layer_firing_rate",
+                &labels(&[("layer", &layer.name), ("id", &layer.id.to_string())]),
+                rate,
+            );
+        }
+    }
+
+    if let Some(novelty) = state.model_structure.novelty {
+        gauge(
+            &mut out,
+            "csdp_novelty",
+            "Novelty of the last published timestep, in baseline standard deviations",
+        );
+        sample(&mut out, "csdp_novelty", "", novelty);
+    }
+
+    let synapses = &state.model_structure.synapses;
+    if !synapses.is_empty() {
+        let syn_labels: Vec<String> = synapses
+            .iter()
+            .map(|syn| synapse_labels(state, syn))
+            .collect();
+        gauge(
+            &mut out,
+            "csdp_weight_norm",
+            "L2 norm of the synapse weights",
+        );
+        for (syn, syn_labels) in synapses.iter().zip(&syn_labels) {
+            let w = &syn.weight_stats;
+            let norm = (w.num_weights as f32 * (w.std * w.std + w.mean * w.mean)).sqrt();
+            sample(&mut out, "csdp_weight_norm", syn_labels, norm);
+        }
+        gauge(&mut out, "csdp_weight_mean", "Mean synapse weight");
+        for (syn, syn_labels) in synapses.iter().zip(&syn_labels) {
+            sample(
+                &mut out,
+                "csdp_weight_mean",
+                syn_labels,
+                syn.weight_stats.mean,
+            );
+        }
+        gauge(
+            &mut out,
+            "csdp_weight_std",
+            "Standard deviation of synapse weights",
+        );
+        for (syn, syn_labels) in synapses.iter().zip(&syn_labels) {
+            sample(
+                &mut out,
+                "csdp_weight_std",
+                syn_labels,
+                syn.weight_stats.std,
+            );
+        }
+    }
+
+    if !state.robot_status.is_empty() {
+        for (name, help, values) in [
+            (
+                "csdp_robot_position",
+                "Present joint position",
+                robot_values(state, |r| &r.positions),
+            ),
+            (
+                "csdp_robot_goal",
+                "Goal joint position",
+                robot_values(state, |r| &r.goals),
+            ),
+            (
+                "csdp_robot_load",
+                "Present joint load",
+                robot_values(state, |r| &r.loads),
+            ),
+        ] {
+            gauge(&mut out, name, help);
+            for (robot, joint, value) in values {
+                sample(
+                    &mut out,
+                    name,
+                    &labels(&[("robot", &robot), ("joint", &joint.to_string())]),
+                    value as f32,
+                );
+            }
+        }
+    }
+
+    out
+}
+
+fn synapse_labels(state: &VisualizationState, syn: &SynapseVisInfo) -> String {
+    let layer_name = |layer_id: usize| {
+        state
+            .model_structure
+            .layers
+            .iter()
+            .find(|l| l.id == layer_id)
+            .map(|l| l.name.clone())
+            .unwrap_or_else(|| layer_id.to_string())
+    };
+    labels(&[
+        ("synapse", &syn.id.to_string()),
+        ("pre", &layer_name(syn.pre_layer)),
+        ("post", &layer_name(syn.post_layer)),
+    ])
+}
+
+fn robot_values(
+    state: &VisualizationState,
+    field: impl Fn(&RobotVisInfo) -> &Vec<f64>,
+) -> Vec<(String, usize, f64)> {
+    state
+        .robot_status
+        .iter()
+        .flat_map(|robot| {
+            field(robot)
+                .iter()
+                .enumerate()
+                .map(|(joint, &value)| (robot.name.clone(), joint, value))
+        })
+        .collect()
+}
+
+fn gauge(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+}
+
+fn sample(out: &mut String, name: &str, labels: &str, value: f32) {
+    let _ = writeln!(out, "{}{} {}", name, labels, value);
+}
+
+/// `{k="v",...}`, or nothing without labels
+fn labels(pairs: &[(&str, &str)]) -> String {
+    if pairs.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = pairs
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
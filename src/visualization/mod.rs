@@ -1,11 +1,50 @@
+#[cfg(all(feature = "gui", not(target_arch = "wasm32")))]
 pub mod app;
+pub mod layout;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod metrics;
+pub mod publisher;
+pub mod replay;
+pub mod selection;
 
 use crate::layer::LayerPosition;
+use crate::layer::topography::Topography;
 use crate::synapse::{LayerId, SynapseId, WeightStats};
+use replay::SessionRecorder;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 
 pub static GLOBAL_LOGS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
 
+/// Collects log records into [`GLOBAL_LOGS`] for the TUI's log panel; printing them would
+/// disrupt Ratatui
+struct TuiLogger;
+impl log::Log for TuiLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            let msg = format!("[{}] {}", record.level(), record.args());
+            if let Ok(mut logs) = GLOBAL_LOGS.lock() {
+                logs.push(msg);
+                if logs.len() > 100 {
+                    logs.remove(0);
+                }
+            }
+        }
+    }
+    fn flush(&self) {}
+}
+static TUI_LOGGER: TuiLogger = TuiLogger;
+
+/// Route `log` output to the TUI's log panel instead of the terminal
+pub fn install_tui_logger() {
+    let _ = log::set_logger(&TUI_LOGGER);
+    log::set_max_level(log::LevelFilter::Info);
+}
+
 /// State shared between training loop and visualization thread
 pub struct VisualizationState {
     pub model_structure: ModelStructure,
@@ -20,21 +59,61 @@ pub struct VisualizationState {
     pub epoch_rewards: Vec<(usize, f32)>,
     pub save_requested: bool,
     pub load_requested: bool,
+    /// Restore the weights the model had when training started
+    pub reset_requested: bool,
+    /// End training at the next pause point but keep the visualizer open
+    pub stop_requested: bool,
+    /// Commands from the visualizer not yet seen by the training loop
+    pub commands: VecDeque<TrainingCommand>,
     pub delay_ms: u64,
     pub render_trail: Vec<(f64, f64)>,
     pub model_probabilities: Option<Vec<(String, Vec<f32>)>>,
     pub sort_probabilities: bool,
+    pub robot_status: Vec<RobotVisInfo>,
+    pub step_request: Option<StepGranularity>,
+    /// Reference snapshot (e.g. a checkpoint or a pinned earlier state) shown next to the live model
+    pub comparison: Option<(String, ModelStructure)>,
+    pub perf_stats: Option<PerfStats>,
+    /// Whether the perf overlay is shown; `csdp3`, the only loop that fills `perf_stats`,
+    /// profiles its critic only while this is set
+    pub profiling_requested: bool,
+    /// Appends every snapshot the training loop publishes to a session file for later replay
+    pub recorder: Option<SessionRecorder>,
+}
+
+/// How far a single-step request issued while paused lets the training loop advance
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepGranularity {
+    /// One model timestep (only honored by loops that step the model explicitly)
+    Timestep,
+    /// One sample / environment step
+    Sample,
+}
+
+/// Control command sent from the visualizer to the training loop
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrainingCommand {
+    Pause,
+    Resume,
+    /// End training; the algorithm returns at its next pause point
+    Stop,
+    /// Save a checkpoint at the end of the current episode
+    SaveCheckpoint,
+    /// Restore the initial weights at the end of the current episode
+    ResetModel,
 }
 
 /// Structure of the model for visualization
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ModelStructure {
     pub layers: Vec<LayerVisInfo>,
     pub synapses: Vec<SynapseVisInfo>,
+    /// novelty of the last step, for models that score it
+    pub novelty: Option<f32>,
 }
 
 /// Visualization info for a layer
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct LayerVisInfo {
     pub id: LayerId,
@@ -45,10 +124,13 @@ pub struct LayerVisInfo {
     pub velocity: (f32, f32), // For force-directed layout
     pub current_activity: Vec<f32>,
     pub spike_count: usize,
+    /// arrangement of the neurons, drawn as a neuron map when the layer is selected
+    #[serde(default)]
+    pub topography: Option<Topography>,
 }
 
 /// Visualization info for a synapse
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct SynapseVisInfo {
     pub id: SynapseId,
@@ -56,10 +138,43 @@ pub struct SynapseVisInfo {
     pub post_layer: LayerId,
     pub synapse_type: String,
     pub weight_stats: WeightStats,
+    /// plasticity is applied, i.e. the synapse isn't frozen
+    pub is_learning: bool,
+    /// the connection isn't ablated
+    pub enabled: bool,
+}
+
+/// Visualization info for a connected robot arm
+#[derive(Clone, Debug, Default)]
+pub struct RobotVisInfo {
+    pub name: String,
+    pub positions: Vec<f64>,
+    pub goals: Vec<f64>,
+    pub loads: Vec<f64>,
+}
+
+impl RobotVisInfo {
+    /// Joints with any reading; a failed read leaves its column shorter than the others
+    pub fn joints(&self) -> usize {
+        self.positions
+            .len()
+            .max(self.goals.len())
+            .max(self.loads.len())
+    }
+}
+
+/// Per-phase timing breakdown of a model step, averaged over the last reporting window
+#[derive(Clone, Debug, Default)]
+pub struct PerfStats {
+    pub layer_step_ms: Vec<(String, f32)>,
+    pub synapse_forward_ms: f32,
+    pub synapse_update_ms: f32,
+    pub timesteps_per_second: f32,
+    pub gpu_memory_mb: Option<f32>,
 }
 
 /// Runtime statistics
-#[derive(Clone, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct RuntimeStats {
     pub epoch: usize,
     pub iteration: usize,
@@ -73,6 +188,7 @@ impl VisualizationState {
             model_structure: ModelStructure {
                 layers: Vec::new(),
                 synapses: Vec::new(),
+                novelty: None,
             },
             runtime_stats: RuntimeStats::default(),
             should_close: false,
@@ -85,10 +201,19 @@ impl VisualizationState {
             epoch_rewards: Vec::new(),
             save_requested: false,
             load_requested: false,
+            reset_requested: false,
+            stop_requested: false,
+            commands: VecDeque::new(),
             delay_ms: 0,
             render_trail: Vec::new(),
             model_probabilities: None,
             sort_probabilities: false,
+            robot_status: Vec::new(),
+            step_request: None,
+            comparison: None,
+            perf_stats: None,
+            profiling_requested: false,
+            recorder: None,
         }
     }
 }
@@ -102,6 +227,8 @@ impl Default for VisualizationState {
 impl VisualizationState {
     /// Update model structure from snapshot, preserving animated positions
     pub fn update_from_snapshot(&mut self, snapshot: ModelStructure) {
+        self.record_snapshot(&snapshot);
+
         // If positions haven't been initialized yet, just use the snapshot as-is
         if !self.positions_initialized {
             self.model_structure = snapshot;
@@ -123,6 +250,7 @@ impl VisualizationState {
                 existing_layer.size = new_layer.size;
                 existing_layer.current_activity = new_layer.current_activity.clone();
                 existing_layer.spike_count = new_layer.spike_count;
+                existing_layer.topography = new_layer.topography;
                 // Position and velocity are preserved
             } else {
                 // New layer - add it (clone since we're iterating by reference)
@@ -139,6 +267,74 @@ impl VisualizationState {
         self.model_structure.synapses = snapshot.synapses;
     }
 
+    /// Hand a published snapshot to the session recorder, if recording. A failing recorder is
+    /// dropped so training goes on without it.
+    fn record_snapshot(&mut self, snapshot: &ModelStructure) {
+        let Some(recorder) = self.recorder.as_mut() else {
+            return;
+        };
+        if let Err(e) = recorder.record(&self.runtime_stats, &self.epoch_rewards, snapshot) {
+            log::error!(
+                "Stopped recording the session after {} frames: {}",
+                recorder.frames(),
+                e
+            );
+            self.recorder = None;
+        }
+    }
+
+    /// Whether the training loop may pass a pause point of the given granularity.
+    /// Consumes a pending single-step request when it matches; a sample step lets
+    /// timestep pause points through until the next sample boundary is reached.
+    pub fn try_advance(&mut self, point: StepGranularity) -> bool {
+        if !self.is_paused {
+            return true;
+        }
+        match (self.step_request, point) {
+            (None, _) => false,
+            (Some(StepGranularity::Sample), StepGranularity::Timestep) => true,
+            (Some(_), _) => {
+                self.step_request = None;
+                true
+            }
+        }
+    }
+
+    /// Queue a command for the training loop
+    pub fn send_command(&mut self, command: TrainingCommand) {
+        self.commands.push_back(command);
+    }
+
+    /// Apply queued commands in order. Pause, resume and stop take effect immediately; saving
+    /// and resetting need the model, so they set `save_requested` / `reset_requested` for the
+    /// algorithm to act on at the end of the episode.
+    pub fn apply_commands(&mut self) {
+        while let Some(command) = self.commands.pop_front() {
+            match command {
+                TrainingCommand::Pause => self.is_paused = true,
+                TrainingCommand::Resume => {
+                    self.is_paused = false;
+                    self.step_request = None;
+                }
+                TrainingCommand::Stop => self.stop_requested = true,
+                TrainingCommand::SaveCheckpoint => self.save_requested = true,
+                TrainingCommand::ResetModel => self.reset_requested = true,
+            }
+        }
+    }
+
+    /// Set the reference snapshot the live model is compared against
+    pub fn set_comparison(&mut self, label: impl Into<String>, snapshot: ModelStructure) {
+        self.comparison = Some((label.into(), snapshot));
+    }
+
+    /// Pin the current live model structure as the comparison reference
+    pub fn pin_live_as_comparison(&mut self) {
+        let label = format!("Pinned @ epoch {}", self.runtime_stats.epoch);
+        let snapshot = self.model_structure.clone();
+        self.set_comparison(label, snapshot);
+    }
+
     /// Save the epoch rewards (graph values) to a CSV file for later analysis
     pub fn save_graphs_to_csv(&self, path: &std::path::Path) -> std::io::Result<()> {
         use std::io::Write;
@@ -155,51 +351,103 @@ impl VisualizationState {
     }
 }
 
+/// Block at a pause point until the visualizer lets the training loop advance, applying
+/// pending [`TrainingCommand`]s while waiting.
+/// Returns false if the visualizer asked to close or to stop training.
+pub fn wait_for_advance(vis_state: &Arc<Mutex<VisualizationState>>, point: StepGranularity) -> bool {
+    loop {
+        // Block on the lock rather than skip it: a pause point missed while the visualizer
+        // holds the state would let a paused or single-stepped run through. A poisoned lock
+        // means the visualizer panicked, so stop.
+        let (advance, should_close, delay) = vis_state
+            .lock()
+            .map(|mut state| {
+                state.apply_commands();
+                let stop = state.should_close || state.stop_requested;
+                (state.try_advance(point), stop, state.delay_ms)
+            })
+            .unwrap_or((false, true, 0));
+        if should_close {
+            return false;
+        }
+        if advance {
+            // Throttling only applies between samples, not between individual timesteps
+            if delay > 0 && point == StepGranularity::Sample {
+                std::thread::sleep(std::time::Duration::from_millis(delay));
+            }
+            return true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+}
+
+/// Apply pending [`TrainingCommand`]s without waiting, for work between pause points such as
+/// the inference steps of the reward-ranking algorithms.
+/// Returns false if the visualizer asked to close or to stop training.
+pub fn keep_running(vis_state: &Arc<Mutex<VisualizationState>>) -> bool {
+    vis_state
+        .lock()
+        .map(|mut state| {
+            state.apply_commands();
+            !(state.should_close || state.stop_requested)
+        })
+        .unwrap_or(false)
+}
+
 /// Start the visualization in a separate thread
+#[cfg(all(feature = "gui", not(target_arch = "wasm32")))]
 pub fn start_visualization(state: Arc<Mutex<VisualizationState>>) -> std::thread::JoinHandle<()> {
-    std::thread::spawn(move || {
-        use crossterm::{
-            event::{DisableMouseCapture, EnableMouseCapture},
-            execute,
-            terminal::{
-                EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
-            },
-        };
-        use ratatui::Terminal;
-        use ratatui::backend::CrosstermBackend;
-        use std::io;
+    std::thread::spawn(move || run_app(app::NeuralNetworkVisualizerApp::new(state)))
+}
+
+/// Show a recorded training session in the visualizer until it is closed with `q`
+#[cfg(all(feature = "gui", not(target_arch = "wasm32")))]
+pub fn replay_session(replay: replay::SessionReplay) {
+    let state = Arc::new(Mutex::new(VisualizationState::new(0)));
+    run_app(app::NeuralNetworkVisualizerApp::new(state).with_replay(replay));
+}
 
-        // Setup terminal
-        enable_raw_mode().unwrap();
+/// Run the visualizer app on this thread, taking over the terminal while it runs
+#[cfg(all(feature = "gui", not(target_arch = "wasm32")))]
+fn run_app(mut app: app::NeuralNetworkVisualizerApp) {
+    use crossterm::{
+        event::{DisableMouseCapture, EnableMouseCapture},
+        execute,
+        terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+    };
+    use ratatui::Terminal;
+    use ratatui::backend::CrosstermBackend;
+    use std::io;
+
+    // Setup terminal
+    enable_raw_mode().unwrap();
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture).unwrap();
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend).unwrap();
+
+    // Ensure cleanup on panic
+    let original_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic| {
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture).unwrap();
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend).unwrap();
-
-        // Ensure cleanup on panic
-        let original_hook = std::panic::take_hook();
-        std::panic::set_hook(Box::new(move |panic| {
-            let mut stdout = io::stdout();
-            let _ = execute!(stdout, LeaveAlternateScreen, DisableMouseCapture);
-            let _ = disable_raw_mode();
-            original_hook(panic);
-        }));
-
-        let mut app = app::NeuralNetworkVisualizerApp::new(state);
-        let res = app.run(&mut terminal);
-
-        // Cleanup terminal
-        disable_raw_mode().unwrap();
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        )
-        .unwrap();
-        terminal.show_cursor().unwrap();
-
-        if let Err(err) = res {
-            log::error!("{:?}", err);
-        }
-    })
+        let _ = execute!(stdout, LeaveAlternateScreen, DisableMouseCapture);
+        let _ = disable_raw_mode();
+        original_hook(panic);
+    }));
+
+    let res = app.run(&mut terminal);
+
+    // Cleanup terminal
+    disable_raw_mode().unwrap();
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )
+    .unwrap();
+    terminal.show_cursor().unwrap();
+
+    if let Err(err) = res {
+        log::error!("{:?}", err);
+    }
 }
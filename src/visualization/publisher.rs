@@ -0,0 +1,98 @@
+use super::{ModelStructure, RuntimeStats, StepGranularity, VisualizationState, wait_for_advance};
+use crate::synapse::LayerId;
+use candle_core::Result as CandleResult;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// A network that can describe itself to the visualizer
+pub trait SnapshotSource {
+    fn snapshot(&self) -> CandleResult<ModelStructure>;
+    fn layer_activity(&self, layer_id: LayerId) -> CandleResult<Vec<f32>>;
+}
+
+/// Glue between a training loop and the shared visualization state. Call `begin_sample`,
+/// then `before_timestep`/`after_timestep` around every model step, then `end_sample`.
+pub struct VisPublisher {
+    vis_state: Arc<Mutex<VisualizationState>>,
+    start_time: Instant,
+    /// publish a full snapshot every this many timesteps (always while paused)
+    pub snapshot_interval: usize,
+    record_layer: Option<LayerId>,
+    spike_history: Vec<Vec<f32>>,
+    timesteps: usize,
+}
+
+impl VisPublisher {
+    pub fn new(vis_state: Arc<Mutex<VisualizationState>>) -> Self {
+        Self {
+            vis_state,
+            start_time: Instant::now(),
+            // snapshots pull every layer back to the host, so don't do it every timestep
+            snapshot_interval: 800,
+            record_layer: None,
+            spike_history: Vec::new(),
+            timesteps: 0,
+        }
+    }
+
+    /// Waits at the sample pause point and picks up the currently selected layer for recording.
+    /// Returns false if the visualizer asked to close.
+    pub fn begin_sample(&mut self) -> bool {
+        if !wait_for_advance(&self.vis_state, StepGranularity::Sample) {
+            return false;
+        }
+        self.spike_history.clear();
+        self.record_layer = self
+            .vis_state
+            .try_lock()
+            .ok()
+            .and_then(|s| s.selected_layer_id);
+        true
+    }
+
+    /// Waits at the timestep pause point. Returns false if the visualizer asked to close.
+    pub fn before_timestep(&self) -> bool {
+        wait_for_advance(&self.vis_state, StepGranularity::Timestep)
+    }
+
+    /// Records the selected layer's spikes and publishes the snapshot and runtime stats
+    pub fn after_timestep(&mut self, source: &dyn SnapshotSource, epoch: usize, iteration: usize) {
+        self.timesteps += 1;
+
+        if let Some(layer_id) = self.record_layer
+            && let Ok(activity) = source.layer_activity(layer_id)
+        {
+            self.spike_history.push(activity);
+        }
+
+        if let Ok(mut state) = self.vis_state.try_lock()
+            && (state.is_paused || self.timesteps.is_multiple_of(self.snapshot_interval.max(1)))
+        {
+            // Stats first, so a recorded session stores them with the snapshot they belong to
+            let elapsed = self.start_time.elapsed().as_secs_f32();
+            state.runtime_stats = RuntimeStats {
+                epoch,
+                iteration,
+                timestep: self.timesteps,
+                iterations_per_second: if elapsed > 0.0 {
+                    iteration as f32 / elapsed
+                } else {
+                    0.0
+                },
+            };
+            if let Ok(snapshot) = source.snapshot() {
+                state.update_from_snapshot(snapshot);
+            }
+        }
+    }
+
+    /// Hands the spikes recorded during this sample to the raster view
+    pub fn end_sample(&mut self, epoch: usize) {
+        if self.spike_history.is_empty() {
+            return;
+        }
+        if let Ok(mut state) = self.vis_state.try_lock() {
+            state.epoch_spike_history = Some((epoch, std::mem::take(&mut self.spike_history)));
+        }
+    }
+}
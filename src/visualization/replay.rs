@@ -0,0 +1,184 @@
+//! Recording the visualizer's snapshots during training and replaying them afterwards.
+//!
+//! A session file holds one JSON [`SessionFrame`] per line, appended as the training loop
+//! publishes snapshots, so a run that dies midway still leaves a replayable file.
+
+use super::{ModelStructure, RuntimeStats, VisualizationState};
+use crate::error::{CsdpError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// One published snapshot of the model with the training progress at that point
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionFrame {
+    pub stats: RuntimeStats,
+    /// (episode, reward) of the episodes finished since the previous frame
+    pub rewards: Vec<(usize, f32)>,
+    pub structure: ModelStructure,
+}
+
+/// A [`SessionFrame`] borrowing from the shared state, so recording doesn't clone the model
+#[derive(Serialize)]
+struct FrameRef<'a> {
+    stats: &'a RuntimeStats,
+    rewards: &'a [(usize, f32)],
+    structure: &'a ModelStructure,
+}
+
+/// Appends frames to a session file
+pub struct SessionRecorder {
+    writer: BufWriter<File>,
+    frames: usize,
+    /// length of the reward history at the previous frame
+    rewards_seen: usize,
+}
+
+impl SessionRecorder {
+    /// Start a new session file at `path`, replacing any existing one
+    pub fn create(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            frames: 0,
+            rewards_seen: 0,
+        })
+    }
+
+    /// Append a frame with the rewards `epoch_rewards` gained since the previous one. It is
+    /// flushed right away so the file is complete up to the last frame.
+    pub fn record(
+        &mut self,
+        stats: &RuntimeStats,
+        epoch_rewards: &[(usize, f32)],
+        structure: &ModelStructure,
+    ) -> Result<()> {
+        let frame = FrameRef {
+            stats,
+            rewards: &epoch_rewards[self.rewards_seen.min(epoch_rewards.len())..],
+            structure,
+        };
+        serde_json::to_writer(&mut self.writer, &frame)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        self.frames += 1;
+        self.rewards_seen = epoch_rewards.len();
+        Ok(())
+    }
+
+    /// Frames recorded so far
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+}
+
+/// A recorded session with a cursor to scrub through it
+pub struct SessionReplay {
+    frames: Vec<SessionFrame>,
+    position: usize,
+    /// advance through the frames on its own, as the visualizer does while playing
+    pub playing: bool,
+}
+
+impl SessionReplay {
+    pub fn new(frames: Vec<SessionFrame>) -> Result<Self> {
+        if frames.is_empty() {
+            return Err(CsdpError::Data(
+                "a session replay needs at least one frame".into(),
+            ));
+        }
+        Ok(Self {
+            frames,
+            position: 0,
+            playing: false,
+        })
+    }
+
+    /// Read a session file written by [`SessionRecorder`]. A malformed last line, as left by a
+    /// run killed while writing, is dropped with a warning; anywhere else it is an error.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = BufReader::new(File::open(path)?)
+            .lines()
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let lines: Vec<_> = text
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .collect();
+
+        let mut frames = Vec::with_capacity(lines.len());
+        for (i, &(number, line)) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(frame) => frames.push(frame),
+                Err(e) if i + 1 == lines.len() => {
+                    log::warn!("Dropped the truncated last frame of {:?}: {}", path, e);
+                }
+                Err(e) => {
+                    return Err(CsdpError::Data(format!(
+                        "{:?} line {}: {}",
+                        path,
+                        number + 1,
+                        e
+                    )));
+                }
+            }
+        }
+        Self::new(frames)
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Index of the current frame
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn frame(&self) -> &SessionFrame {
+        &self.frames[self.position]
+    }
+
+    /// Move to frame `position`, clamped to the last one
+    pub fn seek(&mut self, position: usize) {
+        self.position = position.min(self.frames.len() - 1);
+    }
+
+    /// Move `delta` frames forward, or backward if negative, stopping at either end
+    pub fn step(&mut self, delta: isize) {
+        self.seek(self.position.saturating_add_signed(delta));
+    }
+
+    /// Whether the current frame is the last one
+    pub fn at_end(&self) -> bool {
+        self.position + 1 == self.frames.len()
+    }
+
+    /// The episode rewards reported up to the current frame, as the reward graph had them
+    pub fn rewards(&self) -> Vec<(usize, f32)> {
+        self.frames[..=self.position]
+            .iter()
+            .flat_map(|frame| frame.rewards.iter().copied())
+            .collect()
+    }
+
+    /// Put the current frame into `state` as if the training loop had just published it. Layer
+    /// positions animated by the visualizer are kept, like for live snapshots.
+    pub fn show(&self, state: &mut VisualizationState) {
+        let frame = self.frame();
+        state.update_from_snapshot(frame.structure.clone());
+        state.runtime_stats = frame.stats.clone();
+        state.epoch_rewards = self.rewards();
+        state.total_epochs = self.frames.last().map_or(0, |last| last.stats.epoch);
+        state.is_paused = !self.playing;
+    }
+}
@@ -0,0 +1,62 @@
+/// Parse a neuron selection such as "0-15, 42" into a sorted, deduplicated list of indices.
+/// Ranges are inclusive on both ends. Indices at or beyond `layer_size` are rejected.
+pub fn parse_neuron_selection(input: &str, layer_size: usize) -> Result<Vec<usize>, String> {
+    let mut indices = Vec::new();
+
+    for part in input.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((a, b)) => (parse_index(a)?, parse_index(b)?),
+            None => {
+                let idx = parse_index(part)?;
+                (idx, idx)
+            }
+        };
+
+        if start > end {
+            return Err(format!("range '{}' is reversed", part));
+        }
+        if end >= layer_size {
+            return Err(format!(
+                "neuron {} is out of range for a layer of size {}",
+                end, layer_size
+            ));
+        }
+
+        indices.extend(start..=end);
+    }
+
+    indices.sort_unstable();
+    indices.dedup();
+    Ok(indices)
+}
+
+fn parse_index(s: &str) -> Result<usize, String> {
+    s.trim()
+        .parse::<usize>()
+        .map_err(|_| format!("'{}' is not a valid neuron index", s.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ranges_and_singles() {
+        let sel = parse_neuron_selection("0-3, 42,7", 64).unwrap();
+        assert_eq!(sel, vec![0, 1, 2, 3, 7, 42]);
+    }
+
+    #[test]
+    fn test_parse_dedups_overlaps() {
+        let sel = parse_neuron_selection("2-5, 4-6, 5", 10).unwrap();
+        assert_eq!(sel, vec![2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_input() {
+        assert!(parse_neuron_selection("5-2", 10).is_err());
+        assert!(parse_neuron_selection("abc", 10).is_err());
+        assert!(parse_neuron_selection("0-10", 10).is_err());
+        assert_eq!(parse_neuron_selection("", 10).unwrap(), Vec::<usize>::new());
+    }
+}
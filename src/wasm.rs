@@ -0,0 +1,107 @@
+//! Browser-facing API for interactive demos. Built with
+//!
+//! ```text
+//! cargo build --lib --release --target wasm32-unknown-unknown
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/custom_framework.wasm
+//! ```
+//!
+//! ```js
+//! const net = new CsdpNetwork(4, 2, new Uint32Array([32, 32]), 0.1);
+//! net.step(new Float32Array([0, 1, 0, 1]));
+//! const spikes = net.spikes(net.num_layers() - 1); // Float32Array, one entry per neuron
+//! ```
+//!
+//! The network always runs on the CPU with a batch size of one.
+
+use crate::models::Model;
+use candle_core::{Device, Tensor};
+use wasm_bindgen::prelude::*;
+
+fn js_err(e: impl std::fmt::Display) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+#[wasm_bindgen]
+pub struct CsdpNetwork {
+    model: Model,
+}
+
+#[wasm_bindgen]
+impl CsdpNetwork {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        input_size: usize,
+        output_size: usize,
+        hidden_sizes: &[u32],
+        dt: f32,
+    ) -> Result<CsdpNetwork, JsError> {
+        let hidden_sizes = hidden_sizes.iter().map(|&s| s as usize).collect();
+        let mut model = Model::new(
+            input_size,
+            output_size,
+            hidden_sizes,
+            &Device::Cpu,
+            dt,
+            None,
+        )
+        .map_err(js_err)?;
+        model.reset(1).map_err(js_err)?;
+        Ok(Self { model })
+    }
+
+    /// Seed spike sampling; weights were already drawn at construction
+    pub fn seed(&mut self, seed: u64) -> Result<(), JsError> {
+        crate::seed::set_global_seed(seed, &Device::Cpu).map_err(js_err)
+    }
+
+    /// Advance one timestep. `label` (length `output_size`) drives the context layer.
+    pub fn step(&mut self, input: &[f32], label: Option<Vec<f32>>) -> Result<(), JsError> {
+        let input = Tensor::from_slice(input, (input.len(), 1), &Device::Cpu).map_err(js_err)?;
+        let label = label
+            .map(|l| {
+                let n = l.len();
+                Tensor::from_vec(l, (n, 1), &Device::Cpu)
+            })
+            .transpose()
+            .map_err(js_err)?;
+        self.model.step(&input, label.as_ref()).map_err(js_err)
+    }
+
+    /// Clear membrane potentials, traces and spikes
+    pub fn reset(&mut self) -> Result<(), JsError> {
+        self.model.reset(1).map_err(js_err)
+    }
+
+    /// Current spike output of one layer
+    pub fn spikes(&self, layer_id: usize) -> Result<Vec<f32>, JsError> {
+        self.model.get_layer_activity(layer_id).map_err(js_err)
+    }
+
+    pub fn num_layers(&self) -> usize {
+        self.model.layers.len()
+    }
+
+    pub fn layer_size(&self, layer_id: usize) -> Result<usize, JsError> {
+        self.model
+            .layers
+            .get(layer_id)
+            .map(|l| l.size())
+            .ok_or_else(|| JsError::new(&format!("layer {} does not exist", layer_id)))
+    }
+
+    pub fn layer_name(&self, layer_id: usize) -> Result<String, JsError> {
+        self.model
+            .layer_metadata
+            .get(layer_id)
+            .map(|m| m.name.clone())
+            .ok_or_else(|| JsError::new(&format!("layer {} does not exist", layer_id)))
+    }
+
+    pub fn set_learning(&mut self, enabled: bool) {
+        if enabled {
+            self.model.enable_learning();
+        } else {
+            self.model.disable_learning();
+        }
+    }
+}
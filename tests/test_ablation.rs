@@ -0,0 +1,66 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::Model;
+use custom_framework::seed;
+
+fn weights(model: &Model, id: usize) -> Vec<f32> {
+    model.synapses[id].synapse.get_state().unwrap()["weights"]
+        .flatten_all()
+        .unwrap()
+        .to_vec1::<f32>()
+        .unwrap()
+}
+
+#[test]
+fn test_frozen_and_disabled_synapses() {
+    let device = Device::Cpu;
+    seed::set_global_seed(8, &device).unwrap();
+    let mut model = Model::new(4, 2, vec![16, 16], &device, 0.1, None).unwrap();
+    let output = model.layers.len() - 1;
+    // Backward synapses between hidden layers
+    let backward: Vec<usize> = model
+        .synapses
+        .iter()
+        .filter(|s| s.metadata.pre_layer > s.metadata.post_layer && s.metadata.pre_layer != output)
+        .map(|s| s.metadata.id)
+        .collect();
+    let into_output: Vec<usize> = model
+        .synapses
+        .iter()
+        .filter(|s| s.metadata.post_layer == output)
+        .map(|s| s.metadata.id)
+        .collect();
+    assert!(!backward.is_empty());
+    for &id in &backward {
+        model.freeze_synapse(id).unwrap();
+    }
+    for &id in &into_output {
+        model.disable_connection(id).unwrap();
+    }
+    assert!(model.freeze_synapse(model.synapses.len()).is_err());
+
+    let frozen_before: Vec<_> = backward.iter().map(|&id| weights(&model, id)).collect();
+    let disabled_before: Vec<_> = into_output.iter().map(|&id| weights(&model, id)).collect();
+    model.reset(1).unwrap();
+    let input = Tensor::ones((4, 1), DType::F32, &device).unwrap();
+    let context = Tensor::ones((2, 1), DType::F32, &device).unwrap();
+    for _ in 0..20 {
+        model.step(&input, Some(&context)).unwrap();
+        let spikes = model.layers[output].output().unwrap();
+        assert_eq!(spikes.sum_all().unwrap().to_scalar::<f32>().unwrap(), 0.0);
+    }
+    for (&id, before) in backward.iter().zip(&frozen_before) {
+        assert_eq!(&weights(&model, id), before);
+    }
+    for (&id, before) in into_output.iter().zip(&disabled_before) {
+        assert_eq!(&weights(&model, id), before);
+    }
+
+    let snapshot = model.get_visualization_snapshot().unwrap();
+    assert!(snapshot.synapses.iter().any(|s| !s.is_learning));
+    assert!(snapshot.synapses.iter().any(|s| !s.enabled));
+
+    model.unfreeze_synapse(backward[0]).unwrap();
+    model.enable_connection(into_output[0]).unwrap();
+    assert!(model.synapses[backward[0]].metadata.is_learning);
+    assert!(model.synapses[into_output[0]].metadata.enabled);
+}
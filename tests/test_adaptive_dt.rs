@@ -0,0 +1,42 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::{AdaptiveDt, Model, ModelConfig};
+use custom_framework::seed;
+
+fn adaptive_model(tolerance: f32, device: &Device) -> Model {
+    let mut config = ModelConfig::standard(4, 2, vec![8], 0.1, None).unwrap();
+    config.adaptive_dt = Some(AdaptiveDt::around(0.1, tolerance));
+    Model::from_config(config, device).unwrap()
+}
+
+#[test]
+fn test_adaptive_dt_controller() {
+    let adaptive = AdaptiveDt::around(0.1, 0.05);
+    assert!((adaptive.next_dt(0.1, 0.1) - 0.05).abs() < 1e-6);
+    assert!((adaptive.next_dt(0.1, 0.0) - 0.2).abs() < 1e-6);
+    assert!((adaptive.next_dt(0.4, 0.0) - 0.4).abs() < 1e-6);
+    assert!((adaptive.next_dt(0.025, 10.0) - 0.025).abs() < 1e-6);
+}
+
+#[test]
+fn test_adaptive_dt_takes_fewer_steps_when_quiescent() {
+    let device = Device::Cpu;
+    seed::set_global_seed(3, &device).unwrap();
+
+    let mut quiet = adaptive_model(0.001, &device);
+    let silent = Tensor::zeros((4, 1), DType::F32, &device).unwrap();
+    let steps = quiet
+        .process(&silent, 40, true, &device)
+        .unwrap()
+        .output_activity
+        .len();
+    assert!(steps < 20, "quiescent model took {} steps", steps);
+
+    let mut driven = adaptive_model(0.001, &device);
+    let active = Tensor::ones((4, 1), DType::F32, &device).unwrap();
+    let driven_steps = driven
+        .process(&active, 40, true, &device)
+        .unwrap()
+        .output_activity
+        .len();
+    assert!(driven_steps > steps);
+}
@@ -0,0 +1,97 @@
+use candle_core::{Device, Tensor};
+use custom_framework::layer::Layer;
+use custom_framework::layer::lif::LIFLayer;
+use custom_framework::layer::mod_signal::standard::StandardModSignal;
+use custom_framework::models::Model;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Counts heap allocations per thread so parallel tests don't see each other's
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.with(|a| a.set(a.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn count_allocs<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCS.with(|a| a.get());
+    let out = f();
+    (out, ALLOCS.with(|a| a.get()) - before)
+}
+
+#[test]
+fn test_input_compartment_reuses_buffers() {
+    let device = Device::Cpu;
+    let size = 64;
+    let batch = 8;
+    let mod_signal = Box::new(StandardModSignal::new(size, 5.0, 1.0, 32.0, &device).unwrap());
+    let mut layer = LIFLayer::new(size, 13.0, 0.5, 0.01, mod_signal, &device).unwrap();
+    let input = Tensor::ones((size, batch), candle_core::DType::F32, &device).unwrap();
+
+    layer.reset(batch).unwrap();
+
+    // Same batch size: no new zero tensors
+    let (_, n) = count_allocs(|| layer.reset(batch).unwrap());
+    assert_eq!(n, 0, "reset with an unchanged batch size allocated");
+
+    // Clearing the inputs and receiving a single input adopts the tensor
+    let (_, n) = count_allocs(|| {
+        layer.reset_input().unwrap();
+        layer.add_input(&input).unwrap();
+    });
+    assert_eq!(n, 0, "reset_input + first add_input allocated");
+
+    // A second input still has to be summed, but costs no more than one add
+    let (_, add) = count_allocs(|| (&input + &input).unwrap());
+    let (_, n) = count_allocs(|| layer.add_input(&input).unwrap());
+    assert!(
+        n <= add,
+        "add_input allocated {n}, a plain add allocates {add}"
+    );
+}
+
+/// Allocations per step for this model before inputs were pooled
+const BASELINE_ALLOCS_PER_STEP: usize = 1425;
+
+#[test]
+fn test_allocations_per_step_below_baseline() {
+    let device = Device::Cpu;
+    let mut model = Model::new(32, 4, vec![64, 64], &device, 0.1, None).unwrap();
+    let input = Tensor::rand(0.0f32, 1.0, (32, 1), &device).unwrap();
+    let label = Tensor::ones((4, 1), candle_core::DType::F32, &device).unwrap();
+    model.enable_learning();
+    model.reset(1).unwrap();
+
+    // A single-thread pool keeps every phase sequential on the thread being counted
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    let steps = 40;
+    let (_, n) = pool.install(|| {
+        count_allocs(|| {
+            for _ in 0..steps {
+                model.step(&input, Some(&label)).unwrap();
+            }
+        })
+    });
+    let per_step = n / steps;
+    assert!(
+        per_step < BASELINE_ALLOCS_PER_STEP,
+        "{per_step} allocations per step, baseline was {BASELINE_ALLOCS_PER_STEP}"
+    );
+}
@@ -0,0 +1,114 @@
+#![cfg(feature = "robot")]
+
+use custom_framework::robot::anomaly::{
+    AnomalyConfig, AnomalyDetector, AnomalyKind, SensorReading,
+};
+use std::f64::consts::PI;
+
+/// Cycle `k` of healthy operation: every joint swings with a 2 s period, loads follow the
+/// velocity and joint 2 holds a constant load on top
+fn healthy(k: usize) -> SensorReading {
+    let phase = |j: usize| 2.0 * PI * k as f64 * 0.05 / 2.0 + j as f64;
+    let velocity = |j: usize| 0.5 * PI * phase(j).cos();
+    SensorReading {
+        positions: (0..6).map(|j| 0.5 * phase(j).sin()).collect(),
+        loads: (0..6)
+            .map(|j| 100.0 * velocity(j) + if j == 1 { 200.0 } else { 0.0 })
+            .collect(),
+    }
+}
+
+fn fitted() -> AnomalyDetector {
+    let readings: Vec<_> = (0..400).map(|k| Some(healthy(k))).collect();
+    AnomalyDetector::fit(6, AnomalyConfig::default(), &readings).unwrap()
+}
+
+#[test]
+fn test_healthy_operation_raises_nothing() {
+    let mut detector = fitted();
+    assert_eq!(detector.num_prototypes(), 8);
+    assert!(detector.observe(Some(&healthy(400))).is_empty());
+    assert_eq!(detector.score(), None);
+    for k in 401..480 {
+        assert!(detector.observe(Some(&healthy(k))).is_empty());
+        assert!(detector.score().unwrap() < 2.0);
+    }
+}
+
+#[test]
+fn test_anomalies_are_classified() {
+    let mut detector = fitted();
+    detector.observe(Some(&healthy(400)));
+
+    let mut spike = healthy(401);
+    spike.loads[2] += 500.0;
+    let events = detector.observe(Some(&spike));
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, AnomalyKind::LoadSpike { joint: 2 });
+    assert_eq!(events[0].cycle, 1);
+
+    assert!(detector.observe(Some(&healthy(402))).is_empty());
+
+    // Joint 2 stops dead against an obstacle while swinging at full speed
+    detector.reset();
+    detector.observe(Some(&healthy(410)));
+    assert!(detector.observe(Some(&healthy(411))).is_empty());
+    let mut collision = healthy(412);
+    collision.positions[1] = healthy(411).positions[1];
+    collision.loads[1] += 400.0;
+    let events = detector.observe(Some(&collision));
+    assert_eq!(events[0].kind, AnomalyKind::Collision { joint: 1 });
+
+    // A jump no joint makes in normal operation, with normal loads
+    let mut jump = healthy(413);
+    jump.positions[4] += 0.5;
+    let events = detector.observe(Some(&jump));
+    assert_eq!(events[0].kind, AnomalyKind::Unfamiliar);
+    assert!(events[0].score > detector.config.threshold);
+}
+
+#[test]
+fn test_dropouts_are_reported_after_consecutive_failures() {
+    let mut detector = fitted();
+    detector.observe(Some(&healthy(400)));
+    assert!(detector.observe(None).is_empty());
+    let mut garbled = healthy(401);
+    garbled.positions[0] = f64::NAN;
+    assert!(detector.observe(Some(&garbled)).is_empty());
+    let events = detector.observe(None);
+    assert_eq!(events[0].kind, AnomalyKind::SensorDropout { cycles: 3 });
+    assert_eq!(
+        events[0].to_string(),
+        "sensor dropout for 3 cycles (cycle 3)"
+    );
+
+    // The next good reading restarts the velocities and isn't scored
+    assert!(detector.observe(Some(&healthy(410))).is_empty());
+    assert_eq!(detector.score(), None);
+    assert!(detector.observe(Some(&healthy(411))).is_empty());
+    assert!(detector.score().is_some());
+}
+
+#[test]
+fn test_save_and_load() {
+    let detector = fitted();
+    let path = std::env::temp_dir().join(format!("csdp_anomaly_{}.json", std::process::id()));
+    detector.save(&path).unwrap();
+    let mut loaded = AnomalyDetector::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(loaded.num_prototypes(), detector.num_prototypes());
+
+    let mut detector = detector;
+    let mut spike = healthy(401);
+    spike.loads[2] += 500.0;
+    for reading in [healthy(400), spike] {
+        assert_eq!(
+            loaded.observe(Some(&reading)).len(),
+            detector.observe(Some(&reading)).len()
+        );
+    }
+    assert!((loaded.score().unwrap() - detector.score().unwrap()).abs() < 1e-9);
+
+    let few: Vec<_> = (0..5).map(|k| Some(healthy(k))).collect();
+    assert!(AnomalyDetector::fit(6, AnomalyConfig::default(), &few).is_err());
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "robot")]
+
+use custom_framework::robot::bilateral::{ForceFeedback, ForceFeedbackConfig};
+
+fn feedback(smoothing: f64) -> ForceFeedback {
+    ForceFeedback::new(ForceFeedbackConfig {
+        gain: 0.001,
+        deadband: 50.0,
+        max_offset: 0.2,
+        smoothing,
+        torque_limit: 150,
+    })
+    .unwrap()
+}
+
+#[test]
+fn test_loads_push_the_leader_back() {
+    let mut feedback = feedback(1.0);
+    let offsets = feedback.offsets(&[30.0, -30.0, 150.0, -150.0, 1000.0, -1000.0]);
+    let expected = [0.0, 0.0, -0.1, 0.1, -0.2, 0.2];
+    for (offset, expected) in offsets.iter().zip(expected) {
+        assert!((offset - expected).abs() < 1e-9, "{:?}", offsets);
+    }
+
+    let goal = feedback.leader_goal(&[1.0; 6], &[150.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    assert!((goal[0] - 0.9).abs() < 1e-9);
+    assert_eq!(goal[1..], [1.0; 5]);
+}
+
+#[test]
+fn test_load_spikes_are_filtered() {
+    let mut feedback = feedback(0.25);
+    assert_eq!(feedback.offsets(&[0.0]), vec![0.0]);
+    // A single spike moves the filtered load by a quarter, within the deadband
+    assert_eq!(feedback.offsets(&[200.0]), vec![0.0]);
+    for _ in 0..30 {
+        feedback.offsets(&[200.0]);
+    }
+    assert!((feedback.offsets(&[200.0])[0] + 0.15).abs() < 1e-3);
+
+    feedback.reset();
+    assert_eq!(feedback.offsets(&[0.0]), vec![0.0]);
+}
+
+#[test]
+fn test_invalid_feedback_is_rejected() {
+    let config = ForceFeedbackConfig::default();
+    assert!(ForceFeedback::new(config).is_ok());
+    for invalid in [
+        ForceFeedbackConfig {
+            gain: -1.0,
+            ..config
+        },
+        ForceFeedbackConfig {
+            smoothing: 0.0,
+            ..config
+        },
+        ForceFeedbackConfig {
+            max_offset: f64::NAN,
+            ..config
+        },
+    ] {
+        assert!(ForceFeedback::new(invalid).is_err());
+    }
+}
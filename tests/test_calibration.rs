@@ -0,0 +1,128 @@
+use candle_core::{Device, Tensor};
+use custom_framework::models::calibration::{
+    LinearReadout, ReadoutFit, readout_features, readout_layers,
+};
+use custom_framework::models::{Model, ModelConfig};
+use custom_framework::seed;
+
+/// Noisy rates of three classes, each driving its own feature, plus a constant fourth feature
+fn clusters() -> (Vec<Vec<f32>>, Vec<usize>) {
+    let mut features = Vec::new();
+    let mut labels = Vec::new();
+    for i in 0..20 {
+        let noise = |k: usize| ((i * 7 + k * 3) % 5) as f32 * 0.04;
+        for class in 0..3 {
+            let mut row: Vec<f32> = (0..3)
+                .map(|k| noise(k) + if k == class { 0.7 } else { 0.1 })
+                .collect();
+            row.push(0.3);
+            features.push(row);
+            labels.push(class);
+        }
+    }
+    (features, labels)
+}
+
+#[test]
+fn test_fits_separate_classes() {
+    seed::set_global_seed(5, &Device::Cpu).unwrap();
+    let (features, labels) = clusters();
+    for fit in [
+        ReadoutFit::LeastSquares { ridge: 1e-4 },
+        ReadoutFit::Delta {
+            epochs: 200,
+            learning_rate: 0.1,
+        },
+    ] {
+        let readout = LinearReadout::fit(&features, &labels, 3, fit).unwrap();
+        assert_eq!(readout.accuracy(&features, &labels), 1.0, "{:?}", fit);
+        assert_eq!(readout.predict(&[0.9, 0.0, 0.1, 0.3]), 0);
+    }
+
+    // The constant feature duplicates the bias
+    assert!(
+        LinearReadout::fit(
+            &features,
+            &labels,
+            3,
+            ReadoutFit::LeastSquares { ridge: 0.0 }
+        )
+        .is_err()
+    );
+    assert!(LinearReadout::fit(&features, &labels, 2, ReadoutFit::default()).is_err());
+    assert!(LinearReadout::fit(&features, &labels[1..], 3, ReadoutFit::default()).is_err());
+}
+
+#[test]
+fn test_apply_writes_only_the_output_synapses() {
+    let device = Device::Cpu;
+    seed::set_global_seed(9, &device).unwrap();
+    let config = ModelConfig::standard(4, 2, vec![8, 8], 0.1, None).unwrap();
+    let mut model = Model::from_config(config, &device).unwrap();
+    model.disable_learning();
+
+    let layers = readout_layers(&model);
+    assert!(!layers.is_empty());
+    let input = Tensor::new(
+        &[
+            [1.0f32, 0.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0, 1.0],
+            [1.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 1.0],
+        ],
+        &device,
+    )
+    .unwrap();
+    let features = readout_features(&mut model, &input, 20).unwrap();
+    assert_eq!(features.len(), 4);
+    assert_eq!(features[0].len(), 8 * layers.len());
+    assert!(features.iter().flatten().all(|r| (0.0..=1.0).contains(r)));
+
+    let labels = [0, 1, 0, 1];
+    let readout = LinearReadout::fit(&features, &labels, 2, ReadoutFit::default()).unwrap();
+    let before: Vec<_> = model
+        .synapses
+        .iter()
+        .map(|s| s.synapse.get_state().unwrap()["weights"].clone())
+        .collect();
+    let changed = readout.apply(&mut model, 2.0).unwrap();
+    assert_eq!(
+        changed,
+        layers.iter().map(|&(id, _)| id).collect::<Vec<_>>()
+    );
+
+    let (weights, _) = readout.tensors(&device).unwrap();
+    for (conn, old) in model.synapses.iter().zip(before) {
+        let new = conn.synapse.get_state().unwrap()["weights"].clone();
+        let difference = |a: &Tensor, b: &Tensor| {
+            a.sub(b)
+                .unwrap()
+                .abs()
+                .unwrap()
+                .sum_all()
+                .unwrap()
+                .to_scalar::<f32>()
+                .unwrap()
+        };
+        if changed.contains(&conn.metadata.id) {
+            let offset = layers
+                .iter()
+                .position(|&(id, _)| id == conn.metadata.id)
+                .unwrap()
+                * 8;
+            let expected = weights
+                .narrow(1, offset, 8)
+                .unwrap()
+                .affine(2.0, 0.0)
+                .unwrap();
+            assert!(difference(&new, &expected) < 1e-5);
+        } else {
+            assert_eq!(difference(&new, &old), 0.0);
+        }
+    }
+
+    // A readout of another shape doesn't fit the model
+    let (features, labels) = clusters();
+    let other = LinearReadout::fit(&features, &labels, 3, ReadoutFit::default()).unwrap();
+    assert!(other.apply(&mut model, 1.0).is_err());
+}
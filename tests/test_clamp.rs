@@ -0,0 +1,32 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::Model;
+use custom_framework::seed;
+
+#[test]
+fn test_output_clamped_to_target_and_anti_pattern() {
+    let device = Device::Cpu;
+    seed::set_global_seed(5, &device).unwrap();
+    let mut model = Model::new(3, 2, vec![8], &device, 0.1, None).unwrap();
+    let target = Tensor::from_vec(vec![1.0f32, 0.0, 0.0, 1.0], (2, 2), &device).unwrap();
+    model.clamp_output(Some(&target)).unwrap();
+    let wrong_size = Tensor::ones((3, 1), DType::F32, &device).unwrap();
+    assert!(model.clamp_output(Some(&wrong_size)).is_err());
+
+    // Both phases of a contrastive batch: labels 1, 1, 0, 0
+    let label = Tensor::from_vec(vec![1.0f32, 1.0, 0.0, 0.0], (1, 4), &device).unwrap();
+    model.set_positive_sample(&label).unwrap();
+    model.reset(4).unwrap();
+    let input = Tensor::ones((3, 4), DType::F32, &device).unwrap();
+    model.step(&input, None).unwrap();
+    let spikes = model.layers.last().unwrap().output().unwrap();
+    assert_eq!(
+        spikes.to_vec2::<f32>().unwrap(),
+        vec![vec![1.0, 0.0, 0.0, 1.0], vec![0.0, 1.0, 1.0, 0.0]]
+    );
+
+    model.clamp_output(None).unwrap();
+    model.reset(4).unwrap();
+    model.step(&input, None).unwrap();
+    let free = model.layers.last().unwrap().output().unwrap();
+    assert_eq!(free.sum_all().unwrap().to_scalar::<f32>().unwrap(), 0.0);
+}
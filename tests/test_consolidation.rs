@@ -0,0 +1,123 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::consolidation::ConsolidationConfig;
+use custom_framework::models::{Model, ModelConfig};
+use custom_framework::seed;
+
+fn new_model(device: &Device) -> Model {
+    seed::set_global_seed(7, device).unwrap();
+    let config = ModelConfig::standard(4, 2, vec![8], 0.1, None).unwrap();
+    let mut model = Model::from_config(config, device).unwrap();
+    model.reset(1).unwrap();
+    model
+}
+
+fn train(model: &mut Model, input: &[f32; 4], label: &[f32; 2], steps: usize) {
+    let device = model.device.clone();
+    let input = Tensor::new(input, &device)
+        .unwrap()
+        .reshape((4, 1))
+        .unwrap();
+    let label = Tensor::new(label, &device)
+        .unwrap()
+        .reshape((2, 1))
+        .unwrap();
+    model
+        .set_positive_sample(&Tensor::ones((1, 1), DType::F32, &device).unwrap())
+        .unwrap();
+    for _ in 0..steps {
+        model.step(&input, Some(&label)).unwrap();
+    }
+}
+
+fn weights(model: &Model, synapse: usize) -> Vec<f32> {
+    model.synapses[synapse].synapse.get_state().unwrap()["weights"]
+        .flatten_all()
+        .unwrap()
+        .to_vec1::<f32>()
+        .unwrap()
+}
+
+#[test]
+fn test_important_weights_are_pinned() {
+    let device = Device::Cpu;
+    let mut model = new_model(&device);
+    model
+        .enable_consolidation(ConsolidationConfig { strength: 1.0 })
+        .unwrap();
+
+    train(&mut model, &[1.0, 1.0, 0.0, 0.0], &[1.0, 0.0], 30);
+    assert_eq!(model.consolidation().unwrap().steps(), 30);
+    model.consolidate().unwrap();
+    let consolidation = model.consolidation().unwrap();
+    assert!(consolidation.is_anchored());
+    assert_eq!(consolidation.steps(), 0);
+
+    // Normalized per tensor, so the most important weight has importance 1
+    let importance = consolidation.importance(0)[0]
+        .flatten_all()
+        .unwrap()
+        .to_vec1::<f32>()
+        .unwrap();
+    assert!(importance.iter().all(|i| (0.0..=1.0).contains(i)));
+    let (most, &max) = importance
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .unwrap();
+    assert!((max - 1.0).abs() < 1e-6);
+
+    let anchor = weights(&model, 0);
+    train(&mut model, &[0.0, 0.0, 1.0, 1.0], &[0.0, 1.0], 30);
+    let after = weights(&model, 0);
+    assert!((after[most] - anchor[most]).abs() < 1e-5);
+    assert_ne!(after, anchor);
+}
+
+#[test]
+fn test_consolidation_is_saved() {
+    let device = Device::Cpu;
+    let mut model = new_model(&device);
+    model
+        .enable_consolidation(ConsolidationConfig::default())
+        .unwrap();
+    train(&mut model, &[1.0, 0.0, 1.0, 0.0], &[1.0, 0.0], 10);
+    model.consolidate().unwrap();
+
+    let path = std::env::temp_dir().join(format!("consolidation_{}.st", std::process::id()));
+    model.save(&path).unwrap();
+    let mut restored = new_model(&device);
+    restored
+        .enable_consolidation(ConsolidationConfig::default())
+        .unwrap();
+    restored.load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let original = model.consolidation().unwrap();
+    let loaded = restored.consolidation().unwrap();
+    assert!(loaded.is_anchored());
+    for synapse in 0..model.synapses.len() {
+        let expected = original.importance(synapse);
+        let actual = loaded.importance(synapse);
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            let difference = a.sub(e).unwrap().abs().unwrap().sum_all().unwrap();
+            assert_eq!(difference.to_scalar::<f32>().unwrap(), 0.0);
+        }
+    }
+}
+
+#[test]
+fn test_consolidate_needs_consolidation_enabled() {
+    let device = Device::Cpu;
+    let mut model = new_model(&device);
+    assert!(model.consolidate().is_err());
+    assert!(
+        model
+            .enable_consolidation(ConsolidationConfig { strength: -1.0 })
+            .is_err()
+    );
+    // Without a consolidation, plasticity is unchanged
+    let input = Tensor::ones((4, 1), DType::F32, &device).unwrap();
+    model.step(&input, None).unwrap();
+    assert!(model.consolidation().is_none());
+}
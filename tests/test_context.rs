@@ -0,0 +1,89 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::context::ContextGatingConfig;
+use custom_framework::models::{Model, ModelConfig};
+use custom_framework::seed;
+
+fn gated_model(seed_value: u64, device: &Device) -> Model {
+    seed::set_global_seed(seed_value, device).unwrap();
+    let config = ModelConfig::standard(4, 2, vec![16, 16], 0.1, None).unwrap();
+    let mut model = Model::from_config(config, device).unwrap();
+    let config = ContextGatingConfig {
+        inhibition: 100.0,
+        ..ContextGatingConfig::new(3)
+    };
+    model.enable_context_gating(config).unwrap();
+    model.reset(1).unwrap();
+    model
+}
+
+/// Gate of the first hidden layer under the current task
+fn gate(model: &Model) -> Vec<f32> {
+    let (id, gate) = &model.context_gating().unwrap().active()[0];
+    assert_eq!(*id, 2);
+    gate.flatten_all().unwrap().to_vec1::<f32>().unwrap()
+}
+
+#[test]
+fn test_gated_neurons_stay_silent() {
+    let device = Device::Cpu;
+    let mut model = gated_model(1, &device);
+    // Both hidden layers are gated, the output layer is not
+    assert_eq!(model.context_gating().unwrap().active().len(), 2);
+
+    let input = Tensor::ones((4, 1), DType::F32, &device).unwrap();
+    for task in [0, 2] {
+        model.set_context(task).unwrap();
+        assert_eq!(model.context(), Some(task));
+        let active = gate(&model);
+        assert_eq!(active.iter().filter(|&&g| g == 0.0).count(), 8);
+
+        let mut spikes = vec![0.0f32; 16];
+        for _ in 0..20 {
+            model.step(&input, None).unwrap();
+            let output = model.layers[2].output().unwrap();
+            for (total, s) in spikes
+                .iter_mut()
+                .zip(output.flatten_all().unwrap().to_vec1::<f32>().unwrap())
+            {
+                *total += s;
+            }
+        }
+        for (total, g) in spikes.iter().zip(&active) {
+            if *g < 0.0 {
+                assert_eq!(*total, 0.0);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_tasks_get_their_own_subsets() {
+    let device = Device::Cpu;
+    let mut model = gated_model(2, &device);
+    let first = gate(&model);
+    model.set_context(1).unwrap();
+    assert_ne!(gate(&model), first);
+
+    assert!(model.set_context(3).is_err());
+    assert_eq!(model.context(), Some(1));
+
+    model.disable_context_gating();
+    assert!(model.set_context(0).is_err());
+    assert_eq!(model.context(), None);
+}
+
+#[test]
+fn test_gates_are_saved() {
+    let device = Device::Cpu;
+    let mut model = gated_model(3, &device);
+    model.set_context(2).unwrap();
+    let path = std::env::temp_dir().join(format!("context_{}.st", std::process::id()));
+    model.save(&path).unwrap();
+
+    // Another seed draws other subsets until the saved ones are loaded
+    let mut restored = gated_model(4, &device);
+    restored.load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    restored.set_context(2).unwrap();
+    assert_eq!(gate(&restored), gate(&model));
+}
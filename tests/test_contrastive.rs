@@ -0,0 +1,34 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::Model;
+use custom_framework::seed;
+
+#[test]
+fn test_contrastive_phases_share_one_pass() {
+    let device = Device::Cpu;
+    seed::set_global_seed(21, &device).unwrap();
+    let mut model = Model::new(3, 1, vec![16], &device, 0.1, None).unwrap();
+
+    let positive = Tensor::ones((3, 4), DType::F32, &device).unwrap();
+    let negative = Tensor::zeros((3, 4), DType::F32, &device).unwrap();
+    let context = Tensor::ones((1, 4), DType::F32, &device).unwrap();
+    let output = model
+        .process_contrastive(&positive, &negative, Some(&context), Some(&context), 100)
+        .unwrap();
+    assert_eq!(output.positive_goodness.dims(), [4]);
+    assert_eq!(output.negative_goodness.dims(), [4]);
+    // All-on and all-off inputs drive the hidden layer differently
+    let positive_goodness: Vec<f32> = output.positive_goodness.to_vec1().unwrap();
+    let negative_goodness: Vec<f32> = output.negative_goodness.to_vec1().unwrap();
+    assert_ne!(positive_goodness, negative_goodness);
+
+    // The stacked (1, 8) label doesn't outlive the call: a normal batch of 4 still runs
+    let out = model.process(&positive, 5, false, &device).unwrap();
+    assert_eq!(out.final_output.dims(), &[1, 4]);
+
+    let short = Tensor::zeros((3, 2), DType::F32, &device).unwrap();
+    assert!(
+        model
+            .process_contrastive(&positive, &short, None, None, 10)
+            .is_err()
+    );
+}
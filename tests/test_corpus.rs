@@ -0,0 +1,66 @@
+#![cfg(feature = "robot")]
+
+use custom_framework::dataset::trajectory::Trajectory;
+use custom_framework::robot::corpus::{Appended, Corpus, MANIFEST_FILE};
+use custom_framework::robot::profile::RobotProfile;
+
+fn episode(start_ms: u64, value: f64) -> Trajectory {
+    Trajectory {
+        channels: (1..=6).map(|j| format!("j{}", j)).collect(),
+        timestamps_ms: Some(vec![start_ms, start_ms + 33, start_ms + 66]),
+        frames: vec![vec![0.0; 6], vec![value; 6], vec![2.0 * value; 6]],
+    }
+}
+
+#[test]
+fn test_append_deduplicates_across_sessions() {
+    let dir = std::env::temp_dir().join(format!("csdp_corpus_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let profile = RobotProfile::leader();
+
+    let mut corpus = Corpus::open_or_create(&dir, "pick", "leader", &profile).unwrap();
+    assert!(corpus.is_empty());
+    let first = corpus.append(&episode(0, 0.1)).unwrap();
+    assert_eq!(
+        first,
+        Appended::Added {
+            index: 0,
+            file: "episode_000.csv".to_string()
+        }
+    );
+    // Shifted in time, but the same recording
+    assert_eq!(
+        corpus.append(&episode(5000, 0.1)).unwrap(),
+        Appended::Duplicate { index: 0 }
+    );
+    assert!(matches!(
+        corpus.append(&episode(0, 0.2)).unwrap(),
+        Appended::Added { index: 1, .. }
+    ));
+
+    // A later session reopens the corpus and still knows its episodes
+    let mut corpus = Corpus::open_or_create(&dir, "ignored", "leader", &profile).unwrap();
+    assert_eq!((corpus.len(), corpus.manifest().task.as_str()), (2, "pick"));
+    assert_eq!(
+        corpus.append(&episode(0, 0.2)).unwrap(),
+        Appended::Duplicate { index: 1 }
+    );
+
+    // Importing a whole session skips what is already there
+    let other = dir.join("other");
+    let mut session = Corpus::open_or_create(&other, "pick", "leader", &profile).unwrap();
+    session.append(&episode(0, 0.1)).unwrap();
+    session.append(&episode(0, 0.3)).unwrap();
+    let outcomes = corpus.append_session(&other).unwrap();
+    assert_eq!(outcomes[0], Appended::Duplicate { index: 0 });
+    assert!(matches!(outcomes[1], Appended::Added { index: 2, .. }));
+
+    let stored = Trajectory::load(corpus.episode_path(2).unwrap()).unwrap();
+    assert_eq!(stored, episode(0, 0.3));
+    let manifest: serde_json::Value =
+        serde_json::from_reader(std::fs::File::open(dir.join(MANIFEST_FILE)).unwrap()).unwrap();
+    assert_eq!(manifest["episodes"].as_array().unwrap().len(), 3);
+    assert!(manifest["episodes"][2]["hash"].is_u64());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
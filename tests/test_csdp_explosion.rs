@@ -64,7 +64,7 @@ fn test_investigate_csdp_explosion() {
             post_layer.reset(1).unwrap();
             post_layer.step(dt).unwrap();
 
-            csdp.update_weights(&pre_activity, &mut post_layer, dt)
+            csdp.update_weights(&pre_activity, post_layer.as_ref(), dt)
                 .unwrap();
 
             // Re-simulate pre_activity as somewhat sparse spikes
@@ -0,0 +1,71 @@
+use candle_core::Device;
+use custom_framework::models::curriculum::{ActionCurriculum, CurriculumConfig};
+use custom_framework::models::robot_model::RobotModel;
+
+fn config() -> CurriculumConfig {
+    CurriculumConfig {
+        initial_step: 0.2,
+        final_step: 0.04,
+        refine_factor: 0.5,
+        advance_ratio: 2.0,
+        patience: 3,
+    }
+}
+
+#[test]
+fn test_step_is_refined_as_tracking_improves() {
+    let mut curriculum = ActionCurriculum::new(config()).unwrap();
+    assert_eq!(curriculum.step_size(), 0.2);
+    // Mean error 0.5 rad is more than two steps of 0.2
+    for error in [0.6, 0.5, 0.4] {
+        assert_eq!(curriculum.observe(error), None);
+    }
+    // The last three errors average 0.3, within two steps
+    assert_eq!(curriculum.observe(0.0), Some(0.1));
+    assert_eq!(curriculum.stage(), 1);
+
+    // Errors seen at the coarse step don't count for the finer one
+    assert_eq!(curriculum.observe(0.05), None);
+    assert_eq!(curriculum.observe(f64::NAN), None);
+    assert_eq!(curriculum.observe(0.05), None);
+    // Refinement stops at the final step
+    assert_eq!(curriculum.observe(0.05), Some(0.05));
+    for _ in 0..3 {
+        curriculum.observe(0.0);
+    }
+    assert_eq!(curriculum.step_size(), 0.04);
+    assert!(curriculum.is_final());
+    assert_eq!(curriculum.observe(0.0), None);
+
+    curriculum.reset();
+    assert_eq!((curriculum.step_size(), curriculum.stage()), (0.2, 0));
+}
+
+#[test]
+fn test_curriculum_sets_the_decoder_step() {
+    let mut model = RobotModel::new(1, 8, &Device::Cpu, 0.1);
+    let curriculum = ActionCurriculum::new(config()).unwrap();
+    curriculum.apply(&mut model);
+    assert_eq!(model.step_size(), 0.2);
+    assert_eq!(model.decoder.step_size, 0.2);
+}
+
+#[test]
+fn test_invalid_curriculum_is_rejected() {
+    for invalid in [
+        CurriculumConfig {
+            final_step: 0.5,
+            ..config()
+        },
+        CurriculumConfig {
+            refine_factor: 1.0,
+            ..config()
+        },
+        CurriculumConfig {
+            patience: 0,
+            ..config()
+        },
+    ] {
+        assert!(ActionCurriculum::new(invalid).is_err());
+    }
+}
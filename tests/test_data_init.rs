@@ -0,0 +1,96 @@
+use candle_core::{Device, Tensor};
+use custom_framework::models::data_init::{DataInit, init_from_data};
+use custom_framework::models::{Model, ModelConfig};
+use custom_framework::seed;
+
+/// 40 samples from two clusters that differ along (1, 1, -1, -1)
+fn two_clusters(device: &Device) -> Tensor {
+    let mut values = vec![];
+    for i in 0..40 {
+        let jitter = (i % 5) as f32 * 0.01;
+        let sample = if i % 2 == 0 {
+            [0.9, 0.9, 0.1, 0.1]
+        } else {
+            [0.1, 0.1, 0.9, 0.9]
+        };
+        values.extend(sample.map(|x| x + jitter));
+    }
+    Tensor::from_vec(values, (40, 4), device)
+        .unwrap()
+        .t()
+        .unwrap()
+}
+
+fn input_weights(model: &Model) -> Vec<Vec<f32>> {
+    model.synapses[0].synapse.get_state().unwrap()["weights"]
+        .to_vec2::<f32>()
+        .unwrap()
+}
+
+fn norm(v: &[f32]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Cosine of `row` with the direction the clusters differ along
+fn alignment(row: &[f32]) -> f32 {
+    (row[0] + row[1] - row[2] - row[3]) / 2.0 / norm(row)
+}
+
+fn model(device: &Device) -> Model {
+    let config = ModelConfig::standard(4, 2, vec![2], 0.1, None).unwrap();
+    Model::from_config(config, device).unwrap()
+}
+
+#[test]
+fn test_k_means_finds_the_clusters() {
+    let device = Device::Cpu;
+    seed::set_global_seed(12, &device).unwrap();
+    let mut model = model(&device);
+    assert_eq!(model.synapses[0].metadata.pre_layer, 0);
+    let random = input_weights(&model);
+
+    let changed = init_from_data(&mut model, &two_clusters(&device), DataInit::default()).unwrap();
+    assert_eq!(changed, vec![0]);
+    let weights = input_weights(&model);
+    for (row, before) in weights.iter().zip(&random) {
+        assert!(alignment(row).abs() > 0.99, "{:?}", row);
+        assert!((norm(row) - norm(before)).abs() < 1e-4);
+    }
+    // One neuron per cluster
+    assert!(alignment(&weights[0]) * alignment(&weights[1]) < 0.0);
+}
+
+#[test]
+fn test_pca_stops_at_the_rank_of_the_samples() {
+    let device = Device::Cpu;
+    seed::set_global_seed(13, &device).unwrap();
+    let mut model = model(&device);
+    let random = input_weights(&model);
+
+    // Without jitter the samples vary along one direction only
+    let samples = Tensor::from_vec(
+        [[0.9f32, 0.9, 0.1, 0.1], [0.1, 0.1, 0.9, 0.9]]
+            .repeat(5)
+            .concat(),
+        (10, 4),
+        &device,
+    )
+    .unwrap()
+    .t()
+    .unwrap();
+    init_from_data(&mut model, &samples, DataInit::Pca).unwrap();
+    let weights = input_weights(&model);
+    assert!(alignment(&weights[0]).abs() > 0.99);
+    assert_eq!(weights[1], random[1]);
+}
+
+#[test]
+fn test_unusable_samples_are_rejected() {
+    let device = Device::Cpu;
+    let mut model = model(&device);
+    let wrong_size = Tensor::zeros((3, 10), candle_core::DType::F32, &device).unwrap();
+    assert!(init_from_data(&mut model, &wrong_size, DataInit::Pca).is_err());
+    // Fewer samples than neurons to place centroids on
+    let one = two_clusters(&device).narrow(1, 0, 1).unwrap();
+    assert!(init_from_data(&mut model, &one, DataInit::default()).is_err());
+}
@@ -0,0 +1,66 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::dataset::cache::DatasetCache;
+use custom_framework::dataset::xor::XorDataset;
+use custom_framework::seed;
+
+#[test]
+fn test_cache_gathers_batches() {
+    let device = Device::Cpu;
+    let xor = XorDataset::new(&device).unwrap();
+    let (inputs, labels): (Vec<Tensor>, Vec<Tensor>) =
+        xor.iter().map(|(x, y)| (x.clone(), y.clone())).unzip();
+    let cache = DatasetCache::from_samples(&inputs, &labels, &device).unwrap();
+    assert_eq!(cache.len(), 4);
+
+    let (x, y) = cache.batch(&[3, 1]).unwrap();
+    assert_eq!(
+        x.to_vec2::<f32>().unwrap(),
+        vec![vec![1.0, 0.0], vec![1.0, 1.0]]
+    );
+    assert_eq!(y.to_vec2::<f32>().unwrap(), vec![vec![0.0, 1.0]]);
+    assert!(cache.batch(&[4]).is_err());
+    assert!(cache.spike_train(&[0]).is_err());
+}
+
+#[test]
+fn test_cached_spike_trains() {
+    let device = Device::Cpu;
+    seed::set_global_seed(3, &device).unwrap();
+    let inputs = Tensor::from_vec(vec![0.0f32, 1.0, 0.5, 0.5], (2, 2), &device).unwrap();
+    let labels = Tensor::ones((1, 2), DType::F32, &device).unwrap();
+    let mut cache = DatasetCache::new(&inputs, &labels, &device)
+        .unwrap()
+        .with_spike_trains(200)
+        .unwrap();
+    assert_eq!(cache.spike_train_steps(), Some(200));
+    // u8 spikes: 2 x 2 x 200 bytes on top of the f32 inputs and labels
+    assert_eq!(cache.memory_bytes(), 4 * 4 + 2 * 4 + 800);
+
+    let train = cache.spike_train(&[0, 1]).unwrap();
+    assert_eq!(train.len(), 200);
+    assert_eq!(train[0].dims(), &[2, 2]);
+    let counts = Tensor::stack(&train, 0)
+        .unwrap()
+        .sum(0)
+        .unwrap()
+        .to_vec2::<f32>()
+        .unwrap();
+    assert_eq!(counts[0][0], 0.0);
+    assert_eq!(counts[0][1], 200.0);
+    assert!((60.0..140.0).contains(&counts[1][0]));
+
+    // The same trains until resampled
+    let again = cache.spike_train(&[0, 1]).unwrap();
+    assert_eq!(
+        train[5].to_vec2::<f32>().unwrap(),
+        again[5].to_vec2::<f32>().unwrap()
+    );
+    cache.resample_spike_trains().unwrap();
+    let fresh = cache.spike_train(&[0, 1]).unwrap();
+    assert_eq!(fresh.len(), 200);
+    let changed = train
+        .iter()
+        .zip(&fresh)
+        .any(|(a, b)| a.to_vec2::<f32>().unwrap() != b.to_vec2::<f32>().unwrap());
+    assert!(changed);
+}
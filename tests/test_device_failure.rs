@@ -0,0 +1,62 @@
+use candle_core::Device;
+use custom_framework::algorithms::Algorithm;
+use custom_framework::algorithms::algorithm_csdp1::Algorithm1;
+use custom_framework::experiment::is_device_failure;
+use std::error::Error;
+
+#[derive(Debug)]
+struct Wrapped(Box<dyn Error>);
+
+impl std::fmt::Display for Wrapped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "episode 12 failed")
+    }
+}
+
+impl Error for Wrapped {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.0.as_ref())
+    }
+}
+
+#[test]
+fn test_device_errors_are_recognized() {
+    for message in [
+        "DriverError(CUDA_ERROR_OUT_OF_MEMORY, \"out of memory\")",
+        "DriverError(CUDA_ERROR_LAUNCH_FAILED, \"unspecified launch failure\")",
+        "CublasError(CUBLAS_STATUS_EXECUTION_FAILED)",
+    ] {
+        let err: Box<dyn Error> = candle_core::Error::Msg(message.to_string()).into();
+        assert!(is_device_failure(err.as_ref()), "{}", message);
+    }
+    // Found through the errors wrapping it
+    let wrapped = Wrapped("CUDA_ERROR_ILLEGAL_ADDRESS".into());
+    assert!(is_device_failure(&wrapped));
+
+    let shape: Box<dyn Error> =
+        candle_core::Error::Msg("shape mismatch in matmul".to_string()).into();
+    assert!(!is_device_failure(shape.as_ref()));
+    assert!(!is_device_failure(&Wrapped("checkpoint not found".into())));
+}
+
+#[test]
+fn test_saved_state_restores_on_the_cpu() {
+    let new = || Algorithm1::new(4, 2, vec![8], 0.1, Device::Cpu, None).unwrap();
+    let dir = std::env::temp_dir().join(format!("csdp_device_failure_{}", std::process::id()));
+    let mut algo = new();
+    algo.save_to(&dir).unwrap();
+
+    let mut restored = new();
+    let weights = |algo: &Algorithm1| -> Vec<Vec<f32>> {
+        algo.named_tensors()
+            .unwrap()
+            .into_iter()
+            .map(|(_, t)| t.flatten_all().unwrap().to_vec1::<f32>().unwrap())
+            .collect()
+    };
+    assert_ne!(weights(&restored), weights(&algo));
+    restored.restore(&dir).unwrap();
+    assert_eq!(weights(&restored), weights(&algo));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
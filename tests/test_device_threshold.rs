@@ -0,0 +1,50 @@
+use candle_core::{Device, Tensor};
+use custom_framework::layer::Layer;
+use custom_framework::layer::lif::LIFLayer;
+use custom_framework::layer::mod_signal::standard::StandardModSignal;
+
+const SIZE: usize = 50;
+const BATCH: usize = 2;
+
+fn lif(device: &Device) -> LIFLayer {
+    let mod_signal = Box::new(StandardModSignal::new(SIZE, 5.0, 1.0, 25.0, device).unwrap());
+    LIFLayer::new(SIZE, 13.0, 0.5, 0.01, mod_signal, device).unwrap()
+}
+
+/// Spikes of every step and the final threshold under a varying input
+fn run(mut layer: LIFLayer, device: &Device) -> (Vec<Vec<Vec<f32>>>, f32) {
+    layer.reset(BATCH).unwrap();
+    let mut spikes = Vec::new();
+    for t in 0..100 {
+        let input: Vec<f32> = (0..SIZE * BATCH)
+            .map(|i| (((i * 7 + t * 3) % 11) as f32 / 5.0).sin().abs() * 2.0)
+            .collect();
+        let input = Tensor::from_vec(input, (SIZE, BATCH), device).unwrap();
+        layer.reset_input().unwrap();
+        layer.add_input(&input).unwrap();
+        layer.step(1.0).unwrap();
+        spikes.push(layer.output().unwrap().to_vec2::<f32>().unwrap());
+    }
+    let state = layer.get_state().unwrap();
+    let thresh = state["thresh"]
+        .flatten_all()
+        .unwrap()
+        .to_vec1::<f32>()
+        .unwrap()[0];
+    (spikes, thresh)
+}
+
+#[test]
+fn test_device_threshold_matches_host() {
+    let device = Device::Cpu;
+    let (host_spikes, host_thresh) = run(lif(&device), &device);
+    let mut on_device = lif(&device);
+    on_device.keep_threshold_on_device().unwrap();
+    let (device_spikes, device_thresh) = run(on_device, &device);
+
+    assert!(host_spikes.iter().flatten().flatten().any(|&s| s > 0.0));
+    assert_eq!(device_spikes, host_spikes);
+    assert!((device_thresh - host_thresh).abs() < 1e-5);
+    // The threshold adapted away from its initial value
+    assert!((host_thresh - 0.5).abs() > 1e-3);
+}
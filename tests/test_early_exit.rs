@@ -0,0 +1,52 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::{EarlyExit, Model, ModelConfig};
+use custom_framework::seed;
+
+fn new_model(device: &Device) -> Model {
+    seed::set_global_seed(11, device).unwrap();
+    let config = ModelConfig::standard(4, 3, vec![16], 0.1, None).unwrap();
+    let mut model = Model::from_config(config, device).unwrap();
+    model.disable_learning();
+    model
+}
+
+#[test]
+fn test_settled_rates_end_the_run() {
+    let device = Device::Cpu;
+    let input = Tensor::ones((4, 2), DType::F32, &device).unwrap();
+
+    let mut model = new_model(&device);
+    let full = model.process(&input, 200, true, &device).unwrap();
+    assert_eq!(model.step_count(), 200);
+    assert_eq!(full.output_activity.len(), 200);
+
+    // Any estimate moves by at most 1 per step, so a tolerance of 1 stops at `min_steps`
+    model.early_exit = Some(EarlyExit::new(1.0, 12));
+    let out = model.process(&input, 200, true, &device).unwrap();
+    assert_eq!(model.step_count(), 12);
+    assert_eq!(out.output_activity.len(), 12);
+
+    model.early_exit = Some(EarlyExit::new(0.05, 10));
+    model.process(&input, 200, false, &device).unwrap();
+    let steps = model.step_count();
+    assert!((10..200).contains(&steps), "{steps}");
+}
+
+#[test]
+fn test_no_exit_before_the_window_fills() {
+    let device = Device::Cpu;
+    let input = Tensor::ones((4, 1), DType::F32, &device).unwrap();
+    let mut model = new_model(&device);
+    model.early_exit = Some(EarlyExit {
+        tolerance: 1.0,
+        min_steps: 0,
+        window: 8,
+    });
+    model.process(&input, 40, false, &device).unwrap();
+    assert_eq!(model.step_count(), 9);
+
+    // `timesteps` stays the upper bound
+    model.early_exit = Some(EarlyExit::new(0.0, 0));
+    model.process(&input, 6, false, &device).unwrap();
+    assert_eq!(model.step_count(), 6);
+}
@@ -0,0 +1,104 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::layer::Layer;
+use custom_framework::layer::lif::LIFLayer;
+use custom_framework::layer::mod_signal::standard::StandardModSignal;
+use custom_framework::models::{Model, ModelConfig, SynapseType};
+use custom_framework::synapse::SynapseOps;
+use custom_framework::synapse::csdp::CSDP;
+use custom_framework::synapse::eligibility::{MultiTraceCSDP, TraceConfig};
+
+/// A LIF layer driven hard enough to spike, so its modulatory signal is nonzero
+fn spiking_layer(size: usize, device: &Device) -> LIFLayer {
+    let mod_signal = StandardModSignal::new(size, 5.0, 1.0, 4.0, device).unwrap();
+    let mut layer = LIFLayer::new(size, 1.0, 0.5, 0.0, Box::new(mod_signal), device).unwrap();
+    layer
+        .add_input(&Tensor::full(10.0f32, (size, 1), device).unwrap())
+        .unwrap();
+    layer.step(1.0).unwrap();
+    layer
+}
+
+fn abs_sum(tensor: &Tensor) -> f32 {
+    tensor
+        .abs()
+        .unwrap()
+        .sum_all()
+        .unwrap()
+        .to_scalar()
+        .unwrap()
+}
+
+#[test]
+fn test_fast_trace_alone_matches_csdp() {
+    let device = Device::Cpu;
+    let post = spiking_layer(3, &device);
+    assert!(abs_sum(post.get_mod_signal()) > 0.0);
+
+    let config = TraceConfig {
+        tau_fast: 1e-4,
+        slow_weight: 0.0,
+        ..Default::default()
+    };
+    let mut traced = MultiTraceCSDP::new(4, 3, config, &device).unwrap();
+    let mut plain = CSDP::new(4, 3, &device).unwrap();
+    plain.set_state(&traced.get_state().unwrap()).unwrap();
+
+    let pre = Tensor::new(&[[1.0f32], [0.0], [1.0], [1.0]], &device).unwrap();
+    traced.update_weights(&pre, &post, 1.0).unwrap();
+    plain.update_weights(&pre, &post, 1.0).unwrap();
+    let difference = traced.weights.sub(&plain.weights).unwrap();
+    assert!(abs_sum(&difference) < 1e-5);
+}
+
+#[test]
+fn test_slow_trace_outlasts_fast_trace() {
+    let device = Device::Cpu;
+    let post = spiking_layer(3, &device);
+    let config = TraceConfig {
+        tau_fast: 1.0,
+        ..Default::default()
+    };
+    let mut synapse = MultiTraceCSDP::new(4, 3, config, &device).unwrap();
+
+    let pre = Tensor::ones((4, 1), DType::F32, &device).unwrap();
+    synapse.update_weights(&pre, &post, 1.0).unwrap();
+    assert!(abs_sum(synapse.fast_trace()) > abs_sum(synapse.slow_trace()));
+
+    // Without pre-synaptic activity there is nothing new to tag, and both traces decay
+    let silent = pre.zeros_like().unwrap();
+    for _ in 0..10 {
+        synapse.update_weights(&silent, &post, 1.0).unwrap();
+    }
+    assert!(abs_sum(synapse.slow_trace()) > abs_sum(synapse.fast_trace()));
+
+    // The slow tag keeps being written into the weights
+    let before = synapse.weights.clone();
+    synapse.update_weights(&silent, &post, 1.0).unwrap();
+    assert!(abs_sum(&synapse.weights.sub(&before).unwrap()) > 0.0);
+
+    synapse.reset();
+    assert_eq!(abs_sum(synapse.fast_trace()), 0.0);
+    assert_eq!(abs_sum(synapse.slow_trace()), 0.0);
+}
+
+#[test]
+fn test_model_with_multi_trace_synapses() {
+    let device = Device::Cpu;
+    let mut config = ModelConfig::standard(4, 2, vec![8], 0.1, None).unwrap();
+    for synapse in config.synapse_configs.iter_mut() {
+        synapse.synapse_type = SynapseType::MultiTrace(TraceConfig::default());
+    }
+    let mut model = Model::from_config(config, &device).unwrap();
+    model.reset(1).unwrap();
+    let input = Tensor::ones((4, 1), DType::F32, &device).unwrap();
+    let label = Tensor::ones((2, 1), DType::F32, &device).unwrap();
+    for _ in 0..5 {
+        model.step(&input, Some(&label)).unwrap();
+    }
+
+    let bad = TraceConfig {
+        tau_slow: 0.0,
+        ..Default::default()
+    };
+    assert!(MultiTraceCSDP::new(4, 3, bad, &device).is_err());
+}
@@ -0,0 +1,50 @@
+use candle_core::{Device, Tensor};
+use custom_framework::layer::lif::LIFLayer;
+use custom_framework::layer::mod_signal::standard::StandardModSignal;
+use custom_framework::layer::{Integrator, Layer};
+use custom_framework::models::Model;
+use custom_framework::models::engine::{EventDriven, ExponentialEuler};
+
+fn potential_change(integrator: Integrator) -> f32 {
+    let device = Device::Cpu;
+    let mod_signal = Box::new(StandardModSignal::new(3, 5.0, 1.0, 1.5, &device).unwrap());
+    let mut layer = LIFLayer::new(3, 13.0, 0.5, 0.01, mod_signal, &device).unwrap();
+    layer.set_integrator(integrator);
+    layer.reset(1).unwrap();
+    layer
+        .add_input(&Tensor::ones((3, 1), candle_core::DType::F32, &device).unwrap())
+        .unwrap();
+    // One step as long as the membrane time constant
+    layer.step(13.0).unwrap();
+    let change = layer.potential_change().unwrap().unwrap();
+    change.to_scalar::<f32>().unwrap()
+}
+
+#[test]
+fn test_exponential_euler_integration() {
+    assert!((potential_change(Integrator::Euler) - 1.0).abs() < 1e-6);
+    let exact = 1.0 - (-1.0f32).exp();
+    assert!((potential_change(Integrator::ExponentialEuler) - exact).abs() < 1e-6);
+}
+
+#[test]
+fn test_model_engines() {
+    let device = Device::Cpu;
+    let mut model = Model::new(4, 2, vec![8], &device, 0.1, None).unwrap();
+    assert_eq!(model.engine_name(), "clock-driven");
+    let input = Tensor::ones((4, 2), candle_core::DType::F32, &device).unwrap();
+
+    for engine in [
+        Box::new(EventDriven) as Box<dyn custom_framework::models::engine::SimulationEngine>,
+        Box::new(ExponentialEuler),
+    ] {
+        let name = engine.name();
+        model.set_engine(engine).unwrap();
+        assert_eq!(model.engine_name(), name);
+        let out = model.process(&input, 10, true, &device).unwrap();
+        assert_eq!(out.output_activity.len(), 10);
+        assert_eq!(out.final_output.dims(), &[2, 2]);
+        assert_eq!(model.step_count(), 10);
+    }
+    assert_eq!(model.fork().engine_name(), "exponential-euler");
+}
@@ -0,0 +1,122 @@
+#![cfg(feature = "robot")]
+
+use custom_framework::dataset::trajectory::Trajectory;
+use custom_framework::robot::Arm;
+use custom_framework::robot::envelope::{EnvelopeMode, EnvelopedArm, SafetyEnvelope, Violation};
+use custom_framework::robot::sim_lerobot::SimLeRobot;
+
+fn demonstrations() -> Vec<Trajectory> {
+    let channels = vec!["j1".to_string(), "j2".to_string()];
+    vec![
+        Trajectory {
+            channels: channels.clone(),
+            timestamps_ms: Some(vec![0, 100, 100]),
+            frames: vec![vec![0.0, 0.0], vec![0.1, -0.2], vec![0.1, -0.1]],
+        },
+        Trajectory {
+            channels,
+            timestamps_ms: Some(vec![0]),
+            frames: vec![vec![0.5, 0.0]],
+        },
+    ]
+}
+
+#[test]
+fn test_envelope_from_demonstrations() {
+    let envelope = SafetyEnvelope::from_trajectories(&demonstrations()).unwrap();
+    assert_eq!(envelope.min_positions, [0.0, -0.2]);
+    assert_eq!(envelope.max_positions, [0.5, 0.0]);
+    // The repeated timestamp is skipped rather than read as an infinite speed
+    assert_eq!(envelope.max_velocities, Some(vec![1.0, 2.0]));
+
+    let previous = [0.0, 0.0];
+    let goal = [0.4, -0.5];
+    assert_eq!(
+        envelope.check(Some(&previous[..]), &goal, 0.1),
+        [
+            Violation::Position {
+                joint: 1,
+                value: -0.5
+            },
+            Violation::Velocity {
+                joint: 0,
+                velocity: 4.0
+            },
+            Violation::Velocity {
+                joint: 1,
+                velocity: -5.0
+            },
+        ]
+    );
+    let clamped = envelope.clamp(Some(&previous[..]), &goal, 0.1);
+    assert!((clamped[0] - 0.1).abs() < 1e-12);
+    assert!((clamped[1] + 0.2).abs() < 1e-12);
+    assert!(envelope.check(None, &[0.25, -0.1], 0.1).is_empty());
+
+    let widened = envelope.with_margin(0.1).with_velocity_scale(2.0);
+    assert!((widened.min_positions[0] + 0.1).abs() < 1e-12);
+    assert_eq!(widened.max_velocities, Some(vec![2.0, 4.0]));
+}
+
+#[test]
+fn test_envelope_rejects_mismatched_demonstrations() {
+    let mut demos = demonstrations();
+    demos[1].channels.push("j3".to_string());
+    assert!(SafetyEnvelope::from_trajectories(&demos).is_err());
+}
+
+fn envelope(mode: EnvelopeMode) -> SafetyEnvelope {
+    SafetyEnvelope {
+        min_positions: vec![-0.1; 6],
+        max_positions: vec![0.1; 6],
+        max_velocities: Some(vec![1.0; 6]),
+        mode,
+    }
+}
+
+#[test]
+fn test_enveloped_arm_clamps_goals() {
+    let sim = SimLeRobot::new([0.0; 6], [-1.0; 6], [1.0; 6]);
+    let mut arm =
+        EnvelopedArm::new(Box::new(sim), envelope(EnvelopeMode::Clamp)).with_control_period(0.05);
+    arm.enable().unwrap();
+
+    // 1 rad/s for 0.05 s limits the first command to 0.05 rad, and the range to 0.1 rad
+    arm.set_goal_positions(&[0.3, 0.0, 0.0, 0.0, 0.0, -0.02])
+        .unwrap();
+    let goal = arm.get_goal_positions().unwrap();
+    assert!((goal[0] - 0.05).abs() < 1e-12);
+    assert!((goal[5] + 0.02).abs() < 1e-12);
+    arm.set_goal_positions(&[0.3, 0.0, 0.0, 0.0, 0.0, 0.0])
+        .unwrap();
+    assert!((arm.get_goal_positions().unwrap()[0] - 0.1).abs() < 1e-12);
+    assert_eq!(arm.clamped(), 2);
+    assert_eq!(arm.rejected(), 0);
+}
+
+#[test]
+fn test_enveloped_arm_rejects_goals() {
+    let sim = SimLeRobot::new([0.0; 6], [-1.0; 6], [1.0; 6]);
+    let mut arm = EnvelopedArm::new(Box::new(sim), envelope(EnvelopeMode::Reject));
+    arm.enable().unwrap();
+
+    arm.set_goal_positions(&[0.3, 0.0, 0.0, 0.0, 0.0, 0.0])
+        .unwrap();
+    assert_eq!(arm.get_goal_positions().unwrap(), vec![0.0; 6]);
+    assert_eq!(arm.rejected(), 1);
+
+    arm.set_goal_positions(&[0.02, 0.0, 0.0, 0.0, 0.0, 0.0])
+        .unwrap();
+    assert_eq!(arm.get_goal_positions().unwrap()[0], 0.02);
+    assert_eq!(arm.rejected(), 1);
+}
+
+#[test]
+fn test_envelope_json_round_trip() {
+    let path = std::env::temp_dir().join(format!("envelope_{}.json", std::process::id()));
+    let envelope = envelope(EnvelopeMode::Reject);
+    envelope.save(&path).unwrap();
+    let loaded = SafetyEnvelope::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(loaded, envelope);
+}
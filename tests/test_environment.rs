@@ -0,0 +1,96 @@
+#![cfg(feature = "robot")]
+
+use custom_framework::environment::Environment;
+use custom_framework::environment::grid::GridEnvironment;
+use custom_framework::environment::robot::RobotEnvironment;
+use custom_framework::models::curriculum::{ActionCurriculum, CurriculumConfig};
+use custom_framework::robot::Arm;
+use custom_framework::robot::profile::RobotProfile;
+use custom_framework::robot::sim_lerobot::SimLeRobot;
+
+#[test]
+fn test_sim_arm_moves_toward_clamped_goal() {
+    let mut arm = SimLeRobot::new([0.0; 6], [-1.0; 6], [0.25; 6]);
+    arm.enable().unwrap();
+    arm.set_goal_positions(&[0.2, -0.05, 1.0, 0.0, 0.0, 0.0])
+        .unwrap();
+    assert_eq!(arm.get_goal_positions().unwrap()[2], 0.25);
+
+    let first = arm.get_motor_positions().unwrap();
+    assert_eq!(first[..3], [0.1, -0.05, 0.1]);
+    for _ in 0..5 {
+        arm.get_motor_positions().unwrap();
+    }
+    assert!((arm.get_motor_positions().unwrap()[2] - 0.25).abs() < 1e-12);
+}
+
+#[test]
+fn test_gym_style_loop_on_simulated_robot() {
+    let mut env = RobotEnvironment::simulated(&RobotProfile::follower());
+    let start = env.start_episode().unwrap();
+    assert_eq!(start, vec![0.0; 6]);
+
+    // Action 5 nudges the gripper towards the target; the next observation shows the move
+    let step = env.step(5).unwrap();
+    assert!((step.observation[5] - 0.05).abs() < 1e-12);
+    assert!(step.reward < 0.0);
+    assert!(!step.done);
+
+    // The simulated arm can be duplicated for vectorized algorithms
+    let mut copy = env.clone_box();
+    assert_eq!(copy.get_state().unwrap(), env.get_state().unwrap());
+
+    // The status panel shows the positions of the last state without reading them again
+    let state = env.get_state().unwrap();
+    let status = env.robot_status();
+    assert_eq!(status[0].positions, state);
+    assert_eq!(status[0].joints(), 6);
+}
+
+#[test]
+fn test_curriculum_refines_the_action_delta() {
+    let curriculum = ActionCurriculum::new(CurriculumConfig {
+        patience: 1,
+        advance_ratio: 4.0,
+        ..CurriculumConfig::default()
+    })
+    .unwrap();
+    // Limits wide enough to reach the target
+    let profile = RobotProfile {
+        home_positions: [0.0; 6],
+        min_positions: [-3.0; 6],
+        max_positions: [3.0; 6],
+        ..RobotProfile::follower()
+    };
+    let mut env = RobotEnvironment::simulated(&profile).with_curriculum(curriculum);
+    assert_eq!(env.action_delta(), 0.2);
+    // Action 5 moves the gripper 0.2 rad toward its target of 0.5, action 11 away from it
+    let gain = env.evaluate_action(&[0.0; 6], 5) - env.evaluate_action(&[0.0; 6], 11);
+    assert!((gain - (0.7f64.powi(2) - 0.3f64.powi(2))).abs() < 1e-12);
+
+    // The first reset ends no episode, so the distance from home doesn't count
+    env.start_episode().unwrap();
+    assert_eq!(env.action_delta(), 0.2);
+    for _ in 0..40 {
+        let state = env.get_state().unwrap();
+        let best = (0..env.action_size())
+            .max_by(|&a, &b| {
+                env.evaluate_action(&state, a)
+                    .total_cmp(&env.evaluate_action(&state, b))
+            })
+            .unwrap();
+        env.apply_action(best).unwrap();
+    }
+    let state = env.get_state().unwrap();
+    assert!(env.tracking_error(&state) < 0.2);
+    env.reset().unwrap();
+    assert_eq!(env.action_delta(), 0.1);
+    assert_eq!(env.curriculum().unwrap().stage(), 1);
+}
+
+#[test]
+fn test_grid_episode_ends_on_goal() {
+    let env = GridEnvironment::new();
+    assert!(env.is_done(&[0.0, 0.0, 10.0, 10.0]));
+    assert!(!env.is_done(&[1.0, 0.0, 10.0, 10.0]));
+}
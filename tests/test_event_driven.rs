@@ -0,0 +1,33 @@
+use candle_core::{Device, Tensor};
+use custom_framework::seed;
+use custom_framework::synapse::SynapseOps;
+use custom_framework::synapse::csdp::CSDP;
+
+#[test]
+fn test_forward_active_matches_dense() {
+    let device = Device::Cpu;
+    seed::set_global_seed(11, &device).unwrap();
+    let synapse = CSDP::new(8, 5, &device).unwrap();
+
+    // Neurons 1 and 6 spike, in different batch columns
+    let mut spikes = vec![0.0f32; 8 * 2];
+    spikes[2] = 1.0;
+    spikes[6 * 2 + 1] = 1.0;
+    let pre = Tensor::from_vec(spikes, (8, 2), &device).unwrap();
+    let active = Tensor::new(&[1u32, 6], &device).unwrap();
+
+    let dense = synapse.forward(&pre).unwrap().to_vec2::<f32>().unwrap();
+    let events = synapse
+        .forward_active(&pre, &active)
+        .unwrap()
+        .to_vec2::<f32>()
+        .unwrap();
+    for (d, e) in dense.iter().flatten().zip(events.iter().flatten()) {
+        assert!((d - e).abs() < 1e-6);
+    }
+
+    let silent = Tensor::zeros((8, 2), candle_core::DType::F32, &device).unwrap();
+    let none = Tensor::from_vec(Vec::<u32>::new(), 0, &device).unwrap();
+    let biases_only = synapse.forward_active(&silent, &none).unwrap();
+    assert_eq!(biases_only.dims(), [5, 2]);
+}
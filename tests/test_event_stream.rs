@@ -0,0 +1,139 @@
+use candle_core::Device;
+use custom_framework::dataset::event_stream::{
+    DvsEvent, EventInput, EventInputConfig, EventSource, Sensor, StreamEventSource, UdpEventSource,
+};
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+/// AEDAT 2.0 bytes of a DAVIS pixel event
+fn davis_event(x: u32, y: u32, on: bool, timestamp: u32) -> Vec<u8> {
+    let address = (y << 22) | (x << 12) | ((on as u32) << 11);
+    let mut bytes = address.to_be_bytes().to_vec();
+    bytes.extend(timestamp.to_be_bytes());
+    bytes
+}
+
+#[test]
+fn test_address_decoding() {
+    let sensor = Sensor::davis240();
+    let mut events = Vec::new();
+    let mut bytes = davis_event(10, 20, true, 5);
+    // Frame/IMU samples and pixels off the sensor are skipped, as is a partial event
+    let mut imu = davis_event(10, 20, true, 6);
+    imu[0] |= 0x80;
+    bytes.extend(imu);
+    bytes.extend(davis_event(300, 20, false, 7));
+    bytes.extend(davis_event(239, 179, false, 8));
+    bytes.extend([0, 1, 2]);
+    sensor.parse(&bytes, &mut events);
+    assert_eq!(
+        events,
+        vec![
+            DvsEvent {
+                x: 10,
+                y: 20,
+                on: true,
+                timestamp_us: 5
+            },
+            DvsEvent {
+                x: 239,
+                y: 179,
+                on: false,
+                timestamp_us: 8
+            },
+        ]
+    );
+
+    let event = Sensor::Dvs128.decode((7 << 8) | (3 << 1) | 1, 0).unwrap();
+    assert_eq!((event.x, event.y, event.on), (3, 7, true));
+    assert!(Sensor::Dvs128.decode(0x8000, 0).is_none());
+}
+
+#[test]
+fn test_events_become_input_spikes() {
+    let config = EventInputConfig {
+        pool: 4,
+        threshold: 2,
+        ..EventInputConfig::new(Sensor::Dvs128)
+    };
+    assert_eq!(config.grid(), (32, 32));
+    assert_eq!(config.input_size(), 2 * 32 * 32);
+
+    let event = |x, y, on| DvsEvent {
+        x,
+        y,
+        on,
+        timestamp_us: 0,
+    };
+    // Two ON events in one pooled pixel reach the threshold, a single OFF event does not
+    let spikes = config.spikes(&[event(4, 0, true), event(7, 3, true), event(0, 0, false)]);
+    let on_neuron = config.neuron(&event(4, 0, true));
+    assert_eq!(on_neuron, 32 * 32 + 1);
+    assert_eq!(spikes[on_neuron], 1.0);
+    assert_eq!(spikes.iter().sum::<f32>(), 1.0);
+
+    let merged = EventInputConfig {
+        split_polarity: false,
+        threshold: 1,
+        ..config
+    };
+    assert_eq!(merged.input_size(), 32 * 32);
+    assert_eq!(merged.spikes(&[event(0, 0, false)])[0], 1.0);
+}
+
+#[test]
+fn test_udp_source() {
+    let mut source = UdpEventSource::bind("127.0.0.1:0", Sensor::davis240()).unwrap();
+    let address = source.local_addr().unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    for sequence in [0u32, 2] {
+        let mut datagram = sequence.to_be_bytes().to_vec();
+        datagram.extend(davis_event(1, 2, true, sequence));
+        sender.send_to(&datagram, address).unwrap();
+    }
+
+    let mut events = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while events.len() < 2 && Instant::now() < deadline {
+        source.poll(&mut events).unwrap();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(events.len(), 2);
+    assert_eq!(source.dropped(), 1);
+}
+
+#[test]
+fn test_stream_source_feeds_model_input() {
+    let device = Device::Cpu;
+    let mut bytes = Vec::new();
+    for x in 0..3 {
+        bytes.extend(davis_event(x, 0, false, x));
+    }
+    let source = StreamEventSource::spawn(std::io::Cursor::new(bytes), Sensor::davis240());
+    let config = EventInputConfig {
+        split_polarity: false,
+        ..EventInputConfig::new(Sensor::davis240())
+    };
+    let mut input = EventInput::new(Box::new(source), config, &device).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut seen = vec![0.0f32; input.input_size()];
+    while input.total_events() < 3 && Instant::now() < deadline {
+        let spikes = input.next_input().unwrap();
+        assert_eq!(spikes.dims(), &[240 * 180, 1]);
+        for (total, s) in seen
+            .iter_mut()
+            .zip(spikes.flatten_all().unwrap().to_vec1::<f32>().unwrap())
+        {
+            *total += s;
+        }
+    }
+    assert_eq!(&seen[..4], &[1.0, 1.0, 1.0, 0.0]);
+
+    let invalid = EventInputConfig {
+        pool: 0,
+        ..EventInputConfig::new(Sensor::Dvs128)
+    };
+    let source = StreamEventSource::spawn(std::io::empty(), Sensor::Dvs128);
+    assert!(EventInput::new(Box::new(source), invalid, &device).is_err());
+}
@@ -0,0 +1,163 @@
+#![cfg(feature = "robot")]
+
+use custom_framework::error::CsdpError;
+use custom_framework::robot::Arm;
+use custom_framework::robot::faults::{FaultConfig, FaultStats, FaultyArm};
+use custom_framework::robot::pipeline::{Pipeline, PipelineConfig, Stamped};
+use custom_framework::robot::sim_lerobot::SimLeRobot;
+use std::time::Duration;
+
+fn sim() -> SimLeRobot {
+    let mut arm = SimLeRobot::new([0.0; 6], [-1.0; 6], [1.0; 6]);
+    arm.enable().unwrap();
+    arm
+}
+
+fn faults(config: FaultConfig) -> FaultConfig {
+    FaultConfig {
+        timeout_ms: 0,
+        seed: Some(7),
+        ..config
+    }
+}
+
+#[test]
+fn test_no_faults_pass_through() {
+    let mut arm = FaultyArm::new(sim(), faults(FaultConfig::default())).unwrap();
+    arm.set_goal_positions(&[0.5; 6]).unwrap();
+    assert!((arm.get_motor_positions().unwrap()[0] - 0.1).abs() < 1e-9);
+    assert_eq!(arm.get_goal_positions().unwrap(), vec![0.5; 6]);
+    assert_eq!(
+        arm.stats(),
+        FaultStats {
+            reads: 2,
+            writes: 1,
+            ..FaultStats::default()
+        }
+    );
+}
+
+#[test]
+fn test_injected_faults() {
+    let config = faults(FaultConfig {
+        read_timeout: 1.0,
+        ..FaultConfig::default()
+    });
+    let mut arm = FaultyArm::new(sim(), config).unwrap();
+    match arm.get_motor_positions() {
+        Err(CsdpError::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::TimedOut),
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+    // Torque commands are never faulted
+    arm.disable().unwrap();
+
+    let config = faults(FaultConfig {
+        dropped_write: 1.0,
+        ..FaultConfig::default()
+    });
+    let mut arm = FaultyArm::new(sim(), config).unwrap();
+    arm.set_goal_positions(&[0.5; 6]).unwrap();
+    assert_eq!(arm.get_goal_positions().unwrap(), vec![0.0; 6]);
+    assert_eq!(arm.stats().dropped_writes, 1);
+
+    let config = faults(FaultConfig {
+        stale_position: 1.0,
+        ..FaultConfig::default()
+    });
+    let mut arm = FaultyArm::new(sim(), config).unwrap();
+    arm.set_goal_positions(&[0.5; 6]).unwrap();
+    let first = arm.get_motor_positions().unwrap();
+    // The arm keeps moving, but every later reading repeats the first
+    assert_eq!(arm.get_motor_positions().unwrap(), first);
+    assert_eq!(arm.get_motor_positions().unwrap(), first);
+    assert!(arm.inner_mut().get_motor_positions().unwrap()[0] > first[0]);
+    assert_eq!(arm.stats().stale_positions, 2);
+
+    // Paused, the same arm reads fresh positions again
+    arm.set_active(false);
+    assert!(arm.get_motor_positions().unwrap()[0] > first[0]);
+}
+
+#[test]
+fn test_faults_are_reproducible() {
+    let config = faults(FaultConfig {
+        read_timeout: 0.5,
+        write_error: 0.5,
+        ..FaultConfig::default()
+    });
+    let pattern = |config: FaultConfig| {
+        let mut arm = FaultyArm::new(sim(), config).unwrap();
+        (0..50)
+            .map(|_| {
+                (
+                    arm.get_motor_positions().is_ok(),
+                    arm.set_goal_positions(&[0.1; 6]).is_ok(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let first = pattern(config);
+    assert_eq!(pattern(config), first);
+    assert!(first.iter().any(|&(read, _)| read) && first.iter().any(|&(read, _)| !read));
+
+    // Clones carry the faults along, each with its own draws
+    let arm = FaultyArm::new(sim(), config).unwrap();
+    let mut clone = arm.try_clone().unwrap();
+    let mut other = arm.try_clone().unwrap();
+    let reads = |arm: &mut dyn Arm| {
+        (0..50)
+            .map(|_| arm.get_motor_positions().is_ok())
+            .collect::<Vec<_>>()
+    };
+    let first = reads(clone.as_mut());
+    assert!(first.iter().any(|&read| !read));
+    assert_ne!(reads(other.as_mut()), first);
+}
+
+#[test]
+fn test_config_round_trips_and_is_validated() {
+    let config = FaultConfig {
+        read_timeout: 0.01,
+        stale_position: 0.05,
+        dropped_write: 0.02,
+        ..FaultConfig::default()
+    };
+    let path = std::env::temp_dir().join(format!("faults_{}.json", std::process::id()));
+    config.save(&path).unwrap();
+    assert_eq!(FaultConfig::load(&path).unwrap(), config);
+
+    std::fs::write(&path, r#"{ "dropped_write": 1.5 }"#).unwrap();
+    assert!(FaultConfig::load(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+    let invalid = FaultConfig {
+        read_timeout: -0.1,
+        ..FaultConfig::default()
+    };
+    assert!(FaultyArm::new(sim(), invalid).is_err());
+}
+
+#[test]
+fn test_pipeline_survives_a_flaky_bus() {
+    let config = faults(FaultConfig {
+        read_timeout: 0.2,
+        write_error: 0.2,
+        dropped_write: 0.2,
+        stale_position: 0.2,
+        ..FaultConfig::default()
+    });
+    let arm = FaultyArm::new(sim(), config).unwrap();
+    let policy = |reading: &Stamped<Vec<f64>>| Ok(reading.value.iter().map(|p| p + 0.05).collect());
+    let pipeline = Pipeline::spawn(arm, PipelineConfig::new(200.0), policy, |_| Ok(())).unwrap();
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(pipeline.is_running());
+    let (arm, report) = pipeline.stop().unwrap();
+
+    let stats = arm.stats();
+    assert_eq!(report.read_errors as usize, stats.read_timeouts);
+    assert_eq!(report.write_errors as usize, stats.write_errors);
+    assert!(stats.faults() > 0);
+    // The loop kept running and the arm kept moving between the faults
+    assert!(report.reads > report.read_errors && report.writes > 0);
+    let mut arm = arm.into_inner();
+    assert!(arm.get_motor_positions().unwrap()[0] > 0.0);
+}
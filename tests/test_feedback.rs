@@ -0,0 +1,81 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::{FeedbackTopology, Model, ModelConfig, SynapseType};
+use custom_framework::seed;
+
+fn feedback(model: &Model) -> Vec<(usize, usize, f32)> {
+    model
+        .synapses
+        .iter()
+        .map(|s| &s.metadata)
+        .filter(|m| m.post_layer >= 2 && m.pre_layer > m.post_layer)
+        .map(|m| (m.pre_layer, m.post_layer, m.gain))
+        .collect()
+}
+
+fn config(topology: FeedbackTopology, gain: f32) -> ModelConfig {
+    ModelConfig::standard(4, 2, vec![8, 8, 8], 0.1, None)
+        .unwrap()
+        .with_feedback(topology, SynapseType::CSDP, gain)
+        .unwrap()
+}
+
+#[test]
+fn test_feedback_topologies() {
+    let device = Device::Cpu;
+    let standard = Model::new(4, 2, vec![8, 8, 8], &device, 0.1, None).unwrap();
+    let mut expected = feedback(&standard);
+    expected.sort_by_key(|&(pre, post, _)| (pre, post));
+
+    let full = Model::from_config(config(FeedbackTopology::Full, 1.0), &device).unwrap();
+    let mut connections = feedback(&full);
+    connections.sort_by_key(|&(pre, post, _)| (pre, post));
+    assert_eq!(connections, expected);
+
+    let adjacent = Model::from_config(config(FeedbackTopology::Adjacent, 0.5), &device).unwrap();
+    assert_eq!(
+        feedback(&adjacent),
+        vec![(3, 2, 0.5), (4, 3, 0.5), (5, 4, 0.5)]
+    );
+    let from_output =
+        Model::from_config(config(FeedbackTopology::FromOutput, 0.5), &device).unwrap();
+    assert_eq!(
+        feedback(&from_output),
+        vec![(5, 2, 0.5), (5, 3, 0.5), (5, 4, 0.5)]
+    );
+    let none = Model::from_config(config(FeedbackTopology::None, 1.0), &device).unwrap();
+    assert!(feedback(&none).is_empty());
+
+    let invalid = ModelConfig::standard(4, 2, vec![8], 0.1, None)
+        .unwrap()
+        .with_feedback_connection(2, 3, SynapseType::CSDP, 1.0);
+    assert!(invalid.is_err());
+}
+
+fn run(ablate_feedback: bool) -> Vec<Vec<f32>> {
+    let device = Device::Cpu;
+    seed::set_global_seed(11, &device).unwrap();
+    let mut model = Model::from_config(config(FeedbackTopology::FromOutput, 0.0), &device).unwrap();
+    if ablate_feedback {
+        for id in 0..model.synapses.len() {
+            let meta = &model.synapses[id].metadata;
+            if meta.post_layer >= 2 && meta.pre_layer > meta.post_layer {
+                model.disable_connection(id).unwrap();
+            }
+        }
+    }
+    let input = Tensor::ones((4, 1), DType::F32, &device).unwrap();
+    let out = model.process(&input, 20, false, &device).unwrap();
+    out.final_output.to_vec2::<f32>().unwrap()
+}
+
+#[test]
+fn test_zero_gain_silences_feedback() {
+    assert_eq!(run(false), run(true));
+
+    let device = Device::Cpu;
+    let mut model = Model::from_config(config(FeedbackTopology::FromOutput, 0.0), &device).unwrap();
+    let last = model.synapses.len() - 1;
+    model.set_synapse_gain(last, 2.0).unwrap();
+    assert_eq!(model.synapses[last].metadata.gain, 2.0);
+    assert!(model.set_synapse_gain(last + 1, 1.0).is_err());
+}
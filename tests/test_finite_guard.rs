@@ -0,0 +1,29 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::Model;
+
+#[test]
+fn test_non_finite_weights_are_reported() {
+    let device = Device::Cpu;
+    let mut model = Model::new(4, 2, vec![8], &device, 0.1, None).unwrap();
+    let input = Tensor::ones((4, 1), DType::F32, &device).unwrap();
+    model.check_finite = true;
+    model.reset(1).unwrap();
+    for _ in 0..3 {
+        model.step(&input, None).unwrap();
+    }
+    assert_eq!(model.step_count(), 3);
+
+    // Poison the input -> hidden weights
+    let mut state = model.synapses[0].synapse.get_state().unwrap();
+    let weights = Tensor::full(f32::NAN, state["weights"].shape().clone(), &device).unwrap();
+    state.insert("weights".to_string(), weights);
+    model.synapses[0].synapse.set_state(&state).unwrap();
+
+    let mut unchecked = model.fork();
+    unchecked.check_finite = false;
+    unchecked.step(&input, None).unwrap();
+
+    let message = model.step(&input, None).unwrap_err().to_string();
+    assert!(message.contains("layer 2 (Hidden_0)"), "{}", message);
+    assert!(message.contains("timestep 3"), "{}", message);
+}
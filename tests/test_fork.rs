@@ -0,0 +1,73 @@
+use candle_core::{Device, Tensor};
+use custom_framework::models::Model;
+use custom_framework::seed;
+
+fn weights(model: &Model) -> Vec<Vec<f32>> {
+    model
+        .synapses
+        .iter()
+        .map(|s| {
+            s.synapse.get_state().unwrap()["weights"]
+                .flatten_all()
+                .unwrap()
+        })
+        .map(|w| w.to_vec1::<f32>().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_fork_learns_independently() {
+    let device = Device::Cpu;
+    seed::set_global_seed(7, &device).unwrap();
+    let model = Model::new(4, 2, vec![16], &device, 0.1, None).unwrap();
+    let before = weights(&model);
+
+    let mut fork = model.fork();
+    let input = Tensor::ones((4, 1), candle_core::DType::F32, &device).unwrap();
+    let label = Tensor::ones((2, 1), candle_core::DType::F32, &device).unwrap();
+    fork.reset(1).unwrap();
+    for _ in 0..10 {
+        fork.step(&input, Some(&label)).unwrap();
+    }
+
+    assert_eq!(weights(&model), before);
+    assert_ne!(weights(&fork), before);
+}
+
+#[test]
+fn test_process_many_leaves_model_untouched() {
+    let device = Device::Cpu;
+    seed::set_global_seed(7, &device).unwrap();
+    let model = Model::new(4, 2, vec![16], &device, 0.1, None).unwrap();
+    let before = weights(&model);
+
+    let inputs: Vec<Tensor> = (0..5)
+        .map(|i| Tensor::full(i as f32 / 4.0, (4, 3), &device).unwrap())
+        .collect();
+    let outputs = model.process_many(&inputs, 10).unwrap();
+
+    assert_eq!(outputs.len(), inputs.len());
+    assert!(outputs.iter().all(|o| o.dims() == [2, 3]));
+    assert_eq!(weights(&model), before);
+}
+
+#[test]
+fn test_seeded_process_many_is_reproducible() {
+    let device = Device::Cpu;
+    seed::set_global_seed(7, &device).unwrap();
+    let model = Model::new(4, 2, vec![16], &device, 0.1, None).unwrap();
+    let inputs: Vec<Tensor> = (0..4)
+        .map(|i| Tensor::full(0.2 + i as f32 / 8.0, (4, 3), &device).unwrap())
+        .collect();
+
+    let run = || {
+        seed::set_global_seed(11, &device).unwrap();
+        model
+            .process_many(&inputs, 20)
+            .unwrap()
+            .iter()
+            .map(|o| o.flatten_all().unwrap().to_vec1::<f32>().unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(run(), run());
+}
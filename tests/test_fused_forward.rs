@@ -0,0 +1,63 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::layer::Layer;
+use custom_framework::models::Model;
+use custom_framework::models::parallel::{self, SynapseGroups};
+use custom_framework::seed;
+
+/// Membrane potentials of the layers driven by the synapses, after one forward pass and step
+/// from the current state of `model`
+fn driven_activity(model: &Model, groups: &mut SynapseGroups) -> Vec<Vec<f32>> {
+    let mut layers: Vec<Box<dyn Layer>> = model.layers.iter().map(|l| l.box_clone()).collect();
+    for layer in layers.iter_mut() {
+        layer.reset_input().unwrap();
+    }
+    parallel::forward_synapses(&mut layers, &model.synapses, groups, false, false).unwrap();
+    layers[2..]
+        .iter_mut()
+        .map(|layer| {
+            layer.step(0.1).unwrap();
+            layer
+                .activity()
+                .unwrap()
+                .flatten_all()
+                .unwrap()
+                .to_vec1::<f32>()
+                .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_fused_forward_matches_separate_passes() {
+    let device = Device::Cpu;
+    seed::set_global_seed(5, &device).unwrap();
+    let mut model = Model::new(8, 3, vec![16, 16], &device, 0.1, None).unwrap();
+    let input = Tensor::rand(0.0f32, 1.0, (8, 2), &device).unwrap();
+    let label = Tensor::ones((3, 2), DType::F32, &device).unwrap();
+    model.enable_learning();
+    model.reset(2).unwrap();
+
+    // Both hidden layers and the output feed two layers each
+    let mut fused = SynapseGroups::new(&model.synapses);
+    let mut separate = SynapseGroups::unfused(&model.synapses);
+    assert_eq!(fused.num_fused(), 3);
+    assert_eq!(separate.num_fused(), 0);
+
+    // Learning replaces the weights every step, so the stacked weights must follow
+    for _ in 0..5 {
+        model.step(&input, Some(&label)).unwrap();
+        let expected = driven_activity(&model, &mut separate);
+        let actual = driven_activity(&model, &mut fused);
+        for (e, a) in expected.iter().flatten().zip(actual.iter().flatten()) {
+            assert!((e - a).abs() < 1e-5, "fused {} vs separate {}", a, e);
+        }
+    }
+
+    // An ablated synapse drops out of its fused group
+    model.synapses[4].metadata.enabled = false;
+    let expected = driven_activity(&model, &mut separate);
+    let actual = driven_activity(&model, &mut fused);
+    for (e, a) in expected.iter().flatten().zip(actual.iter().flatten()) {
+        assert!((e - a).abs() < 1e-5, "fused {} vs separate {}", a, e);
+    }
+}
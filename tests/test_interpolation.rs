@@ -0,0 +1,79 @@
+#![cfg(feature = "robot")]
+
+use custom_framework::dataset::trajectory::Trajectory;
+use custom_framework::robot::interpolation::{InterpolatedTrajectory, Interpolation};
+use std::time::Duration;
+
+/// One joint, recorded at 10 Hz with a gap: 0 -> 1 -> 1 -> (gap) -> 0
+fn recording() -> Trajectory {
+    Trajectory {
+        channels: vec!["j1".to_string()],
+        timestamps_ms: Some(vec![0, 100, 200, 600]),
+        frames: vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0]],
+    }
+}
+
+fn at(path: &InterpolatedTrajectory, ms: u64) -> f64 {
+    path.sample(Duration::from_millis(ms))[0]
+}
+
+#[test]
+fn test_interpolation_methods() {
+    let step = InterpolatedTrajectory::new(&recording(), Interpolation::Step).unwrap();
+    assert_eq!(at(&step, 50), 0.0);
+    assert_eq!(at(&step, 400), 1.0);
+
+    let linear = InterpolatedTrajectory::new(&recording(), Interpolation::Linear).unwrap();
+    assert!((at(&linear, 50) - 0.5).abs() < 1e-9);
+    assert!((at(&linear, 400) - 0.5).abs() < 1e-9);
+
+    let cubic = InterpolatedTrajectory::new(&recording(), Interpolation::Cubic).unwrap();
+    assert_eq!(cubic.duration(), Duration::from_millis(600));
+    for (t, frame) in cubic.knots() {
+        assert!((cubic.sample(t)[0] - frame[0]).abs() < 1e-9);
+    }
+    // Smooth but monotone: no overshoot above the plateau or below the start
+    let samples: Vec<f64> = (0..=600).map(|ms| at(&cubic, ms)).collect();
+    assert!(samples.iter().all(|&p| (-1e-9..=1.0 + 1e-9).contains(&p)));
+    assert!(samples.windows(2).all(|w| (w[1] - w[0]).abs() < 0.02));
+    assert_eq!(at(&cubic, 5000), 0.0);
+}
+
+#[test]
+fn test_limits_and_velocities() {
+    let path = InterpolatedTrajectory::new(&recording(), Interpolation::Cubic)
+        .unwrap()
+        .with_limits(&[-1.0], &[0.8]);
+    assert!((0..=600).all(|ms| at(&path, ms) <= 0.8 + 1e-9));
+
+    let limit = 2.0;
+    for method in [Interpolation::Linear, Interpolation::Cubic] {
+        let path = InterpolatedTrajectory::new(&recording(), method)
+            .unwrap()
+            .with_max_velocities(&[limit]);
+        // 0 -> 1 in 100 ms is 10 rad/s, so the path has to slow down
+        assert!(path.duration() > Duration::from_millis(600));
+        let end = path.duration().as_millis() as u64;
+        for ms in 0..end {
+            let speed = (at(&path, ms + 1) - at(&path, ms)).abs() * 1000.0;
+            assert!(
+                speed <= limit * 1.001,
+                "{:?} at {} ms: {}",
+                method,
+                ms,
+                speed
+            );
+        }
+    }
+}
+
+#[test]
+fn test_repeated_timestamps_keep_the_later_frame() {
+    let mut trajectory = recording();
+    trajectory.timestamps_ms = Some(vec![0, 100, 100, 600]);
+    let path = InterpolatedTrajectory::new(&trajectory, Interpolation::Linear).unwrap();
+    assert_eq!(path.knots().count(), 3);
+
+    trajectory.timestamps_ms = None;
+    assert!(InterpolatedTrajectory::new(&trajectory, Interpolation::Linear).is_err());
+}
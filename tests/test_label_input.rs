@@ -0,0 +1,45 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::{Model, ModelConfig};
+use custom_framework::seed;
+
+#[test]
+fn test_label_input_extends_the_input_layer() {
+    let device = Device::Cpu;
+    seed::set_global_seed(5, &device).unwrap();
+    let config = ModelConfig::standard(4, 2, vec![8], 0.1, None)
+        .unwrap()
+        .with_label_input(3)
+        .unwrap();
+    let mut model = Model::from_config(config, &device).unwrap();
+    assert_eq!(model.layers[0].size(), 7);
+
+    model.reset(2).unwrap();
+    let input = Tensor::zeros((4, 2), DType::F32, &device).unwrap();
+    model.set_input_classes(&[2, 0]).unwrap();
+    model.step(&input, None).unwrap();
+    // Bernoulli spikes with probability 1 where the label is set and 0 elsewhere
+    let spikes = model.layers[0].output().unwrap().to_vec2::<f32>().unwrap();
+    assert_eq!(
+        spikes[4..],
+        [vec![0.0, 1.0], vec![0.0, 0.0], vec![1.0, 0.0]]
+    );
+
+    // No label presents zeros
+    model.set_input_label(None).unwrap();
+    model.step(&input, None).unwrap();
+    let spikes = model.layers[0].output().unwrap().sum_all().unwrap();
+    assert_eq!(spikes.to_scalar::<f32>().unwrap(), 0.0);
+
+    assert!(model.set_input_classes(&[3]).is_err());
+    let wrong = Tensor::ones((2, 1), DType::F32, &device).unwrap();
+    assert!(model.set_input_label(Some(&wrong)).is_err());
+}
+
+#[test]
+fn test_label_input_needs_bernoulli_input() {
+    let config = ModelConfig::standard(4, 2, vec![8], 0.1, Some(vec![2, 2])).unwrap();
+    assert!(config.with_label_input(2).is_err());
+
+    let mut model = Model::new(4, 2, vec![8], &Device::Cpu, 0.1, None).unwrap();
+    assert!(model.set_input_label(None).is_err());
+}
@@ -181,7 +181,7 @@ fn test_ac_csdp_actor_logic_learning() {
                             .synapse
                             .update_weights(
                                 &pre_act,
-                                &mut model.actor.layers[syn_conn.metadata.post_layer],
+                                model.actor.layers[syn_conn.metadata.post_layer].as_ref(),
                                 dt,
                             )
                             .unwrap();
@@ -0,0 +1,57 @@
+use custom_framework::VisualizationState;
+use custom_framework::layer::LayerPosition;
+use custom_framework::synapse::WeightStats;
+use custom_framework::visualization::metrics::{EpochRate, render};
+use custom_framework::visualization::{LayerVisInfo, RobotVisInfo, SynapseVisInfo};
+
+#[test]
+fn test_render_prometheus_metrics() {
+    let mut state = VisualizationState::new(100);
+    state.runtime_stats.epoch = 7;
+    state.epoch_rewards.push((6, 1.5));
+    state.model_structure.layers.push(LayerVisInfo {
+        id: 0,
+        name: "hidden \"0\"".to_string(),
+        layer_type: "LIF".to_string(),
+        size: 10,
+        position: LayerPosition { x: 0.0, y: 0.0 },
+        velocity: (0.0, 0.0),
+        current_activity: Vec::new(),
+        spike_count: 3,
+        topography: None,
+    });
+    state.model_structure.synapses.push(SynapseVisInfo {
+        id: 0,
+        pre_layer: 0,
+        post_layer: 0,
+        synapse_type: "CSDP".to_string(),
+        weight_stats: WeightStats {
+            mean: 0.0,
+            std: 0.5,
+            min: -1.0,
+            max: 1.0,
+            num_weights: 16,
+        },
+        is_learning: true,
+        enabled: true,
+    });
+    state.robot_status.push(RobotVisInfo {
+        name: "follower".to_string(),
+        positions: vec![2048.0],
+        goals: vec![2000.0],
+        loads: vec![12.0],
+    });
+
+    let text = render(&state, &mut EpochRate::new(), Some(512.0));
+
+    assert!(text.contains("csdp_epoch 7\n"));
+    assert!(text.contains("csdp_total_epochs 100\n"));
+    assert!(text.contains("csdp_epoch_reward{epoch=\"6\"} 1.5\n"));
+    assert!(text.contains("csdp_gpu_memory_mb 512\n"));
+    assert!(text.contains("csdp_layer_firing_rate{layer=\"hidden \\\"0\\\"\",id=\"0\"} 0.3\n"));
+    // sqrt(16 * 0.5^2) = 2
+    assert!(text.contains("csdp_weight_norm{synapse=\"0\","));
+    assert!(text.contains("} 2\n"));
+    assert!(text.contains("csdp_robot_position{robot=\"follower\",joint=\"0\"} 2048\n"));
+    assert!(text.contains("# TYPE csdp_robot_load gauge\n"));
+}
@@ -0,0 +1,122 @@
+use candle_core::{Device, Tensor};
+use custom_framework::layer::Layer;
+use custom_framework::layer::normalization::{NormalizationConfig, NormalizationLayer};
+use custom_framework::models::{Model, ModelConfig};
+
+fn column_values(tensor: &Tensor) -> Vec<f32> {
+    tensor.flatten_all().unwrap().to_vec1::<f32>().unwrap()
+}
+
+#[test]
+fn test_running_statistics_match_batch_statistics() {
+    let device = Device::Cpu;
+    let mut layer = NormalizationLayer::new(2, NormalizationConfig::default(), &device).unwrap();
+    layer.reset(2).unwrap();
+
+    let batches = [[[1.0f32, 3.0], [10.0, 10.0]], [[5.0f32, 7.0], [10.0, 30.0]]];
+    for batch in batches {
+        layer.reset_input().unwrap();
+        layer
+            .add_input(&Tensor::new(&batch, &device).unwrap())
+            .unwrap();
+        layer.step(0.1).unwrap();
+    }
+
+    // Channel 0 saw 1, 3, 5, 7 and channel 1 saw 10, 10, 10, 30
+    assert_eq!(layer.count(), 4.0);
+    assert_eq!(column_values(layer.mean()), [4.0, 15.0]);
+    assert_eq!(column_values(&layer.variance().unwrap()), [5.0, 75.0]);
+
+    // 1 / sqrt(5) standard deviations above the mean, with 3 standard deviations to 1
+    let output = layer.output().unwrap().to_vec2::<f32>().unwrap();
+    let expected = 0.5 + 1.0 / (5.0f32.sqrt() * 6.0);
+    assert!((output[0][0] - expected).abs() < 1e-4);
+    assert!(output.iter().flatten().all(|p| (0.0..=1.0).contains(p)));
+}
+
+#[test]
+fn test_capped_statistics_follow_drift() {
+    let device = Device::Cpu;
+    let config = NormalizationConfig {
+        max_count: Some(10),
+        ..Default::default()
+    };
+    let mut capped = NormalizationLayer::new(1, config, &device).unwrap();
+    let mut uncapped = NormalizationLayer::new(1, NormalizationConfig::default(), &device).unwrap();
+
+    for value in [0.0f32, 100.0] {
+        let input = Tensor::new(&[[value]], &device).unwrap();
+        for _ in 0..50 {
+            for layer in [&mut capped, &mut uncapped] {
+                layer.reset_input().unwrap();
+                layer.add_input(&input).unwrap();
+                layer.step(0.1).unwrap();
+            }
+        }
+    }
+
+    assert_eq!(capped.count(), 10.0);
+    assert!(column_values(capped.mean())[0] > 99.0);
+    assert!((column_values(uncapped.mean())[0] - 50.0).abs() < 1e-3);
+
+    // Frozen statistics stay put
+    capped.set_learning(false);
+    capped.reset_input().unwrap();
+    capped
+        .add_input(&Tensor::new(&[[-100.0f32]], &device).unwrap())
+        .unwrap();
+    capped.step(0.1).unwrap();
+    assert_eq!(capped.count(), 10.0);
+    assert_eq!(column_values(capped.output().unwrap()), [0.0]);
+}
+
+#[test]
+fn test_model_normalizes_raw_input() {
+    let device = Device::Cpu;
+    let config = ModelConfig::standard(2, 2, vec![4], 0.1, None)
+        .unwrap()
+        .with_input_normalization(NormalizationConfig::default())
+        .unwrap();
+    let mut model = Model::from_config(config, &device).unwrap();
+    model.reset(1).unwrap();
+
+    // Far outside [0, 1], the raw reading would saturate the Bernoulli layer
+    let input = Tensor::new(&[[250.0f32], [-40.0]], &device).unwrap();
+    for _ in 0..3 {
+        model.step(&input, None).unwrap();
+    }
+    let probs = column_values(model.layers[0].activity().unwrap());
+    assert!(probs.iter().all(|p| (p - 0.5).abs() < 1e-3));
+
+    let normalization = model.input_normalization().unwrap();
+    assert_eq!(normalization.count(), 3.0);
+    assert_eq!(column_values(normalization.mean()), [250.0, -40.0]);
+
+    let path = std::env::temp_dir().join(format!("normalization_{}.st", std::process::id()));
+    model.save(&path).unwrap();
+    let config = ModelConfig::standard(2, 2, vec![4], 0.1, None)
+        .unwrap()
+        .with_input_normalization(NormalizationConfig::default())
+        .unwrap();
+    let mut restored = Model::from_config(config, &device).unwrap();
+    restored.load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    let statistics = restored.input_normalization().unwrap();
+    assert_eq!(statistics.count(), 3.0);
+    assert_eq!(column_values(statistics.mean()), [250.0, -40.0]);
+}
+
+#[test]
+fn test_rejects_non_positive_clip() {
+    let config = NormalizationConfig {
+        clip: 0.0,
+        ..Default::default()
+    };
+    assert!(NormalizationLayer::new(3, config, &Device::Cpu).is_err());
+    assert!(
+        ModelConfig::standard(2, 2, vec![4], 0.1, None)
+            .unwrap()
+            .with_input_normalization(config)
+            .is_err()
+    );
+}
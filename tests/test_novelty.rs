@@ -0,0 +1,123 @@
+use candle_core::{Device, Tensor};
+use custom_framework::VisualizationState;
+use custom_framework::layer::Layer;
+use custom_framework::layer::predictive::PredictionLayer;
+use custom_framework::models::novelty::{NoveltyConfig, NoveltyDetector, NoveltySource};
+use custom_framework::models::{Model, ModelConfig};
+use custom_framework::visualization::metrics::{EpochRate, render};
+
+/// Rate layers whose outputs are set directly
+fn rate_layers(count: usize, device: &Device) -> Vec<Box<dyn Layer>> {
+    (0..count)
+        .map(|_| Box::new(PredictionLayer::new(4, device).unwrap()) as Box<dyn Layer>)
+        .collect()
+}
+
+fn set_output(layer: &mut Box<dyn Layer>, value: f32, device: &Device) {
+    layer.reset_input().unwrap();
+    layer
+        .add_input(&Tensor::full(value, (4, 1), device).unwrap())
+        .unwrap();
+    layer.step(1.0).unwrap();
+}
+
+#[test]
+fn test_activity_jump_is_novel_until_familiar() {
+    let device = Device::Cpu;
+    let mut layers = rate_layers(1, &device);
+    let mut config = NoveltyConfig::new(NoveltySource::Activity { layer: 0 });
+    config.tau = 10.0;
+    let mut detector = NoveltyDetector::new(config);
+
+    for _ in 0..20 {
+        set_output(&mut layers[0], 0.1, &device);
+        detector.observe(&layers, 1.0).unwrap();
+    }
+    assert_eq!(detector.mean_score().unwrap(), Some(0.0));
+    assert_eq!(detector.plasticity_scale().unwrap(), 1.0);
+
+    // A sudden rise is far outside the settled baseline and hits the cap
+    set_output(&mut layers[0], 0.9, &device);
+    detector.observe(&layers, 1.0).unwrap();
+    assert_eq!(detector.mean_score().unwrap(), Some(5.0));
+    assert_eq!(detector.plasticity_scale().unwrap(), 6.0);
+
+    for _ in 0..200 {
+        set_output(&mut layers[0], 0.9, &device);
+        detector.observe(&layers, 1.0).unwrap();
+    }
+    assert!(detector.mean_score().unwrap().unwrap() < 0.5);
+}
+
+#[test]
+fn test_prediction_error_scores_the_previous_prediction() {
+    let device = Device::Cpu;
+    let mut layers = rate_layers(2, &device);
+    let mut detector = NoveltyDetector::new(NoveltyConfig::new(NoveltySource::PredictionError {
+        prediction: 0,
+        target: 1,
+    }));
+
+    // The first step has no earlier prediction to score
+    set_output(&mut layers[0], 0.5, &device);
+    set_output(&mut layers[1], 0.2, &device);
+    detector.observe(&layers, 1.0).unwrap();
+    assert!(detector.score().is_none());
+
+    // Accurate predictions keep the error, and so the novelty, at zero
+    for _ in 0..10 {
+        set_output(&mut layers[1], 0.5, &device);
+        detector.observe(&layers, 1.0).unwrap();
+    }
+    assert_eq!(detector.mean_score().unwrap(), Some(0.0));
+
+    set_output(&mut layers[1], 1.0, &device);
+    detector.observe(&layers, 1.0).unwrap();
+    assert!(detector.mean_score().unwrap().unwrap() > 1.0);
+}
+
+#[test]
+fn test_model_reports_novelty() {
+    let device = Device::Cpu;
+    let config = ModelConfig::standard(4, 2, vec![8], 0.1, None)
+        .unwrap()
+        .with_novelty(NoveltyConfig::new(NoveltySource::Activity { layer: 2 }))
+        .unwrap();
+    let mut model = Model::from_config(config, &device).unwrap();
+    model.reset(1).unwrap();
+    assert_eq!(model.novelty_score().unwrap(), None);
+
+    let input = Tensor::ones((4, 1), candle_core::DType::F32, &device).unwrap();
+    for _ in 0..5 {
+        model.step(&input, None).unwrap();
+    }
+    assert_eq!(model.novelty().unwrap().dims(), [1, 1]);
+    let score = model.novelty_score().unwrap().unwrap();
+    assert!((0.0..=5.0).contains(&score));
+
+    let mut state = VisualizationState::new(1);
+    state.model_structure = model.get_visualization_snapshot().unwrap();
+    assert_eq!(state.model_structure.novelty, Some(score));
+    let text = render(&state, &mut EpochRate::new(), None);
+    assert!(text.contains(&format!("csdp_novelty {}", score)));
+}
+
+#[test]
+fn test_novelty_source_must_exist() {
+    let config = ModelConfig::standard(4, 2, vec![8], 0.1, None).unwrap();
+    assert!(
+        config
+            .with_novelty(NoveltyConfig::new(NoveltySource::Activity { layer: 9 }))
+            .is_err()
+    );
+    // The input and hidden layers differ in size, so one can't predict the other
+    let config = ModelConfig::standard(4, 2, vec![8], 0.1, None).unwrap();
+    assert!(
+        config
+            .with_novelty(NoveltyConfig::new(NoveltySource::PredictionError {
+                prediction: 2,
+                target: 0,
+            }))
+            .is_err()
+    );
+}
@@ -0,0 +1,135 @@
+use candle_core::Device;
+use custom_framework::experiment::ExperimentConfig;
+use custom_framework::models::observation::{Modality, ObservationSpace};
+use custom_framework::models::robot_model::{NUM_MOTORS, ObservationEncoder, RobotModel};
+
+fn space() -> ObservationSpace {
+    ObservationSpace::new()
+        .with(
+            "arm",
+            Modality::Joints {
+                lower: vec![-1.0; NUM_MOTORS],
+                upper: vec![1.0; NUM_MOTORS],
+                population: 3,
+            },
+        )
+        .unwrap()
+        .with(
+            "camera",
+            Modality::Camera {
+                width: 4,
+                height: 2,
+                out_width: 2,
+                out_height: 1,
+                max_value: 10.0,
+            },
+        )
+        .unwrap()
+        .with("gripper", Modality::Gripper { threshold: 0.5 })
+        .unwrap()
+        .with("previous", Modality::PreviousAction)
+        .unwrap()
+}
+
+#[test]
+fn test_slices_are_laid_out_in_order() {
+    let space = space();
+    assert_eq!(space.input_size(), NUM_MOTORS * 3 + 2 + 2 + NUM_MOTORS);
+    assert_eq!(space.slice("arm"), Some(0..18));
+    assert_eq!(space.slice("camera"), Some(18..20));
+    assert_eq!(space.slice("gripper"), Some(20..22));
+    assert_eq!(space.slice("previous"), Some(22..28));
+    assert_eq!(space.slice("depth"), None);
+    // Names are unique
+    assert!(space.clone().with("arm", Modality::Reward).is_err());
+}
+
+#[test]
+fn test_modalities_are_encoded_into_their_slices() {
+    let space = space();
+    let joints = [0.0; NUM_MOTORS];
+    // Left half averages 2, right half averages 6
+    let frame = [1.0, 3.0, 5.0, 7.0, 1.0, 3.0, 5.0, 7.0];
+    let readings: [(&str, &[f64]); 3] = [("arm", &joints), ("camera", &frame), ("gripper", &[0.9])];
+    let values: Vec<f32> = space
+        .encode(&readings, &Device::Cpu)
+        .unwrap()
+        .flatten_all()
+        .unwrap()
+        .to_vec1()
+        .unwrap();
+    assert_eq!(values.len(), space.input_size());
+    assert!((values[18] - 0.2).abs() < 1e-6);
+    assert!((values[19] - 0.6).abs() < 1e-6);
+    // Closed gripper
+    assert_eq!(&values[20..22], &[0.0, 1.0]);
+    // No previous action reads as zero
+    assert!(values[22..].iter().all(|&v| v == 0.5));
+
+    // Every sensor slice needs a reading of the right size
+    let missing: [(&str, &[f64]); 2] = [("arm", &joints), ("camera", &frame)];
+    assert!(space.encode(&missing, &Device::Cpu).is_err());
+    let short: [(&str, &[f64]); 3] = [("arm", &joints), ("camera", &[1.0]), ("gripper", &[0.0])];
+    assert!(space.encode(&short, &Device::Cpu).is_err());
+}
+
+#[test]
+fn test_space_round_trips_through_json() {
+    let space = space();
+    let path = std::env::temp_dir().join(format!("observation_{}.json", std::process::id()));
+    space.save(&path).unwrap();
+    let loaded = ObservationSpace::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, space);
+
+    // Invalid modalities are rejected on load
+    let json = r#"{ "slices": [ { "name": "cam", "kind": "camera", "width": 4, "height": 4,
+        "out_width": 8, "out_height": 2 } ] }"#;
+    std::fs::write(&path, json).unwrap();
+    assert!(ObservationSpace::load(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_robot_model_observes_a_composite_space() {
+    let mut model = RobotModel::with_observation_space(space(), 1, 8, &Device::Cpu, 0.1);
+    assert_eq!(model.observation_space().unwrap().input_size(), 28);
+    let joints = [0.1; NUM_MOTORS];
+    let frame = [5.0; 8];
+    let readings: [(&str, &[f64]); 3] = [("arm", &joints), ("camera", &frame), ("gripper", &[0.0])];
+    let command = model.observe(&readings, None).unwrap();
+    assert!(command.iter().all(|c| c.abs() <= model.step_size() + 1e-9));
+
+    // Joint readings alone don't fill the space
+    assert!(model.control(&joints, None).is_err());
+
+    // A model built for joints alone has no space to observe by
+    let mut plain = RobotModel::new(1, 8, &Device::Cpu, 0.1);
+    assert!(plain.observe(&readings, None).is_err());
+}
+
+#[test]
+fn test_experiment_config_loads_the_space() {
+    let path = std::env::temp_dir().join(format!("observation_cfg_{}.json", std::process::id()));
+    space().save(&path).unwrap();
+    let config = ExperimentConfig {
+        hidden_sizes: Some(vec![8, 8]),
+        observation_space: Some(path.clone()),
+        ..ExperimentConfig::default()
+    };
+    let model = config
+        .robot_model(ObservationEncoder::default(), &Device::Cpu)
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(model.observation_space(), Some(&space()));
+
+    let plain = ExperimentConfig {
+        hidden_sizes: Some(vec![8, 16]),
+        ..ExperimentConfig::default()
+    };
+    assert!(
+        plain
+            .robot_model(ObservationEncoder::default(), &Device::Cpu)
+            .is_err()
+    );
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "robot")]
+
+use custom_framework::error::CsdpError;
+use custom_framework::robot::Arm;
+use custom_framework::robot::pipeline::{CycleRecord, Pipeline, PipelineConfig, Stamped};
+use custom_framework::robot::sim_lerobot::SimLeRobot;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn sim() -> SimLeRobot {
+    let mut arm = SimLeRobot::new([0.0; 6], [-1.0; 6], [1.0; 6]);
+    arm.enable().unwrap();
+    arm
+}
+
+#[test]
+fn test_cycles_reach_the_arm_and_the_log() {
+    let records: Arc<Mutex<Vec<CycleRecord>>> = Arc::default();
+    let sink = {
+        let records = records.clone();
+        move |record: &CycleRecord| {
+            records.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+    };
+    let policy = |reading: &Stamped<Vec<f64>>| Ok(reading.value.iter().map(|p| p + 0.05).collect());
+    let pipeline = Pipeline::spawn(sim(), PipelineConfig::new(100.0), policy, sink).unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(pipeline.is_running());
+    let (mut arm, report) = pipeline.stop().unwrap();
+
+    assert!(report.reads > 0 && report.writes > 0);
+    assert_eq!(report.latencies.len() as u64, report.writes);
+    assert_eq!((report.read_errors, report.write_errors), (0, 0));
+    // The goals kept moving the arm away from home
+    assert!(arm.get_motor_positions().unwrap()[0] > 0.0);
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len() as u64, report.writes - report.dropped_logs);
+    assert!(records.windows(2).all(|w| w[0].seq < w[1].seq));
+    for record in records.iter() {
+        assert!(record.read_at <= record.computed_at && record.computed_at <= record.written_at);
+        assert!((record.goals[0] - record.positions[0] - 0.05).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_slow_sink_does_not_stall_the_reader() {
+    let mut config = PipelineConfig::new(200.0);
+    config.log_capacity = 1;
+    let sink = |_: &CycleRecord| {
+        std::thread::sleep(Duration::from_millis(50));
+        Ok(())
+    };
+    let pipeline = Pipeline::spawn(sim(), config, |r| Ok(r.value.clone()), sink).unwrap();
+    std::thread::sleep(Duration::from_millis(300));
+    let (_, report) = pipeline.stop().unwrap();
+
+    assert!(report.dropped_logs > 0);
+    // About 60 reads at 200 Hz, far more than the ~6 records the sink could take
+    assert!(report.reads > 30, "{} reads", report.reads);
+    assert!(report.deadlines.miss_rate() < 0.5, "{}", report.deadlines);
+}
+
+#[test]
+fn test_policy_error_stops_the_pipeline() {
+    let policy = |r: &Stamped<Vec<f64>>| {
+        if r.seq < 3 {
+            Ok(r.value.clone())
+        } else {
+            Err(CsdpError::Data("policy failed".to_string()))
+        }
+    };
+    let pipeline = Pipeline::spawn(
+        sim(),
+        PipelineConfig::new(100.0),
+        policy,
+        |_: &CycleRecord| Ok(()),
+    )
+    .unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(!pipeline.is_running());
+    assert!(matches!(pipeline.stop(), Err(CsdpError::Data(_))));
+}
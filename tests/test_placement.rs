@@ -0,0 +1,118 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::layer::normalization::NormalizationConfig;
+use custom_framework::models::consolidation::ConsolidationConfig;
+use custom_framework::models::context::ContextGatingConfig;
+use custom_framework::models::{Model, ModelConfig};
+use custom_framework::seed;
+
+fn run(config: ModelConfig, device: &Device) -> Vec<Vec<f32>> {
+    seed::set_global_seed(11, device).unwrap();
+    let mut model = Model::from_config(config, device).unwrap();
+    let input = Tensor::ones((4, 2), DType::F32, device).unwrap();
+    let label = Tensor::ones((2, 2), DType::F32, device).unwrap();
+    model.reset(2).unwrap();
+    for _ in 0..20 {
+        model.step(&input, Some(&label)).unwrap();
+    }
+    model
+        .process(&input, 10, false, device)
+        .unwrap()
+        .final_output
+        .to_vec2::<f32>()
+        .unwrap()
+}
+
+#[test]
+fn test_layer_placement_matches_single_device() {
+    let device = Device::Cpu;
+    let config = || ModelConfig::standard(4, 2, vec![8, 8], 0.1, None).unwrap();
+    let placed = config()
+        .with_layer_devices(vec![device.clone(); 5])
+        .unwrap();
+    assert_eq!(run(placed, &device), run(config(), &device));
+
+    assert!(config().with_layer_devices(vec![device; 3]).is_err());
+}
+
+#[test]
+fn test_offloaded_synapse_matches_resident() {
+    let device = Device::Cpu;
+    let config = || ModelConfig::standard(4, 2, vec![8, 8], 0.1, None).unwrap();
+    let offloaded = config().with_offloaded_synapse(0, 2).unwrap();
+    assert_eq!(run(offloaded, &device), run(config(), &device));
+
+    let model =
+        Model::from_config(config().with_offloaded_synapse(2, 3).unwrap(), &device).unwrap();
+    let summary = model.summary().unwrap();
+    assert!(summary.synapses.iter().all(|s| s.device == "cpu"));
+
+    // No such connection, and a synapse with per-step state
+    let missing = config().with_offloaded_synapse(4, 0).unwrap();
+    assert!(Model::from_config(missing, &device).is_err());
+    let predictive = config()
+        .with_predictive_front_end(0.1)
+        .unwrap()
+        .with_offloaded_synapse(0, 2)
+        .unwrap();
+    assert!(Model::from_config(predictive, &device).is_err());
+    assert!(config().with_offloaded_synapse(0, 9).is_err());
+}
+
+#[test]
+fn test_split_model_state_loads_onto_layer_devices() {
+    let device = Device::Cpu;
+    let split = |seed_value| {
+        seed::set_global_seed(seed_value, &device).unwrap();
+        let config = ModelConfig::standard(4, 2, vec![8, 8], 0.1, None)
+            .unwrap()
+            .with_input_normalization(NormalizationConfig::default())
+            .unwrap()
+            .with_layer_devices(vec![device.clone(); 5])
+            .unwrap();
+        let mut model = Model::from_config(config, &device).unwrap();
+        model
+            .enable_consolidation(ConsolidationConfig::default())
+            .unwrap();
+        model
+            .enable_context_gating(ContextGatingConfig::new(2))
+            .unwrap();
+        model.reset(2).unwrap();
+        model
+    };
+    let input = Tensor::ones((4, 2), DType::F32, &device).unwrap();
+    let label = Tensor::ones((2, 2), DType::F32, &device).unwrap();
+    let mut model = split(11);
+    for _ in 0..20 {
+        model.step(&input, Some(&label)).unwrap();
+    }
+    model.consolidate().unwrap();
+
+    let path = std::env::temp_dir().join(format!("placement_{}.st", std::process::id()));
+    model.save(&path).unwrap();
+    let mut restored = split(12);
+    restored.load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let mean = restored.input_normalization().unwrap().mean();
+    assert!(mean.device().same_device(restored.layer_device(0)));
+    for (id, gate) in restored.context_gating().unwrap().active() {
+        assert!(gate.device().same_device(restored.layer_device(*id)));
+    }
+    for (index, conn) in restored.synapses.iter().enumerate() {
+        let post = restored.layer_device(conn.metadata.post_layer);
+        for importance in restored.consolidation().unwrap().importance(index) {
+            assert!(importance.device().same_device(post));
+        }
+    }
+
+    // A step after loading runs, and matches the original from the same state
+    let mut outputs = Vec::new();
+    for each in [&mut model, &mut restored] {
+        seed::set_global_seed(13, &device).unwrap();
+        each.reset(2).unwrap();
+        each.step(&input, Some(&label)).unwrap();
+        let output = each.process(&input, 10, false, &device).unwrap();
+        outputs.push(output.final_output.to_vec2::<f32>().unwrap());
+    }
+    assert_eq!(outputs[0], outputs[1]);
+}
@@ -0,0 +1,88 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::robot_model::{
+    NUM_MOTORS, ObservationEncoder, PopulationDecoder, RobotModel, TuningCurve,
+};
+
+#[test]
+fn test_population_vector_decoding() {
+    let device = Device::Cpu;
+    // Preferred velocities -1, -0.5, 0, 0.5 and 1 (times max_velocity)
+    let mut decoder = PopulationDecoder::new(5, 2, 0.2, TuningCurve::default());
+    assert_eq!(decoder.output_size(), NUM_MOTORS * 5);
+
+    let mut values = vec![0.0f32; decoder.output_size()];
+    // Motor 0: neurons at 0.5 and 1 fire equally -> 0.75
+    values[3] = 1.0;
+    values[4] = 1.0;
+    // Motor 1: only the neuron at -0.5 fires
+    values[6] = 1.0;
+    let spikes = Tensor::from_vec(values, (decoder.output_size(), 1), &device).unwrap();
+    for _ in 0..2 {
+        assert!(!decoder.ready());
+        decoder.accumulate(&spikes).unwrap();
+    }
+    assert!(decoder.ready());
+    let command = decoder.decode();
+    assert!((command[0] - 0.15).abs() < 1e-9);
+    assert!((command[1] + 0.1).abs() < 1e-9);
+    // Silent populations keep their joint still
+    assert!(command[2..].iter().all(|&v| v == 0.0));
+    assert!(!decoder.ready());
+
+    let wrong = Tensor::zeros((18, 1), DType::F32, &device).unwrap();
+    assert!(decoder.accumulate(&wrong).is_err());
+}
+
+#[test]
+fn test_decoding_the_target_pattern() {
+    let device = Device::Cpu;
+    for tuning in [
+        TuningCurve::Gaussian { width: 1.0 },
+        TuningCurve::Cosine { width: 2.0 },
+    ] {
+        let mut decoder = PopulationDecoder::new(9, 1, 1.0, tuning);
+        let velocities = [0.0, 0.25, -0.5, 0.3, -0.1, 0.6];
+        let target = decoder.target(&velocities, &device).unwrap();
+        assert_eq!(target.dims(), &[decoder.output_size(), 1]);
+        decoder.accumulate(&target).unwrap();
+        // Symmetric tuning curves decode back to the velocity away from the range ends
+        for (decoded, expected) in decoder.decode().iter().zip(velocities) {
+            assert!((decoded - expected).abs() < 0.05, "{decoded} vs {expected}");
+        }
+    }
+    let decoder = PopulationDecoder::new(9, 1, 1.0, TuningCurve::default());
+    assert!(decoder.target(&[0.0; 3], &device).is_err());
+}
+
+#[test]
+fn test_smoothing_blends_commands() {
+    let device = Device::Cpu;
+    let mut decoder = PopulationDecoder::new(3, 1, 1.0, TuningCurve::default()).with_smoothing(0.5);
+    let mut values = vec![0.0f32; decoder.output_size()];
+    values[2] = 1.0;
+    let spikes = Tensor::from_vec(values, (decoder.output_size(), 1), &device).unwrap();
+    decoder.accumulate(&spikes).unwrap();
+    assert_eq!(decoder.decode()[0], 0.5);
+    decoder.accumulate(&spikes).unwrap();
+    assert_eq!(decoder.decode()[0], 0.75);
+    decoder.reset();
+    decoder.accumulate(&spikes).unwrap();
+    assert_eq!(decoder.decode()[0], 0.5);
+}
+
+#[test]
+fn test_robot_model_with_population_decoder() {
+    let device = Device::Cpu;
+    let decoder = PopulationDecoder::new(7, 4, 0.1, TuningCurve::default());
+    let mut model = RobotModel::with_population_decoder(
+        ObservationEncoder::default().with_previous_action(),
+        decoder,
+        1,
+        16,
+        &device,
+        0.1,
+    );
+    assert_eq!(model.population_decoder().unwrap().output_size(), 42);
+    let command = model.control(&[0.0; NUM_MOTORS], None).unwrap();
+    assert!(command.iter().all(|v| v.abs() <= 0.1 + 1e-9));
+}
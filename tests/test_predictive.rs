@@ -0,0 +1,52 @@
+use candle_core::{Device, Tensor};
+use custom_framework::layer::predictive::PredictionLayer;
+use custom_framework::models::{Model, ModelConfig};
+use custom_framework::seed;
+use custom_framework::synapse::SynapseOps;
+use custom_framework::synapse::predictive::PredictiveSynapse;
+
+#[test]
+fn test_predictive_synapse_learns_next_input() {
+    let device = Device::Cpu;
+    seed::set_global_seed(1, &device).unwrap();
+    let a = Tensor::from_vec(vec![1.0f32, 0.0, 0.0, 1.0], (4, 1), &device).unwrap();
+    let b = Tensor::from_vec(vec![0.0f32, 1.0, 1.0, 0.0], (4, 1), &device).unwrap();
+    let mut synapse = PredictiveSynapse::new(4, 0.2, &device).unwrap();
+    let post = PredictionLayer::new(4, &device).unwrap();
+
+    for t in 0..300 {
+        let x = if t % 2 == 0 { &a } else { &b };
+        synapse.update_weights(x, &post, 0.1).unwrap();
+    }
+
+    assert!(synapse.prediction_error().unwrap().unwrap() < 1e-3);
+    let predicted = synapse.forward(&a).unwrap().to_vec2::<f32>().unwrap();
+    for (p, e) in predicted.iter().zip(b.to_vec2::<f32>().unwrap()) {
+        assert!((p[0] - e[0]).abs() < 0.05);
+    }
+}
+
+#[test]
+fn test_predictive_front_end_feeds_hidden_layers() {
+    let device = Device::Cpu;
+    let config = ModelConfig::standard(4, 2, vec![8], 0.1, None)
+        .unwrap()
+        .with_predictive_front_end(0.1)
+        .unwrap();
+    let mut model = Model::from_config(config, &device).unwrap();
+    assert_eq!(model.layer_metadata[2].layer_type, "Prediction");
+    let input_targets: Vec<usize> = model
+        .synapses
+        .iter()
+        .filter(|s| s.metadata.pre_layer == 0)
+        .map(|s| s.metadata.post_layer)
+        .collect();
+    assert_eq!(input_targets, vec![2]);
+
+    model.reset(1).unwrap();
+    let input = Tensor::from_vec(vec![0.9f32, 0.1, 0.9, 0.1], (4, 1), &device).unwrap();
+    for _ in 0..10 {
+        model.step(&input, None).unwrap();
+    }
+    assert_eq!(model.layers[2].output().unwrap().dims(), &[4, 1]);
+}
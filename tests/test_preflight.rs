@@ -0,0 +1,96 @@
+use candle_core::{Device, Tensor};
+use custom_framework::dataset::cache::DatasetCache;
+use custom_framework::environment::grid::GridEnvironment;
+use custom_framework::experiment;
+use custom_framework::models::Model;
+use custom_framework::models::preflight::{
+    InputEncoding, InputWarning, PreflightConfig, check_inputs,
+};
+use custom_framework::seed;
+
+/// (features, samples) from one row per feature
+fn samples(rows: &[[f32; 4]]) -> Tensor {
+    let values: Vec<f32> = rows.iter().flatten().copied().collect();
+    Tensor::from_vec(values, (rows.len(), 4), &Device::Cpu).unwrap()
+}
+
+#[test]
+fn test_probabilities_pass() {
+    let data = samples(&[[0.1, 0.5, 0.9, 0.3], [1.0, 0.0, 0.5, 0.2]]);
+    let report = check_inputs(&data, InputEncoding::Rates, &PreflightConfig::default()).unwrap();
+    assert!(report.is_clean(), "{:?}", report.warnings);
+    assert_eq!((report.features, report.samples), (2, 4));
+    assert!((report.mean_rate().unwrap() - 0.4375).abs() < 1e-3);
+}
+
+#[test]
+fn test_unscaled_constant_and_non_finite_features_are_reported() {
+    let data = samples(&[
+        [0.0, 255.0, 128.0, 64.0],
+        [-0.5, 0.2, 0.4, 0.6],
+        [0.3, 0.3, 0.3, 0.3],
+        [0.1, f32::NAN, 0.2, 0.3],
+    ]);
+    let report = check_inputs(&data, InputEncoding::Rates, &PreflightConfig::default()).unwrap();
+    assert_eq!(
+        report.warnings,
+        vec![
+            InputWarning::NonFinite { features: vec![3] },
+            InputWarning::OutOfRange {
+                features: vec![0, 1],
+                min: -0.5,
+                max: 255.0,
+            },
+            InputWarning::Constant { features: vec![2] },
+        ]
+    );
+    assert!(report.warnings[1].to_string().contains("rescale"));
+
+    // A one-hot layer reads indices, so only the constant and non-finite features count
+    let report = check_inputs(&data, InputEncoding::Rescaled, &PreflightConfig::default()).unwrap();
+    assert_eq!(report.warnings.len(), 2);
+    assert_eq!(report.rates, None);
+}
+
+#[test]
+fn test_near_silent_input_is_reported() {
+    let data = samples(&[[0.001, 0.0, 0.002, 0.0], [0.0, 0.003, 0.0, 0.001]]);
+    let report = check_inputs(&data, InputEncoding::Rates, &PreflightConfig::default()).unwrap();
+    assert!(matches!(
+        report.warnings.as_slice(),
+        [InputWarning::LowRate { rate, .. }] if *rate < 0.01
+    ));
+}
+
+#[test]
+fn test_model_and_dataset_checks() {
+    let device = Device::Cpu;
+    let data = samples(&[[0.0, 2.0, 1.0, 0.0], [0.0, 1.0, 2.0, 1.0]]);
+    let config = PreflightConfig::default();
+
+    let model = Model::new(2, 2, vec![8], &device, 0.1, None).unwrap();
+    let report = model.check_inputs(&data, &config).unwrap();
+    assert!(matches!(
+        report.warnings[0],
+        InputWarning::OutOfRange { .. }
+    ));
+    // The same values are valid indices for a one-hot input layer
+    let model = Model::new(2, 2, vec![8], &device, 0.1, Some(vec![3, 3])).unwrap();
+    assert!(model.check_inputs(&data, &config).unwrap().is_clean());
+
+    let labels = Tensor::zeros((1, 4), candle_core::DType::F32, &device).unwrap();
+    let cache = DatasetCache::new(&data, &labels, &device).unwrap();
+    assert!(!cache.check_inputs(&config).unwrap().is_clean());
+}
+
+#[test]
+fn test_environment_preflight() {
+    let env = GridEnvironment::new();
+    let before = seed::fingerprint();
+    let report = experiment::preflight(&env, 200).unwrap();
+    // The check draws from a copy of the host RNG, so a seeded run isn't shifted by it
+    assert_eq!(seed::fingerprint(), before);
+    assert_eq!((report.features, report.samples), (4, 200));
+    // Grid states are bounded indices, and the goal moves between the pre-flight's episodes
+    assert!(report.is_clean(), "{:?}", report.warnings);
+}
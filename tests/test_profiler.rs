@@ -0,0 +1,48 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::Model;
+use custom_framework::models::profiler::ProfilePhase;
+
+#[test]
+fn test_profile_report_covers_the_whole_run() {
+    let device = Device::Cpu;
+    let mut model = Model::new(4, 2, vec![6], &device, 0.1, None).unwrap();
+    let input = Tensor::ones((4, 3), DType::F32, &device).unwrap();
+    let label = Tensor::ones((2, 3), DType::F32, &device).unwrap();
+    assert!(model.profile_report().is_none());
+    model.reset(3).unwrap();
+
+    model.enable_profiling();
+    for _ in 0..3 {
+        model.step(&input, Some(&label)).unwrap();
+    }
+    // Taking the visualizer's window doesn't drop it from the run
+    assert!(model.take_perf_stats().is_some());
+    for _ in 0..2 {
+        model.step(&input, Some(&label)).unwrap();
+    }
+
+    let report = model.profile_report().unwrap();
+    assert_eq!(report.steps, 5);
+    let components = |phase| -> Vec<&str> {
+        report
+            .entries
+            .iter()
+            .filter(|e| e.phase == phase)
+            .map(|e| e.component.as_str())
+            .collect()
+    };
+    assert!(components(ProfilePhase::SynapseForward).contains(&"Input->Hidden_0"));
+    assert!(components(ProfilePhase::SynapseUpdate).contains(&"Hidden_0->Output"));
+    assert!(components(ProfilePhase::LayerStep).contains(&"Hidden_0"));
+    assert!(report.measured() <= report.wall);
+    assert!(
+        report
+            .entries
+            .windows(2)
+            .all(|pair| pair[0].total >= pair[1].total)
+    );
+    assert!(report.to_string().contains("Input->Hidden_0"));
+
+    model.disable_profiling();
+    assert!(model.profile_report().is_none());
+}
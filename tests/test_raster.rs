@@ -0,0 +1,80 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::{Model, ModelConfig};
+use custom_framework::{raster, seed, spike_metrics};
+
+/// (T, 1, 1) raster of a single neuron's train
+fn train(values: &[f32], device: &Device) -> Tensor {
+    Tensor::from_vec(values.to_vec(), (values.len(), 1, 1), device).unwrap()
+}
+
+fn flat(t: &Tensor) -> Vec<f32> {
+    t.flatten_all().unwrap().to_vec1::<f32>().unwrap()
+}
+
+#[test]
+fn test_windows_and_filters() {
+    let device = Device::Cpu;
+    let spikes = train(&[1.0, 0.0, 1.0, 1.0, 0.0, 1.0], &device);
+    assert_eq!(flat(&raster::counts(&spikes).unwrap()), vec![4.0]);
+    assert_eq!(
+        flat(&raster::window_sums(&spikes, 3, 2).unwrap()),
+        vec![2.0, 2.0]
+    );
+    assert_eq!(
+        flat(&raster::window_sums(&spikes, 2, 1).unwrap()),
+        vec![1.0, 1.0, 2.0, 1.0, 1.0]
+    );
+    assert!(raster::window_sums(&spikes, 7, 1).is_err());
+
+    assert_eq!(
+        flat(&raster::causal_convolve(&spikes, &[1.0, 0.5]).unwrap()),
+        vec![1.0, 0.5, 1.0, 1.5, 0.5, 1.0]
+    );
+    let traces = flat(&raster::exponential_filter(&spikes, 1.0, 1.0).unwrap());
+    let decay = (-1.0f32).exp();
+    assert!((traces[1] - decay).abs() < 1e-6);
+    assert!((traces[2] - (decay * decay + 1.0)).abs() < 1e-6);
+}
+
+#[test]
+fn test_matches_step_wise_metrics() {
+    let device = Device::Cpu;
+    seed::set_global_seed(3, &device).unwrap();
+    let output = Tensor::rand(0.0f32, 1.0, (20, 3, 2), &device)
+        .unwrap()
+        .ge(0.5f32)
+        .unwrap()
+        .to_dtype(DType::F32)
+        .unwrap();
+    let target = output.affine(-1.0, 1.0).unwrap();
+    let vectorized = raster::population_van_rossum(&output, &target, 2.0, 0.5).unwrap();
+    let step_wise = spike_metrics::population_van_rossum(
+        &raster::unstack(&output).unwrap(),
+        &raster::unstack(&target).unwrap(),
+        2.0,
+        0.5,
+    )
+    .unwrap();
+    for (a, b) in vectorized.iter().zip(&step_wise) {
+        assert!((a - b).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn test_process_raster() {
+    let device = Device::Cpu;
+    seed::set_global_seed(4, &device).unwrap();
+    let config = ModelConfig::standard(4, 2, vec![8], 0.1, None).unwrap();
+    let mut model = Model::from_config(config, &device).unwrap();
+    model.disable_learning();
+    let input = Tensor::ones((4, 3), DType::F32, &device).unwrap();
+
+    let spikes = model.process_raster(&input, 12).unwrap();
+    assert_eq!(spikes.dims(), &[12, 2, 3]);
+    let out = model.process(&input, 12, true, &device).unwrap();
+    assert_eq!(out.raster().unwrap().dims(), spikes.dims());
+    assert_eq!(
+        flat(&raster::rates(&spikes).unwrap()).len(),
+        out.final_output.elem_count()
+    );
+}
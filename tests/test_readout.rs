@@ -0,0 +1,47 @@
+use candle_core::Device;
+use custom_framework::models::{Model, ModelConfig, Readout};
+
+fn readout(readout: Readout, plastic: bool) -> Vec<(usize, bool)> {
+    let config = ModelConfig::standard(4, 2, vec![8, 8, 8], 0.1, None)
+        .unwrap()
+        .with_readout(readout, plastic)
+        .unwrap();
+    let model = Model::from_config(config, &Device::Cpu).unwrap();
+    model
+        .synapses
+        .iter()
+        .map(|s| &s.metadata)
+        .filter(|m| m.post_layer == 5)
+        .map(|m| (m.pre_layer, m.is_learning))
+        .collect()
+}
+
+#[test]
+fn test_readout_options() {
+    assert_eq!(readout(Readout::LastOnly, true), vec![(4, true)]);
+    assert_eq!(
+        readout(Readout::All, true),
+        vec![(2, true), (3, true), (4, true)]
+    );
+    assert_eq!(
+        readout(Readout::Layers(vec![2, 0]), false),
+        vec![(2, false), (4, false)]
+    );
+
+    // Frozen readouts leave the rest of the network plastic
+    let config = ModelConfig::standard(4, 2, vec![8, 8], 0.1, None)
+        .unwrap()
+        .with_readout(Readout::All, false)
+        .unwrap();
+    let model = Model::from_config(config, &Device::Cpu).unwrap();
+    assert!(
+        model
+            .synapses
+            .iter()
+            .filter(|s| s.metadata.post_layer != 4)
+            .all(|s| s.metadata.is_learning)
+    );
+
+    let config = ModelConfig::standard(4, 2, vec![8], 0.1, None).unwrap();
+    assert!(config.with_readout(Readout::Layers(vec![1]), true).is_err());
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "robot")]
+
+use custom_framework::robot::realtime::Scheduler;
+use std::time::Duration;
+
+#[test]
+fn test_deadlines_are_absolute() {
+    let mut scheduler = Scheduler::at_rate(200.0);
+    for _ in 0..10 {
+        // Work shorter than the period doesn't shift later deadlines
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(scheduler.wait());
+    }
+    let elapsed = scheduler.elapsed();
+    assert!(elapsed >= Duration::from_millis(50), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_millis(60), "{:?}", elapsed);
+    let stats = scheduler.stats();
+    assert_eq!((stats.cycles, stats.missed), (10, 0));
+    assert!(stats.max_lateness < Duration::from_millis(5));
+}
+
+#[test]
+fn test_overruns_are_counted_and_skipped() {
+    let mut scheduler = Scheduler::at_rate(200.0);
+    std::thread::sleep(Duration::from_millis(12));
+    assert!(!scheduler.wait());
+    let stats = scheduler.stats().clone();
+    assert_eq!(stats.missed, 1);
+    assert!(stats.skipped >= 1);
+    assert!(stats.max_overrun >= Duration::from_millis(7));
+
+    // The next deadline is still ahead, not in the past
+    assert!(scheduler.wait());
+    assert_eq!(scheduler.stats().missed, 1);
+
+    assert!(!scheduler.wait_until(Duration::from_millis(1)));
+    assert!(scheduler.wait_until(scheduler.elapsed() + Duration::from_millis(2)));
+    assert_eq!(scheduler.stats().cycles, 4);
+    assert!(scheduler.stats().miss_rate() > 0.4);
+}
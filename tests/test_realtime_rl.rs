@@ -0,0 +1,35 @@
+use candle_core::{Device, Tensor};
+use custom_framework::algorithms::realtime_rl::RealtimeRL;
+use custom_framework::seed;
+
+#[test]
+fn test_realtime_rl_episode_bookkeeping() {
+    let device = Device::Cpu;
+    seed::set_global_seed(2, &device).unwrap();
+    let mut trainer = RealtimeRL::new(5, 3, vec![8], 0.1, device.clone()).unwrap();
+    trainer.n_timesteps = 2;
+    trainer.steps_per_episode = 10;
+    trainer.epsilon_decay = 0.5;
+
+    // 25 readings: two full episodes of 10 scored actions (11 readings each), then 2 more
+    let stream = (0..25).map(|i| -> candle_core::Result<(Tensor, Tensor, f32)> {
+        let angles = Tensor::from_vec(vec![0.1 * i as f32; 5], (5, 1), &device)?;
+        let label = Tensor::from_vec(vec![1.0f32], (1, 1), &device)?;
+        Ok((angles, label, 1.0))
+    });
+    let mut actions = Vec::new();
+    let history = trainer
+        .run(stream, 5, |a| {
+            actions.push(a);
+            Ok(())
+        })
+        .unwrap();
+
+    let steps: Vec<usize> = history.iter().map(|e| e.steps).collect();
+    assert_eq!(steps, vec![10, 10, 2]);
+    assert_eq!(history[2].total_reward, 2.0);
+    let epsilons: Vec<f32> = history.iter().map(|e| e.epsilon).collect();
+    assert_eq!(epsilons, vec![1.0, 0.5, 0.25]);
+    assert_eq!(actions.len(), 23);
+    assert!(actions.iter().all(|&a| a < 3));
+}
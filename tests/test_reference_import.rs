@@ -0,0 +1,61 @@
+use candle_core::{Device, Tensor};
+use custom_framework::models::Model;
+use custom_framework::models::reference::ReferenceMapping;
+use std::collections::HashMap;
+
+#[test]
+fn test_import_reference_checkpoint() {
+    let device = Device::Cpu;
+    let mut model = Model::new(3, 2, vec![4], &device, 0.1, None).unwrap();
+
+    let weights: Vec<f32> = (0..12).map(|i| i as f32 / 10.0).collect();
+    let mut tensors = HashMap::new();
+    tensors.insert(
+        "W1.weight".to_string(),
+        Tensor::from_vec(weights.clone(), (4, 3), &device).unwrap(),
+    );
+    tensors.insert(
+        "W1.bias".to_string(),
+        Tensor::from_vec(vec![0.5f32; 4], 4, &device).unwrap(),
+    );
+    tensors.insert(
+        "out.thr".to_string(),
+        Tensor::from_vec(vec![0.2f32, 0.4], 2, &device).unwrap(),
+    );
+    tensors.insert(
+        "M1.weight".to_string(),
+        Tensor::zeros((4, 4), candle_core::DType::F32, &device).unwrap(),
+    );
+    let path = std::env::temp_dir().join(format!("reference_{}.safetensors", std::process::id()));
+    candle_core::safetensors::save(&tensors, &path).unwrap();
+
+    let report = model
+        .import_reference(&path, &ReferenceMapping::csdp_paper(1))
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(report.imported, vec!["W1.bias", "W1.weight", "out.thr"]);
+    assert_eq!(report.unused, vec!["M1.weight"]);
+    assert!(report.missing.contains(&"Y1.weight".to_string()));
+
+    let synapse = model
+        .synapses
+        .iter()
+        .find(|s| s.metadata.pre_layer == 0 && s.metadata.post_layer == 2)
+        .unwrap();
+    let state = synapse.synapse.get_state().unwrap();
+    let imported = state["weights"]
+        .flatten_all()
+        .unwrap()
+        .to_vec1::<f32>()
+        .unwrap();
+    assert_eq!(imported, weights);
+    assert_eq!(state["biases"].dims(), &[4, 1]);
+
+    let thresh = model.layers[3].get_state().unwrap()["thresh"]
+        .flatten_all()
+        .unwrap()
+        .to_vec1::<f32>()
+        .unwrap();
+    assert!((thresh[0] - 0.3).abs() < 1e-6);
+}
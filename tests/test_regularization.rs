@@ -0,0 +1,58 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::layer::mod_signal::ModSignalGenerator;
+use custom_framework::layer::mod_signal::regularized::{ActivityRegularizer, RegularizedModSignal};
+use custom_framework::layer::mod_signal::standard::StandardModSignal;
+use custom_framework::models::{Model, ModelConfig};
+
+#[test]
+fn test_synchronous_firing_is_penalized() {
+    let device = Device::Cpu;
+    let size = 8;
+    let mut plain = StandardModSignal::new(size, 5.0, 1.0, 4.0, &device).unwrap();
+    let mut regularized = RegularizedModSignal::new(
+        Box::new(plain.clone()),
+        ActivityRegularizer::default(),
+        size,
+        &device,
+    )
+    .unwrap();
+
+    // Every neuron fires in every step of a batch of two
+    let spikes = Tensor::ones((size, 2), DType::F32, &device).unwrap();
+    let label = Tensor::ones((1, 2), DType::F32, &device).unwrap();
+    let reward = Tensor::zeros((1, 2), DType::F32, &device).unwrap();
+    for _ in 0..10 {
+        plain
+            .calc_mod_signal(&spikes, &label, &reward, 0.1)
+            .unwrap();
+        regularized
+            .calc_mod_signal(&spikes, &label, &reward, 0.1)
+            .unwrap();
+    }
+    let difference = plain
+        .get_mod_signal()
+        .sub(regularized.get_mod_signal())
+        .unwrap()
+        .flatten_all()
+        .unwrap()
+        .to_vec1::<f32>()
+        .unwrap();
+    assert!(difference.iter().all(|&d| d > 0.0));
+    assert_eq!(regularized.rate().dims(), &[size, 2]);
+}
+
+#[test]
+fn test_model_with_activity_regularizer() {
+    let device = Device::Cpu;
+    let config = ModelConfig::standard(4, 2, vec![8, 8, 8], 0.1, None)
+        .unwrap()
+        .with_activity_regularizer(ActivityRegularizer::default());
+    let mut model = Model::from_config(config, &device).unwrap();
+    model.reset(1).unwrap();
+    let input = Tensor::ones((4, 1), DType::F32, &device).unwrap();
+    let label = Tensor::ones((2, 1), DType::F32, &device).unwrap();
+    for _ in 0..10 {
+        model.step(&input, Some(&label)).unwrap();
+    }
+    assert_eq!(model.layers[2].get_mod_signal().dims(), &[8, 1]);
+}
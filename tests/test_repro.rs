@@ -0,0 +1,93 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::{Model, ModelConfig};
+use custom_framework::repro::{self, Divergence, ReproducibilityLog, hash_tensor};
+
+fn run(device: &Device, log: &mut ReproducibilityLog) -> custom_framework::error::Result<()> {
+    let config = ModelConfig::standard(3, 2, vec![8], 0.1, None)?;
+    let mut model = Model::from_config(config, device)?;
+    log.record("init", &model)?;
+    let input = Tensor::ones((3, 2), DType::F32, device)?;
+    let label = Tensor::new(&[[1.0f32, 0.0], [0.0, 1.0]], device)?;
+    model.reset(2)?;
+    for step in 0..5 {
+        model.step(&input, Some(&label))?;
+        log.record(format!("step {}", step), &model)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_seeded_cpu_runs_are_bit_exact() {
+    let device = Device::Cpu;
+    let (log, divergence) = repro::compare_runs(7, &device, |log| run(&device, log)).unwrap();
+    assert_eq!(divergence, None);
+    assert_eq!(log.checkpoints.len(), 6);
+    assert!(
+        log.checkpoints[0]
+            .components
+            .iter()
+            .any(|(n, _)| n == "rng")
+    );
+
+    let path = std::env::temp_dir().join("csdp_repro_log.json");
+    log.save(&path).unwrap();
+    assert_eq!(ReproducibilityLog::load(&path).unwrap(), log);
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn test_reports_the_first_divergent_component() {
+    let device = Device::Cpu;
+    let (log, _) = repro::compare_runs(7, &device, |log| run(&device, log)).unwrap();
+
+    let mut other = log.clone();
+    let (name, hash) = &mut other.checkpoints[3].components[1];
+    *hash ^= 1;
+    let name = name.clone();
+    other.checkpoints[4].components[0].1 ^= 1;
+    match log.first_divergence(&other) {
+        Some(Divergence::Component {
+            index,
+            label,
+            component,
+            ..
+        }) => {
+            assert_eq!((index, label.as_str()), (3, "step 2"));
+            assert_eq!(component, name);
+        }
+        divergence => panic!("unexpected {:?}", divergence),
+    }
+
+    let mut shorter = log.clone();
+    shorter.checkpoints.pop();
+    assert_eq!(
+        log.first_divergence(&shorter),
+        Some(Divergence::Length {
+            expected: 6,
+            actual: 5
+        })
+    );
+
+    // A different seed changes the initial weights
+    let (other_seed, _) = repro::compare_runs(8, &device, |log| run(&device, log)).unwrap();
+    assert!(log.first_divergence(&other_seed).is_some());
+}
+
+#[test]
+fn test_hash_covers_bits_and_shape() {
+    let device = Device::Cpu;
+    let zeros = Tensor::zeros((2, 2), DType::F32, &device).unwrap();
+    let negative = Tensor::new(&[[-0.0f32, 0.0], [0.0, 0.0]], &device).unwrap();
+    assert_eq!(
+        hash_tensor(&zeros).unwrap(),
+        hash_tensor(&zeros.copy().unwrap()).unwrap()
+    );
+    assert_ne!(
+        hash_tensor(&zeros).unwrap(),
+        hash_tensor(&negative).unwrap()
+    );
+    assert_ne!(
+        hash_tensor(&zeros).unwrap(),
+        hash_tensor(&zeros.reshape(4).unwrap()).unwrap()
+    );
+}
@@ -0,0 +1,34 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::Model;
+
+#[test]
+fn test_save_load_restores_adaptive_thresholds() {
+    let device = Device::Cpu;
+    let mut model = Model::new(8, 2, vec![16], &device, 0.1, None).unwrap();
+    let input = Tensor::ones((8, 1), DType::F32, &device).unwrap();
+    model.reset(1).unwrap();
+    for _ in 0..50 {
+        model.step(&input, None).unwrap();
+    }
+
+    let thresholds = |model: &Model| -> Vec<Vec<f32>> {
+        model
+            .layers
+            .iter()
+            .filter_map(|layer| layer.get_state().unwrap().remove("thresh"))
+            .map(|t| t.to_vec1::<f32>().unwrap())
+            .collect()
+    };
+
+    let dir = std::env::temp_dir().join(format!("csdp_resume_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("model.safetensors");
+    model.save(&path).unwrap();
+
+    let mut restored = Model::new(8, 2, vec![16], &device, 0.1, None).unwrap();
+    assert_ne!(thresholds(&restored), thresholds(&model));
+    restored.load(&path).unwrap();
+    assert_eq!(thresholds(&restored), thresholds(&model));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
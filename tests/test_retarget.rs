@@ -0,0 +1,70 @@
+#![cfg(feature = "robot")]
+
+use custom_framework::dataset::trajectory::Trajectory;
+use custom_framework::robot::profile::RobotProfile;
+use custom_framework::robot::retarget::{RetargetMode, Retargeting};
+
+fn profile(home: f64, min: f64, max: f64) -> RobotProfile {
+    RobotProfile {
+        port: String::new(),
+        home_positions: [home; 6],
+        min_positions: [min; 6],
+        max_positions: [max; 6],
+    }
+}
+
+#[test]
+fn test_angle_mode_keeps_angles_within_target_limits() {
+    // Source range relative to home is [-2, 2], target [-1, 0.5]
+    let retargeting = Retargeting::new(
+        &profile(0.0, -2.0, 2.0),
+        &profile(1.0, 0.0, 1.5),
+        RetargetMode::Angle,
+    )
+    .with_flips(&[1])
+    .unwrap();
+    let mapped = retargeting.map(&[0.25, 0.25, -1.5, 1.5, 0.0, 0.0]);
+    assert_eq!(mapped, vec![0.25, -0.25, -1.0, 0.5, 0.0, 0.0]);
+}
+
+#[test]
+fn test_range_mode_maps_fractions_and_flips() {
+    let retargeting = Retargeting::new(
+        &profile(0.0, -2.0, 2.0),
+        &profile(1.0, 0.0, 3.0),
+        RetargetMode::Range,
+    )
+    .with_flips(&[5])
+    .unwrap()
+    .with_offsets([0.0, 0.0, 0.0, 0.0, 0.5, 0.0]);
+    // Target range relative to home is [-1, 2]
+    let mapped = retargeting.map(&[-2.0, 2.0, 0.0, 1.0, 0.0, -2.0]);
+    let expected = [-1.0, 2.0, 0.5, 1.25, 1.0, 2.0];
+    for (m, e) in mapped.iter().zip(expected) {
+        assert!((m - e).abs() < 1e-12, "{:?}", mapped);
+    }
+
+    assert!(retargeting.clone().with_flips(&[6]).is_err());
+}
+
+#[test]
+fn test_apply_keeps_timestamps() {
+    let retargeting = Retargeting::new(
+        &RobotProfile::leader(),
+        &RobotProfile::follower(),
+        RetargetMode::Range,
+    );
+    let recording = Trajectory {
+        channels: (1..=6).map(|j| format!("j{}", j)).collect(),
+        timestamps_ms: Some(vec![0, 33]),
+        frames: vec![vec![0.0; 6], vec![0.1; 6]],
+    };
+    let retargeted = retargeting.apply(&recording).unwrap();
+    assert_eq!(retargeted.timestamps_ms, recording.timestamps_ms);
+    assert_eq!(retargeted.channels, recording.channels);
+    assert_eq!(retargeted.frames[0], retargeting.map(&recording.frames[0]));
+
+    let mut short = recording.clone();
+    short.channels.truncate(3);
+    assert!(retargeting.apply(&short).is_err());
+}
@@ -0,0 +1,75 @@
+use candle_core::{Device, Tensor};
+use custom_framework::models::robot_model::{ActionDecoder, ObservationEncoder, RobotModel, Vote};
+
+/// One timestep of output spikes where motor 0 votes right, motor 1 left and the rest stay
+fn spikes(device: &Device) -> Tensor {
+    let mut values = vec![0.0f32; 18];
+    values[2] = 1.0;
+    values[4] = 1.0;
+    for motor in 2..6 {
+        values[motor * 3] = 1.0;
+    }
+    Tensor::from_vec(values, (18, 1), device).unwrap()
+}
+
+#[test]
+fn test_majority_vote_decoding() {
+    let device = Device::Cpu;
+    let mut decoder = ActionDecoder::new(3, 0.1, Vote::Majority);
+    for _ in 0..3 {
+        assert!(!decoder.ready());
+        decoder.accumulate(&spikes(&device)).unwrap();
+    }
+    assert!(decoder.ready());
+    assert_eq!(decoder.decode(), [0.1, -0.1, 0.0, 0.0, 0.0, 0.0]);
+    assert!(!decoder.ready());
+}
+
+#[test]
+fn test_softmax_vote_decoding() {
+    let device = Device::Cpu;
+    let mut decoder = ActionDecoder::new(1, 1.0, Vote::Softmax { temperature: 1.0 });
+    decoder.accumulate(&spikes(&device)).unwrap();
+    let command = decoder.decode();
+    // counts (0, 0, 1) -> P(right) - P(left) = (e - 1) / (e + 2)
+    let expected = (1f64.exp() - 1.0) / (1f64.exp() + 2.0);
+    assert!((command[0] - expected).abs() < 1e-6);
+    assert!((command[1] + expected).abs() < 1e-6);
+    assert!(command[2..].iter().all(|&v| v == 0.0));
+}
+
+#[test]
+fn test_observation_encoder_channels() {
+    let device = Device::Cpu;
+    let home = [1.0; 6];
+    let encoder = ObservationEncoder::from_calibration(&home, &[0.0; 6], &[3.0; 6]);
+    assert_eq!(encoder.lower, vec![-1.0; 6]);
+    assert_eq!(
+        encoder.normalize(&[0.5, -1.0, 2.0, 5.0, 0.0, 0.0])[..4],
+        [0.5, 0.0, 1.0, 1.0]
+    );
+
+    let encoder = encoder
+        .with_population(5)
+        .with_previous_action()
+        .with_reward();
+    assert_eq!(encoder.input_size(), 6 * 5 + 6 + 1);
+    let input = encoder
+        .encode(&[0.5; 6], Some(&[1.0; 6]), Some(-1.0), &device)
+        .unwrap()
+        .flatten_all()
+        .unwrap()
+        .to_vec1::<f32>()
+        .unwrap();
+    assert_eq!(input.len(), encoder.input_size());
+    // 0.5 sits on the middle tuning curve of each joint
+    assert_eq!(input[2], 1.0);
+    assert!(input[0] < input[1] && input[1] < input[2]);
+    assert_eq!(input[30..36], [1.0; 6]);
+    assert_eq!(input[36], 0.0);
+
+    let mut model = RobotModel::with_encoder(encoder, 1, 16, &device, 0.1);
+    let command = model.control(&[0.5; 6], Some(1.0)).unwrap();
+    let step = model.decoder.step_size;
+    assert!(command.iter().all(|&c| c == 0.0 || c.abs() == step));
+}
@@ -0,0 +1,105 @@
+use custom_framework::algorithms::checkpoint::{CheckpointPolicy, Checkpointer};
+use custom_framework::run::{RunDir, RunSummary, run_stamp};
+use std::time::Duration;
+
+#[test]
+fn test_run_stamp() {
+    assert_eq!(run_stamp(0), "19700101-000000");
+    // 2024-02-29 23:59:59 UTC
+    assert_eq!(run_stamp(1709251199), "20240229-235959");
+}
+
+#[test]
+fn test_runs_get_their_own_directories() {
+    let output = std::env::temp_dir().join(format!("csdp_runs_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&output);
+
+    let first = RunDir::create(&output, "grid").unwrap();
+    let second = RunDir::create(&output, "grid").unwrap();
+    assert_ne!(first.path(), second.path());
+    assert!(first.path().join("run.json").exists());
+    assert_eq!(first.info().unwrap().name, "grid");
+
+    let latest = RunDir::latest(&output, "grid").unwrap().unwrap();
+    assert_eq!(latest.path(), second.path());
+    assert!(RunDir::latest(&output, "rocket").unwrap().is_none());
+
+    second
+        .write_config(&serde_json::json!({ "algo": "csdp5" }))
+        .unwrap();
+    assert!(second.config_path().exists());
+    let plot = second
+        .export_reward_plot(&[(0, 1.0), (1, 2.5), (2, f32::NAN)])
+        .unwrap();
+    let svg = std::fs::read_to_string(plot).unwrap();
+    assert!(svg.starts_with("<svg") && svg.contains("<polyline"));
+
+    std::fs::remove_dir_all(&output).unwrap();
+}
+
+#[test]
+fn test_run_summary() {
+    let output = std::env::temp_dir().join(format!("csdp_summary_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&output);
+    let run = RunDir::create(&output, "csdp5").unwrap();
+    assert_eq!(run.config_hash().unwrap(), None);
+
+    let rewards: Vec<(usize, f32)> = (0..12)
+        .map(|e| (e, if e == 4 { 9.0 } else { e as f32 * 0.5 }))
+        .chain([(12, f32::NAN)])
+        .collect();
+    let mut summary = RunSummary::new("csdp5", &rewards, Duration::from_millis(1500));
+    assert_eq!(summary.episodes, 13);
+    assert_eq!(
+        (summary.best_reward, summary.best_episode),
+        (Some(9.0), Some(4))
+    );
+    assert_eq!(summary.final_reward, None);
+    // Episodes 3..=11 without the one at 4, plus the 9 at 4
+    let expected = ((3..12)
+        .filter(|&e| e != 4)
+        .map(|e| e as f32 * 0.5)
+        .sum::<f32>()
+        + 9.0)
+        / 9.0;
+    assert!((summary.final_mean_reward.unwrap() - expected).abs() < 1e-6);
+    assert_eq!(summary.wall_time_s, 1.5);
+
+    run.write_config(&serde_json::json!({ "algo": "csdp5" }))
+        .unwrap();
+    let hash = run.config_hash().unwrap().unwrap();
+    assert_eq!(hash.len(), 16);
+    let other = RunDir::create(&output, "csdp5").unwrap();
+    other
+        .write_config(&serde_json::json!({ "algo": "csdp5", "seed": 1 }))
+        .unwrap();
+    assert_ne!(other.config_hash().unwrap().unwrap(), hash);
+
+    let mut checkpoints = Checkpointer::new(run.checkpoints_dir(), CheckpointPolicy::every(1));
+    assert_eq!(checkpoints.latest(), None);
+    for (episode, metric) in [(1, 2.0), (2, 1.0)] {
+        checkpoints.begin(episode).unwrap();
+        checkpoints.commit(Some(metric)).unwrap();
+    }
+    summary.config_hash = Some(hash);
+    summary.latest_checkpoint = checkpoints.latest();
+    summary.best_checkpoint = checkpoints.best();
+    summary.best_checkpoint_metric = checkpoints.best_metric();
+    assert!(
+        summary
+            .latest_checkpoint
+            .as_ref()
+            .unwrap()
+            .ends_with("episode_000002")
+    );
+    assert_eq!(summary.best_checkpoint_metric, Some(2.0));
+
+    run.write_summary(&summary).unwrap();
+    assert_eq!(run.summary().unwrap(), summary);
+    let json: serde_json::Value =
+        serde_json::from_reader(std::fs::File::open(run.summary_path()).unwrap()).unwrap();
+    assert_eq!(json["completed"], true);
+    assert!(json["best_checkpoint"].as_str().unwrap().ends_with("best"));
+
+    std::fs::remove_dir_all(&output).unwrap();
+}
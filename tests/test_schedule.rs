@@ -0,0 +1,40 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::Model;
+use custom_framework::models::schedule::Schedule;
+use custom_framework::seed;
+
+#[test]
+fn test_schedule_segments_run_back_to_back() {
+    let device = Device::Cpu;
+    seed::set_global_seed(3, &device).unwrap();
+    let mut model = Model::new(3, 2, vec![8], &device, 0.1, None).unwrap();
+    let a = Tensor::ones((3, 2), DType::F32, &device).unwrap();
+    let b = Tensor::zeros((3, 2), DType::F32, &device).unwrap();
+
+    let schedule = Schedule::hold(&a, 5, 15, 20);
+    assert_eq!(schedule.total_steps(), 20);
+    assert_eq!(schedule.segments.len(), 3);
+    let out = model.process_schedule(&schedule, true).unwrap();
+    assert_eq!(out.output_activity.len(), 20);
+    assert_eq!(out.final_output.dims(), &[2, 2]);
+
+    let sequence = Schedule::sequence(&[a.clone(), b, a.clone()], 4, 2);
+    assert_eq!(sequence.total_steps(), 16);
+    assert_eq!(sequence.segments.len(), 5);
+
+    let context = Tensor::ones((2, 2), DType::F32, &device).unwrap();
+    let labelled = Schedule::new()
+        .present_with_context(&a, &context, 3)
+        .blank(2);
+    let out = model.process_schedule(&labelled, true).unwrap();
+    assert_eq!(out.output_activity.len(), 5);
+
+    assert!(
+        model
+            .process_schedule(&Schedule::new().blank(5), false)
+            .is_err()
+    );
+    let wide = Tensor::ones((3, 3), DType::F32, &device).unwrap();
+    let mismatched = Schedule::new().present(&a, 2).present(&wide, 2);
+    assert!(model.process_schedule(&mismatched, false).is_err());
+}
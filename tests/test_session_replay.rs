@@ -0,0 +1,72 @@
+use candle_core::Device;
+use custom_framework::VisualizationState;
+use custom_framework::models::Model;
+use custom_framework::visualization::replay::{SessionRecorder, SessionReplay};
+use std::io::Write;
+
+#[test]
+fn test_recorded_session_replays() {
+    let device = Device::Cpu;
+    let model = Model::new(4, 2, vec![8], &device, 0.1, None).unwrap();
+    let path = std::env::temp_dir().join(format!("csdp_session_{}.jsonl", std::process::id()));
+
+    let mut state = VisualizationState::new(3);
+    state.recorder = Some(SessionRecorder::create(&path).unwrap());
+    for episode in 0..3 {
+        state.runtime_stats.epoch = episode;
+        state.runtime_stats.iteration = episode * 10;
+        state.update_from_snapshot(model.get_visualization_snapshot().unwrap());
+        // Two episodes may finish between snapshots
+        state.epoch_rewards.push((2 * episode, episode as f32));
+        state
+            .epoch_rewards
+            .push((2 * episode + 1, episode as f32 + 0.5));
+    }
+    assert_eq!(state.recorder.as_ref().unwrap().frames(), 3);
+    drop(state);
+
+    let mut replay = SessionReplay::load(&path).unwrap();
+    assert_eq!(replay.len(), 3);
+    assert!(replay.frame().rewards.is_empty());
+    replay.step(2);
+    assert_eq!(replay.frame().stats.iteration, 20);
+    assert_eq!(
+        replay.rewards(),
+        vec![(0, 0.0), (1, 0.5), (2, 1.0), (3, 1.5)]
+    );
+    replay.step(5);
+    assert!(replay.at_end());
+    replay.step(-5);
+    assert_eq!(replay.position(), 0);
+
+    replay.seek(1);
+    let mut shown = VisualizationState::new(0);
+    replay.show(&mut shown);
+    assert_eq!(shown.runtime_stats.epoch, 1);
+    assert_eq!(shown.total_epochs, 2);
+    assert_eq!(shown.epoch_rewards.len(), 2);
+    assert_eq!(
+        shown.model_structure.layers.len(),
+        model.get_visualization_snapshot().unwrap().layers.len()
+    );
+
+    // A frame cut off by a killed run is dropped; a broken frame before the end is an error
+    let first = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .next()
+        .unwrap()
+        .to_string();
+    let append = |text: &str| {
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    };
+    append("{\"stats\":{\"epoch\":3");
+    assert_eq!(SessionReplay::load(&path).unwrap().len(), 3);
+    append(&format!("\n{}\n", first));
+    assert!(SessionReplay::load(&path).is_err());
+    std::fs::remove_file(&path).ok();
+}
@@ -0,0 +1,102 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::environment::grid::GridEnvironment;
+use custom_framework::experiment::{self, ExperimentConfig};
+use custom_framework::models::Model;
+use custom_framework::shaping::{
+    RewardClip, RewardNormalizer, RewardShaping, discounted_returns, smooth_labels,
+};
+
+#[test]
+fn test_label_smoothing() {
+    let device = Device::Cpu;
+    let label = Tensor::from_vec(vec![0.0f32, 1.0, 0.0, 0.0], (4, 1), &device).unwrap();
+    let smoothed = smooth_labels(&label, 0.2)
+        .unwrap()
+        .flatten_all()
+        .unwrap()
+        .to_vec1::<f32>()
+        .unwrap();
+    let expected = [0.05, 0.85, 0.05, 0.05];
+    for (s, e) in smoothed.iter().zip(expected) {
+        assert!((s - e).abs() < 1e-6, "{:?}", smoothed);
+    }
+    assert!(smooth_labels(&label, 1.5).is_err());
+    // A single row is a binary label, smoothed toward 0.5 from both sides
+    let binary = Tensor::from_vec(vec![0.0f32, 1.0], (1, 2), &device).unwrap();
+    let smoothed = smooth_labels(&binary, 0.2)
+        .unwrap()
+        .flatten_all()
+        .unwrap()
+        .to_vec1::<f32>()
+        .unwrap();
+    assert!((smoothed[0] - 0.1).abs() < 1e-6 && (smoothed[1] - 0.9).abs() < 1e-6);
+
+    // The model smooths every context before the context layer sees it
+    let mut model = Model::new(3, 4, vec![8], &device, 0.1, None).unwrap();
+    // The smoothing is checked once, when it is set
+    assert!(model.set_label_smoothing(Some(1.5)).is_err());
+    model.set_label_smoothing(Some(0.2)).unwrap();
+    let input = Tensor::zeros((3, 1), DType::F32, &device).unwrap();
+    model.step(&input, Some(&label)).unwrap();
+    let rates = model.layers[1]
+        .activity()
+        .unwrap()
+        .flatten_all()
+        .unwrap()
+        .to_vec1::<f32>()
+        .unwrap();
+    assert!((rates[1] - 0.85).abs() < 1e-6 && (rates[0] - 0.05).abs() < 1e-6);
+}
+
+#[test]
+fn test_reward_clipping_and_scaling() {
+    assert_eq!(RewardClip::Sign.apply(-3.5), -1.0);
+    assert_eq!(RewardClip::Sign.apply(0.0), 0.0);
+    let shaping = RewardShaping {
+        scale: 0.1,
+        clip: RewardClip::Range {
+            min: -1.0,
+            max: 0.5,
+        },
+    };
+    assert_eq!(shaping.apply(20.0), 0.5);
+    assert!((shaping.apply(-4.0) + 0.4).abs() < 1e-12);
+    assert_eq!(shaping.apply(-40.0), -1.0);
+
+    let json: RewardShaping = serde_json::from_str(r#"{"clip": "sign"}"#).unwrap();
+    assert_eq!(json.scale, 1.0);
+    assert_eq!(json.apply(7.0), 1.0);
+}
+
+#[test]
+fn test_returns_and_normalization() {
+    let returns = discounted_returns(&[1.0, 0.0, 2.0], 0.5);
+    assert_eq!(returns, vec![1.5, 1.0, 2.0]);
+
+    let mut normalizer = RewardNormalizer::new();
+    assert_eq!(normalizer.normalize(3.0), 3.0);
+    let mut values = [1.0f32, 2.0, 3.0, 4.0, 5.0];
+    normalizer.normalize_batch(&mut values);
+    assert_eq!(normalizer.mean(), 3.0);
+    assert!(values.iter().sum::<f32>().abs() < 1e-6);
+    let std = (values.iter().map(|v| v * v).sum::<f32>() / 4.0).sqrt();
+    assert!((std - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn test_experiment_config_applies_the_shaping() {
+    let env = GridEnvironment::new();
+    let config = ExperimentConfig {
+        hidden_sizes: Some(vec![8]),
+        label_smoothing: Some(0.1),
+        normalize_returns: Some(true),
+        ..ExperimentConfig::default()
+    };
+    assert!(experiment::build_algorithm("csdp4", &env, Device::Cpu, &config, Some(1)).is_ok());
+
+    let invalid = ExperimentConfig {
+        label_smoothing: Some(-0.1),
+        ..config
+    };
+    assert!(experiment::build_algorithm("csdp2", &env, Device::Cpu, &invalid, Some(1)).is_err());
+}
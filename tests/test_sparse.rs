@@ -0,0 +1,54 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::seed;
+use custom_framework::synapse::SynapseOps;
+use custom_framework::synapse::sparse::SparseCSDP;
+
+/// Dense (post, pre) matrix assembled from the stored blocks
+fn densify(synapse: &SparseCSDP, pre: usize, post: usize, block: usize) -> Vec<Vec<f32>> {
+    let state = synapse.get_state().unwrap();
+    let layout = state["layout"].to_vec2::<u32>().unwrap();
+    let mut dense = vec![vec![0.0; pre]; post];
+    for (i, rc) in layout.iter().enumerate() {
+        let weights = state[&format!("block_{:06}", i)].to_vec2::<f32>().unwrap();
+        for (dr, row) in weights.iter().enumerate() {
+            for (dc, &w) in row.iter().enumerate() {
+                dense[rc[0] as usize * block + dr][rc[1] as usize * block + dc] = w;
+            }
+        }
+    }
+    dense
+}
+
+#[test]
+fn test_sparse_forward_matches_dense() {
+    let device = Device::Cpu;
+    seed::set_global_seed(3, &device).unwrap();
+    let (pre, post, block) = (12, 7, 4);
+    let synapse = SparseCSDP::new(pre, post, 0.5, block, &device).unwrap();
+    // 3 column blocks, 2 of them kept in each of the 2 row blocks
+    assert!((synapse.density() - 2.0 / 3.0).abs() < 1e-6);
+
+    let input = seed::rand_uniform(0.0, 1.0, (pre, 3), &device).unwrap();
+    let dense = densify(&synapse, pre, post, block);
+    let dense = Tensor::from_vec(dense.concat(), (post, pre), &device).unwrap();
+    let expected = dense.matmul(&input).unwrap().to_vec2::<f32>().unwrap();
+    let actual = synapse.forward(&input).unwrap().to_vec2::<f32>().unwrap();
+    for (a, e) in actual.iter().flatten().zip(expected.iter().flatten()) {
+        assert!((a - e).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn test_sparse_state_round_trip() {
+    let device = Device::Cpu;
+    seed::set_global_seed(5, &device).unwrap();
+    let original = SparseCSDP::new(12, 9, 0.3, 3, &device).unwrap();
+    let mut restored = SparseCSDP::new(12, 9, 0.3, 3, &device).unwrap();
+    restored.set_state(&original.get_state().unwrap()).unwrap();
+
+    let input = Tensor::ones((12, 2), DType::F32, &device).unwrap();
+    assert_eq!(
+        restored.forward(&input).unwrap().to_vec2::<f32>().unwrap(),
+        original.forward(&input).unwrap().to_vec2::<f32>().unwrap()
+    );
+}
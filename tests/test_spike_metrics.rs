@@ -0,0 +1,42 @@
+use candle_core::{Device, Tensor};
+use custom_framework::spike_metrics::*;
+
+#[test]
+fn test_single_train_distances() {
+    let mut a = vec![0.0f32; 1000];
+    let mut b = vec![0.0f32; 1000];
+    a[100] = 1.0;
+    assert_eq!(van_rossum(&a, &a, 1.0, 0.01), 0.0);
+    assert!((van_rossum(&a, &b, 1.0, 0.01) - 0.5f32.sqrt()).abs() < 0.01);
+    b[110] = 1.0;
+    assert!(van_rossum(&a, &b, 1.0, 0.01) < 0.5f32.sqrt());
+    assert_eq!(spike_count_distance(&a, &b), 0.0);
+
+    assert_eq!(spike_times(&[0.0, 1.0, 0.0, 2.0], 0.5), vec![0.5, 1.5, 1.5]);
+    // Moving a spike by 0.5 costs 0.5; by 4, deleting and inserting it is cheaper
+    assert_eq!(victor_purpura(&[1.0], &[1.5], 1.0), 0.5);
+    assert_eq!(victor_purpura(&[1.0], &[5.0], 1.0), 2.0);
+    assert_eq!(victor_purpura(&[1.0, 2.0], &[], 1.0), 2.0);
+    assert_eq!(victor_purpura(&[1.0, 2.0, 3.0], &[9.0], 0.0), 2.0);
+}
+
+#[test]
+fn test_population_metrics() {
+    let device = Device::Cpu;
+    // Two samples over two steps: the first matches its one-hot target, the second is silent
+    let step = Tensor::from_vec(vec![1.0f32, 0.0, 0.0, 0.0], (2, 2), &device).unwrap();
+    let output = vec![step.clone(), step.clone()];
+    let target = Tensor::from_vec(vec![1.0f32, 0.0, 0.0, 1.0], (2, 2), &device).unwrap();
+    let similarity = population_similarity(&output, &target).unwrap();
+    assert_eq!(similarity, vec![1.0, 0.0]);
+
+    let target_steps = vec![target.clone(), target];
+    let distance = population_van_rossum(&output, &target_steps, 1.0, 0.1).unwrap();
+    assert_eq!(distance[0], 0.0);
+    assert!(distance[1] > 0.0);
+    assert!(population_van_rossum(&output, &target_steps[..1], 1.0, 0.1).is_err());
+
+    assert_eq!(regular_train(0.5, 4), vec![0.0, 1.0, 0.0, 1.0]);
+    assert_eq!(distance_reward(0.0, 1.0), 1.0);
+    assert_eq!(distance_reward(2.0, 1.0), 0.25);
+}
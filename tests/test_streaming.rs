@@ -0,0 +1,34 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::models::Model;
+use custom_framework::seed;
+
+fn model(device: &Device) -> Model {
+    seed::set_global_seed(17, device).unwrap();
+    Model::new(4, 2, vec![8], device, 0.1, None).unwrap()
+}
+
+#[test]
+fn test_streaming_carries_state_across_calls() {
+    let device = Device::Cpu;
+    let input = Tensor::from_vec(vec![0.9f32, 0.1, 0.8, 0.3], (4, 1), &device).unwrap();
+
+    let mut whole = model(&device);
+    let expected = whole
+        .process(&input, 20, false, &device)
+        .unwrap()
+        .final_output
+        .to_vec2::<f32>()
+        .unwrap();
+
+    let mut streamed = model(&device);
+    streamed.reset(1).unwrap();
+    let first = streamed.process_streaming(&input, None, 10, true).unwrap();
+    assert_eq!(first.output_activity.len(), 10);
+    let second = streamed.process_streaming(&input, None, 10, false).unwrap();
+    assert_eq!(second.final_output.to_vec2::<f32>().unwrap(), expected);
+
+    // A new batch size starts a new stream
+    let batch = Tensor::ones((4, 3), DType::F32, &device).unwrap();
+    let out = streamed.process_streaming(&batch, None, 5, false).unwrap();
+    assert_eq!(out.final_output.dims(), &[2, 3]);
+}
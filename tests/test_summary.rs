@@ -0,0 +1,29 @@
+use candle_core::Device;
+use custom_framework::models::Model;
+
+#[test]
+fn test_summary_counts_parameters() {
+    let device = Device::Cpu;
+    let mut model = Model::new(4, 2, vec![8], &device, 0.1, None).unwrap();
+    model.freeze_synapse(3).unwrap();
+    let summary = model.summary().unwrap();
+
+    let sizes: Vec<usize> = summary.layers.iter().map(|l| l.size).collect();
+    assert_eq!(sizes, vec![4, 2, 8, 2]);
+    assert_eq!(summary.layers[2].layer_type, "LIF");
+    assert_eq!(summary.layers[2].device, "cpu");
+
+    // input->hidden 8x4, context->hidden 8x2, hidden->output 2x8 and output->hidden 8x2,
+    // each with biases, plus one threshold per LIF layer
+    let shapes: Vec<(usize, usize)> = summary.synapses.iter().map(|s| s.shape).collect();
+    assert_eq!(shapes, vec![(8, 4), (8, 2), (2, 8), (8, 2)]);
+    assert_eq!(summary.synapses[0].parameters, 40);
+    assert!(!summary.synapses[3].is_learning);
+    assert_eq!(summary.total_parameters, 40 + 24 + 18 + 24 + 2);
+    assert_eq!(summary.parameter_bytes, 4 * summary.total_parameters);
+
+    let text = summary.to_string();
+    assert!(text.contains("Hidden_0"));
+    assert!(text.contains("frozen"));
+    assert!(text.contains("108 parameters"));
+}
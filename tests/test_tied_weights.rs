@@ -0,0 +1,73 @@
+use candle_core::{Device, Tensor};
+use custom_framework::models::{Model, ModelConfig};
+use custom_framework::seed;
+
+fn state(model: &Model, id: usize, key: &str) -> Tensor {
+    model.synapses[id].synapse.get_state().unwrap()[key].clone()
+}
+
+fn difference(a: &Tensor, b: &Tensor) -> f32 {
+    a.sub(b)
+        .unwrap()
+        .abs()
+        .unwrap()
+        .max_all()
+        .unwrap()
+        .to_scalar::<f32>()
+        .unwrap()
+}
+
+#[test]
+fn test_backward_weights_follow_the_forward_weights() {
+    let device = Device::Cpu;
+    seed::set_global_seed(21, &device).unwrap();
+    // Hidden_0 -> Hidden_1 and back
+    let config = ModelConfig::standard(4, 2, vec![6, 5], 0.1, None)
+        .unwrap()
+        .with_tied_weights(2, 3)
+        .unwrap();
+    let mut model = Model::from_config(config, &device).unwrap();
+    let &[(forward, backward)] = model.tied_weights() else {
+        panic!("expected one tied pair");
+    };
+    assert_eq!(
+        (
+            model.synapses[forward].metadata.pre_layer,
+            model.synapses[forward].metadata.post_layer
+        ),
+        (2, 3)
+    );
+    assert!(!model.synapses[backward].metadata.is_learning);
+
+    let tied = |model: &Model| {
+        difference(
+            &state(model, forward, "weights").t().unwrap(),
+            &state(model, backward, "weights"),
+        )
+    };
+    assert_eq!(tied(&model), 0.0);
+
+    let initial = state(&model, forward, "weights");
+    let biases = state(&model, backward, "biases");
+    let input = Tensor::ones((4, 3), candle_core::DType::F32, &device).unwrap();
+    let label = Tensor::ones((2, 3), candle_core::DType::F32, &device).unwrap();
+    model.reset(3).unwrap();
+    for _ in 0..10 {
+        model.step(&input, Some(&label)).unwrap();
+    }
+    assert!(difference(&initial, &state(&model, forward, "weights")) > 0.0);
+    assert_eq!(tied(&model), 0.0);
+    assert_eq!(difference(&biases, &state(&model, backward, "biases")), 0.0);
+}
+
+#[test]
+fn test_invalid_ties() {
+    let config = || ModelConfig::standard(4, 2, vec![6, 5], 0.1, None).unwrap();
+    assert!(config().with_tied_weights(2, 2).is_err());
+    assert!(config().with_tied_weights(2, 9).is_err());
+    let tied = config().with_tied_weights(2, 3).unwrap();
+    assert!(tied.with_tied_weights(3, 2).is_err());
+    // The input layer receives no backward connection
+    let config = config().with_tied_weights(0, 2).unwrap();
+    assert!(Model::from_config(config, &Device::Cpu).is_err());
+}
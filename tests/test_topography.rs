@@ -0,0 +1,130 @@
+use candle_core::{DType, Device, Tensor};
+use custom_framework::layer::topography::Topography;
+use custom_framework::models::{Model, ModelConfig};
+use custom_framework::seed;
+use custom_framework::synapse::topographic::DistanceConnectivity;
+
+fn grid(width: usize, height: usize) -> Topography {
+    Topography::Grid { width, height }
+}
+
+fn config() -> ModelConfig {
+    // A 3 x 3 hidden layer with receptive fields on a 4 x 4 input grid
+    ModelConfig::standard(16, 2, vec![9], 0.1, None)
+        .unwrap()
+        .with_topography(0, grid(4, 4))
+        .unwrap()
+        .with_topography(2, grid(3, 3))
+        .unwrap()
+        .with_distance_connectivity(0, 2, DistanceConnectivity::receptive_field(0.3))
+        .unwrap()
+}
+
+fn weights(model: &Model) -> Vec<Vec<f32>> {
+    let synapse = model
+        .synapses
+        .iter()
+        .find(|s| (s.metadata.pre_layer, s.metadata.post_layer) == (0, 2))
+        .unwrap();
+    synapse.synapse.get_state().unwrap()["weights"]
+        .to_vec2::<f32>()
+        .unwrap()
+}
+
+#[test]
+fn test_grid_coordinates() {
+    let grid = grid(2, 2);
+    assert_eq!(grid.size(), 4);
+    assert_eq!(
+        grid.coordinates(),
+        vec![(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]
+    );
+    assert_eq!(
+        Topography::Line { len: 2 }.coordinates(),
+        vec![(0.25, 0.5), (0.75, 0.5)]
+    );
+}
+
+#[test]
+fn test_connections_outside_the_receptive_field_stay_absent() {
+    let device = Device::Cpu;
+    seed::set_global_seed(8, &device).unwrap();
+    let mut model = Model::from_config(config(), &device).unwrap();
+    assert_eq!(model.topography(2), Some(grid(3, 3)));
+
+    let input_coords = grid(4, 4).coordinates();
+    let hidden_coords = grid(3, 3).coordinates();
+    let outside = |w: &[Vec<f32>]| {
+        let mut inside = 0;
+        for (i, &p) in hidden_coords.iter().enumerate() {
+            for (j, &q) in input_coords.iter().enumerate() {
+                let d = (p.0 - q.0).hypot(p.1 - q.1);
+                if d > 0.3 {
+                    assert_eq!(w[i][j], 0.0, "hidden {} input {}", i, j);
+                } else if w[i][j] != 0.0 {
+                    inside += 1;
+                }
+            }
+        }
+        inside
+    };
+    assert!(outside(&weights(&model)) > 0);
+
+    let input = Tensor::ones((16, 3), DType::F32, &device).unwrap();
+    let label = Tensor::ones((2, 3), DType::F32, &device).unwrap();
+    model.reset(3).unwrap();
+    for _ in 0..10 {
+        model.step(&input, Some(&label)).unwrap();
+    }
+    outside(&weights(&model));
+
+    let snapshot = model.get_visualization_snapshot().unwrap();
+    assert_eq!(snapshot.layers[0].topography, Some(grid(4, 4)));
+    assert_eq!(snapshot.layers[1].topography, None);
+}
+
+#[test]
+fn test_connections_survive_save_and_load() {
+    let device = Device::Cpu;
+    seed::set_global_seed(9, &device).unwrap();
+    let model = Model::from_config(config(), &device).unwrap();
+    let path = std::env::temp_dir().join(format!("csdp_topography_{}.st", std::process::id()));
+    model.save(&path).unwrap();
+
+    // A fresh build draws other connections; loading restores the saved ones
+    seed::set_global_seed(10, &device).unwrap();
+    let mut loaded = Model::from_config(config(), &device).unwrap();
+    loaded.load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(weights(&loaded), weights(&model));
+
+    let input = Tensor::ones((16, 3), DType::F32, &device).unwrap();
+    let mut saved = model;
+    saved.reset(3).unwrap();
+    loaded.reset(3).unwrap();
+    saved.step(&input, None).unwrap();
+    loaded.step(&input, None).unwrap();
+    let absent = |model: &Model| -> Vec<bool> {
+        weights(model).concat().iter().map(|&w| w == 0.0).collect()
+    };
+    assert_eq!(absent(&loaded), absent(&saved));
+}
+
+#[test]
+fn test_invalid_topographies_are_rejected() {
+    let standard = || ModelConfig::standard(16, 2, vec![9], 0.1, None).unwrap();
+    assert!(standard().with_topography(2, grid(4, 4)).is_err());
+    assert!(
+        standard()
+            .with_distance_connectivity(0, 2, DistanceConnectivity::gaussian(1.5, 0.2))
+            .is_err()
+    );
+
+    // Connected by distance without a topography on the hidden layer
+    let config = standard()
+        .with_topography(0, grid(4, 4))
+        .unwrap()
+        .with_distance_connectivity(0, 2, DistanceConnectivity::gaussian(0.8, 0.2))
+        .unwrap();
+    assert!(Model::from_config(config, &Device::Cpu).is_err());
+}
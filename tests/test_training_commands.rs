@@ -0,0 +1,31 @@
+use custom_framework::VisualizationState;
+use custom_framework::visualization::{StepGranularity, TrainingCommand, wait_for_advance};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_training_commands_reach_the_training_loop() {
+    let vis_state = Arc::new(Mutex::new(VisualizationState::new(10)));
+    {
+        let mut state = vis_state.lock().unwrap();
+        state.send_command(TrainingCommand::Resume);
+        state.send_command(TrainingCommand::SaveCheckpoint);
+        state.send_command(TrainingCommand::ResetModel);
+    }
+    assert!(wait_for_advance(&vis_state, StepGranularity::Sample));
+    {
+        let mut state = vis_state.lock().unwrap();
+        assert!(!state.is_paused);
+        assert!(state.save_requested && state.reset_requested);
+        assert!(state.commands.is_empty());
+
+        state.send_command(TrainingCommand::Pause);
+        state.apply_commands();
+        assert!(state.is_paused);
+        assert!(!state.try_advance(StepGranularity::Sample));
+
+        state.send_command(TrainingCommand::Stop);
+    }
+    // Stopping also releases a paused loop
+    assert!(!wait_for_advance(&vis_state, StepGranularity::Sample));
+    assert!(!vis_state.lock().unwrap().should_close);
+}
@@ -0,0 +1,80 @@
+use custom_framework::dataset::trajectory::Trajectory;
+
+fn recording() -> Trajectory {
+    Trajectory {
+        channels: vec!["j1".to_string(), "j2".to_string()],
+        timestamps_ms: Some(vec![0, 33, 66, 200, 233, 233]),
+        frames: vec![
+            vec![0.0, 1.0],
+            vec![0.1, 1.0],
+            vec![0.2, 1.0],
+            vec![0.3, 1.0],
+            vec![0.4, 1.0],
+            vec![0.5, 1.0],
+        ],
+    }
+}
+
+#[test]
+fn test_trajectory_csv_round_trip() {
+    let path = std::env::temp_dir().join(format!("trajectory_{}.csv", std::process::id()));
+    let trajectory = recording();
+    trajectory.save(&path).unwrap();
+    let loaded = Trajectory::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(loaded, trajectory);
+}
+
+#[test]
+fn test_trajectory_timing_and_stats() {
+    let trajectory = recording();
+    let timing = trajectory.timing(3.0).unwrap();
+    assert_eq!(timing.median_interval_ms, 33.0);
+    assert_eq!(timing.gaps, vec![(3, 134)]);
+    assert_eq!(timing.out_of_order, vec![5]);
+
+    let stats = trajectory.channel_stats();
+    assert!((stats[0].mean - 0.25).abs() < 1e-9);
+    assert_eq!(stats[1].std, 0.0);
+}
+
+/// Rest, a ramp on j1, a long rest, another ramp, rest; one frame every 100 ms
+fn two_episodes() -> Trajectory {
+    let j1 = [
+        0.0, 0.0, 0.0, 0.5, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.5, 2.0, 2.0, 2.0,
+    ];
+    Trajectory {
+        channels: vec!["j1".to_string()],
+        timestamps_ms: Some((0..j1.len() as u64).map(|i| i * 100).collect()),
+        frames: j1.iter().map(|&v| vec![v]).collect(),
+    }
+}
+
+#[test]
+fn test_trajectory_trim_and_split() {
+    let trajectory = two_episodes();
+
+    let trimmed = trajectory.trim_still(0.01);
+    assert_eq!(trimmed.frames.first().unwrap(), &vec![0.0]);
+    assert_eq!(trimmed.frames.last().unwrap(), &vec![2.0]);
+    assert_eq!(trimmed.len(), 10);
+    assert_eq!(trimmed.timestamps_ms.as_ref().unwrap()[0], 0);
+
+    let episodes = trajectory.split_at_pauses(0.01, 300).unwrap();
+    let values: Vec<Vec<f64>> = episodes.iter().map(|e| e.channel(0).collect()).collect();
+    assert_eq!(values, vec![vec![0.0, 0.5, 1.0], vec![1.0, 1.5, 2.0]]);
+}
+
+#[test]
+fn test_trajectory_resample_and_concat() {
+    let trajectory = two_episodes();
+    let resampled = trajectory.resample(20.0).unwrap();
+    assert_eq!(resampled.len(), 27);
+    assert_eq!(resampled.timestamps_ms.as_ref().unwrap()[1], 50);
+    assert_eq!(resampled.frames[7], vec![0.75]);
+
+    let joined = Trajectory::concat(&[trajectory.clone(), trajectory.clone()]).unwrap();
+    assert_eq!(joined.len(), 2 * trajectory.len());
+    assert_eq!(joined.timestamps_ms.as_ref().unwrap()[14], 1400);
+}